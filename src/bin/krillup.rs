@@ -1,5 +1,10 @@
 extern crate krill;
 
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
 use clap::{App, Arg, ArgMatches, SubCommand};
 use log::{info, LevelFilter};
 
@@ -10,7 +15,12 @@ use krill::{
         properties::PropertiesManager,
     },
     upgrades::{
-        data_migration::migrate, prepare_upgrade_data_migrations, UpgradeMode,
+        data_migration::{migrate, migrate_storage},
+        fsck::{check_storage, FsckReport},
+        prepare_upgrade_data_migrations,
+        reencrypt::reencrypt_storage,
+        restore::restore,
+        UpgradeMode,
     },
 };
 use url::Url;
@@ -80,6 +90,59 @@ fn main() {
                     ::std::process::exit(1);
                 }
             }
+            KrillUpMode::MigrateStorage { from, to } => {
+                match migrate_storage(&from, &to) {
+                    Err(e) => {
+                        eprintln!("*** Error Migrating Storage ***");
+                        eprintln!("{}", e);
+                        eprintln!();
+                        eprintln!(
+                            "Note that the data in {} has NOT been modified.",
+                            from
+                        );
+                        ::std::process::exit(1);
+                    }
+                    Ok(report) => print!("{}", report),
+                }
+            }
+            KrillUpMode::Restore { config, archive } => {
+                if let Err(e) = restore(&config, &archive) {
+                    eprintln!("*** Error Restoring Backup ***");
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            }
+            KrillUpMode::Reencrypt { config } => {
+                if let Err(e) = reencrypt_storage(&config) {
+                    eprintln!("*** Error Re-encrypting Storage ***");
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+            }
+            KrillUpMode::Fsck {
+                config,
+                repair,
+                yes,
+            } => {
+                let confirm_repair = |report: &FsckReport| {
+                    yes || confirm(&format!(
+                        "Rebuild the snapshots of {} instances? Make sure that Krill is stopped.",
+                        report.repairable
+                    ))
+                };
+                match check_storage(&config, repair, confirm_repair) {
+                    Err(e) => {
+                        eprintln!("*** Error Checking Storage ***");
+                        eprintln!("{}", e);
+                        ::std::process::exit(1);
+                    }
+                    Ok(report) => {
+                        if report.has_unrepaired_issues() {
+                            ::std::process::exit(1);
+                        }
+                    }
+                }
+            }
         },
     }
 }
@@ -98,6 +161,61 @@ fn make_matches<'a>() -> ArgMatches<'a> {
     migrate_sub = add_new_storage_arg(migrate_sub);
     app = app.subcommand(migrate_sub);
 
+    let mut migrate_storage_sub = SubCommand::with_name("migrate-storage")
+        .about("Copy all Krill data as is from one storage to another, e.g. to move data to a different disk or to a database. Stop Krill before running this tool to ensure data does not change during migration. The destination must be empty. All data is read back from both storages and compared after it is copied, and a report is printed for each namespace. The source data is not modified.");
+    migrate_storage_sub = migrate_storage_sub
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .value_name("URL")
+                .help("The storage URI string to copy data from. E.g. local:///var/lib/krill/data.")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .value_name("URL")
+                .help("The empty storage URI string to copy data to.")
+                .required(true),
+        );
+    app = app.subcommand(migrate_storage_sub);
+
+    let mut restore_sub = SubCommand::with_name("restore")
+        .about("Restore a backup made with 'krillc admin backup' into the storage defined in the config file. The archive is verified before anything is restored, and the storage must be empty. Backups made by an older version of Krill are upgraded to this version after they are restored. Backups made by a newer version of Krill cannot be restored.");
+    restore_sub = add_config_arg(restore_sub);
+    restore_sub = restore_sub.arg(
+        Arg::with_name("archive")
+            .short("a")
+            .long("archive")
+            .value_name("FILE")
+            .help("The backup archive to restore.")
+            .required(true),
+    );
+    app = app.subcommand(restore_sub);
+
+    let mut reencrypt_sub = SubCommand::with_name("reencrypt")
+        .about("Re-encrypt all Krill data in the storage defined in the config file with the current storage encryption key. Use this after rotating the key, while the old key is still configured as a previous key, or to encrypt data stored before encryption was enabled, while 'storage_encryption_allow_plaintext' is set. This can be done while Krill is running.");
+    reencrypt_sub = add_config_arg(reencrypt_sub);
+    app = app.subcommand(reencrypt_sub);
+
+    let mut fsck_sub = SubCommand::with_name("fsck")
+        .about("Check the consistency of the Krill data in the storage defined in the config file. All stored commands are replayed and compared to the stored snapshots, and version sequences are checked for gaps. Issues are reported, and this tool will exit with an error if any issues remain. Use --repair to rebuild snapshots that do not match the stored history, this asks for confirmation unless --yes is used. Stop Krill before using --repair.");
+    fsck_sub = add_config_arg(fsck_sub);
+    fsck_sub = fsck_sub.arg(
+        Arg::with_name("repair")
+            .long("repair")
+            .help("Rebuild snapshots from the stored history where this resolves the issues found.")
+            .required(false),
+    );
+    fsck_sub = fsck_sub.arg(
+        Arg::with_name("yes")
+            .long("yes")
+            .requires("repair")
+            .help("Do not ask for confirmation before repairing.")
+            .required(false),
+    );
+    app = app.subcommand(fsck_sub);
+
     app.get_matches()
 }
 
@@ -136,11 +254,53 @@ fn parse_matches(matches: ArgMatches) -> Result<KrillUpMode, String> {
 
         let config = parse_config(m)?;
         Ok(KrillUpMode::Migrate { config, target })
+    } else if let Some(m) = matches.subcommand_matches("migrate-storage") {
+        let from = parse_storage_uri(m, "from")?;
+        let to = parse_storage_uri(m, "to")?;
+        Ok(KrillUpMode::MigrateStorage { from, to })
+    } else if let Some(m) = matches.subcommand_matches("restore") {
+        let archive = PathBuf::from(
+            m.value_of("archive")
+                .ok_or("--archive missing".to_string())?,
+        );
+        let config = parse_config(m)?;
+        Ok(KrillUpMode::Restore { config, archive })
+    } else if let Some(m) = matches.subcommand_matches("reencrypt") {
+        let config = parse_config(m)?;
+        Ok(KrillUpMode::Reencrypt { config })
+    } else if let Some(m) = matches.subcommand_matches("fsck") {
+        let config = parse_config(m)?;
+        let repair = m.is_present("repair");
+        let yes = m.is_present("yes");
+        Ok(KrillUpMode::Fsck {
+            config,
+            repair,
+            yes,
+        })
     } else {
         Err("Cannot parse arguments. Use --help.".to_string())
     }
 }
 
+/// Asks the user the given question on the terminal, and returns whether
+/// they answered yes.
+fn confirm(question: &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    let _ = io::stderr().flush();
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+fn parse_storage_uri(m: &ArgMatches, arg: &str) -> Result<Url, String> {
+    let uri = m.value_of(arg).ok_or(format!("--{} missing", arg))?;
+    Url::parse(uri)
+        .map_err(|e| format!("cannot parse url: {}. Error: {}", uri, e))
+}
+
 fn parse_config(m: &ArgMatches) -> Result<Config, String> {
     let config_file =
         m.value_of("config").unwrap_or(KRILL_DEFAULT_CONFIG_FILE);
@@ -155,6 +315,27 @@ fn parse_config(m: &ArgMatches) -> Result<Config, String> {
 }
 
 enum KrillUpMode {
-    Prepare { config: Config },
-    Migrate { config: Config, target: Url },
+    Prepare {
+        config: Config,
+    },
+    Migrate {
+        config: Config,
+        target: Url,
+    },
+    MigrateStorage {
+        from: Url,
+        to: Url,
+    },
+    Restore {
+        config: Config,
+        archive: PathBuf,
+    },
+    Reencrypt {
+        config: Config,
+    },
+    Fsck {
+        config: Config,
+        repair: bool,
+        yes: bool,
+    },
 }
@@ -1,14 +1,14 @@
-use std::{env, fmt};
+use std::{env, fmt, fs::File, io::BufReader, path::PathBuf};
 
 use serde::{de::DeserializeOwned, Serialize};
 
-use rpki::ca::idexchange;
+use rpki::ca::{idexchange, idexchange::CaHandle};
 
 use crate::{
     cli::{
         options::{
-            BulkCaCommand, CaCommand, Command, KrillInitDetails, Options,
-            PubServerCommand,
+            AdminCommand, BulkCaCommand, CaCommand, Command,
+            KrillInitDetails, Options, PubServerCommand, TaskCommand,
         },
         report::{ApiResponse, ReportError},
     },
@@ -16,16 +16,20 @@ use crate::{
         api::{
             AllCertAuthIssues, ApiRepositoryContact, AspaDefinitionUpdates,
             BgpSecDefinitionUpdates, CaRepoDetails, CertAuthIssues,
-            ChildCaInfo, ChildrenConnectionStats, ParentCaContact,
-            ParentStatuses, PublisherDetails, PublisherList, RepoStatus,
-            Token,
+            ChildCaInfo, ChildrenConnectionStats, EmergencyRekeyRequest,
+            HistoryPoint, ParentCaContact, ParentStatuses, PublisherDetails,
+            PublisherList, RepoStatus, Token,
         },
         bgp::BgpAnalysisAdvice,
         error::KrillIoError,
         util::{file, httpclient},
     },
     constants::KRILL_CLI_API_ENV,
-    daemon::config::Config,
+    daemon::{
+        backup,
+        ca::{SignedGeofeed, SignedGeofeeds, SignedRsc},
+        config::Config,
+    },
 };
 
 #[cfg(feature = "multi-user")]
@@ -72,6 +76,23 @@ async fn post_json(
         .map_err(Error::HttpClientError)
 }
 
+/// Returns the API path for a CA, or a resource under it. If a point in
+/// history is given, then the path is for the state of the CA at that point.
+fn ca_path(
+    handle: &CaHandle,
+    at: Option<HistoryPoint>,
+    resource: Option<&str>,
+) -> String {
+    let ca = match at {
+        Some(at) => format!("api/v1/cas/{}/history/at/{}", handle, at),
+        None => format!("api/v1/cas/{}", handle),
+    };
+    match resource {
+        Some(resource) => format!("{}/{}", ca, resource),
+        None => ca,
+    }
+}
+
 async fn post_json_with_response<T: DeserializeOwned>(
     server: &idexchange::ServiceUri,
     token: &Token,
@@ -146,7 +167,10 @@ impl KrillClient {
         match options.command {
             Command::Health => client.health().await,
             Command::Info => client.info().await,
+            Command::Ha => client.ha().await,
             Command::Bulk(cmd) => client.bulk(cmd).await,
+            Command::Admin(cmd) => client.admin(cmd).await,
+            Command::Tasks(cmd) => client.tasks(cmd).await,
             Command::CertAuth(cmd) => client.certauth(cmd).await,
             Command::PubServer(cmd) => client.publishers(cmd).await,
             Command::Init(details) => client.init_config(details),
@@ -174,6 +198,93 @@ impl KrillClient {
         Ok(ApiResponse::Info(info))
     }
 
+    async fn ha(&self) -> Result<ApiResponse, Error> {
+        let status = httpclient::get_json(
+            &resolve_uri(&self.server, "api/v1/ha"),
+            Some(&self.token),
+        )
+        .await?;
+        Ok(ApiResponse::Ha(status))
+    }
+
+    async fn admin(
+        &self,
+        command: AdminCommand,
+    ) -> Result<ApiResponse, Error> {
+        match command {
+            AdminCommand::Backup(path) => {
+                let uri = resolve_uri(&self.server, "api/v1/admin/backup");
+                let mut file = File::create(&path).map_err(|e| {
+                    KrillIoError::new(
+                        format!("Cannot create file '{}'", path.display()),
+                        e,
+                    )
+                })?;
+                httpclient::get_to_writer(&uri, Some(&self.token), &mut file)
+                    .await?;
+
+                // The server cannot report errors once it started sending
+                // the backup, so check that we got all of it.
+                let file = File::open(&path).map_err(|e| {
+                    KrillIoError::new(
+                        format!("Cannot read file '{}'", path.display()),
+                        e,
+                    )
+                })?;
+                let manifest = backup::verify_backup(BufReader::new(file))
+                    .map_err(|e| Error::InvalidBackup(e.to_string()))?;
+
+                Ok(ApiResponse::Backup(manifest))
+            }
+        }
+    }
+
+    async fn tasks(
+        &self,
+        command: TaskCommand,
+    ) -> Result<ApiResponse, Error> {
+        match command {
+            TaskCommand::List => {
+                let tasks =
+                    get_json(&self.server, &self.token, "api/v1/tasks")
+                        .await?;
+                Ok(ApiResponse::Tasks(tasks))
+            }
+            TaskCommand::Show(name) => {
+                let uri = format!("api/v1/tasks/{}", name);
+                let details =
+                    get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::TaskDetails(details))
+            }
+            TaskCommand::Reschedule(name) => {
+                let uri = format!("api/v1/tasks/{}/reschedule", name);
+                post_empty(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+            TaskCommand::Cancel(name) => {
+                let uri = format!("api/v1/tasks/{}", name);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+            TaskCommand::DeadLetters => {
+                let tasks =
+                    get_json(&self.server, &self.token, "api/v1/tasks/dead")
+                        .await?;
+                Ok(ApiResponse::DeadLetters(tasks))
+            }
+            TaskCommand::Requeue(name) => {
+                let uri = format!("api/v1/tasks/dead/{}/requeue", name);
+                post_empty(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+            TaskCommand::Discard(name) => {
+                let uri = format!("api/v1/tasks/dead/{}", name);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+        }
+    }
+
     async fn bulk(
         &self,
         command: BulkCaCommand,
@@ -219,6 +330,16 @@ impl KrillClient {
                 )
                 .await?;
             }
+            BulkCaCommand::CompactHistory(retention) => {
+                let reports = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    "api/v1/bulk/cas/history/compact",
+                    retention,
+                )
+                .await?;
+                return Ok(ApiResponse::HistoryCompactions(reports));
+            }
             BulkCaCommand::Import(structure) => {
                 post_json(
                     &self.server,
@@ -250,6 +371,42 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::Rename(ca, rename) => {
+                let uri = format!("api/v1/cas/{}/rename", ca);
+                post_json(&self.server, &self.token, &uri, rename).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::Clone(ca, clone) => {
+                let uri = format!("api/v1/cas/{}/clone", ca);
+                let report = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    clone,
+                )
+                .await?;
+                Ok(ApiResponse::CaTemplateReport(report))
+            }
+
+            CaCommand::TemplateShow(ca) => {
+                let uri = format!("api/v1/cas/{}/template", ca);
+                let template =
+                    get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::CaTemplate(template))
+            }
+
+            CaCommand::InitFromTemplate(request) => {
+                let report = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    "api/v1/bulk/cas/template",
+                    request,
+                )
+                .await?;
+                Ok(ApiResponse::CaTemplateReport(report))
+            }
+
             CaCommand::UpdateId(handle) => {
                 let uri = format!("api/v1/cas/{}/id", handle);
                 post_empty(&self.server, &self.token, &uri).await?;
@@ -305,6 +462,36 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::RepoMirrorAdd(handle, name, response) => {
+                let uri =
+                    format!("api/v1/cas/{}/repo/mirrors/{}", handle, name);
+                let api_contact = ApiRepositoryContact::new(response);
+                post_json(&self.server, &self.token, &uri, api_contact)
+                    .await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RepoMirrorRemove(handle, name) => {
+                let uri =
+                    format!("api/v1/cas/{}/repo/mirrors/{}", handle, name);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RepoMigrationStart(handle, response) => {
+                let uri = format!("api/v1/cas/{}/repo/migration", handle);
+                let api_contact = ApiRepositoryContact::new(response);
+                post_json(&self.server, &self.token, &uri, api_contact)
+                    .await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RepoMigrationAbort(handle) => {
+                let uri = format!("api/v1/cas/{}/repo/migration", handle);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+
             CaCommand::AddParent(handle, parent_req) => {
                 let uri = format!("api/v1/cas/{}/parents", handle);
                 post_json(&self.server, &self.token, &uri, parent_req)
@@ -403,9 +590,72 @@ impl KrillClient {
                 post_empty(&self.server, &self.token, &uri).await?;
                 Ok(ApiResponse::Empty)
             }
+            CaCommand::KeyRollEmergency(handle, reason) => {
+                let uri =
+                    format!("api/v1/cas/{}/keys/emergency_rekey", handle);
+                let request = EmergencyRekeyRequest::new(reason);
+                let report = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    request,
+                )
+                .await?;
+                Ok(ApiResponse::EmergencyRekey(report))
+            }
+            CaCommand::KeyRollPolicyShow(handle) => {
+                let uri = format!("api/v1/cas/{}/keys/roll_policy", handle);
+                let status =
+                    get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::KeyRollStatus(status))
+            }
+            CaCommand::KeyRollPolicySet(handle, policy) => {
+                let uri = format!("api/v1/cas/{}/keys/roll_policy", handle);
+                post_json(&self.server, &self.token, &uri, policy).await?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::KeyRollPolicyRemove(handle) => {
+                let uri = format!("api/v1/cas/{}/keys/roll_policy", handle);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
 
-            CaCommand::RouteAuthorizationsList(handle) => {
-                let uri = format!("api/v1/cas/{}/routes", handle);
+            CaCommand::IssuanceTimingShow(handle) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                let timing =
+                    get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::IssuanceTiming(timing))
+            }
+            CaCommand::IssuanceTimingSet(handle, timing) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                post_json(&self.server, &self.token, &uri, timing).await?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::IssuanceTimingRemove(handle) => {
+                let uri = format!("api/v1/cas/{}/timing", handle);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::ResourcePoolsList(handle) => {
+                let uri = format!("api/v1/cas/{}/pools", handle);
+                let pools = get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::ResourcePools(pools))
+            }
+            CaCommand::ResourcePoolUpdate(handle, definition) => {
+                let uri = format!("api/v1/cas/{}/pools", handle);
+                post_json(&self.server, &self.token, &uri, definition)
+                    .await?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::ResourcePoolRemove(handle, name) => {
+                let uri = format!("api/v1/cas/{}/pools/{}", handle, name);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RouteAuthorizationsList(handle, at) => {
+                let uri = ca_path(&handle, at, Some("routes"));
                 let roas = get_json(&self.server, &self.token, &uri).await?;
                 Ok(ApiResponse::RouteAuthorizations(roas))
             }
@@ -474,8 +724,8 @@ impl KrillClient {
                 Ok(ApiResponse::BgpAnalysisSuggestions(suggestions))
             }
 
-            CaCommand::BgpSecList(handle) => {
-                let uri = format!("api/v1/cas/{}/bgpsec", handle);
+            CaCommand::BgpSecList(handle, at) => {
+                let uri = ca_path(&handle, at, Some("bgpsec"));
                 let bgpsec_list =
                     get_json(&self.server, &self.token, &uri).await?;
                 Ok(ApiResponse::BgpSecDefinitions(bgpsec_list))
@@ -497,8 +747,8 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
-            CaCommand::AspasList(handle) => {
-                let uri = format!("api/v1/cas/{}/aspas", handle);
+            CaCommand::AspasList(handle, at) => {
+                let uri = ca_path(&handle, at, Some("aspas"));
                 let aspas = get_json(&self.server, &self.token, &uri).await?;
                 Ok(ApiResponse::AspaDefinitions(aspas))
             }
@@ -525,8 +775,8 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
-            CaCommand::Show(handle) => {
-                let uri = format!("api/v1/cas/{}", handle);
+            CaCommand::Show(handle, at) => {
+                let uri = ca_path(&handle, at, None);
                 let ca_info =
                     get_json(&self.server, &self.token, &uri).await?;
 
@@ -554,6 +804,19 @@ impl KrillClient {
                 Ok(ApiResponse::CertAuthAction(action))
             }
 
+            CaCommand::CompactHistory(handle, retention) => {
+                let uri = format!("api/v1/cas/{}/history/compact", handle);
+                let report = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    retention,
+                )
+                .await?;
+
+                Ok(ApiResponse::HistoryCompaction(report))
+            }
+
             CaCommand::Issues(ca_opt) => match ca_opt {
                 Some(ca) => {
                     let uri = format!("api/v1/cas/{}/issues", ca);
@@ -617,6 +880,109 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::RscList(ca) => {
+                let uri = format!("api/v1/cas/{}/rsc", ca);
+                let list = get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::RscList(list))
+            }
+
+            CaCommand::RscShow(ca, name, out) => {
+                let uri = format!("api/v1/cas/{}/rsc/{}", ca, name);
+                let rsc: SignedRsc =
+                    get_json(&self.server, &self.token, &uri).await?;
+
+                match out {
+                    None => Ok(ApiResponse::Rsc(rsc)),
+                    Some(out) => {
+                        if let [object] = rsc.objects().as_slice() {
+                            file::save(object.rsc().as_ref(), &out)?;
+                        } else {
+                            for object in rsc.objects() {
+                                let mut file_name =
+                                    out.as_os_str().to_owned();
+                                file_name.push(format!(
+                                    ".{}",
+                                    object.resource_class_name()
+                                ));
+                                file::save(
+                                    object.rsc().as_ref(),
+                                    &PathBuf::from(file_name),
+                                )?;
+                            }
+                        }
+                        Ok(ApiResponse::Empty)
+                    }
+                }
+            }
+
+            CaCommand::RscSign(ca, name, request) => {
+                let uri = format!("api/v1/cas/{}/rsc/{}/sign", ca, name);
+                post_json(&self.server, &self.token, &uri, request).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::RscCoSign(ca, name, request) => {
+                let uri = format!("api/v1/cas/{}/rsc/{}/cosign", ca, name);
+                post_json(&self.server, &self.token, &uri, request).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::GeofeedList(ca) => {
+                let uri = format!("api/v1/cas/{}/geofeeds", ca);
+                let list = get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::GeofeedList(list))
+            }
+
+            CaCommand::GeofeedShow(ca, number, out) => {
+                let uri = format!("api/v1/cas/{}/geofeeds/{}", ca, number);
+                let geofeed: SignedGeofeed =
+                    get_json(&self.server, &self.token, &uri).await?;
+
+                match out {
+                    None => Ok(ApiResponse::Geofeed(geofeed)),
+                    Some(out) => {
+                        file::save(geofeed.geofeed().as_bytes(), &out)?;
+                        Ok(ApiResponse::Empty)
+                    }
+                }
+            }
+
+            CaCommand::GeofeedSign(ca, request, out) => {
+                let uri = format!("api/v1/cas/{}/geofeeds/sign", ca);
+                let signed: SignedGeofeeds = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    request,
+                )
+                .await?;
+
+                match out {
+                    None => Ok(ApiResponse::SignedGeofeeds(signed)),
+                    Some(out) => {
+                        if signed.len() == 1 {
+                            for (_, geofeed) in signed.iter() {
+                                file::save(
+                                    geofeed.geofeed().as_bytes(),
+                                    &out,
+                                )?;
+                            }
+                        } else {
+                            for (ca, geofeed) in signed.iter() {
+                                let mut file_name =
+                                    out.as_os_str().to_owned();
+                                file_name.push(format!(".{}", ca));
+                                file::save(
+                                    geofeed.geofeed().as_bytes(),
+                                    &PathBuf::from(file_name),
+                                )?;
+                            }
+                        }
+                        Ok(ApiResponse::Empty)
+                    }
+                }
+            }
+
             CaCommand::List => {
                 let cas =
                     get_json(&self.server, &self.token, "api/v1/cas").await?;
@@ -861,6 +1227,7 @@ pub enum Error {
     Rfc8183(idexchange::Error),
     InitError(String),
     InputError(String),
+    InvalidBackup(String),
 }
 
 impl fmt::Display for Error {
@@ -881,6 +1248,9 @@ impl fmt::Display for Error {
             Error::Rfc8183(e) => e.fmt(f),
             Error::InitError(s) => s.fmt(f),
             Error::InputError(s) => s.fmt(f),
+            Error::InvalidBackup(s) => {
+                write!(f, "Received an invalid backup: {}", s)
+            }
         }
     }
 }
@@ -33,9 +33,13 @@ use crate::{
             self, import::ImportChild, AddChildRequest, AspaDefinition,
             AspaDefinitionFormatError, AspaProvidersUpdate,
             AuthorizationFmtError, BgpSecAsnKey, BgpSecDefinition,
-            CertAuthInit, CustomerAsn, ParentCaReq, ProviderAsn,
-            PublicationServerUris, RepoFileDeleteCriteria, RoaConfiguration,
-            RoaConfigurationUpdates, RoaPayload, RtaName, Token,
+            CaIssuanceTiming, CertAuthClone, CertAuthFromTemplate,
+            CertAuthInit, CertAuthRename, ChildAllocation, ChildCertTiming,
+            CustomerAsn, HistoryPoint, HistoryRetention, KeyRollPolicy,
+            ParentCaReq, PoolAllocation, ProviderAsn, PublicationServerUris,
+            RepoFileDeleteCriteria, RepositoryMirrorName,
+            ResourcePoolDefinition, ResourcePoolName, RoaConfiguration,
+            RoaConfigurationUpdates, RoaPayload, RscName, RtaName, Token,
             UpdateChildRequest,
         },
         crypto::SignSupport,
@@ -44,10 +48,71 @@ use crate::{
     },
     constants::*,
     daemon::ca::{
-        ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
+        GeofeedSignRequest, ResourceTaggedAttestation, RscChecklistItem,
+        RscSignRequest, RtaContentRequest, RtaPrepareRequest,
     },
 };
 
+/// The arguments for overriding the issuance timing of a CA: the name of
+/// the setting, the long option, and the help text.
+const ISSUANCE_TIMING_ARGS: [(&str, &str, &str); 11] = [
+    (
+        "timing_publish_next_hours",
+        "publish-next-hours",
+        "Hours until the next update of manifests and CRLs, at least 2",
+    ),
+    (
+        "timing_publish_next_jitter_hours",
+        "publish-next-jitter-hours",
+        "Maximum random hours added to the next update time",
+    ),
+    (
+        "timing_publish_hours_before_next",
+        "publish-hours-before-next",
+        "Republish manifests and CRLs this many hours before the next update",
+    ),
+    (
+        "timing_child_certificate_valid_weeks",
+        "child-certificate-valid-weeks",
+        "Validity of certificates issued to children, in weeks",
+    ),
+    (
+        "timing_child_certificate_reissue_weeks_before",
+        "child-certificate-reissue-weeks-before",
+        "Re-issue child certificates this many weeks before they expire",
+    ),
+    (
+        "timing_roa_valid_weeks",
+        "roa-valid-weeks",
+        "Validity of ROAs, in weeks",
+    ),
+    (
+        "timing_roa_reissue_weeks_before",
+        "roa-reissue-weeks-before",
+        "Re-issue ROAs this many weeks before they expire",
+    ),
+    (
+        "timing_aspa_valid_weeks",
+        "aspa-valid-weeks",
+        "Validity of ASPA objects, in weeks",
+    ),
+    (
+        "timing_aspa_reissue_weeks_before",
+        "aspa-reissue-weeks-before",
+        "Re-issue ASPA objects this many weeks before they expire",
+    ),
+    (
+        "timing_bgpsec_valid_weeks",
+        "bgpsec-valid-weeks",
+        "Validity of BGPSec router certificates, in weeks",
+    ),
+    (
+        "timing_bgpsec_reissue_weeks_before",
+        "bgpsec-reissue-weeks-before",
+        "Re-issue BGPSec router certificates this many weeks before they expire",
+    ),
+];
+
 #[derive(Debug)]
 pub struct GeneralArgs {
     pub server: idexchange::ServiceUri,
@@ -356,6 +421,7 @@ impl Options {
 
         sub = GeneralArgs::add_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_history_point_arg(sub);
 
         app.subcommand(sub)
     }
@@ -424,12 +490,125 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_history_compact_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("compact")
+            .about("Archive old commands in the history of a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_history_retention_args(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn add_history_retention_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("keep_versions")
+                .long("keep-versions")
+                .help("Keep at least this many of the most recent commands")
+                .value_name("<number>")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("keep_days")
+                .long("keep-days")
+                .help("Keep all commands from this number of recent days")
+                .value_name("<number>")
+                .required(false),
+        )
+    }
+
+    fn add_child_cert_timing_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("valid_weeks")
+                .long("valid-weeks")
+                .help("Issue certificates to this child valid for this many weeks, instead of the server default")
+                .value_name("<number>")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("reissue_weeks_before")
+                .long("reissue-weeks-before")
+                .help("Re-issue certificates to this child this many weeks before they expire, instead of the server default")
+                .value_name("<number>")
+                .required(false),
+        )
+    }
+
+    fn add_child_allocation_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("allocate_from")
+                .long("allocate-from")
+                .help("Allocate the next free resources from this resource pool, in addition to any resources given")
+                .value_name("pool")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("allocate_ipv4")
+                .long("allocate-ipv4")
+                .help("Allocate the next free IPv4 prefix of this length from the pool, e.g. 24")
+                .value_name("<prefix length>")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("allocate_ipv6")
+                .long("allocate-ipv6")
+                .help("Allocate the next free IPv6 prefix of this length from the pool, e.g. 48")
+                .value_name("<prefix length>")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("allocate_asns")
+                .long("allocate-asns")
+                .help("Allocate this number of the next free ASNs from the pool")
+                .value_name("<number>")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("allow_overlap")
+                .long("allow-overlap")
+                .help("Allow resources in pools which are also delegated to other children")
+                .required(false),
+        )
+    }
+
+    fn add_pool_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("pool")
+                .long("pool")
+                .help("The name of the resource pool")
+                .value_name("name")
+                .required(true),
+        )
+    }
+
+    fn add_repo_mirror_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("mirror")
+                .long("mirror")
+                .help("The name of the repository mirror")
+                .value_name("name")
+                .required(true),
+        )
+    }
+
+    fn add_history_point_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("at")
+                .long("at")
+                .help("Show the state at a version, or date/time in RFC 3339 format, in the history of the CA")
+                .value_name("version or date/time")
+                .required(false),
+        )
+    }
+
     fn make_cas_show_history_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("history")
             .about("Show the history of a CA");
 
         sub = Self::make_cas_show_history_list_sc(sub);
         sub = Self::make_cas_show_history_details_sc(sub);
+        sub = Self::make_cas_history_compact_sc(sub);
 
         app.subcommand(sub)
     }
@@ -453,6 +632,109 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_rename_ca_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("rename")
+            .about("Rename a CA, keeping its keys, parents and children");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("new")
+                    .long("new")
+                    .value_name("name")
+                    .help("The new name for the CA")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("alias_days")
+                    .long("alias-days")
+                    .value_name("number of days")
+                    .help("Keep the old name as an alias for this many days")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn add_copy_definitions_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("copy_definitions")
+                .long("copy-definitions")
+                .help("Copy ROA and ASPA definitions for resources the new CA holds")
+                .required(false),
+        )
+    }
+
+    fn make_cas_clone_ca_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("clone").about(
+            "Create a new CA with the same repository and parents as a CA",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = sub.arg(
+            Arg::with_name("new")
+                .long("new")
+                .value_name("name")
+                .help("The name for the new CA")
+                .required(true),
+        );
+        sub = Self::add_copy_definitions_arg(sub);
+        sub = Self::add_resource_args(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_template_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the setup of a CA as a template for new CAs");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_template_apply_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("apply")
+            .about("Create a new CA using a template");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = sub
+            .arg(
+                Arg::with_name("new")
+                    .long("new")
+                    .value_name("name")
+                    .help("The name for the new CA")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("template")
+                    .long("template")
+                    .value_name("file")
+                    .help(
+                        "The JSON template file, see 'krillc template show'",
+                    )
+                    .required(true),
+            );
+        sub = Self::add_copy_definitions_arg(sub);
+        sub = Self::add_resource_args(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_template_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("template")
+            .about("Show CA templates, and create new CAs from them");
+
+        sub = Self::make_cas_template_show_sc(sub);
+        sub = Self::make_cas_template_apply_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_children_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub =
             SubCommand::with_name("add").about("Add a child to a CA");
@@ -461,6 +743,8 @@ impl Options {
         sub = Self::add_my_ca_arg(sub);
         sub = Self::add_child_arg(sub);
         sub = Self::add_resource_args(sub);
+        sub = Self::add_child_cert_timing_args(sub);
+        sub = Self::add_child_allocation_args(sub);
         let sub = sub.arg(
             Arg::with_name("request")
                 .long("request")
@@ -481,6 +765,8 @@ impl Options {
         sub = Self::add_my_ca_arg(sub);
         sub = Self::add_child_arg(sub);
         sub = Self::add_resource_args(sub);
+        sub = Self::add_child_cert_timing_args(sub);
+        sub = Self::add_child_allocation_args(sub);
         sub = sub.arg(
             Arg::with_name("idcert")
                 .long("idcert")
@@ -674,12 +960,195 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_keyroll_emergency_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("emergency")
+            .about("Replace all keys held by a CA immediately, e.g. if they may be compromised");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("reason")
+                .long("reason")
+                .help("The reason for the emergency key roll, kept in the CA history")
+                .value_name("<text>")
+                .required(true),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_show_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the key roll policy and next planned key roll");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_set_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("set")
+            .about("Set the key roll policy for a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("months")
+                    .long("months")
+                    .help("Roll keys which have been active for this many months, or 0 to never roll keys automatically")
+                    .value_name("<number>")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("staging_hours")
+                    .long("staging-hours")
+                    .help("Activate new keys after this many hours, at least 24 (default)")
+                    .value_name("<number>")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove")
+            .about("Remove the key roll policy, use the server default");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("policy")
+            .about("Manage the policy for automatic key rolls of a CA");
+
+        sub = Self::make_cas_keyroll_policy_show_sc(sub);
+        sub = Self::make_cas_keyroll_policy_set_sc(sub);
+        sub = Self::make_cas_keyroll_policy_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_keyroll_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("keyroll")
             .about("Perform a manual key rollover for a CA");
 
         sub = Self::make_cas_keyroll_init_sc(sub);
         sub = Self::make_cas_keyroll_activate_sc(sub);
+        sub = Self::make_cas_keyroll_emergency_sc(sub);
+        sub = Self::make_cas_keyroll_policy_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the issuance timing overrides for a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_set_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("set").about(
+            "Set issuance timing overrides for a CA, replacing any previous overrides",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        for (name, long, help) in ISSUANCE_TIMING_ARGS {
+            sub = sub.arg(
+                Arg::with_name(name)
+                    .long(long)
+                    .help(help)
+                    .value_name("<number>")
+                    .required(false),
+            );
+        }
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about(
+            "Remove the issuance timing overrides, use the server defaults",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_timing_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("timing")
+            .about("Manage the issuance timing of a CA");
+
+        sub = Self::make_cas_timing_show_sc(sub);
+        sub = Self::make_cas_timing_set_sc(sub);
+        sub = Self::make_cas_timing_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_pools_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list")
+            .about("Show the resource pools of a CA and their utilization");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_pools_set_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("set")
+            .about("Add or replace a resource pool with resources of a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_pool_arg(sub);
+        sub = Self::add_resource_args(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_pools_remove_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about(
+            "Remove a resource pool, this does not affect the resources of children",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_pool_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_pools_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("pools")
+            .about("Manage the resource pools used for allocating resources to children");
+
+        sub = Self::make_cas_pools_list_sc(sub);
+        sub = Self::make_cas_pools_set_sc(sub);
+        sub = Self::make_cas_pools_remove_sc(sub);
 
         app.subcommand(sub)
     }
@@ -690,6 +1159,7 @@ impl Options {
 
         sub = GeneralArgs::add_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_history_point_arg(sub);
 
         app.subcommand(sub)
     }
@@ -821,6 +1291,7 @@ impl Options {
 
         sub = GeneralArgs::add_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_history_point_arg(sub);
 
         app.subcommand(sub)
     }
@@ -968,6 +1439,7 @@ impl Options {
 
         sub = GeneralArgs::add_args(sub);
         sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_history_point_arg(sub);
 
         app.subcommand(sub)
     }
@@ -1032,19 +1504,110 @@ impl Options {
         app.subcommand(sub)
     }
 
-    fn make_cas_repo_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
-        let mut sub = SubCommand::with_name("repo")
-            .about("Manage the repository for a CA");
-
-        sub = Self::make_cas_repo_request_sc(sub);
-        sub = Self::make_cas_repo_show_sc(sub);
-        sub = Self::make_cas_repo_status_sc(sub);
-        sub = Self::make_cas_repo_configure_sc(sub);
-
-        app.subcommand(sub)
-    }
+    fn make_cas_repo_mirror_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("add").about(
+            "Add or update a repository mirror, which must use the same base URI as the primary repository",
+        );
 
-    fn make_cas_issues_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_repo_mirror_arg(sub);
+        sub = sub.arg(
+            Arg::with_name("response")
+                .value_name("file")
+                .long("response")
+                .short("r")
+                .help("The location of the RFC 8183 Publisher Response XML file of the mirror")
+                .required(true),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_mirror_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about(
+            "Remove a repository mirror, and withdraw the objects published there",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_repo_mirror_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_mirror_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("mirror").about(
+            "Manage repositories to which a CA publishes a copy of its objects",
+        );
+
+        sub = Self::make_cas_repo_mirror_add_sc(sub);
+        sub = Self::make_cas_repo_mirror_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_migrate_start_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("start").about(
+            "Start a staged migration to a new repository, which is verified before new certificates are requested",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = sub.arg(
+            Arg::with_name("response")
+                .value_name("file")
+                .long("response")
+                .short("r")
+                .help("The location of the RFC 8183 Publisher Response XML file of the new repository")
+                .required(true),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_migrate_abort_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("abort").about(
+            "Abort a migration which is still staging objects in the new repository",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_migrate_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("migrate")
+            .about("Manage a staged migration to a new repository");
+
+        sub = Self::make_cas_repo_migrate_start_sc(sub);
+        sub = Self::make_cas_repo_migrate_abort_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_repo_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("repo")
+            .about("Manage the repository for a CA");
+
+        sub = Self::make_cas_repo_request_sc(sub);
+        sub = Self::make_cas_repo_show_sc(sub);
+        sub = Self::make_cas_repo_status_sc(sub);
+        sub = Self::make_cas_repo_configure_sc(sub);
+        sub = Self::make_cas_repo_mirror_sc(sub);
+        sub = Self::make_cas_repo_migrate_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_issues_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub =
             SubCommand::with_name("issues").about("Show issues for a CA");
 
@@ -1224,6 +1787,217 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn add_rsc_name_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("name")
+                .long("name")
+                .short("n")
+                .value_name("string")
+                .help("Your local name for this RSC")
+                .required(true),
+        )
+    }
+
+    fn make_cas_rsc_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list")
+            .about("List the RSCs signed by this CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_rsc_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show an RSC signed by this CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_rsc_name_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("out")
+                .long("out")
+                .short("o")
+                .value_name("path")
+                .help(
+                    "File to write the RSC to. If the RSC consists of \
+                    multiple objects then the resource class name is added \
+                    to the file name of each",
+                )
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_rsc_sign_args<'a, 'b>(
+        name: &'a str,
+        about: &'a str,
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name(name).about(about);
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+        sub = Self::add_rsc_name_arg(sub);
+        sub = Self::add_resource_args(sub);
+
+        sub = sub.arg(
+            Arg::with_name("days")
+                .long("days")
+                .short("d")
+                .value_name("number of days")
+                .help("Validity time of the RSC in days")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("file")
+                .long("file")
+                .short("f")
+                .value_name("path")
+                .multiple(true)
+                .help("File to include on the checklist")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("no_file_names")
+                .long("no-file-names")
+                .help("Only include the digests of files on the checklist")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_rsc_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("rsc")
+            .about("Manage RPKI Signed Checklists (RFC 9323)");
+
+        sub = Self::make_cas_rsc_list_sc(sub);
+        sub = Self::make_cas_rsc_show_sc(sub);
+        sub = Self::make_cas_rsc_sign_args(
+            "sign",
+            "Sign a checklist for resources held by this CA",
+            sub,
+        );
+        sub = Self::make_cas_rsc_sign_args(
+            "cosign",
+            "Co-sign a checklist for the resources held by this CA, when \
+            the resources span multiple CAs",
+            sub,
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_geofeed_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list")
+            .about("List the geofeeds signed by this CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_geofeed_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show a geofeed signed by this CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("number")
+                .long("number")
+                .short("n")
+                .value_name("number")
+                .help("The number of the signed geofeed, see 'list'")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("out")
+                .long("out")
+                .short("o")
+                .value_name("path")
+                .help("File to write the signed geofeed to")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_geofeed_sign_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("sign").about(
+            "Sign a geofeed (RFC 9632) for prefixes held by this CA, and \
+            optionally its co-signers",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("geofeed")
+                .long("geofeed")
+                .short("g")
+                .value_name("path")
+                .help("The geofeed CSV file to sign")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("days")
+                .long("days")
+                .short("d")
+                .value_name("number of days")
+                .help("Validity time of the signature in days")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("cosigner")
+                .long("cosigner")
+                .value_name("name")
+                .multiple(true)
+                .help(
+                    "Another CA which holds prefixes in the geofeed. Each \
+                    CA then signs its own geofeed with its own prefixes",
+                )
+                .required(false),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("out")
+                .long("out")
+                .short("o")
+                .value_name("path")
+                .help(
+                    "File to write the signed geofeed to. If there are \
+                    co-signers then the name of the CA is added to the file \
+                    name of each geofeed",
+                )
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_geofeed_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("geofeed")
+            .about("Manage signed geofeeds (RFC 9632)");
+
+        sub = Self::make_cas_geofeed_list_sc(sub);
+        sub = Self::make_cas_geofeed_show_sc(sub);
+        sub = Self::make_cas_geofeed_sign_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_bulk_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("bulk")
             .about("Manually trigger refresh/republish/resync for all CAs");
@@ -1241,14 +2015,101 @@ impl Options {
             .about("Force that all CAs sync with their repo server");
         resync = GeneralArgs::add_args(resync);
 
+        let mut compact = SubCommand::with_name("compact")
+            .about("Archive old commands in the history of all CAs");
+        compact = GeneralArgs::add_args(compact);
+        compact = Self::add_history_retention_args(compact);
+
         sub = sub
             .subcommand(refresh)
             .subcommand(republish)
-            .subcommand(resync);
+            .subcommand(resync)
+            .subcommand(compact);
 
         app.subcommand(sub)
     }
 
+    fn make_admin_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("admin")
+            .about("Perform administrative tasks for the server");
+
+        let mut backup = SubCommand::with_name("backup")
+            .about("Save a backup of all data, while the server is running");
+        backup = GeneralArgs::add_args(backup);
+        backup = backup.arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help("The file to save the backup to")
+                .value_name("path")
+                .required(true),
+        );
+
+        sub = sub.subcommand(backup);
+
+        app.subcommand(sub)
+    }
+
+    fn make_tasks_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("tasks")
+            .about("Inspect and manage the server's task queue");
+
+        let mut list = SubCommand::with_name("list")
+            .about("List all pending and running tasks");
+        list = GeneralArgs::add_args(list);
+
+        let mut show =
+            SubCommand::with_name("show").about("Show the details of a task");
+        show = GeneralArgs::add_args(show);
+        show = Self::add_task_name_arg(show);
+
+        let mut reschedule = SubCommand::with_name("reschedule")
+            .about("Reschedule a task to run now");
+        reschedule = GeneralArgs::add_args(reschedule);
+        reschedule = Self::add_task_name_arg(reschedule);
+
+        let mut cancel = SubCommand::with_name("cancel")
+            .about("Remove a task from the queue");
+        cancel = GeneralArgs::add_args(cancel);
+        cancel = Self::add_task_name_arg(cancel);
+
+        let mut dead = SubCommand::with_name("dead").about(
+            "List tasks that were given up on after failing too often",
+        );
+        dead = GeneralArgs::add_args(dead);
+
+        let mut requeue = SubCommand::with_name("requeue")
+            .about("Move a dead letter task back onto the queue, to run now");
+        requeue = GeneralArgs::add_args(requeue);
+        requeue = Self::add_task_name_arg(requeue);
+
+        let mut discard = SubCommand::with_name("discard")
+            .about("Remove a dead letter task");
+        discard = GeneralArgs::add_args(discard);
+        discard = Self::add_task_name_arg(discard);
+
+        sub = sub
+            .subcommand(list)
+            .subcommand(show)
+            .subcommand(reschedule)
+            .subcommand(cancel)
+            .subcommand(dead)
+            .subcommand(requeue)
+            .subcommand(discard);
+
+        app.subcommand(sub)
+    }
+
+    fn add_task_name_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("name")
+                .long("name")
+                .help("The name of the task, as shown by 'tasks list' or 'tasks dead'")
+                .value_name("name")
+                .required(true),
+        )
+    }
+
     fn make_health_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let health = SubCommand::with_name("health")
             .about("Perform an authenticated health check");
@@ -1262,6 +2123,13 @@ impl Options {
         app.subcommand(info)
     }
 
+    fn make_ha_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let ha = SubCommand::with_name("ha")
+            .about("Show the leader election status of the server");
+        let ha = GeneralArgs::add_args(ha);
+        app.subcommand(ha)
+    }
+
     fn make_publishers_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub =
             SubCommand::with_name("list").about("List all publishers");
@@ -1469,15 +2337,22 @@ impl Options {
         app = Self::make_cas_show_history_sc(app);
         app = Self::make_cas_add_ca_sc(app);
         app = Self::make_cas_delete_ca_sc(app);
+        app = Self::make_cas_rename_ca_sc(app);
+        app = Self::make_cas_clone_ca_sc(app);
+        app = Self::make_cas_template_sc(app);
         app = Self::make_cas_children_sc(app);
         app = Self::make_cas_parents_sc(app);
         app = Self::make_cas_keyroll_sc(app);
+        app = Self::make_cas_timing_sc(app);
+        app = Self::make_cas_pools_sc(app);
         app = Self::make_cas_routes_sc(app);
         app = Self::make_cas_bgpsec_sc(app);
         app = Self::make_cas_repo_sc(app);
         app = Self::make_cas_issues_sc(app);
         app = Self::make_pubserver_sc(app);
         app = Self::make_cas_aspas_sc(app);
+        app = Self::make_cas_rsc_sc(app);
+        app = Self::make_cas_geofeed_sc(app);
 
         #[cfg(feature = "rta")]
         {
@@ -1488,8 +2363,14 @@ impl Options {
 
         app = Self::make_info_sc(app);
 
+        app = Self::make_ha_sc(app);
+
         app = Self::make_bulk_sc(app);
 
+        app = Self::make_admin_sc(app);
+
+        app = Self::make_tasks_sc(app);
+
         app.get_matches()
     }
 
@@ -1652,13 +2533,114 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_cas_rename(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let new_handle = matches.value_of("new").unwrap();
+        let new_handle = CaHandle::from_str(new_handle)
+            .map_err(|_| Error::InvalidHandle)?;
+
+        let alias_days = match matches.value_of("alias_days") {
+            None => None,
+            Some(days) => Some(u32::from_str(days).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid number of days: {}",
+                    e
+                ))
+            })?),
+        };
+
+        let rename = CertAuthRename::new(new_handle, alias_days);
+        let command = Command::CertAuth(CaCommand::Rename(my_ca, rename));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_clone(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let handle = matches.value_of("new").unwrap();
+        let handle =
+            CaHandle::from_str(handle).map_err(|_| Error::InvalidHandle)?;
+
+        let clone = CertAuthClone {
+            handle,
+            copy_definitions: matches.is_present("copy_definitions"),
+            resources: Self::parse_resource_args(matches)?,
+        };
+        let command = Command::CertAuth(CaCommand::Clone(my_ca, clone));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_template(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_template_show(m)
+        } else if let Some(m) = matches.subcommand_matches("apply") {
+            Self::parse_matches_cas_template_apply(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_template_show(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::TemplateShow(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_template_apply(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+
+        let handle = matches.value_of("new").unwrap();
+        let handle =
+            CaHandle::from_str(handle).map_err(|_| Error::InvalidHandle)?;
+
+        let path = matches.value_of("template").unwrap();
+        let bytes = Self::read_file_arg(path)?;
+        let template =
+            serde_json::from_slice(bytes.as_ref()).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid template file: {}, error: {}",
+                    path, e
+                ))
+            })?;
+
+        let request = CertAuthFromTemplate {
+            handle,
+            template,
+            copy_definitions: matches.is_present("copy_definitions"),
+            resources: Self::parse_resource_args(matches)?,
+        };
+        let command = Command::CertAuth(CaCommand::InitFromTemplate(request));
+
+        Ok(Options::make(general_args, command))
+    }
+
     fn parse_matches_cas_show(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::Show(my_ca));
+        let at = Self::parse_history_point_arg(matches)?;
+
+        let command = Command::CertAuth(CaCommand::Show(my_ca, at));
         Ok(Options::make(general_args, command))
     }
 
@@ -1722,6 +2704,125 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_cas_history_compact(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let retention = Self::parse_history_retention_args(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::CompactHistory(my_ca, retention));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_history_retention_args(
+        matches: &ArgMatches,
+    ) -> Result<HistoryRetention, Error> {
+        let keep_versions = match matches.value_of("keep_versions") {
+            Some(versions) => Some(u64::from_str(versions).map_err(|e| {
+                Error::general(&format!("invalid number: {}", e))
+            })?),
+            None => None,
+        };
+
+        let keep_days = match matches.value_of("keep_days") {
+            Some(days) => Some(u32::from_str(days).map_err(|e| {
+                Error::general(&format!("invalid number: {}", e))
+            })?),
+            None => None,
+        };
+
+        Ok(HistoryRetention::new(keep_versions, keep_days))
+    }
+
+    fn parse_child_cert_timing_args(
+        matches: &ArgMatches,
+    ) -> Result<ChildCertTiming, Error> {
+        let parse_weeks = |name: &str| -> Result<Option<u32>, Error> {
+            match matches.value_of(name) {
+                Some(weeks) => {
+                    Ok(Some(u32::from_str(weeks).map_err(|e| {
+                        Error::general(&format!("invalid number: {}", e))
+                    })?))
+                }
+                None => Ok(None),
+            }
+        };
+
+        Ok(ChildCertTiming::new(
+            parse_weeks("valid_weeks")?,
+            parse_weeks("reissue_weeks_before")?,
+        ))
+    }
+
+    fn parse_child_allocation_args(
+        matches: &ArgMatches,
+    ) -> Result<ChildAllocation, Error> {
+        let parse_number = |name: &str| -> Result<Option<u32>, Error> {
+            match matches.value_of(name) {
+                Some(number) => {
+                    Ok(Some(u32::from_str(number).map_err(|e| {
+                        Error::general(&format!("invalid number: {}", e))
+                    })?))
+                }
+                None => Ok(None),
+            }
+        };
+        let parse_prefix_len = |name: &str| -> Result<Option<u8>, Error> {
+            match matches.value_of(name) {
+                Some(len) => Ok(Some(u8::from_str(len).map_err(|e| {
+                    Error::general(&format!("invalid prefix length: {}", e))
+                })?)),
+                None => Ok(None),
+            }
+        };
+
+        let ipv4 = parse_prefix_len("allocate_ipv4")?;
+        let ipv6 = parse_prefix_len("allocate_ipv6")?;
+        let asns = parse_number("allocate_asns")?;
+
+        let from_pool = match matches.value_of("allocate_from") {
+            Some(pool) => {
+                if ipv4.is_none() && ipv6.is_none() && asns.is_none() {
+                    return Err(Error::general(
+                        "use --allocate-ipv4, --allocate-ipv6 or --allocate-asns with --allocate-from",
+                    ));
+                }
+                Some(PoolAllocation {
+                    pool: pool.to_string(),
+                    ipv4_prefix_len: ipv4,
+                    ipv6_prefix_len: ipv6,
+                    asns,
+                })
+            }
+            None => {
+                if ipv4.is_some() || ipv6.is_some() || asns.is_some() {
+                    return Err(Error::general(
+                        "use --allocate-from to select the pool to allocate from",
+                    ));
+                }
+                None
+            }
+        };
+
+        Ok(ChildAllocation::new(
+            from_pool,
+            matches.is_present("allow_overlap"),
+        ))
+    }
+
+    fn parse_history_point_arg(
+        matches: &ArgMatches,
+    ) -> Result<Option<HistoryPoint>, Error> {
+        match matches.value_of("at") {
+            Some(at) => HistoryPoint::from_str(at)
+                .map(Some)
+                .map_err(|e| Error::general(&e.to_string())),
+            None => Ok(None),
+        }
+    }
+
     fn parse_matches_cas_history(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
@@ -1729,6 +2830,8 @@ impl Options {
             Self::parse_matches_cas_history_commands(m)
         } else if let Some(m) = matches.subcommand_matches("details") {
             Self::parse_matches_cas_history_details(m)
+        } else if let Some(m) = matches.subcommand_matches("compact") {
+            Self::parse_matches_cas_history_compact(m)
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
@@ -1748,12 +2851,22 @@ impl Options {
         let child =
             ChildHandle::from_str(child).map_err(|_| Error::InvalidHandle)?;
 
-        let resources = Self::parse_resource_args(matches)?
-            .ok_or(Error::MissingResources)?;
+        let allocation = Self::parse_child_allocation_args(matches)?;
+
+        // Resources may be omitted if they are allocated from a pool.
+        let resources = match Self::parse_resource_args(matches)? {
+            Some(resources) => resources,
+            None if allocation.from_pool.is_some() => ResourceSet::empty(),
+            None => return Err(Error::MissingResources),
+        };
+
+        let cert_timing = Self::parse_child_cert_timing_args(matches)?;
 
         let id_cert = child_request.validate()?;
         let add_child_request =
-            AddChildRequest::new(child, resources, id_cert);
+            AddChildRequest::new(child, resources, id_cert)
+                .with_cert_timing(cert_timing)
+                .with_allocation(allocation);
         let command =
             Command::CertAuth(CaCommand::ChildAdd(my_ca, add_child_request));
         Ok(Options::make(general_args, command))
@@ -1780,8 +2893,15 @@ impl Options {
             }
         };
         let resources = Self::parse_resource_args(matches)?;
+        let cert_timing = Self::parse_child_cert_timing_args(matches)?;
+
+        let allocation = Self::parse_child_allocation_args(matches)?;
 
-        let update = UpdateChildRequest::new(id_cert, resources, None);
+        let mut update = UpdateChildRequest::new(id_cert, resources, None)
+            .with_allocation(allocation);
+        if !cert_timing.is_empty() {
+            update = update.with_cert_timing(cert_timing);
+        }
 
         let command =
             Command::CertAuth(CaCommand::ChildUpdate(my_ca, child, update));
@@ -1986,35 +3106,261 @@ impl Options {
         }
     }
 
-    fn parse_matches_cas_keyroll_init(
+    fn parse_matches_cas_keyroll_init(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::KeyRollInit(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_activate(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::KeyRollActivate(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_emergency(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let reason = matches.value_of("reason").unwrap().to_string();
+
+        let command =
+            Command::CertAuth(CaCommand::KeyRollEmergency(my_ca, reason));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy_show(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::KeyRollPolicyShow(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy_set(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let months = matches.value_of("months").unwrap();
+        let months = u32::from_str(months)
+            .map_err(|e| Error::general(&format!("invalid number: {}", e)))?;
+
+        let staging_hours = match matches.value_of("staging_hours") {
+            Some(hours) => u32::from_str(hours).map_err(|e| {
+                Error::general(&format!("invalid number: {}", e))
+            })?,
+            None => KeyRollPolicy::MIN_STAGING_HOURS,
+        };
+
+        let policy = KeyRollPolicy::new(months, staging_hours);
+        let command =
+            Command::CertAuth(CaCommand::KeyRollPolicySet(my_ca, policy));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy_remove(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::KeyRollPolicyRemove(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_keyroll_policy_show(m)
+        } else if let Some(m) = matches.subcommand_matches("set") {
+            Self::parse_matches_cas_keyroll_policy_set(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_keyroll_policy_remove(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_keyroll(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("init") {
+            Self::parse_matches_cas_keyroll_init(m)
+        } else if let Some(m) = matches.subcommand_matches("activate") {
+            Self::parse_matches_cas_keyroll_activate(m)
+        } else if let Some(m) = matches.subcommand_matches("emergency") {
+            Self::parse_matches_cas_keyroll_emergency(m)
+        } else if let Some(m) = matches.subcommand_matches("policy") {
+            Self::parse_matches_cas_keyroll_policy(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_timing_show(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::IssuanceTimingShow(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_timing_set(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let value = |name: &str| -> Result<Option<u32>, Error> {
+            match matches.value_of(name) {
+                Some(value) => u32::from_str(value).map(Some).map_err(|e| {
+                    Error::general(&format!("invalid number: {}", e))
+                }),
+                None => Ok(None),
+            }
+        };
+
+        let timing = CaIssuanceTiming {
+            timing_publish_next_hours: value("timing_publish_next_hours")?,
+            timing_publish_next_jitter_hours: value(
+                "timing_publish_next_jitter_hours",
+            )?,
+            timing_publish_hours_before_next: value(
+                "timing_publish_hours_before_next",
+            )?,
+            timing_child_certificate_valid_weeks: value(
+                "timing_child_certificate_valid_weeks",
+            )?,
+            timing_child_certificate_reissue_weeks_before: value(
+                "timing_child_certificate_reissue_weeks_before",
+            )?,
+            timing_roa_valid_weeks: value("timing_roa_valid_weeks")?,
+            timing_roa_reissue_weeks_before: value(
+                "timing_roa_reissue_weeks_before",
+            )?,
+            timing_aspa_valid_weeks: value("timing_aspa_valid_weeks")?,
+            timing_aspa_reissue_weeks_before: value(
+                "timing_aspa_reissue_weeks_before",
+            )?,
+            timing_bgpsec_valid_weeks: value("timing_bgpsec_valid_weeks")?,
+            timing_bgpsec_reissue_weeks_before: value(
+                "timing_bgpsec_reissue_weeks_before",
+            )?,
+        };
+
+        if timing.is_empty() {
+            return Err(Error::general(
+                "set at least one timing value, or use 'timing remove'",
+            ));
+        }
+
+        let command =
+            Command::CertAuth(CaCommand::IssuanceTimingSet(my_ca, timing));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_timing_remove(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::IssuanceTimingRemove(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_timing(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_timing_show(m)
+        } else if let Some(m) = matches.subcommand_matches("set") {
+            Self::parse_matches_cas_timing_set(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_timing_remove(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_pools_list(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::ResourcePoolsList(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_pools_set(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::KeyRollInit(my_ca));
+        let name = matches.value_of("pool").unwrap().to_string();
+        let resources = Self::parse_resource_args(matches)?
+            .ok_or(Error::MissingResources)?;
+
+        let definition = ResourcePoolDefinition::new(name, resources);
+        let command = Command::CertAuth(CaCommand::ResourcePoolUpdate(
+            my_ca, definition,
+        ));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_keyroll_activate(
+    fn parse_matches_cas_pools_remove(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::KeyRollActivate(my_ca));
+        let name = matches.value_of("pool").unwrap().to_string();
+        let command =
+            Command::CertAuth(CaCommand::ResourcePoolRemove(my_ca, name));
 
         Ok(Options::make(general_args, command))
     }
 
-    fn parse_matches_cas_keyroll(
+    fn parse_matches_cas_pools(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
-        if let Some(m) = matches.subcommand_matches("init") {
-            Self::parse_matches_cas_keyroll_init(m)
-        } else if let Some(m) = matches.subcommand_matches("activate") {
-            Self::parse_matches_cas_keyroll_activate(m)
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_pools_list(m)
+        } else if let Some(m) = matches.subcommand_matches("set") {
+            Self::parse_matches_cas_pools_set(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_pools_remove(m)
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
@@ -2026,8 +3372,10 @@ impl Options {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
+        let at = Self::parse_history_point_arg(matches)?;
+
         let command =
-            Command::CertAuth(CaCommand::RouteAuthorizationsList(my_ca));
+            Command::CertAuth(CaCommand::RouteAuthorizationsList(my_ca, at));
 
         Ok(Options::make(general_args, command))
     }
@@ -2171,7 +3519,9 @@ impl Options {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::BgpSecList(my_ca));
+        let at = Self::parse_history_point_arg(matches)?;
+
+        let command = Command::CertAuth(CaCommand::BgpSecList(my_ca, at));
 
         Ok(Options::make(general_args, command))
     }
@@ -2350,7 +3700,9 @@ impl Options {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::AspasList(my_ca));
+        let at = Self::parse_history_point_arg(matches)?;
+
+        let command = Command::CertAuth(CaCommand::AspasList(my_ca, at));
 
         Ok(Options::make(general_args, command))
     }
@@ -2421,6 +3773,88 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_cas_repo_mirror_add(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let name = matches.value_of("mirror").unwrap().to_string();
+
+        let path = matches.value_of("response").unwrap();
+        let bytes = Self::read_file_arg(path)?;
+        let response = idexchange::RepositoryResponse::parse(bytes.as_ref())?;
+
+        let command = Command::CertAuth(CaCommand::RepoMirrorAdd(
+            my_ca, name, response,
+        ));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_repo_mirror_remove(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let name = matches.value_of("mirror").unwrap().to_string();
+
+        let command =
+            Command::CertAuth(CaCommand::RepoMirrorRemove(my_ca, name));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_repo_mirror(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("add") {
+            Self::parse_matches_cas_repo_mirror_add(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_repo_mirror_remove(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_repo_migrate_start(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let path = matches.value_of("response").unwrap();
+        let bytes = Self::read_file_arg(path)?;
+        let response = idexchange::RepositoryResponse::parse(bytes.as_ref())?;
+
+        let command =
+            Command::CertAuth(CaCommand::RepoMigrationStart(my_ca, response));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_repo_migrate_abort(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::RepoMigrationAbort(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_repo_migrate(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("start") {
+            Self::parse_matches_cas_repo_migrate_start(m)
+        } else if let Some(m) = matches.subcommand_matches("abort") {
+            Self::parse_matches_cas_repo_migrate_abort(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
     fn parse_matches_cas_repo(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
@@ -2432,6 +3866,10 @@ impl Options {
             Self::parse_matches_cas_repo_status(m)
         } else if let Some(m) = matches.subcommand_matches("configure") {
             Self::parse_matches_cas_repo_configure(m)
+        } else if let Some(m) = matches.subcommand_matches("mirror") {
+            Self::parse_matches_cas_repo_mirror(m)
+        } else if let Some(m) = matches.subcommand_matches("migrate") {
+            Self::parse_matches_cas_repo_migrate(m)
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
@@ -2624,6 +4062,218 @@ impl Options {
         }
     }
 
+    fn parse_matches_cas_rsc_list(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+        let command = Command::CertAuth(CaCommand::RscList(ca));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_rsc_show(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+        let name = matches.value_of("name").unwrap().to_string();
+        let out_file = Self::parse_out_file(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::RscShow(ca, name, out_file));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_rsc_sign_request(
+        matches: &ArgMatches,
+    ) -> Result<RscSignRequest, Error> {
+        let days = matches.value_of("days").unwrap();
+        let days = i64::from_str(days).map_err(|e| {
+            Error::GeneralArgumentError(format!(
+                "Invalid number of days: {}",
+                e
+            ))
+        })?;
+        let validity = SignSupport::sign_validity_days(days);
+
+        let resources = Self::parse_resource_args(matches)?
+            .ok_or_else(|| Error::general("You must specify at least one of --ipv4, --ipv6 or --asn"))?;
+
+        let include_names = !matches.is_present("no_file_names");
+
+        let mut checklist = vec![];
+        for path in matches.values_of("file").unwrap() {
+            let path = PathBuf::from_str(path).map_err(|_| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid filename: {}",
+                    path
+                ))
+            })?;
+
+            let content = file::read(&path).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Can't read file '{}', error: {}",
+                    path.to_string_lossy(),
+                    e,
+                ))
+            })?;
+
+            let file_name = if include_names {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            } else {
+                None
+            };
+
+            checklist
+                .push(RscChecklistItem::for_content(file_name, &content));
+        }
+
+        Ok(RscSignRequest::new(resources, validity, checklist))
+    }
+
+    fn parse_matches_cas_rsc_sign(
+        matches: &ArgMatches,
+        cosign: bool,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+        let name = matches.value_of("name").unwrap().to_string();
+        let request = Self::parse_rsc_sign_request(matches)?;
+
+        let command = if cosign {
+            Command::CertAuth(CaCommand::RscCoSign(ca, name, request))
+        } else {
+            Command::CertAuth(CaCommand::RscSign(ca, name, request))
+        };
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_rsc(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_rsc_list(m)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_rsc_show(m)
+        } else if let Some(m) = matches.subcommand_matches("sign") {
+            Self::parse_matches_cas_rsc_sign(m, false)
+        } else if let Some(m) = matches.subcommand_matches("cosign") {
+            Self::parse_matches_cas_rsc_sign(m, true)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_out_file(
+        matches: &ArgMatches,
+    ) -> Result<Option<PathBuf>, Error> {
+        match matches.value_of("out") {
+            None => Ok(None),
+            Some(out_file) => {
+                Ok(Some(PathBuf::from_str(out_file).map_err(|_| {
+                    Error::GeneralArgumentError(format!(
+                        "Invalid filename: {}",
+                        out_file
+                    ))
+                })?))
+            }
+        }
+    }
+
+    fn parse_matches_cas_geofeed_list(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+        let command = Command::CertAuth(CaCommand::GeofeedList(ca));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_geofeed_show(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+
+        let number = matches.value_of("number").unwrap();
+        let number = u64::from_str(number).map_err(|e| {
+            Error::GeneralArgumentError(format!(
+                "Invalid geofeed number: {}",
+                e
+            ))
+        })?;
+
+        let out_file = Self::parse_out_file(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::GeofeedShow(ca, number, out_file));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_geofeed_sign(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+
+        let days = matches.value_of("days").unwrap();
+        let days = i64::from_str(days).map_err(|e| {
+            Error::GeneralArgumentError(format!(
+                "Invalid number of days: {}",
+                e
+            ))
+        })?;
+        let validity = SignSupport::sign_validity_days(days);
+
+        let path = matches.value_of("geofeed").unwrap();
+        let path = PathBuf::from_str(path).map_err(|_| {
+            Error::GeneralArgumentError(format!("Invalid filename: {}", path))
+        })?;
+        let bytes = file::read(&path).map_err(|e| {
+            Error::GeneralArgumentError(format!(
+                "Can't read file '{}', error: {}",
+                path.to_string_lossy(),
+                e,
+            ))
+        })?;
+        let geofeed = String::from_utf8(bytes.to_vec()).map_err(|_| {
+            Error::GeneralArgumentError(format!(
+                "Geofeed file '{}' is not valid UTF-8",
+                path.to_string_lossy()
+            ))
+        })?;
+
+        let mut cosigners = vec![];
+        if let Some(values) = matches.values_of("cosigner") {
+            for cosigner in values {
+                cosigners.push(
+                    CaHandle::from_str(cosigner)
+                        .map_err(|_| Error::InvalidHandle)?,
+                );
+            }
+        }
+
+        let out_file = Self::parse_out_file(matches)?;
+
+        let request = GeofeedSignRequest::new(geofeed, validity, cosigners);
+        let command =
+            Command::CertAuth(CaCommand::GeofeedSign(ca, request, out_file));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_geofeed(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_geofeed_list(m)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_cas_geofeed_show(m)
+        } else if let Some(m) = matches.subcommand_matches("sign") {
+            Self::parse_matches_cas_geofeed_sign(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
     fn parse_matches_bulk(matches: &ArgMatches) -> Result<Options, Error> {
         if let Some(m) = matches.subcommand_matches("publish") {
             let general_args = GeneralArgs::from_matches(m)?;
@@ -2637,11 +4287,55 @@ impl Options {
             let general_args = GeneralArgs::from_matches(m)?;
             let command = Command::Bulk(BulkCaCommand::Sync);
             Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("compact") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let retention = Self::parse_history_retention_args(m)?;
+            let command =
+                Command::Bulk(BulkCaCommand::CompactHistory(retention));
+            Ok(Options::make(general_args, command))
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_admin(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("backup") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let output = PathBuf::from(m.value_of("output").unwrap());
+            let command = Command::Admin(AdminCommand::Backup(output));
+            Ok(Options::make(general_args, command))
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
     }
 
+    fn parse_matches_tasks(matches: &ArgMatches) -> Result<Options, Error> {
+        let (m, command) = if let Some(m) = matches.subcommand_matches("list")
+        {
+            (m, TaskCommand::List)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            (m, TaskCommand::Show(Self::parse_task_name(m)))
+        } else if let Some(m) = matches.subcommand_matches("reschedule") {
+            (m, TaskCommand::Reschedule(Self::parse_task_name(m)))
+        } else if let Some(m) = matches.subcommand_matches("cancel") {
+            (m, TaskCommand::Cancel(Self::parse_task_name(m)))
+        } else if let Some(m) = matches.subcommand_matches("dead") {
+            (m, TaskCommand::DeadLetters)
+        } else if let Some(m) = matches.subcommand_matches("requeue") {
+            (m, TaskCommand::Requeue(Self::parse_task_name(m)))
+        } else if let Some(m) = matches.subcommand_matches("discard") {
+            (m, TaskCommand::Discard(Self::parse_task_name(m)))
+        } else {
+            return Err(Error::UnrecognizedSubCommand);
+        };
+        let general_args = GeneralArgs::from_matches(m)?;
+        Ok(Options::make(general_args, Command::Tasks(command)))
+    }
+
+    fn parse_task_name(matches: &ArgMatches) -> String {
+        matches.value_of("name").unwrap().to_string()
+    }
+
     fn parse_matches_health(matches: &ArgMatches) -> Result<Options, Error> {
         let general_args = GeneralArgs::from_matches(matches)?;
         let command = Command::Health;
@@ -2654,6 +4348,12 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_ha(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let command = Command::Ha;
+        Ok(Options::make(general_args, command))
+    }
+
     fn parse_publisher_arg(
         matches: &ArgMatches,
     ) -> Result<PublisherHandle, Error> {
@@ -2872,6 +4572,12 @@ impl Options {
             Self::parse_matches_cas_add(m)
         } else if let Some(m) = matches.subcommand_matches("delete") {
             Self::parse_matches_cas_delete(m)
+        } else if let Some(m) = matches.subcommand_matches("rename") {
+            Self::parse_matches_cas_rename(m)
+        } else if let Some(m) = matches.subcommand_matches("clone") {
+            Self::parse_matches_cas_clone(m)
+        } else if let Some(m) = matches.subcommand_matches("template") {
+            Self::parse_matches_cas_template(m)
         } else if let Some(m) = matches.subcommand_matches("show") {
             Self::parse_matches_cas_show(m)
         } else if let Some(m) = matches.subcommand_matches("history") {
@@ -2882,6 +4588,10 @@ impl Options {
             Self::parse_matches_cas_parents(m)
         } else if let Some(m) = matches.subcommand_matches("keyroll") {
             Self::parse_matches_cas_keyroll(m)
+        } else if let Some(m) = matches.subcommand_matches("timing") {
+            Self::parse_matches_cas_timing(m)
+        } else if let Some(m) = matches.subcommand_matches("pools") {
+            Self::parse_matches_cas_pools(m)
         } else if let Some(m) = matches.subcommand_matches("roas") {
             Self::parse_matches_cas_routes(m)
         } else if let Some(m) = matches.subcommand_matches("bgpsec") {
@@ -2892,14 +4602,24 @@ impl Options {
             Self::parse_matches_cas_repo(m)
         } else if let Some(m) = matches.subcommand_matches("issues") {
             Self::parse_matches_cas_issues(m)
+        } else if let Some(m) = matches.subcommand_matches("rsc") {
+            Self::parse_matches_cas_rsc(m)
+        } else if let Some(m) = matches.subcommand_matches("geofeed") {
+            Self::parse_matches_cas_geofeed(m)
         } else if let Some(m) = matches.subcommand_matches("rta") {
             Self::parse_matches_cas_rta(m)
         } else if let Some(m) = matches.subcommand_matches("bulk") {
             Self::parse_matches_bulk(m)
+        } else if let Some(m) = matches.subcommand_matches("admin") {
+            Self::parse_matches_admin(m)
+        } else if let Some(m) = matches.subcommand_matches("tasks") {
+            Self::parse_matches_tasks(m)
         } else if let Some(m) = matches.subcommand_matches("health") {
             Self::parse_matches_health(m)
         } else if let Some(m) = matches.subcommand_matches("info") {
             Self::parse_matches_info(m)
+        } else if let Some(m) = matches.subcommand_matches("ha") {
+            Self::parse_matches_ha(m)
         } else if let Some(m) = matches.subcommand_matches("pubserver") {
             Self::parse_matches_pubserver(m)
         } else {
@@ -2919,7 +4639,10 @@ pub enum Command {
     NotSet,
     Health,
     Info,
+    Ha,
     Bulk(BulkCaCommand),
+    Admin(AdminCommand),
+    Tasks(TaskCommand),
     CertAuth(CaCommand),
     PubServer(PubServerCommand),
     Init(KrillInitDetails),
@@ -2934,12 +4657,24 @@ pub enum CaCommand {
     UpdateId(CaHandle), // Update CA id
     Delete(CaHandle),   /* Delete the CA -> let it withdraw and request
                          * revocation as well */
+    Rename(CaHandle, CertAuthRename), // Rename the CA
+    Clone(CaHandle, CertAuthClone),   // Create a CA like this CA
+    TemplateShow(CaHandle),
+    InitFromTemplate(CertAuthFromTemplate),
 
     // Publishing
     RepoPublisherRequest(CaHandle), // Get the RFC 8183 Publisher Request
     RepoDetails(CaHandle),
     RepoUpdate(CaHandle, idexchange::RepositoryResponse),
     RepoStatus(CaHandle),
+    RepoMirrorAdd(
+        CaHandle,
+        RepositoryMirrorName,
+        idexchange::RepositoryResponse,
+    ),
+    RepoMirrorRemove(CaHandle, RepositoryMirrorName),
+    RepoMigrationStart(CaHandle, idexchange::RepositoryResponse),
+    RepoMigrationAbort(CaHandle),
 
     // Parents (to this CA)
     ChildRequest(CaHandle), // Get the RFC 8183 Child Request
@@ -2963,9 +4698,23 @@ pub enum CaCommand {
     // Key Management
     KeyRollInit(CaHandle),
     KeyRollActivate(CaHandle),
+    KeyRollEmergency(CaHandle, String),
+    KeyRollPolicyShow(CaHandle),
+    KeyRollPolicySet(CaHandle, KeyRollPolicy),
+    KeyRollPolicyRemove(CaHandle),
+
+    // Issuance timing
+    IssuanceTimingShow(CaHandle),
+    IssuanceTimingSet(CaHandle, CaIssuanceTiming),
+    IssuanceTimingRemove(CaHandle),
+
+    // Resource pools
+    ResourcePoolsList(CaHandle),
+    ResourcePoolUpdate(CaHandle, ResourcePoolDefinition),
+    ResourcePoolRemove(CaHandle, ResourcePoolName),
 
     // Authorizations
-    RouteAuthorizationsList(CaHandle),
+    RouteAuthorizationsList(CaHandle, Option<HistoryPoint>),
     RouteAuthorizationsUpdate(CaHandle, RoaConfigurationUpdates),
     RouteAuthorizationsTryUpdate(CaHandle, RoaConfigurationUpdates),
     RouteAuthorizationsDryRunUpdate(CaHandle, RoaConfigurationUpdates),
@@ -2973,20 +4722,21 @@ pub enum CaCommand {
     BgpAnalysisSuggest(CaHandle, Option<ResourceSet>),
 
     // ASPAs
-    AspasList(CaHandle),
+    AspasList(CaHandle, Option<HistoryPoint>),
     AspasAddOrReplace(CaHandle, AspaDefinition),
     AspasUpdate(CaHandle, CustomerAsn, AspaProvidersUpdate),
     AspasRemove(CaHandle, CustomerAsn),
 
     // BGPSec
-    BgpSecList(CaHandle),
+    BgpSecList(CaHandle, Option<HistoryPoint>),
     BgpSecAdd(CaHandle, BgpSecDefinition),
     BgpSecRemove(CaHandle, BgpSecAsnKey),
 
     // Show details for this CA
-    Show(CaHandle),
+    Show(CaHandle, Option<HistoryPoint>),
     ShowHistoryCommands(CaHandle, HistoryOptions),
     ShowHistoryDetails(CaHandle, String),
+    CompactHistory(CaHandle, HistoryRetention),
     Issues(Option<CaHandle>),
 
     // RTA
@@ -2996,6 +4746,17 @@ pub enum CaCommand {
     RtaMultiPrep(CaHandle, RtaName, RtaPrepareRequest),
     RtaMultiCoSign(CaHandle, RtaName, ResourceTaggedAttestation),
 
+    // RSC
+    RscList(CaHandle),
+    RscShow(CaHandle, RscName, Option<PathBuf>),
+    RscSign(CaHandle, RscName, RscSignRequest),
+    RscCoSign(CaHandle, RscName, RscSignRequest),
+
+    // Signed geofeeds
+    GeofeedList(CaHandle),
+    GeofeedShow(CaHandle, u64, Option<PathBuf>),
+    GeofeedSign(CaHandle, GeofeedSignRequest, Option<PathBuf>),
+
     // List all CAs
     List,
 }
@@ -3044,15 +4805,33 @@ impl HistoryOptions {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum BulkCaCommand {
     Refresh,
     Publish,      // re-publish mft/crl before they would expire
     ForcePublish, // force republish all mft/crls
     Sync,
     Suspend,
+    CompactHistory(HistoryRetention),
     Import(api::import::Structure),
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminCommand {
+    Backup(PathBuf), // Save a backup to this file
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TaskCommand {
+    List,
+    Show(String),       // Show the task with this name
+    Reschedule(String), // Reschedule the task with this name to now
+    Cancel(String),     // Remove the task with this name from the queue
+    DeadLetters,
+    Requeue(String), // Move the dead letter task with this name to the queue
+    Discard(String), // Remove the dead letter task with this name
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct KrillInitDetails {
     data_dir: Option<String>,
@@ -9,20 +9,27 @@ use crate::{
     commons::{
         api::{
             import::ExportChild, AllCertAuthIssues, AspaDefinitionList,
-            BgpSecCsrInfoList, CaCommandDetails, CaRepoDetails, CertAuthInfo,
-            CertAuthIssues, CertAuthList, ChildCaInfo,
+            BackupManifest, BgpSecCsrInfoList, CaCommandDetails,
+            CaIssuanceTiming, CaRepoDetails, CaTemplate, CaTemplateReport,
+            CertAuthInfo, CertAuthIssues, CertAuthList, ChildCaInfo,
             ChildrenConnectionStats, CommandHistory, ConfiguredRoas,
-            IdCertInfo, ParentCaContact, ParentStatuses, PublisherDetails,
-            PublisherList, RepoStatus, RepositoryContact, RtaList,
-            RtaPrepResponse, ServerInfo,
+            DeadLetterList, EmergencyRekeyReport, GeofeedList, HaStatus,
+            HistoryCompactionReport, HistoryCompactionReports, IdCertInfo,
+            KeyRollStatus, ParentCaContact, ParentStatuses, PublisherDetails,
+            PublisherList, RepoStatus, RepositoryContact, ResourcePoolList,
+            RscList, RtaList, RtaPrepResponse, ServerInfo, TaskDetails,
+            TaskList,
         },
         bgp::{BgpAnalysisAdvice, BgpAnalysisReport, BgpAnalysisSuggestion},
     },
-    daemon::ca::ResourceTaggedAttestation,
+    daemon::ca::{
+        ResourceTaggedAttestation, SignedGeofeed, SignedGeofeeds, SignedRsc,
+    },
     pubd::RepoStats,
     ta::{
-        TrustAnchorProxySignerExchanges, TrustAnchorSignedRequest,
-        TrustAnchorSignedResponse, TrustAnchorSignerInfo,
+        TaSuccessor, TrustAnchorProxySignerExchanges,
+        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
+        TrustAnchorSignerInfo,
     },
 };
 
@@ -34,10 +41,23 @@ use crate::{
 pub enum ApiResponse {
     Health,
     Info(ServerInfo),
+    Ha(HaStatus),
+    Backup(BackupManifest),
+    Tasks(TaskList),
+    TaskDetails(TaskDetails),
+    DeadLetters(DeadLetterList),
 
     CertAuthInfo(CertAuthInfo),
     CertAuthHistory(CommandHistory),
+    KeyRollStatus(KeyRollStatus),
+    IssuanceTiming(CaIssuanceTiming),
+    ResourcePools(ResourcePoolList),
+    EmergencyRekey(EmergencyRekeyReport),
+    CaTemplate(CaTemplate),
+    CaTemplateReport(CaTemplateReport),
     CertAuthAction(CaCommandDetails),
+    HistoryCompaction(HistoryCompactionReport),
+    HistoryCompactions(HistoryCompactionReports),
     CertAuths(CertAuthList),
 
     // ROA related
@@ -78,6 +98,13 @@ pub enum ApiResponse {
     RtaMultiPrep(RtaPrepResponse),
     Rta(ResourceTaggedAttestation),
 
+    RscList(RscList),
+    Rsc(SignedRsc),
+
+    GeofeedList(GeofeedList),
+    Geofeed(SignedGeofeed),
+    SignedGeofeeds(SignedGeofeeds),
+
     Empty, // Typically a successful post just gets an empty 200 response
     GenericBody(String), /* For when the server echos Json to a
             * successful post */
@@ -94,6 +121,17 @@ impl ApiResponse {
             match self {
                 ApiResponse::Health => Ok(None),
                 ApiResponse::Info(info) => Ok(Some(info.report(fmt)?)),
+                ApiResponse::Ha(status) => Ok(Some(status.report(fmt)?)),
+                ApiResponse::Backup(manifest) => {
+                    Ok(Some(manifest.report(fmt)?))
+                }
+                ApiResponse::Tasks(tasks) => Ok(Some(tasks.report(fmt)?)),
+                ApiResponse::TaskDetails(details) => {
+                    Ok(Some(details.report(fmt)?))
+                }
+                ApiResponse::DeadLetters(tasks) => {
+                    Ok(Some(tasks.report(fmt)?))
+                }
                 ApiResponse::CertAuths(list) => Ok(Some(list.report(fmt)?)),
                 ApiResponse::CertAuthInfo(info) => {
                     Ok(Some(info.report(fmt)?))
@@ -101,9 +139,33 @@ impl ApiResponse {
                 ApiResponse::CertAuthHistory(history) => {
                     Ok(Some(history.report(fmt)?))
                 }
+                ApiResponse::KeyRollStatus(status) => {
+                    Ok(Some(status.report(fmt)?))
+                }
+                ApiResponse::IssuanceTiming(timing) => {
+                    Ok(Some(timing.report(fmt)?))
+                }
+                ApiResponse::ResourcePools(pools) => {
+                    Ok(Some(pools.report(fmt)?))
+                }
+                ApiResponse::EmergencyRekey(report) => {
+                    Ok(Some(report.report(fmt)?))
+                }
+                ApiResponse::CaTemplate(template) => {
+                    Ok(Some(template.report(fmt)?))
+                }
+                ApiResponse::CaTemplateReport(report) => {
+                    Ok(Some(report.report(fmt)?))
+                }
                 ApiResponse::CertAuthAction(details) => {
                     Ok(Some(details.report(fmt)?))
                 }
+                ApiResponse::HistoryCompaction(report) => {
+                    Ok(Some(report.report(fmt)?))
+                }
+                ApiResponse::HistoryCompactions(reports) => {
+                    Ok(Some(reports.report(fmt)?))
+                }
                 ApiResponse::CertAuthIssues(issues) => {
                     Ok(Some(issues.report(fmt)?))
                 }
@@ -169,6 +231,15 @@ impl ApiResponse {
                 ApiResponse::Rta(rta) => Ok(Some(rta.report(fmt)?)),
                 ApiResponse::RtaList(list) => Ok(Some(list.report(fmt)?)),
                 ApiResponse::RtaMultiPrep(res) => Ok(Some(res.report(fmt)?)),
+                ApiResponse::Rsc(rsc) => Ok(Some(rsc.report(fmt)?)),
+                ApiResponse::RscList(list) => Ok(Some(list.report(fmt)?)),
+                ApiResponse::GeofeedList(list) => Ok(Some(list.report(fmt)?)),
+                ApiResponse::Geofeed(geofeed) => {
+                    Ok(Some(geofeed.report(fmt)?))
+                }
+                ApiResponse::SignedGeofeeds(signed) => {
+                    Ok(Some(signed.report(fmt)?))
+                }
                 ApiResponse::GenericBody(body) => Ok(Some(body.clone())),
                 ApiResponse::Empty => Ok(None),
             }
@@ -245,6 +316,12 @@ pub trait Report: Serialize + ToString {
 
 impl Report for CertAuthList {}
 impl Report for CertAuthInfo {}
+impl Report for KeyRollStatus {}
+impl Report for CaIssuanceTiming {}
+impl Report for ResourcePoolList {}
+impl Report for EmergencyRekeyReport {}
+impl Report for CaTemplate {}
+impl Report for CaTemplateReport {}
 impl Report for IdCertInfo {}
 impl Report for RepositoryContact {}
 
@@ -256,6 +333,8 @@ impl Report for ParentStatuses {}
 
 impl Report for CommandHistory {}
 impl Report for CaCommandDetails {}
+impl Report for HistoryCompactionReport {}
+impl Report for HistoryCompactionReports {}
 
 impl Report for PublisherList {}
 
@@ -306,12 +385,25 @@ impl Report for CertAuthIssues {}
 impl Report for AllCertAuthIssues {}
 
 impl Report for ServerInfo {}
+impl Report for HaStatus {}
+impl Report for BackupManifest {}
+impl Report for TaskList {}
+impl Report for TaskDetails {}
+impl Report for DeadLetterList {}
 
 impl Report for ResourceTaggedAttestation {}
 impl Report for RtaList {}
 impl Report for RtaPrepResponse {}
 
+impl Report for SignedRsc {}
+impl Report for RscList {}
+
+impl Report for GeofeedList {}
+impl Report for SignedGeofeed {}
+impl Report for SignedGeofeeds {}
+
 impl Report for TrustAnchorSignerInfo {}
 impl Report for TrustAnchorSignedRequest {}
 impl Report for TrustAnchorSignedResponse {}
 impl Report for TrustAnchorProxySignerExchanges {}
+impl Report for TaSuccessor {}
@@ -18,7 +18,7 @@ use crate::{
         actor::Actor,
         api::{
             AddChildRequest, ApiRepositoryContact, CertAuthInfo, IdCertInfo,
-            RepositoryContact, Token,
+            RepositoryContact, Token, UpdateChildRequest,
         },
         crypto::KrillSigner,
         error::Error as KrillError,
@@ -32,9 +32,10 @@ use crate::{
         KRILL_VERSION,
     },
     ta::{
-        self, Config, TrustAnchorHandle, TrustAnchorProxySignerExchanges,
-        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
-        TrustAnchorSigner, TrustAnchorSignerCommand, TrustAnchorSignerInfo,
+        self, Config, TaKeyRollInitRequest, TaSuccessor, TrustAnchorHandle,
+        TrustAnchorProxySignerExchanges, TrustAnchorSignedRequest,
+        TrustAnchorSignedResponse, TrustAnchorSigner,
+        TrustAnchorSignerCommand, TrustAnchorSignerInfo,
         TrustAnchorSignerInitCommand, TrustAnchorSignerInitCommandDetails,
     },
 };
@@ -156,7 +157,12 @@ pub enum ProxyCommandDetails {
     SignerMakeRequest,
     SignerShowRequest,
     SignerProcessResponse(TrustAnchorSignedResponse),
+    KeyRollInit(TaKeyRollInitRequest),
+    KeyRollActivate,
+    KeyRollShow,
     ChildAdd(AddChildRequest),
+    ChildUpdate(ChildHandle, ResourceSet),
+    ChildRemove(ChildHandle),
     ChildResponse(ChildHandle),
 }
 
@@ -218,6 +224,7 @@ impl TrustAnchorClientCommand {
         sub = Self::make_proxy_id_sc(sub);
         sub = Self::make_proxy_repo_sc(sub);
         sub = Self::make_proxy_signer_sc(sub);
+        sub = Self::make_proxy_key_roll_sc(sub);
         sub = Self::make_proxy_children_sc(sub);
 
         app.subcommand(sub)
@@ -341,10 +348,47 @@ impl TrustAnchorClientCommand {
         app.subcommand(sub)
     }
 
+    fn make_proxy_key_roll_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("key-roll")
+            .about("Manage a Trust Anchor key roll (RFC 9691)");
+        sub = Self::make_proxy_key_roll_init_sc(sub);
+        sub = Self::make_proxy_key_roll_activate_sc(sub);
+        sub = Self::make_proxy_key_roll_show_sc(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_proxy_key_roll_init_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("init").about(
+            "Make a request for the signer to create a successor key.",
+        );
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_tal_args(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_proxy_key_roll_activate_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("activate").about(
+            "Make a request for the signer to activate the successor key.",
+        );
+        sub = GeneralArgs::add_args(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_proxy_key_roll_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the successor key, if a key roll is in progress.");
+        sub = GeneralArgs::add_args(sub);
+        app.subcommand(sub)
+    }
+
     fn make_proxy_children_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("children")
             .about("Manage children under the TA proxy");
         sub = Self::make_proxy_children_add_sc(sub);
+        sub = Self::make_proxy_children_update_sc(sub);
+        sub = Self::make_proxy_children_remove_sc(sub);
         sub = Self::make_proxy_children_response_sc(sub);
         app.subcommand(sub)
     }
@@ -353,37 +397,35 @@ impl TrustAnchorClientCommand {
         let mut sub = SubCommand::with_name("add").about("Add a child. Recommended: add 1 child with all resources and use that as a parent to other CAs. This way the resources for those children can be updated without the need to have the offline signer sign a new certificate to them.");
 
         sub = GeneralArgs::add_args(sub);
-        sub = sub
-            .arg(
-                Arg::with_name("info")
-                    .value_name("info")
-                    .long("info")
-                    .short("i")
-                    .help("The Child info JSON (as 'krillc show --ca <ca_name>')")
-                    .required(true),
-            )
-            .arg(
-                Arg::with_name("asn")
-                    .value_name("asn resources")
-                    .long("asn")
-                    .help("The ASN resources for the child. Default: all")
-                    .required(false),
-            )
-            .arg(
-                Arg::with_name("ipv4")
-                    .value_name("IPv4 resources")
-                    .long("ipv4")
-                    .help("The IPv4 resources for the child. Default: all")
-                    .required(false),
-            )
-            .arg(
-                Arg::with_name("ipv6")
-                    .value_name("IPv6 resources")
-                    .long("ipv6")
-                    .help("The IPv6 resources for the child. Default: all")
-                    .required(false),
-            );
+        sub = sub.arg(
+            Arg::with_name("info")
+                .value_name("info")
+                .long("info")
+                .short("i")
+                .help("The Child info JSON (as 'krillc show --ca <ca_name>')")
+                .required(true),
+        );
+        sub = Self::add_resources_args(sub);
+
+        app.subcommand(sub)
+    }
 
+    fn make_proxy_children_update_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("update").about("Update the resources of a child. The signer will re-issue its certificate in the next signing session.");
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_child_arg(sub);
+        sub = Self::add_resources_args(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_proxy_children_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about("Remove a child. The signer will revoke its certificate in the next signing session.");
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_child_arg(sub);
         app.subcommand(sub)
     }
 
@@ -432,22 +474,8 @@ impl TrustAnchorClientCommand {
                     .value_name("path")
                     .help("Path to Proxy ID JSON")
                     .required(true),
-            )
-            .arg(
-                Arg::with_name("tal_rsync")
-                    .long("tal_rsync")
-                    .value_name("Rsync URI")
-                    .help("Used for TA certificate on TAL and AIA")
-                    .required(true),
-            )
-            .arg(
-                Arg::with_name("tal_https")
-                    .long("tal_https")
-                    .value_name("HTTPS URI")
-                    .help("Used for TAL. Multiple allowed.")
-                    .multiple(true)
-                    .required(true),
-            )
+            );
+        sub = Self::add_tal_args(sub)
             .arg(
                 Arg::with_name("private_key_pem")
                     .long("private_key_pem")
@@ -539,6 +567,24 @@ impl TrustAnchorClientCommand {
         )
     }
 
+    fn add_tal_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("tal_rsync")
+                .long("tal_rsync")
+                .value_name("Rsync URI")
+                .help("Used for TA certificate on TAL and AIA")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("tal_https")
+                .long("tal_https")
+                .value_name("HTTPS URI")
+                .help("Used for TAL. Multiple allowed.")
+                .multiple(true)
+                .required(true),
+        )
+    }
+
     fn add_child_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         app.arg(
             Arg::with_name("child")
@@ -548,6 +594,30 @@ impl TrustAnchorClientCommand {
                 .required(true),
         )
     }
+
+    fn add_resources_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("asn")
+                .value_name("asn resources")
+                .long("asn")
+                .help("The ASN resources for the child. Default: all")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("ipv4")
+                .value_name("IPv4 resources")
+                .long("ipv4")
+                .help("The IPv4 resources for the child. Default: all")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("ipv6")
+                .value_name("IPv6 resources")
+                .long("ipv6")
+                .help("The IPv6 resources for the child. Default: all")
+                .required(false),
+        )
+    }
 }
 
 /// # Parse command line matches
@@ -574,6 +644,8 @@ impl TrustAnchorClientCommand {
             Self::parse_matches_proxy_repo(m)
         } else if let Some(m) = matches.subcommand_matches("signer") {
             Self::parse_matches_proxy_signer(m)
+        } else if let Some(m) = matches.subcommand_matches("key-roll") {
+            Self::parse_matches_proxy_key_roll(m)
         } else if let Some(m) = matches.subcommand_matches("children") {
             Self::parse_matches_proxy_children(m)
         } else {
@@ -742,11 +814,44 @@ impl TrustAnchorClientCommand {
         }))
     }
 
+    fn parse_matches_proxy_key_roll(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let (m, details) = if let Some(m) = matches.subcommand_matches("init")
+        {
+            let tal_https = Self::parse_tal_https_arg(m)?;
+            let tal_rsync = Self::parse_tal_rsync_arg(m)?;
+            let init = TaKeyRollInitRequest {
+                tal_https,
+                tal_rsync,
+            };
+            (m, ProxyCommandDetails::KeyRollInit(init))
+        } else if let Some(m) = matches.subcommand_matches("activate") {
+            (m, ProxyCommandDetails::KeyRollActivate)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            (m, ProxyCommandDetails::KeyRollShow)
+        } else {
+            return Err(TaClientError::UnrecognizedMatch);
+        };
+
+        let general = GeneralArgs::from_matches(m)
+            .map_err(|e| TaClientError::Other(e.to_string()))?;
+
+        Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
+            general,
+            details,
+        }))
+    }
+
     fn parse_matches_proxy_children(
         matches: &ArgMatches,
     ) -> Result<Self, TaClientError> {
         if let Some(m) = matches.subcommand_matches("add") {
             Self::parse_matches_proxy_children_add(m)
+        } else if let Some(m) = matches.subcommand_matches("update") {
+            Self::parse_matches_proxy_children_update(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_proxy_children_remove(m)
         } else if let Some(m) = matches.subcommand_matches("response") {
             Self::parse_matches_proxy_children_response(m)
         } else {
@@ -762,14 +867,7 @@ impl TrustAnchorClientCommand {
 
         let info: CertAuthInfo =
             Self::read_json(matches.value_of("info").unwrap())?;
-        let resources: ResourceSet = {
-            let asn = matches.value_of("asn").unwrap_or("AS0-AS4294967295");
-            let ipv4 = matches.value_of("ipv4").unwrap_or("0.0.0.0/0");
-            let ipv6 = matches.value_of("ipv6").unwrap_or("::/0");
-            ResourceSet::from_strs(asn, ipv4, ipv6).map_err(|e| {
-                TaClientError::Other(format!("Cannot parse resources: {}", e))
-            })?
-        };
+        let resources = Self::parse_resources_args(matches)?;
 
         Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
             general,
@@ -781,6 +879,44 @@ impl TrustAnchorClientCommand {
         }))
     }
 
+    fn parse_matches_proxy_children_update(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let general = GeneralArgs::from_matches(matches)
+            .map_err(|e| TaClientError::Other(e.to_string()))?;
+        let child = Self::parse_child_arg(matches)?;
+        let resources = Self::parse_resources_args(matches)?;
+
+        Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
+            general,
+            details: ProxyCommandDetails::ChildUpdate(child, resources),
+        }))
+    }
+
+    fn parse_matches_proxy_children_remove(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let general = GeneralArgs::from_matches(matches)
+            .map_err(|e| TaClientError::Other(e.to_string()))?;
+        let child = Self::parse_child_arg(matches)?;
+
+        Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
+            general,
+            details: ProxyCommandDetails::ChildRemove(child),
+        }))
+    }
+
+    fn parse_resources_args(
+        matches: &ArgMatches,
+    ) -> Result<ResourceSet, TaClientError> {
+        let asn = matches.value_of("asn").unwrap_or("AS0-AS4294967295");
+        let ipv4 = matches.value_of("ipv4").unwrap_or("0.0.0.0/0");
+        let ipv6 = matches.value_of("ipv6").unwrap_or("::/0");
+        ResourceSet::from_strs(asn, ipv4, ipv6).map_err(|e| {
+            TaClientError::Other(format!("Cannot parse resources: {}", e))
+        })
+    }
+
     fn parse_matches_proxy_children_response(
         matches: &ArgMatches,
     ) -> Result<Self, TaClientError> {
@@ -794,6 +930,31 @@ impl TrustAnchorClientCommand {
         }))
     }
 
+    fn parse_tal_https_arg(
+        matches: &ArgMatches,
+    ) -> Result<Vec<uri::Https>, TaClientError> {
+        let uri_strs = matches.values_of("tal_https").unwrap();
+        let mut uris = vec![];
+        for uri_str in uri_strs {
+            uris.push(uri::Https::from_str(uri_str).map_err(|_| {
+                TaClientError::Other(format!(
+                    "Invalid HTTPS URI: {}",
+                    uri_str
+                ))
+            })?);
+        }
+        Ok(uris)
+    }
+
+    fn parse_tal_rsync_arg(
+        matches: &ArgMatches,
+    ) -> Result<uri::Rsync, TaClientError> {
+        let rsync_str = matches.value_of("tal_rsync").unwrap();
+        uri::Rsync::from_str(rsync_str).map_err(|_| {
+            TaClientError::Other(format!("Invalid rsync uri: {}", rsync_str))
+        })
+    }
+
     fn parse_child_arg(
         matches: &ArgMatches,
     ) -> Result<ChildHandle, TaClientError> {
@@ -862,29 +1023,8 @@ impl TrustAnchorClientCommand {
             repo_contact.into()
         };
 
-        let tal_https = {
-            let uri_strs = matches.values_of("tal_https").unwrap();
-            let mut uris = vec![];
-            for uri_str in uri_strs {
-                uris.push(uri::Https::from_str(uri_str).map_err(|_| {
-                    TaClientError::Other(format!(
-                        "Invalid HTTPS URI: {}",
-                        uri_str
-                    ))
-                })?);
-            }
-            uris
-        };
-
-        let tal_rsync = {
-            let rsync_str = matches.value_of("tal_rsync").unwrap();
-            uri::Rsync::from_str(rsync_str).map_err(|_| {
-                TaClientError::Other(format!(
-                    "Invalid rsync uri: {}",
-                    rsync_str
-                ))
-            })?
-        };
+        let tal_https = Self::parse_tal_https_arg(matches)?;
+        let tal_rsync = Self::parse_tal_rsync_arg(matches)?;
 
         let ta_mft_nr_override = if let Some(number) =
             matches.value_of("initial_manifest_number")
@@ -1091,6 +1231,35 @@ impl TrustAnchorClient {
                             )
                             .await
                     }
+                    ProxyCommandDetails::KeyRollInit(init) => {
+                        let request = client
+                            .post_json_with_response(
+                                "api/v1/ta/proxy/key-roll/init",
+                                init,
+                            )
+                            .await?;
+                        Ok(TrustAnchorClientApiResponse::SignerRequest(
+                            request,
+                        ))
+                    }
+                    ProxyCommandDetails::KeyRollActivate => {
+                        let request = client
+                            .post_empty_with_response(
+                                "api/v1/ta/proxy/key-roll/activate",
+                            )
+                            .await?;
+                        Ok(TrustAnchorClientApiResponse::SignerRequest(
+                            request,
+                        ))
+                    }
+                    ProxyCommandDetails::KeyRollShow => {
+                        let successor = client
+                            .get_json("api/v1/ta/proxy/key-roll")
+                            .await?;
+                        Ok(TrustAnchorClientApiResponse::KeyRollSuccessor(
+                            successor,
+                        ))
+                    }
                     ProxyCommandDetails::ChildAdd(child) => {
                         let response = client
                             .post_json_with_response(
@@ -1102,6 +1271,21 @@ impl TrustAnchorClient {
                             response,
                         ))
                     }
+                    ProxyCommandDetails::ChildUpdate(child, resources) => {
+                        let uri_path =
+                            format!("api/v1/ta/proxy/children/{}", child);
+                        client
+                            .post_json(
+                                &uri_path,
+                                UpdateChildRequest::resources(resources),
+                            )
+                            .await
+                    }
+                    ProxyCommandDetails::ChildRemove(child) => {
+                        let uri_path =
+                            format!("api/v1/ta/proxy/children/{}", child);
+                        client.delete(&uri_path).await
+                    }
                     ProxyCommandDetails::ChildResponse(child) => {
                         let uri_path = format!("api/v1/ta/proxy/children/{}/parent_response.json", child);
                         let response = client.get_json(&uri_path).await?;
@@ -1147,6 +1331,7 @@ pub enum TrustAnchorClientApiResponse {
     SignerRequest(TrustAnchorSignedRequest),
     SignerResponse(TrustAnchorSignedResponse),
     ProxySignerExchanges(TrustAnchorProxySignerExchanges),
+    KeyRollSuccessor(Option<TaSuccessor>),
     Empty,
 }
 
@@ -1183,6 +1368,18 @@ impl TrustAnchorClientApiResponse {
                 TrustAnchorClientApiResponse::ProxySignerExchanges(
                     exchanges,
                 ) => exchanges.report(fmt).map(Some),
+                TrustAnchorClientApiResponse::KeyRollSuccessor(successor) => {
+                    match successor {
+                        Some(successor) => successor.report(fmt).map(Some),
+                        None if fmt == report::ReportFormat::Json => {
+                            Ok(Some("null".to_string()))
+                        }
+                        None => Ok(Some(
+                            "No Trust Anchor key roll in progress"
+                                .to_string(),
+                        )),
+                    }
+                }
                 TrustAnchorClientApiResponse::Empty => Ok(None),
             }
         }
@@ -1267,6 +1464,17 @@ impl ProxyClient {
             .map_err(TaClientError::HttpClientError)
     }
 
+    async fn delete(
+        &self,
+        path: &str,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        let uri = self.resolve_uri(path);
+        httpclient::delete(&uri, Some(&self.token))
+            .await
+            .map(|_| TrustAnchorClientApiResponse::Empty)
+            .map_err(TaClientError::HttpClientError)
+    }
+
     fn resolve_uri(&self, path: &str) -> String {
         format!("{}{}", self.server, path)
     }
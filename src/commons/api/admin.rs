@@ -19,8 +19,9 @@ use rpki::{
 };
 
 use crate::commons::{
-    api::{rrdp::PublishElement, IdCertInfo, Timestamp},
+    api::{rrdp::PublishElement, ChildAllocation, IdCertInfo, Timestamp},
     error::Error,
+    util::KrillVersion,
     KrillResult,
 };
 
@@ -245,6 +246,12 @@ impl TryFrom<ApiRepositoryContact> for RepositoryContact {
     }
 }
 
+//------------ RepositoryMirrorName ------------------------------------------
+
+/// The name by which a CA refers to a repository that mirrors its primary
+/// repository.
+pub type RepositoryMirrorName = String;
+
 //------------ RepositoryContact ---------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -531,6 +538,40 @@ impl CertAuthInit {
     }
 }
 
+//------------ CertAuthRename ------------------------------------------------
+
+/// Renames a CA. The old handle can be kept as an alias for the new handle
+/// for a number of days, so that API users can switch over.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CertAuthRename {
+    new_handle: CaHandle,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    alias_days: Option<u32>,
+}
+
+impl fmt::Display for CertAuthRename {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.new_handle)?;
+        if let Some(days) = self.alias_days {
+            write!(f, " (alias kept for {} days)", days)?;
+        }
+        Ok(())
+    }
+}
+
+impl CertAuthRename {
+    pub fn new(new_handle: CaHandle, alias_days: Option<u32>) -> Self {
+        CertAuthRename {
+            new_handle,
+            alias_days,
+        }
+    }
+
+    pub fn unpack(self) -> (CaHandle, Option<u32>) {
+        (self.new_handle, self.alias_days)
+    }
+}
+
 //------------ AddChildRequest -----------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -538,11 +579,24 @@ pub struct AddChildRequest {
     handle: ChildHandle,
     resources: ResourceSet,
     id_cert: IdCert,
+
+    #[serde(default, skip_serializing_if = "ChildCertTiming::is_empty")]
+    cert_timing: ChildCertTiming,
+
+    #[serde(default, skip_serializing_if = "ChildAllocation::is_empty")]
+    allocation: ChildAllocation,
 }
 
 impl fmt::Display for AddChildRequest {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "handle '{}' resources '{}'", self.handle, self.resources,)
+        write!(f, "handle '{}' resources '{}'", self.handle, self.resources,)?;
+        if !self.cert_timing.is_empty() {
+            write!(f, " certificate timing '{}'", self.cert_timing)?;
+        }
+        if !self.allocation.is_empty() {
+            write!(f, " allocation '{}'", self.allocation)?;
+        }
+        Ok(())
     }
 }
 
@@ -556,13 +610,33 @@ impl AddChildRequest {
             handle,
             resources,
             id_cert,
+            cert_timing: ChildCertTiming::default(),
+            allocation: ChildAllocation::default(),
         }
     }
 
+    pub fn with_cert_timing(mut self, cert_timing: ChildCertTiming) -> Self {
+        self.cert_timing = cert_timing;
+        self
+    }
+
+    pub fn with_allocation(mut self, allocation: ChildAllocation) -> Self {
+        self.allocation = allocation;
+        self
+    }
+
     pub fn handle(&self) -> &ChildHandle {
         &self.handle
     }
 
+    pub fn cert_timing(&self) -> &ChildCertTiming {
+        &self.cert_timing
+    }
+
+    pub fn allocation(&self) -> &ChildAllocation {
+        &self.allocation
+    }
+
     pub fn unpack(self) -> (ChildHandle, ResourceSet, IdCert) {
         (self.handle, self.resources, self.id_cert)
     }
@@ -583,6 +657,14 @@ pub struct UpdateChildRequest {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     resource_class_name_mapping: Option<ResourceClassNameMapping>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cert_timing: Option<ChildCertTiming>,
+
+    /// Allocate resources from a pool, in addition to any new resources,
+    /// or the current resources of the child if there are none.
+    #[serde(default, skip_serializing_if = "ChildAllocation::is_empty")]
+    allocation: ChildAllocation,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -602,6 +684,8 @@ impl UpdateChildRequest {
             resources,
             suspend,
             resource_class_name_mapping: None,
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
     pub fn id_cert(id_cert: IdCert) -> Self {
@@ -610,6 +694,8 @@ impl UpdateChildRequest {
             resources: None,
             suspend: None,
             resource_class_name_mapping: None,
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
 
@@ -619,6 +705,8 @@ impl UpdateChildRequest {
             resources: Some(resources),
             suspend: None,
             resource_class_name_mapping: None,
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
 
@@ -628,6 +716,8 @@ impl UpdateChildRequest {
             resources: None,
             suspend: Some(true),
             resource_class_name_mapping: None,
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
 
@@ -637,6 +727,8 @@ impl UpdateChildRequest {
             resources: None,
             suspend: Some(false),
             resource_class_name_mapping: None,
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
 
@@ -648,9 +740,33 @@ impl UpdateChildRequest {
             resources: None,
             suspend: None,
             resource_class_name_mapping: Some(mapping),
+            cert_timing: None,
+            allocation: ChildAllocation::default(),
         }
     }
 
+    pub fn cert_timing(cert_timing: ChildCertTiming) -> Self {
+        UpdateChildRequest {
+            id_cert: None,
+            resources: None,
+            suspend: None,
+            resource_class_name_mapping: None,
+            cert_timing: Some(cert_timing),
+            allocation: ChildAllocation::default(),
+        }
+    }
+
+    pub fn with_cert_timing(mut self, cert_timing: ChildCertTiming) -> Self {
+        self.cert_timing = Some(cert_timing);
+        self
+    }
+
+    pub fn with_allocation(mut self, allocation: ChildAllocation) -> Self {
+        self.allocation = allocation;
+        self
+    }
+
+    #[allow(clippy::type_complexity)]
     pub fn unpack(
         self,
     ) -> (
@@ -658,12 +774,16 @@ impl UpdateChildRequest {
         Option<ResourceSet>,
         Option<bool>,
         Option<ResourceClassNameMapping>,
+        Option<ChildCertTiming>,
+        ChildAllocation,
     ) {
         (
             self.id_cert,
             self.resources,
             self.suspend,
             self.resource_class_name_mapping,
+            self.cert_timing,
+            self.allocation,
         )
     }
 }
@@ -677,12 +797,175 @@ impl fmt::Display for UpdateChildRequest {
             write!(f, "new resources: {} ", resources)?;
         }
         if let Some(suspend) = self.suspend {
-            write!(f, "change suspend status to: {}", suspend)?;
+            write!(f, "change suspend status to: {} ", suspend)?;
+        }
+        if let Some(cert_timing) = &self.cert_timing {
+            write!(f, "new certificate timing: {}", cert_timing)?;
+        }
+        if !self.allocation.is_empty() {
+            write!(f, "allocation: {}", self.allocation)?;
         }
         Ok(())
     }
 }
 
+//------------ ChildCertTiming -----------------------------------------------
+
+/// Overrides for the validity of certificates issued to a child, and for
+/// how long before they expire they are re-issued. The server defaults
+/// from the `timing_child_certificate_valid_weeks` and
+/// `timing_child_certificate_reissue_weeks_before` settings are used if
+/// there is no override.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+pub struct ChildCertTiming {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_weeks: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reissue_weeks_before: Option<u32>,
+}
+
+impl ChildCertTiming {
+    pub fn new(
+        valid_weeks: Option<u32>,
+        reissue_weeks_before: Option<u32>,
+    ) -> Self {
+        ChildCertTiming {
+            valid_weeks,
+            reissue_weeks_before,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.valid_weeks.is_none() && self.reissue_weeks_before.is_none()
+    }
+}
+
+impl fmt::Display for ChildCertTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.valid_weeks {
+            Some(weeks) => write!(f, "valid for {} weeks", weeks)?,
+            None => write!(f, "default validity")?,
+        }
+        match self.reissue_weeks_before {
+            Some(weeks) => {
+                write!(f, ", re-issue {} weeks before expiry", weeks)
+            }
+            None => write!(f, ", default re-issue time"),
+        }
+    }
+}
+
+//------------ CaIssuanceTiming ----------------------------------------------
+
+/// Overrides of the server issuance timing settings for a single CA. The
+/// fields use the names of the corresponding settings in the server
+/// configuration, and the server setting is used for every field that is
+/// not set.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+pub struct CaIssuanceTiming {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_publish_next_hours: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_publish_next_jitter_hours: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_publish_hours_before_next: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_child_certificate_valid_weeks: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_child_certificate_reissue_weeks_before: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_roa_valid_weeks: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_roa_reissue_weeks_before: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_aspa_valid_weeks: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_aspa_reissue_weeks_before: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_bgpsec_valid_weeks: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_bgpsec_reissue_weeks_before: Option<u32>,
+}
+
+impl CaIssuanceTiming {
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|(_, value)| value.is_none())
+    }
+
+    /// Returns the names and values of all fields.
+    pub fn fields(&self) -> [(&'static str, Option<u32>); 11] {
+        [
+            ("timing_publish_next_hours", self.timing_publish_next_hours),
+            (
+                "timing_publish_next_jitter_hours",
+                self.timing_publish_next_jitter_hours,
+            ),
+            (
+                "timing_publish_hours_before_next",
+                self.timing_publish_hours_before_next,
+            ),
+            (
+                "timing_child_certificate_valid_weeks",
+                self.timing_child_certificate_valid_weeks,
+            ),
+            (
+                "timing_child_certificate_reissue_weeks_before",
+                self.timing_child_certificate_reissue_weeks_before,
+            ),
+            ("timing_roa_valid_weeks", self.timing_roa_valid_weeks),
+            (
+                "timing_roa_reissue_weeks_before",
+                self.timing_roa_reissue_weeks_before,
+            ),
+            ("timing_aspa_valid_weeks", self.timing_aspa_valid_weeks),
+            (
+                "timing_aspa_reissue_weeks_before",
+                self.timing_aspa_reissue_weeks_before,
+            ),
+            ("timing_bgpsec_valid_weeks", self.timing_bgpsec_valid_weeks),
+            (
+                "timing_bgpsec_reissue_weeks_before",
+                self.timing_bgpsec_reissue_weeks_before,
+            ),
+        ]
+    }
+
+    /// Returns the names and values of the fields that are set.
+    pub fn overrides(&self) -> impl Iterator<Item = (&'static str, u32)> {
+        self.fields()
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+    }
+}
+
+impl fmt::Display for CaIssuanceTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "server defaults");
+        }
+        let overrides: Vec<_> = self
+            .overrides()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        write!(f, "{}", overrides.join(", "))
+    }
+}
+
 //------------ ServerInfo ----------------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -719,6 +1002,49 @@ impl fmt::Display for ServerInfo {
     }
 }
 
+//------------ BackupManifest ------------------------------------------------
+
+/// Describes the contents of a backup archive.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BackupManifest {
+    /// The version of the archive format.
+    pub format: u32,
+
+    /// The version of Krill that created the backup.
+    pub krill_version: KrillVersion,
+    pub created: Timestamp,
+    pub namespaces: Vec<NamespaceBackup>,
+}
+
+impl fmt::Display for BackupManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Krill version: {}", self.krill_version)?;
+        writeln!(f, "Created: {}", self.created.to_rfc3339())?;
+        writeln!(f, "Namespaces:")?;
+        for namespace in &self.namespaces {
+            writeln!(
+                f,
+                "  {}: {} keys, sha256 {}",
+                namespace.namespace, namespace.keys, namespace.sha256
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The contents of a single namespace in a backup archive.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NamespaceBackup {
+    pub namespace: String,
+
+    /// The number of keys stored for the namespace.
+    pub keys: usize,
+
+    /// The hex encoded SHA-256 digest over the paths and contents of all
+    /// entries for the namespace, in archive order.
+    pub sha256: String,
+}
+
 //------------ RepoFileDeleteCriteria ----------------------------------------
 
 /// This is used to send criteria for purging matching files from the
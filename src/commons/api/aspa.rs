@@ -63,6 +63,10 @@ impl AspaDefinitionList {
     pub fn new(definitions: Vec<AspaDefinition>) -> Self {
         AspaDefinitionList(definitions)
     }
+
+    pub fn unpack(self) -> Vec<AspaDefinition> {
+        self.0
+    }
 }
 
 impl fmt::Display for AspaDefinitionList {
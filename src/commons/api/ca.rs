@@ -1,7 +1,7 @@
 //! Common data types for Certificate Authorities, defined here so that the
 //! CLI can have access without needing to depend on the full krill_ca module.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::{self};
 use std::str::FromStr;
 use std::sync::Arc;
@@ -49,14 +49,15 @@ use crate::{
     commons::{
         api::{
             rrdp::PublishElement, AspaDefinition, ErrorResponse,
-            ParentCaContact, RepositoryContact, RoaAggregateKey, RoaPayload,
+            ParentCaContact, RepositoryContact, RepositoryMirrorName,
+            RoaAggregateKey, RoaPayload,
         },
         util::KrillVersion,
     },
     daemon::ca::RoaPayloadJsonMapKey,
 };
 
-use super::{rrdp, BgpSecAsnKey};
+use super::{rrdp, BgpSecAsnKey, ChildCertTiming, KeyRollStatus};
 
 //------------ IdCertInfo ----------------------------------------------------
 
@@ -173,6 +174,9 @@ pub struct ChildCaInfo {
     state: ChildState,
     id_cert: IdCertInfo,
     entitled_resources: ResourceSet,
+
+    #[serde(default, skip_serializing_if = "ChildCertTiming::is_empty")]
+    cert_timing: ChildCertTiming,
 }
 
 impl ChildCaInfo {
@@ -185,9 +189,15 @@ impl ChildCaInfo {
             state,
             id_cert,
             entitled_resources,
+            cert_timing: ChildCertTiming::default(),
         }
     }
 
+    pub fn with_cert_timing(mut self, cert_timing: ChildCertTiming) -> Self {
+        self.cert_timing = cert_timing;
+        self
+    }
+
     pub fn state(&self) -> ChildState {
         self.state
     }
@@ -199,6 +209,11 @@ impl ChildCaInfo {
     pub fn entitled_resources(&self) -> &ResourceSet {
         &self.entitled_resources
     }
+
+    /// Returns the overrides of the certificate timing for this child.
+    pub fn cert_timing(&self) -> &ChildCertTiming {
+        &self.cert_timing
+    }
 }
 
 impl fmt::Display for ChildCaInfo {
@@ -210,6 +225,9 @@ impl fmt::Display for ChildCaInfo {
             self.id_cert.hash()
         )?;
         writeln!(f, "resources: {}", self.entitled_resources)?;
+        if !self.cert_timing.is_empty() {
+            writeln!(f, "certificate timing: {}", self.cert_timing)?;
+        }
         writeln!(f, "state: {}", self.state)
     }
 }
@@ -598,6 +616,10 @@ impl ObjectName {
         ObjectName::new(ki, "crl")
     }
 
+    pub fn tak_for_key(ki: &KeyIdentifier) -> Self {
+        ObjectName::new(ki, "tak")
+    }
+
     pub fn aspa(customer: Asn) -> Self {
         ObjectName(format!("{}.asa", customer).into())
     }
@@ -936,6 +958,10 @@ impl ParentInfo {
         let kind = ParentKindInfo::Rfc6492;
         ParentInfo { handle, kind }
     }
+
+    pub fn handle(&self) -> &ParentHandle {
+        &self.handle
+    }
 }
 
 impl fmt::Display for ParentInfo {
@@ -1268,6 +1294,10 @@ pub struct RepoStatus {
     last_exchange: Option<ParentExchange>,
     last_success: Option<Timestamp>,
     published: Vec<PublishElement>,
+
+    /// The status of each repository mirroring the primary repository.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mirrors: BTreeMap<RepositoryMirrorName, RepoStatus>,
 }
 
 impl RepoStatus {
@@ -1282,6 +1312,14 @@ impl RepoStatus {
     pub fn to_failure_opt(&self) -> Option<ErrorResponse> {
         self.last_exchange.as_ref().and_then(|e| e.to_failure_opt())
     }
+
+    pub fn mirrors(&self) -> &BTreeMap<RepositoryMirrorName, RepoStatus> {
+        &self.mirrors
+    }
+
+    pub fn mirror(&self, name: &str) -> Option<&RepoStatus> {
+        self.mirrors.get(name)
+    }
 }
 
 impl RepoStatus {
@@ -1331,6 +1369,15 @@ impl RepoStatus {
         });
         self.last_success = Some(timestamp);
     }
+
+    /// Returns the status of a mirror, which is added if it is missing.
+    pub fn mirror_mut(&mut self, name: &str) -> &mut RepoStatus {
+        self.mirrors.entry(name.to_string()).or_default()
+    }
+
+    pub fn remove_mirror(&mut self, name: &str) {
+        self.mirrors.remove(name);
+    }
 }
 
 impl fmt::Display for RepoStatus {
@@ -1356,6 +1403,13 @@ impl fmt::Display for RepoStatus {
                 }
             }
         }
+        for (name, mirror) in &self.mirrors {
+            writeln!(f)?;
+            writeln!(f, "Mirror '{}':", name)?;
+            for line in mirror.to_string().lines() {
+                writeln!(f, "  {}", line)?;
+            }
+        }
         Ok(())
     }
 }
@@ -1766,6 +1820,12 @@ pub struct CertAuthInfo {
     resource_classes: HashMap<ResourceClassName, ResourceClassInfo>,
     children: Vec<ChildHandle>,
     suspended_children: Vec<ChildHandle>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    key_roll: Option<KeyRollStatus>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    repo_migration: Option<RepoMigration>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    aliases: HashMap<CaHandle, Timestamp>,
 }
 
 impl CertAuthInfo {
@@ -1798,9 +1858,33 @@ impl CertAuthInfo {
             resource_classes,
             children,
             suspended_children,
+            key_roll: None,
+            repo_migration: None,
+            aliases: HashMap::new(),
         }
     }
 
+    pub fn with_key_roll(mut self, key_roll: KeyRollStatus) -> Self {
+        self.key_roll = Some(key_roll);
+        self
+    }
+
+    pub fn with_repo_migration(
+        mut self,
+        repo_migration: Option<RepoMigration>,
+    ) -> Self {
+        self.repo_migration = repo_migration;
+        self
+    }
+
+    pub fn with_aliases(
+        mut self,
+        aliases: HashMap<CaHandle, Timestamp>,
+    ) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
     pub fn handle(&self) -> &CaHandle {
         &self.handle
     }
@@ -1834,11 +1918,33 @@ impl CertAuthInfo {
     pub fn suspended_children(&self) -> &Vec<ChildHandle> {
         &self.suspended_children
     }
+
+    pub fn key_roll(&self) -> Option<&KeyRollStatus> {
+        self.key_roll.as_ref()
+    }
+
+    pub fn repo_migration(&self) -> Option<&RepoMigration> {
+        self.repo_migration.as_ref()
+    }
+
+    /// Returns the old handles of this CA which can still be used in API
+    /// calls, and until when.
+    pub fn aliases(&self) -> &HashMap<CaHandle, Timestamp> {
+        &self.aliases
+    }
 }
 
 impl fmt::Display for CertAuthInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Name:     {}", self.handle())?;
+        for (alias, until) in self.aliases() {
+            writeln!(
+                f,
+                "Alias:    {} (until {})",
+                alias,
+                until.to_rfc3339()
+            )?;
+        }
         writeln!(f)?;
 
         if let Some(repo_info) = self.repo_info() {
@@ -1855,6 +1961,10 @@ impl fmt::Display for CertAuthInfo {
         }
         writeln!(f)?;
 
+        if let Some(migration) = self.repo_migration() {
+            write!(f, "{}", migration)?;
+        }
+
         writeln!(f, "ID cert PEM:\n{}", self.id_cert().pem())?;
         writeln!(f, "Hash: {}", self.id_cert().hash())?;
         writeln!(f)?;
@@ -1886,6 +1996,10 @@ impl fmt::Display for CertAuthInfo {
             writeln!(f, "{}", rc.keys())?;
         }
 
+        if let Some(key_roll) = self.key_roll() {
+            writeln!(f, "{}", key_roll)?;
+        }
+
         writeln!(f, "Children:")?;
         if !self.children().is_empty() {
             for child_handle in self.children() {
@@ -2053,16 +2167,52 @@ impl fmt::Display for ResourceClassKeysInfo {
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct CaRepoDetails {
     contact: RepositoryContact,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mirrors: BTreeMap<RepositoryMirrorName, RepositoryContact>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    migration: Option<RepoMigration>,
 }
 
 impl CaRepoDetails {
     pub fn new(contact: RepositoryContact) -> Self {
-        CaRepoDetails { contact }
+        CaRepoDetails {
+            contact,
+            mirrors: BTreeMap::new(),
+            migration: None,
+        }
+    }
+
+    pub fn with_migration(
+        mut self,
+        migration: Option<RepoMigration>,
+    ) -> Self {
+        self.migration = migration;
+        self
+    }
+
+    pub fn with_mirrors(
+        mut self,
+        mirrors: BTreeMap<RepositoryMirrorName, RepositoryContact>,
+    ) -> Self {
+        self.mirrors = mirrors;
+        self
     }
 
     pub fn contact(&self) -> &RepositoryContact {
         &self.contact
     }
+
+    pub fn mirrors(
+        &self,
+    ) -> &BTreeMap<RepositoryMirrorName, RepositoryContact> {
+        &self.mirrors
+    }
+
+    pub fn migration(&self) -> Option<&RepoMigration> {
+        self.migration.as_ref()
+    }
 }
 
 impl fmt::Display for CaRepoDetails {
@@ -2085,10 +2235,139 @@ impl fmt::Display for CaRepoDetails {
         writeln!(f, "  rpki_notify:    {}", rrdp_uri)?;
         writeln!(f)?;
 
+        for (name, mirror) in &self.mirrors {
+            writeln!(f, "Mirror '{}':", name)?;
+            writeln!(
+                f,
+                "  service uri:    {}",
+                mirror.server_info().service_uri()
+            )?;
+            writeln!(
+                f,
+                "  key identifier: {}",
+                mirror.server_info().public_key().key_identifier()
+            )?;
+            writeln!(f)?;
+        }
+
+        if let Some(migration) = &self.migration {
+            write!(f, "{}", migration)?;
+        }
+
         Ok(())
     }
 }
 
+//------------ RepoMigration -------------------------------------------------
+
+/// A staged migration of a CA to a new repository.
+///
+/// The objects of the CA are published to the new repository first, and
+/// only when it is verified that they can be retrieved from there, new
+/// certificates pointing to the new repository are requested from the
+/// parents in a key roll. The old repository is cleaned up when the key
+/// roll is finished.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RepoMigration {
+    from: RepositoryContact,
+    to: RepositoryContact,
+    phase: RepoMigrationPhase,
+
+    /// The number of objects found in the new repository when it was
+    /// verified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verified_objects: Option<usize>,
+}
+
+impl RepoMigration {
+    pub fn new(from: RepositoryContact, to: RepositoryContact) -> Self {
+        RepoMigration {
+            from,
+            to,
+            phase: RepoMigrationPhase::Staging,
+            verified_objects: None,
+        }
+    }
+
+    pub fn from(&self) -> &RepositoryContact {
+        &self.from
+    }
+
+    pub fn to(&self) -> &RepositoryContact {
+        &self.to
+    }
+
+    pub fn phase(&self) -> RepoMigrationPhase {
+        self.phase
+    }
+
+    pub fn verified_objects(&self) -> Option<usize> {
+        self.verified_objects
+    }
+
+    pub fn set_verified(&mut self, objects: usize) {
+        self.verified_objects = Some(objects);
+        self.phase = RepoMigrationPhase::Certifying;
+    }
+
+    pub fn set_withdrawing(&mut self) {
+        self.phase = RepoMigrationPhase::Withdrawing;
+    }
+}
+
+impl fmt::Display for RepoMigration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Migrating to repository:")?;
+        writeln!(
+            f,
+            "  service uri:    {}",
+            self.to.server_info().service_uri()
+        )?;
+        writeln!(f, "  base_uri:       {}", self.to.repo_info().base_uri())?;
+        writeln!(f, "  phase:          {}", self.phase)?;
+        if let Some(objects) = self.verified_objects {
+            writeln!(f, "  verified:       {} objects", objects)?;
+        }
+        writeln!(f)
+    }
+}
+
+//------------ RepoMigrationPhase --------------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoMigrationPhase {
+    /// The objects are published to the new repository, and it is
+    /// verified that they can be retrieved from there. The migration can
+    /// still be aborted in this phase.
+    Staging,
+
+    /// New certificates for the new repository are requested from the
+    /// parents, and the new keys are activated in a key roll.
+    Certifying,
+
+    /// The key roll is finished, and the objects are withdrawn from the
+    /// old repository.
+    Withdrawing,
+}
+
+impl fmt::Display for RepoMigrationPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepoMigrationPhase::Staging => {
+                write!(f, "staging objects in the new repository")
+            }
+            RepoMigrationPhase::Certifying => write!(
+                f,
+                "requesting certificates for the new repository (key roll)"
+            ),
+            RepoMigrationPhase::Withdrawing => {
+                write!(f, "withdrawing objects from the old repository")
+            }
+        }
+    }
+}
+
 //------------ AllCertAuthIssues ---------------------------------------------
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
@@ -2341,6 +2620,90 @@ impl fmt::Display for RtaPrepResponse {
     }
 }
 
+//------------ RscName, RscList ----------------------------------------------
+
+pub type RscName = String;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscList(Vec<RscName>);
+
+impl RscList {
+    pub fn new(list: Vec<RscName>) -> Self {
+        RscList(list)
+    }
+}
+
+impl fmt::Display for RscList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for name in &self.0 {
+            writeln!(f, "{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ GeofeedList, GeofeedSummary ----------------------------------
+
+/// The geofeeds signed by a CA, oldest first.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GeofeedList(Vec<GeofeedSummary>);
+
+impl GeofeedList {
+    pub fn new(list: Vec<GeofeedSummary>) -> Self {
+        GeofeedList(list)
+    }
+
+    pub fn as_slice(&self) -> &[GeofeedSummary] {
+        &self.0
+    }
+}
+
+impl fmt::Display for GeofeedList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for summary in &self.0 {
+            writeln!(f, "{}", summary)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GeofeedSummary {
+    number: u64,
+    time: Timestamp,
+    resources: ResourceSet,
+}
+
+impl GeofeedSummary {
+    pub fn new(number: u64, time: Timestamp, resources: ResourceSet) -> Self {
+        GeofeedSummary {
+            number,
+            time,
+            resources,
+        }
+    }
+
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+}
+
+impl fmt::Display for GeofeedSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}  {}",
+            self.number,
+            self.time.to_rfc3339(),
+            self.resources
+        )
+    }
+}
+
 //============ Tests =========================================================
 
 #[cfg(test)]
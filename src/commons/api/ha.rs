@@ -0,0 +1,121 @@
+//! Types for reporting on the leader election between Krill nodes that
+//! share the same storage.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::Timestamp;
+
+//------------ LeaderLease ---------------------------------------------------
+
+/// The lease that makes a Krill node the leader.
+///
+/// The lease is kept in storage and renewed by the leader on every
+/// heartbeat. Other nodes may take over the lease once it has expired.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LeaderLease {
+    /// The id of the node holding the lease.
+    pub holder: String,
+
+    /// The fencing token for the lease. This is increased whenever the
+    /// lease changes hands, so that a node that lost the lease can tell
+    /// that it may no longer act as the leader.
+    pub fencing_token: u64,
+
+    /// When the current holder acquired the lease.
+    pub acquired: Timestamp,
+
+    /// When the current holder last renewed the lease.
+    pub renewed: Timestamp,
+
+    /// When the lease expires, unless it is renewed before then.
+    pub expires: Timestamp,
+}
+
+impl LeaderLease {
+    pub fn is_held_by(&self, node_id: &str) -> bool {
+        self.holder == node_id
+    }
+
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires <= now
+    }
+}
+
+//------------ HaStatus ------------------------------------------------------
+
+/// The leader election status as seen by a single Krill node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HaStatus {
+    /// The id of the node reporting this status, if high availability
+    /// is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+
+    pub role: HaRole,
+
+    /// The last lease seen by this node, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lease: Option<LeaderLease>,
+}
+
+impl HaStatus {
+    pub fn standalone() -> Self {
+        HaStatus {
+            node_id: None,
+            role: HaRole::Standalone,
+            lease: None,
+        }
+    }
+}
+
+impl fmt::Display for HaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let node_id = match &self.node_id {
+            Some(node_id) => node_id,
+            None => return writeln!(f, "Role:          {}", self.role),
+        };
+        writeln!(f, "Node:          {}", node_id)?;
+        writeln!(f, "Role:          {}", self.role)?;
+        match &self.lease {
+            Some(lease) => {
+                writeln!(f, "Leader:        {}", lease.holder)?;
+                writeln!(f, "Fencing token: {}", lease.fencing_token)?;
+                writeln!(
+                    f,
+                    "Acquired:      {}",
+                    lease.acquired.to_rfc3339()
+                )?;
+                writeln!(f, "Renewed:       {}", lease.renewed.to_rfc3339())?;
+                writeln!(f, "Expires:       {}", lease.expires.to_rfc3339())
+            }
+            None => writeln!(f, "Leader:        none"),
+        }
+    }
+}
+
+//------------ HaRole --------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HaRole {
+    /// High availability is not configured, this node does everything.
+    Standalone,
+
+    /// This node holds the lease and runs the scheduler.
+    Leader,
+
+    /// This node serves read-only requests and waits for the lease.
+    Standby,
+}
+
+impl fmt::Display for HaRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HaRole::Standalone => write!(f, "standalone"),
+            HaRole::Leader => write!(f, "leader"),
+            HaRole::Standby => write!(f, "standby"),
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use std::{collections::BTreeMap, fmt};
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
 use chrono::{DateTime, SecondsFormat};
 
 use rpki::{
     ca::{
         idexchange::{
-            ChildHandle, MyHandle, ParentHandle, PublisherHandle, ServiceUri,
+            CaHandle, ChildHandle, MyHandle, ParentHandle, PublisherHandle,
+            ServiceUri,
         },
         provisioning::{
             RequestResourceLimit, ResourceClassName, RevocationRequest,
@@ -19,8 +20,11 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            ArgKey, ArgVal, AspaProvidersUpdate, CustomerAsn, Message,
-            RoaConfigurationUpdates, RtaName, StorableParentContact,
+            ArgKey, ArgVal, AspaProvidersUpdate, CaIssuanceTiming,
+            ChildAllocation, ChildCertTiming, CustomerAsn, KeyRollPolicy,
+            Message, RepositoryMirrorName, ResourcePoolName,
+            RoaConfigurationUpdates, RscName, RtaName, StorableParentContact,
+            Timestamp,
         },
         eventsourcing::{
             Event, InitEvent, StoredCommand, StoredEffect,
@@ -197,6 +201,13 @@ impl CommandSummary {
             .with_arg("ipv6_blocks", summary.ipv6_blocks())
     }
 
+    pub fn with_allocation(self, allocation: &ChildAllocation) -> Self {
+        match &allocation.from_pool {
+            Some(from_pool) => self.with_arg("pool", &from_pool.pool),
+            None => self,
+        }
+    }
+
     pub fn with_rcn(self, rcn: &ResourceClassName) -> Self {
         self.with_arg("class_name", rcn)
     }
@@ -235,6 +246,10 @@ impl CommandSummary {
     pub fn with_rta_name(self, name: &str) -> Self {
         self.with_arg("rta_name", name)
     }
+
+    pub fn with_rsc_name(self, name: &str) -> Self {
+        self.with_arg("rsc_name", name)
+    }
 }
 
 //------------ CommandHistoryCriteria ----------------------------------------
@@ -353,6 +368,209 @@ impl Default for CommandHistoryCriteria {
     }
 }
 
+//------------ HistoryRetention ----------------------------------------------
+
+/// Determines which stored commands are kept when the history of an
+/// aggregate is compacted. Commands are only archived if they fall outside
+/// of all limits that are set. If no limit is set, all commands are kept.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+pub struct HistoryRetention {
+    /// Keep at least this many of the most recent commands.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_versions: Option<u64>,
+
+    /// Keep all commands issued in this number of most recent days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u32>,
+}
+
+impl HistoryRetention {
+    pub fn new(keep_versions: Option<u64>, keep_days: Option<u32>) -> Self {
+        HistoryRetention {
+            keep_versions,
+            keep_days,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keep_versions.is_none() && self.keep_days.is_none()
+    }
+
+    /// Returns true if the command with the given version and time may be
+    /// archived, given the version of the aggregate it belongs to.
+    pub fn may_archive(
+        &self,
+        version: u64,
+        time: Time,
+        aggregate_version: u64,
+    ) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        let outside_versions = match self.keep_versions {
+            Some(keep) => version < aggregate_version.saturating_sub(keep),
+            None => true,
+        };
+
+        let outside_days = match self.keep_days {
+            Some(days) => {
+                time < Time::now() - chrono::Duration::days(days.into())
+            }
+            None => true,
+        };
+
+        outside_versions && outside_days
+    }
+}
+
+impl fmt::Display for HistoryRetention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.keep_versions, self.keep_days) {
+            (None, None) => write!(f, "keep all"),
+            (Some(versions), None) => write!(f, "keep {} versions", versions),
+            (None, Some(days)) => write!(f, "keep {} days", days),
+            (Some(versions), Some(days)) => {
+                write!(f, "keep {} versions and {} days", versions, days)
+            }
+        }
+    }
+}
+
+//------------ HistoryCompactionReport ---------------------------------------
+
+/// The result of compacting the command history of a single aggregate.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HistoryCompactionReport {
+    pub handle: MyHandle,
+    pub archived_commands: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_archived_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_archived_version: Option<u64>,
+    pub bytes_reclaimed: u64,
+}
+
+impl HistoryCompactionReport {
+    pub fn unchanged(handle: MyHandle) -> Self {
+        HistoryCompactionReport {
+            handle,
+            archived_commands: 0,
+            first_archived_version: None,
+            last_archived_version: None,
+            bytes_reclaimed: 0,
+        }
+    }
+}
+
+impl fmt::Display for HistoryCompactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.first_archived_version, self.last_archived_version) {
+            (Some(first), Some(last)) => writeln!(
+                f,
+                "{}: archived {} commands (versions {}-{}), reclaimed {} bytes",
+                self.handle,
+                self.archived_commands,
+                first,
+                last,
+                self.bytes_reclaimed
+            ),
+            _ => writeln!(f, "{}: nothing to archive", self.handle),
+        }
+    }
+}
+
+//------------ HistoryCompactionReports --------------------------------------
+
+/// The results of compacting the command history of multiple aggregates.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HistoryCompactionReports {
+    reports: Vec<HistoryCompactionReport>,
+}
+
+impl HistoryCompactionReports {
+    pub fn add(&mut self, report: HistoryCompactionReport) {
+        self.reports.push(report);
+    }
+
+    pub fn reports(&self) -> &Vec<HistoryCompactionReport> {
+        &self.reports
+    }
+
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.reports.iter().map(|r| r.bytes_reclaimed).sum()
+    }
+}
+
+impl fmt::Display for HistoryCompactionReports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for report in &self.reports {
+            report.fmt(f)?;
+        }
+        writeln!(f, "Total reclaimed: {} bytes", self.bytes_reclaimed())
+    }
+}
+
+//------------ HistoryPoint --------------------------------------------------
+
+/// A point in the history of an aggregate, given either as a version or as
+/// a moment in time.
+///
+/// A version refers to the state right after the command with that version
+/// was applied. A time refers to the state after all commands that were
+/// applied up to and including that time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HistoryPoint {
+    Version(u64),
+    Time(Time),
+}
+
+impl FromStr for HistoryPoint {
+    type Err = HistoryPointFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(version) = u64::from_str(s) {
+            Ok(HistoryPoint::Version(version))
+        } else {
+            Time::from_str(s)
+                .map(HistoryPoint::Time)
+                .map_err(|_| HistoryPointFormatError(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for HistoryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryPoint::Version(version) => write!(f, "{}", version),
+            HistoryPoint::Time(time) => {
+                write!(
+                    f,
+                    "{}",
+                    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryPointFormatError(String);
+
+impl fmt::Display for HistoryPointFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid point in history '{}', expected a version or a date/time in RFC 3339 format",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for HistoryPointFormatError {}
+
 //------------ CaCommandDetails ----------------------------------------------
 pub type CaCommandDetails = StoredCommand<CertAuth>;
 
@@ -397,6 +615,8 @@ pub enum CertAuthStorableCommand {
         child: ChildHandle,
         ski: String,
         resources: ResourceSet,
+        #[serde(default, skip_serializing_if = "ChildAllocation::is_empty")]
+        allocation: ChildAllocation,
     },
     ChildImport {
         child: ChildHandle,
@@ -406,15 +626,28 @@ pub enum CertAuthStorableCommand {
     ChildUpdateResources {
         child: ChildHandle,
         resources: ResourceSet,
+        #[serde(default, skip_serializing_if = "ChildAllocation::is_empty")]
+        allocation: ChildAllocation,
     },
     ChildUpdateId {
         child: ChildHandle,
         ski: String,
     },
+    ChildUpdateCertTiming {
+        child: ChildHandle,
+        cert_timing: ChildCertTiming,
+    },
     ChildUpdateResourceClassNameMapping {
         child: ChildHandle,
         mapping: ResourceClassNameMapping,
     },
+    ResourcePoolUpdate {
+        name: ResourcePoolName,
+        resources: ResourceSet,
+    },
+    ResourcePoolRemove {
+        name: ResourcePoolName,
+    },
     ChildCertify {
         child: ChildHandle,
         resource_class_name: ResourceClassName,
@@ -434,6 +667,10 @@ pub enum CertAuthStorableCommand {
     ChildUnsuspend {
         child: ChildHandle,
     },
+    ChildRename {
+        child: ChildHandle,
+        new_handle: ChildHandle,
+    },
     GenerateNewIdKey,
     AddParent {
         parent: ParentHandle,
@@ -446,6 +683,10 @@ pub enum CertAuthStorableCommand {
     RemoveParent {
         parent: ParentHandle,
     },
+    RenameParent {
+        parent: ParentHandle,
+        new_name: ParentHandle,
+    },
     UpdateResourceEntitlements {
         parent: ParentHandle,
         entitlements: Vec<StorableRcEntitlement>,
@@ -467,6 +708,15 @@ pub enum CertAuthStorableCommand {
     KeyRollFinish {
         resource_class_name: ResourceClassName,
     },
+    KeyRollPolicyUpdate {
+        policy: Option<KeyRollPolicy>,
+    },
+    KeyRollEmergency {
+        reason: String,
+    },
+    IssuanceTimingUpdate {
+        timing: Option<CaIssuanceTiming>,
+    },
     RoaDefinitionUpdates {
         updates: RoaConfigurationUpdates,
     },
@@ -486,6 +736,21 @@ pub enum CertAuthStorableCommand {
     RepoUpdate {
         service_uri: ServiceUri,
     },
+    RepoMirrorAdd {
+        name: RepositoryMirrorName,
+        service_uri: ServiceUri,
+    },
+    RepoMirrorRemove {
+        name: RepositoryMirrorName,
+    },
+    RepoMigrationStart {
+        service_uri: ServiceUri,
+    },
+    RepoMigrationVerified {
+        objects: usize,
+    },
+    RepoMigrationComplete,
+    RepoMigrationAbort,
     RtaPrepare {
         name: RtaName,
     },
@@ -495,6 +760,23 @@ pub enum CertAuthStorableCommand {
     RtaCoSign {
         name: RtaName,
     },
+    RscSign {
+        name: RscName,
+    },
+    RscCoSign {
+        name: RscName,
+    },
+    GeofeedSign {
+        resources: ResourceSet,
+    },
+    RenameStart {
+        to: CaHandle,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        alias_until: Option<Timestamp>,
+    },
+    Rename {
+        from: CaHandle,
+    },
     Deactivate,
 }
 
@@ -508,34 +790,60 @@ impl WithStorableDetails for CertAuthStorableCommand {
     fn summary(&self) -> CommandSummary {
         match self {
             CertAuthStorableCommand::Init => CommandSummary::new("cmd-ca-init", self),
-            CertAuthStorableCommand::ChildAdd { child, ski, resources } => {
-                CommandSummary::new("cmd-ca-child-add", self)
-                    .with_child(child)
-                    .with_id_ski(ski.as_ref())
-                    .with_resources(resources)
-            }
+            CertAuthStorableCommand::ChildAdd {
+                child,
+                ski,
+                resources,
+                allocation,
+            } => CommandSummary::new("cmd-ca-child-add", self)
+                .with_child(child)
+                .with_id_ski(ski.as_ref())
+                .with_resources(resources)
+                .with_allocation(allocation),
             CertAuthStorableCommand::ChildImport { child, ski, resources } => {
                 CommandSummary::new("cmd-ca-child-import", self)
                     .with_child(child)
                     .with_id_ski(ski)
                     .with_resources(resources)
             }
-            CertAuthStorableCommand::ChildUpdateResources { child, resources } => {
-                CommandSummary::new("cmd-ca-child-update-res", self)
-                    .with_child(child)
-                    .with_resources(resources)
-            }
+            CertAuthStorableCommand::ChildUpdateResources {
+                child,
+                resources,
+                allocation,
+            } => CommandSummary::new("cmd-ca-child-update-res", self)
+                .with_child(child)
+                .with_resources(resources)
+                .with_allocation(allocation),
             CertAuthStorableCommand::ChildUpdateId { child, ski } => {
                 CommandSummary::new("cmd-ca-child-update-id", self)
                     .with_child(child)
                     .with_id_ski(ski)
             }
+            CertAuthStorableCommand::ChildUpdateCertTiming { child, cert_timing } => {
+                let mut summary = CommandSummary::new("cmd-ca-child-update-cert-timing", self)
+                    .with_child(child);
+                if let Some(weeks) = cert_timing.valid_weeks {
+                    summary = summary.with_arg("valid_weeks", weeks);
+                }
+                if let Some(weeks) = cert_timing.reissue_weeks_before {
+                    summary = summary.with_arg("reissue_weeks_before", weeks);
+                }
+                summary
+            }
             CertAuthStorableCommand::ChildUpdateResourceClassNameMapping { child, mapping } => {
                 CommandSummary::new("cmd-ca-child-update-rcn-mapping", self)
                     .with_child(child)
                     .with_arg("parent_rcn", &mapping.name_in_parent)
                     .with_arg("child_rcn", &mapping.name_for_child)
             }
+            CertAuthStorableCommand::ResourcePoolUpdate { name, resources } => {
+                CommandSummary::new("cmd-ca-pool-update", self)
+                    .with_arg("pool", name)
+                    .with_resources(resources)
+            }
+            CertAuthStorableCommand::ResourcePoolRemove { name } => {
+                CommandSummary::new("cmd-ca-pool-remove", self).with_arg("pool", name)
+            }
             CertAuthStorableCommand::ChildCertify {
                 child,
                 resource_class_name,
@@ -551,6 +859,11 @@ impl WithStorableDetails for CertAuthStorableCommand {
             CertAuthStorableCommand::ChildSuspendInactive { child } => {
                 CommandSummary::new("cmd-ca-child-suspend-inactive", self).with_child(child)
             }
+            CertAuthStorableCommand::ChildRename { child, new_handle } => {
+                CommandSummary::new("cmd-ca-child-rename", self)
+                    .with_child(child)
+                    .with_arg("new_handle", new_handle)
+            }
             CertAuthStorableCommand::ChildUnsuspend { child } => {
                 CommandSummary::new("cmd-ca-child-unsuspend", self).with_child(child)
             }
@@ -572,6 +885,11 @@ impl WithStorableDetails for CertAuthStorableCommand {
             CertAuthStorableCommand::RemoveParent { parent } => {
                 CommandSummary::new("cmd-ca-parent-remove", self).with_parent(parent)
             }
+            CertAuthStorableCommand::RenameParent { parent, new_name } => {
+                CommandSummary::new("cmd-ca-parent-rename", self)
+                    .with_parent(parent)
+                    .with_arg("new_name", new_name)
+            }
             CertAuthStorableCommand::UpdateResourceEntitlements { parent, .. } => {
                 CommandSummary::new("cmd-ca-parent-entitlements", self).with_parent(parent)
             }
@@ -598,6 +916,27 @@ impl WithStorableDetails for CertAuthStorableCommand {
             CertAuthStorableCommand::KeyRollFinish { resource_class_name } => {
                 CommandSummary::new("cmd-ca-keyroll-finish", self).with_rcn(resource_class_name)
             }
+            CertAuthStorableCommand::KeyRollPolicyUpdate { policy } => {
+                let summary = CommandSummary::new("cmd-ca-keyroll-policy", self);
+                match policy {
+                    Some(policy) => summary
+                        .with_arg("roll_months", policy.roll_months)
+                        .with_arg("staging_hours", policy.staging_hours),
+                    None => summary,
+                }
+            }
+            CertAuthStorableCommand::KeyRollEmergency { reason } => {
+                CommandSummary::new("cmd-ca-keyroll-emergency", self).with_arg("reason", reason)
+            }
+            CertAuthStorableCommand::IssuanceTimingUpdate { timing } => {
+                let mut summary = CommandSummary::new("cmd-ca-timing-update", self);
+                if let Some(timing) = timing {
+                    for (name, value) in timing.overrides() {
+                        summary = summary.with_arg(name, value);
+                    }
+                }
+                summary
+            }
 
             // ROA
             CertAuthStorableCommand::RoaDefinitionUpdates { updates } => {
@@ -620,6 +959,24 @@ impl WithStorableDetails for CertAuthStorableCommand {
             CertAuthStorableCommand::RepoUpdate { service_uri } => {
                 CommandSummary::new("cmd-ca-repo-update", self).with_service_uri(service_uri)
             }
+            CertAuthStorableCommand::RepoMirrorAdd { name, service_uri } => {
+                CommandSummary::new("cmd-ca-repo-mirror-add", self)
+                    .with_arg("mirror", name)
+                    .with_service_uri(service_uri)
+            }
+            CertAuthStorableCommand::RepoMirrorRemove { name } => {
+                CommandSummary::new("cmd-ca-repo-mirror-remove", self).with_arg("mirror", name)
+            }
+            CertAuthStorableCommand::RepoMigrationStart { service_uri } => {
+                CommandSummary::new("cmd-ca-repo-migration-start", self).with_service_uri(service_uri)
+            }
+            CertAuthStorableCommand::RepoMigrationVerified { objects } => {
+                CommandSummary::new("cmd-ca-repo-migration-verified", self).with_arg("objects", objects)
+            }
+            CertAuthStorableCommand::RepoMigrationComplete => {
+                CommandSummary::new("cmd-ca-repo-migration-complete", self)
+            }
+            CertAuthStorableCommand::RepoMigrationAbort => CommandSummary::new("cmd-ca-repo-migration-abort", self),
 
             CertAuthStorableCommand::ReissueBeforeExpiring => {
                 CommandSummary::new("cmd-ca-reissue-before-expiring", self)
@@ -637,6 +994,27 @@ impl WithStorableDetails for CertAuthStorableCommand {
                 CommandSummary::new("cmd-ca-rta-cosign", self).with_rta_name(name)
             }
 
+            // RSC
+            CertAuthStorableCommand::RscSign { name } => {
+                CommandSummary::new("cmd-ca-rsc-sign", self).with_rsc_name(name)
+            }
+            CertAuthStorableCommand::RscCoSign { name } => {
+                CommandSummary::new("cmd-ca-rsc-cosign", self).with_rsc_name(name)
+            }
+
+            // Geofeed
+            CertAuthStorableCommand::GeofeedSign { resources } => {
+                CommandSummary::new("cmd-ca-geofeed-sign", self).with_resources(resources)
+            }
+
+            // Rename
+            CertAuthStorableCommand::RenameStart { to, .. } => {
+                CommandSummary::new("cmd-ca-rename-start", self).with_arg("to", to)
+            }
+            CertAuthStorableCommand::Rename { from } => {
+                CommandSummary::new("cmd-ca-rename", self).with_arg("from", from)
+            }
+
             // Deactivation
             CertAuthStorableCommand::Deactivate => CommandSummary::new("cmd-ca-deactivate", self),
         }
@@ -658,13 +1036,22 @@ impl fmt::Display for CertAuthStorableCommand {
             // ------------------------------------------------------------
             // Being a parent
             // ------------------------------------------------------------
-            CertAuthStorableCommand::ChildAdd { child, ski, resources } => {
+            CertAuthStorableCommand::ChildAdd {
+                child,
+                ski,
+                resources,
+                allocation,
+            } => {
                 let summary = ResourceSetSummary::from(resources);
                 write!(
                     f,
                     "Add child '{}' with RFC8183 key '{}' and resources '{}'",
                     child, ski, summary
-                )
+                )?;
+                if !allocation.is_empty() {
+                    write!(f, ", allocation: {}", allocation)?;
+                }
+                Ok(())
             }
             CertAuthStorableCommand::ChildImport { child, ski, resources } => {
                 let summary = ResourceSetSummary::from(resources);
@@ -674,13 +1061,24 @@ impl fmt::Display for CertAuthStorableCommand {
                     child, ski, summary
                 )
             }
-            CertAuthStorableCommand::ChildUpdateResources { child, resources } => {
+            CertAuthStorableCommand::ChildUpdateResources {
+                child,
+                resources,
+                allocation,
+            } => {
                 let summary = ResourceSetSummary::from(resources);
-                write!(f, "Update resources for child '{}' to: {}", child, summary)
+                write!(f, "Update resources for child '{}' to: {}", child, summary)?;
+                if !allocation.is_empty() {
+                    write!(f, ", allocation: {}", allocation)?;
+                }
+                Ok(())
             }
             CertAuthStorableCommand::ChildUpdateId { child, ski } => {
                 write!(f, "Update child '{}' RFC 8183 key '{}'", child, ski)
             }
+            CertAuthStorableCommand::ChildUpdateCertTiming { child, cert_timing } => {
+                write!(f, "Update child '{}' certificate timing: {}", child, cert_timing)
+            }
             CertAuthStorableCommand::ChildUpdateResourceClassNameMapping { child, mapping } => {
                 write!(
                     f,
@@ -688,6 +1086,13 @@ impl fmt::Display for CertAuthStorableCommand {
                     child, mapping.name_in_parent, mapping.name_for_child
                 )
             }
+            CertAuthStorableCommand::ResourcePoolUpdate { name, resources } => {
+                let summary = ResourceSetSummary::from(resources);
+                write!(f, "Set resource pool '{}' to resources: {}", name, summary)
+            }
+            CertAuthStorableCommand::ResourcePoolRemove { name } => {
+                write!(f, "Remove resource pool '{}'", name)
+            }
             CertAuthStorableCommand::ChildCertify { child, ki, .. } => {
                 write!(f, "Issue certificate to child '{}' for key '{}'", child, ki)
             }
@@ -704,6 +1109,9 @@ impl fmt::Display for CertAuthStorableCommand {
             CertAuthStorableCommand::ChildSuspendInactive { child } => {
                 write!(f, "Suspend inactive child '{}': stop publishing its certs", child)
             }
+            CertAuthStorableCommand::ChildRename { child, new_handle } => {
+                write!(f, "Rename child '{}' to '{}'", child, new_handle)
+            }
             CertAuthStorableCommand::ChildUnsuspend { child } => {
                 write!(f, "Unsuspend child '{}': publish its unexpired certs", child)
             }
@@ -719,6 +1127,9 @@ impl fmt::Display for CertAuthStorableCommand {
                 write!(f, "Update contact for parent '{}' to '{}'", parent, contact)
             }
             CertAuthStorableCommand::RemoveParent { parent } => write!(f, "Remove parent '{}'", parent),
+            CertAuthStorableCommand::RenameParent { parent, new_name } => {
+                write!(f, "Rename parent '{}' to '{}'", parent, new_name)
+            }
 
             CertAuthStorableCommand::UpdateResourceEntitlements { parent, entitlements } => {
                 write!(f, "Update entitlements under parent '{}': ", parent)?;
@@ -771,6 +1182,17 @@ impl fmt::Display for CertAuthStorableCommand {
             CertAuthStorableCommand::KeyRollFinish { resource_class_name } => {
                 write!(f, "Retire old revoked key in RC '{}'", resource_class_name)
             }
+            CertAuthStorableCommand::KeyRollPolicyUpdate { policy } => match policy {
+                Some(policy) => write!(f, "Set key roll policy: {}", policy),
+                None => write!(f, "Use the server default key roll policy"),
+            },
+            CertAuthStorableCommand::KeyRollEmergency { reason } => {
+                write!(f, "Emergency key roll, reason: {}", reason)
+            }
+            CertAuthStorableCommand::IssuanceTimingUpdate { timing } => match timing {
+                Some(timing) => write!(f, "Set issuance timing: {}", timing),
+                None => write!(f, "Use the server default issuance timing"),
+            },
 
             // ------------------------------------------------------------
             // ROA Support
@@ -822,6 +1244,20 @@ impl fmt::Display for CertAuthStorableCommand {
             CertAuthStorableCommand::RepoUpdate { service_uri } => {
                 write!(f, "Update repo to server at: {}", service_uri)
             }
+            CertAuthStorableCommand::RepoMirrorAdd { name, service_uri } => {
+                write!(f, "Add repo mirror '{}' at server: {}", name, service_uri)
+            }
+            CertAuthStorableCommand::RepoMirrorRemove { name } => {
+                write!(f, "Remove repo mirror '{}'", name)
+            }
+            CertAuthStorableCommand::RepoMigrationStart { service_uri } => {
+                write!(f, "Start migration to repo at server: {}", service_uri)
+            }
+            CertAuthStorableCommand::RepoMigrationVerified { objects } => {
+                write!(f, "Verified {} objects in the new repo", objects)
+            }
+            CertAuthStorableCommand::RepoMigrationComplete => write!(f, "Complete repo migration"),
+            CertAuthStorableCommand::RepoMigrationAbort => write!(f, "Abort repo migration"),
 
             // ------------------------------------------------------------
             // RTA
@@ -830,6 +1266,31 @@ impl fmt::Display for CertAuthStorableCommand {
             CertAuthStorableCommand::RtaSign { name } => write!(f, "RTA Sign {}", name),
             CertAuthStorableCommand::RtaCoSign { name } => write!(f, "RTA Co-Sign {}", name),
 
+            // ------------------------------------------------------------
+            // RSC
+            // ------------------------------------------------------------
+            CertAuthStorableCommand::RscSign { name } => write!(f, "RSC Sign {}", name),
+            CertAuthStorableCommand::RscCoSign { name } => write!(f, "RSC Co-Sign {}", name),
+
+            // ------------------------------------------------------------
+            // Geofeed
+            // ------------------------------------------------------------
+            CertAuthStorableCommand::GeofeedSign { resources } => write!(f, "Sign geofeed for {}", resources),
+
+            // ------------------------------------------------------------
+            // Rename
+            // ------------------------------------------------------------
+            CertAuthStorableCommand::RenameStart { to, alias_until } => {
+                write!(f, "Start renaming CA to '{}'", to)?;
+                if let Some(until) = alias_until {
+                    write!(f, ", keep alias until {}", until.to_rfc3339())?;
+                }
+                Ok(())
+            }
+            CertAuthStorableCommand::Rename { from } => {
+                write!(f, "Finish renaming CA from '{}'", from)
+            }
+
             // ------------------------------------------------------------
             // Deactivate
             // ------------------------------------------------------------
@@ -845,8 +1306,16 @@ impl fmt::Display for CertAuthStorableCommand {
 #[serde(rename_all = "snake_case", tag = "type")]
 pub enum StorableRepositoryCommand {
     Init,
-    AddPublisher { name: PublisherHandle },
-    RemovePublisher { name: PublisherHandle },
+    AddPublisher {
+        name: PublisherHandle,
+    },
+    RemovePublisher {
+        name: PublisherHandle,
+    },
+    RenamePublisher {
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+    },
 }
 
 impl WithStorableDetails for StorableRepositoryCommand {
@@ -863,6 +1332,11 @@ impl WithStorableDetails for StorableRepositoryCommand {
                 CommandSummary::new("pubd-publisher-remove", self)
                     .with_publisher(name)
             }
+            StorableRepositoryCommand::RenamePublisher { name, new_name } => {
+                CommandSummary::new("pubd-publisher-rename", self)
+                    .with_publisher(name)
+                    .with_arg("new_name", new_name)
+            }
         }
     }
 
@@ -883,6 +1357,9 @@ impl fmt::Display for StorableRepositoryCommand {
             StorableRepositoryCommand::RemovePublisher { name } => {
                 write!(f, "Removed publisher '{}'", name)
             }
+            StorableRepositoryCommand::RenamePublisher { name, new_name } => {
+                write!(f, "Renamed publisher '{}' to '{}'", name, new_name)
+            }
         }
     }
 }
@@ -0,0 +1,324 @@
+//! Types for the key roll policy of CAs, and for emergency key rolls.
+
+use std::fmt;
+
+use chrono::{Duration, Months};
+use rpki::{
+    ca::{
+        idexchange::{CaHandle, ParentHandle},
+        provisioning::ResourceClassName,
+    },
+    crypto::KeyIdentifier,
+    repository::x509::Time,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{ObjectName, Timestamp};
+
+//------------ KeyRollPolicy -------------------------------------------------
+
+/// The policy for rolling the keys of a CA automatically.
+///
+/// The scheduler initiates a key roll for every resource class with a
+/// key that has been active for the configured number of months. The new
+/// key is activated after it has been staged for the configured number of
+/// hours, which RFC 6489 says should be at least 24 hours.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollPolicy {
+    /// Roll keys which have been active for this many months. Keys are
+    /// not rolled automatically if this is 0.
+    pub roll_months: u32,
+
+    /// Activate new keys after they have been staged for this many hours.
+    pub staging_hours: u32,
+}
+
+impl KeyRollPolicy {
+    /// The minimum staging period required by section 4.4.1 of RFC 6489.
+    pub const MIN_STAGING_HOURS: u32 = 24;
+
+    pub fn new(roll_months: u32, staging_hours: u32) -> Self {
+        KeyRollPolicy {
+            roll_months,
+            staging_hours,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        KeyRollPolicy::new(0, Self::MIN_STAGING_HOURS)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.roll_months > 0
+    }
+
+    pub fn staging_too_short(&self) -> bool {
+        self.staging_hours < Self::MIN_STAGING_HOURS
+    }
+
+    /// Returns when a key that became active at the given time should be
+    /// rolled.
+    pub fn roll_due(&self, active_since: Time) -> Time {
+        active_since
+            .checked_add_months(Months::new(self.roll_months))
+            .map(Time::new)
+            .unwrap_or_else(|| Time::years_from_now(1000))
+    }
+
+    /// Returns when a key that was staged at the given time should be
+    /// activated.
+    pub fn activate_due(&self, staged_since: Time) -> Time {
+        staged_since + self.staging_period()
+    }
+
+    /// Returns the age at which keys should be rolled, counting back the
+    /// number of months from the given time.
+    pub fn max_key_age(&self, now: Time) -> Duration {
+        match now.checked_sub_months(Months::new(self.roll_months)) {
+            Some(since) => *now - since,
+            None => Duration::max_value(),
+        }
+    }
+
+    pub fn staging_period(&self) -> Duration {
+        Duration::hours(self.staging_hours.into())
+    }
+}
+
+impl fmt::Display for KeyRollPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_enabled() {
+            write!(
+                f,
+                "roll every {} months, activate after {} hours",
+                self.roll_months, self.staging_hours
+            )
+        } else {
+            write!(f, "disabled")
+        }
+    }
+}
+
+//------------ KeyRollStatus -------------------------------------------------
+
+/// The key roll policy of a CA and the next key roll phase that the
+/// scheduler will start because of it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollStatus {
+    pub policy: KeyRollPolicy,
+
+    /// Whether the policy was set for this CA, rather than taken from the
+    /// server configuration.
+    pub ca_specific: bool,
+
+    /// The next planned key roll phase, if any. There is none if keys are
+    /// not rolled automatically, or while the CA waits for its parent to
+    /// certify a new key or revoke an old key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<PlannedKeyRoll>,
+}
+
+impl fmt::Display for KeyRollStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let source = if self.ca_specific {
+            "CA"
+        } else {
+            "server default"
+        };
+        writeln!(f, "Key roll policy: {} ({})", self.policy, source)?;
+        match &self.next {
+            Some(next) => writeln!(f, "Next key roll:   {}", next),
+            None => writeln!(f, "Next key roll:   <none planned>"),
+        }
+    }
+}
+
+//------------ PlannedKeyRoll ------------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PlannedKeyRoll {
+    pub resource_class_name: ResourceClassName,
+    pub phase: KeyRollPhase,
+    pub due: Timestamp,
+}
+
+impl fmt::Display for PlannedKeyRoll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} for resource class '{}' at {}",
+            self.phase,
+            self.resource_class_name,
+            self.due.to_rfc3339()
+        )
+    }
+}
+
+//------------ KeyRollPhase --------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRollPhase {
+    /// Create a new key and request a certificate for it.
+    Initiate,
+
+    /// Activate the staged new key, and request revocation of the old key.
+    Activate,
+}
+
+impl fmt::Display for KeyRollPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyRollPhase::Initiate => write!(f, "initiate"),
+            KeyRollPhase::Activate => write!(f, "activate"),
+        }
+    }
+}
+
+//------------ EmergencyRekeyRequest -----------------------------------------
+
+/// Requests an emergency key roll for a CA, e.g. because its keys may
+/// have been compromised.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EmergencyRekeyRequest {
+    pub reason: String,
+}
+
+impl EmergencyRekeyRequest {
+    pub fn new(reason: String) -> Self {
+        EmergencyRekeyRequest { reason }
+    }
+}
+
+//------------ EmergencyRekeyReport ------------------------------------------
+
+/// Reports the outcome of an emergency key roll: the keys that were
+/// replaced and every object that was re-issued under the new keys.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EmergencyRekeyReport {
+    pub ca: CaHandle,
+    pub reason: String,
+    pub resource_classes: Vec<RekeyedResourceClass>,
+}
+
+impl fmt::Display for EmergencyRekeyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Emergency key roll for CA '{}'", self.ca)?;
+        writeln!(f, "Reason: {}", self.reason)?;
+        if self.resource_classes.is_empty() {
+            writeln!(f, "No keys were rolled.")?;
+        }
+        for rc in &self.resource_classes {
+            writeln!(f)?;
+            write!(f, "{}", rc)?;
+        }
+        Ok(())
+    }
+}
+
+/// The keys replaced in a resource class during an emergency key roll,
+/// and the objects re-issued under the new key.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RekeyedResourceClass {
+    pub resource_class_name: ResourceClassName,
+    pub parent: ParentHandle,
+    pub old_key: KeyIdentifier,
+    pub new_key: KeyIdentifier,
+
+    /// Whether the parent confirmed the revocation of the old key. If not,
+    /// then the revocation request will be sent again later.
+    pub old_key_revoked: bool,
+
+    pub roas: Vec<ObjectName>,
+    pub aspas: Vec<ObjectName>,
+    pub bgpsec_certs: Vec<ObjectName>,
+    pub child_certs: Vec<ObjectName>,
+}
+
+impl RekeyedResourceClass {
+    pub fn new(
+        resource_class_name: ResourceClassName,
+        parent: ParentHandle,
+        old_key: KeyIdentifier,
+        new_key: KeyIdentifier,
+    ) -> Self {
+        RekeyedResourceClass {
+            resource_class_name,
+            parent,
+            old_key,
+            new_key,
+            old_key_revoked: false,
+            roas: vec![],
+            aspas: vec![],
+            bgpsec_certs: vec![],
+            child_certs: vec![],
+        }
+    }
+
+    /// Returns the names of all re-issued objects.
+    pub fn reissued(&self) -> impl Iterator<Item = &ObjectName> {
+        self.roas
+            .iter()
+            .chain(self.aspas.iter())
+            .chain(self.bgpsec_certs.iter())
+            .chain(self.child_certs.iter())
+    }
+}
+
+impl fmt::Display for RekeyedResourceClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Resource class '{}' under parent '{}'",
+            self.resource_class_name, self.parent
+        )?;
+        writeln!(f, "  old key: {}", self.old_key)?;
+        writeln!(f, "  new key: {}", self.new_key)?;
+        if self.old_key_revoked {
+            writeln!(f, "  old key revoked by parent")?;
+        } else {
+            writeln!(f, "  old key revocation pending")?;
+        }
+        let mut reissued = self.reissued().peekable();
+        if reissued.peek().is_none() {
+            writeln!(f, "  re-issued objects: <none>")?;
+        } else {
+            writeln!(f, "  re-issued objects:")?;
+            for name in reissued {
+                writeln!(f, "    {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_roll_policy_timing() {
+        let policy = KeyRollPolicy::new(12, 24);
+        let since = Time::utc(2023, 1, 31, 12, 0, 0);
+
+        assert_eq!(policy.roll_due(since), Time::utc(2024, 1, 31, 12, 0, 0));
+        assert_eq!(
+            policy.activate_due(since),
+            Time::utc(2023, 2, 1, 12, 0, 0)
+        );
+        assert_eq!(
+            KeyRollPolicy::new(1, 24).roll_due(since),
+            Time::utc(2023, 2, 28, 12, 0, 0)
+        );
+
+        let now = Time::utc(2024, 3, 1, 0, 0, 0);
+        assert_eq!(policy.max_key_age(now), Duration::days(366));
+
+        assert!(policy.is_enabled());
+        assert!(!policy.staging_too_short());
+        assert!(!KeyRollPolicy::disabled().is_enabled());
+        assert!(KeyRollPolicy::new(12, 23).staging_too_short());
+    }
+}
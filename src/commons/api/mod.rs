@@ -12,16 +12,31 @@ pub use self::bgpsec::*;
 mod ca;
 pub use self::ca::*;
 
+mod ha;
+pub use self::ha::*;
+
 mod history;
 pub use self::history::*;
 
 pub mod import;
 
+mod keyroll;
+pub use self::keyroll::*;
+
+mod pools;
+pub use self::pools::*;
+
 mod roas;
 pub use self::roas::*;
 
 pub mod rrdp;
 
+mod tasks;
+pub use self::tasks::*;
+
+mod template;
+pub use self::template::*;
+
 use std::{collections::HashMap, fmt};
 
 use rpki::ca::csr::BgpsecCsr;
@@ -98,6 +113,10 @@ impl ErrorResponse {
         self.with_arg("ca", ca)
     }
 
+    pub fn with_alias(self, alias: &CaHandle) -> Self {
+        self.with_arg("alias", alias)
+    }
+
     pub fn with_parent(self, parent: &ParentHandle) -> Self {
         self.with_arg("parent", parent)
     }
@@ -122,6 +141,10 @@ impl ErrorResponse {
         self.with_arg("asn", asn)
     }
 
+    pub fn with_leader(self, leader: &str) -> Self {
+        self.with_arg("leader", leader)
+    }
+
     pub fn with_bgpsec_csr(self, csr: &BgpsecCsr) -> Self {
         let base64 = Base64::from_content(csr.to_captured().as_slice());
         self.with_arg("bgpsec_csr", base64)
@@ -139,6 +162,26 @@ impl ErrorResponse {
         self.with_arg("key_id", ki)
     }
 
+    pub fn with_pool(self, pool: &str) -> Self {
+        self.with_arg("pool", pool)
+    }
+
+    pub fn with_mirror(self, mirror: &str) -> Self {
+        self.with_arg("mirror", mirror)
+    }
+
+    pub fn with_rsc(self, name: &str) -> Self {
+        self.with_arg("rsc", name)
+    }
+
+    pub fn with_geofeed(self, number: u64) -> Self {
+        self.with_arg("geofeed", number)
+    }
+
+    pub fn with_prefixes(self, prefixes: &str) -> Self {
+        self.with_arg("prefixes", prefixes)
+    }
+
     pub fn with_resource_class(self, class_name: &ResourceClassName) -> Self {
         self.with_arg("class_name", class_name)
     }
@@ -0,0 +1,346 @@
+//! Types for resource pools of CAs, from which resources can be
+//! allocated to children.
+
+use std::fmt;
+
+use rpki::repository::resources::{AsBlocks, IpBlocks, ResourceSet};
+use serde::{Deserialize, Serialize};
+
+//------------ ResourcePoolName ----------------------------------------------
+
+pub type ResourcePoolName = String;
+
+//------------ ResourcePoolDefinition ----------------------------------------
+
+/// Defines a named pool of resources held by a CA. Resources in pools are
+/// delegated to at most one child, unless explicitly allowed otherwise.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourcePoolDefinition {
+    pub name: ResourcePoolName,
+    pub resources: ResourceSet,
+}
+
+impl ResourcePoolDefinition {
+    pub fn new(name: ResourcePoolName, resources: ResourceSet) -> Self {
+        ResourcePoolDefinition { name, resources }
+    }
+}
+
+impl fmt::Display for ResourcePoolDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pool '{}' with resources '{}'",
+            self.name, self.resources
+        )
+    }
+}
+
+//------------ PoolAllocation ------------------------------------------------
+
+/// Requests the next free resources of the given sizes from a pool.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PoolAllocation {
+    pub pool: ResourcePoolName,
+
+    /// Allocate the first free IPv4 prefix of this length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv4_prefix_len: Option<u8>,
+
+    /// Allocate the first free IPv6 prefix of this length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6_prefix_len: Option<u8>,
+
+    /// Allocate this many of the first free ASNs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asns: Option<u32>,
+}
+
+impl PoolAllocation {
+    pub fn new(pool: ResourcePoolName) -> Self {
+        PoolAllocation {
+            pool,
+            ipv4_prefix_len: None,
+            ipv6_prefix_len: None,
+            asns: None,
+        }
+    }
+
+    pub fn with_ipv4_prefix_len(mut self, len: u8) -> Self {
+        self.ipv4_prefix_len = Some(len);
+        self
+    }
+
+    pub fn with_ipv6_prefix_len(mut self, len: u8) -> Self {
+        self.ipv6_prefix_len = Some(len);
+        self
+    }
+
+    pub fn with_asns(mut self, asns: u32) -> Self {
+        self.asns = Some(asns);
+        self
+    }
+}
+
+impl fmt::Display for PoolAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(len) = self.ipv4_prefix_len {
+            parts.push(format!("IPv4 /{}", len));
+        }
+        if let Some(len) = self.ipv6_prefix_len {
+            parts.push(format!("IPv6 /{}", len));
+        }
+        if let Some(asns) = self.asns {
+            parts.push(format!("{} ASNs", asns));
+        }
+        write!(
+            f,
+            "next free {} from pool '{}'",
+            parts.join(", "),
+            self.pool
+        )
+    }
+}
+
+//------------ ChildAllocation -----------------------------------------------
+
+/// How the resources of a child are allocated from the resource pools of
+/// its parent CA.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ChildAllocation {
+    /// Resources to allocate from a pool, in addition to any resources
+    /// that were given explicitly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_pool: Option<PoolAllocation>,
+
+    /// Allow giving the child resources in pools that are also delegated
+    /// to other children.
+    #[serde(default)]
+    pub allow_overlap: bool,
+}
+
+impl ChildAllocation {
+    pub fn new(
+        from_pool: Option<PoolAllocation>,
+        allow_overlap: bool,
+    ) -> Self {
+        ChildAllocation {
+            from_pool,
+            allow_overlap,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from_pool.is_none() && !self.allow_overlap
+    }
+}
+
+impl fmt::Display for ChildAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.from_pool {
+            Some(allocation) => write!(f, "{}", allocation)?,
+            None => write!(f, "no allocation")?,
+        }
+        if self.allow_overlap {
+            write!(f, ", allowing overlaps with other children")?;
+        }
+        Ok(())
+    }
+}
+
+//------------ ResourcePoolList ----------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourcePoolList(Vec<ResourcePoolInfo>);
+
+impl ResourcePoolList {
+    pub fn new(pools: Vec<ResourcePoolInfo>) -> Self {
+        ResourcePoolList(pools)
+    }
+
+    pub fn pools(&self) -> &Vec<ResourcePoolInfo> {
+        &self.0
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ResourcePoolInfo> {
+        self.0.iter().find(|pool| pool.name == name)
+    }
+}
+
+impl fmt::Display for ResourcePoolList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            writeln!(f, "No resource pools defined.")?;
+        }
+        for pool in &self.0 {
+            write!(f, "{}", pool)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ ResourcePoolInfo ----------------------------------------------
+
+/// Shows the resources in a pool, and how many of them are delegated to
+/// children.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourcePoolInfo {
+    pub name: ResourcePoolName,
+    pub resources: ResourceSet,
+    pub allocated: ResourceSet,
+    pub asns: ResourceUsage,
+    pub ipv4: ResourceUsage,
+    pub ipv6: ResourceUsage,
+}
+
+impl ResourcePoolInfo {
+    pub fn new(
+        name: ResourcePoolName,
+        resources: ResourceSet,
+        allocated: ResourceSet,
+    ) -> Self {
+        let asns = ResourceUsage::new(
+            asn_count(resources.asn()),
+            asn_count(allocated.asn()),
+        );
+        let ipv4 = ResourceUsage::new(
+            address_count(resources.ipv4(), 32),
+            address_count(allocated.ipv4(), 32),
+        );
+        let ipv6 = ResourceUsage::new(
+            address_count(resources.ipv6(), 128),
+            address_count(allocated.ipv6(), 128),
+        );
+
+        ResourcePoolInfo {
+            name,
+            resources,
+            allocated,
+            asns,
+            ipv4,
+            ipv6,
+        }
+    }
+}
+
+impl fmt::Display for ResourcePoolInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Pool '{}': {}", self.name, self.resources)?;
+        if self.allocated.is_empty() {
+            writeln!(f, "  allocated: <none>")?;
+        } else {
+            writeln!(f, "  allocated: {}", self.allocated)?;
+        }
+        if self.asns.total > 0 {
+            writeln!(f, "  ASNs: {}", self.asns)?;
+        }
+        if self.ipv4.total > 0 {
+            writeln!(f, "  IPv4 addresses: {}", self.ipv4)?;
+        }
+        if self.ipv6.total > 0 {
+            writeln!(f, "  IPv6 addresses: {}", self.ipv6)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ ResourceUsage -------------------------------------------------
+
+/// The number of resources of one type in a pool, and how many of them
+/// are allocated.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    pub total: u128,
+    pub allocated: u128,
+}
+
+impl ResourceUsage {
+    pub fn new(total: u128, allocated: u128) -> Self {
+        ResourceUsage { total, allocated }
+    }
+
+    /// Returns the allocated resources as a percentage of the total.
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.allocated as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} allocated ({:.1}%)",
+            self.allocated,
+            self.total,
+            self.percentage()
+        )
+    }
+}
+
+fn asn_count(blocks: &AsBlocks) -> u128 {
+    blocks
+        .iter()
+        .map(|block| {
+            u128::from(block.max().into_u32() - block.min().into_u32()) + 1
+        })
+        .sum()
+}
+
+/// Counts the addresses in the blocks. Addresses are stored in the most
+/// significant bits, so the count is shifted by the unused bits. The
+/// count saturates for the full IPv6 address space.
+fn address_count(blocks: &IpBlocks, bits: u8) -> u128 {
+    let shift = 128 - u32::from(bits);
+    blocks
+        .iter()
+        .map(|block| {
+            ((block.max().to_bits() - block.min().to_bits()) >> shift)
+                .saturating_add(1)
+        })
+        .fold(0, u128::saturating_add)
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_pool_usage() {
+        let resources = ResourceSet::from_strs(
+            "AS65000-AS65009",
+            "10.0.0.0/16",
+            "2001:db8::/32",
+        )
+        .unwrap();
+        let allocated =
+            ResourceSet::from_strs("AS65000", "10.0.0.0/24, 10.0.2.0/23", "")
+                .unwrap();
+
+        let info = ResourcePoolInfo::new(
+            "customers".to_string(),
+            resources,
+            allocated,
+        );
+
+        assert_eq!(info.asns, ResourceUsage::new(10, 1));
+        assert_eq!(info.ipv4, ResourceUsage::new(65536, 768));
+        assert_eq!(info.ipv6, ResourceUsage::new(1 << 96, 0));
+        assert_eq!(info.asns.percentage(), 10.0);
+
+        let all = ResourcePoolInfo::new(
+            "all".to_string(),
+            ResourceSet::all(),
+            ResourceSet::empty(),
+        );
+        assert_eq!(all.asns.total, 1 << 32);
+        assert_eq!(all.ipv4.total, 1 << 32);
+        assert_eq!(all.ipv6.total, u128::MAX);
+    }
+}
@@ -30,7 +30,11 @@ pub struct PublishDelta {
 }
 
 impl PublishDelta {
-    pub fn new(publishes: Vec<Publish>, updates: Vec<Update>, withdraws: Vec<Withdraw>) -> Self {
+    pub fn new(
+        publishes: Vec<Publish>,
+        updates: Vec<Update>,
+        withdraws: Vec<Withdraw>,
+    ) -> Self {
         PublishDelta {
             publishes,
             updates,
@@ -134,7 +138,11 @@ pub struct Publish {
 }
 
 impl Publish {
-    pub fn new(tag: Option<String>, uri: uri::Rsync, content: Base64) -> Self {
+    pub fn new(
+        tag: Option<String>,
+        uri: uri::Rsync,
+        content: Base64,
+    ) -> Self {
         Publish { tag, uri, content }
     }
     pub fn with_hash_tag(uri: uri::Rsync, content: Base64) -> Self {
@@ -177,7 +185,12 @@ pub struct Update {
 }
 
 impl Update {
-    pub fn new(tag: Option<String>, uri: uri::Rsync, content: Base64, old_hash: HexEncodedHash) -> Self {
+    pub fn new(
+        tag: Option<String>,
+        uri: uri::Rsync,
+        content: Base64,
+        old_hash: HexEncodedHash,
+    ) -> Self {
         Update {
             tag,
             uri,
@@ -185,7 +198,11 @@ impl Update {
             hash: old_hash,
         }
     }
-    pub fn with_hash_tag(uri: uri::Rsync, content: Base64, old_hash: HexEncodedHash) -> Self {
+    pub fn with_hash_tag(
+        uri: uri::Rsync,
+        content: Base64,
+        old_hash: HexEncodedHash,
+    ) -> Self {
         let tag = Some(content.to_hex_hash());
         Update {
             tag,
@@ -214,7 +231,9 @@ impl Update {
         &self.hash
     }
 
-    pub fn unwrap(self) -> (Option<String>, uri::Rsync, Base64, HexEncodedHash) {
+    pub fn unwrap(
+        self,
+    ) -> (Option<String>, uri::Rsync, Base64, HexEncodedHash) {
         (self.tag, self.uri, self.content, self.hash)
     }
 }
@@ -232,7 +251,11 @@ pub struct Withdraw {
 }
 
 impl Withdraw {
-    pub fn new(tag: Option<String>, uri: uri::Rsync, hash: HexEncodedHash) -> Self {
+    pub fn new(
+        tag: Option<String>,
+        uri: uri::Rsync,
+        hash: HexEncodedHash,
+    ) -> Self {
         Withdraw { tag, uri, hash }
     }
 
@@ -293,7 +316,10 @@ impl ListReply {
     }
 
     pub fn from_files(files: Vec<CurrentFile>) -> Self {
-        let elements = files.into_iter().map(CurrentFile::into_list_element).collect();
+        let elements = files
+            .into_iter()
+            .map(CurrentFile::into_list_element)
+            .collect();
         ListReply { elements }
     }
 
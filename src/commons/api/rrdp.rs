@@ -695,6 +695,21 @@ impl SnapshotData {
         self.publishers_current_objects.remove(publisher);
     }
 
+    /// Applies the renaming of a publisher.
+    ///
+    /// This is a no-op in case the publisher does not exists.
+    pub fn apply_publisher_renamed(
+        &mut self,
+        publisher: &PublisherHandle,
+        new_name: PublisherHandle,
+    ) {
+        if let Some(objects) =
+            self.publishers_current_objects.remove(publisher)
+        {
+            self.publishers_current_objects.insert(new_name, objects);
+        }
+    }
+
     fn rel_path(&self, session: RrdpSession, serial: u64) -> String {
         format!("{}/{}/{}/snapshot.xml", session, serial, self.random.0)
     }
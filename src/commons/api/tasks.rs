@@ -0,0 +1,266 @@
+//! Types for inspecting and controlling the task queue.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::Timestamp;
+
+//------------ TaskList ------------------------------------------------------
+
+/// The running and pending tasks in the task queue.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskList {
+    pub tasks: Vec<TaskInfo>,
+}
+
+impl fmt::Display for TaskList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tasks.is_empty() {
+            return writeln!(f, "No tasks queued.");
+        }
+        for task in &self.tasks {
+            match task.state {
+                TaskState::Running => writeln!(
+                    f,
+                    "running  {} (for {}s) {}",
+                    task.name,
+                    task.running_seconds.unwrap_or_default(),
+                    task.description
+                )?,
+                TaskState::Pending => writeln!(
+                    f,
+                    "pending  {} (at {}) {}",
+                    task.name,
+                    task.scheduled
+                        .map(|ts| ts.to_rfc3339())
+                        .unwrap_or_default(),
+                    task.description
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ TaskDetails ---------------------------------------------------
+
+/// The running and pending tasks by a single name. A task can be pending
+/// while an earlier run of it is still running.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskDetails {
+    pub tasks: Vec<TaskInfo>,
+}
+
+impl fmt::Display for TaskDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, task) in self.tasks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            task.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ TaskInfo ------------------------------------------------------
+
+/// The details of a single queued task.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskInfo {
+    /// The unique name of the task in the queue.
+    pub name: String,
+
+    /// A human readable description of the task.
+    pub description: String,
+
+    /// The task itself, as it is stored in the queue.
+    pub task: serde_json::Value,
+
+    pub state: TaskState,
+
+    /// When a pending task is scheduled to run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<Timestamp>,
+
+    /// When a running task was started.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<Timestamp>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running_seconds: Option<i64>,
+
+    /// The number of consecutive failed runs of this task, if it is
+    /// being retried.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_attempts: Option<u32>,
+
+    /// The outcome of the last run of this task since the server started,
+    /// if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_result: Option<TaskOutcome>,
+}
+
+impl fmt::Display for TaskInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name:        {}", self.name)?;
+        writeln!(f, "Description: {}", self.description)?;
+        writeln!(f, "State:       {}", self.state)?;
+        if let Some(scheduled) = self.scheduled {
+            writeln!(f, "Scheduled:   {}", scheduled.to_rfc3339())?;
+        }
+        if let Some(started) = self.started {
+            writeln!(f, "Started:     {}", started.to_rfc3339())?;
+        }
+        if let Some(seconds) = self.running_seconds {
+            writeln!(f, "Running for: {}s", seconds)?;
+        }
+        if let Some(attempts) = self.failed_attempts {
+            writeln!(f, "Failed:      {} attempt(s)", attempts)?;
+        }
+        writeln!(f, "Task:        {}", self.task)?;
+        match &self.last_result {
+            Some(outcome) => writeln!(f, "Last result: {}", outcome)?,
+            None => writeln!(f, "Last result: none")?,
+        }
+        Ok(())
+    }
+}
+
+//------------ TaskState -----------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Pending,
+    Running,
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskState::Pending => write!(f, "pending"),
+            TaskState::Running => write!(f, "running"),
+        }
+    }
+}
+
+//------------ TaskOutcome ---------------------------------------------------
+
+/// The outcome of running a task.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskOutcome {
+    pub finished: Timestamp,
+    pub result: TaskOutcomeResult,
+}
+
+impl fmt::Display for TaskOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.result, self.finished.to_rfc3339())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskOutcomeResult {
+    Done,
+    FollowUp {
+        task: String,
+        scheduled: Timestamp,
+    },
+    Rescheduled {
+        scheduled: Timestamp,
+    },
+    Failed {
+        error: String,
+        attempts: u32,
+        retry: Timestamp,
+    },
+    DeadLettered {
+        error: String,
+        attempts: u32,
+    },
+}
+
+impl fmt::Display for TaskOutcomeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskOutcomeResult::Done => write!(f, "done"),
+            TaskOutcomeResult::FollowUp { task, scheduled } => write!(
+                f,
+                "done, follow-up '{}' scheduled for {}",
+                task,
+                scheduled.to_rfc3339()
+            ),
+            TaskOutcomeResult::Rescheduled { scheduled } => {
+                write!(f, "rescheduled for {}", scheduled.to_rfc3339())
+            }
+            TaskOutcomeResult::Failed {
+                error,
+                attempts,
+                retry,
+            } => write!(
+                f,
+                "failed (attempt {}), retry at {}: {}",
+                attempts,
+                retry.to_rfc3339(),
+                error
+            ),
+            TaskOutcomeResult::DeadLettered { error, attempts } => write!(
+                f,
+                "failed (attempt {}), moved to dead letters: {}",
+                attempts, error
+            ),
+        }
+    }
+}
+
+//------------ DeadLetterList ------------------------------------------------
+
+/// The tasks that were given up on after failing too often.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DeadLetterList {
+    pub tasks: Vec<DeadLetterTask>,
+}
+
+impl fmt::Display for DeadLetterList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tasks.is_empty() {
+            return writeln!(f, "No dead letter tasks.");
+        }
+        for (i, task) in self.tasks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            task.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ DeadLetterTask ------------------------------------------------
+
+/// A task that failed more often than its retry policy allows. It is kept
+/// until an operator requeues or discards it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DeadLetterTask {
+    pub name: String,
+    pub description: String,
+    pub task: serde_json::Value,
+    pub attempts: u32,
+    pub first_failure: Timestamp,
+    pub last_failure: Timestamp,
+    pub last_error: String,
+}
+
+impl fmt::Display for DeadLetterTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name:          {}", self.name)?;
+        writeln!(f, "Description:   {}", self.description)?;
+        writeln!(f, "Attempts:      {}", self.attempts)?;
+        writeln!(f, "First failure: {}", self.first_failure.to_rfc3339())?;
+        writeln!(f, "Last failure:  {}", self.last_failure.to_rfc3339())?;
+        writeln!(f, "Last error:    {}", self.last_error)
+    }
+}
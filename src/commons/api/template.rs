@@ -0,0 +1,226 @@
+//! Types for creating new CAs using the setup of an existing CA as a
+//! template.
+
+use std::fmt;
+
+use rpki::{
+    ca::idexchange::{CaHandle, ParentHandle, ServiceUri},
+    repository::resources::ResourceSet,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::commons::api::{AspaDefinition, CustomerAsn, RoaConfiguration};
+
+//------------ CaTemplate ----------------------------------------------------
+
+/// Describes the repository and parents of a CA, and optionally its ROA
+/// and ASPA definitions, so that new CAs can be set up in the same way.
+///
+/// New CAs always get their own ID key. The RFC 8183 exchanges with local
+/// parents and the embedded repository are done by Krill, but remote
+/// parents and repositories need to be set up by hand.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CaTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<TemplateRepository>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<TemplateParent>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roas: Vec<RoaConfiguration>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aspas: Vec<AspaDefinition>,
+}
+
+impl fmt::Display for CaTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.repository {
+            Some(repository) => writeln!(f, "Repository: {}", repository)?,
+            None => writeln!(f, "Repository: none")?,
+        }
+        writeln!(f, "Parents:")?;
+        for parent in &self.parents {
+            writeln!(f, "  {}", parent)?;
+        }
+        writeln!(f, "ROAs:")?;
+        for roa in &self.roas {
+            writeln!(f, "  {}", roa)?;
+        }
+        writeln!(f, "ASPAs:")?;
+        for aspa in &self.aspas {
+            writeln!(f, "  {}", aspa)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ TemplateRepository --------------------------------------------
+
+/// The repository used by a template CA.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum TemplateRepository {
+    /// The repository embedded in this Krill instance.
+    Embedded,
+
+    /// A remote repository, which needs an RFC 8183 exchange by hand.
+    Remote { service_uri: ServiceUri },
+}
+
+impl fmt::Display for TemplateRepository {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateRepository::Embedded => write!(f, "embedded"),
+            TemplateRepository::Remote { service_uri } => {
+                write!(f, "remote at {}", service_uri)
+            }
+        }
+    }
+}
+
+//------------ TemplateParent ------------------------------------------------
+
+/// A parent of a template CA. For local parents the resources that the
+/// template CA is entitled to are included, so that new CAs can be given
+/// the same resources.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TemplateParent {
+    pub name: ParentHandle,
+    pub service_uri: ServiceUri,
+
+    /// The local CA acting as this parent, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<CaHandle>,
+
+    #[serde(default, skip_serializing_if = "ResourceSet::is_empty")]
+    pub resources: ResourceSet,
+}
+
+impl fmt::Display for TemplateParent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.local {
+            Some(ca) => write!(
+                f,
+                "{} (local CA '{}', resources '{}')",
+                self.name, ca, self.resources
+            ),
+            None => {
+                write!(f, "{} (remote at {})", self.name, self.service_uri)
+            }
+        }
+    }
+}
+
+//------------ CertAuthFromTemplate ------------------------------------------
+
+/// Creates a new CA using a template. If resources are given, then local
+/// parents only delegate the resources in the template which are also
+/// included in this set.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CertAuthFromTemplate {
+    pub handle: CaHandle,
+    pub template: CaTemplate,
+
+    #[serde(default)]
+    pub copy_definitions: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceSet>,
+}
+
+impl fmt::Display for CertAuthFromTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "create CA '{}' from template", self.handle)
+    }
+}
+
+//------------ CertAuthClone -------------------------------------------------
+
+/// Creates a new CA using an existing CA as its template.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CertAuthClone {
+    pub handle: CaHandle,
+
+    #[serde(default)]
+    pub copy_definitions: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceSet>,
+}
+
+impl CertAuthClone {
+    pub fn into_from_template(
+        self,
+        template: CaTemplate,
+    ) -> CertAuthFromTemplate {
+        CertAuthFromTemplate {
+            handle: self.handle,
+            template,
+            copy_definitions: self.copy_definitions,
+            resources: self.resources,
+        }
+    }
+}
+
+//------------ CaTemplateReport ----------------------------------------------
+
+/// Reports what was set up for a CA created from a template, and what
+/// still needs to be done by hand.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CaTemplateReport {
+    pub handle: CaHandle,
+    pub repository_configured: bool,
+    pub parents_added: Vec<ParentHandle>,
+    pub parents_skipped: Vec<ParentHandle>,
+    pub roas_copied: Vec<RoaConfiguration>,
+    pub roas_skipped: Vec<RoaConfiguration>,
+    pub aspas_copied: Vec<CustomerAsn>,
+    pub aspas_skipped: Vec<CustomerAsn>,
+}
+
+impl CaTemplateReport {
+    pub fn new(handle: CaHandle) -> Self {
+        CaTemplateReport {
+            handle,
+            repository_configured: false,
+            parents_added: vec![],
+            parents_skipped: vec![],
+            roas_copied: vec![],
+            roas_skipped: vec![],
+            aspas_copied: vec![],
+            aspas_skipped: vec![],
+        }
+    }
+}
+
+impl fmt::Display for CaTemplateReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Created CA '{}'", self.handle)?;
+        if self.repository_configured {
+            writeln!(f, "Repository: configured")?;
+        } else {
+            writeln!(f, "Repository: needs to be configured by hand")?;
+        }
+        for parent in &self.parents_added {
+            writeln!(f, "Parent added: {}", parent)?;
+        }
+        for parent in &self.parents_skipped {
+            writeln!(f, "Parent needs to be added by hand: {}", parent)?;
+        }
+        for roa in &self.roas_copied {
+            writeln!(f, "ROA copied: {}", roa)?;
+        }
+        for roa in &self.roas_skipped {
+            writeln!(f, "ROA skipped, resources not held: {}", roa)?;
+        }
+        for customer in &self.aspas_copied {
+            writeln!(f, "ASPA copied for customer: {}", customer)?;
+        }
+        for customer in &self.aspas_skipped {
+            writeln!(f, "ASPA skipped, customer not held: {}", customer)?;
+        }
+        Ok(())
+    }
+}
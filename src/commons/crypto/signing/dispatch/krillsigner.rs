@@ -1,5 +1,6 @@
 use std::{sync::Arc, time::Duration};
 
+use bcder::Oid;
 use bytes::Bytes;
 use rpki::{
     ca::{
@@ -20,7 +21,7 @@ use rpki::{
         manifest::ManifestContent,
         roa::RoaBuilder,
         rta,
-        sigobj::SignedObjectBuilder,
+        sigobj::{SignedObject, SignedObjectBuilder},
         x509::{Serial, Time, Validity},
         Cert, Crl, Manifest, Roa,
     },
@@ -365,6 +366,21 @@ impl KrillSigner {
             .map_err(crypto::Error::signing)
     }
 
+    /// Signs an RPKI signed object with the given content type and
+    /// encoded content. This is used for object types which are not
+    /// supported by rpki-rs, such as TAKs.
+    pub fn sign_object(
+        &self,
+        content_type: Oid<Bytes>,
+        content: Bytes,
+        object_builder: SignedObjectBuilder,
+        key_id: &KeyIdentifier,
+    ) -> CryptoResult<SignedObject> {
+        object_builder
+            .finalize(content_type, content, &self.router, key_id)
+            .map_err(crypto::Error::signing)
+    }
+
     pub fn sign_rta(
         &self,
         rta_builder: &mut rta::RtaBuilder,
@@ -442,6 +442,11 @@ impl SignerMapper {
         Ok(SignerMapper { store })
     }
 
+    /// Drops the cached signer information.
+    pub fn clear_cache(&self) {
+        self.store.clear_cache();
+    }
+
     /// Record the existence of a new signer.
     ///
     /// A signer has several properties, some fixed, some modifiable. The
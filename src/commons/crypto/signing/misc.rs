@@ -1,9 +1,15 @@
 //! Support for signing mft, crl, certificates, roas..
 //! Common objects for TAs and CAs
 
+use bcder::{
+    encode::{self, PrimitiveContent, Values},
+    Mode, OctetString, Oid, Tag,
+};
+use bytes::Bytes;
 use rpki::{
     ca::{csr::RpkiCaCsr, provisioning::RequestResourceLimit},
-    crypto::{KeyIdentifier, PublicKey},
+    crypto::{DigestAlgorithm, KeyIdentifier, PublicKey},
+    oid,
     repository::{
         cert::{KeyUsage, Overclaim, TbsCert},
         resources::ResourceSet,
@@ -151,9 +157,11 @@ impl SignSupport {
         })
     }
 
-    /// Create an EE certificate for use in ResourceTaggedAttestations.
-    /// Note that for RPKI signed objects such as ROAs and Manifests, the
-    /// EE certificate is created by the rpki.rs library instead.
+    /// Create an EE certificate for use in ResourceTaggedAttestations,
+    /// RPKI Signed Checklists and signed geofeeds, i.e. without a Subject
+    /// Information Access extension. Note that for RPKI signed objects such as ROAs and
+    /// Manifests, the EE certificate is created by the rpki.rs library
+    /// instead.
     pub fn make_rta_ee_cert(
         resources: &ResourceSet,
         signing_key: &CertifiedKey,
@@ -170,6 +178,98 @@ impl SignSupport {
         Ok(cert)
     }
 
+    /// Signs content with a one-off key, and returns the encoded CMS
+    /// signed data together with its EE certificate. The EE certificate
+    /// is made using [`Self::make_rta_ee_cert`].
+    ///
+    /// If `detached` is true then the content itself is left out of the
+    /// CMS, as is done for signed geofeeds (RFC 9632).
+    pub fn sign_one_off_cms(
+        content_type: Oid<&[u8]>,
+        content: &[u8],
+        detached: bool,
+        resources: &ResourceSet,
+        validity: Validity,
+        signing_key: &CertifiedKey,
+        signer: &KrillSigner,
+    ) -> KrillResult<(Bytes, Cert)> {
+        let digest_algorithm = DigestAlgorithm::default();
+
+        // The signed attributes must be a DER SET OF, so they need to be
+        // sorted by their encoding.
+        let message_digest = digest_algorithm.digest(content);
+        let mut signed_attrs = [
+            encode::sequence((
+                oid::CONTENT_TYPE.encode(),
+                encode::set(content_type.encode_ref()),
+            ))
+            .to_captured(Mode::Der),
+            encode::sequence((
+                oid::MESSAGE_DIGEST.encode(),
+                encode::set(OctetString::encode_slice(
+                    message_digest.as_ref(),
+                )),
+            ))
+            .to_captured(Mode::Der),
+        ];
+        signed_attrs.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        let [first_attr, second_attr] = signed_attrs;
+
+        let (signature, pub_key) = signer.sign_one_off(
+            encode::set((&first_attr, &second_attr))
+                .to_captured(Mode::Der)
+                .as_slice(),
+        )?;
+
+        let ee = Self::make_rta_ee_cert(
+            resources,
+            signing_key,
+            validity,
+            pub_key,
+            signer,
+        )?;
+
+        let encap_content = if detached {
+            None
+        } else {
+            Some(encode::sequence_as(
+                Tag::CTX_0,
+                OctetString::encode_slice(content),
+            ))
+        };
+
+        let bytes = encode::sequence((
+            oid::SIGNED_DATA.encode(),
+            encode::sequence_as(
+                Tag::CTX_0,
+                encode::sequence((
+                    3u8.encode(),
+                    digest_algorithm.encode_set(),
+                    encode::sequence((
+                        content_type.encode_ref(),
+                        encap_content,
+                    )),
+                    encode::sequence_as(Tag::CTX_0, ee.encode_ref()),
+                    encode::set(encode::sequence((
+                        3u8.encode(),
+                        ee.subject_key_identifier().encode_ref_as(Tag::CTX_0),
+                        digest_algorithm.encode(),
+                        encode::sequence_as(
+                            Tag::CTX_0,
+                            (&first_attr, &second_attr),
+                        ),
+                        signature.algorithm().cms_encode(),
+                        OctetString::encode_slice(signature.value().as_ref()),
+                    ))),
+                )),
+            ),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes();
+
+        Ok((bytes, ee))
+    }
+
     fn make_tbs_cert(
         resources: &ResourceSet,
         signing_cert: &ReceivedCert,
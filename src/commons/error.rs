@@ -12,7 +12,7 @@ use rpki::{
         publication,
     },
     crypto::KeyIdentifier,
-    repository::error::ValidationError,
+    repository::{error::ValidationError, resources::ResourceSet, x509::Time},
     uri,
 };
 
@@ -20,7 +20,7 @@ use crate::{
     commons::{
         api::{
             rrdp::PublicationDeltaError, CustomerAsn, ErrorResponse,
-            RoaPayload,
+            RepositoryMirrorName, ResourcePoolName, RoaPayload, RscName,
         },
         crypto::SignerError,
         eventsourcing::{AggregateStoreError, KeyValueError},
@@ -32,7 +32,7 @@ use crate::{
 };
 
 use super::{
-    api::{BgpSecAsnKey, BgpSecDefinition, RoaConfiguration},
+    api::{BgpSecAsnKey, BgpSecDefinition, KeyRollPolicy, RoaConfiguration},
     eventsourcing::WalStoreError,
 };
 
@@ -200,6 +200,7 @@ pub enum Error {
     HttpClientError(httpclient::Error),
     ConfigError(String),
     UpgradeError(UpgradeError),
+    HaNotLeader(Option<String>),
 
     //-----------------------------------------------------------------
     // General API Client Issues
@@ -253,12 +254,20 @@ pub enum Error {
     //-----------------------------------------------------------------
     CaDuplicate(CaHandle),
     CaUnknown(CaHandle),
+    CaIssuanceTimingInvalid(CaHandle, String),
+    CaRenameNotPossible(CaHandle, String),
+    CaAliasInUse(CaHandle, CaHandle),
 
     // CA Repo Issues
     CaRepoInUse(CaHandle),
     CaRepoIssue(CaHandle, String),
     CaRepoResponseInvalid(CaHandle, String),
     CaRepoResponseWrongXml(CaHandle),
+    CaRepoMirrorUnknown(CaHandle, RepositoryMirrorName),
+    CaRepoMirrorInvalid(CaHandle, RepositoryMirrorName, String),
+    CaRepoMigrationNone(CaHandle),
+    CaRepoMigrationInProgress(CaHandle),
+    CaRepoMigrationNotAbortable(CaHandle),
 
     // CA Parent Issues
     CaParentDuplicateName(CaHandle, ParentHandle),
@@ -290,6 +299,20 @@ pub enum Error {
     CaChildMustHaveResources(CaHandle, ChildHandle),
     CaChildExtraResources(CaHandle, ChildHandle),
     CaChildUnauthorized(CaHandle, ChildHandle),
+    CaChildCertTimingInvalid(CaHandle, ChildHandle, String),
+    CaChildResourcesOverlap(CaHandle, ChildHandle, ChildHandle, ResourceSet),
+
+    //-----------------------------------------------------------------
+    // Resource pools
+    //-----------------------------------------------------------------
+    CaResourcePoolUnknown(CaHandle, ResourcePoolName),
+    CaResourcePoolInvalid(CaHandle, ResourcePoolName, String),
+    CaResourcePoolAllocationFailed(
+        CaHandle,
+        ChildHandle,
+        ResourcePoolName,
+        String,
+    ),
 
     //-----------------------------------------------------------------
     // RouteAuthorizations - ROAs
@@ -328,6 +351,8 @@ pub enum Error {
     KeyUseNoMatch(KeyIdentifier),
     KeyRollInProgress,
     KeyRollActivatePendingRequests,
+    KeyRollStagingTooShort(u32),
+    KeyRollNotCertified(ParentHandle, ResourceClassName),
 
     //-----------------------------------------------------------------
     // Resource Issues
@@ -350,12 +375,30 @@ pub enum Error {
     TaProxyHasNoRequest,
     TaProxyHasRequest,
     TaProxyRequestNonceMismatch(ta::Nonce, ta::Nonce),
+    TaKeyRollInProgress,
+    TaKeyRollNotInProgress,
+    TaKeyRollNotReady(Time),
 
     //-----------------------------------------------------------------
     // Resource Tagged Attestation issues
     //-----------------------------------------------------------------
     RtaResourcesNotHeld,
 
+    //-----------------------------------------------------------------
+    // RPKI Signed Checklist issues
+    //-----------------------------------------------------------------
+    RscUnknown(RscName),
+    RscAlreadyExists(RscName),
+    RscResourcesNotHeld,
+    RscInvalidChecklist(String),
+
+    //-----------------------------------------------------------------
+    // Signed geofeed issues
+    //-----------------------------------------------------------------
+    GeofeedUnknown(u64),
+    GeofeedPrefixesNotHeld(String),
+    GeofeedInvalid(String),
+
     //-----------------------------------------------------------------
     // If we really don't know any more..
     //-----------------------------------------------------------------
@@ -378,6 +421,8 @@ impl fmt::Display for Error {
             Error::HttpClientError(e) => write!(f, "HTTP client error: {}", e),
             Error::ConfigError(e) => write!(f, "Configuration error: {}", e),
             Error::UpgradeError(e) => write!(f, "Could not upgrade Krill: {}", e),
+            Error::HaNotLeader(Some(leader)) => write!(f, "This Krill node is on standby, changes must be sent to the leader '{}'", leader),
+            Error::HaNotLeader(None) => write!(f, "This Krill node is on standby and there is currently no leader, please try again later"),
 
             //-----------------------------------------------------------------
             // General API Client Issues
@@ -433,6 +478,9 @@ impl fmt::Display for Error {
             //-----------------------------------------------------------------
             Error::CaDuplicate(ca) => write!(f, "CA '{}' was already initialized", ca),
             Error::CaUnknown(ca) => write!(f, "CA '{}' is unknown", ca),
+            Error::CaIssuanceTimingInvalid(ca, msg) => write!(f, "Invalid issuance timing for CA '{}': {}", ca, msg),
+            Error::CaRenameNotPossible(ca, msg) => write!(f, "CA '{}' cannot be renamed: {}", ca, msg),
+            Error::CaAliasInUse(alias, ca) => write!(f, "Name '{}' is in use as an alias for CA '{}'", alias, ca),
 
             // CA Repo Issues
             Error::CaRepoInUse(ca) => write!(f, "CA '{}' already uses this repository", ca),
@@ -440,6 +488,11 @@ impl fmt::Display for Error {
             re-install of Krill you will need to send XML to all other parties again: parent(s), children, and repository", ca,        e),
             Error::CaRepoResponseInvalid(ca, e) => write!(f, "CA '{}' got invalid repository response: {}", ca, e),
             Error::CaRepoResponseWrongXml(ca) => write!(f, "CA '{}' got parent instead of repository response", ca),
+            Error::CaRepoMirrorUnknown(ca, mirror) => write!(f, "CA '{}' does not have a repository mirror named '{}'", ca, mirror),
+            Error::CaRepoMirrorInvalid(ca, mirror, msg) => write!(f, "Invalid repository mirror '{}' for CA '{}': {}", mirror, ca, msg),
+            Error::CaRepoMigrationNone(ca) => write!(f, "CA '{}' is not migrating to a new repository", ca),
+            Error::CaRepoMigrationInProgress(ca) => write!(f, "CA '{}' is already migrating to a new repository", ca),
+            Error::CaRepoMigrationNotAbortable(ca) => write!(f, "CA '{}' has requested certificates for the new repository, the migration can no longer be aborted", ca),
 
             // CA Parent Issues
             Error::CaParentDuplicateName(ca, parent) => write!(f, "CA '{}' already has a parent named '{}'", ca, parent),
@@ -480,6 +533,15 @@ impl fmt::Display for Error {
             Error::CaChildMustHaveResources(ca, child) => write!(f, "Child '{}' for CA '{}' MUST have resources specified", child, ca),
             Error::CaChildExtraResources(ca, child) => write!(f, "Child '{}' cannot have resources not held by CA '{}'", child, ca),
             Error::CaChildUnauthorized(ca, child) => write!(f, "CA '{}' does not know id certificate for child '{}'", ca, child),
+            Error::CaChildCertTimingInvalid(ca, child, msg) => write!(f, "Invalid certificate timing for child '{}' under CA '{}': {}", child, ca, msg),
+            Error::CaChildResourcesOverlap(ca, child, other, resources) => write!(f, "Child '{}' under CA '{}' cannot get resources in a resource pool that are delegated to child '{}': {}", child, ca, other, resources),
+
+            //-----------------------------------------------------------------
+            // Resource pools
+            //-----------------------------------------------------------------
+            Error::CaResourcePoolUnknown(ca, pool) => write!(f, "CA '{}' does not have a resource pool named '{}'", ca, pool),
+            Error::CaResourcePoolInvalid(ca, pool, msg) => write!(f, "Invalid resource pool '{}' for CA '{}': {}", pool, ca, msg),
+            Error::CaResourcePoolAllocationFailed(ca, child, pool, msg) => write!(f, "Cannot allocate resources from pool '{}' of CA '{}' to child '{}': {}", pool, ca, child, msg),
 
             //-----------------------------------------------------------------
             // RouteAuthorizations - ROAs
@@ -519,6 +581,8 @@ impl fmt::Display for Error {
             Error::KeyUseNoMatch(ki) => write!(f, "No key found matching key identifier: '{}'", ki),
             Error::KeyRollInProgress => write!(f, "Key roll in progress"),
             Error::KeyRollActivatePendingRequests => write!(f, "Cannot activate key while there are still pending requests."),
+            Error::KeyRollStagingTooShort(hours) => write!(f, "Key roll staging period of {} hours is too short, RFC 6489 requires at least {} hours", hours, KeyRollPolicy::MIN_STAGING_HOURS),
+            Error::KeyRollNotCertified(parent, rcn) => write!(f, "Parent '{}' did not certify the new key for resource class '{}' yet, please try again later", parent, rcn),
 
             //-----------------------------------------------------------------
             // Resource Issues
@@ -542,12 +606,30 @@ impl fmt::Display for Error {
             Error::TaProxyHasNoRequest => write!(f, "Trust Anchor Proxy has no signer request"),
             Error::TaProxyHasRequest => write!(f, "Trust Anchor Proxy already has signer request"),
             Error::TaProxyRequestNonceMismatch(rcvd, expected) => write!(f, "Trust Anchor Response nonce '{}' does not match open Request nonce '{}'", rcvd, expected),
+            Error::TaKeyRollInProgress => write!(f, "Trust Anchor key roll already in progress"),
+            Error::TaKeyRollNotInProgress => write!(f, "Trust Anchor has no key roll in progress"),
+            Error::TaKeyRollNotReady(time) => write!(f, "Trust Anchor successor key cannot be activated before {}", time.to_rfc3339()),
 
             //-----------------------------------------------------------------
             // Resource Tagged Attestation issues
             //-----------------------------------------------------------------
             Error::RtaResourcesNotHeld => write!(f, "Your CA does not hold the requested resources"),
 
+            //-----------------------------------------------------------------
+            // RPKI Signed Checklist issues
+            //-----------------------------------------------------------------
+            Error::RscUnknown(name) => write!(f, "Unknown RSC '{}'", name),
+            Error::RscAlreadyExists(name) => write!(f, "RSC with name '{}' already exists", name),
+            Error::RscResourcesNotHeld => write!(f, "Your CA does not hold the requested resources"),
+            Error::RscInvalidChecklist(msg) => write!(f, "Invalid RSC request: {}", msg),
+
+            //-----------------------------------------------------------------
+            // Signed geofeed issues
+            //-----------------------------------------------------------------
+            Error::GeofeedUnknown(number) => write!(f, "Unknown signed geofeed '{}'", number),
+            Error::GeofeedPrefixesNotHeld(prefixes) => write!(f, "Prefixes in the geofeed are not held: {}", prefixes),
+            Error::GeofeedInvalid(msg) => write!(f, "Invalid geofeed: {}", msg),
+
             //-----------------------------------------------------------------
             // If we really don't know any more..
             //-----------------------------------------------------------------
@@ -707,6 +789,9 @@ impl Error {
             Error::PublisherUnknown(_)
             | Error::CaUnknown(_)
             | Error::CaChildUnknown(_, _)
+            | Error::CaResourcePoolUnknown(_, _)
+            | Error::CaRepoMirrorUnknown(_, _)
+            | Error::CaRepoMigrationNone(_)
             | Error::CaParentUnknown(_, _)
             | Error::ApiUnknownResource => StatusCode::NOT_FOUND,
 
@@ -716,6 +801,7 @@ impl Error {
             | Error::ApiAuthSessionExpired(_)
             | Error::ApiLoginError(_) => StatusCode::UNAUTHORIZED,
             Error::ApiInsufficientRights(_) => StatusCode::FORBIDDEN,
+            Error::HaNotLeader(_) => StatusCode::SERVICE_UNAVAILABLE,
 
             _ => StatusCode::BAD_REQUEST,
         }
@@ -772,6 +858,15 @@ impl Error {
                 ErrorResponse::new("sys-upgrade", self).with_cause(e)
             }
 
+            // service unavailable
+            Error::HaNotLeader(leader) => {
+                let response = ErrorResponse::new("sys-ha-standby", self);
+                match leader {
+                    Some(leader) => response.with_leader(leader),
+                    None => response,
+                }
+            }
+
             //-----------------------------------------------------------------
             // General API Client Issues (label: api-*)
             //-----------------------------------------------------------------
@@ -911,6 +1006,21 @@ impl Error {
                 ErrorResponse::new("ca-unknown", self).with_ca(ca)
             }
 
+            Error::CaIssuanceTimingInvalid(ca, _) => {
+                ErrorResponse::new("ca-issuance-timing-invalid", self)
+                    .with_ca(ca)
+            }
+
+            Error::CaRenameNotPossible(ca, _) => {
+                ErrorResponse::new("ca-rename-not-possible", self).with_ca(ca)
+            }
+
+            Error::CaAliasInUse(alias, ca) => {
+                ErrorResponse::new("ca-alias-in-use", self)
+                    .with_ca(ca)
+                    .with_alias(alias)
+            }
+
             Error::CaRepoInUse(ca) => {
                 ErrorResponse::new("ca-repo-same", self).with_ca(ca)
             }
@@ -932,6 +1042,32 @@ impl Error {
                     .with_ca(ca)
             }
 
+            Error::CaRepoMirrorUnknown(ca, mirror) => {
+                ErrorResponse::new("ca-repo-mirror-unknown", self)
+                    .with_ca(ca)
+                    .with_mirror(mirror)
+            }
+
+            Error::CaRepoMirrorInvalid(ca, mirror, _) => {
+                ErrorResponse::new("ca-repo-mirror-invalid", self)
+                    .with_ca(ca)
+                    .with_mirror(mirror)
+            }
+
+            Error::CaRepoMigrationNone(ca) => {
+                ErrorResponse::new("ca-repo-migration-none", self).with_ca(ca)
+            }
+
+            Error::CaRepoMigrationInProgress(ca) => {
+                ErrorResponse::new("ca-repo-migration-in-progress", self)
+                    .with_ca(ca)
+            }
+
+            Error::CaRepoMigrationNotAbortable(ca) => {
+                ErrorResponse::new("ca-repo-migration-not-abortable", self)
+                    .with_ca(ca)
+            }
+
             Error::CaParentDuplicateName(ca, parent) => {
                 ErrorResponse::new("ca-parent-duplicate", self)
                     .with_ca(ca)
@@ -1028,6 +1164,34 @@ impl Error {
                     .with_ca(ca)
                     .with_child(child)
             }
+            Error::CaChildCertTimingInvalid(ca, child, _) => {
+                ErrorResponse::new("ca-child-cert-timing-invalid", self)
+                    .with_ca(ca)
+                    .with_child(child)
+            }
+            Error::CaChildResourcesOverlap(ca, child, _, _) => {
+                ErrorResponse::new("ca-child-resources-overlap", self)
+                    .with_ca(ca)
+                    .with_child(child)
+            }
+
+            // Resource pools
+            Error::CaResourcePoolUnknown(ca, pool) => {
+                ErrorResponse::new("ca-resource-pool-unknown", self)
+                    .with_ca(ca)
+                    .with_pool(pool)
+            }
+            Error::CaResourcePoolInvalid(ca, pool, _) => {
+                ErrorResponse::new("ca-resource-pool-invalid", self)
+                    .with_ca(ca)
+                    .with_pool(pool)
+            }
+            Error::CaResourcePoolAllocationFailed(ca, child, pool, _) => {
+                ErrorResponse::new("ca-resource-pool-allocation-failed", self)
+                    .with_ca(ca)
+                    .with_child(child)
+                    .with_pool(pool)
+            }
 
             // RouteAuthorizations
             Error::CaAuthorizationUnknown(ca, auth) => {
@@ -1142,6 +1306,14 @@ impl Error {
             Error::KeyRollActivatePendingRequests => {
                 ErrorResponse::new("key-roll-pending-requests", self)
             }
+            Error::KeyRollStagingTooShort(_) => {
+                ErrorResponse::new("key-roll-staging-too-short", self)
+            }
+            Error::KeyRollNotCertified(parent, rcn) => {
+                ErrorResponse::new("key-roll-not-certified", self)
+                    .with_parent(parent)
+                    .with_resource_class(rcn)
+            }
 
             //-----------------------------------------------------------------
             // Resource Issues (label: rc-*)
@@ -1191,6 +1363,15 @@ impl Error {
             Error::TaProxyRequestNonceMismatch(_rcvd, _expected) => {
                 ErrorResponse::new("ta-proxy-response-nonce", self)
             }
+            Error::TaKeyRollInProgress => {
+                ErrorResponse::new("ta-key-roll-in-progress", self)
+            }
+            Error::TaKeyRollNotInProgress => {
+                ErrorResponse::new("ta-no-key-roll", self)
+            }
+            Error::TaKeyRollNotReady(_) => {
+                ErrorResponse::new("ta-key-roll-not-ready", self)
+            }
 
             //-----------------------------------------------------------------
             // Resource Tagged Attestation issues
@@ -1199,6 +1380,38 @@ impl Error {
                 ErrorResponse::new("rta-resources-not-held", self)
             }
 
+            //-----------------------------------------------------------------
+            // RPKI Signed Checklist issues
+            //-----------------------------------------------------------------
+            Error::RscUnknown(name) => {
+                ErrorResponse::new("rsc-unknown", self).with_rsc(name)
+            }
+            Error::RscAlreadyExists(name) => {
+                ErrorResponse::new("rsc-duplicate", self).with_rsc(name)
+            }
+            Error::RscResourcesNotHeld => {
+                ErrorResponse::new("rsc-resources-not-held", self)
+            }
+            Error::RscInvalidChecklist(msg) => {
+                ErrorResponse::new("rsc-invalid-request", self)
+                    .with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // Signed geofeed issues
+            //-----------------------------------------------------------------
+            Error::GeofeedUnknown(number) => {
+                ErrorResponse::new("geofeed-unknown", self)
+                    .with_geofeed(*number)
+            }
+            Error::GeofeedPrefixesNotHeld(prefixes) => {
+                ErrorResponse::new("geofeed-prefixes-not-held", self)
+                    .with_prefixes(prefixes)
+            }
+            Error::GeofeedInvalid(msg) => {
+                ErrorResponse::new("geofeed-invalid", self).with_cause(msg)
+            }
+
             //-----------------------------------------------------------------
             // If we really don't know any more..
             //-----------------------------------------------------------------
@@ -1318,6 +1531,10 @@ mod tests {
             include_str!("../../test-resources/errors/sys-https.json"),
             Error::HttpsSetup("can't find pem file".to_string()),
         );
+        verify(
+            include_str!("../../test-resources/errors/sys-ha-standby.json"),
+            Error::HaNotLeader(Some("krill-b".to_string())),
+        );
         verify(
             include_str!("../../test-resources/errors/sys-http-client.json"),
             Error::HttpClientError(httpclient::Error::forbidden(
@@ -1413,6 +1630,31 @@ mod tests {
             include_str!("../../test-resources/errors/ca-unknown.json"),
             Error::CaUnknown(ca.clone()),
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-issuance-timing-invalid.json"
+            ),
+            Error::CaIssuanceTimingInvalid(
+                ca.clone(),
+                "timing_roa_valid_weeks must be at least 2".to_string(),
+            ),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-rename-not-possible.json"
+            ),
+            Error::CaRenameNotPossible(
+                ca.clone(),
+                "a key roll is in progress".to_string(),
+            ),
+        );
+        verify(
+            include_str!("../../test-resources/errors/ca-alias-in-use.json"),
+            Error::CaAliasInUse(
+                CaHandle::from_str("old").unwrap(),
+                ca.clone(),
+            ),
+        );
 
         verify(
             include_str!("../../test-resources/errors/ca-repo-same.json"),
@@ -1432,6 +1674,41 @@ mod tests {
             ),
             Error::CaRepoResponseWrongXml(ca.clone()),
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-repo-mirror-unknown.json"
+            ),
+            Error::CaRepoMirrorUnknown(ca.clone(), "mirror".to_string()),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-repo-mirror-invalid.json"
+            ),
+            Error::CaRepoMirrorInvalid(
+                ca.clone(),
+                "mirror".to_string(),
+                "the base URI differs from the primary repository"
+                    .to_string(),
+            ),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-repo-migration-none.json"
+            ),
+            Error::CaRepoMigrationNone(ca.clone()),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-repo-migration-in-progress.json"
+            ),
+            Error::CaRepoMigrationInProgress(ca.clone()),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-repo-migration-not-abortable.json"
+            ),
+            Error::CaRepoMigrationNotAbortable(ca.clone()),
+        );
 
         verify(
             include_str!(
@@ -1499,6 +1776,55 @@ mod tests {
             ),
             Error::CaChildUnauthorized(ca.clone(), child),
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-child-cert-timing-invalid.json"
+            ),
+            Error::CaChildCertTimingInvalid(
+                ca.clone(),
+                ChildHandle::from_str("child").unwrap(),
+                "re-issue time must be shorter than validity".to_string(),
+            ),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-child-resources-overlap.json"
+            ),
+            Error::CaChildResourcesOverlap(
+                ca.clone(),
+                ChildHandle::from_str("child").unwrap(),
+                ChildHandle::from_str("other").unwrap(),
+                ResourceSet::from_strs("", "10.0.0.0/24", "").unwrap(),
+            ),
+        );
+
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-resource-pool-unknown.json"
+            ),
+            Error::CaResourcePoolUnknown(ca.clone(), "pool".to_string()),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-resource-pool-invalid.json"
+            ),
+            Error::CaResourcePoolInvalid(
+                ca.clone(),
+                "pool".to_string(),
+                "resources are not held by the CA".to_string(),
+            ),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ca-resource-pool-allocation-failed.json"
+            ),
+            Error::CaResourcePoolAllocationFailed(
+                ca.clone(),
+                ChildHandle::from_str("child").unwrap(),
+                "pool".to_string(),
+                "no free IPv4 /24 left".to_string(),
+            ),
+        );
 
         verify(
             include_str!("../../test-resources/errors/ca-roa-unknown.json"),
@@ -1549,6 +1875,21 @@ mod tests {
             include_str!("../../test-resources/errors/key-no-match.json"),
             Error::KeyUseNoMatch(ki),
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/key-roll-staging-too-short.json"
+            ),
+            Error::KeyRollStagingTooShort(12),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/key-roll-not-certified.json"
+            ),
+            Error::KeyRollNotCertified(
+                ParentHandle::from_str("parent").unwrap(),
+                ResourceClassName::from("RC0"),
+            ),
+        );
 
         verify(
             include_str!("../../test-resources/errors/rc-unknown.json"),
@@ -1573,6 +1914,60 @@ mod tests {
             include_str!("../../test-resources/errors/ta-initialized.json"),
             Error::TaAlreadyInitialized,
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ta-key-roll-in-progress.json"
+            ),
+            Error::TaKeyRollInProgress,
+        );
+        verify(
+            include_str!("../../test-resources/errors/ta-no-key-roll.json"),
+            Error::TaKeyRollNotInProgress,
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/ta-key-roll-not-ready.json"
+            ),
+            Error::TaKeyRollNotReady(Time::utc(2026, 11, 1, 0, 0, 0)),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/rsc-unknown.json"),
+            Error::RscUnknown("checklist".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/rsc-duplicate.json"),
+            Error::RscAlreadyExists("checklist".to_string()),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/rsc-resources-not-held.json"
+            ),
+            Error::RscResourcesNotHeld,
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/rsc-invalid-request.json"
+            ),
+            Error::RscInvalidChecklist("the checklist is empty".to_string()),
+        );
+
+        verify(
+            include_str!("../../test-resources/errors/geofeed-unknown.json"),
+            Error::GeofeedUnknown(3),
+        );
+        verify(
+            include_str!(
+                "../../test-resources/errors/geofeed-prefixes-not-held.json"
+            ),
+            Error::GeofeedPrefixesNotHeld("10.0.0.0/24".to_string()),
+        );
+        verify(
+            include_str!("../../test-resources/errors/geofeed-invalid.json"),
+            Error::GeofeedInvalid(
+                "the geofeed contains no entries".to_string(),
+            ),
+        );
 
         verify(
             include_str!("../../test-resources/errors/general-error.json"),
@@ -0,0 +1,210 @@
+//! Consistency checks for stored aggregates and WAL-supported types.
+
+use std::fmt;
+
+use chrono::DateTime;
+use rpki::ca::idexchange::MyHandle;
+
+//------------ StorageIssue --------------------------------------------------
+
+/// An inconsistency found in the stored data for a single instance.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StorageIssue {
+    /// A command (or change set) is missing, while later ones exist.
+    VersionGap { missing: u64, next: u64 },
+
+    /// The state could not be rebuilt from the stored commands or change
+    /// sets.
+    ReplayFailed(String),
+
+    /// The snapshot could not be parsed.
+    SnapshotCorrupt(String),
+
+    /// The snapshot has a later version than can be rebuilt from the
+    /// stored commands.
+    SnapshotAhead { snapshot: u64, replayed: u64 },
+
+    /// The snapshot differs from the state rebuilt from the stored commands
+    /// for the same version.
+    SnapshotMismatch { version: u64 },
+
+    /// A change set is stored for a revision that is already included in
+    /// the snapshot.
+    StaleChangeSet { revision: u64 },
+
+    /// A change set claims a different revision than the one it is stored
+    /// under.
+    ChangeSetMisplaced { key: u64, revision: u64 },
+}
+
+impl StorageIssue {
+    /// Returns whether the issue can be repaired by rebuilding the
+    /// snapshot.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            StorageIssue::SnapshotCorrupt(_)
+                | StorageIssue::SnapshotAhead { .. }
+                | StorageIssue::SnapshotMismatch { .. }
+                | StorageIssue::StaleChangeSet { .. }
+        )
+    }
+}
+
+impl fmt::Display for StorageIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageIssue::VersionGap { missing, next } => write!(
+                f,
+                "version {} is missing, but version {} exists",
+                missing, next
+            ),
+            StorageIssue::ReplayFailed(e) => {
+                write!(f, "cannot rebuild state: {}", e)
+            }
+            StorageIssue::SnapshotCorrupt(e) => {
+                write!(f, "snapshot is corrupt: {}", e)
+            }
+            StorageIssue::SnapshotAhead { snapshot, replayed } => write!(
+                f,
+                "snapshot has version {}, but history only has version {}",
+                snapshot, replayed
+            ),
+            StorageIssue::SnapshotMismatch { version } => write!(
+                f,
+                "snapshot does not match the history at version {}",
+                version
+            ),
+            StorageIssue::StaleChangeSet { revision } => write!(
+                f,
+                "change set for revision {} is included in the snapshot",
+                revision
+            ),
+            StorageIssue::ChangeSetMisplaced { key, revision } => write!(
+                f,
+                "change set stored as revision {} is for revision {}",
+                key, revision
+            ),
+        }
+    }
+}
+
+//------------ StorageCheck --------------------------------------------------
+
+/// The result of checking the stored data for a single instance.
+#[derive(Clone, Debug)]
+pub struct StorageCheck {
+    pub handle: MyHandle,
+    pub issues: Vec<StorageIssue>,
+
+    /// Whether the issues were repaired.
+    pub repaired: bool,
+}
+
+impl StorageCheck {
+    pub fn new(handle: MyHandle) -> Self {
+        StorageCheck {
+            handle,
+            issues: vec![],
+            repaired: false,
+        }
+    }
+
+    pub fn add(&mut self, issue: StorageIssue) {
+        self.issues.push(issue);
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns whether all issues can be repaired.
+    pub fn is_repairable(&self) -> bool {
+        self.issues.iter().all(StorageIssue::is_repairable)
+    }
+}
+
+//------------ equivalent_values ---------------------------------------------
+
+/// Returns whether two stored values are equivalent.
+///
+/// Timestamps are not compared. Some aggregates record the time at which
+/// an event was applied, e.g. the time of the last key change in a resource
+/// class, and these will differ when an aggregate is rebuilt from history.
+pub(super) fn equivalent_values(
+    left: &serde_json::Value,
+    right: &serde_json::Value,
+) -> bool {
+    use serde_json::Value;
+
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            left.len() == right.len()
+                && left.iter().all(|(key, value)| {
+                    right
+                        .get(key)
+                        .map(|other| equivalent_values(value, other))
+                        .unwrap_or(false)
+                })
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right.iter())
+                    .all(|(value, other)| equivalent_values(value, other))
+        }
+        (Value::String(left), Value::String(right)) => {
+            left == right
+                || (DateTime::parse_from_rfc3339(left).is_ok()
+                    && DateTime::parse_from_rfc3339(right).is_ok())
+        }
+        _ => left == right,
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn equivalent_values_ignore_timestamps() {
+        let left = serde_json::json!({
+            "name": "ca",
+            "keys": [{ "last_change": "2024-01-01T00:00:00Z" }],
+        });
+        let right = serde_json::json!({
+            "name": "ca",
+            "keys": [{ "last_change": "2025-06-01T12:00:00+02:00" }],
+        });
+        assert!(equivalent_values(&left, &right));
+
+        let other = serde_json::json!({
+            "name": "other",
+            "keys": [{ "last_change": "2024-01-01T00:00:00Z" }],
+        });
+        assert!(!equivalent_values(&left, &other));
+
+        let missing = serde_json::json!({ "name": "ca" });
+        assert!(!equivalent_values(&left, &missing));
+    }
+
+    #[test]
+    fn only_snapshot_issues_are_repairable() {
+        let mut check = StorageCheck::new(MyHandle::from_str("ca").unwrap());
+        check.add(StorageIssue::SnapshotMismatch { version: 2 });
+        check.add(StorageIssue::StaleChangeSet { revision: 1 });
+        assert!(check.is_repairable());
+
+        check.add(StorageIssue::VersionGap {
+            missing: 1,
+            next: 2,
+        });
+        assert!(!check.is_repairable());
+        assert!(!StorageIssue::ReplayFailed("oops".into()).is_repairable());
+    }
+}
@@ -0,0 +1,652 @@
+//! Optional encryption at rest for values in a [`KeyValueStore`].
+//!
+//! When storage keys are registered for a storage URI, all stores created
+//! for that URI encrypt every value they write using AES-256-GCM with the
+//! current key. Each encrypted value records the identifier of the key
+//! that was used, so that values written with a previous key can still be
+//! read after the current key was rotated.
+//!
+//! The namespace, scope and name of the key under which a value is stored
+//! are used as additional authenticated data, so that an encrypted value
+//! cannot be swapped with the value of another key. Values are therefore
+//! re-encrypted when they are moved to another key or namespace.
+//!
+//! Values that are not encrypted are only read, as is, if this is allowed
+//! explicitly. This is meant for migrating existing data when encryption
+//! is first enabled.
+//!
+//! Re-storing a value encrypts it with the current key. This is used by
+//! [`KeyValueStore::reencrypt`] to rotate keys in place.
+//!
+//! [`KeyValueStore`]: super::KeyValueStore
+//! [`KeyValueStore::reencrypt`]: super::KeyValueStore::reencrypt
+
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Deref,
+    str::FromStr,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use base64::engine::{
+    general_purpose::STANDARD as BASE64_ENGINE, Engine as _,
+};
+use kvx::{
+    Key, KeyValueStoreBackend, Namespace, NamespaceBuf,
+    PubKeyValueStoreBackend, ReadStore, Scope, WriteStore,
+};
+use openssl::{
+    rand::rand_bytes,
+    sha::sha256,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+type Result<T> = std::result::Result<T, kvx::Error>;
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+//------------ StorageKey ----------------------------------------------------
+
+/// A key used to encrypt stored values.
+#[derive(Clone)]
+pub struct StorageKey {
+    id: String,
+    key: [u8; KEY_SIZE],
+}
+
+impl StorageKey {
+    /// Parses a base64 encoded 256-bit key, e.g. as generated using
+    /// `openssl rand -base64 32`.
+    ///
+    /// The identifier of the key is derived from the key itself.
+    pub fn from_base64(s: &str) -> std::result::Result<Self, String> {
+        let bytes = BASE64_ENGINE
+            .decode(s.trim())
+            .map_err(|e| format!("invalid base64: {}", e))?;
+        let key: [u8; KEY_SIZE] = bytes.try_into().map_err(|_| {
+            format!("a storage key must be {} bytes", KEY_SIZE)
+        })?;
+        let id = hex::encode(&sha256(&key)[..8]);
+        Ok(StorageKey { id, key })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never show the key itself.
+        write!(f, "StorageKey({})", self.id)
+    }
+}
+
+//------------ StorageKeys ---------------------------------------------------
+
+/// The keys used to encrypt and decrypt stored values.
+#[derive(Clone, Debug)]
+pub struct StorageKeys {
+    /// The key used to encrypt values.
+    current: StorageKey,
+
+    /// Keys that are only used to decrypt values written before the
+    /// current key was introduced.
+    previous: Vec<StorageKey>,
+
+    /// Whether values that are not encrypted may be read.
+    allow_plaintext: bool,
+}
+
+impl StorageKeys {
+    pub fn new(
+        current: StorageKey,
+        previous: Vec<StorageKey>,
+        allow_plaintext: bool,
+    ) -> Self {
+        StorageKeys {
+            current,
+            previous,
+            allow_plaintext,
+        }
+    }
+
+    pub fn current(&self) -> &StorageKey {
+        &self.current
+    }
+
+    fn find(&self, id: &str) -> Option<&StorageKey> {
+        std::iter::once(&self.current)
+            .chain(self.previous.iter())
+            .find(|key| key.id == id)
+    }
+
+    /// Returns the additional authenticated data for a value stored under
+    /// the given key.
+    fn aad(namespace: &Namespace, key: &Key) -> Vec<u8> {
+        format!("{}\0{}\0{}", namespace, key.scope(), key.name()).into_bytes()
+    }
+
+    fn encrypt(
+        &self,
+        namespace: &Namespace,
+        key: &Key,
+        value: &Value,
+    ) -> Result<Value> {
+        let plain = serde_json::to_vec(value)?;
+        let mut nonce = [0; NONCE_SIZE];
+        rand_bytes(&mut nonce).map_err(crypto_err)?;
+        let mut tag = [0; TAG_SIZE];
+        let mut data = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.current.key,
+            Some(&nonce),
+            &Self::aad(namespace, key),
+            &plain,
+            &mut tag,
+        )
+        .map_err(crypto_err)?;
+        data.extend_from_slice(&tag);
+
+        let envelope = Envelope {
+            aes_256_gcm: EncryptedValue {
+                key_id: self.current.id.clone(),
+                nonce: BASE64_ENGINE.encode(nonce),
+                data: BASE64_ENGINE.encode(data),
+            },
+        };
+        Ok(serde_json::to_value(envelope)?)
+    }
+
+    /// Decrypts the value stored under the given key.
+    ///
+    /// Returns a value that is not encrypted as is, if that is allowed.
+    fn decrypt(
+        &self,
+        namespace: &Namespace,
+        key: &Key,
+        value: Value,
+    ) -> Result<Value> {
+        if !Envelope::matches(&value) {
+            return if self.allow_plaintext {
+                Ok(value)
+            } else {
+                Err(crypto_err(format!(
+                    "value for '{}' is not encrypted, set 'storage_encryption_allow_plaintext' to read unencrypted data",
+                    key
+                )))
+            };
+        }
+        let encrypted =
+            serde_json::from_value::<Envelope>(value)?.aes_256_gcm;
+
+        let storage_key = self.find(&encrypted.key_id).ok_or_else(|| {
+            kvx::Error::Other(format!(
+                "value was encrypted with unknown storage key '{}'",
+                encrypted.key_id
+            ))
+        })?;
+        let nonce = BASE64_ENGINE
+            .decode(&encrypted.nonce)
+            .map_err(|e| crypto_err(format!("invalid nonce: {}", e)))?;
+        let mut data = BASE64_ENGINE
+            .decode(&encrypted.data)
+            .map_err(|e| crypto_err(format!("invalid data: {}", e)))?;
+        if data.len() < TAG_SIZE {
+            return Err(crypto_err("encrypted value is truncated"));
+        }
+        let tag = data.split_off(data.len() - TAG_SIZE);
+
+        let plain = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &storage_key.key,
+            Some(&nonce),
+            &Self::aad(namespace, key),
+            &data,
+            &tag,
+        )
+        .map_err(crypto_err)?;
+        Ok(serde_json::from_slice(&plain)?)
+    }
+}
+
+fn crypto_err(e: impl fmt::Display) -> kvx::Error {
+    kvx::Error::Other(format!("storage encryption error: {}", e))
+}
+
+/// The stored form of an encrypted value.
+#[derive(Deserialize, Serialize)]
+struct Envelope {
+    aes_256_gcm: EncryptedValue,
+}
+
+impl Envelope {
+    fn matches(value: &Value) -> bool {
+        value.as_object().is_some_and(|map| {
+            map.len() == 1 && map.contains_key("aes_256_gcm")
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct EncryptedValue {
+    key_id: String,
+    nonce: String,
+    data: String,
+}
+
+//------------ Registered Keys -----------------------------------------------
+
+static STORAGE_KEYS: OnceLock<RwLock<HashMap<Url, Arc<StorageKeys>>>> =
+    OnceLock::new();
+
+fn registry() -> &'static RwLock<HashMap<Url, Arc<StorageKeys>>> {
+    STORAGE_KEYS.get_or_init(Default::default)
+}
+
+/// Registers the keys used for all stores created for the storage URI
+/// from now on.
+pub fn register_storage_keys(storage_uri: &Url, keys: StorageKeys) {
+    registry()
+        .write()
+        .unwrap()
+        .insert(storage_uri.clone(), Arc::new(keys));
+}
+
+/// Returns the keys registered for the storage URI, if any.
+pub fn storage_keys(storage_uri: &Url) -> Option<Arc<StorageKeys>> {
+    registry().read().unwrap().get(storage_uri).cloned()
+}
+
+//------------ EncryptedStore ------------------------------------------------
+
+/// A backend that encrypts values stored in, and decrypts values read
+/// from, an inner backend.
+///
+/// Keys and scopes are not encrypted.
+pub(super) struct EncryptedStore<B> {
+    inner: B,
+    namespace: NamespaceBuf,
+    keys: Arc<StorageKeys>,
+}
+
+impl EncryptedStore<Box<dyn PubKeyValueStoreBackend>> {
+    pub fn new(
+        inner: Box<dyn PubKeyValueStoreBackend>,
+        namespace: &Namespace,
+        keys: Arc<StorageKeys>,
+    ) -> Self {
+        EncryptedStore {
+            inner,
+            namespace: namespace.to_owned(),
+            keys,
+        }
+    }
+}
+
+/// Support for renaming the namespace of the inner backend.
+///
+/// A namespace can only be migrated outside of a transaction, i.e. not
+/// through the view of the backend that is passed to a transaction.
+pub(super) trait MigrateNamespace {
+    fn migrate_inner(&mut self, to: NamespaceBuf) -> Result<()>;
+}
+
+impl MigrateNamespace for Box<dyn PubKeyValueStoreBackend> {
+    fn migrate_inner(&mut self, to: NamespaceBuf) -> Result<()> {
+        self.migrate_namespace(to)
+    }
+}
+
+impl MigrateNamespace for &dyn KeyValueStoreBackend {
+    fn migrate_inner(&mut self, _to: NamespaceBuf) -> Result<()> {
+        Err(kvx::Error::NamespaceMigration(
+            "cannot migrate a namespace inside a transaction".to_string(),
+        ))
+    }
+}
+
+impl<B: Deref> EncryptedStore<B>
+where
+    B::Target: KeyValueStoreBackend,
+{
+    /// Re-encrypts all values for the new namespace, after the namespace
+    /// of the inner backend was migrated.
+    fn reencrypt_namespace(&self, to: &Namespace) -> Result<()> {
+        let mut scopes = self.inner.list_scopes()?;
+        scopes.push(Scope::global());
+
+        for scope in scopes {
+            for key in self.inner.list_keys(&scope)? {
+                // kvx list_keys returns keys in sub-scopes
+                if key.scope() != &scope {
+                    continue;
+                }
+                let value = match self.inner.get(&key)? {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let value =
+                    self.keys.decrypt(&self.namespace, &key, value)?;
+                self.inner
+                    .store(&key, self.keys.encrypt(to, &key, &value)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<B: Deref> ReadStore for EncryptedStore<B>
+where
+    B::Target: ReadStore,
+{
+    fn is_empty(&self) -> Result<bool> {
+        self.inner.is_empty()
+    }
+
+    fn has(&self, key: &Key) -> Result<bool> {
+        self.inner.has(key)
+    }
+
+    fn has_scope(&self, scope: &Scope) -> Result<bool> {
+        self.inner.has_scope(scope)
+    }
+
+    fn get(&self, key: &Key) -> Result<Option<Value>> {
+        self.inner
+            .get(key)?
+            .map(|value| self.keys.decrypt(&self.namespace, key, value))
+            .transpose()
+    }
+
+    fn list_keys(&self, scope: &Scope) -> Result<Vec<Key>> {
+        self.inner.list_keys(scope)
+    }
+
+    fn list_scopes(&self) -> Result<Vec<Scope>> {
+        self.inner.list_scopes()
+    }
+}
+
+impl<B: Deref + MigrateNamespace> WriteStore for EncryptedStore<B>
+where
+    B::Target: KeyValueStoreBackend,
+{
+    fn store(&self, key: &Key, value: Value) -> Result<()> {
+        self.inner
+            .store(key, self.keys.encrypt(&self.namespace, key, &value)?)
+    }
+
+    fn move_value(&self, from: &Key, to: &Key) -> Result<()> {
+        let value = self.get(from)?;
+        self.inner.move_value(from, to)?;
+        match value {
+            Some(value) => self.store(to, value),
+            None => Ok(()),
+        }
+    }
+
+    fn move_scope(&self, from: &Scope, to: &Scope) -> Result<()> {
+        let mut values = vec![];
+        for key in self.inner.list_keys(from)? {
+            if let Some(value) = self.get(&key)? {
+                values.push((moved_key(&key, from, to)?, value));
+            }
+        }
+        self.inner.move_scope(from, to)?;
+        for (key, value) in values {
+            self.store(&key, value)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &Key) -> Result<()> {
+        self.inner.delete(key)
+    }
+
+    fn delete_scope(&self, scope: &Scope) -> Result<()> {
+        self.inner.delete_scope(scope)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.inner.clear()
+    }
+
+    fn migrate_namespace(&mut self, to: NamespaceBuf) -> Result<()> {
+        self.inner.migrate_inner(to.clone())?;
+        self.reencrypt_namespace(&to)?;
+        self.namespace = to;
+        Ok(())
+    }
+}
+
+/// Returns the key for a value in the `from` scope or its sub-scopes,
+/// after the scope was moved to `to`.
+fn moved_key(key: &Key, from: &Scope, to: &Scope) -> Result<Key> {
+    let key_str = key.to_string();
+    let rest = key_str
+        .strip_prefix(&from.to_string())
+        .ok_or(kvx::Error::InvalidKey)?
+        .trim_start_matches(Scope::SEPARATOR);
+    if to.is_global() {
+        Key::from_str(rest)
+    } else {
+        Key::from_str(&format!("{}{}{}", to, Scope::SEPARATOR, rest))
+    }
+    .map_err(|_| kvx::Error::InvalidKey)
+}
+
+impl<B: Deref + MigrateNamespace> KeyValueStoreBackend for EncryptedStore<B>
+where
+    B::Target: KeyValueStoreBackend,
+{
+    fn transaction(
+        &self,
+        scope: &Scope,
+        callback: &mut dyn FnMut(&dyn KeyValueStoreBackend) -> Result<()>,
+    ) -> Result<()> {
+        self.inner.transaction(scope, &mut |kv| {
+            callback(&EncryptedStore {
+                inner: kv,
+                namespace: self.namespace.clone(),
+                keys: self.keys.clone(),
+            })
+        })
+    }
+}
+
+impl<B: Deref> fmt::Debug for EncryptedStore<B>
+where
+    B::Target: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedStore")
+            .field("inner", &&*self.inner)
+            .field("key", &self.keys.current)
+            .finish()
+    }
+}
+
+impl<B: Deref> fmt::Display for EncryptedStore<B>
+where
+    B::Target: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.inner, f)
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kvx::{namespace, segment, Namespace, Segment};
+
+    use crate::{commons::eventsourcing::KeyValueStore, test};
+
+    fn key(byte: u8) -> StorageKey {
+        StorageKey::from_base64(&BASE64_ENGINE.encode([byte; KEY_SIZE]))
+            .unwrap()
+    }
+
+    #[test]
+    fn values_are_encrypted_at_rest() {
+        let storage_uri = test::mem_storage();
+        let ns = namespace!("encrypted");
+        let plain_key = Key::new_global(segment!("plain"));
+        let secret_key = Key::new_global(segment!("secret"));
+
+        // Data stored before encryption was enabled can only be read if
+        // this is allowed. Use another namespace to check that it is
+        // rejected, as the memory store keeps the lock after an error.
+        let rejected_ns = namespace!("rejected");
+        for ns in [ns, rejected_ns] {
+            KeyValueStore::create(&storage_uri, ns)
+                .unwrap()
+                .store(&plain_key, &"plain")
+                .unwrap();
+        }
+
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(1), vec![], false),
+        );
+        let store = KeyValueStore::create(&storage_uri, rejected_ns).unwrap();
+        assert!(store.get::<String>(&plain_key).is_err());
+
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(1), vec![], true),
+        );
+        let store = KeyValueStore::create(&storage_uri, ns).unwrap();
+        store.store(&secret_key, &"secret").unwrap();
+        assert_eq!(
+            store.get::<String>(&plain_key).unwrap().unwrap(),
+            "plain"
+        );
+        assert_eq!(
+            store.get::<String>(&secret_key).unwrap().unwrap(),
+            "secret"
+        );
+
+        let raw = KeyValueStore::create_raw(&storage_uri, ns).unwrap();
+        let stored: Value = raw.get(&secret_key).unwrap().unwrap();
+        assert!(Envelope::matches(&stored));
+        assert!(!stored.to_string().contains("secret"));
+
+        // Rotate the key, then re-encrypt everything with the new key.
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(2), vec![key(1)], true),
+        );
+        let store = KeyValueStore::create(&storage_uri, ns).unwrap();
+        assert_eq!(store.reencrypt().unwrap(), 2);
+
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(2), vec![], false),
+        );
+        let store = KeyValueStore::create(&storage_uri, ns).unwrap();
+        assert_eq!(
+            store.get::<String>(&plain_key).unwrap().unwrap(),
+            "plain"
+        );
+        assert_eq!(
+            store.get::<String>(&secret_key).unwrap().unwrap(),
+            "secret"
+        );
+
+        // Values cannot be read without the key.
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(3), vec![], false),
+        );
+        let store = KeyValueStore::create(&storage_uri, ns).unwrap();
+        assert!(store.get::<String>(&secret_key).is_err());
+    }
+
+    #[test]
+    fn tampered_value_is_rejected() {
+        let keys = StorageKeys::new(key(1), vec![], false);
+        let ns = namespace!("ns");
+        let secret = Key::new_global(segment!("secret"));
+        let mut value =
+            keys.encrypt(ns, &secret, &Value::from("secret")).unwrap();
+
+        let data = value["aes_256_gcm"]["data"].as_str().unwrap();
+        let mut bytes = BASE64_ENGINE.decode(data).unwrap();
+        bytes[0] ^= 1;
+        value["aes_256_gcm"]["data"] = BASE64_ENGINE.encode(bytes).into();
+
+        assert!(keys.decrypt(ns, &secret, value).is_err());
+    }
+
+    #[test]
+    fn value_is_bound_to_its_key() {
+        let keys = StorageKeys::new(key(1), vec![], false);
+        let ns = namespace!("ns");
+        let secret = Key::new_global(segment!("secret"));
+        let value =
+            keys.encrypt(ns, &secret, &Value::from("secret")).unwrap();
+
+        assert!(keys.decrypt(ns, &secret, value.clone()).is_ok());
+
+        let other = Key::new_global(segment!("other"));
+        assert!(keys.decrypt(ns, &other, value.clone()).is_err());
+
+        let scoped = Key::new_scoped(
+            Scope::from_segment(segment!("scope")),
+            segment!("secret"),
+        );
+        assert!(keys.decrypt(ns, &scoped, value.clone()).is_err());
+
+        assert!(keys.decrypt(namespace!("other"), &secret, value).is_err());
+    }
+
+    #[test]
+    fn moved_values_are_reencrypted() {
+        let storage_uri = test::mem_storage();
+        register_storage_keys(
+            &storage_uri,
+            StorageKeys::new(key(1), vec![], false),
+        );
+
+        let from = Scope::from_segment(segment!("from"));
+        let to = Scope::from_segment(segment!("to"));
+        let first = Key::new_scoped(from.clone(), segment!("first"));
+        let second = Key::new_scoped(from.clone(), segment!("second"));
+
+        let mut store =
+            KeyValueStore::create(&storage_uri, namespace!("moved")).unwrap();
+        store.store(&first, &1).unwrap();
+        store.store(&second, &2).unwrap();
+
+        let moved = Key::new_scoped(from.clone(), segment!("moved"));
+        store
+            .execute(&Scope::global(), |kv| kv.move_value(&first, &moved))
+            .unwrap();
+        assert_eq!(store.get::<u32>(&moved).unwrap(), Some(1));
+
+        store
+            .execute(&Scope::global(), |kv| kv.move_scope(&from, &to))
+            .unwrap();
+        let second = Key::new_scoped(to.clone(), segment!("second"));
+        assert_eq!(store.get::<u32>(&second).unwrap(), Some(2));
+
+        store
+            .migrate_to_current(&storage_uri, namespace!("migrated"))
+            .unwrap();
+        let migrated =
+            KeyValueStore::create(&storage_uri, namespace!("migrated"))
+                .unwrap();
+        assert_eq!(migrated.get::<u32>(&second).unwrap(), Some(2));
+    }
+}
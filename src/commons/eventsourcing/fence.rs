@@ -0,0 +1,179 @@
+//! Fencing of writes to storage that is shared between nodes.
+//!
+//! When several Krill nodes share the same storage, only one of them may
+//! make changes. A node that stalled for a while may not know yet that it
+//! lost this right when it resumes. To keep such a node from writing, a
+//! [`WriteFence`] can be registered for a storage URI. All stores that are
+//! created for that URI from then on check the fence before the first
+//! write in each transaction, while the transaction holds its lock, and
+//! abort the transaction if the check fails.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use kvx::{
+    Key, KeyValueStoreBackend, Namespace, NamespaceBuf, ReadStore, Scope,
+    WriteStore,
+};
+use serde_json::Value;
+use url::Url;
+
+type Result<T> = std::result::Result<T, kvx::Error>;
+
+//------------ WriteFence ----------------------------------------------------
+
+/// Decides whether this node may write to the storage.
+pub trait WriteFence: fmt::Debug + Send + Sync {
+    /// Returns whether writes to the namespace are fenced.
+    fn fences(&self, namespace: &Namespace) -> bool;
+
+    /// Returns an error if this node may not write to the storage.
+    ///
+    /// This is called inside the transaction of the write. For backends
+    /// that support this, i.e. SQLite, any reads from the storage done
+    /// by the check are part of that same transaction.
+    fn check(&self) -> Result<()>;
+}
+
+//------------ Registry ------------------------------------------------------
+
+type Fences = HashMap<Url, Arc<dyn WriteFence>>;
+
+static WRITE_FENCES: OnceLock<RwLock<Fences>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<Fences> {
+    WRITE_FENCES.get_or_init(Default::default)
+}
+
+/// Registers the fence for all stores created for the storage URI from
+/// now on.
+pub fn register_write_fence(storage_uri: &Url, fence: Arc<dyn WriteFence>) {
+    registry()
+        .write()
+        .unwrap()
+        .insert(storage_uri.clone(), fence);
+}
+
+/// Returns the fence for a store for the namespace, if any.
+pub(super) fn write_fence(
+    storage_uri: &Url,
+    namespace: &Namespace,
+) -> Option<Arc<dyn WriteFence>> {
+    registry()
+        .read()
+        .unwrap()
+        .get(storage_uri)
+        .filter(|fence| fence.fences(namespace))
+        .cloned()
+}
+
+//------------ FencedView ----------------------------------------------------
+
+/// The view of a backend inside a transaction that checks the fence
+/// before its first write.
+pub(super) struct FencedView<'a> {
+    inner: &'a dyn KeyValueStoreBackend,
+    fence: &'a dyn WriteFence,
+    checked: Cell<bool>,
+}
+
+impl<'a> FencedView<'a> {
+    pub fn new(
+        inner: &'a dyn KeyValueStoreBackend,
+        fence: &'a dyn WriteFence,
+    ) -> Self {
+        FencedView {
+            inner,
+            fence,
+            checked: Cell::new(false),
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if !self.checked.get() {
+            self.fence.check()?;
+            self.checked.set(true);
+        }
+        Ok(())
+    }
+}
+
+impl ReadStore for FencedView<'_> {
+    fn is_empty(&self) -> Result<bool> {
+        self.inner.is_empty()
+    }
+
+    fn has(&self, key: &Key) -> Result<bool> {
+        self.inner.has(key)
+    }
+
+    fn has_scope(&self, scope: &Scope) -> Result<bool> {
+        self.inner.has_scope(scope)
+    }
+
+    fn get(&self, key: &Key) -> Result<Option<Value>> {
+        self.inner.get(key)
+    }
+
+    fn list_keys(&self, scope: &Scope) -> Result<Vec<Key>> {
+        self.inner.list_keys(scope)
+    }
+
+    fn list_scopes(&self) -> Result<Vec<Scope>> {
+        self.inner.list_scopes()
+    }
+}
+
+impl WriteStore for FencedView<'_> {
+    fn store(&self, key: &Key, value: Value) -> Result<()> {
+        self.check()?;
+        self.inner.store(key, value)
+    }
+
+    fn move_value(&self, from: &Key, to: &Key) -> Result<()> {
+        self.check()?;
+        self.inner.move_value(from, to)
+    }
+
+    fn move_scope(&self, from: &Scope, to: &Scope) -> Result<()> {
+        self.check()?;
+        self.inner.move_scope(from, to)
+    }
+
+    fn delete(&self, key: &Key) -> Result<()> {
+        self.check()?;
+        self.inner.delete(key)
+    }
+
+    fn delete_scope(&self, scope: &Scope) -> Result<()> {
+        self.check()?;
+        self.inner.delete_scope(scope)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.check()?;
+        self.inner.clear()
+    }
+
+    fn migrate_namespace(&mut self, _to: NamespaceBuf) -> Result<()> {
+        Err(kvx::Error::NamespaceMigration(
+            "cannot migrate a namespace inside a transaction".to_string(),
+        ))
+    }
+}
+
+impl KeyValueStoreBackend for FencedView<'_> {
+    fn transaction(
+        &self,
+        scope: &Scope,
+        callback: &mut dyn FnMut(&dyn KeyValueStoreBackend) -> Result<()>,
+    ) -> Result<()> {
+        self.inner.transaction(scope, &mut |kv| {
+            callback(&FencedView::new(kv, self.fence))
+        })
+    }
+}
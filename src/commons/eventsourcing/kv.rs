@@ -1,14 +1,20 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, ops, str::FromStr, sync::Arc};
 
 pub use kvx::{
     namespace, segment, Key, Namespace, Scope, Segment, SegmentBuf,
 };
-use kvx::{KeyValueStoreBackend, NamespaceBuf, ReadStore, WriteStore};
+use kvx::{KeyValueStoreBackend, NamespaceBuf, PubKeyValueStoreBackend};
 use serde::{de::DeserializeOwned, Serialize};
 use url::Url;
 
 use crate::commons::error::KrillIoError;
 
+use super::{
+    encryption::{storage_keys, EncryptedStore},
+    fence::{write_fence, FencedView, WriteFence},
+    nslock::{namespace_lock, NamespaceGuard, NamespaceLock},
+};
+
 pub trait SegmentExt {
     fn parse_lossy(value: &str) -> SegmentBuf;
     fn concat(
@@ -46,19 +52,123 @@ impl SegmentExt for Segment {
 
 #[derive(Debug)]
 pub struct KeyValueStore {
-    inner: kvx::KeyValueStore,
+    inner: Inner,
+
+    /// Whether single reads are consistent without locking the scope.
+    ///
+    /// This is the case for SQLite, where every statement sees a
+    /// snapshot of the database, so that reads need not wait for writers.
+    snapshot_reads: bool,
+
+    /// The fence checked before writes, if any.
+    fence: Option<Arc<dyn WriteFence>>,
+
+    /// The lock of the namespace, held shared by every transaction.
+    namespace_lock: Arc<NamespaceLock>,
+}
+
+/// A plain kvx store, used while its namespace is locked shared.
+pub(super) struct LockedKvx<'a> {
+    store: &'a kvx::KeyValueStore,
+    _lock: NamespaceGuard<'a>,
+}
+
+impl ops::Deref for LockedKvx<'_> {
+    type Target = kvx::KeyValueStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.store
+    }
+}
+
+/// The backend used by a [`KeyValueStore`].
+///
+/// Plain kvx stores are kept as they are, so that kvx's own task queue
+/// is used for them. Other backends, i.e. SQLite and encrypted stores,
+/// use the queue implemented in the `queue` module.
+#[derive(Debug)]
+enum Inner {
+    Kvx(kvx::KeyValueStore),
+    Other(Box<dyn PubKeyValueStoreBackend>),
+}
+
+impl Inner {
+    fn backend(&self) -> &dyn PubKeyValueStoreBackend {
+        match self {
+            Inner::Kvx(store) => store,
+            Inner::Other(backend) => backend.as_ref(),
+        }
+    }
+
+    fn backend_mut(&mut self) -> &mut dyn PubKeyValueStoreBackend {
+        match self {
+            Inner::Kvx(store) => store,
+            Inner::Other(backend) => backend.as_mut(),
+        }
+    }
+
+    fn into_backend(self) -> Box<dyn PubKeyValueStoreBackend> {
+        match self {
+            Inner::Kvx(store) => Box::new(store),
+            Inner::Other(backend) => backend,
+        }
+    }
 }
 
 // # Construct and high level functions.
 impl KeyValueStore {
     /// Creates a new KeyValueStore.
+    ///
+    /// The `sqlite://` scheme is handled by Krill itself, all other
+    /// schemes are passed on to kvx. If storage keys are registered for
+    /// the storage URI, then values are encrypted at rest. If a write
+    /// fence is registered for it, then it is checked before writes.
     pub fn create(
         storage_uri: &Url,
         namespace: &Namespace,
     ) -> Result<Self, KeyValueError> {
-        kvx::KeyValueStore::new(storage_uri, namespace)
-            .map(|inner| KeyValueStore { inner })
-            .map_err(KeyValueError::Inner)
+        let mut store = Self::create_raw(storage_uri, namespace)?;
+        if let Some(keys) = storage_keys(storage_uri) {
+            store.inner = Inner::Other(Box::new(EncryptedStore::new(
+                store.inner.into_backend(),
+                namespace,
+                keys,
+            )));
+        }
+        store.fence = write_fence(storage_uri, namespace);
+        Ok(store)
+    }
+
+    /// Creates a new KeyValueStore that reads and writes values exactly
+    /// as they are stored, i.e. without decrypting or encrypting them.
+    ///
+    /// This is used to copy data, e.g. for backups, without revealing
+    /// the values of encrypted stores.
+    pub fn create_raw(
+        storage_uri: &Url,
+        namespace: &Namespace,
+    ) -> Result<Self, KeyValueError> {
+        let inner = match storage_uri.scheme() {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Inner::Other(Box::new(
+                super::sqlite::SqliteStore::new(storage_uri, namespace)
+                    .map_err(KeyValueError::Inner)?,
+            )),
+            _ => Inner::Kvx(
+                kvx::KeyValueStore::new(storage_uri, namespace)
+                    .map_err(KeyValueError::Inner)?,
+            ),
+        };
+
+        // SQLite is the only backend that is not a plain kvx store here.
+        let snapshot_reads = matches!(inner, Inner::Other(_));
+
+        Ok(KeyValueStore {
+            inner,
+            snapshot_reads,
+            fence: None,
+            namespace_lock: namespace_lock(storage_uri, namespace),
+        })
     }
 
     /// Returns true if this KeyValueStore (with this namespace) has any
@@ -67,7 +177,10 @@ impl KeyValueStore {
         // NOTE: this is done using `self.execute` as this would result in a
         // lockfile       to be created for disk based inner stores,
         // and that would make them       appear as not empty.
-        self.inner.is_empty().map_err(KeyValueError::Inner)
+        self.inner
+            .backend()
+            .is_empty()
+            .map_err(KeyValueError::Inner)
     }
 
     /// Wipe the complete store. Needless to say perhaps.. use with care..
@@ -97,7 +210,69 @@ impl KeyValueStore {
     where
         F: FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
     {
-        self.inner.execute(scope, op).map_err(KeyValueError::Inner)
+        self.execute_kvx(scope, op).map_err(KeyValueError::Inner)
+    }
+
+    /// Executes a read-only operation.
+    ///
+    /// The operation must consist of a single read. It is executed without
+    /// locking the scope if the backend supports consistent reads without
+    /// it, otherwise this is the same as [`execute`](Self::execute).
+    fn read<F, T>(&self, scope: &Scope, mut op: F) -> Result<T, KeyValueError>
+    where
+        F: FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
+    {
+        if self.snapshot_reads {
+            op(self.inner.backend()).map_err(KeyValueError::Inner)
+        } else {
+            self.execute(scope, op)
+        }
+    }
+
+    /// Returns the kvx store, if this store uses a plain kvx backend.
+    ///
+    /// Fenced stores never do, as kvx would write to it without checking
+    /// the fence. The namespace is locked shared while the kvx store is
+    /// used, as it is for transactions.
+    pub(super) fn as_kvx(&self) -> Option<LockedKvx<'_>> {
+        match &self.inner {
+            Inner::Kvx(store) if self.fence.is_none() => Some(LockedKvx {
+                store,
+                _lock: self.namespace_lock.shared(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Locks the whole namespace until the returned guard is dropped.
+    ///
+    /// While it is locked, no transactions can be started on the namespace
+    /// by other threads of this process. Transactions on this thread still
+    /// can, e.g. to read the namespace.
+    pub fn lock_namespace(&self) -> NamespaceGuard<'_> {
+        self.namespace_lock.exclusive()
+    }
+
+    /// Like [`execute`](Self::execute), but leaves the kvx::Error as is.
+    /// This is used by the task queue implementation.
+    pub(super) fn execute_kvx<F, T>(
+        &self,
+        scope: &Scope,
+        mut op: F,
+    ) -> Result<T, kvx::Error>
+    where
+        F: FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
+    {
+        let _namespace = self.namespace_lock.shared();
+        let mut res = None;
+        self.inner.backend().transaction(scope, &mut |kv| {
+            res = Some(match self.fence.as_deref() {
+                Some(fence) => op(&FencedView::new(kv, fence))?,
+                None => op(kv)?,
+            });
+            Ok(())
+        })?;
+        res.ok_or(kvx::Error::Unknown)
     }
 }
 
@@ -138,7 +313,7 @@ impl KeyValueStore {
         &self,
         key: &Key,
     ) -> Result<Option<V>, KeyValueError> {
-        self.execute(key.scope(), |kv| {
+        self.read(key.scope(), |kv| {
             if let Some(value) = kv.get(key)? {
                 Ok(Some(serde_json::from_value(value)?))
             } else {
@@ -149,7 +324,7 @@ impl KeyValueStore {
 
     /// Returns whether a key exists
     pub fn has(&self, key: &Key) -> Result<bool, KeyValueError> {
-        self.execute(key.scope(), |kv| kv.has(key))
+        self.read(key.scope(), |kv| kv.has(key))
     }
 
     /// Delete a key-value pair
@@ -167,7 +342,7 @@ impl KeyValueStore {
         scope: &Scope,
         matching: &str,
     ) -> Result<Vec<Key>, KeyValueError> {
-        self.execute(scope, |kv| {
+        self.read(scope, |kv| {
             // kvx list_keys returns keys in sub-scopes
             kv.list_keys(scope).map(|keys| {
                 keys.into_iter()
@@ -186,7 +361,7 @@ impl KeyValueStore {
 impl KeyValueStore {
     /// Returns whether a scope exists
     pub fn has_scope(&self, scope: &Scope) -> Result<bool, KeyValueError> {
-        self.execute(&Scope::global(), |kv| kv.has_scope(scope))
+        self.read(&Scope::global(), |kv| kv.has_scope(scope))
     }
 
     /// Delete a scope
@@ -196,7 +371,7 @@ impl KeyValueStore {
 
     /// Returns all scopes, including sub_scopes
     pub fn scopes(&self) -> Result<Vec<Scope>, KeyValueError> {
-        self.execute(&Scope::global(), |kv| kv.list_scopes())
+        self.read(&Scope::global(), |kv| kv.list_scopes())
     }
 }
 
@@ -210,10 +385,7 @@ impl KeyValueStore {
         namespace: &Namespace,
     ) -> Result<Self, KeyValueError> {
         let namespace = Self::prefixed_namespace(namespace, "upgrade")?;
-
-        kvx::KeyValueStore::new(storage_uri, namespace)
-            .map(|inner| KeyValueStore { inner })
-            .map_err(KeyValueError::Inner)
+        Self::create(storage_uri, &namespace)
     }
 
     fn prefixed_namespace(
@@ -243,6 +415,7 @@ impl KeyValueStore {
         archive_store.wipe()?;
 
         self.inner
+            .backend_mut()
             .migrate_namespace(archive_ns)
             .map_err(KeyValueError::Inner)
     }
@@ -263,6 +436,7 @@ impl KeyValueStore {
             )))
         } else {
             self.inner
+                .backend_mut()
                 .migrate_namespace(namespace.into())
                 .map_err(KeyValueError::Inner)
         }
@@ -276,29 +450,82 @@ impl KeyValueStore {
     /// okay, because this function       is intended to be used for
     /// migrations and testing (copy test data       into a store) while
     /// Krill is not running.
+    ///
+    /// The values of each scope are read first, and then written in a
+    /// transaction for that scope, so that a write fence is checked.
     pub fn import(&self, other: &Self) -> Result<(), KeyValueError> {
         let mut scopes = other.scopes()?;
         scopes.push(Scope::global()); // not explicitly listed but should be migrated as well.
 
         for scope in scopes {
+            let mut values = vec![];
             for key in other.keys(&scope, "")? {
-                if let Some(value) =
-                    other.inner.get(&key).map_err(KeyValueError::Inner)?
+                if let Some(value) = other
+                    .inner
+                    .backend()
+                    .get(&key)
+                    .map_err(KeyValueError::Inner)?
                 {
-                    self.inner
-                        .store(&key, value)
-                        .map_err(KeyValueError::Inner)?;
+                    values.push((key, value));
                 }
             }
+
+            if !values.is_empty() {
+                self.execute(&scope, |kv| {
+                    for (key, value) in &values {
+                        kv.store(key, value.clone())?;
+                    }
+                    Ok(())
+                })?;
+            }
         }
 
         Ok(())
     }
 }
 
+// # Encryption Support
+impl KeyValueStore {
+    /// Re-stores all values in this store, so that they are encrypted
+    /// with the current storage key. Returns the number of values.
+    ///
+    /// Each scope is locked while its values are re-encrypted, so this
+    /// can be done while Krill is running.
+    pub fn reencrypt(&self) -> Result<usize, KeyValueError> {
+        self.execute(&Scope::global(), |kv| {
+            let mut count = reencrypt_scope(kv, &Scope::global())?;
+            for scope in kv.list_scopes()? {
+                kv.transaction(&scope, &mut |kv| {
+                    count += reencrypt_scope(kv, &scope)?;
+                    Ok(())
+                })?;
+            }
+            Ok(count)
+        })
+    }
+}
+
+fn reencrypt_scope(
+    kv: &dyn KeyValueStoreBackend,
+    scope: &Scope,
+) -> Result<usize, kvx::Error> {
+    let mut count = 0;
+    for key in kv.list_keys(scope)? {
+        // kvx list_keys returns keys in sub-scopes
+        if key.scope() != scope {
+            continue;
+        }
+        if let Some(value) = kv.get(&key)? {
+            kv.store(&key, value)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
 impl fmt::Display for KeyValueStore {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.inner.fmt(f)
+        fmt::Display::fmt(self.inner.backend(), f)
     }
 }
 
@@ -625,4 +852,42 @@ mod tests {
 
         assert_eq!(keys, expected);
     }
+
+    #[derive(Debug)]
+    struct RefusingFence;
+
+    impl WriteFence for RefusingFence {
+        fn fences(&self, _namespace: &Namespace) -> bool {
+            true
+        }
+
+        fn check(&self) -> Result<(), kvx::Error> {
+            Err(kvx::Error::Unknown)
+        }
+    }
+
+    #[test]
+    fn test_import_checks_fence() {
+        let source =
+            KeyValueStore::create(&get_storage_uri(), &random_namespace())
+                .unwrap();
+        let key = Key::new_scoped(
+            Scope::from_segment(random_segment()),
+            random_segment(),
+        );
+        source.store(&key, &"content".to_owned()).unwrap();
+
+        let fenced_uri = crate::test::mem_storage();
+        super::super::register_write_fence(
+            &fenced_uri,
+            Arc::new(RefusingFence),
+        );
+        let target =
+            KeyValueStore::create(&fenced_uri, &random_namespace()).unwrap();
+        assert!(target.import(&source).is_err());
+
+        // Read without locking, as the memory store keeps its lock after
+        // a failed write.
+        assert!(!target.inner.backend().has(&key).unwrap());
+    }
 }
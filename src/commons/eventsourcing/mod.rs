@@ -18,12 +18,32 @@ pub use self::store::*;
 mod listener;
 pub use self::listener::*;
 
+mod check;
+pub use self::check::*;
+
 mod kv;
 pub use self::kv::{
     namespace, segment, Key, KeyValueError, KeyValueStore, Namespace, Scope,
     Segment, SegmentBuf, SegmentExt,
 };
 
+mod queue;
+pub use self::queue::QueuedTask;
+
+mod encryption;
+pub use self::encryption::{
+    register_storage_keys, storage_keys, StorageKey, StorageKeys,
+};
+
+mod fence;
+pub use self::fence::{register_write_fence, WriteFence};
+
+mod nslock;
+pub use self::nslock::NamespaceGuard;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
 //------------ Tests ---------------------------------------------------------
 
 #[cfg(test)]
@@ -37,12 +57,12 @@ mod tests {
 
     use serde::Serialize;
 
-    use rpki::ca::idexchange::MyHandle;
+    use rpki::{ca::idexchange::MyHandle, repository::x509::Time};
 
     use crate::{
         commons::{
             actor::Actor,
-            api::{CommandHistoryCriteria, CommandSummary},
+            api::{CommandHistoryCriteria, CommandSummary, HistoryRetention},
         },
         constants::ACTOR_DEF_TEST,
         test::mem_storage,
@@ -462,4 +482,296 @@ mod tests {
         assert_eq!(history.total(), 1);
         // })
     }
+
+    #[test]
+    fn compact_history() {
+        let storage_uri = mem_storage();
+
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+
+        let bob_handle = MyHandle::from_str("bob").unwrap();
+        manager
+            .add(PersonInitCommand::make(&bob_handle, "bob".to_string()))
+            .unwrap();
+
+        for _ in 0..20 {
+            let get_older = PersonCommand::go_around_sun(&bob_handle, None);
+            manager.command(get_older).unwrap();
+        }
+
+        // Nothing is archived without a retention policy.
+        let report = manager
+            .compact_history(&bob_handle, &HistoryRetention::default())
+            .unwrap();
+        assert_eq!(report.archived_commands, 0);
+
+        let retention = HistoryRetention::new(Some(5), None);
+        let report =
+            manager.compact_history(&bob_handle, &retention).unwrap();
+        assert_eq!(report.first_archived_version, Some(1));
+        assert_eq!(report.last_archived_version, Some(15));
+        assert_eq!(report.archived_commands, 15);
+        assert!(report.bytes_reclaimed > 0);
+
+        // Compacting again with the same policy does nothing.
+        let report =
+            manager.compact_history(&bob_handle, &retention).unwrap();
+        assert_eq!(report.archived_commands, 0);
+
+        // Archived commands can still be retrieved.
+        let archived = manager.get_command(&bob_handle, 3).unwrap();
+        assert_eq!(archived.version(), 3);
+        let archived = manager.get_command(&bob_handle, 15).unwrap();
+        assert_eq!(archived.version(), 15);
+
+        // But they are no longer part of the history.
+        let history = manager
+            .command_history(&bob_handle, CommandHistoryCriteria::default())
+            .unwrap();
+        assert_eq!(history.total(), 5);
+        assert_eq!(history.commands().first().unwrap().version, 16);
+
+        // The aggregate can still be loaded, and updated.
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+        let bob = manager.get_latest(&bob_handle).unwrap();
+        assert_eq!(20, bob.age());
+
+        let get_older = PersonCommand::go_around_sun(&bob_handle, None);
+        let bob = manager.command(get_older).unwrap();
+        assert_eq!(21, bob.age());
+        assert_eq!(22, bob.version());
+    }
+
+    #[test]
+    fn compacted_history_without_usable_snapshot() {
+        let storage_uri = mem_storage();
+        let kv = KeyValueStore::create(&storage_uri, namespace!("person"))
+            .unwrap();
+        let scope = Scope::from_segment(segment!("erin"));
+        let snapshot_key =
+            Key::new_scoped(scope.clone(), segment!("snapshot.json"));
+
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+
+        let erin_handle = MyHandle::from_str("erin").unwrap();
+        manager
+            .add(PersonInitCommand::make(&erin_handle, "erin".to_string()))
+            .unwrap();
+        for _ in 0..5 {
+            let get_older = PersonCommand::go_around_sun(&erin_handle, None);
+            manager.command(get_older).unwrap();
+        }
+        manager.save_snapshot(&erin_handle).unwrap();
+        let old_snapshot: serde_json::Value =
+            kv.get(&snapshot_key).unwrap().unwrap();
+
+        for _ in 0..5 {
+            let get_older = PersonCommand::go_around_sun(&erin_handle, None);
+            manager.command(get_older).unwrap();
+        }
+        manager
+            .compact_history(
+                &erin_handle,
+                &HistoryRetention::new(Some(2), None),
+            )
+            .unwrap();
+
+        // Without a snapshot, the archived commands must be replayed.
+        kv.drop_key(&snapshot_key).unwrap();
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+        let get_older = PersonCommand::go_around_sun(&erin_handle, None);
+        let erin = manager.command(get_older).unwrap();
+        assert_eq!(11, erin.age());
+        assert_eq!(12, erin.version());
+        assert!(kv
+            .has(&Key::new_scoped(scope.clone(), segment!("command-11.json")))
+            .unwrap());
+
+        // The same goes for a snapshot older than the archived commands.
+        kv.store(&snapshot_key, &old_snapshot).unwrap();
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+        let get_older = PersonCommand::go_around_sun(&erin_handle, None);
+        let erin = manager.command(get_older).unwrap();
+        assert_eq!(12, erin.age());
+        assert_eq!(13, erin.version());
+    }
+
+    #[test]
+    fn aggregate_at_point_in_history() {
+        let storage_uri = mem_storage();
+
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+
+        let carol_handle = MyHandle::from_str("carol").unwrap();
+        manager
+            .add(PersonInitCommand::make(&carol_handle, "carol".to_string()))
+            .unwrap();
+
+        for _ in 0..10 {
+            let get_older = PersonCommand::go_around_sun(&carol_handle, None);
+            manager.command(get_older).unwrap();
+        }
+        let change_name =
+            PersonCommand::change_name(&carol_handle, None, "caroline");
+        manager.command(change_name).unwrap();
+
+        // Version 0 is the init command, and every command adds a year.
+        let carol = manager.get_at_version(&carol_handle, 0).unwrap();
+        assert_eq!(0, carol.age());
+        assert_eq!("carol", carol.name());
+
+        let carol = manager.get_at_version(&carol_handle, 4).unwrap();
+        assert_eq!(4, carol.age());
+
+        let carol = manager.get_at_version(&carol_handle, 11).unwrap();
+        assert_eq!(10, carol.age());
+        assert_eq!("caroline", carol.name());
+
+        assert!(manager.get_at_version(&carol_handle, 12).is_err());
+
+        // Replaying must also work for archived commands.
+        manager
+            .compact_history(
+                &carol_handle,
+                &HistoryRetention::new(Some(2), None),
+            )
+            .unwrap();
+        let carol = manager.get_at_version(&carol_handle, 4).unwrap();
+        assert_eq!(4, carol.age());
+
+        // The latest state is unaffected.
+        let latest = manager.get_latest(&carol_handle).unwrap();
+        assert_eq!(10, latest.age());
+        assert_eq!("caroline", latest.name());
+
+        let time = manager.get_command(&carol_handle, 4).unwrap().time();
+        let carol = manager.get_at_time(&carol_handle, time).unwrap();
+        assert!(carol.age() >= 4);
+
+        let now = manager.get_at_time(&carol_handle, Time::now()).unwrap();
+        assert_eq!(10, now.age());
+
+        let init_time = manager.get_command(&carol_handle, 0).unwrap().time();
+        let before = init_time - chrono::Duration::seconds(1);
+        assert!(manager.get_at_time(&carol_handle, before).is_err());
+    }
+
+    #[test]
+    fn check_consistency() {
+        let storage_uri = mem_storage();
+
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+
+        let dave_handle = MyHandle::from_str("dave").unwrap();
+        manager
+            .add(PersonInitCommand::make(&dave_handle, "dave".to_string()))
+            .unwrap();
+        for _ in 0..5 {
+            let get_older = PersonCommand::go_around_sun(&dave_handle, None);
+            manager.command(get_older).unwrap();
+        }
+        manager.save_snapshot(&dave_handle).unwrap();
+        manager
+            .compact_history(
+                &dave_handle,
+                &HistoryRetention::new(Some(2), None),
+            )
+            .unwrap();
+
+        let check = manager.check_consistency(&dave_handle, false).unwrap();
+        assert!(check.is_ok());
+
+        // Tamper with the snapshot.
+        let kv = KeyValueStore::create(&storage_uri, namespace!("person"))
+            .unwrap();
+        let snapshot_key = Key::new_scoped(
+            Scope::from_segment(segment!("dave")),
+            segment!("snapshot.json"),
+        );
+        let mut snapshot: serde_json::Value =
+            kv.get(&snapshot_key).unwrap().unwrap();
+        snapshot["age"] = serde_json::Value::from(42);
+        kv.store(&snapshot_key, &snapshot).unwrap();
+
+        let check = manager.check_consistency(&dave_handle, false).unwrap();
+        assert_eq!(
+            check.issues,
+            vec![StorageIssue::SnapshotMismatch { version: 6 }]
+        );
+        assert!(!check.repaired);
+
+        let check = manager.check_consistency(&dave_handle, true).unwrap();
+        assert!(check.repaired);
+        assert!(manager
+            .check_consistency(&dave_handle, false)
+            .unwrap()
+            .is_ok());
+
+        let manager = AggregateStore::<Person>::create(
+            &storage_uri,
+            namespace!("person"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(5, manager.get_latest(&dave_handle).unwrap().age());
+
+        // A missing command cannot be repaired.
+        let command_key = Key::new_scoped(
+            Scope::from_segment(segment!("dave")),
+            segment!("command-4.json"),
+        );
+        kv.drop_key(&command_key).unwrap();
+
+        let check = manager.check_consistency(&dave_handle, true).unwrap();
+        assert!(check.issues.contains(&StorageIssue::VersionGap {
+            missing: 4,
+            next: 5
+        }));
+        assert!(!check.repaired);
+
+        // Neither can a command that cannot be read.
+        kv.store(&command_key, &"not a command").unwrap();
+        let check = manager.check_consistency(&dave_handle, true).unwrap();
+        assert!(matches!(
+            check.issues.as_slice(),
+            [StorageIssue::ReplayFailed(_)]
+        ));
+        assert!(!check.is_repairable());
+        assert!(!check.repaired);
+    }
 }
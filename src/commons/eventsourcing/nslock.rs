@@ -0,0 +1,147 @@
+//! Locks for whole namespaces.
+//!
+//! Transactions lock a single scope of a namespace, so a consistent copy
+//! of a whole namespace, e.g. for a backup, would need the locks of all
+//! scopes in it. Instead, every transaction in this process also holds the
+//! lock of its namespace shared, and a backup holds it exclusively while
+//! it reads the namespace.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError, RwLock, RwLockReadGuard,
+        RwLockWriteGuard,
+    },
+};
+
+use kvx::{Namespace, NamespaceBuf};
+use url::Url;
+
+//------------ Registry ------------------------------------------------------
+
+type Locks = HashMap<(Url, NamespaceBuf), Arc<NamespaceLock>>;
+
+static NAMESPACE_LOCKS: OnceLock<Mutex<Locks>> = OnceLock::new();
+
+/// Returns the lock for the namespace in the storage.
+pub(super) fn namespace_lock(
+    storage_uri: &Url,
+    namespace: &Namespace,
+) -> Arc<NamespaceLock> {
+    NAMESPACE_LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry((storage_uri.clone(), namespace.to_owned()))
+        .or_default()
+        .clone()
+}
+
+thread_local! {
+    /// The namespace locks held by the current thread.
+    static HELD: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+//------------ NamespaceLock -------------------------------------------------
+
+#[derive(Debug, Default)]
+pub struct NamespaceLock(RwLock<()>);
+
+impl NamespaceLock {
+    /// Holds the lock shared, unless the current thread holds it already.
+    ///
+    /// Transactions may be nested in other transactions for the same
+    /// namespace. These must not wait for a backup that waits for the
+    /// outer transaction.
+    pub(super) fn shared(&self) -> NamespaceGuard<'_> {
+        if self.is_held() {
+            return NamespaceGuard::default();
+        }
+        let guard = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        NamespaceGuard::new(self, Some(guard), None)
+    }
+
+    /// Holds the lock exclusively.
+    pub(super) fn exclusive(&self) -> NamespaceGuard<'_> {
+        let guard = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        NamespaceGuard::new(self, None, Some(guard))
+    }
+
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    fn is_held(&self) -> bool {
+        HELD.with(|held| held.borrow().contains(&self.id()))
+    }
+}
+
+//------------ NamespaceGuard ------------------------------------------------
+
+/// Keeps a namespace locked until it is dropped.
+#[derive(Default)]
+pub struct NamespaceGuard<'a> {
+    held: Option<usize>,
+    _shared: Option<RwLockReadGuard<'a, ()>>,
+    _exclusive: Option<RwLockWriteGuard<'a, ()>>,
+}
+
+impl<'a> NamespaceGuard<'a> {
+    /// Marks the lock as held by the current thread, until the guard is
+    /// dropped.
+    fn new(
+        lock: &NamespaceLock,
+        shared: Option<RwLockReadGuard<'a, ()>>,
+        exclusive: Option<RwLockWriteGuard<'a, ()>>,
+    ) -> Self {
+        HELD.with(|held| held.borrow_mut().push(lock.id()));
+        NamespaceGuard {
+            held: Some(lock.id()),
+            _shared: shared,
+            _exclusive: exclusive,
+        }
+    }
+}
+
+impl Drop for NamespaceGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.held {
+            HELD.with(|held| held.borrow_mut().retain(|held| *held != id));
+        }
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn nested_transactions_pass_a_waiting_backup() {
+        let lock = Arc::new(NamespaceLock::default());
+        let outer = lock.shared();
+
+        let (locked, done) = mpsc::channel();
+        let backup = thread::spawn({
+            let lock = lock.clone();
+            move || {
+                let _exclusive = lock.exclusive();
+                locked.send(()).unwrap();
+            }
+        });
+
+        // The backup waits for the outer transaction, which must still be
+        // able to start a nested one.
+        thread::sleep(Duration::from_millis(50));
+        drop(lock.shared());
+        assert!(done.try_recv().is_err());
+
+        drop(outer);
+        done.recv_timeout(Duration::from_secs(5)).unwrap();
+        backup.join().unwrap();
+    }
+}
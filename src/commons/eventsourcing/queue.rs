@@ -0,0 +1,622 @@
+//! Task queue support for the [`KeyValueStore`].
+//!
+//! kvx only implements its [`Queue`] trait for its own store type. Plain
+//! kvx backends use that implementation. Since our [`KeyValueStore`] may
+//! also use backends that kvx does not know about, such as SQLite or
+//! encrypted stores, we implement the same queue semantics here on top of
+//! any backend for those.
+
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use kvx::{
+    queue::{PendingTask, Queue, RunningTask, ScheduleMode},
+    KeyValueStoreBackend,
+};
+
+use super::{Key, KeyValueError, KeyValueStore, Scope, Segment, SegmentBuf};
+
+type Result<T> = std::result::Result<T, kvx::Error>;
+
+const SEPARATOR: char = '-';
+
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time-travel is not supported")
+        .as_millis()
+}
+
+//------------ TaskKey -------------------------------------------------------
+
+/// The name and time of a task, parsed from a "{timestamp}-{name}" key.
+struct TaskKey {
+    name: SegmentBuf,
+    timestamp_millis: u128,
+}
+
+impl TaskKey {
+    fn segment(&self) -> SegmentBuf {
+        SegmentBuf::from_str(&format!(
+            "{}{}{}",
+            self.timestamp_millis, SEPARATOR, self.name
+        ))
+        .unwrap() // cannot fail, the name is a valid segment
+    }
+
+    fn running_key(&self) -> Key {
+        Key::new_scoped(KeyValueStore::running_scope(), self.segment())
+    }
+
+    fn pending_key(&self) -> Key {
+        Key::new_scoped(KeyValueStore::pending_scope(), self.segment())
+    }
+}
+
+impl TryFrom<&Key> for TaskKey {
+    type Error = kvx::Error;
+
+    fn try_from(key: &Key) -> Result<Self> {
+        let (ts, name) = key
+            .name()
+            .as_str()
+            .split_once(SEPARATOR)
+            .ok_or(kvx::Error::InvalidKey)?;
+        Ok(TaskKey {
+            name: Segment::parse(name)?.to_owned(),
+            timestamp_millis: ts
+                .parse()
+                .map_err(|_| kvx::Error::InvalidKey)?,
+        })
+    }
+}
+
+/// Returns the parsed task keys in the given (pending or running) scope.
+fn task_keys(
+    kv: &dyn KeyValueStoreBackend,
+    scope: &Scope,
+) -> Result<Vec<TaskKey>> {
+    Ok(kv
+        .list_keys(scope)?
+        .iter()
+        .filter_map(|k| TaskKey::try_from(k).ok())
+        .collect())
+}
+
+//------------ Queue for KeyValueStore ---------------------------------------
+
+impl Queue for KeyValueStore {
+    fn pending_tasks_remaining(&self) -> Result<usize> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.pending_tasks_remaining(),
+            None => BackendQueue(self).pending_tasks_remaining(),
+        }
+    }
+
+    fn running_tasks_remaining(&self) -> Result<usize> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.running_tasks_remaining(),
+            None => BackendQueue(self).running_tasks_remaining(),
+        }
+    }
+
+    fn running_tasks_keys(&self) -> Result<Vec<Key>> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.running_tasks_keys(),
+            None => BackendQueue(self).running_tasks_keys(),
+        }
+    }
+
+    fn schedule_task(
+        &self,
+        name: SegmentBuf,
+        value: serde_json::Value,
+        timestamp_millis: Option<u128>,
+        mode: ScheduleMode,
+    ) -> Result<()> {
+        match self.as_kvx() {
+            Some(kvx) => {
+                kvx.schedule_task(name, value, timestamp_millis, mode)
+            }
+            None => BackendQueue(self).schedule_task(
+                name,
+                value,
+                timestamp_millis,
+                mode,
+            ),
+        }
+    }
+
+    fn pending_task_scheduled(
+        &self,
+        name: SegmentBuf,
+    ) -> Result<Option<u128>> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.pending_task_scheduled(name),
+            None => BackendQueue(self).pending_task_scheduled(name),
+        }
+    }
+
+    fn finish_running_task(&self, running: &Key) -> Result<()> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.finish_running_task(running),
+            None => BackendQueue(self).finish_running_task(running),
+        }
+    }
+
+    fn reschedule_running_task(
+        &self,
+        running: &Key,
+        timestamp_millis: Option<u128>,
+    ) -> Result<()> {
+        match self.as_kvx() {
+            Some(kvx) => {
+                kvx.reschedule_running_task(running, timestamp_millis)
+            }
+            None => BackendQueue(self)
+                .reschedule_running_task(running, timestamp_millis),
+        }
+    }
+
+    fn claim_scheduled_pending_task(&self) -> Result<Option<RunningTask>> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.claim_scheduled_pending_task(),
+            None => BackendQueue(self).claim_scheduled_pending_task(),
+        }
+    }
+
+    fn reschedule_long_running_tasks(
+        &self,
+        reschedule_after: Option<&Duration>,
+    ) -> Result<()> {
+        match self.as_kvx() {
+            Some(kvx) => kvx.reschedule_long_running_tasks(reschedule_after),
+            None => BackendQueue(self)
+                .reschedule_long_running_tasks(reschedule_after),
+        }
+    }
+}
+
+//------------ BackendQueue --------------------------------------------------
+
+/// The queue for stores that do not use a plain kvx backend.
+struct BackendQueue<'a>(&'a KeyValueStore);
+
+impl BackendQueue<'_> {
+    fn execute_kvx<F, T>(&self, scope: &Scope, op: F) -> Result<T>
+    where
+        F: FnMut(&dyn KeyValueStoreBackend) -> Result<T>,
+    {
+        self.0.execute_kvx(scope, op)
+    }
+}
+
+impl Queue for BackendQueue<'_> {
+    fn pending_tasks_remaining(&self) -> Result<usize> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            kv.list_keys(&Self::pending_scope()).map(|list| list.len())
+        })
+    }
+
+    fn running_tasks_remaining(&self) -> Result<usize> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            kv.list_keys(&Self::running_scope()).map(|list| list.len())
+        })
+    }
+
+    fn running_tasks_keys(&self) -> Result<Vec<Key>> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            kv.list_keys(&Self::running_scope())
+        })
+    }
+
+    fn schedule_task(
+        &self,
+        name: SegmentBuf,
+        value: serde_json::Value,
+        timestamp_millis: Option<u128>,
+        mode: ScheduleMode,
+    ) -> Result<()> {
+        let timestamp_millis = timestamp_millis.unwrap_or_else(now);
+
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            let running = task_keys(kv, &Self::running_scope())?
+                .into_iter()
+                .find(|running| running.name == name);
+
+            let pending = task_keys(kv, &Self::pending_scope())?
+                .into_iter()
+                .find(|pending| pending.name == name);
+
+            let mut new_task = PendingTask {
+                name: name.clone(),
+                timestamp_millis,
+                value: value.clone(),
+            };
+
+            let finish_running = matches!(
+                mode,
+                ScheduleMode::FinishOrReplaceExisting
+                    | ScheduleMode::FinishOrReplaceExistingSoonest
+            );
+            let keep_soonest = matches!(
+                mode,
+                ScheduleMode::ReplaceExistingSoonest
+                    | ScheduleMode::FinishOrReplaceExistingSoonest
+            );
+
+            if let ScheduleMode::IfMissing = mode {
+                if pending.is_some() || running.is_some() {
+                    // nothing to do, there is something
+                    return Ok(());
+                }
+            }
+
+            if finish_running {
+                if let Some(running) = running {
+                    kv.delete(&running.running_key())?;
+                }
+            }
+
+            if let Some(pending) = pending {
+                if keep_soonest {
+                    new_task.timestamp_millis = new_task
+                        .timestamp_millis
+                        .min(pending.timestamp_millis);
+                }
+                kv.delete(&pending.pending_key())?;
+            }
+
+            kv.store(&Key::from(&new_task), new_task.value)
+        })
+    }
+
+    fn pending_task_scheduled(
+        &self,
+        name: SegmentBuf,
+    ) -> Result<Option<u128>> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            Ok(task_keys(kv, &Self::pending_scope())?
+                .into_iter()
+                .find(|pending| pending.name == name)
+                .map(|pending| pending.timestamp_millis))
+        })
+    }
+
+    fn finish_running_task(&self, running: &Key) -> Result<()> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            if kv.has(running)? {
+                kv.delete(running)
+            } else {
+                Err(kvx::Error::Other(format!(
+                    "Cannot finish task {}. It is not running.",
+                    running
+                )))
+            }
+        })
+    }
+
+    fn reschedule_running_task(
+        &self,
+        running: &Key,
+        timestamp_millis: Option<u128>,
+    ) -> Result<()> {
+        let mut task_key = TaskKey::try_from(running)?;
+        task_key.timestamp_millis = timestamp_millis.unwrap_or_else(now);
+        let pending_key = task_key.pending_key();
+
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            kv.move_value(running, &pending_key)
+        })
+    }
+
+    fn claim_scheduled_pending_task(&self) -> Result<Option<RunningTask>> {
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            let tasks_before = now();
+
+            let pending = match task_keys(kv, &Self::pending_scope())?
+                .into_iter()
+                .filter(|pending| pending.timestamp_millis <= tasks_before)
+                .min_by_key(|pending| pending.timestamp_millis)
+            {
+                Some(pending) => pending,
+                None => return Ok(None),
+            };
+
+            let pending_key = pending.pending_key();
+            let value = match kv.get(&pending_key)? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+
+            let mut running_task = RunningTask {
+                name: pending.name,
+                timestamp_millis: tasks_before,
+                value,
+            };
+            let mut running_key = Key::from(&running_task);
+
+            // Avoid a name collision with an existing running task by
+            // moving the start time forward, rather than waiting for the
+            // clock while the transaction is open.
+            while kv.has(&running_key)? {
+                running_task.timestamp_millis += 1;
+                running_key = Key::from(&running_task);
+            }
+
+            kv.move_value(&pending_key, &running_key)?;
+
+            Ok(Some(running_task))
+        })
+    }
+
+    fn reschedule_long_running_tasks(
+        &self,
+        reschedule_after: Option<&Duration>,
+    ) -> Result<()> {
+        let now = now();
+        let reschedule_after =
+            reschedule_after.unwrap_or(&KeyValueStore::RESCHEDULE_AFTER);
+        let reschedule_timeout =
+            now.saturating_sub(reschedule_after.as_millis());
+
+        self.execute_kvx(&Self::lock_scope(), |kv| {
+            for running in task_keys(kv, &Self::running_scope())? {
+                if running.timestamp_millis <= reschedule_timeout {
+                    let pending = TaskKey {
+                        name: running.name.clone(),
+                        timestamp_millis: now,
+                    };
+                    let _ = kv.move_value(
+                        &running.running_key(),
+                        &pending.pending_key(),
+                    );
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+//------------ Introspection and Control ------------------------------------
+
+/// A pending or running task in the queue.
+#[derive(Clone, Debug)]
+pub struct QueuedTask {
+    pub name: SegmentBuf,
+
+    /// The time the task is scheduled for if it is pending, or the time it
+    /// was started if it is running.
+    pub timestamp_millis: u128,
+
+    pub running: bool,
+    pub value: serde_json::Value,
+}
+
+impl KeyValueStore {
+    /// Returns all running and pending tasks.
+    pub fn queued_tasks(
+        &self,
+    ) -> std::result::Result<Vec<QueuedTask>, KeyValueError> {
+        self.execute(&Self::lock_scope(), |kv| {
+            let mut tasks = vec![];
+            for (scope, running) in [
+                (Self::running_scope(), true),
+                (Self::pending_scope(), false),
+            ] {
+                for task_key in task_keys(kv, &scope)? {
+                    let key = if running {
+                        task_key.running_key()
+                    } else {
+                        task_key.pending_key()
+                    };
+                    if let Some(value) = kv.get(&key)? {
+                        tasks.push(QueuedTask {
+                            name: task_key.name,
+                            timestamp_millis: task_key.timestamp_millis,
+                            running,
+                            value,
+                        });
+                    }
+                }
+            }
+            Ok(tasks)
+        })
+    }
+
+    /// Reschedules the task with the given name.
+    ///
+    /// A pending task is moved to the given time. If the task is only
+    /// running then it is moved back to pending, so that it is picked up
+    /// again. Returns whether the task was found.
+    pub fn reschedule_task_by_name(
+        &self,
+        name: &Segment,
+        timestamp_millis: u128,
+    ) -> std::result::Result<bool, KeyValueError> {
+        self.execute(&Self::lock_scope(), |kv| {
+            let pending = task_keys(kv, &Self::pending_scope())?
+                .into_iter()
+                .find(|pending| pending.name.as_ref() == name)
+                .map(|pending| pending.pending_key());
+            let existing = match pending {
+                Some(key) => Some(key),
+                None => task_keys(kv, &Self::running_scope())?
+                    .into_iter()
+                    .find(|running| running.name.as_ref() == name)
+                    .map(|running| running.running_key()),
+            };
+
+            match existing {
+                Some(key) => {
+                    let pending = TaskKey {
+                        name: name.to_owned(),
+                        timestamp_millis,
+                    };
+                    kv.move_value(&key, &pending.pending_key())?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+
+    /// Removes the pending and running tasks with the given name. Returns
+    /// whether any task was found.
+    pub fn drop_task_by_name(
+        &self,
+        name: &Segment,
+    ) -> std::result::Result<bool, KeyValueError> {
+        self.execute(&Self::lock_scope(), |kv| {
+            let mut found = false;
+            for task_key in task_keys(kv, &Self::pending_scope())? {
+                if task_key.name.as_ref() == name {
+                    kv.delete(&task_key.pending_key())?;
+                    found = true;
+                }
+            }
+            for task_key in task_keys(kv, &Self::running_scope())? {
+                if task_key.name.as_ref() == name {
+                    kv.delete(&task_key.running_key())?;
+                    found = true;
+                }
+            }
+            Ok(found)
+        })
+    }
+
+    /// Reschedules a running task with a new value. Returns false, and
+    /// leaves the queue as is, if the task is no longer running.
+    pub fn reschedule_running_task_with_value(
+        &self,
+        running: &Key,
+        value: serde_json::Value,
+        timestamp_millis: u128,
+    ) -> std::result::Result<bool, KeyValueError> {
+        let mut task_key =
+            TaskKey::try_from(running).map_err(KeyValueError::Inner)?;
+        task_key.timestamp_millis = timestamp_millis;
+        let pending_key = task_key.pending_key();
+
+        self.execute(&Self::lock_scope(), |kv| {
+            if !kv.has(running)? {
+                return Ok(false);
+            }
+            kv.delete(running)?;
+            kv.store(&pending_key, value.clone())?;
+            Ok(true)
+        })
+    }
+
+    /// Returns whether the given running task is still running, i.e. it
+    /// was not rescheduled or dropped by an operator while it was running.
+    pub fn is_running_task(
+        &self,
+        running: &Key,
+    ) -> std::result::Result<bool, KeyValueError> {
+        self.execute(&Self::lock_scope(), |kv| kv.has(running))
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        commons::eventsourcing::{namespace, Namespace},
+        test::mem_storage,
+    };
+
+    fn queue(storage_uri: &url::Url) -> KeyValueStore {
+        KeyValueStore::create(storage_uri, namespace!("tasks")).unwrap()
+    }
+
+    fn check_claim_and_finish(q: &KeyValueStore) {
+        let name = SegmentBuf::from_str("task").unwrap();
+
+        q.schedule_task(
+            name.clone(),
+            serde_json::Value::from(1),
+            Some(now() + 60_000),
+            ScheduleMode::ReplaceExisting,
+        )
+        .unwrap();
+        assert!(q.claim_scheduled_pending_task().unwrap().is_none());
+
+        q.schedule_task(
+            name.clone(),
+            serde_json::Value::from(2),
+            Some(now()),
+            ScheduleMode::ReplaceExistingSoonest,
+        )
+        .unwrap();
+        assert_eq!(q.pending_tasks_remaining().unwrap(), 1);
+
+        let running = q.claim_scheduled_pending_task().unwrap().unwrap();
+        assert_eq!(running.name, name);
+        assert_eq!(running.value, serde_json::Value::from(2));
+        assert_eq!(q.pending_tasks_remaining().unwrap(), 0);
+        assert_eq!(q.running_tasks_remaining().unwrap(), 1);
+
+        // Not scheduled again while it is running.
+        q.schedule_task(
+            name.clone(),
+            serde_json::Value::from(3),
+            None,
+            ScheduleMode::IfMissing,
+        )
+        .unwrap();
+        assert_eq!(q.pending_tasks_remaining().unwrap(), 0);
+
+        q.finish_running_task(&Key::from(&running)).unwrap();
+        assert_eq!(q.running_tasks_remaining().unwrap(), 0);
+    }
+
+    #[test]
+    fn reschedule_and_drop_task_by_name() {
+        let q = queue(&mem_storage());
+        let name = SegmentBuf::from_str("task").unwrap();
+        q.schedule_task(
+            name.clone(),
+            serde_json::Value::from(1),
+            Some(now() + 60_000),
+            ScheduleMode::ReplaceExisting,
+        )
+        .unwrap();
+
+        let tasks = q.queued_tasks().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert!(!tasks[0].running);
+        assert_eq!(tasks[0].value, serde_json::Value::from(1));
+
+        assert!(q.reschedule_task_by_name(&name, now()).unwrap());
+        let running = q.claim_scheduled_pending_task().unwrap().unwrap();
+        assert!(q.queued_tasks().unwrap()[0].running);
+
+        // A running task is moved back to pending.
+        assert!(q.reschedule_task_by_name(&name, now()).unwrap());
+        assert!(!q.is_running_task(&Key::from(&running)).unwrap());
+        assert_eq!(q.pending_tasks_remaining().unwrap(), 1);
+
+        assert!(q.drop_task_by_name(&name).unwrap());
+        assert!(q.queued_tasks().unwrap().is_empty());
+        assert!(!q.drop_task_by_name(&name).unwrap());
+    }
+
+    #[test]
+    fn claim_and_finish_task() {
+        check_claim_and_finish(&queue(&mem_storage()));
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn claim_and_finish_task_sqlite() {
+        let (dir, cleanup) = crate::test::tmp_dir();
+        check_claim_and_finish(&queue(&crate::test::sqlite_storage(&dir)));
+        cleanup();
+    }
+}
@@ -0,0 +1,725 @@
+//! SQLite based backend for the [`KeyValueStore`].
+//!
+//! All namespaces, scopes and keys are kept in a single table in one
+//! database file. This means that a backup of the database file is a
+//! consistent backup of all Krill data, and that operations executed
+//! through [`KeyValueStore::execute`] are real transactions.
+//!
+//! Stores in this process that use the same database file share a pool
+//! of connections. Reads outside of a transaction use any idle connection,
+//! so they do not wait for each other, nor for a writer, as the database
+//! uses write-ahead logging. A scope 'lock' is implemented as an immediate
+//! (write) transaction, which SQLite serializes across all connections and
+//! processes using the database file. Transactions started (on any
+//! namespace of the same database) from within the closure of another
+//! transaction on the same thread use the same connection and are nested
+//! using savepoints. This is needed because e.g. event listeners for an
+//! aggregate in one namespace will typically schedule tasks in the 'tasks'
+//! namespace while the aggregate's scope is still locked.
+//!
+//! [`KeyValueStore`]: super::KeyValueStore
+//! [`KeyValueStore::execute`]: super::KeyValueStore::execute
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock, Weak},
+    time::Duration,
+};
+
+use kvx::{
+    Key, KeyValueStoreBackend, Namespace, NamespaceBuf, ReadStore, Scope,
+    WriteStore,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use url::Url;
+
+/// The time a connection will wait for a write lock held by another
+/// connection to the same database file, in this or another process, e.g.
+/// `krillup`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum number of idle connections kept open per database file.
+const MAX_IDLE_CONNECTIONS: usize = 8;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS krill_kv (
+        namespace   TEXT NOT NULL,
+        scope       TEXT NOT NULL,
+        name        TEXT NOT NULL,
+        value       TEXT NOT NULL,
+        PRIMARY KEY (namespace, scope, name)
+    ) WITHOUT ROWID;
+";
+
+type Result<T> = std::result::Result<T, kvx::Error>;
+
+fn db_err(e: rusqlite::Error) -> kvx::Error {
+    kvx::Error::Other(format!("sqlite error: {}", e))
+}
+
+//------------ Database ------------------------------------------------------
+
+/// A database file, with the pool of connections that all stores in this
+/// process use to access it.
+struct Database {
+    path: PathBuf,
+    /// Idle connections that can be used by any thread.
+    idle: Mutex<Vec<Connection>>,
+}
+
+static DATABASES: OnceLock<Mutex<HashMap<PathBuf, Weak<Database>>>> =
+    OnceLock::new();
+
+thread_local! {
+    /// Connections used by the current thread for a transaction, by
+    /// database path.
+    static ACTIVE: RefCell<HashMap<PathBuf, Rc<Connection>>> =
+        RefCell::new(HashMap::new());
+}
+
+impl Database {
+    /// Returns the shared database for the given path, opening it if
+    /// needed.
+    fn open(path: &Path) -> Result<Arc<Self>> {
+        let mut databases = DATABASES
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .map_err(|e| kvx::Error::MutexLock(e.to_string()))?;
+
+        if let Some(db) = databases.get(path).and_then(Weak::upgrade) {
+            return Ok(db);
+        }
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    kvx::Error::IoWithContext(
+                        format!("Cannot create dir {}", parent.display()),
+                        e,
+                    )
+                })?;
+            }
+        }
+
+        let connection = Self::connect(path)?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_err)?;
+        connection.execute_batch(SCHEMA).map_err(db_err)?;
+
+        let db = Arc::new(Database {
+            path: path.to_path_buf(),
+            idle: Mutex::new(vec![connection]),
+        });
+
+        databases.insert(path.to_path_buf(), Arc::downgrade(&db));
+
+        Ok(db)
+    }
+
+    /// Opens a new connection to the database file.
+    fn connect(path: &Path) -> Result<Connection> {
+        let connection = Connection::open(path).map_err(db_err)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(db_err)?;
+        Ok(connection)
+    }
+
+    /// Returns the connection used by this thread for a transaction.
+    fn active(&self) -> Option<Rc<Connection>> {
+        ACTIVE.with(|active| active.borrow().get(&self.path).cloned())
+    }
+
+    /// Takes an idle connection from the pool, or opens a new one.
+    fn take(&self) -> Result<Connection> {
+        let idle = self
+            .idle
+            .lock()
+            .map_err(|e| kvx::Error::MutexLock(e.to_string()))?
+            .pop();
+        match idle {
+            Some(connection) => Ok(connection),
+            None => Self::connect(&self.path),
+        }
+    }
+
+    /// Returns a connection to the pool, or closes it if there are enough
+    /// idle connections already.
+    fn give_back(&self, connection: Connection) {
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < MAX_IDLE_CONNECTIONS {
+                idle.push(connection);
+            }
+        }
+    }
+
+    /// Runs the operation using the connection of the transaction of this
+    /// thread, or using a connection from the pool.
+    fn with_connection<T>(
+        &self,
+        op: impl FnOnce(&Connection) -> Result<T>,
+    ) -> Result<T> {
+        match self.active() {
+            Some(connection) => op(&connection),
+            None => {
+                let connection = self.take()?;
+                let res = op(&connection);
+                self.give_back(connection);
+                res
+            }
+        }
+    }
+
+    /// Runs the operation inside a transaction. Nested transactions on the
+    /// same thread use savepoints.
+    fn transaction(&self, op: impl FnOnce() -> Result<()>) -> Result<()> {
+        if let Some(connection) = self.active() {
+            connection
+                .execute_batch("SAVEPOINT nested")
+                .map_err(db_err)?;
+            match op() {
+                Ok(()) => {
+                    connection.execute_batch("RELEASE nested").map_err(db_err)
+                }
+                Err(e) => {
+                    connection
+                        .execute_batch("ROLLBACK TO nested; RELEASE nested")
+                        .map_err(db_err)?;
+                    Err(e)
+                }
+            }
+        } else {
+            let connection = self.take()?;
+            if let Err(e) = connection.execute_batch("BEGIN IMMEDIATE") {
+                self.give_back(connection);
+                return Err(db_err(e));
+            }
+
+            ACTIVE.with(|active| {
+                active
+                    .borrow_mut()
+                    .insert(self.path.clone(), Rc::new(connection))
+            });
+
+            let res = op();
+
+            let connection = ACTIVE
+                .with(|active| active.borrow_mut().remove(&self.path))
+                .and_then(|rc| Rc::try_unwrap(rc).ok())
+                .ok_or_else(|| {
+                    kvx::Error::Other(
+                        "sqlite connection still in use after transaction"
+                            .to_string(),
+                    )
+                })?;
+
+            let end = match &res {
+                Ok(()) => connection.execute_batch("COMMIT"),
+                Err(_) => connection.execute_batch("ROLLBACK"),
+            };
+            self.give_back(connection);
+
+            res.and(end.map_err(db_err))
+        }
+    }
+}
+
+//------------ SqliteStore ---------------------------------------------------
+
+/// A key value store backend for a single namespace in an SQLite database.
+pub struct SqliteStore {
+    db: Arc<Database>,
+    namespace: NamespaceBuf,
+}
+
+impl SqliteStore {
+    /// Creates a store for the namespace in the database file referenced
+    /// by the `sqlite://` URI. The database file and its parent
+    /// directories are created if needed.
+    pub fn new(storage_uri: &Url, namespace: &Namespace) -> Result<Self> {
+        let path = PathBuf::from(format!(
+            "{}{}",
+            storage_uri.host_str().unwrap_or_default(),
+            storage_uri.path()
+        ));
+
+        let db = Database::open(&path)?;
+        Ok(SqliteStore {
+            db,
+            namespace: namespace.to_owned(),
+        })
+    }
+
+    fn scope_str(scope: &Scope) -> String {
+        scope.to_string()
+    }
+
+    fn parse_scope(scope: &str) -> Result<Scope> {
+        if scope.is_empty() {
+            Ok(Scope::global())
+        } else {
+            Scope::from_str(scope).map_err(kvx::Error::from)
+        }
+    }
+
+    /// Returns the SQL condition and parameters matching all rows for
+    /// the given scope, including its sub-scopes.
+    fn scope_filter(scope: &Scope) -> (String, usize) {
+        let prefix = format!("{}{}", scope, Scope::SEPARATOR);
+        let len = prefix.chars().count();
+        (prefix, len)
+    }
+}
+
+impl fmt::Debug for SqliteStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteStore")
+            .field("path", &self.db.path)
+            .field("namespace", &self.namespace)
+            .finish()
+    }
+}
+
+impl fmt::Display for SqliteStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "KeyValueStore::Sqlite({}, {})",
+            self.db.path.display(),
+            self.namespace
+        )
+    }
+}
+
+impl ReadStore for SqliteStore {
+    fn is_empty(&self) -> Result<bool> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT NOT EXISTS (SELECT 1 FROM krill_kv WHERE namespace = ?1)",
+                params![self.namespace.as_str()],
+                |row| row.get(0),
+            )
+            .map_err(db_err)
+        })
+    }
+
+    fn has(&self, key: &Key) -> Result<bool> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM krill_kv WHERE namespace = ?1 AND scope = ?2 AND name = ?3)",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(key.scope()),
+                    key.name().as_str()
+                ],
+                |row| row.get(0),
+            )
+            .map_err(db_err)
+        })
+    }
+
+    fn has_scope(&self, scope: &Scope) -> Result<bool> {
+        if scope.is_global() {
+            return self.is_empty().map(|empty| !empty);
+        }
+        let (prefix, len) = Self::scope_filter(scope);
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM krill_kv WHERE namespace = ?1 AND (scope = ?2 OR substr(scope, 1, ?3) = ?4))",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(scope),
+                    len,
+                    prefix
+                ],
+                |row| row.get(0),
+            )
+            .map_err(db_err)
+        })
+    }
+
+    fn get(&self, key: &Key) -> Result<Option<Value>> {
+        let json: Option<String> = self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT value FROM krill_kv WHERE namespace = ?1 AND scope = ?2 AND name = ?3",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(key.scope()),
+                    key.name().as_str()
+                ],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_err)
+        })?;
+
+        match json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn list_keys(&self, scope: &Scope) -> Result<Vec<Key>> {
+        let (prefix, len) = Self::scope_filter(scope);
+        let rows: Vec<(String, String)> = self.db.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT scope, name FROM krill_kv WHERE namespace = ?1 AND (?2 = '' OR scope = ?2 OR substr(scope, 1, ?3) = ?4)",
+                )
+                .map_err(db_err)?;
+            let rows = stmt
+                .query_map(
+                    params![
+                        self.namespace.as_str(),
+                        Self::scope_str(scope),
+                        len,
+                        prefix
+                    ],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(db_err)?;
+            rows.collect::<rusqlite::Result<_>>().map_err(db_err)
+        })?;
+
+        rows.into_iter()
+            .map(|(scope, name)| {
+                Ok(Key::new_scoped(
+                    Self::parse_scope(&scope)?,
+                    kvx::SegmentBuf::from_str(&name)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn list_scopes(&self) -> Result<Vec<Scope>> {
+        let scopes: Vec<String> = self.db.with_connection(|conn| {
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT DISTINCT scope FROM krill_kv WHERE namespace = ?1 AND scope != ''",
+                )
+                .map_err(db_err)?;
+            let rows = stmt
+                .query_map(params![self.namespace.as_str()], |row| row.get(0))
+                .map_err(db_err)?;
+            rows.collect::<rusqlite::Result<_>>().map_err(db_err)
+        })?;
+
+        let mut res = BTreeSet::new();
+        for scope in scopes {
+            res.extend(Self::parse_scope(&scope)?.sub_scopes());
+        }
+        Ok(res.into_iter().collect())
+    }
+}
+
+impl WriteStore for SqliteStore {
+    fn store(&self, key: &Key, value: Value) -> Result<()> {
+        let json = serde_json::to_string(&value)?;
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO krill_kv (namespace, scope, name, value) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(key.scope()),
+                    key.name().as_str(),
+                    json
+                ],
+            )
+            .map(|_| ())
+            .map_err(db_err)
+        })
+    }
+
+    fn move_value(&self, from: &Key, to: &Key) -> Result<()> {
+        self.transaction(&Scope::global(), &mut |_| {
+            let value = self.get(from)?.ok_or(kvx::Error::UnknownKey)?;
+            self.delete(from)?;
+            self.store(to, value)
+        })
+    }
+
+    fn move_scope(&self, from: &Scope, to: &Scope) -> Result<()> {
+        self.transaction(&Scope::global(), &mut |_| {
+            for key in self.list_keys(from)? {
+                // replace the 'from' prefix of the scope with 'to'
+                let mut segments = to.as_vec().clone();
+                segments.extend(
+                    key.scope()
+                        .as_vec()
+                        .iter()
+                        .skip(from.as_vec().len())
+                        .cloned(),
+                );
+                let new_key =
+                    Key::new_scoped(Scope::new(segments), key.name());
+                self.move_value(&key, &new_key)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn delete(&self, key: &Key) -> Result<()> {
+        let deleted = self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM krill_kv WHERE namespace = ?1 AND scope = ?2 AND name = ?3",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(key.scope()),
+                    key.name().as_str()
+                ],
+            )
+            .map_err(db_err)
+        })?;
+
+        if deleted == 0 {
+            Err(kvx::Error::UnknownKey)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn delete_scope(&self, scope: &Scope) -> Result<()> {
+        if scope.is_global() {
+            return self.clear();
+        }
+        let (prefix, len) = Self::scope_filter(scope);
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM krill_kv WHERE namespace = ?1 AND (scope = ?2 OR substr(scope, 1, ?3) = ?4)",
+                params![
+                    self.namespace.as_str(),
+                    Self::scope_str(scope),
+                    len,
+                    prefix
+                ],
+            )
+            .map(|_| ())
+            .map_err(db_err)
+        })
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "DELETE FROM krill_kv WHERE namespace = ?1",
+                params![self.namespace.as_str()],
+            )
+            .map(|_| ())
+            .map_err(db_err)
+        })
+    }
+
+    fn migrate_namespace(&mut self, to: NamespaceBuf) -> Result<()> {
+        let target = SqliteStore {
+            db: self.db.clone(),
+            namespace: to.clone(),
+        };
+
+        self.transaction(&Scope::global(), &mut |_| {
+            if !target.is_empty()? {
+                return Err(kvx::Error::NamespaceMigration(format!(
+                    "target sqlite namespace {} is not empty",
+                    to
+                )));
+            }
+            self.db.with_connection(|conn| {
+                conn.execute(
+                    "UPDATE krill_kv SET namespace = ?1 WHERE namespace = ?2",
+                    params![to.as_str(), self.namespace.as_str()],
+                )
+                .map(|_| ())
+                .map_err(db_err)
+            })
+        })?;
+
+        self.namespace = to;
+        Ok(())
+    }
+}
+
+impl KeyValueStoreBackend for SqliteStore {
+    fn transaction(
+        &self,
+        _scope: &Scope,
+        callback: &mut dyn FnMut(&dyn KeyValueStoreBackend) -> Result<()>,
+    ) -> Result<()> {
+        self.db.transaction(|| callback(self))
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use kvx::{namespace, segment, Segment};
+
+    use crate::{
+        commons::eventsourcing::KeyValueStore,
+        test::{sqlite_storage, tmp_dir},
+    };
+
+    #[test]
+    fn nested_transactions_share_connection() {
+        let (dir, cleanup) = tmp_dir();
+        let storage_uri = sqlite_storage(&dir);
+
+        let outer =
+            KeyValueStore::create(&storage_uri, namespace!("outer")).unwrap();
+        let inner =
+            KeyValueStore::create(&storage_uri, namespace!("inner")).unwrap();
+
+        let outer_key = Key::new_global(segment!("outer"));
+        let inner_key = Key::new_global(segment!("inner"));
+
+        outer
+            .execute(&Scope::global(), |kv| {
+                kv.store(&outer_key, Value::from(1))?;
+                inner.store(&inner_key, &2).unwrap();
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(outer.get::<u32>(&outer_key).unwrap(), Some(1));
+        assert_eq!(inner.get::<u32>(&inner_key).unwrap(), Some(2));
+
+        cleanup();
+    }
+
+    #[test]
+    fn reads_do_not_wait_for_transaction() {
+        let (dir, cleanup) = tmp_dir();
+        let storage_uri = sqlite_storage(&dir);
+        let store =
+            KeyValueStore::create(&storage_uri, namespace!("readers"))
+                .unwrap();
+
+        let key = Key::new_global(segment!("key"));
+        store.store(&key, &"before").unwrap();
+
+        store
+            .execute(&Scope::global(), |kv| {
+                kv.store(&key, Value::from("after"))?;
+
+                // Another thread sees the last committed value, rather than
+                // waiting for this transaction to finish.
+                let read = std::thread::scope(|s| {
+                    s.spawn(|| store.get::<String>(&key).unwrap())
+                        .join()
+                        .unwrap()
+                });
+                assert_eq!(read, Some("before".to_string()));
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(
+            store.get::<String>(&key).unwrap(),
+            Some("after".to_string())
+        );
+
+        cleanup();
+    }
+
+    #[test]
+    fn failed_transaction_is_rolled_back() {
+        let (dir, cleanup) = tmp_dir();
+        let storage_uri = sqlite_storage(&dir);
+        let store =
+            KeyValueStore::create(&storage_uri, namespace!("rollback"))
+                .unwrap();
+
+        let key = Key::new_global(segment!("key"));
+        store.store(&key, &"before").unwrap();
+
+        let res = store.execute(&Scope::global(), |kv| {
+            kv.store(&key, Value::from("after"))?;
+            Err::<(), _>(kvx::Error::Unknown)
+        });
+
+        assert!(res.is_err());
+        assert_eq!(
+            store.get::<String>(&key).unwrap(),
+            Some("before".to_string())
+        );
+
+        cleanup();
+    }
+
+    #[test]
+    fn list_keys_includes_sub_scopes_only() {
+        let (dir, cleanup) = tmp_dir();
+        let storage_uri = sqlite_storage(&dir);
+        let store = KeyValueStore::create(&storage_uri, namespace!("scopes"))
+            .unwrap();
+
+        let ca = Scope::from_segment(segment!("ca"));
+        let ca_sub = ca.with_sub_scope(segment!("sub"));
+        let other = Scope::from_segment(segment!("ca_other"));
+
+        let key = Key::new_scoped(ca.clone(), segment!("a"));
+        let sub_key = Key::new_scoped(ca_sub.clone(), segment!("b"));
+        let other_key = Key::new_scoped(other.clone(), segment!("c"));
+
+        for key in [&key, &sub_key, &other_key] {
+            store.store(key, &"value").unwrap();
+        }
+
+        assert_eq!(store.keys(&ca, "").unwrap(), vec![key]);
+        assert!(store.has_scope(&ca_sub).unwrap());
+
+        let mut scopes = store.scopes().unwrap();
+        scopes.sort();
+        let mut expected = vec![ca.clone(), ca_sub, other];
+        expected.sort();
+        assert_eq!(scopes, expected);
+
+        store.drop_scope(&ca).unwrap();
+        assert!(!store.has(&sub_key).unwrap());
+        assert!(store.has(&other_key).unwrap());
+
+        cleanup();
+    }
+
+    #[test]
+    fn import_from_disk() {
+        let (source_dir, cleanup) = tmp_dir();
+        let source_uri =
+            Url::parse(&format!("local://{}/", source_dir.display()))
+                .unwrap();
+        let source =
+            KeyValueStore::create(&source_uri, namespace!("cas")).unwrap();
+
+        let scope = Scope::from_segment(Segment::parse("ca").unwrap());
+        let key = Key::new_scoped(scope, segment!("command-1.json"));
+        let global = Key::new_global(segment!("version"));
+        source.store(&key, &"command").unwrap();
+        source.store(&global, &"0.14.5").unwrap();
+
+        let (target_dir, target_cleanup) = tmp_dir();
+        let target = KeyValueStore::create(
+            &sqlite_storage(&target_dir),
+            namespace!("cas"),
+        )
+        .unwrap();
+        target.import(&source).unwrap();
+
+        assert_eq!(
+            target.get::<String>(&key).unwrap(),
+            Some("command".to_string())
+        );
+        assert_eq!(
+            target.get::<String>(&global).unwrap(),
+            Some("0.14.5".to_string())
+        );
+
+        cleanup();
+        target_cleanup();
+    }
+}
@@ -1,22 +1,31 @@
 use std::{
     collections::HashMap,
     fmt,
+    io::{Read, Write},
     str::FromStr,
     sync::{Arc, Mutex, RwLock},
 };
 
-use kvx::Namespace;
+use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
+use base64::engine::Engine as _;
+use chrono::SecondsFormat;
+use kvx::{KeyValueStoreBackend, Namespace};
+use libflate::gzip;
 use rpki::{ca::idexchange::MyHandle, repository::x509::Time};
 use serde::{de::DeserializeOwned, Serialize};
 use url::Url;
 
 use crate::commons::{
-    api::{CommandHistory, CommandHistoryCriteria, CommandHistoryRecord},
+    api::{
+        CommandHistory, CommandHistoryCriteria, CommandHistoryRecord,
+        HistoryCompactionReport, HistoryRetention,
+    },
     error::KrillIoError,
     eventsourcing::{
-        cmd::Command, segment, Aggregate, Key, KeyValueError, KeyValueStore,
-        PostSaveEventListener, PreSaveEventListener, Scope, Segment,
-        SegmentExt, StoredCommand, StoredCommandBuilder,
+        check::equivalent_values, cmd::Command, segment, Aggregate, Key,
+        KeyValueError, KeyValueStore, PostSaveEventListener,
+        PreSaveEventListener, Scope, Segment, SegmentExt, StorageCheck,
+        StorageIssue, StoredCommand, StoredCommandBuilder,
     },
 };
 
@@ -115,6 +124,17 @@ impl<A: Aggregate> AggregateStore<A> {
         Ok(())
     }
 
+    /// Drops all cached aggregates and command histories.
+    ///
+    /// To be used when another node may have changed the store, e.g. when
+    /// this node takes over as leader.
+    pub fn clear_cache(&self) {
+        self.cache.write().unwrap().clear();
+        if let Some(mutex) = &self.history_cache {
+            mutex.lock().unwrap().clear();
+        }
+    }
+
     /// Adds a listener that will receive all events before they are stored.
     pub fn add_pre_save_listener<L: PreSaveEventListener<A>>(
         &mut self,
@@ -278,33 +298,41 @@ where
                         changed_from_cached = true;
 
                         let snapshot_key = Self::key_for_snapshot(handle);
-                        match kv.get(&snapshot_key)? {
-                            Some(value) => {
+                        let snapshot: Option<A> = match kv.get(&snapshot_key)? {
+                            Some(value) => Some(serde_json::from_value(value)?),
+                            None => None,
+                        };
+
+                        // Use the snapshot, unless the commands that follow
+                        // it were archived. That happens if the snapshot was
+                        // restored from an older backup, for example.
+                        let snapshot = match snapshot {
+                            Some(agg) if !kv.has(&Self::key_for_command(handle, agg.version()))?
+                                && Self::archive_ranges(kv, &Self::scope_for_agg(handle))?
+                                    .iter()
+                                    .any(|(first, last)| *first <= agg.version() && agg.version() <= *last) =>
+                            {
+                                warn!("Snapshot for {handle} is older than its archived commands, replaying them");
+                                None
+                            }
+                            snapshot => snapshot,
+                        };
+
+                        match snapshot {
+                            Some(agg) => {
                                 trace!("found snapshot for {handle}");
-                                let agg: A = serde_json::from_value(value)?;
                                 Ok(Arc::new(agg))
                             }
                             None => {
-                                let init_key = Self::key_for_command(handle, 0);
-                                match kv.get(&init_key)? {
-                                    Some(value) => {
-                                        trace!("found init command for {handle}");
-                                        let init_command: StoredCommand<A> = serde_json::from_value(value)?;
-
-                                        match init_command.into_init() {
-                                            Some(init_event) => {
-                                                let agg = A::init(handle.clone(), init_event);
-                                                Ok(Arc::new(agg))
-                                            }
-                                            None => Err(A::Error::from(AggregateStoreError::UnknownAggregate(
-                                                handle.clone(),
-                                            ))),
-                                        }
-                                    }
-                                    None => {
-                                        trace!("neither snapshot nor init command found for {handle}");
-                                        Err(A::Error::from(AggregateStoreError::UnknownAggregate(handle.clone())))
-                                    }
+                                // Replay all commands, including archived
+                                // commands. Replaying just the commands that
+                                // are still stored would silently lose the
+                                // archived history.
+                                trace!("no usable snapshot for {handle}, replaying commands");
+                                match Self::replay_in(kv, handle, &mut |_| true)? {
+                                    Ok(Some(agg)) => Ok(Arc::new(agg)),
+                                    Ok(None) => Err(A::Error::from(AggregateStoreError::InitError(handle.clone()))),
+                                    Err(e) => Err(A::Error::from(e)),
                                 }
                             }
                         }
@@ -528,12 +556,17 @@ where
         records: &mut Vec<CommandHistoryRecord>,
         id: &MyHandle,
     ) -> Result<(), AggregateStoreError> {
+        // Archived commands are not included in the history, so start
+        // at the first command that was not archived.
         let mut version = match records.last() {
             Some(record) => record.version + 1,
-            None => 1,
+            None => self.first_unarchived_version(id)?,
         };
 
-        while let Ok(command) = self.get_command(id, version) {
+        let mut archived = None;
+        while let Ok(command) =
+            self.get_command_cached(id, version, &mut archived)
+        {
             records.push(CommandHistoryRecord::from(command));
             version += 1;
         }
@@ -546,13 +579,635 @@ where
         &self,
         id: &MyHandle,
         version: u64,
+    ) -> Result<StoredCommand<A>, AggregateStoreError> {
+        self.get_command_cached(id, version, &mut None)
+    }
+
+    /// Get the command for this key, if it exists.
+    ///
+    /// If the command was archived, then the decoded archive is kept in
+    /// `archived`, so that it is decoded only once when iterating over
+    /// commands.
+    fn get_command_cached(
+        &self,
+        id: &MyHandle,
+        version: u64,
+        archived: &mut Option<ArchivedCommands>,
     ) -> Result<StoredCommand<A>, AggregateStoreError> {
         let key = Self::key_for_command(id, version);
 
         match self.kv.get(&key)? {
             Some(cmd) => Ok(cmd),
-            None => {
-                Err(AggregateStoreError::CommandNotFound(id.clone(), version))
+            None => match self.get_archived_command(id, version, archived)? {
+                Some(cmd) => Ok(cmd),
+                None => Err(AggregateStoreError::CommandNotFound(
+                    id.clone(),
+                    version,
+                )),
+            },
+        }
+    }
+}
+
+/// # Point in time
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Reconstructs the aggregate as it was right after the command with
+    /// the given version was applied.
+    ///
+    /// The aggregate is rebuilt by replaying all stored commands, including
+    /// archived commands, from the init command onwards. Snapshots and the
+    /// cached latest version are neither used nor affected.
+    pub fn get_at_version(
+        &self,
+        handle: &MyHandle,
+        version: u64,
+    ) -> Result<A, A::Error> {
+        match self.replay(handle, |command| command.version() <= version)? {
+            Some(agg) if agg.version() > version => Ok(agg),
+            _ => Err(A::Error::from(AggregateStoreError::UnknownCommand(
+                handle.clone(),
+                version,
+            ))),
+        }
+    }
+
+    /// Reconstructs the aggregate as it was at the given time, i.e. with
+    /// all commands applied that were processed up to and including that
+    /// time.
+    ///
+    /// Like `get_at_version` this replays all stored commands, and does not
+    /// affect the latest version of the aggregate.
+    pub fn get_at_time(
+        &self,
+        handle: &MyHandle,
+        time: Time,
+    ) -> Result<A, A::Error> {
+        match self.replay(handle, |command| command.time() <= time)? {
+            Some(agg) => Ok(agg),
+            None => Err(A::Error::from(AggregateStoreError::UnknownAtTime(
+                handle.clone(),
+                time,
+            ))),
+        }
+    }
+
+    /// Replays the stored commands for an aggregate, in order, for as long
+    /// as they are accepted. Returns `None` if the init command was not
+    /// accepted.
+    fn replay(
+        &self,
+        handle: &MyHandle,
+        mut accept: impl FnMut(&StoredCommand<A>) -> bool,
+    ) -> Result<Option<A>, A::Error> {
+        self.kv
+            .execute(&Self::scope_for_agg(handle), |kv| {
+                Self::replay_in(kv, handle, &mut accept)
+            })
+            .map_err(|e| {
+                A::Error::from(AggregateStoreError::KeyStoreError(e))
+            })?
+            .map_err(A::Error::from)
+    }
+
+    /// Replays the stored commands like `replay`, inside a transaction
+    /// that was already started.
+    fn replay_in(
+        kv: &dyn KeyValueStoreBackend,
+        handle: &MyHandle,
+        accept: &mut impl FnMut(&StoredCommand<A>) -> bool,
+    ) -> Result<Result<Option<A>, AggregateStoreError>, kvx::Error> {
+        let scope = Self::scope_for_agg(handle);
+        let archives = Self::archive_ranges(kv, &scope)?;
+
+        // The decoded commands of the last archive that was needed,
+        // and the version of the first command in it.
+        let mut archived: Vec<serde_json::Value> = vec![];
+        let mut archived_first = 0;
+
+        let mut agg: Option<A> = None;
+        let mut version = 0;
+
+        loop {
+            let key = Self::key_for_command(handle, version);
+            let value = match kv.get(&key)? {
+                Some(value) => value,
+                None => {
+                    let in_archived = version >= archived_first
+                        && version - archived_first < archived.len() as u64;
+
+                    if !in_archived {
+                        let range = archives.iter().find(|(first, last)| {
+                            *first <= version && version <= *last
+                        });
+                        let (first, last) = match range {
+                            Some(range) => *range,
+                            None => break,
+                        };
+                        let key =
+                            CommandArchive::key_for(&scope, first, last);
+                        let archive: CommandArchive = match kv.get(&key)? {
+                            Some(value) => serde_json::from_value(value)?,
+                            None => break,
+                        };
+                        archived = match archive.commands() {
+                            Ok(commands) => commands,
+                            Err(e) => {
+                                return Ok(Err(
+                                    AggregateStoreError::CouldNotArchive(
+                                        handle.clone(),
+                                        e.to_string(),
+                                    ),
+                                ))
+                            }
+                        };
+                        archived_first = first;
+                    }
+
+                    match archived.get((version - archived_first) as usize) {
+                        Some(value) => value.clone(),
+                        None => break,
+                    }
+                }
+            };
+
+            let command: StoredCommand<A> = serde_json::from_value(value)?;
+            if !accept(&command) {
+                break;
+            }
+
+            let next = match agg.as_mut() {
+                Some(agg) => {
+                    agg.apply_command(command);
+                    agg.version()
+                }
+                None => match command.into_init() {
+                    Some(init) => {
+                        let init = A::init(handle.clone(), init);
+                        let next = init.version();
+                        agg = Some(init);
+                        next
+                    }
+                    None => {
+                        return Ok(Err(AggregateStoreError::InitError(
+                            handle.clone(),
+                        )))
+                    }
+                },
+            };
+
+            // Continue with the command for the version of the
+            // aggregate, like `execute_opt_command` does.
+            version = next;
+        }
+
+        if agg.is_none()
+            && version == 0
+            && !kv.has(&Self::key_for_command(handle, 0))?
+        {
+            return Ok(Err(AggregateStoreError::UnknownAggregate(
+                handle.clone(),
+            )));
+        }
+
+        Ok(Ok(agg))
+    }
+}
+
+/// # Compact history
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Compacts the command history of an aggregate.
+    ///
+    /// This saves a snapshot for the latest version of the aggregate, and
+    /// verifies that it can be read back. Then all stored commands that
+    /// are covered by the snapshot and that fall outside of the retention
+    /// policy are moved into a single compressed archive in the scope of
+    /// the aggregate. The init command is never archived.
+    ///
+    /// Archived commands are no longer included in the command history,
+    /// but they can still be retrieved using `get_command`.
+    pub fn compact_history(
+        &self,
+        handle: &MyHandle,
+        retention: &HistoryRetention,
+    ) -> Result<HistoryCompactionReport, A::Error> {
+        self.save_snapshot(handle)?;
+
+        let scope = Self::scope_for_agg(handle);
+
+        let report = self
+            .kv
+            .execute(&scope, |kv| {
+                // Verify the snapshot. We will only archive commands that
+                // are included in it.
+                let snapshot_key = Self::key_for_snapshot(handle);
+                let snapshot_value = match kv.get(&snapshot_key)? {
+                    Some(value) => value,
+                    None => {
+                        return Ok(Err(AggregateStoreError::CouldNotArchive(
+                            handle.clone(),
+                            "snapshot was not saved".to_string(),
+                        )))
+                    }
+                };
+                let snapshot: A =
+                    serde_json::from_value(snapshot_value.clone())?;
+                if serde_json::to_value(&snapshot)? != snapshot_value {
+                    return Ok(Err(AggregateStoreError::CouldNotArchive(
+                        handle.clone(),
+                        "snapshot could not be verified".to_string(),
+                    )));
+                }
+                let snapshot_version = snapshot.version();
+
+                let first = Self::first_unarchived_version_kv(kv, &scope)?;
+
+                let mut commands = vec![];
+                let mut bytes_before = 0;
+                for version in first..snapshot_version {
+                    let key = Self::key_for_command(handle, version);
+                    let value = match kv.get(&key)? {
+                        Some(value) => value,
+                        None => break,
+                    };
+                    let command: StoredCommand<A> =
+                        serde_json::from_value(value.clone())?;
+                    if !retention.may_archive(
+                        version,
+                        command.time(),
+                        snapshot_version,
+                    ) {
+                        break;
+                    }
+                    bytes_before += serde_json::to_vec(&value)?.len();
+                    commands.push(value);
+                }
+
+                if commands.is_empty() {
+                    return Ok(Ok(HistoryCompactionReport::unchanged(
+                        handle.clone(),
+                    )));
+                }
+
+                let archive = match CommandArchive::create(first, &commands) {
+                    Ok(archive) => archive,
+                    Err(e) => {
+                        return Ok(Err(AggregateStoreError::CouldNotArchive(
+                            handle.clone(),
+                            e.to_string(),
+                        )))
+                    }
+                };
+
+                // Make sure that the archive contains exactly what we
+                // are about to remove, before we remove it.
+                match archive.commands() {
+                    Ok(archived) if archived == commands => {}
+                    _ => {
+                        return Ok(Err(AggregateStoreError::CouldNotArchive(
+                            handle.clone(),
+                            "archive could not be verified".to_string(),
+                        )))
+                    }
+                }
+
+                let archive_value = serde_json::to_value(&archive)?;
+                let bytes_after = serde_json::to_vec(&archive_value)?.len();
+                kv.store(&archive.key(&scope), archive_value)?;
+
+                for version in archive.first_version..=archive.last_version {
+                    kv.delete(&Self::key_for_command(handle, version))?;
+                }
+
+                Ok(Ok(HistoryCompactionReport {
+                    handle: handle.clone(),
+                    archived_commands: commands.len() as u64,
+                    first_archived_version: Some(archive.first_version),
+                    last_archived_version: Some(archive.last_version),
+                    bytes_reclaimed: bytes_before.saturating_sub(bytes_after)
+                        as u64,
+                }))
+            })
+            .map_err(|e| {
+                A::Error::from(AggregateStoreError::KeyStoreError(e))
+            })?
+            .map_err(A::Error::from)?;
+
+        // Cached history records may include archived commands.
+        if let Some(mutex) = &self.history_cache {
+            mutex.lock().unwrap().remove(handle);
+        }
+
+        if report.archived_commands > 0 {
+            info!(
+                "Archived {} commands for '{}', reclaimed {} bytes",
+                report.archived_commands, handle, report.bytes_reclaimed
+            );
+        }
+
+        Ok(report)
+    }
+}
+
+/// # Rename aggregates
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Moves an aggregate to a new handle.
+    ///
+    /// The stored commands and command archives are moved as they are, so
+    /// that the history still shows the handle that each command was sent
+    /// to. Only the snapshot, which just caches the latest state, gets the
+    /// new handle. The aggregate is not changed otherwise, so the caller
+    /// should record the rename in a command to the aggregate under its new
+    /// handle.
+    ///
+    /// Nothing is done if the aggregate was moved already, so that an
+    /// interrupted rename can be resumed.
+    pub fn rename_aggregate(
+        &self,
+        from: &MyHandle,
+        to: &MyHandle,
+    ) -> StoreResult<()> {
+        let from_scope = Self::scope_for_agg(from);
+        let to_scope = Self::scope_for_agg(to);
+
+        self.kv.execute(&from_scope, |kv| {
+            let moved = kv.has_scope(&to_scope)?;
+            if !kv.has_scope(&from_scope)? {
+                return Ok(if moved {
+                    Ok(())
+                } else {
+                    Err(AggregateStoreError::UnknownAggregate(from.clone()))
+                });
+            }
+            if moved {
+                return Ok(Err(AggregateStoreError::DuplicateAggregate(
+                    to.clone(),
+                )));
+            }
+
+            kv.move_scope(&from_scope, &to_scope)?;
+
+            let snapshot_key = Self::key_for_snapshot(to);
+            if let Some(mut snapshot) = kv.get(&snapshot_key)? {
+                if let Some(handle) = snapshot.get_mut("handle") {
+                    *handle = serde_json::Value::String(to.to_string());
+                    kv.store(&snapshot_key, snapshot)?;
+                }
+            }
+            Ok(Ok(()))
+        })??;
+
+        self.cache_remove(from);
+        if let Some(mutex) = &self.history_cache {
+            mutex.lock().unwrap().remove(from);
+        }
+
+        Ok(())
+    }
+}
+
+/// # Check consistency
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Checks the stored data for an aggregate: the stored and archived
+    /// commands must have consecutive versions, the aggregate must be
+    /// rebuilt from them without errors, and the snapshot (if any) must
+    /// match the aggregate rebuilt up to the version of the snapshot.
+    ///
+    /// If `repair` is set and all issues found can be repaired, then the
+    /// snapshot is replaced with the latest aggregate rebuilt from the
+    /// commands.
+    pub fn check_consistency(
+        &self,
+        handle: &MyHandle,
+        repair: bool,
+    ) -> StoreResult<StorageCheck> {
+        let mut check = StorageCheck::new(handle.clone());
+        let scope = Self::scope_for_agg(handle);
+
+        let (mut versions, snapshot) = self.kv.execute(&scope, |kv| {
+            let mut versions = vec![];
+            for key in kv.list_keys(&scope)? {
+                if key.scope() != &scope {
+                    continue;
+                }
+                if let Some((first, last)) = CommandArchive::parse_range(&key)
+                {
+                    versions.extend(first..=last);
+                } else if let Some(version) = Self::parse_command_key(&key) {
+                    versions.push(version);
+                }
+            }
+            let snapshot = kv.get(&Self::key_for_snapshot(handle))?;
+            Ok((versions, snapshot))
+        })?;
+
+        versions.sort_unstable();
+        versions.dedup();
+        if let Some((missing, next)) = versions
+            .iter()
+            .enumerate()
+            .map(|(expected, version)| (expected as u64, *version))
+            .find(|(expected, version)| expected != version)
+        {
+            check.add(StorageIssue::VersionGap { missing, next });
+        }
+
+        let latest = match self.replay(handle, |_| true) {
+            Ok(Some(latest)) => latest,
+            Ok(None) => {
+                check.add(StorageIssue::ReplayFailed(
+                    AggregateStoreError::InitError(handle.clone())
+                        .to_string(),
+                ));
+                return Ok(check);
+            }
+            Err(e) => {
+                check.add(StorageIssue::ReplayFailed(e.to_string()));
+                return Ok(check);
+            }
+        };
+
+        if let Some(value) = snapshot {
+            match serde_json::from_value::<A>(value) {
+                Err(e) => {
+                    check.add(StorageIssue::SnapshotCorrupt(e.to_string()))
+                }
+                Ok(snapshot) => {
+                    let version = snapshot.version();
+                    if version > latest.version() {
+                        check.add(StorageIssue::SnapshotAhead {
+                            snapshot: version,
+                            replayed: latest.version(),
+                        });
+                    } else {
+                        let replayed = if version == latest.version() {
+                            Some(latest.clone())
+                        } else {
+                            self.replay(handle, |command| {
+                                command.version() < version
+                            })
+                            .ok()
+                            .flatten()
+                        };
+
+                        let matches = match (
+                            replayed.map(|agg| serde_json::to_value(&agg)),
+                            serde_json::to_value(&snapshot),
+                        ) {
+                            (Some(Ok(replayed)), Ok(snapshot)) => {
+                                equivalent_values(&replayed, &snapshot)
+                            }
+                            _ => false,
+                        };
+                        if !matches {
+                            check.add(StorageIssue::SnapshotMismatch {
+                                version,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if repair && !check.is_ok() && check.is_repairable() {
+            let key = Self::key_for_snapshot(handle);
+            self.kv.execute(&scope, |kv| {
+                kv.store(&key, serde_json::to_value(&latest)?)
+            })?;
+            self.cache_remove(handle);
+            check.repaired = true;
+        }
+
+        Ok(check)
+    }
+}
+
+/// # Manage archived commands
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Returns the first version for which the command was not archived.
+    fn first_unarchived_version(
+        &self,
+        id: &MyHandle,
+    ) -> Result<u64, AggregateStoreError> {
+        let scope = Self::scope_for_agg(id);
+        self.kv
+            .execute(&scope, |kv| {
+                Self::first_unarchived_version_kv(kv, &scope)
+            })
+            .map_err(AggregateStoreError::KeyStoreError)
+    }
+
+    fn first_unarchived_version_kv(
+        kv: &dyn KeyValueStoreBackend,
+        scope: &Scope,
+    ) -> Result<u64, kvx::Error> {
+        Ok(Self::archive_ranges(kv, scope)?
+            .into_iter()
+            .map(|(_, last)| last + 1)
+            .max()
+            .unwrap_or(1))
+    }
+
+    /// Returns the version ranges of all command archives in the scope.
+    fn archive_ranges(
+        kv: &dyn KeyValueStoreBackend,
+        scope: &Scope,
+    ) -> Result<Vec<(u64, u64)>, kvx::Error> {
+        Ok(kv
+            .list_keys(scope)?
+            .into_iter()
+            .filter(|key| key.scope() == scope)
+            .filter_map(|key| CommandArchive::parse_range(&key))
+            .collect())
+    }
+
+    /// Gets a command from the archive, if it was archived.
+    ///
+    /// The archive is only read and decoded if the command is not in the
+    /// given, previously decoded, archive.
+    fn get_archived_command(
+        &self,
+        id: &MyHandle,
+        version: u64,
+        archived: &mut Option<ArchivedCommands>,
+    ) -> Result<Option<StoredCommand<A>>, AggregateStoreError> {
+        let cached = archived
+            .as_ref()
+            .is_some_and(|archived| archived.contains(version));
+        if !cached {
+            *archived = self.read_archive(id, version)?;
+        }
+
+        let value = match archived
+            .as_ref()
+            .and_then(|archived| archived.get(version))
+        {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
+
+        serde_json::from_value(value).map(Some).map_err(|_| {
+            AggregateStoreError::CommandCorrupt(id.clone(), version)
+        })
+    }
+
+    /// Reads and decodes the archive containing the given version, if any.
+    fn read_archive(
+        &self,
+        id: &MyHandle,
+        version: u64,
+    ) -> Result<Option<ArchivedCommands>, AggregateStoreError> {
+        let scope = Self::scope_for_agg(id);
+
+        let archive: Option<CommandArchive> =
+            self.kv.execute(&scope, |kv| {
+                let range =
+                    Self::archive_ranges(kv, &scope)?.into_iter().find(
+                        |(first, last)| *first <= version && version <= *last,
+                    );
+
+                match range {
+                    None => Ok(None),
+                    Some((first, last)) => {
+                        let key =
+                            CommandArchive::key_for(&scope, first, last);
+                        match kv.get(&key)? {
+                            Some(value) => {
+                                Ok(Some(serde_json::from_value(value)?))
+                            }
+                            None => Ok(None),
+                        }
+                    }
+                }
+            })?;
+
+        match archive {
+            None => Ok(None),
+            Some(archive) => {
+                let commands = archive.commands().map_err(|e| {
+                    AggregateStoreError::CouldNotArchive(
+                        id.clone(),
+                        e.to_string(),
+                    )
+                })?;
+                Ok(Some(ArchivedCommands {
+                    first_version: archive.first_version,
+                    commands,
+                }))
             }
         }
     }
@@ -597,6 +1252,16 @@ where
         )
     }
 
+    /// Returns the version if the key is for a stored command.
+    fn parse_command_key(key: &Key) -> Option<u64> {
+        key.name()
+            .as_str()
+            .strip_prefix("command-")?
+            .strip_suffix(".json")?
+            .parse()
+            .ok()
+    }
+
     /// Private, should be called through `list` which takes care of locking.
     fn aggregates(&self) -> Result<Vec<MyHandle>, AggregateStoreError> {
         let mut res = vec![];
@@ -624,6 +1289,103 @@ where
     }
 }
 
+//------------ CommandArchive ------------------------------------------------
+
+/// A compressed archive of the stored commands for a consecutive range of
+/// versions of an aggregate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CommandArchive {
+    first_version: u64,
+    last_version: u64,
+
+    /// The stored commands as a JSON array, gzip compressed and base64
+    /// encoded.
+    commands: String,
+}
+
+impl CommandArchive {
+    const PREFIX: &'static str = "archive-";
+    const SUFFIX: &'static str = ".json";
+
+    fn create(
+        first_version: u64,
+        commands: &[serde_json::Value],
+    ) -> Result<Self, std::io::Error> {
+        let json = serde_json::to_vec(commands)?;
+
+        let mut encoder = gzip::Encoder::new(Vec::new())?;
+        encoder.write_all(&json)?;
+        let compressed = encoder.finish().into_result()?;
+
+        Ok(CommandArchive {
+            first_version,
+            last_version: first_version + commands.len() as u64 - 1,
+            commands: BASE64_ENGINE.encode(compressed),
+        })
+    }
+
+    fn commands(&self) -> Result<Vec<serde_json::Value>, std::io::Error> {
+        let compressed =
+            BASE64_ENGINE.decode(&self.commands).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+            })?;
+
+        let mut json = vec![];
+        gzip::Decoder::new(compressed.as_slice())?.read_to_end(&mut json)?;
+
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    fn key(&self, scope: &Scope) -> Key {
+        Self::key_for(scope, self.first_version, self.last_version)
+    }
+
+    fn key_for(scope: &Scope, first: u64, last: u64) -> Key {
+        Key::new_scoped(
+            scope.clone(),
+            Segment::parse(&format!(
+                "{}{}-{}{}",
+                Self::PREFIX,
+                first,
+                last,
+                Self::SUFFIX
+            ))
+            .unwrap(), // cannot panic as a u64 cannot contain a Scope::SEPARATOR
+        )
+    }
+
+    /// Returns the range of versions if the key is for an archive.
+    fn parse_range(key: &Key) -> Option<(u64, u64)> {
+        let (first, last) = key
+            .name()
+            .as_str()
+            .strip_prefix(Self::PREFIX)?
+            .strip_suffix(Self::SUFFIX)?
+            .split_once('-')?;
+
+        Some((first.parse().ok()?, last.parse().ok()?))
+    }
+}
+
+//------------ ArchivedCommands ----------------------------------------------
+
+/// The decoded commands of a [`CommandArchive`].
+struct ArchivedCommands {
+    first_version: u64,
+    commands: Vec<serde_json::Value>,
+}
+
+impl ArchivedCommands {
+    fn contains(&self, version: u64) -> bool {
+        self.get(version).is_some()
+    }
+
+    fn get(&self, version: u64) -> Option<&serde_json::Value> {
+        let index = version.checked_sub(self.first_version)?;
+        self.commands.get(usize::try_from(index).ok()?)
+    }
+}
+
 //------------ AggregateStoreError -------------------------------------------
 
 /// This type defines possible Errors for the AggregateStore
@@ -642,6 +1404,7 @@ pub enum AggregateStoreError {
     CouldNotArchive(MyHandle, String),
     CommandCorrupt(MyHandle, u64),
     CommandNotFound(MyHandle, u64),
+    UnknownAtTime(MyHandle, Time),
 }
 
 impl fmt::Display for AggregateStoreError {
@@ -706,6 +1469,12 @@ impl fmt::Display for AggregateStoreError {
                     handle, key
                 )
             }
+            AggregateStoreError::UnknownAtTime(handle, time) => write!(
+                f,
+                "entity '{}' did not exist at {}",
+                handle,
+                time.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ),
         }
     }
 }
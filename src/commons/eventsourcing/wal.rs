@@ -12,7 +12,7 @@ use url::Url;
 
 use crate::commons::eventsourcing::{
     segment, Key, KeyValueError, KeyValueStore, Scope, Segment, SegmentExt,
-    Storable,
+    Storable, StorageCheck, StorageIssue,
 };
 
 //------------ WalSupport ----------------------------------------------------
@@ -158,6 +158,14 @@ impl<T: WalSupport> WalStore<T> {
         Ok(())
     }
 
+    /// Drops all cached instances.
+    ///
+    /// Cached instances are not refreshed from the stored snapshots, so this
+    /// must be used when another node may have changed the store.
+    pub fn clear_cache(&self) {
+        self.cache.write().unwrap().clear();
+    }
+
     /// Add a new entity for the given handle. Fails if the handle is in use.
     pub fn add(&self, handle: &MyHandle, instance: T) -> WalStoreResult<()> {
         let scope = Self::scope_for_handle(handle);
@@ -404,6 +412,98 @@ impl<T: WalSupport> WalStore<T> {
         self.execute_opt_command(handle, None, true)
     }
 
+    /// Checks the stored data for an instance: the snapshot must be
+    /// readable, and the change sets must continue from the revision of the
+    /// snapshot without gaps.
+    ///
+    /// Unlike aggregates, instances cannot be rebuilt without a snapshot.
+    /// So, the only issue that can be repaired is that of change sets that
+    /// were already included in the snapshot. If `repair` is set and no
+    /// other issues are found, then the snapshot is updated and all change
+    /// sets are removed.
+    pub fn check_consistency(
+        &self,
+        handle: &MyHandle,
+        repair: bool,
+    ) -> WalStoreResult<StorageCheck> {
+        let mut check = StorageCheck::new(handle.clone());
+        let scope = Self::scope_for_handle(handle);
+
+        let (snapshot, mut sets) = self.kv.execute(&scope, |kv| {
+            let snapshot = kv.get(&Self::key_for_snapshot(handle))?;
+            let mut sets = vec![];
+            for key in kv.list_keys(&scope)? {
+                if let Some(revision) = Self::parse_wal_set_key(&key) {
+                    if let Some(value) = kv.get(&key)? {
+                        sets.push((revision, value));
+                    }
+                }
+            }
+            Ok((snapshot, sets))
+        })?;
+        sets.sort_by_key(|(revision, _)| *revision);
+
+        let mut latest: T = match snapshot.map(serde_json::from_value) {
+            Some(Ok(latest)) => latest,
+            Some(Err(e)) => {
+                check.add(StorageIssue::ReplayFailed(format!(
+                    "snapshot is corrupt: {}",
+                    e
+                )));
+                return Ok(check);
+            }
+            None => {
+                check.add(StorageIssue::ReplayFailed(
+                    "snapshot is missing".to_string(),
+                ));
+                return Ok(check);
+            }
+        };
+
+        for (key, value) in sets {
+            if key < latest.revision() {
+                check.add(StorageIssue::StaleChangeSet { revision: key });
+                continue;
+            }
+            if key != latest.revision() {
+                check.add(StorageIssue::VersionGap {
+                    missing: latest.revision(),
+                    next: key,
+                });
+                break;
+            }
+
+            let set: WalSet<T> = match serde_json::from_value(value) {
+                Ok(set) => set,
+                Err(e) => {
+                    check.add(StorageIssue::ReplayFailed(format!(
+                        "change set {} is corrupt: {}",
+                        key, e
+                    )));
+                    break;
+                }
+            };
+            if set.revision != key {
+                check.add(StorageIssue::ChangeSetMisplaced {
+                    key,
+                    revision: set.revision,
+                });
+                break;
+            }
+            latest.apply(set);
+        }
+
+        if repair && !check.is_ok() && check.is_repairable() {
+            self.cache_remove(handle);
+            self.update_snapshot(handle).map_err(|e| {
+                WalStoreError::RepairFailed(handle.clone(), e.to_string())
+            })?;
+            check.repaired = true;
+        }
+
+        Ok(check)
+    }
+
     fn cache_get(&self, id: &MyHandle) -> Option<Arc<T>> {
         self.cache.read().unwrap().get(id).cloned()
     }
@@ -434,6 +534,16 @@ impl<T: WalSupport> WalStore<T> {
             Segment::parse(&format!("wal-{}.json", revision)).unwrap(), /* cannot panic as a u64 cannot contain a Scope::SEPARATOR */
         )
     }
+
+    /// Returns the revision if the key is for a change set.
+    fn parse_wal_set_key(key: &Key) -> Option<u64> {
+        key.name()
+            .as_str()
+            .strip_prefix("wal-")?
+            .strip_suffix(".json")?
+            .parse()
+            .ok()
+    }
 }
 
 //------------ WalStoreResult-------------------------------------------------
@@ -448,6 +558,7 @@ pub enum WalStoreError {
     KeyStoreError(KeyValueError),
     Unknown(MyHandle),
     WarmupFailed(MyHandle, String),
+    RepairFailed(MyHandle, String),
 }
 
 impl From<KeyValueError> for WalStoreError {
@@ -470,6 +581,11 @@ impl fmt::Display for WalStoreError {
                 "Warmup failed with entity '{}' error: {}",
                 handle, e
             ),
+            WalStoreError::RepairFailed(handle, e) => write!(
+                f,
+                "Repair failed with entity '{}' error: {}",
+                handle, e
+            ),
         }
     }
 }
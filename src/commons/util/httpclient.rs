@@ -1,5 +1,5 @@
 //! Some helper functions for HTTP calls
-use std::{env, fmt, path::PathBuf, str::FromStr, time::Duration};
+use std::{env, fmt, io, path::PathBuf, str::FromStr, time::Duration};
 
 use bytes::Bytes;
 use reqwest::{
@@ -14,8 +14,8 @@ use crate::{
         util::file,
     },
     constants::{
-        HTTP_CLIENT_TIMEOUT_SECS, KRILL_CLI_API_ENV,
-        KRILL_HTTPS_ROOT_CERTS_ENV, KRILL_VERSION,
+        HTTP_CLIENT_DOWNLOAD_TIMEOUT_SECS, HTTP_CLIENT_TIMEOUT_SECS,
+        KRILL_CLI_API_ENV, KRILL_HTTPS_ROOT_CERTS_ENV, KRILL_VERSION,
     },
 };
 
@@ -150,6 +150,44 @@ pub async fn get_ok(uri: &str, token: Option<&Token>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Performs a GET request and writes the response body to `out` as it is
+/// received. This is meant for large downloads, so it uses a longer timeout
+/// than other requests.
+pub async fn get_to_writer(
+    uri: &str,
+    token: Option<&Token>,
+    out: &mut impl io::Write,
+) -> Result<(), Error> {
+    if env::var(KRILL_CLI_API_ENV).is_ok() {
+        report_get_and_exit(uri, token);
+    }
+
+    let headers = headers(uri, None, token)?;
+    let mut res = client_with_tweaks(
+        uri,
+        Duration::from_secs(HTTP_CLIENT_DOWNLOAD_TIMEOUT_SECS),
+        true,
+    )?
+    .get(uri)
+    .headers(headers)
+    .send()
+    .await
+    .map_err(|e| Error::execute(uri, e))?;
+
+    match res.status() {
+        StatusCode::OK => {
+            while let Some(chunk) =
+                res.chunk().await.map_err(|e| Error::response(uri, e))?
+            {
+                out.write_all(&chunk).map_err(|e| Error::response(uri, e))?;
+            }
+            Ok(())
+        }
+        StatusCode::FORBIDDEN => Err(Error::Forbidden(uri.to_string())),
+        _ => Err(Error::from_res(uri, res).await),
+    }
+}
+
 /// Performs a POST of data that can be serialized into json, and expects
 /// a 200 OK response, without a body.
 pub async fn post_json(
@@ -26,6 +26,8 @@ pub const KRILL_ENV_ADMIN_TOKEN_DEPRECATED: &str = "KRILL_AUTH_TOKEN";
 pub const KRILL_ENV_SERVER_PORT: &str = "KRILL_SERVER_PORT";
 pub const KRILL_ENV_HTTP_LOG_INFO: &str = "KRILL_HTTP_LOG_INFO";
 pub const KRILL_ENV_STORAGE_URI: &str = "KRILL_STORAGE_URI";
+pub const KRILL_ENV_STORAGE_ENCRYPTION_KEY: &str =
+    "KRILL_STORAGE_ENCRYPTION_KEY";
 
 pub fn enable_test_mode() {
     std::env::set_var(KRILL_ENV_TEST, "1");
@@ -47,6 +49,9 @@ pub fn test_announcements_enabled() -> bool {
 // to be able to expand the segment macro at compile time, while running the
 // expanded code, which actually makes it a Segment, at runtime
 pub const TASK_QUEUE_NS: &Namespace = namespace!("tasks");
+pub const TASK_DEAD_LETTER_NS: &Namespace = namespace!("tasks_dead_letter");
+pub const HA_NS: &Namespace = namespace!("ha");
+pub const EVENT_STREAM_NS: &Namespace = namespace!("events");
 pub const CASERVER_NS: &Namespace = namespace!("cas");
 pub const CA_OBJECTS_NS: &Namespace = namespace!("ca_objects");
 pub const KEYS_NS: &Namespace = namespace!("keys");
@@ -85,12 +90,16 @@ pub const KRILL_CLI_MY_CA_ENV: &str = "KRILL_CLI_MY_CA";
 pub const CA_REFRESH_SECONDS_MIN: u32 = 3600;
 pub const CA_REFRESH_SECONDS_MAX: u32 = 3 * 24 * 3600; // 3 days
 pub const CA_SUSPEND_MIN_HOURS: u32 = 48; // at least 2 days
-pub const SCHEDULER_REQUEUE_DELAY_SECONDS: i64 = 300;
+pub const SCHEDULER_REQUEUE_DELAY_SECONDS: u64 = 300;
+pub const SCHEDULER_REQUEUE_MAX_DELAY_SECONDS: u64 = 3600;
+pub const TASK_RETRY_MAX_DELAY_SECONDS: u64 = 7 * 24 * 3600;
 pub const SCHEDULER_RESYNC_REPO_CAS_THRESHOLD: usize = 5;
 pub const SCHEDULER_USE_JITTER_CAS_THRESHOLD: usize = 50;
 pub const SCHEDULER_USE_JITTER_CAS_PARENTS_THRESHOLD: usize = 5;
 pub const SCHEDULER_INTERVAL_REPUBLISH_MINS: i64 = 5;
 pub const SCHEDULER_INTERVAL_RENEW_MINS: i64 = 60;
+pub const SCHEDULER_INTERVAL_KEY_ROLL_MINS: i64 = 60;
+pub const SCHEDULER_INTERVAL_REPO_MIGRATION_SECS: i64 = 5;
 
 pub const KRILL_HTTPS_ROOT_CERTS_ENV: &str = "KRILL_HTTPS_ROOT_CERTS";
 
@@ -99,9 +108,15 @@ pub const ID_CERTIFICATE_VALIDITY_YEARS: i32 = 15;
 pub const BGP_RIS_REFRESH_MINUTES: i64 = 60;
 
 pub const HTTP_CLIENT_TIMEOUT_SECS: u64 = 120;
+pub const HTTP_CLIENT_DOWNLOAD_TIMEOUT_SECS: u64 = 3600;
 pub const HTTP_USER_AGENT_TRUNCATE: usize = 256; // Will truncate received user-agent values at this size.
 pub const OPENID_CONNECT_HTTP_CLIENT_TIMEOUT_SECS: u64 = 30;
 
+pub const EVENT_STREAM_BUFFER_SIZE: usize = 1000;
+pub const EVENT_STREAM_KEEP_ALIVE_SECS: u64 = 30;
+
+pub const BACKUP_CHUNK_SIZE: usize = 64 * 1024;
+
 pub const NO_RESOURCE: NoResourceType = NoResourceType;
 
 pub const ACTOR_DEF_KRILL: ActorDef = ActorDef::system("krill", "admin");
@@ -76,6 +76,14 @@ iterable_enum! {
         BGPSEC_UPDATE,
         RTA_LIST,
         RTA_READ,
-        RTA_UPDATE
+        RTA_UPDATE,
+        RSC_LIST,
+        RSC_READ,
+        RSC_UPDATE,
+        GEOFEED_LIST,
+        GEOFEED_READ,
+        GEOFEED_UPDATE,
+        TASKS_READ,
+        TASKS_UPDATE
     }
 }
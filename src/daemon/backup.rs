@@ -0,0 +1,596 @@
+//! Online backups of all data stored by Krill.
+//!
+//! A backup is a gzipped tar archive with an entry for every key in every
+//! namespace, followed by a manifest that records the Krill version that
+//! made the backup and a checksum for each namespace. Backups can be made
+//! while Krill is running: all namespaces are locked in a fixed order, and
+//! held until all of their keys and values are read. So the backup is a
+//! consistent copy of all data, also across namespaces. Krill cannot make
+//! any changes while the data is read, but it can once the archive is
+//! written, which may take as long as the client takes to download it.
+//!
+//! The namespace locks only keep out transactions of this process. With
+//! high availability, backups must therefore be made by the leader.
+//!
+//! Values are copied exactly as they are stored. If storage encryption is
+//! used, then the archive only contains encrypted values, and the same
+//! storage keys are needed to use the data after it is restored.
+
+use std::{
+    io::{self, Read, Write},
+    str::FromStr,
+};
+
+use kvx::KeyValueStoreBackend;
+use libflate::gzip;
+use openssl::sha::Sha256;
+use url::Url;
+
+use crate::{
+    commons::{
+        api::{BackupManifest, NamespaceBackup, Timestamp},
+        error::{Error, KrillIoError},
+        eventsourcing::{Key, KeyValueStore, Namespace, Scope},
+        util::KrillVersion,
+        KrillResult,
+    },
+    constants::{
+        CASERVER_NS, CA_OBJECTS_NS, EVENT_STREAM_NS, KEYS_NS, PROPERTIES_NS,
+        PUBSERVER_CONTENT_NS, PUBSERVER_NS, SIGNERS_NS, STATUS_NS,
+        TASK_DEAD_LETTER_NS, TASK_QUEUE_NS, TA_PROXY_SERVER_NS,
+        TA_SIGNER_SERVER_NS,
+    },
+};
+
+/// The version of the archive format written by this code.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// The name of the manifest entry in a backup archive.
+const BACKUP_MANIFEST: &str = "manifest.json";
+
+/// The keys and values of a namespace, in the order they were read.
+type Entries = Vec<(Key, serde_json::Value)>;
+
+/// The namespaces included in a backup.
+///
+/// The locks for the namespaces are taken in this order. It matches the
+/// order in which Krill itself nests locks, e.g. CAs take locks on their
+/// objects, keys and the task queue while a CA is locked, so that taking
+/// the locks for a backup cannot deadlock with Krill. The event stream is
+/// last, because it is written after changes to CAs, the TA proxy and the
+/// publication server, while their locks may be held.
+///
+/// The namespace for high availability (`HA_NS`) is left out on purpose.
+/// It only holds the lease of the current leader, which must not be
+/// restored: a restored lease would let a node act as leader based on a
+/// lease that expired, or that is held by another node by now.
+pub const BACKUP_NAMESPACES: &[&Namespace] = &[
+    CASERVER_NS,
+    TA_PROXY_SERVER_NS,
+    TA_SIGNER_SERVER_NS,
+    PUBSERVER_NS,
+    PUBSERVER_CONTENT_NS,
+    CA_OBJECTS_NS,
+    STATUS_NS,
+    SIGNERS_NS,
+    KEYS_NS,
+    TASK_QUEUE_NS,
+    TASK_DEAD_LETTER_NS,
+    PROPERTIES_NS,
+    EVENT_STREAM_NS,
+];
+
+//------------ Create --------------------------------------------------------
+
+/// Writes a backup of all namespaces in the given storage to `out`.
+pub fn write_backup<W: Write>(
+    storage_uri: &Url,
+    out: W,
+) -> KrillResult<BackupManifest> {
+    let stores = BACKUP_NAMESPACES
+        .iter()
+        .map(|namespace| KeyValueStore::create_raw(storage_uri, namespace))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let entries = read_locked(&stores)?;
+    write_archive(&entries, out)
+}
+
+/// Writes the archive for the entries of all namespaces, in the order of
+/// [`BACKUP_NAMESPACES`].
+fn write_archive<W: Write>(
+    entries: &[Entries],
+    out: W,
+) -> KrillResult<BackupManifest> {
+    let encoder = gzip::Encoder::new(out).map_err(archive_err)?;
+    let mut archive = tar::Builder::new(encoder);
+    let created = Timestamp::now();
+
+    let mut namespaces = vec![];
+    for (namespace, entries) in BACKUP_NAMESPACES.iter().zip(entries) {
+        let mut digest = NamespaceDigest::default();
+        for (key, value) in entries {
+            let path = format!("{}/{}", namespace, key);
+            let json = serde_json::to_vec(value).map_err(Error::JsonError)?;
+            digest.update(&path, &json);
+            append(&mut archive, &path, &json, created)?;
+        }
+        namespaces.push(digest.finish(namespace.as_str()));
+    }
+
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT_VERSION,
+        krill_version: KrillVersion::code_version(),
+        created,
+        namespaces,
+    };
+    let json =
+        serde_json::to_vec_pretty(&manifest).map_err(Error::JsonError)?;
+    append(&mut archive, BACKUP_MANIFEST, &json, created)?;
+
+    let encoder = archive.into_inner().map_err(archive_err)?;
+    let mut out = encoder.finish().into_result().map_err(archive_err)?;
+    out.flush().map_err(archive_err)?;
+
+    Ok(manifest)
+}
+
+/// Reads all keys and values of the given stores, while all of their
+/// namespaces are locked.
+fn read_locked(stores: &[KeyValueStore]) -> KrillResult<Vec<Entries>> {
+    let _locks: Vec<_> =
+        stores.iter().map(KeyValueStore::lock_namespace).collect();
+
+    stores
+        .iter()
+        .map(|store| {
+            store
+                .execute(&Scope::global(), |kv| {
+                    let mut entries = vec![];
+                    read_scope(kv, &Scope::global(), &mut entries)?;
+                    for scope in kv.list_scopes()? {
+                        read_scope(kv, &scope, &mut entries)?;
+                    }
+                    Ok(entries)
+                })
+                .map_err(Error::from)
+        })
+        .collect()
+}
+
+/// Reads the keys and values of a single scope, excluding sub-scopes.
+fn read_scope(
+    kv: &dyn KeyValueStoreBackend,
+    scope: &Scope,
+    entries: &mut Entries,
+) -> Result<(), kvx::Error> {
+    for key in kv.list_keys(scope)? {
+        if key.scope() == scope {
+            if let Some(value) = kv.get(&key)? {
+                entries.push((key, value));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn append<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    created: Timestamp,
+) -> KrillResult<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(i64::from(created).max(0) as u64);
+    archive
+        .append_data(&mut header, path, data)
+        .map_err(archive_err)
+}
+
+//------------ Verify and Restore --------------------------------------------
+
+/// Verifies that a backup archive is complete and matches its manifest.
+///
+/// Returns the manifest of the archive.
+pub fn verify_backup<R: Read>(input: R) -> KrillResult<BackupManifest> {
+    let mut digests: Vec<(String, NamespaceDigest)> = vec![];
+    let mut manifest = None;
+
+    read_archive(input, |entry| {
+        match entry {
+            ArchiveEntry::Manifest(found) => manifest = Some(found),
+            ArchiveEntry::Value {
+                namespace,
+                path,
+                json,
+                ..
+            } => {
+                if manifest.is_some() {
+                    return Err(Error::custom(
+                        "Backup has entries after the manifest",
+                    ));
+                }
+                match digests.last_mut() {
+                    Some((last, digest)) if *last == namespace => {
+                        digest.update(&path, &json)
+                    }
+                    _ => {
+                        if digests.iter().any(|(ns, _)| *ns == namespace) {
+                            return Err(Error::custom(format!(
+                                "Backup has unordered entries for namespace {}",
+                                namespace
+                            )));
+                        }
+                        let mut digest = NamespaceDigest::default();
+                        digest.update(&path, &json);
+                        digests.push((namespace, digest));
+                    }
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    let manifest = manifest.ok_or_else(|| {
+        Error::custom("Backup has no manifest, it may be incomplete")
+    })?;
+
+    if manifest.format != BACKUP_FORMAT_VERSION {
+        return Err(Error::custom(format!(
+            "Unsupported backup format version {}",
+            manifest.format
+        )));
+    }
+
+    // Namespaces without keys have no entries in the archive.
+    for expected in &manifest.namespaces {
+        let found = match digests
+            .iter()
+            .position(|(ns, _)| ns.as_str() == expected.namespace.as_str())
+        {
+            Some(pos) => {
+                let (ns, digest) = digests.remove(pos);
+                digest.finish(&ns)
+            }
+            None => NamespaceDigest::default().finish(&expected.namespace),
+        };
+
+        if found.keys != expected.keys || found.sha256 != expected.sha256 {
+            return Err(Error::custom(format!(
+                "Backup content for namespace {} does not match the manifest",
+                expected.namespace
+            )));
+        }
+    }
+
+    if let Some((ns, _)) = digests.first() {
+        return Err(Error::custom(format!(
+            "Backup has entries for namespace {} which is not in the manifest",
+            ns
+        )));
+    }
+
+    Ok(manifest)
+}
+
+/// Restores all entries from a backup archive into the given storage.
+///
+/// The archive should be verified first using [`verify_backup`], and the
+/// storage is expected to be empty.
+pub fn restore_backup<R: Read>(
+    input: R,
+    storage_uri: &Url,
+) -> KrillResult<()> {
+    let mut current: Option<(String, Entries)> = None;
+
+    read_archive(input, |entry| {
+        if let ArchiveEntry::Value {
+            namespace,
+            key,
+            json,
+            ..
+        } = entry
+        {
+            let value =
+                serde_json::from_slice(&json).map_err(Error::JsonError)?;
+            match current.as_mut() {
+                Some((ns, entries)) if *ns == namespace => {
+                    entries.push((key, value))
+                }
+                _ => {
+                    if let Some((ns, entries)) = current.take() {
+                        store_namespace(storage_uri, &ns, &entries)?;
+                    }
+                    current = Some((namespace, vec![(key, value)]));
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    if let Some((ns, entries)) = current {
+        store_namespace(storage_uri, &ns, &entries)?;
+    }
+
+    Ok(())
+}
+
+fn store_namespace(
+    storage_uri: &Url,
+    namespace: &str,
+    entries: &[(Key, serde_json::Value)],
+) -> KrillResult<()> {
+    info!("Restore {} keys for namespace {}", entries.len(), namespace);
+    let namespace = backup_namespace(namespace)?;
+    let store = KeyValueStore::create_raw(storage_uri, namespace)?;
+    store
+        .execute(&Scope::global(), |kv| {
+            for (key, value) in entries {
+                kv.store(key, value.clone())?;
+            }
+            Ok(())
+        })
+        .map_err(Error::KeyValueError)
+}
+
+//------------ Reading archives ----------------------------------------------
+
+enum ArchiveEntry {
+    Value {
+        namespace: String,
+        path: String,
+        key: Key,
+        json: Vec<u8>,
+    },
+    Manifest(BackupManifest),
+}
+
+fn read_archive<R: Read>(
+    input: R,
+    mut op: impl FnMut(ArchiveEntry) -> KrillResult<()>,
+) -> KrillResult<()> {
+    let decoder = gzip::Decoder::new(input).map_err(archive_err)?;
+    let mut archive = tar::Archive::new(decoder);
+
+    for entry in archive.entries().map_err(archive_err)? {
+        let mut entry = entry.map_err(archive_err)?;
+        let path = entry
+            .path()
+            .map_err(archive_err)?
+            .to_str()
+            .ok_or_else(|| Error::custom("Backup has invalid entry name"))?
+            .to_string();
+
+        let mut data = vec![];
+        entry.read_to_end(&mut data).map_err(archive_err)?;
+
+        if path == BACKUP_MANIFEST {
+            let manifest =
+                serde_json::from_slice(&data).map_err(Error::JsonError)?;
+            op(ArchiveEntry::Manifest(manifest))?;
+        } else {
+            let (namespace, key) = path.split_once('/').ok_or_else(|| {
+                Error::custom(format!("Backup has invalid entry {}", path))
+            })?;
+            backup_namespace(namespace)?;
+            let key = Key::from_str(key).map_err(|e| {
+                Error::custom(format!(
+                    "Backup has invalid key {}: {}",
+                    path, e
+                ))
+            })?;
+            op(ArchiveEntry::Value {
+                namespace: namespace.to_string(),
+                key,
+                path,
+                json: data,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the known namespace for the given name.
+fn backup_namespace(name: &str) -> KrillResult<&'static Namespace> {
+    BACKUP_NAMESPACES
+        .iter()
+        .find(|ns| ns.as_str() == name)
+        .copied()
+        .ok_or_else(|| {
+            Error::custom(format!("Backup has unknown namespace {}", name))
+        })
+}
+
+fn archive_err(e: io::Error) -> Error {
+    Error::IoError(KrillIoError::new("Backup archive error".to_string(), e))
+}
+
+//------------ NamespaceDigest -----------------------------------------------
+
+#[derive(Default)]
+struct NamespaceDigest {
+    keys: usize,
+    hasher: Sha256,
+}
+
+impl NamespaceDigest {
+    fn update(&mut self, path: &str, json: &[u8]) {
+        self.keys += 1;
+        self.hasher.update(path.as_bytes());
+        self.hasher.update(&[0]);
+        self.hasher.update(json);
+    }
+
+    fn finish(self, namespace: &str) -> NamespaceBackup {
+        NamespaceBackup {
+            namespace: namespace.to_string(),
+            keys: self.keys,
+            sha256: hex::encode(self.hasher.finish()),
+        }
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        commons::eventsourcing::{segment, Segment},
+        test,
+    };
+
+    fn fill(storage_uri: &Url) {
+        let cas = KeyValueStore::create(storage_uri, CASERVER_NS).unwrap();
+        let ca = Scope::from_segment(segment!("ca"));
+        cas.store(
+            &Key::new_scoped(ca.clone(), segment!("snapshot.json")),
+            &1,
+        )
+        .unwrap();
+        cas.store(&Key::new_scoped(ca, segment!("command-1.json")), &2)
+            .unwrap();
+
+        let keys = KeyValueStore::create(storage_uri, KEYS_NS).unwrap();
+        keys.store(&Key::new_global(segment!("key")), &"secret")
+            .unwrap();
+    }
+
+    #[test]
+    fn backup_verify_restore() {
+        let source = test::mem_storage();
+        fill(&source);
+
+        let mut archive = vec![];
+        let manifest = write_backup(&source, &mut archive).unwrap();
+        assert_eq!(manifest.namespaces.len(), BACKUP_NAMESPACES.len());
+        assert_eq!(manifest.namespaces[0].keys, 2);
+
+        assert_eq!(verify_backup(archive.as_slice()).unwrap(), manifest);
+
+        let target = test::mem_storage();
+        restore_backup(archive.as_slice(), &target).unwrap();
+
+        let cas = KeyValueStore::create(&target, CASERVER_NS).unwrap();
+        let ca = Scope::from_segment(segment!("ca"));
+        assert_eq!(cas.keys(&ca, "").unwrap().len(), 2);
+        let keys = KeyValueStore::create(&target, KEYS_NS).unwrap();
+        assert_eq!(
+            keys.get::<String>(&Key::new_global(segment!("key")))
+                .unwrap()
+                .unwrap(),
+            "secret"
+        );
+
+        // A truncated archive has no manifest.
+        let truncated = &archive[..archive.len() / 2];
+        assert!(verify_backup(truncated).is_err());
+    }
+
+    /// Stores a value from another thread on every write, and fails if
+    /// that has to wait for the backup.
+    struct WritingWhileArchived(Url);
+
+    impl Write for WritingWhileArchived {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let storage_uri = self.0.clone();
+            let (stored, done) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let cas =
+                    KeyValueStore::create(&storage_uri, CASERVER_NS).unwrap();
+                let ca = Scope::from_segment(segment!("ca"));
+                cas.store(&Key::new_scoped(ca, segment!("other.json")), &3)
+                    .unwrap();
+                stored.send(()).unwrap();
+            });
+            done.recv_timeout(std::time::Duration::from_secs(5))
+                .map_err(|_| io::Error::other("storage is still locked"))?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn archive_is_written_without_locks() {
+        test::test_under_tmp(|dir| {
+            let source =
+                Url::parse(&format!("local://{}/source", dir.display()))
+                    .unwrap();
+            fill(&source);
+
+            let out = WritingWhileArchived(source.clone());
+            let manifest = write_backup(&source, out).unwrap();
+            assert_eq!(manifest.namespaces[0].keys, 2);
+        })
+    }
+
+    /// Returns all keys and values of a namespace.
+    fn read_namespace(
+        storage_uri: &Url,
+        namespace: &Namespace,
+    ) -> Vec<(String, serde_json::Value)> {
+        let store = KeyValueStore::create(storage_uri, namespace).unwrap();
+        let mut scopes = store.scopes().unwrap();
+        scopes.push(Scope::global());
+
+        let mut entries = vec![];
+        for scope in scopes {
+            for key in store.keys(&scope, "").unwrap() {
+                let value = store.get(&key).unwrap().unwrap();
+                entries.push((key.to_string(), value));
+            }
+        }
+        entries.sort_by(|left, right| left.0.cmp(&right.0));
+        entries
+    }
+
+    #[test]
+    fn backup_restore_all_namespaces_on_disk() {
+        test::test_under_tmp(|dir| {
+            let storage = |name: &str| {
+                Url::parse(&format!("local://{}/{}", dir.display(), name))
+                    .unwrap()
+            };
+            let source = storage("source");
+            let target = storage("target");
+
+            for namespace in BACKUP_NAMESPACES {
+                let store =
+                    KeyValueStore::create(&source, namespace).unwrap();
+                let scope = Scope::from_segment(segment!("scope"));
+                store
+                    .store(
+                        &Key::new_scoped(scope, segment!("scoped.json")),
+                        &namespace.as_str(),
+                    )
+                    .unwrap();
+                store
+                    .store(&Key::new_global(segment!("global.json")), &1)
+                    .unwrap();
+            }
+
+            let mut archive = vec![];
+            let manifest = write_backup(&source, &mut archive).unwrap();
+            assert_eq!(verify_backup(archive.as_slice()).unwrap(), manifest);
+            restore_backup(archive.as_slice(), &target).unwrap();
+
+            for (namespace, backup) in
+                BACKUP_NAMESPACES.iter().zip(&manifest.namespaces)
+            {
+                assert_eq!(backup.namespace, namespace.as_str());
+                assert_eq!(backup.keys, 2);
+
+                let restored = read_namespace(&target, namespace);
+                assert_eq!(restored.len(), 2);
+                assert_eq!(restored, read_namespace(&source, namespace));
+            }
+        })
+    }
+}
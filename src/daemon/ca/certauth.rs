@@ -1,4 +1,9 @@
-use std::{collections::HashMap, ops::Deref, sync::Arc, vec};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Deref,
+    sync::Arc,
+    vec,
+};
 
 use bytes::Bytes;
 use chrono::Duration;
@@ -30,11 +35,17 @@ use crate::{
             import::{ExportChild, ImportChild, ImportChildCertificate},
             AspaDefinition, AspaDefinitionList, AspaDefinitionUpdates,
             AspaProvidersUpdate, BgpSecAsnKey, BgpSecCsrInfoList,
-            BgpSecDefinitionUpdates, CertAuthInfo, CertAuthStorableCommand,
-            ConfiguredRoa, CustomerAsn, IdCertInfo, ObjectName,
-            ParentCaContact, ReceivedCert, RepositoryContact,
-            ResourceClassNameMapping, Revocation, RoaConfiguration,
-            RoaConfigurationUpdates, RtaList, RtaName, RtaPrepResponse,
+            BgpSecDefinitionUpdates, CaIssuanceTiming, CertAuthInfo,
+            CertAuthStorableCommand, ChildAllocation, ChildCertTiming,
+            ConfiguredRoa, CustomerAsn, EmergencyRekeyReport, GeofeedList,
+            IdCertInfo,
+            KeyRollPolicy, KeyRollStatus, ObjectName, ParentCaContact,
+            PlannedKeyRoll, ReceivedCert, RekeyedResourceClass,
+            RepoMigration, RepoMigrationPhase, RepositoryContact,
+            RepositoryMirrorName, ResourceClassNameMapping,
+            ResourcePoolDefinition, ResourcePoolList, ResourcePoolName,
+            Revocation, RoaConfiguration, RoaConfigurationUpdates, RscList,
+            RscName, RtaList, RtaName, RtaPrepResponse, Timestamp,
         },
         crypto::{CsrInfo, KrillSigner},
         error::{Error, RoaDeltaError},
@@ -44,12 +55,14 @@ use crate::{
     constants::test_mode_enabled,
     daemon::{
         ca::{
-            events::ChildCertificateUpdates, AspaDefinitions,
-            BgpSecDefinitions, CertAuthCommand, CertAuthCommandDetails,
-            CertAuthEvent, CertAuthInitEvent, ChildDetails, DropReason,
-            PreparedRta, ResourceClass, ResourceTaggedAttestation, Rfc8183Id,
-            RoaInfo, RoaPayloadJsonMapKey, Routes, RtaContentRequest,
-            RtaPrepareRequest, Rtas, SignedRta, StoredBgpSecCsr,
+            allocate_from_pool, events::ChildCertificateUpdates,
+            AspaDefinitions, BgpSecDefinitions, CertAuthCommand,
+            CertAuthCommandDetails, CertAuthEvent, CertAuthInitEvent,
+            ChildDetails, DropReason, PendingRename, PreparedRta, ResourceClass,
+            ResourcePools, ResourceTaggedAttestation, Rfc8183Id, RoaInfo,
+            Geofeed, Geofeeds, RoaPayloadJsonMapKey, Routes, RscObject,
+            RscSignRequest, Rscs, RtaContentRequest, RtaPrepareRequest, Rtas,
+            SignedGeofeed, SignedRsc, SignedRta, StoredBgpSecCsr,
         },
         config::{Config, IssuanceTimingConfig},
     },
@@ -77,6 +90,13 @@ pub struct CertAuth {
     id: Rfc8183Id, // Used for RFC 6492 (up-down) and RFC 8181 (publication)
 
     repository: Option<RepositoryContact>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    repository_mirrors: BTreeMap<RepositoryMirrorName, RepositoryContact>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    repository_migration: Option<RepoMigration>,
+
     parents: HashMap<ParentHandle, ParentCaContact>,
 
     next_class_name: u32,
@@ -88,11 +108,32 @@ pub struct CertAuth {
     #[serde(skip_serializing_if = "Rtas::is_empty", default)]
     rtas: Rtas,
 
+    #[serde(skip_serializing_if = "Rscs::is_empty", default)]
+    rscs: Rscs,
+
+    #[serde(skip_serializing_if = "Geofeeds::is_empty", default)]
+    geofeeds: Geofeeds,
+
     #[serde(skip_serializing_if = "AspaDefinitions::is_empty", default)]
     aspas: AspaDefinitions,
 
     #[serde(skip_serializing_if = "BgpSecDefinitions::is_empty", default)]
     bgpsec_defs: BgpSecDefinitions,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    key_roll_policy: Option<KeyRollPolicy>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    issuance_timing: Option<CaIssuanceTiming>,
+
+    #[serde(skip_serializing_if = "ResourcePools::is_empty", default)]
+    resource_pools: ResourcePools,
+
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    aliases: HashMap<CaHandle, Timestamp>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pending_rename: Option<PendingRename>,
 }
 
 impl Aggregate for CertAuth {
@@ -118,6 +159,8 @@ impl Aggregate for CertAuth {
 
         let routes = Routes::default();
         let rtas = Rtas::default();
+        let rscs = Rscs::default();
+        let geofeeds = Geofeeds::default();
         let aspas = AspaDefinitions::default();
         let bgpsec_defs = BgpSecDefinitions::default();
 
@@ -128,6 +171,8 @@ impl Aggregate for CertAuth {
             id,
 
             repository,
+            repository_mirrors: BTreeMap::new(),
+            repository_migration: None,
             parents,
 
             next_class_name,
@@ -137,8 +182,15 @@ impl Aggregate for CertAuth {
 
             routes,
             rtas,
+            rscs,
+            geofeeds,
             aspas,
             bgpsec_defs,
+            key_roll_policy: None,
+            issuance_timing: None,
+            resource_pools: ResourcePools::default(),
+            aliases: HashMap::new(),
+            pending_rename: None,
         }
     }
 
@@ -236,6 +288,13 @@ impl Aggregate for CertAuth {
                 .unwrap()
                 .set_resources(resources),
 
+            CertAuthEvent::ChildUpdatedCertTiming { child, cert_timing } => {
+                self.children
+                    .get_mut(&child)
+                    .unwrap()
+                    .set_cert_timing(cert_timing)
+            }
+
             CertAuthEvent::ChildUpdatedResourceClassNameMapping {
                 child,
                 name_in_parent,
@@ -250,6 +309,12 @@ impl Aggregate for CertAuth {
                 self.children.remove(&child);
             }
 
+            CertAuthEvent::ChildRenamed { child, new_handle } => {
+                if let Some(details) = self.children.remove(&child) {
+                    self.children.insert(new_handle, details);
+                }
+            }
+
             CertAuthEvent::ChildSuspended { child } => {
                 self.children.get_mut(&child).unwrap().suspend()
             }
@@ -258,6 +323,33 @@ impl Aggregate for CertAuth {
                 self.children.get_mut(&child).unwrap().unsuspend()
             }
 
+            CertAuthEvent::ResourcePoolUpdated { name, resources } => {
+                self.resource_pools.add_or_replace(name, resources)
+            }
+            CertAuthEvent::ResourcePoolRemoved { name } => {
+                self.resource_pools.remove(&name)
+            }
+
+            CertAuthEvent::RenameStarted { rename } => {
+                self.pending_rename = Some(rename);
+            }
+            CertAuthEvent::Renamed {
+                from,
+                alias_until,
+                repository,
+            } => {
+                self.pending_rename = None;
+
+                // A CA can be renamed back to an old name.
+                self.aliases.remove(&self.handle);
+                if let Some(until) = alias_until {
+                    self.aliases.insert(from, until);
+                }
+                if let Some(contact) = repository {
+                    self.repository = Some(contact);
+                }
+            }
+
             //-----------------------------------------------------------------------
             // Being a child
             //-----------------------------------------------------------------------
@@ -274,6 +366,16 @@ impl Aggregate for CertAuth {
                 self.parents.remove(&parent);
                 self.resources.retain(|_, rc| rc.parent_handle() != &parent);
             }
+            CertAuthEvent::ParentRenamed { parent, new_name } => {
+                if let Some(contact) = self.parents.remove(&parent) {
+                    self.parents.insert(new_name.clone(), contact);
+                }
+                for rc in self.resources.values_mut() {
+                    if rc.parent_handle() == &parent {
+                        rc.set_parent_handle(new_name.clone());
+                    }
+                }
+            }
 
             CertAuthEvent::ResourceClassAdded {
                 resource_class_name,
@@ -372,6 +474,15 @@ impl Aggregate for CertAuth {
                 // requests are picked up by the `MessageQueue`
                 // listener.
             }
+            CertAuthEvent::KeyRollPolicyUpdated { policy } => {
+                self.key_roll_policy = policy;
+            }
+            CertAuthEvent::KeyRollEmergencyStarted { .. } => {
+                // Nothing to apply, the reason is only kept in the history.
+            }
+            CertAuthEvent::IssuanceTimingUpdated { timing } => {
+                self.issuance_timing = timing;
+            }
 
             //-----------------------------------------------------------------------
             // Route Authorizations
@@ -448,6 +559,32 @@ impl Aggregate for CertAuth {
                 }
                 self.repository = Some(contact);
             }
+            CertAuthEvent::RepoMirrorAdded { name, contact } => {
+                self.repository_mirrors.insert(name, contact);
+            }
+            CertAuthEvent::RepoMirrorRemoved { name, .. } => {
+                self.repository_mirrors.remove(&name);
+            }
+            CertAuthEvent::RepoMigrationStarted { contact } => {
+                if let Some(current) = &self.repository {
+                    self.repository_migration =
+                        Some(RepoMigration::new(current.clone(), contact));
+                }
+            }
+            CertAuthEvent::RepoMigrationVerified { objects } => {
+                if let Some(migration) = self.repository_migration.as_mut() {
+                    migration.set_verified(objects);
+                }
+            }
+            CertAuthEvent::RepoMigrationWithdrawing => {
+                if let Some(migration) = self.repository_migration.as_mut() {
+                    migration.set_withdrawing();
+                }
+            }
+            CertAuthEvent::RepoMigrationFinished
+            | CertAuthEvent::RepoMigrationAborted { .. } => {
+                self.repository_migration = None;
+            }
 
             //-----------------------------------------------------------------------
             // Resource Tagged Attestations
@@ -458,6 +595,20 @@ impl Aggregate for CertAuth {
             CertAuthEvent::RtaSigned { name, rta } => {
                 self.rtas.add_signed(name, rta);
             }
+
+            //-----------------------------------------------------------------------
+            // RPKI Signed Checklists
+            //-----------------------------------------------------------------------
+            CertAuthEvent::RscSigned { name, rsc } => {
+                self.rscs.add(name, rsc);
+            }
+
+            //-----------------------------------------------------------------------
+            // Signed geofeeds
+            //-----------------------------------------------------------------------
+            CertAuthEvent::GeofeedSigned { geofeed } => {
+                self.geofeeds.add(geofeed);
+            }
         }
     }
 
@@ -476,20 +627,38 @@ impl Aggregate for CertAuth {
 
         match command.into_details() {
             // being a parent
-            CertAuthCommandDetails::ChildAdd(child, id_cert, resources) => {
-                self.child_add(child, id_cert, resources)
-            }
+            CertAuthCommandDetails::ChildAdd(
+                child,
+                id_cert,
+                resources,
+                allocation,
+            ) => self.child_add(child, id_cert, resources, &allocation),
             CertAuthCommandDetails::ChildImport(
                 import_child,
                 config,
                 signer,
-            ) => self.child_import(import_child, &config, signer),
-            CertAuthCommandDetails::ChildUpdateResources(child, res) => {
-                self.child_update_resources(&child, res)
-            }
+            ) => self.child_import(
+                import_child,
+                &self.ca_config(config),
+                signer,
+            ),
+            CertAuthCommandDetails::ChildUpdateResources(
+                child,
+                res,
+                allocation,
+            ) => self.child_update_resources(&child, res, &allocation),
             CertAuthCommandDetails::ChildUpdateId(child, id_cert) => {
                 self.child_update_id_cert(&child, id_cert)
             }
+            CertAuthCommandDetails::ChildUpdateCertTiming(
+                child,
+                cert_timing,
+                config,
+            ) => self.child_update_cert_timing(
+                &child,
+                cert_timing,
+                &self.ca_config(config),
+            ),
             CertAuthCommandDetails::ChildUpdateResourceClassNameMapping(
                 child,
                 mapping,
@@ -499,8 +668,12 @@ impl Aggregate for CertAuth {
                 request,
                 config,
                 signer,
-            ) => self
-                .child_certify_from_command(child, request, &config, signer),
+            ) => self.child_certify_from_command(
+                child,
+                request,
+                &self.ca_config(config),
+                signer,
+            ),
             CertAuthCommandDetails::ChildRevokeKey(child, request) => {
                 self.child_revoke_key(child, request)
             }
@@ -513,6 +686,21 @@ impl Aggregate for CertAuth {
             CertAuthCommandDetails::ChildUnsuspend(child) => {
                 self.child_unsuspend(&child)
             }
+            CertAuthCommandDetails::ResourcePoolUpdate(definition) => {
+                self.resource_pool_update(definition)
+            }
+            CertAuthCommandDetails::ResourcePoolRemove(name) => {
+                self.resource_pool_remove(name)
+            }
+            CertAuthCommandDetails::ChildRename(child, new_handle) => {
+                self.child_rename(child, new_handle)
+            }
+            CertAuthCommandDetails::RenameStart(to, alias_until) => {
+                self.rename_start(to, alias_until)
+            }
+            CertAuthCommandDetails::Rename(from, repository) => {
+                self.rename(from, repository)
+            }
 
             // being a child
             CertAuthCommandDetails::GenerateNewIdKey(signer) => {
@@ -527,6 +715,9 @@ impl Aggregate for CertAuth {
             CertAuthCommandDetails::RemoveParent(parent) => {
                 self.remove_parent(parent)
             }
+            CertAuthCommandDetails::RenameParent(parent, new_name) => {
+                self.rename_parent(parent, new_name)
+            }
 
             CertAuthCommandDetails::UpdateEntitlements(
                 parent,
@@ -538,8 +729,12 @@ impl Aggregate for CertAuth {
                 rcvd_cert,
                 config,
                 signer,
-            ) => self
-                .update_received_cert(class_name, rcvd_cert, &config, signer),
+            ) => self.update_received_cert(
+                class_name,
+                rcvd_cert,
+                &self.ca_config(config),
+                signer,
+            ),
             CertAuthCommandDetails::DropResourceClass(
                 rcn,
                 reason,
@@ -554,38 +749,74 @@ impl Aggregate for CertAuth {
                 duration,
                 config,
                 signer,
-            ) => self.keyroll_activate(duration, config, signer),
+            ) => self.keyroll_activate(
+                duration,
+                self.ca_config(config),
+                signer,
+            ),
             CertAuthCommandDetails::KeyRollFinish(rcn, response) => {
                 self.keyroll_finish(rcn, response)
             }
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy) => {
+                self.keyroll_policy_update(policy)
+            }
+            CertAuthCommandDetails::KeyRollEmergency(reason, signer) => {
+                self.keyroll_emergency(reason, signer)
+            }
+
+            // Issuance timing
+            CertAuthCommandDetails::IssuanceTimingUpdate(timing, config) => {
+                self.issuance_timing_update(timing, &config)
+            }
 
             // Route Authorizations
             CertAuthCommandDetails::RouteAuthorizationsUpdate(
                 updates,
                 config,
                 signer,
-            ) => self.route_authorizations_update(updates, &config, signer),
+            ) => self.route_authorizations_update(
+                updates,
+                &self.ca_config(config),
+                signer,
+            ),
             CertAuthCommandDetails::RouteAuthorizationsRenew(
                 config,
                 signer,
-            ) => self.route_authorizations_renew(false, &config, &signer),
+            ) => self.route_authorizations_renew(
+                false,
+                &self.ca_config(config),
+                &signer,
+            ),
             CertAuthCommandDetails::RouteAuthorizationsForceRenew(
                 config,
                 signer,
-            ) => self.route_authorizations_renew(true, &config, &signer),
+            ) => self.route_authorizations_renew(
+                true,
+                &self.ca_config(config),
+                &signer,
+            ),
 
             // ASPA
             CertAuthCommandDetails::AspasUpdate(updates, config, signer) => {
-                self.aspas_definitions_update(updates, &config, &signer)
+                self.aspas_definitions_update(
+                    updates,
+                    &self.ca_config(config),
+                    &signer,
+                )
             }
             CertAuthCommandDetails::AspasUpdateExisting(
                 customer,
                 update,
                 config,
                 signer,
-            ) => self.aspas_update(customer, update, &config, &signer),
+            ) => self.aspas_update(
+                customer,
+                update,
+                &self.ca_config(config),
+                &signer,
+            ),
             CertAuthCommandDetails::AspasRenew(config, signer) => {
-                self.aspas_renew(&config, &signer)
+                self.aspas_renew(&self.ca_config(config), &signer)
             }
 
             // BGPSec
@@ -593,15 +824,38 @@ impl Aggregate for CertAuth {
                 updates,
                 config,
                 signer,
-            ) => self.bgpsec_definitions_update(updates, &config, &signer),
+            ) => self.bgpsec_definitions_update(
+                updates,
+                &self.ca_config(config),
+                &signer,
+            ),
             CertAuthCommandDetails::BgpSecRenew(config, signer) => {
-                self.bgpsec_renew(&config, &signer)
+                self.bgpsec_renew(&self.ca_config(config), &signer)
             }
 
             // Republish
             CertAuthCommandDetails::RepoUpdate(contact, signer) => {
                 self.update_repo(contact, &signer)
             }
+            CertAuthCommandDetails::RepoMirrorAdd(name, contact) => {
+                self.repo_mirror_add(name, contact)
+            }
+            CertAuthCommandDetails::RepoMirrorRemove(name) => {
+                self.repo_mirror_remove(name)
+            }
+            CertAuthCommandDetails::RepoMigrationStart(contact) => {
+                self.repo_migration_start(contact)
+            }
+            CertAuthCommandDetails::RepoMigrationVerified(
+                objects,
+                signer,
+            ) => self.repo_migration_verified(objects, &signer),
+            CertAuthCommandDetails::RepoMigrationComplete => {
+                self.repo_migration_complete()
+            }
+            CertAuthCommandDetails::RepoMigrationAbort => {
+                self.repo_migration_abort()
+            }
 
             // Resource Tagged Attestations
             CertAuthCommandDetails::RtaMultiPrepare(
@@ -615,6 +869,19 @@ impl Aggregate for CertAuth {
             CertAuthCommandDetails::RtaSign(name, request, signer) => {
                 self.rta_sign(name, request, signer.deref())
             }
+
+            // RPKI Signed Checklists
+            CertAuthCommandDetails::RscSign(name, request, signer) => {
+                self.rsc_sign(name, request, false, signer.deref())
+            }
+            CertAuthCommandDetails::RscCoSign(name, request, signer) => {
+                self.rsc_sign(name, request, true, signer.deref())
+            }
+
+            // Signed geofeeds
+            CertAuthCommandDetails::GeofeedSign(geofeed, validity, signer) => {
+                self.geofeed_sign(geofeed, validity, signer.deref())
+            }
         }
     }
 
@@ -666,6 +933,8 @@ impl CertAuth {
             children,
             suspended_children,
         )
+        .with_repo_migration(self.repository_migration.clone())
+        .with_aliases(self.aliases())
     }
 
     /// Returns the current ConfiguredRoas.
@@ -720,6 +989,103 @@ impl CertAuth {
         &self.handle
     }
 
+    /// Returns the old handles of this CA which can still be used as an
+    /// alias, and until when.
+    pub fn aliases(&self) -> HashMap<CaHandle, Timestamp> {
+        let now = Timestamp::now();
+        self.aliases
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(alias, until)| (alias.clone(), *until))
+            .collect()
+    }
+
+    /// Returns whether the given handle can still be used as an alias for
+    /// this CA.
+    pub fn has_alias(&self, alias: &CaHandle) -> bool {
+        self.aliases
+            .get(alias)
+            .map(|until| *until > Timestamp::now())
+            .unwrap_or(false)
+    }
+
+    /// Checks whether this CA can be renamed. The stored keys and objects
+    /// must be stable, so no key roll or repository migration may be in
+    /// progress.
+    pub fn rename_check(&self) -> KrillResult<()> {
+        let reason = if self.repository_migration.is_some() {
+            Some("a repository migration is in progress")
+        } else if !self.resources.values().all(|rc| rc.key_roll_possible()) {
+            Some("a key roll is in progress")
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => Err(Error::CaRenameNotPossible(
+                self.handle.clone(),
+                reason.to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the rename of this CA which was started, but not yet
+    /// finished, if any.
+    pub fn pending_rename(&self) -> Option<&PendingRename> {
+        self.pending_rename.as_ref()
+    }
+
+    /// Records that this CA is going to be renamed. Starting the same
+    /// rename again is a no-op, so that an interrupted rename can be
+    /// resumed.
+    fn rename_start(
+        &self,
+        to: CaHandle,
+        alias_until: Option<Timestamp>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if let Some(pending) = &self.pending_rename {
+            return if pending.to() == &to {
+                Ok(vec![])
+            } else {
+                Err(Error::CaRenameNotPossible(
+                    self.handle.clone(),
+                    format!("it is being renamed to '{}'", pending.to()),
+                ))
+            };
+        }
+        self.rename_check()?;
+
+        info!("CA '{}' will be renamed to '{}'", self.handle, to);
+        let rename = PendingRename::new(self.handle.clone(), to, alias_until);
+        Ok(vec![CertAuthEvent::RenameStarted { rename }])
+    }
+
+    /// Records that this CA was renamed. The stored data was already moved
+    /// to the new handle.
+    fn rename(
+        &self,
+        from: CaHandle,
+        repository: Option<RepositoryContact>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let pending = match &self.pending_rename {
+            Some(pending) if pending.from() == &from => pending,
+            _ => {
+                return Err(Error::CaRenameNotPossible(
+                    from,
+                    "no rename was started".to_string(),
+                ))
+            }
+        };
+
+        info!("CA '{}' was renamed from '{}'", pending.to(), from);
+        Ok(vec![CertAuthEvent::Renamed {
+            from,
+            alias_until: pending.alias_until(),
+            repository,
+        }])
+    }
+
     /// Returns the complete set of all currently received resources, under
     /// all parents, for this `CertAuth`
     pub fn all_resources(&self) -> ResourceSet {
@@ -738,6 +1104,19 @@ impl CertAuth {
     pub fn repository_contact(&self) -> KrillResult<&RepositoryContact> {
         self.repository.as_ref().ok_or(Error::RepoNotSet)
     }
+
+    /// Returns the mirror repositories to which this CA publishes its
+    /// objects, in addition to its primary repository.
+    pub fn repository_mirrors(
+        &self,
+    ) -> &BTreeMap<RepositoryMirrorName, RepositoryContact> {
+        &self.repository_mirrors
+    }
+
+    /// Returns the staged migration to a new repository, if any.
+    pub fn repository_migration(&self) -> Option<&RepoMigration> {
+        self.repository_migration.as_ref()
+    }
 }
 
 /// # Being a parent
@@ -934,6 +1313,11 @@ impl CertAuth {
         // to the not after time of the other is still fine - it would
         // still trigger them to request a replacement for the first which was
         // about to expire.
+        //
+        // This CA may have its own issuance timing, and the child may have
+        // its own validity and re-issue time overrides.
+        let issuance_timing =
+            child.issuance_timing(&self.issuance_timing(issuance_timing));
         let mut not_after = issuance_timing.new_child_cert_not_after();
         let threshold = issuance_timing.new_child_cert_issuance_threshold();
 
@@ -981,6 +1365,24 @@ impl CertAuth {
         }
     }
 
+    /// Returns the certificate timing overrides of children, by the key
+    /// identifiers of the certificates issued to them in the given
+    /// resource class. Children without overrides are not included.
+    fn child_cert_timings(
+        &self,
+        rcn: &ResourceClassName,
+    ) -> HashMap<KeyIdentifier, ChildCertTiming> {
+        let mut res = HashMap::new();
+        for child in self.children.values() {
+            if !child.cert_timing().is_empty() {
+                for ki in child.issued(rcn) {
+                    res.insert(ki, *child.cert_timing());
+                }
+            }
+        }
+        res
+    }
+
     /// Returns an iterator for the handles of all children under this CA.
     pub fn children(&self) -> impl Iterator<Item = &ChildHandle> {
         self.children.keys()
@@ -993,14 +1395,24 @@ impl CertAuth {
         child: ChildHandle,
         id_cert: IdCertInfo,
         resources: ResourceSet,
+        allocation: &ChildAllocation,
     ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self.has_child(&child) {
+            return Err(Error::CaChildDuplicate(self.handle.clone(), child));
+        }
+
+        let resources = self
+            .child_resources_with_allocation(&child, resources, allocation)?;
+
         if resources.is_empty() {
             Err(Error::CaChildMustHaveResources(self.handle.clone(), child))
         } else if !self.all_resources().contains(&resources) {
             Err(Error::CaChildExtraResources(self.handle.clone(), child))
-        } else if self.has_child(&child) {
-            Err(Error::CaChildDuplicate(self.handle.clone(), child))
         } else {
+            self.verify_child_resources_overlap(
+                &child, &resources, allocation,
+            )?;
+
             info!(
                 "CA '{}' added child '{}' with resources '{}'",
                 self.handle, child, resources
@@ -1057,6 +1469,7 @@ impl CertAuth {
             child_handle.clone(),
             id_cert_info,
             resources.clone(),
+            &ChildAllocation::default(),
         )?);
 
         // Add a resource class name mapping if applicable
@@ -1083,7 +1496,7 @@ impl CertAuth {
             my_rcn,
             csr_info,
             limit,
-            config,
+            &config.issuance_timing,
             signer,
         )?);
 
@@ -1109,6 +1522,7 @@ impl CertAuth {
         let child = self.get_child(&child_handle)?;
         let my_rcn = child.parent_name_for_rcn(&child_rcn);
         let csr_info = CsrInfo::try_from(&csr)?;
+        let issuance_timing = child.issuance_timing(&config.issuance_timing);
 
         self.child_certify(
             child_handle,
@@ -1116,7 +1530,7 @@ impl CertAuth {
             my_rcn,
             csr_info,
             limit,
-            config,
+            &issuance_timing,
             signer,
         )
     }
@@ -1129,7 +1543,7 @@ impl CertAuth {
         my_rcn: ResourceClassName,
         csr_info: CsrInfo,
         limit: RequestResourceLimit,
-        config: &Config,
+        issuance_timing: &IssuanceTimingConfig,
         signer: Arc<KrillSigner>,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         if !csr_info.global_uris() && !test_mode_enabled() {
@@ -1147,7 +1561,7 @@ impl CertAuth {
             csr_info,
             resources,
             limit,
-            &config.issuance_timing,
+            issuance_timing,
             &signer,
         )?;
         let cert_name = ObjectName::new(&issued.key_identifier(), "cer");
@@ -1179,14 +1593,26 @@ impl CertAuth {
         &self,
         child_handle: &ChildHandle,
         resources: ResourceSet,
+        allocation: &ChildAllocation,
     ) -> KrillResult<Vec<CertAuthEvent>> {
+        let child = self.get_child(child_handle)?;
+        let resources = self.child_resources_with_allocation(
+            child_handle,
+            resources,
+            allocation,
+        )?;
+
         if !self.all_resources().contains(&resources) {
             Err(Error::CaChildExtraResources(
                 self.handle.clone(),
                 child_handle.clone(),
             ))
         } else {
-            let child = self.get_child(child_handle)?;
+            self.verify_child_resources_overlap(
+                child_handle,
+                &resources,
+                allocation,
+            )?;
 
             let resources_diff = resources.difference(child.resources());
 
@@ -1246,6 +1672,48 @@ impl CertAuth {
         }
     }
 
+    /// Updates the validity and re-issue time overrides for the
+    /// certificates issued to a child.
+    ///
+    /// Certificates already issued to the child are not re-issued
+    /// here. The new timing applies when they are next renewed.
+    fn child_update_cert_timing(
+        &self,
+        child_handle: &ChildHandle,
+        cert_timing: ChildCertTiming,
+        config: &Config,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let child = self.get_child(child_handle)?;
+
+        config
+            .issuance_timing
+            .verify_child_overrides(&cert_timing)
+            .map_err(|msg| {
+                Error::CaChildCertTimingInvalid(
+                    self.handle.clone(),
+                    child_handle.clone(),
+                    msg.to_string(),
+                )
+            })?;
+
+        if child.cert_timing() != &cert_timing {
+            info!(
+                "CA '{}' update child '{}' certificate timing: {}",
+                self.handle, child_handle, cert_timing
+            );
+            Ok(vec![CertAuthEvent::child_updated_cert_timing(
+                child_handle.clone(),
+                cert_timing,
+            )])
+        } else {
+            debug!(
+                "CA '{}' update child '{}' certificate timing has no effect",
+                self.handle, child_handle
+            );
+            Ok(vec![])
+        }
+    }
+
     /// Updates the child resource class name mapping
     fn child_resource_class_name_mapping(
         &self,
@@ -1500,49 +1968,244 @@ impl CertAuth {
     }
 }
 
-/// # Being a child
+/// # Resource pools
 impl CertAuth {
-    /// Generates a new ID key for this CA.
-    fn generate_new_id_key(
+    /// Adds or replaces a resource pool. Pools may only contain resources
+    /// held by this CA, and may not overlap with other pools.
+    fn resource_pool_update(
         &self,
-        signer: Arc<KrillSigner>,
+        definition: ResourcePoolDefinition,
     ) -> KrillResult<Vec<CertAuthEvent>> {
-        let id = Rfc8183Id::generate(&signer)?;
-
-        info!(
-            "CA '{}' generated new ID certificate with key id: {}",
-            self.handle,
-            id.cert().public_key().key_identifier()
-        );
-        Ok(vec![CertAuthEvent::id_updated(id)])
-    }
+        let ResourcePoolDefinition { name, resources } = definition;
+        let invalid = |msg: String| {
+            Error::CaResourcePoolInvalid(
+                self.handle.clone(),
+                name.clone(),
+                msg,
+            )
+        };
 
-    /// List all parents
-    pub fn parents(&self) -> impl Iterator<Item = &ParentHandle> {
-        self.parents.keys()
-    }
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid(
+                "names may only contain letters, digits, '-' and '_'"
+                    .to_string(),
+            ));
+        }
+        if resources.is_empty() {
+            return Err(invalid("a pool must have resources".to_string()));
+        }
+        if !self.all_resources().contains(&resources) {
+            return Err(invalid(
+                "resources are not held by the CA".to_string(),
+            ));
+        }
+        if let Some(other) =
+            self.resource_pools.overlapping(&name, &resources)
+        {
+            return Err(invalid(format!(
+                "resources overlap with pool '{}'",
+                other
+            )));
+        }
 
-    pub fn nr_parents(&self) -> usize {
-        self.parents.len()
+        if self.resource_pools.get(&name) == Some(&resources) {
+            Ok(vec![])
+        } else {
+            info!(
+                "CA '{}' set resource pool '{}' to resources: {}",
+                self.handle, name, resources
+            );
+            Ok(vec![CertAuthEvent::ResourcePoolUpdated { name, resources }])
+        }
     }
 
-    pub fn parent_known(&self, parent: &ParentHandle) -> bool {
-        self.parents.contains_key(parent)
+    /// Removes a resource pool. Resources allocated from the pool remain
+    /// delegated to the children.
+    fn resource_pool_remove(
+        &self,
+        name: ResourcePoolName,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if !self.resource_pools.has(&name) {
+            Err(Error::CaResourcePoolUnknown(self.handle.clone(), name))
+        } else {
+            info!("CA '{}' removed resource pool '{}'", self.handle, name);
+            Ok(vec![CertAuthEvent::ResourcePoolRemoved { name }])
+        }
     }
 
-    fn parent_for_info(
+    /// Uses a new handle for a local child CA which was renamed. The
+    /// issued certificates and settings for the child are kept.
+    fn child_rename(
         &self,
-        info: &ParentCaContact,
-    ) -> Option<&ParentHandle> {
-        for (parent, parent_info) in &self.parents {
-            if parent_info == info {
-                return Some(parent);
-            }
+        child: ChildHandle,
+        new_handle: ChildHandle,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        self.get_child(&child)?;
+        if self.children.contains_key(&new_handle) {
+            return Err(Error::CaChildDuplicate(
+                self.handle.clone(),
+                new_handle,
+            ));
         }
-        None
+
+        info!(
+            "CA '{}' renamed child '{}' to '{}'",
+            self.handle, child, new_handle
+        );
+        Ok(vec![CertAuthEvent::ChildRenamed { child, new_handle }])
     }
 
-    /// Gets the ParentCaContact for this ParentHandle. Returns an Err when
+    /// Returns the resource pools of this CA, and how much of each pool
+    /// is delegated to children.
+    pub fn resource_pools(&self) -> ResourcePoolList {
+        self.resource_pools.info(&self.delegated_resources(None))
+    }
+
+    /// Returns the resources delegated to all children, other than the
+    /// given child if any.
+    fn delegated_resources(
+        &self,
+        except: Option<&ChildHandle>,
+    ) -> ResourceSet {
+        self.children
+            .iter()
+            .filter(|(handle, _)| Some(*handle) != except)
+            .fold(ResourceSet::empty(), |delegated, (_, child)| {
+                delegated.union(child.resources())
+            })
+    }
+
+    /// Returns the given resources for a child, extended with the next
+    /// free resources from a pool if the allocation asks for them.
+    fn child_resources_with_allocation(
+        &self,
+        child: &ChildHandle,
+        resources: ResourceSet,
+        allocation: &ChildAllocation,
+    ) -> KrillResult<ResourceSet> {
+        let from_pool = match &allocation.from_pool {
+            Some(from_pool) => from_pool,
+            None => return Ok(resources),
+        };
+
+        // Resources of this child, and resources it was given explicitly,
+        // are not free either.
+        let delegated = self.delegated_resources(None).union(&resources);
+        let free = self
+            .resource_pools
+            .free(&from_pool.pool, &self.all_resources(), &delegated)
+            .ok_or_else(|| {
+                Error::CaResourcePoolUnknown(
+                    self.handle.clone(),
+                    from_pool.pool.clone(),
+                )
+            })?;
+
+        let allocated =
+            allocate_from_pool(from_pool, &free).map_err(|msg| {
+                Error::CaResourcePoolAllocationFailed(
+                    self.handle.clone(),
+                    child.clone(),
+                    from_pool.pool.clone(),
+                    msg,
+                )
+            })?;
+
+        info!(
+            "CA '{}' allocated resources '{}' from pool '{}' to child '{}'",
+            self.handle, allocated, from_pool.pool, child
+        );
+        Ok(resources.union(&allocated))
+    }
+
+    /// Verifies that resources in pools are not delegated to the child
+    /// if they are delegated to another child already, unless this is
+    /// explicitly allowed.
+    fn verify_child_resources_overlap(
+        &self,
+        child: &ChildHandle,
+        resources: &ResourceSet,
+        allocation: &ChildAllocation,
+    ) -> KrillResult<()> {
+        if allocation.allow_overlap || self.resource_pools.is_empty() {
+            return Ok(());
+        }
+
+        let pooled =
+            resources.intersection(&self.resource_pools.all_resources());
+        if pooled.is_empty() {
+            return Ok(());
+        }
+
+        let mut others: Vec<_> = self
+            .children
+            .iter()
+            .filter(|(handle, _)| *handle != child)
+            .collect();
+        others.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+        for (other, details) in others {
+            let overlap = pooled.intersection(details.resources());
+            if !overlap.is_empty() {
+                return Err(Error::CaChildResourcesOverlap(
+                    self.handle.clone(),
+                    child.clone(),
+                    other.clone(),
+                    overlap,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # Being a child
+impl CertAuth {
+    /// Generates a new ID key for this CA.
+    fn generate_new_id_key(
+        &self,
+        signer: Arc<KrillSigner>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let id = Rfc8183Id::generate(&signer)?;
+
+        info!(
+            "CA '{}' generated new ID certificate with key id: {}",
+            self.handle,
+            id.cert().public_key().key_identifier()
+        );
+        Ok(vec![CertAuthEvent::id_updated(id)])
+    }
+
+    /// List all parents
+    pub fn parents(&self) -> impl Iterator<Item = &ParentHandle> {
+        self.parents.keys()
+    }
+
+    pub fn nr_parents(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn parent_known(&self, parent: &ParentHandle) -> bool {
+        self.parents.contains_key(parent)
+    }
+
+    fn parent_for_info(
+        &self,
+        info: &ParentCaContact,
+    ) -> Option<&ParentHandle> {
+        for (parent, parent_info) in &self.parents {
+            if parent_info == info {
+                return Some(parent);
+            }
+        }
+        None
+    }
+
+    /// Gets the ParentCaContact for this ParentHandle. Returns an Err when
     /// the parent does not exist.
     pub fn parent(
         &self,
@@ -1617,6 +2280,27 @@ impl CertAuth {
         }
     }
 
+    /// Uses a new local name for a parent. Its contact and resource
+    /// classes are kept. Returns an error if the parent doesn't exist, or
+    /// if the new name is already in use.
+    fn rename_parent(
+        &self,
+        parent: ParentHandle,
+        new_name: ParentHandle,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if !self.parent_known(&parent) {
+            Err(Error::CaParentUnknown(self.handle.clone(), parent))
+        } else if self.parent_known(&new_name) {
+            Err(Error::CaParentDuplicateName(self.handle.clone(), new_name))
+        } else {
+            info!(
+                "CA '{}' renamed parent '{}' to '{}'",
+                self.handle, parent, new_name
+            );
+            Ok(vec![CertAuthEvent::ParentRenamed { parent, new_name }])
+        }
+    }
+
     /// Updates an existing parent's contact. This will return an error if
     /// the parent is not known.
     fn update_parent(
@@ -1844,6 +2528,8 @@ impl CertAuth {
             self.handle, rcn
         );
 
+        let child_timings = self.child_cert_timings(&rcn);
+
         let rc = self
             .resources
             .get(&rcn)
@@ -1856,6 +2542,7 @@ impl CertAuth {
             &self.aspas,
             &self.bgpsec_defs,
             config,
+            &child_timings,
             signer.deref(),
         )
     }
@@ -1938,6 +2625,7 @@ impl CertAuth {
                 .keyroll_activate(
                     staging_time,
                     &config.issuance_timing,
+                    &self.child_cert_timings(rcn),
                     signer.deref(),
                 )?
                 .into_iter()
@@ -1978,7 +2666,273 @@ impl CertAuth {
             my_rc.parent_handle()
         );
 
-        Ok(vec![finish_event])
+        let mut res = vec![finish_event];
+
+        // When the last key roll for a repository migration is finished,
+        // the old repository is no longer used and can be cleaned up.
+        if self.repository_migration.as_ref().map(|m| m.phase())
+            == Some(RepoMigrationPhase::Certifying)
+            && self
+                .resources
+                .iter()
+                .all(|(name, rc)| name == &rcn || rc.key_roll_possible())
+        {
+            res.push(CertAuthEvent::RepoMigrationWithdrawing);
+        }
+
+        Ok(res)
+    }
+
+    /// Starts an emergency key roll in all resource classes. Resource
+    /// classes which are already rolling to a new key keep that new key,
+    /// but the roll cannot be started while an old key is still waiting
+    /// to be revoked.
+    fn keyroll_emergency(
+        &self,
+        reason: String,
+        signer: Arc<KrillSigner>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self
+            .resources
+            .values()
+            .any(|rc| rc.revoke_request().is_some())
+        {
+            return Err(Error::KeyRollInProgress);
+        }
+
+        warn!(
+            "Starting emergency key roll for ca: {}, reason: {}",
+            &self.handle, reason
+        );
+
+        let mut res = vec![CertAuthEvent::KeyRollEmergencyStarted { reason }];
+        res.append(&mut self.keyroll_initiate(Duration::seconds(0), signer)?);
+
+        Ok(res)
+    }
+
+    /// Returns the resource classes, and their parents, in which a key roll
+    /// was initiated, but the new key was not yet certified.
+    pub fn new_keys_pending(&self) -> Vec<(ResourceClassName, ParentHandle)> {
+        self.resources
+            .iter()
+            .filter(|(_, rc)| rc.new_key_pending())
+            .map(|(rcn, rc)| (rcn.clone(), rc.parent_handle().clone()))
+            .collect()
+    }
+
+    /// Returns the parents which did not yet revoke an old key.
+    pub fn parents_with_revoke_requests(&self) -> HashSet<ParentHandle> {
+        self.resources
+            .values()
+            .filter(|rc| rc.revoke_request().is_some())
+            .map(|rc| rc.parent_handle().clone())
+            .collect()
+    }
+
+    /// Returns a report of the keys that were replaced, and the objects
+    /// that were re-issued, in the given key activation events.
+    pub fn emergency_rekey_report(
+        &self,
+        reason: String,
+        events: Vec<CertAuthEvent>,
+    ) -> EmergencyRekeyReport {
+        let mut classes: Vec<RekeyedResourceClass> = vec![];
+
+        for event in events {
+            match event {
+                CertAuthEvent::KeyRollActivated {
+                    resource_class_name,
+                    revoke_req,
+                } => {
+                    if let Some(rc) = self.resources.get(&resource_class_name)
+                    {
+                        if let Some(current) = rc.current_key() {
+                            let mut rekeyed = RekeyedResourceClass::new(
+                                resource_class_name,
+                                rc.parent_handle().clone(),
+                                revoke_req.key(),
+                                *current.key_id(),
+                            );
+                            rekeyed.old_key_revoked =
+                                rc.revoke_request().is_none();
+                            classes.push(rekeyed);
+                        }
+                    }
+                }
+                CertAuthEvent::RoasUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    if let Some(rekeyed) = classes.iter_mut().find(|c| {
+                        c.resource_class_name == resource_class_name
+                    }) {
+                        rekeyed.roas.extend(updates.added_roas().into_keys());
+                    }
+                }
+                CertAuthEvent::AspaObjectsUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    if let Some(rekeyed) = classes.iter_mut().find(|c| {
+                        c.resource_class_name == resource_class_name
+                    }) {
+                        rekeyed.aspas.extend(
+                            updates.updated().iter().map(|aspa| {
+                                ObjectName::aspa(aspa.customer())
+                            }),
+                        );
+                    }
+                }
+                CertAuthEvent::BgpSecCertificatesUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    if let Some(rekeyed) = classes.iter_mut().find(|c| {
+                        c.resource_class_name == resource_class_name
+                    }) {
+                        rekeyed.bgpsec_certs.extend(
+                            updates.updated().iter().map(ObjectName::from),
+                        );
+                    }
+                }
+                CertAuthEvent::ChildCertificatesUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    if let Some(rekeyed) = classes.iter_mut().find(|c| {
+                        c.resource_class_name == resource_class_name
+                    }) {
+                        rekeyed.child_certs.extend(
+                            updates.issued().iter().map(|issued| {
+                                ObjectName::cer_for_key(
+                                    &issued.key_identifier(),
+                                )
+                            }),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        EmergencyRekeyReport {
+            ca: self.handle.clone(),
+            reason,
+            resource_classes: classes,
+        }
+    }
+
+    fn keyroll_policy_update(
+        &self,
+        policy: Option<KeyRollPolicy>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if let Some(policy) = &policy {
+            if policy.staging_too_short() {
+                return Err(Error::KeyRollStagingTooShort(
+                    policy.staging_hours,
+                ));
+            }
+        }
+
+        if policy == self.key_roll_policy {
+            Ok(vec![])
+        } else {
+            Ok(vec![CertAuthEvent::KeyRollPolicyUpdated { policy }])
+        }
+    }
+
+    /// Sets the overrides of the server issuance timing for this CA, or
+    /// removes them if `None` or empty overrides are given.
+    fn issuance_timing_update(
+        &self,
+        timing: Option<CaIssuanceTiming>,
+        config: &Config,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let timing = timing.filter(|timing| !timing.is_empty());
+
+        if let Some(timing) = &timing {
+            config.issuance_timing.for_ca(timing).verify().map_err(
+                |msg| {
+                    Error::CaIssuanceTimingInvalid(
+                        self.handle.clone(),
+                        msg.to_string(),
+                    )
+                },
+            )?;
+        }
+
+        if timing == self.issuance_timing {
+            Ok(vec![])
+        } else {
+            Ok(vec![CertAuthEvent::IssuanceTimingUpdated { timing }])
+        }
+    }
+
+    /// Returns the overrides of the server issuance timing set for this
+    /// CA, if any.
+    pub fn issuance_timing_overrides(&self) -> Option<&CaIssuanceTiming> {
+        self.issuance_timing.as_ref()
+    }
+
+    /// Returns the issuance timing for this CA, i.e. the given timing with
+    /// the overrides for this CA applied.
+    pub fn issuance_timing(
+        &self,
+        issuance_timing: &IssuanceTimingConfig,
+    ) -> IssuanceTimingConfig {
+        match &self.issuance_timing {
+            Some(overrides) => issuance_timing.for_ca(overrides),
+            None => issuance_timing.clone(),
+        }
+    }
+
+    /// Returns the given configuration with the issuance timing overrides
+    /// for this CA applied, if there are any.
+    fn ca_config(&self, config: Arc<Config>) -> Arc<Config> {
+        match &self.issuance_timing {
+            Some(overrides) => {
+                let mut ca_config = config.as_ref().clone();
+                ca_config.issuance_timing =
+                    config.issuance_timing.for_ca(overrides);
+                Arc::new(ca_config)
+            }
+            None => config,
+        }
+    }
+
+    /// Returns the key roll policy set for this CA, if any.
+    pub fn key_roll_policy(&self) -> Option<&KeyRollPolicy> {
+        self.key_roll_policy.as_ref()
+    }
+
+    /// Returns the key roll policy for this CA, and the next key roll
+    /// phase planned because of it. The given policy is used if none was
+    /// set for this CA.
+    pub fn key_roll_status(&self, default: KeyRollPolicy) -> KeyRollStatus {
+        let policy = self.key_roll_policy.unwrap_or(default);
+
+        KeyRollStatus {
+            policy,
+            ca_specific: self.key_roll_policy.is_some(),
+            next: self.planned_key_roll(&policy),
+        }
+    }
+
+    /// Returns the earliest key roll phase that is due for any of the
+    /// resource classes according to the given policy.
+    pub fn planned_key_roll(
+        &self,
+        policy: &KeyRollPolicy,
+    ) -> Option<PlannedKeyRoll> {
+        if !policy.is_enabled() {
+            return None;
+        }
+
+        self.resources
+            .values()
+            .filter_map(|rc| rc.planned_key_roll(policy))
+            .min_by_key(|planned| planned.due)
     }
 }
 
@@ -1986,19 +2940,59 @@ impl CertAuth {
 impl CertAuth {
     /// Update repository:
     ///    - Will return an error in case the repo is already set (issue 481)
-    ///    - Will support migrations using key rollover in future (issue 480)
+    ///    - Will return an error while a staged migration is in progress
     ///    - Assumes that the repository can be reached (this is checked by
     ///      CaManager before issuing the command to this CA)
     pub fn update_repo(
         &self,
         contact: RepositoryContact,
         signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self.repository_migration.is_some() {
+            return Err(Error::CaRepoMigrationInProgress(
+                self.handle.clone(),
+            ));
+        }
+        self.repo_switch(contact, signer)
+    }
+
+    /// Verifies that this CA can move from its current repository to the
+    /// new one.
+    fn repo_switch_check(
+        &self,
+        existing_contact: &RepositoryContact,
+        contact: &RepositoryContact,
+    ) -> KrillResult<()> {
+        if existing_contact == contact {
+            return Err(Error::CaRepoInUse(self.handle.clone()));
+        }
+        // Mirrors must publish the same objects under the same base
+        // URI, so they have to be removed before the CA can move to a
+        // repository with a different base URI.
+        if let Some(name) = self.repository_mirrors.keys().next() {
+            if contact.repo_info().base_uri()
+                != existing_contact.repo_info().base_uri()
+            {
+                return Err(Error::CaRepoMirrorInvalid(
+                    self.handle.clone(),
+                    name.clone(),
+                    "remove mirrors before changing the base URI".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches to the new repository, and initiates key rolls so that
+    /// the new keys are certified for the new repository.
+    fn repo_switch(
+        &self,
+        contact: RepositoryContact,
+        signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         let mut events = vec![];
         if let Some(existing_contact) = &self.repository {
-            if existing_contact == &contact {
-                return Err(Error::CaRepoInUse(self.handle.clone()));
-            }
+            self.repo_switch_check(existing_contact, &contact)?;
             // Initiate rolls in all RCs so we can use the new repo in the new
             // key.
             let info = contact.repo_info().clone();
@@ -2031,6 +3025,213 @@ impl CertAuth {
         events.push(CertAuthEvent::RepoUpdated { contact });
         Ok(events)
     }
+
+    /// Adds or updates a mirror repository. The mirror must use the same
+    /// base URI as the primary repository, because the objects published
+    /// there are identical, but it must use a different publication
+    /// server.
+    fn repo_mirror_add(
+        &self,
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self.repository_migration.is_some() {
+            return Err(Error::CaRepoMigrationInProgress(
+                self.handle.clone(),
+            ));
+        }
+        let invalid = |msg: &str| {
+            Error::CaRepoMirrorInvalid(
+                self.handle.clone(),
+                name.clone(),
+                msg.to_string(),
+            )
+        };
+
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid(
+                "names may only contain letters, digits, '-' and '_'",
+            ));
+        }
+
+        let primary = self.repository_contact()?;
+        if contact.repo_info().base_uri() != primary.repo_info().base_uri() {
+            return Err(invalid(
+                "the base URI differs from the primary repository",
+            ));
+        }
+
+        let service_uri = contact.server_info().service_uri();
+        if service_uri == primary.server_info().service_uri() {
+            return Err(invalid(
+                "the publication server is the primary repository",
+            ));
+        }
+        if self
+            .repository_mirrors
+            .iter()
+            .any(|(other, other_contact)| {
+                other != &name
+                    && other_contact.server_info().service_uri()
+                        == service_uri
+            })
+        {
+            return Err(invalid(
+                "the publication server is used by another mirror",
+            ));
+        }
+
+        if self.repository_mirrors.get(&name) == Some(&contact) {
+            return Ok(vec![]);
+        }
+
+        info!(
+            "CA '{}' set repository mirror '{}'. Service URI will be: {}",
+            self.handle, name, service_uri
+        );
+        Ok(vec![CertAuthEvent::RepoMirrorAdded { name, contact }])
+    }
+
+    /// Removes a mirror repository. Objects published there are withdrawn
+    /// by the publication process afterwards.
+    fn repo_mirror_remove(
+        &self,
+        name: RepositoryMirrorName,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        match self.repository_mirrors.get(&name) {
+            None => {
+                Err(Error::CaRepoMirrorUnknown(self.handle.clone(), name))
+            }
+            Some(contact) => {
+                info!(
+                    "CA '{}' removed repository mirror '{}'",
+                    self.handle, name
+                );
+                Ok(vec![CertAuthEvent::RepoMirrorRemoved {
+                    name,
+                    contact: contact.clone(),
+                }])
+            }
+        }
+    }
+
+    /// Starts a staged migration to a new repository. The objects of this
+    /// CA will be published there first, so that the new repository can
+    /// be verified before new certificates are requested from parents.
+    fn repo_migration_start(
+        &self,
+        contact: RepositoryContact,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self.repository_migration.is_some() {
+            return Err(Error::CaRepoMigrationInProgress(
+                self.handle.clone(),
+            ));
+        }
+        let existing_contact = self.repository_contact()?;
+        self.repo_switch_check(existing_contact, &contact)?;
+
+        // The migration ends in a key roll, so no other roll can be in
+        // progress.
+        if !self.resources.values().all(|rc| rc.key_roll_possible()) {
+            return Err(Error::KeyRollInProgress);
+        }
+
+        info!(
+            "CA '{}' started migration to repository at: {}",
+            self.handle,
+            contact.server_info().service_uri()
+        );
+        Ok(vec![CertAuthEvent::RepoMigrationStarted { contact }])
+    }
+
+    /// Switches to the new repository once all objects were found there.
+    /// This initiates key rolls to request certificates for the new
+    /// repository from the parents.
+    fn repo_migration_verified(
+        &self,
+        objects: usize,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let migration = self
+            .repository_migration
+            .as_ref()
+            .ok_or_else(|| Error::CaRepoMigrationNone(self.handle.clone()))?;
+
+        if migration.phase() != RepoMigrationPhase::Staging {
+            // Already verified, nothing to do.
+            return Ok(vec![]);
+        }
+
+        let mut res = vec![CertAuthEvent::RepoMigrationVerified { objects }];
+        let mut switch = self.repo_switch(migration.to().clone(), signer)?;
+
+        // Without any keys there is nothing to roll, so the old repository
+        // can be cleaned up right away.
+        let rolling = switch
+            .iter()
+            .any(|e| !matches!(e, CertAuthEvent::RepoUpdated { .. }));
+
+        res.append(&mut switch);
+        if !rolling {
+            res.push(CertAuthEvent::RepoMigrationWithdrawing);
+        }
+
+        info!(
+            "CA '{}' verified {} objects in new repository at: {}",
+            self.handle,
+            objects,
+            migration.to().server_info().service_uri()
+        );
+        Ok(res)
+    }
+
+    /// Finishes a migration after the old repository was cleaned up.
+    fn repo_migration_complete(&self) -> KrillResult<Vec<CertAuthEvent>> {
+        let migration = self
+            .repository_migration
+            .as_ref()
+            .ok_or_else(|| Error::CaRepoMigrationNone(self.handle.clone()))?;
+
+        if migration.phase() != RepoMigrationPhase::Withdrawing {
+            return Ok(vec![]);
+        }
+
+        info!(
+            "CA '{}' finished migration to repository at: {}",
+            self.handle,
+            migration.to().server_info().service_uri()
+        );
+        Ok(vec![CertAuthEvent::RepoMigrationFinished])
+    }
+
+    /// Aborts a migration. This is only possible while the new repository
+    /// is staging, because after that the parents may already have issued
+    /// certificates for the new repository.
+    fn repo_migration_abort(&self) -> KrillResult<Vec<CertAuthEvent>> {
+        let migration = self
+            .repository_migration
+            .as_ref()
+            .ok_or_else(|| Error::CaRepoMigrationNone(self.handle.clone()))?;
+
+        if migration.phase() != RepoMigrationPhase::Staging {
+            return Err(Error::CaRepoMigrationNotAbortable(
+                self.handle.clone(),
+            ));
+        }
+
+        info!(
+            "CA '{}' aborted migration to repository at: {}",
+            self.handle,
+            migration.to().server_info().service_uri()
+        );
+        Ok(vec![CertAuthEvent::RepoMigrationAborted {
+            contact: migration.to().clone(),
+        }])
+    }
 }
 
 /// # Managing Route Authorizations
@@ -2786,6 +3987,138 @@ impl CertAuth {
     }
 }
 
+/// # RPKI Signed Checklists
+impl CertAuth {
+    pub fn rsc_list(&self) -> RscList {
+        self.rscs.list()
+    }
+
+    pub fn rsc_show(&self, name: &str) -> KrillResult<SignedRsc> {
+        self.rscs.signed_rsc(name)
+    }
+
+    /// Sign a checklist with a one-off EE certificate under the current
+    /// key of each resource class that holds part of the resources.
+    ///
+    /// Unless this is a co-signing, this CA must hold all resources. When
+    /// co-signing, this CA signs for the resources it holds and leaves the
+    /// remaining resources to other CAs.
+    fn rsc_sign(
+        &self,
+        name: RscName,
+        request: RscSignRequest,
+        cosign: bool,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if self.rscs.has(&name) {
+            return Err(Error::RscAlreadyExists(name));
+        }
+
+        request.verify()?;
+
+        let resources = if cosign {
+            self.all_resources().intersection(request.resources())
+        } else if self.all_resources().contains(request.resources()) {
+            request.resources().clone()
+        } else {
+            return Err(Error::RscResourcesNotHeld);
+        };
+
+        if resources.is_empty() {
+            return Err(Error::RscResourcesNotHeld);
+        }
+
+        let mut objects = vec![];
+        for (rcn, rc) in self.resources.iter() {
+            if let Some(rc_resources) = rc.current_resources() {
+                let intersection = resources.intersection(rc_resources);
+                if !intersection.is_empty() {
+                    let (rsc, ee) = rc.create_rsc(
+                        &intersection,
+                        request.validity(),
+                        request.checklist(),
+                        signer,
+                    )?;
+                    objects.push(RscObject::new(
+                        rcn.clone(),
+                        intersection,
+                        Revocation::from(&ee),
+                        rsc,
+                    ));
+                }
+            }
+        }
+
+        let rsc =
+            SignedRsc::new(resources, request.checklist().clone(), objects);
+
+        info!("CA '{}' signed an RSC object named '{}'", self.handle, name);
+
+        Ok(vec![CertAuthEvent::RscSigned { name, rsc }])
+    }
+}
+
+/// # Signed geofeeds
+impl CertAuth {
+    pub fn geofeed_list(&self) -> GeofeedList {
+        self.geofeeds.list()
+    }
+
+    pub fn geofeed_show(&self, number: u64) -> KrillResult<SignedGeofeed> {
+        self.geofeeds.signed_geofeed(number)
+    }
+
+    pub fn geofeed_latest(&self) -> Option<&SignedGeofeed> {
+        self.geofeeds.latest()
+    }
+
+    /// Sign a geofeed with a one-off EE certificate under the current key
+    /// of the resource class that holds all of its prefixes.
+    fn geofeed_sign(
+        &self,
+        geofeed: Geofeed,
+        validity: Validity,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let not_held = geofeed.not_held(&self.all_resources());
+        if !not_held.is_empty() {
+            return Err(Error::GeofeedPrefixesNotHeld(Geofeed::prefixes(
+                &not_held,
+            )));
+        }
+
+        let resources = geofeed.resources();
+        let rc = self
+            .resources
+            .values()
+            .find(|rc| {
+                rc.current_resources()
+                    .map(|held| held.contains(&resources))
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| {
+                Error::GeofeedInvalid(
+                    "the prefixes are held in different resource classes"
+                        .to_string(),
+                )
+            })?;
+
+        let (text, ee) = rc.sign_geofeed(&geofeed, validity, signer)?;
+
+        let number = self.geofeeds.next_number();
+        let geofeed = SignedGeofeed::new(
+            number,
+            resources,
+            Revocation::from(&ee),
+            text,
+        );
+
+        info!("CA '{}' signed geofeed number {}", self.handle, number);
+
+        Ok(vec![CertAuthEvent::GeofeedSigned { geofeed }])
+    }
+}
+
 /// # Deactivate
 impl CertAuth {
     pub fn revoke_under_parent(
@@ -9,8 +9,8 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            ChildCaInfo, ChildState, IdCertInfo, IssuedCertificate,
-            ReceivedCert, SuspendedCert, UnsuspendedCert,
+            ChildCaInfo, ChildCertTiming, ChildState, IdCertInfo,
+            IssuedCertificate, ReceivedCert, SuspendedCert, UnsuspendedCert,
         },
         crypto::{KrillSigner, SignSupport},
         error::Error,
@@ -49,6 +49,8 @@ pub struct ChildDetails {
     used_keys: HashMap<KeyIdentifier, UsedKeyState>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     rcn_map: HashMap<ResourceClassName, ResourceClassName>,
+    #[serde(default, skip_serializing_if = "ChildCertTiming::is_empty")]
+    cert_timing: ChildCertTiming,
 }
 
 impl ChildDetails {
@@ -59,6 +61,7 @@ impl ChildDetails {
             resources,
             used_keys: HashMap::new(),
             rcn_map: HashMap::new(),
+            cert_timing: ChildCertTiming::default(),
         }
     }
 
@@ -90,6 +93,23 @@ impl ChildDetails {
         self.resources = resources;
     }
 
+    pub fn cert_timing(&self) -> &ChildCertTiming {
+        &self.cert_timing
+    }
+
+    pub fn set_cert_timing(&mut self, cert_timing: ChildCertTiming) {
+        self.cert_timing = cert_timing;
+    }
+
+    /// Returns the issuance timing for certificates issued to this child,
+    /// i.e. the given timing with the overrides for this child applied.
+    pub fn issuance_timing(
+        &self,
+        issuance_timing: &IssuanceTimingConfig,
+    ) -> IssuanceTimingConfig {
+        issuance_timing.for_child(&self.cert_timing)
+    }
+
     pub fn add_mapping(
         &mut self,
         name_in_parent: ResourceClassName,
@@ -190,6 +210,7 @@ impl ChildDetails {
 impl From<ChildDetails> for ChildCaInfo {
     fn from(details: ChildDetails) -> Self {
         ChildCaInfo::new(details.state, details.id_cert, details.resources)
+            .with_cert_timing(details.cert_timing)
     }
 }
 
@@ -265,10 +286,14 @@ impl ChildCertificates {
     }
 
     /// Re-issue everything when activating a new key
+    ///
+    /// Certificates for keys found in `child_timings` are re-issued using
+    /// the timing overrides for the child holding the key.
     pub fn activate_key(
         &self,
         signing_cert: &ReceivedCert,
         issuance_timing: &IssuanceTimingConfig,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<ChildCertificateUpdates> {
         let mut updates = ChildCertificateUpdates::default();
@@ -278,6 +303,7 @@ impl ChildCertificates {
                 None,
                 signing_cert,
                 issuance_timing,
+                child_timings,
                 signer,
             )?);
         }
@@ -290,6 +316,7 @@ impl ChildCertificates {
                     None,
                     signing_cert,
                     issuance_timing,
+                    child_timings,
                     signer,
                 )?
                 .into_converted(),
@@ -308,6 +335,7 @@ impl ChildCertificates {
         &self,
         received_cert: &ReceivedCert,
         issuance_timing: &IssuanceTimingConfig,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<ChildCertificateUpdates> {
         let mut updates = ChildCertificateUpdates::default();
@@ -328,6 +356,7 @@ impl ChildCertificates {
                         Some(reduced_set),
                         received_cert,
                         issuance_timing,
+                        child_timings,
                         signer,
                     )?);
                 }
@@ -355,6 +384,7 @@ impl ChildCertificates {
                             Some(reduced_set),
                             received_cert,
                             issuance_timing,
+                            child_timings,
                             signer,
                         )?
                         .into_converted(),
@@ -374,8 +404,16 @@ impl ChildCertificates {
         updated_resources: Option<ResourceSet>,
         signing_cert: &ReceivedCert,
         issuance_timing: &IssuanceTimingConfig,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<IssuedCertificate> {
+        let validity = match child_timings.get(&previous.key_identifier()) {
+            Some(cert_timing) => issuance_timing
+                .for_child(cert_timing)
+                .new_child_cert_validity(),
+            None => issuance_timing.new_child_cert_validity(),
+        };
+
         let csr_info = previous.csr_info().clone();
         let resource_set =
             updated_resources.unwrap_or_else(|| previous.resources().clone());
@@ -386,7 +424,7 @@ impl ChildCertificates {
             &resource_set,
             limit,
             signing_cert,
-            issuance_timing.new_child_cert_validity(),
+            validity,
             signer,
         )?;
 
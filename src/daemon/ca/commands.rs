@@ -10,7 +10,7 @@ use rpki::{
             ResourceClassName, RevocationRequest, RevocationResponse,
         },
     },
-    repository::resources::ResourceSet,
+    repository::{resources::ResourceSet, x509::Validity},
 };
 
 use crate::{
@@ -18,10 +18,13 @@ use crate::{
         actor::Actor,
         api::{
             import::ImportChild, AspaDefinitionUpdates, AspaProvidersUpdate,
-            BgpSecDefinitionUpdates, CertAuthStorableCommand, CustomerAsn,
-            IdCertInfo, ParentCaContact, ReceivedCert, RepositoryContact,
-            ResourceClassNameMapping, RoaConfigurationUpdates, RtaName,
-            StorableRcEntitlement,
+            BgpSecDefinitionUpdates, CaIssuanceTiming,
+            CertAuthStorableCommand, ChildAllocation, ChildCertTiming,
+            CustomerAsn, IdCertInfo, KeyRollPolicy, ParentCaContact,
+            ReceivedCert, RepositoryContact, RepositoryMirrorName,
+            ResourceClassNameMapping, ResourcePoolDefinition,
+            ResourcePoolName, RoaConfigurationUpdates, RscName, RtaName,
+            StorableRcEntitlement, Timestamp,
         },
         crypto::KrillSigner,
         eventsourcing::{
@@ -31,8 +34,8 @@ use crate::{
     },
     daemon::{
         ca::{
-            CertAuthEvent, ResourceTaggedAttestation, RtaContentRequest,
-            RtaPrepareRequest,
+            CertAuthEvent, Geofeed, ResourceTaggedAttestation,
+            RscSignRequest, RtaContentRequest, RtaPrepareRequest,
         },
         config::Config,
     },
@@ -88,19 +91,25 @@ pub enum CertAuthCommandDetails {
     // Being a parent
     // ------------------------------------------------------------
 
-    // Add a new child under this parent CA
-    ChildAdd(ChildHandle, IdCertInfo, ResourceSet),
+    // Add a new child under this parent CA, with the given resources and
+    // any resources allocated from a resource pool.
+    ChildAdd(ChildHandle, IdCertInfo, ResourceSet, ChildAllocation),
 
     // Import a child under this parent CA
     ChildImport(ImportChild, Arc<Config>, Arc<KrillSigner>),
 
-    // Update the resource entitlements for an existing child.
-    ChildUpdateResources(ChildHandle, ResourceSet),
+    // Update the resource entitlements for an existing child, to the given
+    // resources and any resources allocated from a resource pool.
+    ChildUpdateResources(ChildHandle, ResourceSet, ChildAllocation),
 
     // Update the IdCert used by the child for the RFC 6492 RPKI
     // provisioning protocol.
     ChildUpdateId(ChildHandle, IdCertInfo),
 
+    // Update the certificate validity and re-issue time overrides for
+    // an existing child.
+    ChildUpdateCertTiming(ChildHandle, ChildCertTiming, Arc<Config>),
+
     // Update the mapping the parent uses to map its own resource
     // class name to another name for the child.
     ChildUpdateResourceClassNameMapping(
@@ -131,6 +140,26 @@ pub enum CertAuthCommandDetails {
     // resources no longer associated with this child.
     ChildUnsuspend(ChildHandle),
 
+    // Add or replace a resource pool, from which resources can be
+    // allocated to children.
+    ResourcePoolUpdate(ResourcePoolDefinition),
+
+    // Remove a resource pool. Resources that were allocated from the pool
+    // remain with the children.
+    ResourcePoolRemove(ResourcePoolName),
+
+    // Use a new handle for a local child CA which was renamed.
+    ChildRename(ChildHandle, ChildHandle),
+
+    // Start renaming this CA to the given handle. The old handle may be
+    // kept as an alias until the given time.
+    RenameStart(CaHandle, Option<Timestamp>),
+
+    // Finish renaming this CA from the given handle, after its stored data
+    // was moved. Includes the new contact for the embedded repository, if
+    // the CA publishes there.
+    Rename(CaHandle, Option<RepositoryContact>),
+
     // ------------------------------------------------------------
     // Being a child (only allowed if this CA is not self-signed)
     // ------------------------------------------------------------
@@ -149,6 +178,8 @@ pub enum CertAuthCommandDetails {
     UpdateParentContact(ParentHandle, ParentCaContact),
     // Remove a parent, freeing up its handle for future (re-)use.
     RemoveParent(ParentHandle),
+    // Use a new local name for a parent, keeping its resource classes.
+    RenameParent(ParentHandle, ParentHandle),
 
     // Process new entitlements from a parent and remove/create/update
     // ResourceClasses and certificate requests or key revocation requests
@@ -194,6 +225,20 @@ pub enum CertAuthCommandDetails {
     // withdraw the crl and mft for it.
     KeyRollFinish(ResourceClassName, RevocationResponse),
 
+    // Set the policy for rolling the keys of this CA automatically, or
+    // follow the policy from the server configuration if there is none.
+    KeyRollPolicyUpdate(Option<KeyRollPolicy>),
+
+    // Start an emergency key roll for all resource classes, because the
+    // current keys may be compromised. The reason is kept in the history.
+    // The new keys are activated as soon as they are certified, without
+    // the normal staging period.
+    KeyRollEmergency(String, Arc<KrillSigner>),
+
+    // Set overrides of the server issuance timing for this CA, or remove
+    // them so that the server configuration is used.
+    IssuanceTimingUpdate(Option<CaIssuanceTiming>, Arc<Config>),
+
     // ------------------------------------------------------------
     // ROA Support
     // ------------------------------------------------------------
@@ -261,6 +306,26 @@ pub enum CertAuthCommandDetails {
     // Update the repository where this CA publishes
     RepoUpdate(RepositoryContact, Arc<KrillSigner>),
 
+    // Add or replace a repository which mirrors the primary repository
+    RepoMirrorAdd(RepositoryMirrorName, RepositoryContact),
+
+    // Remove a repository mirror
+    RepoMirrorRemove(RepositoryMirrorName),
+
+    // Start a staged migration to a new repository
+    RepoMigrationStart(RepositoryContact),
+
+    // Mark the new repository as verified and request new certificates
+    // for it from the parents. Includes the number of objects which were
+    // found in the new repository.
+    RepoMigrationVerified(usize, Arc<KrillSigner>),
+
+    // Finish the migration after the old repository was cleaned up
+    RepoMigrationComplete,
+
+    // Abort a migration which is still staging
+    RepoMigrationAbort,
+
     // ------------------------------------------------------------
     // Resource Tagged Attestations
     // ------------------------------------------------------------
@@ -273,6 +338,23 @@ pub enum CertAuthCommandDetails {
 
     // Co-sign an existing multi-signed RTA
     RtaCoSign(RtaName, ResourceTaggedAttestation, Arc<KrillSigner>),
+
+    // ------------------------------------------------------------
+    // RPKI Signed Checklists
+    // ------------------------------------------------------------
+
+    // Sign a new RSC, the CA must hold all resources
+    RscSign(RscName, RscSignRequest, Arc<KrillSigner>),
+
+    // Co-sign an RSC for the resources held by this CA
+    RscCoSign(RscName, RscSignRequest, Arc<KrillSigner>),
+
+    // ------------------------------------------------------------
+    // Signed geofeeds
+    // ------------------------------------------------------------
+
+    // Sign a geofeed, the CA must hold all its prefixes
+    GeofeedSign(Geofeed, Validity, Arc<KrillSigner>),
 }
 
 impl eventsourcing::CommandDetails for CertAuthCommandDetails {
@@ -296,13 +378,17 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             // ------------------------------------------------------------
             // Being a parent
             // ------------------------------------------------------------
-            CertAuthCommandDetails::ChildAdd(child, id_cert, resources) => {
-                CertAuthStorableCommand::ChildAdd {
-                    child,
-                    ski: id_cert.public_key().key_identifier().to_string(),
-                    resources,
-                }
-            }
+            CertAuthCommandDetails::ChildAdd(
+                child,
+                id_cert,
+                resources,
+                allocation,
+            ) => CertAuthStorableCommand::ChildAdd {
+                child,
+                ski: id_cert.public_key().key_identifier().to_string(),
+                resources,
+                allocation,
+            },
             CertAuthCommandDetails::ChildImport(import_child, _, _) => {
                 CertAuthStorableCommand::ChildImport {
                     child: import_child.name,
@@ -317,9 +403,11 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             CertAuthCommandDetails::ChildUpdateResources(
                 child,
                 resources,
+                allocation,
             ) => CertAuthStorableCommand::ChildUpdateResources {
                 child,
                 resources,
+                allocation,
             },
             CertAuthCommandDetails::ChildUpdateId(child, id_cert) => {
                 CertAuthStorableCommand::ChildUpdateId {
@@ -327,6 +415,14 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
                     ski: id_cert.public_key().key_identifier().to_string(),
                 }
             }
+            CertAuthCommandDetails::ChildUpdateCertTiming(
+                child,
+                cert_timing,
+                _,
+            ) => CertAuthStorableCommand::ChildUpdateCertTiming {
+                child,
+                cert_timing,
+            },
             CertAuthCommandDetails::ChildUpdateResourceClassNameMapping(
                 child,
                 mapping,
@@ -358,6 +454,24 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             CertAuthCommandDetails::ChildUnsuspend(child) => {
                 CertAuthStorableCommand::ChildUnsuspend { child }
             }
+            CertAuthCommandDetails::ResourcePoolUpdate(definition) => {
+                CertAuthStorableCommand::ResourcePoolUpdate {
+                    name: definition.name,
+                    resources: definition.resources,
+                }
+            }
+            CertAuthCommandDetails::ResourcePoolRemove(name) => {
+                CertAuthStorableCommand::ResourcePoolRemove { name }
+            }
+            CertAuthCommandDetails::ChildRename(child, new_handle) => {
+                CertAuthStorableCommand::ChildRename { child, new_handle }
+            }
+            CertAuthCommandDetails::RenameStart(to, alias_until) => {
+                CertAuthStorableCommand::RenameStart { to, alias_until }
+            }
+            CertAuthCommandDetails::Rename(from, _) => {
+                CertAuthStorableCommand::Rename { from }
+            }
 
             // ------------------------------------------------------------
             // Being a child
@@ -380,6 +494,9 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             CertAuthCommandDetails::RemoveParent(parent) => {
                 CertAuthStorableCommand::RemoveParent { parent }
             }
+            CertAuthCommandDetails::RenameParent(parent, new_name) => {
+                CertAuthStorableCommand::RenameParent { parent, new_name }
+            }
             CertAuthCommandDetails::UpdateEntitlements(
                 parent,
                 cmd_entitlements,
@@ -434,6 +551,15 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
                     resource_class_name,
                 }
             }
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy) => {
+                CertAuthStorableCommand::KeyRollPolicyUpdate { policy }
+            }
+            CertAuthCommandDetails::KeyRollEmergency(reason, _) => {
+                CertAuthStorableCommand::KeyRollEmergency { reason }
+            }
+            CertAuthCommandDetails::IssuanceTimingUpdate(timing, _) => {
+                CertAuthStorableCommand::IssuanceTimingUpdate { timing }
+            }
 
             // ------------------------------------------------------------
             // ROA Support
@@ -487,6 +613,31 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
                     service_uri: contact.server_info().service_uri().clone(),
                 }
             }
+            CertAuthCommandDetails::RepoMirrorAdd(name, contact) => {
+                CertAuthStorableCommand::RepoMirrorAdd {
+                    name: name.clone(),
+                    service_uri: contact.server_info().service_uri().clone(),
+                }
+            }
+            CertAuthCommandDetails::RepoMirrorRemove(name) => {
+                CertAuthStorableCommand::RepoMirrorRemove {
+                    name: name.clone(),
+                }
+            }
+            CertAuthCommandDetails::RepoMigrationStart(contact) => {
+                CertAuthStorableCommand::RepoMigrationStart {
+                    service_uri: contact.server_info().service_uri().clone(),
+                }
+            }
+            CertAuthCommandDetails::RepoMigrationVerified(objects, _) => {
+                CertAuthStorableCommand::RepoMigrationVerified { objects }
+            }
+            CertAuthCommandDetails::RepoMigrationComplete => {
+                CertAuthStorableCommand::RepoMigrationComplete
+            }
+            CertAuthCommandDetails::RepoMigrationAbort => {
+                CertAuthStorableCommand::RepoMigrationAbort
+            }
 
             // ------------------------------------------------------------
             // Resource Tagged Attestations
@@ -500,6 +651,25 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             CertAuthCommandDetails::RtaCoSign(name, _, _) => {
                 CertAuthStorableCommand::RtaCoSign { name }
             }
+
+            // ------------------------------------------------------------
+            // RPKI Signed Checklists
+            // ------------------------------------------------------------
+            CertAuthCommandDetails::RscSign(name, _, _) => {
+                CertAuthStorableCommand::RscSign { name }
+            }
+            CertAuthCommandDetails::RscCoSign(name, _, _) => {
+                CertAuthStorableCommand::RscCoSign { name }
+            }
+
+            // ------------------------------------------------------------
+            // Signed geofeeds
+            // ------------------------------------------------------------
+            CertAuthCommandDetails::GeofeedSign(geofeed, _, _) => {
+                CertAuthStorableCommand::GeofeedSign {
+                    resources: geofeed.resources(),
+                }
+            }
         }
     }
 }
@@ -512,6 +682,7 @@ impl CertAuthCommandDetails {
         child_handle: ChildHandle,
         id_cert: IdCertInfo,
         resources: ResourceSet,
+        allocation: ChildAllocation,
         actor: &Actor,
     ) -> CertAuthCommand {
         eventsourcing::SentCommand::new(
@@ -521,6 +692,7 @@ impl CertAuthCommandDetails {
                 child_handle,
                 id_cert,
                 resources,
+                allocation,
             ),
             actor,
         )
@@ -545,6 +717,7 @@ impl CertAuthCommandDetails {
         handle: &CaHandle,
         child_handle: ChildHandle,
         resources: ResourceSet,
+        allocation: ChildAllocation,
         actor: &Actor,
     ) -> CertAuthCommand {
         eventsourcing::SentCommand::new(
@@ -553,6 +726,7 @@ impl CertAuthCommandDetails {
             CertAuthCommandDetails::ChildUpdateResources(
                 child_handle,
                 resources,
+                allocation,
             ),
             actor,
         )
@@ -572,6 +746,25 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn child_update_cert_timing(
+        handle: &CaHandle,
+        child_handle: ChildHandle,
+        cert_timing: ChildCertTiming,
+        config: Arc<Config>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::ChildUpdateCertTiming(
+                child_handle,
+                cert_timing,
+                config,
+            ),
+            actor,
+        )
+    }
+
     pub fn child_update_resource_class_name_mapping(
         handle: &CaHandle,
         child_handle: ChildHandle,
@@ -666,6 +859,74 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn resource_pool_update(
+        handle: &CaHandle,
+        definition: ResourcePoolDefinition,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::ResourcePoolUpdate(definition),
+            actor,
+        )
+    }
+
+    pub fn resource_pool_remove(
+        handle: &CaHandle,
+        name: ResourcePoolName,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::ResourcePoolRemove(name),
+            actor,
+        )
+    }
+
+    pub fn child_rename(
+        handle: &CaHandle,
+        child_handle: ChildHandle,
+        new_handle: ChildHandle,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::ChildRename(child_handle, new_handle),
+            actor,
+        )
+    }
+
+    pub fn rename_start(
+        handle: &CaHandle,
+        to: CaHandle,
+        alias_until: Option<Timestamp>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RenameStart(to, alias_until),
+            actor,
+        )
+    }
+
+    pub fn rename(
+        handle: &CaHandle,
+        from: CaHandle,
+        repository: Option<RepositoryContact>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::Rename(from, repository),
+            actor,
+        )
+    }
+
     pub fn update_id(
         handle: &CaHandle,
         signer: Arc<KrillSigner>,
@@ -720,6 +981,20 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn rename_parent(
+        handle: &CaHandle,
+        parent: ParentHandle,
+        new_name: ParentHandle,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RenameParent(parent, new_name),
+            actor,
+        )
+    }
+
     pub fn update_entitlements(
         handle: &CaHandle,
         parent: ParentHandle,
@@ -821,6 +1096,47 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn key_roll_policy_update(
+        handle: &CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy),
+            actor,
+        )
+    }
+
+    pub fn issuance_timing_update(
+        handle: &CaHandle,
+        timing: Option<CaIssuanceTiming>,
+        config: Arc<Config>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::IssuanceTimingUpdate(timing, config),
+            actor,
+        )
+    }
+
+    pub fn key_roll_emergency(
+        handle: &CaHandle,
+        reason: String,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::KeyRollEmergency(reason, signer),
+            actor,
+        )
+    }
+
     pub fn update_repo(
         handle: &CaHandle,
         contact: RepositoryContact,
@@ -835,6 +1151,84 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn repo_mirror_add(
+        handle: &CaHandle,
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMirrorAdd(name, contact),
+            actor,
+        )
+    }
+
+    pub fn repo_mirror_remove(
+        handle: &CaHandle,
+        name: RepositoryMirrorName,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMirrorRemove(name),
+            actor,
+        )
+    }
+
+    pub fn repo_migration_start(
+        handle: &CaHandle,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMigrationStart(contact),
+            actor,
+        )
+    }
+
+    pub fn repo_migration_verified(
+        handle: &CaHandle,
+        objects: usize,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMigrationVerified(objects, signer),
+            actor,
+        )
+    }
+
+    pub fn repo_migration_complete(
+        handle: &CaHandle,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMigrationComplete,
+            actor,
+        )
+    }
+
+    pub fn repo_migration_abort(
+        handle: &CaHandle,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RepoMigrationAbort,
+            actor,
+        )
+    }
+
     //-------------------------------------------------------------------------------
     // Route Authorizations
     //-------------------------------------------------------------------------------
@@ -958,4 +1352,55 @@ impl CertAuthCommandDetails {
             actor,
         )
     }
+
+    //-------------------------------------------------------------------------------
+    // RPKI Signed Checklists
+    //-------------------------------------------------------------------------------
+    pub fn rsc_sign(
+        handle: &CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RscSign(name, request, signer),
+            actor,
+        )
+    }
+
+    pub fn rsc_cosign(
+        handle: &CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::RscCoSign(name, request, signer),
+            actor,
+        )
+    }
+
+    //-------------------------------------------------------------------------------
+    // Signed geofeeds
+    //-------------------------------------------------------------------------------
+    pub fn geofeed_sign(
+        handle: &CaHandle,
+        geofeed: Geofeed,
+        validity: Validity,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::GeofeedSign(geofeed, validity, signer),
+            actor,
+        )
+    }
 }
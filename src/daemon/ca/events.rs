@@ -2,7 +2,7 @@ use std::{collections::HashMap, fmt};
 
 use rpki::{
     ca::{
-        idexchange::{ChildHandle, ParentHandle},
+        idexchange::{CaHandle, ChildHandle, ParentHandle},
         provisioning::{
             IssuanceRequest, ParentResourceClassName, ResourceClassName,
             RevocationRequest,
@@ -15,18 +15,20 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            AspaDefinition, AspaProvidersUpdate, BgpSecAsnKey, CustomerAsn,
-            IdCertInfo, IssuedCertificate, ObjectName, ParentCaContact,
-            ReceivedCert, RepositoryContact, ResourceClassNameMapping,
-            RoaAggregateKey, RtaName, SuspendedCert, UnsuspendedCert,
+            AspaDefinition, AspaProvidersUpdate, BgpSecAsnKey,
+            CaIssuanceTiming, ChildCertTiming, CustomerAsn, IdCertInfo,
+            IssuedCertificate, KeyRollPolicy, ObjectName, ParentCaContact,
+            ReceivedCert, RepositoryContact, RepositoryMirrorName,
+            ResourceClassNameMapping, ResourcePoolName, RoaAggregateKey,
+            RscName, RtaName, SuspendedCert, Timestamp, UnsuspendedCert,
         },
         crypto::KrillSigner,
         eventsourcing::{Event, InitEvent},
         KrillResult,
     },
     daemon::ca::{
-        AspaInfo, CertifiedKey, PreparedRta, RoaInfo, RoaPayloadJsonMapKey,
-        SignedRta,
+        AspaInfo, CertifiedKey, Geofeed, PreparedRta, RoaInfo,
+        RoaPayloadJsonMapKey, SignedGeofeed, SignedRsc, SignedRta,
     },
 };
 
@@ -433,6 +435,57 @@ impl ChildCertificateUpdates {
     }
 }
 
+//------------ PendingRename ------------------------------------------------
+
+/// A rename of a CA which was started, but not yet finished.
+///
+/// The stored data of the CA is moved to the new handle in several steps.
+/// The rename is kept in the CA until all steps are done, so that an
+/// interrupted rename can be resumed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PendingRename {
+    from: CaHandle,
+    to: CaHandle,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    alias_until: Option<Timestamp>,
+}
+
+impl PendingRename {
+    pub fn new(
+        from: CaHandle,
+        to: CaHandle,
+        alias_until: Option<Timestamp>,
+    ) -> Self {
+        PendingRename {
+            from,
+            to,
+            alias_until,
+        }
+    }
+
+    pub fn from(&self) -> &CaHandle {
+        &self.from
+    }
+
+    pub fn to(&self) -> &CaHandle {
+        &self.to
+    }
+
+    pub fn alias_until(&self) -> Option<Timestamp> {
+        self.alias_until
+    }
+}
+
+impl fmt::Display for PendingRename {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "from '{}' to '{}'", self.from, self.to)?;
+        if let Some(until) = self.alias_until {
+            write!(f, ", alias kept until {}", until.to_rfc3339())?;
+        }
+        Ok(())
+    }
+}
+
 //------------ CertAuthEvent ------------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -477,6 +530,10 @@ pub enum CertAuthEvent {
         child: ChildHandle,
         resources: ResourceSet,
     },
+    ChildUpdatedCertTiming {
+        child: ChildHandle,
+        cert_timing: ChildCertTiming,
+    },
     ChildUpdatedResourceClassNameMapping {
         child: ChildHandle,
         name_in_parent: ResourceClassName,
@@ -485,6 +542,12 @@ pub enum CertAuthEvent {
     ChildRemoved {
         child: ChildHandle,
     },
+    ChildRenamed {
+        // A local child CA was renamed, so it uses a new handle in
+        // RFC 6492 messages.
+        child: ChildHandle,
+        new_handle: ChildHandle,
+    },
 
     // (Un)Suspend a child events
     ChildSuspended {
@@ -494,6 +557,30 @@ pub enum CertAuthEvent {
         child: ChildHandle,
     },
 
+    // Resource pools for allocating resources to children
+    ResourcePoolUpdated {
+        name: ResourcePoolName,
+        resources: ResourceSet,
+    },
+    ResourcePoolRemoved {
+        name: ResourcePoolName,
+    },
+
+    // A rename of the CA was started. Its stored data is moved to the new
+    // handle before the rename is finished.
+    RenameStarted {
+        rename: PendingRename,
+    },
+
+    // The CA was renamed. The old handle may be kept as an alias in API
+    // calls until the given time. If the CA used the embedded repository
+    // then it was renamed there as well, and the new contact is included.
+    Renamed {
+        from: CaHandle,
+        alias_until: Option<Timestamp>,
+        repository: Option<RepositoryContact>,
+    },
+
     // Being a child Events
     IdUpdated {
         id: Rfc8183Id,
@@ -509,6 +596,10 @@ pub enum CertAuthEvent {
     ParentRemoved {
         parent: ParentHandle,
     },
+    ParentRenamed {
+        parent: ParentHandle,
+        new_name: ParentHandle,
+    },
     ResourceClassAdded {
         resource_class_name: ResourceClassName,
         parent: ParentHandle,
@@ -586,6 +677,23 @@ pub enum CertAuthEvent {
         resource_class_name: ResourceClassName,
         revoke_req: RevocationRequest,
     },
+    KeyRollPolicyUpdated {
+        // The policy for automatic key rolls was set for this CA, or
+        // removed so that the policy from the server configuration is used.
+        policy: Option<KeyRollPolicy>,
+    },
+    IssuanceTimingUpdated {
+        // Overrides of the server issuance timing were set for this CA, or
+        // removed so that the server configuration is used.
+        timing: Option<CaIssuanceTiming>,
+    },
+    KeyRollEmergencyStarted {
+        // An emergency key roll was started because the current keys may
+        // be compromised. This event does not change the state of the CA,
+        // it records the reason. The key roll itself is tracked in the
+        // normal key roll events.
+        reason: String,
+    },
 
     // Route Authorizations
     RouteAuthorizationAdded {
@@ -656,6 +764,37 @@ pub enum CertAuthEvent {
         // it knows which URIs it can use.
         contact: RepositoryContact,
     },
+    RepoMirrorAdded {
+        // Adds or replaces a repository which mirrors the objects
+        // published in the primary repository.
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+    },
+    RepoMirrorRemoved {
+        // The contact is kept so that objects can be withdrawn from the
+        // mirror.
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+    },
+    RepoMigrationStarted {
+        // Starts publishing the objects of this CA to the new repository,
+        // so that it can be verified before any certificates are
+        // requested for it.
+        contact: RepositoryContact,
+    },
+    RepoMigrationVerified {
+        // All objects were found in the new repository. This event is
+        // followed by a 'RepoUpdated' event, and the key roll events for
+        // requesting certificates for the new repository.
+        objects: usize,
+    },
+    RepoMigrationWithdrawing, // The key roll for the new repository is done.
+    RepoMigrationFinished,    // The old repository was cleaned up.
+    RepoMigrationAborted {
+        // The contact is kept so that the staged objects can be withdrawn
+        // from the new repository.
+        contact: RepositoryContact,
+    },
 
     // Rta
     //
@@ -672,6 +811,19 @@ pub enum CertAuthEvent {
         name: RtaName,
         prepared: PreparedRta,
     },
+
+    // Rsc
+    RscSigned {
+        // Adds the RSC objects signed by this CA for a checklist.
+        name: RscName,
+        rsc: SignedRsc,
+    },
+
+    // Geofeed
+    GeofeedSigned {
+        // Adds a geofeed signed by this CA to its history.
+        geofeed: SignedGeofeed,
+    },
 }
 
 impl Event for CertAuthEvent {}
@@ -724,6 +876,13 @@ impl CertAuthEvent {
         CertAuthEvent::ChildUpdatedResources { child, resources }
     }
 
+    pub(super) fn child_updated_cert_timing(
+        child: ChildHandle,
+        cert_timing: ChildCertTiming,
+    ) -> CertAuthEvent {
+        CertAuthEvent::ChildUpdatedCertTiming { child, cert_timing }
+    }
+
     pub(super) fn child_certificate_issued(
         child: ChildHandle,
         resource_class_name: ResourceClassName,
@@ -867,6 +1026,9 @@ impl fmt::Display for CertAuthEvent {
             CertAuthEvent::ChildUpdatedResources { child, resources } => {
                 write!(f, "updated child '{}' resources to '{}'", child, resources)
             }
+            CertAuthEvent::ChildUpdatedCertTiming { child, cert_timing } => {
+                write!(f, "updated child '{}' certificate timing to: {}", child, cert_timing)
+            }
             CertAuthEvent::ChildUpdatedResourceClassNameMapping {
                 child,
                 name_in_parent,
@@ -880,8 +1042,24 @@ impl fmt::Display for CertAuthEvent {
             }
 
             CertAuthEvent::ChildRemoved { child } => write!(f, "removed child '{}'", child),
+            CertAuthEvent::ChildRenamed { child, new_handle } => write!(f, "renamed child '{}' to '{}'", child, new_handle),
             CertAuthEvent::ChildSuspended { child } => write!(f, "suspended child '{}'", child),
             CertAuthEvent::ChildUnsuspended { child } => write!(f, "unsuspended child '{}'", child),
+            CertAuthEvent::ResourcePoolUpdated { name, resources } => {
+                write!(f, "set resource pool '{}' to resources '{}'", name, resources)
+            }
+            CertAuthEvent::ResourcePoolRemoved { name } => write!(f, "removed resource pool '{}'", name),
+            CertAuthEvent::RenameStarted { rename } => write!(f, "started rename {}", rename),
+            CertAuthEvent::Renamed { from, alias_until, repository } => {
+                write!(f, "renamed from '{}'", from)?;
+                if let Some(until) = alias_until {
+                    write!(f, ", alias kept until {}", until.to_rfc3339())?;
+                }
+                if let Some(contact) = repository {
+                    write!(f, ", now using repository: {}", contact.server_info().service_uri())?;
+                }
+                Ok(())
+            }
 
             // Being a child Events
             CertAuthEvent::IdUpdated { id } => write!(
@@ -896,6 +1074,9 @@ impl fmt::Display for CertAuthEvent {
                 write!(f, "updated parent '{}'", parent)
             }
             CertAuthEvent::ParentRemoved { parent } => write!(f, "removed parent '{}'", parent),
+            CertAuthEvent::ParentRenamed { parent, new_name } => {
+                write!(f, "renamed parent '{}' to '{}'", parent, new_name)
+            }
 
             CertAuthEvent::ResourceClassAdded {
                 resource_class_name, ..
@@ -978,6 +1159,17 @@ impl fmt::Display for CertAuthEvent {
                 resource_class_name,
                 revoke_req.key()
             ),
+            CertAuthEvent::KeyRollPolicyUpdated { policy } => match policy {
+                Some(policy) => write!(f, "key roll policy set to: {}", policy),
+                None => write!(f, "key roll policy removed, using server default"),
+            },
+            CertAuthEvent::KeyRollEmergencyStarted { reason } => {
+                write!(f, "emergency key roll started, reason: {}", reason)
+            }
+            CertAuthEvent::IssuanceTimingUpdated { timing } => match timing {
+                Some(timing) => write!(f, "issuance timing set to: {}", timing),
+                None => write!(f, "issuance timing removed, using server defaults"),
+            },
 
             // Route Authorizations
             CertAuthEvent::RouteAuthorizationAdded { auth } => write!(f, "added ROA: '{}'", auth),
@@ -1102,6 +1294,40 @@ impl fmt::Display for CertAuthEvent {
                     contact.server_info().service_uri()
                 )
             }
+            CertAuthEvent::RepoMirrorAdded { name, contact } => {
+                write!(
+                    f,
+                    "set repository mirror '{}' to remote server: {}",
+                    name,
+                    contact.server_info().service_uri()
+                )
+            }
+            CertAuthEvent::RepoMirrorRemoved { name, .. } => {
+                write!(f, "removed repository mirror '{}'", name)
+            }
+            CertAuthEvent::RepoMigrationStarted { contact } => {
+                write!(
+                    f,
+                    "started migration to repository at remote server: {}",
+                    contact.server_info().service_uri()
+                )
+            }
+            CertAuthEvent::RepoMigrationVerified { objects } => {
+                write!(f, "verified {} objects in the new repository", objects)
+            }
+            CertAuthEvent::RepoMigrationWithdrawing => {
+                write!(f, "withdrawing objects from the old repository")
+            }
+            CertAuthEvent::RepoMigrationFinished => {
+                write!(f, "finished repository migration")
+            }
+            CertAuthEvent::RepoMigrationAborted { contact } => {
+                write!(
+                    f,
+                    "aborted migration to repository at remote server: {}",
+                    contact.server_info().service_uri()
+                )
+            }
 
             // Rta
             CertAuthEvent::RtaPrepared { name, prepared } => {
@@ -1110,6 +1336,16 @@ impl fmt::Display for CertAuthEvent {
             CertAuthEvent::RtaSigned { name, rta } => {
                 write!(f, "Signed RTA '{}' for resources: {}", name, rta.resources())
             }
+
+            // Rsc
+            CertAuthEvent::RscSigned { name, rsc } => {
+                write!(f, "Signed RSC '{}' for resources: {}", name, rsc.resources())
+            }
+
+            // Geofeed
+            CertAuthEvent::GeofeedSigned { geofeed } => {
+                write!(f, "Signed geofeed {} for prefixes: {}", geofeed.number(), Geofeed::prefixes(geofeed.resources()))
+            }
         }
     }
 }
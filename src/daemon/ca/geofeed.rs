@@ -0,0 +1,405 @@
+//! Support for RPKI signed geofeeds.
+//!
+//! See: https://www.rfc-editor.org/rfc/rfc9632.html
+use std::fmt;
+
+use bcder::Oid;
+use rpki::{
+    ca::{idexchange::CaHandle, publication::Base64},
+    repository::{resources::ResourceSet, x509::Validity, Cert},
+};
+
+use crate::{
+    commons::{
+        api::{GeofeedList, GeofeedSummary, Revocation, Timestamp},
+        crypto::{KrillSigner, SignSupport},
+        error::Error,
+        KrillResult,
+    },
+    daemon::ca::CertifiedKey,
+};
+
+/// The content type for signed geofeeds: 1.2.840.113549.1.9.16.1.47
+const CT_GEOFEED_CSV_WITH_CRLF: Oid<&[u8]> =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 47]);
+
+/// The comment which starts the signature block in a signed geofeed.
+const SIGNATURE_START: &str = "# RPKI Signature:";
+
+/// The comment which ends the signature block in a signed geofeed.
+const SIGNATURE_END: &str = "# End Signature:";
+
+/// The maximum length of the base64 encoded signature on a single line.
+const SIGNATURE_LINE_LEN: usize = 72;
+
+//------------ Geofeeds -----------------------------------------------------
+
+/// The history of geofeeds signed by a CA, oldest first.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Geofeeds {
+    history: Vec<SignedGeofeed>,
+}
+
+impl Geofeeds {
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+
+    pub fn next_number(&self) -> u64 {
+        self.history.last().map(|last| last.number + 1).unwrap_or(1)
+    }
+
+    pub fn list(&self) -> GeofeedList {
+        GeofeedList::new(
+            self.history.iter().map(SignedGeofeed::summary).collect(),
+        )
+    }
+
+    pub fn signed_geofeed(&self, number: u64) -> KrillResult<SignedGeofeed> {
+        self.history
+            .iter()
+            .find(|signed| signed.number == number)
+            .cloned()
+            .ok_or(Error::GeofeedUnknown(number))
+    }
+
+    pub fn latest(&self) -> Option<&SignedGeofeed> {
+        self.history.last()
+    }
+
+    pub fn add(&mut self, signed: SignedGeofeed) {
+        self.history.push(signed);
+    }
+}
+
+//------------ Geofeed ------------------------------------------------------
+
+/// The lines of a geofeed file (RFC 8805), and the prefix of each entry.
+///
+/// Comments and empty lines are kept as they are, so that they can be
+/// included in the signed geofeed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Geofeed {
+    lines: Vec<GeofeedLine>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct GeofeedLine {
+    line: String,
+    prefix: Option<ResourceSet>,
+}
+
+impl Geofeed {
+    /// Parses a geofeed file. Any existing signature is removed, as per
+    /// RFC 9632 it has to be replaced when the geofeed is signed again.
+    pub fn parse(text: &str) -> KrillResult<Self> {
+        let mut lines = vec![];
+
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.starts_with(SIGNATURE_START) {
+                break;
+            }
+
+            let prefix = if line.trim().is_empty() || line.starts_with('#') {
+                None
+            } else {
+                let prefix = line.split(',').next().unwrap_or("").trim();
+                Some(Self::parse_prefix(prefix).ok_or_else(|| {
+                    Error::GeofeedInvalid(format!(
+                        "invalid prefix '{}' in line: {}",
+                        prefix, line
+                    ))
+                })?)
+            };
+
+            lines.push(GeofeedLine {
+                line: line.to_string(),
+                prefix,
+            });
+        }
+
+        while lines.last().map(|l| l.line.is_empty()).unwrap_or(false) {
+            lines.pop();
+        }
+
+        let geofeed = Geofeed { lines };
+        if geofeed.is_empty() {
+            Err(Error::GeofeedInvalid(
+                "the geofeed contains no entries".to_string(),
+            ))
+        } else {
+            Ok(geofeed)
+        }
+    }
+
+    fn parse_prefix(prefix: &str) -> Option<ResourceSet> {
+        let resources = if prefix.contains(':') {
+            ResourceSet::from_strs("", "", prefix)
+        } else {
+            ResourceSet::from_strs("", prefix, "")
+        };
+        resources.ok().filter(|resources| !resources.is_empty())
+    }
+
+    /// Returns true if this geofeed has no entries, i.e. only comments.
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.prefix.is_none())
+    }
+
+    /// Returns all prefixes used in the entries of this geofeed.
+    pub fn resources(&self) -> ResourceSet {
+        let mut resources = ResourceSet::default();
+        for prefix in self.lines.iter().filter_map(|l| l.prefix.as_ref()) {
+            resources = resources.union(prefix);
+        }
+        resources
+    }
+
+    /// Returns the prefixes of entries which are not held in the given
+    /// resources.
+    pub fn not_held(&self, held: &ResourceSet) -> ResourceSet {
+        let mut not_held = ResourceSet::default();
+        for prefix in self.lines.iter().filter_map(|l| l.prefix.as_ref()) {
+            if !held.contains(prefix) {
+                not_held = not_held.union(prefix);
+            }
+        }
+        not_held
+    }
+
+    /// Takes the entries for prefixes which are held in the given
+    /// resources out of this geofeed, and returns them as a new geofeed.
+    /// Comments are kept in both geofeeds.
+    pub fn take_held(&mut self, held: &ResourceSet) -> Geofeed {
+        let (taken, kept) = self.lines.drain(..).fold(
+            (vec![], vec![]),
+            |(mut taken, mut kept), line| {
+                match &line.prefix {
+                    None => {
+                        taken.push(line.clone());
+                        kept.push(line);
+                    }
+                    Some(prefix) if held.contains(prefix) => taken.push(line),
+                    Some(_) => kept.push(line),
+                }
+                (taken, kept)
+            },
+        );
+        self.lines = kept;
+        Geofeed { lines: taken }
+    }
+
+    /// Returns the geofeed in its canonical form for signing, i.e. with
+    /// every line ending in CRLF.
+    fn canonical(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for line in &self.lines {
+            bytes.extend_from_slice(line.line.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+        bytes
+    }
+
+    /// Signs this geofeed with a one-off EE certificate issued under the
+    /// given key, and returns the geofeed with its signature appended.
+    ///
+    /// The EE certificate has exactly the prefixes used in the geofeed.
+    pub fn sign(
+        &self,
+        validity: Validity,
+        signing_key: &CertifiedKey,
+        signer: &KrillSigner,
+    ) -> KrillResult<(String, Cert)> {
+        let resources = self.resources();
+        let (bytes, ee) = SignSupport::sign_one_off_cms(
+            CT_GEOFEED_CSV_WITH_CRLF,
+            &self.canonical(),
+            true,
+            &resources,
+            validity,
+            signing_key,
+            signer,
+        )?;
+
+        let ranges = Self::prefixes(&resources);
+        let signature = Base64::from_content(bytes.as_ref()).to_string();
+
+        let mut text = self.to_string();
+        text.push_str(&format!("{} {}\n", SIGNATURE_START, ranges));
+        for chunk in signature.as_bytes().chunks(SIGNATURE_LINE_LEN) {
+            // Base64 is plain ASCII, so chunks are valid UTF-8.
+            text.push_str("# ");
+            text.push_str(&String::from_utf8_lossy(chunk));
+            text.push('\n');
+        }
+        text.push_str(&format!("{} {}\n", SIGNATURE_END, ranges));
+
+        Ok((text, ee))
+    }
+
+    /// Returns the IP prefixes in the resources as a comma separated list.
+    pub fn prefixes(resources: &ResourceSet) -> String {
+        let ipv4 = resources.ipv4();
+        let ipv6 = resources.ipv6();
+        if ipv4.is_empty() {
+            ipv6.to_string()
+        } else if ipv6.is_empty() {
+            ipv4.to_string()
+        } else {
+            format!("{}, {}", ipv4, ipv6)
+        }
+    }
+}
+
+impl fmt::Display for Geofeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line.line)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ GeofeedSignRequest -------------------------------------------
+
+/// A request to sign a geofeed.
+///
+/// All prefixes in the geofeed must be held by the CA that signs it, or
+/// by any of its co-signers. RFC 9632 allows only one signature per
+/// geofeed file, so if co-signers are used then every CA signs its own
+/// file with the entries for the prefixes it holds.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GeofeedSignRequest {
+    geofeed: String,
+    validity: Validity,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    cosigners: Vec<CaHandle>,
+}
+
+impl GeofeedSignRequest {
+    pub fn new(
+        geofeed: String,
+        validity: Validity,
+        cosigners: Vec<CaHandle>,
+    ) -> Self {
+        GeofeedSignRequest {
+            geofeed,
+            validity,
+            cosigners,
+        }
+    }
+
+    pub fn geofeed(&self) -> &str {
+        &self.geofeed
+    }
+
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    pub fn cosigners(&self) -> &Vec<CaHandle> {
+        &self.cosigners
+    }
+}
+
+//------------ SignedGeofeed ------------------------------------------------
+
+/// A geofeed signed by a CA, including its signature.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedGeofeed {
+    number: u64,
+    time: Timestamp,
+    resources: ResourceSet,
+    revocation: Revocation,
+    geofeed: String,
+}
+
+impl SignedGeofeed {
+    pub fn new(
+        number: u64,
+        resources: ResourceSet,
+        revocation: Revocation,
+        geofeed: String,
+    ) -> Self {
+        SignedGeofeed {
+            number,
+            time: Timestamp::now(),
+            resources,
+            revocation,
+            geofeed,
+        }
+    }
+
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    /// Returns the geofeed file with its signature.
+    pub fn geofeed(&self) -> &str {
+        &self.geofeed
+    }
+
+    pub fn summary(&self) -> GeofeedSummary {
+        GeofeedSummary::new(self.number, self.time, self.resources.clone())
+    }
+}
+
+impl fmt::Display for SignedGeofeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.geofeed)
+    }
+}
+
+//------------ SignedGeofeeds -----------------------------------------------
+
+/// The geofeeds signed for a single request, by the CA that signed each.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedGeofeeds(Vec<CaSignedGeofeed>);
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CaSignedGeofeed {
+    ca: CaHandle,
+    signed: SignedGeofeed,
+}
+
+impl SignedGeofeeds {
+    pub fn new(signed: Vec<(CaHandle, SignedGeofeed)>) -> Self {
+        SignedGeofeeds(
+            signed
+                .into_iter()
+                .map(|(ca, signed)| CaSignedGeofeed { ca, signed })
+                .collect(),
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&CaHandle, &SignedGeofeed)> {
+        self.0.iter().map(|signed| (&signed.ca, &signed.signed))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for SignedGeofeeds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let [single] = self.0.as_slice() {
+            write!(f, "{}", single.signed)
+        } else {
+            for signed in &self.0 {
+                writeln!(f, "Geofeed signed by CA '{}':", signed.ca)?;
+                writeln!(f, "{}", signed.signed)?;
+            }
+            Ok(())
+        }
+    }
+}
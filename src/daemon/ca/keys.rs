@@ -98,6 +98,7 @@ impl CertifiedKey {
         rcn: &ResourceClassName,
         new_resources: &ResourceSet,
         new_not_after: Time,
+        parent_key: KeyIdentifier,
     ) -> bool {
         // If we did not have a trailing slash for the id-ad-caRepository,
         // then we should make a new CSR which will include it. See
@@ -106,6 +107,22 @@ impl CertifiedKey {
             return true;
         }
 
+        // If the parent signs with another key now, e.g. after a key roll
+        // of a trust anchor, then we need a certificate issued under that
+        // key.
+        let issuer_key = self
+            .incoming_cert
+            .to_cert()
+            .ok()
+            .and_then(|cert| cert.authority_key_identifier());
+        if issuer_key.is_some_and(|issuer_key| issuer_key != parent_key) {
+            info!(
+                "Will request new certificate for CA '{}' under RC '{}'. The parent signs with a new key: {}",
+                handle, rcn, parent_key
+            );
+            return true;
+        }
+
         // If resources have changed, then we need to request a new
         // certificate.
         let resources_diff =
@@ -383,6 +400,8 @@ impl KeyState {
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         let mut keys_for_requests = vec![];
+        let parent_key =
+            entitlement.signing_cert().cert().subject_key_identifier();
 
         match self {
             KeyState::Pending(pending) => {
@@ -394,6 +413,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = current.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, current.key_id()));
@@ -406,6 +426,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = current.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, current.key_id()));
@@ -417,6 +438,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = new.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, new.key_id()));
@@ -426,6 +448,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = current.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, current.key_id()));
@@ -437,6 +460,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = current.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, current.key_id()));
@@ -446,6 +470,7 @@ impl KeyState {
                     &rcn,
                     entitlement.resource_set(),
                     entitlement.not_after(),
+                    parent_key,
                 ) {
                     let repo = old.old_repo.as_ref().unwrap_or(base_repo);
                     keys_for_requests.push((repo, current.key_id()));
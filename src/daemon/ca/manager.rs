@@ -1,4 +1,9 @@
-use std::{collections::HashMap, ops::Deref, str::FromStr, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use bytes::Bytes;
 use chrono::Duration;
@@ -6,7 +11,7 @@ use chrono::Duration;
 use rpki::{
     ca::{
         idexchange::{self, ServiceUri},
-        idexchange::{CaHandle, ChildHandle, ParentHandle},
+        idexchange::{CaHandle, ChildHandle, ParentHandle, PublisherHandle},
         provisioning,
         provisioning::{
             IssuanceRequest, ProvisioningCms, ResourceClassListResponse,
@@ -17,6 +22,7 @@ use rpki::{
     },
     crypto::KeyIdentifier,
     repository::resources::ResourceSet,
+    rrdp::{Hash, NotificationFile, Snapshot},
     uri,
 };
 
@@ -26,21 +32,27 @@ use crate::{
         api::{
             import::{ExportChild, ImportChild},
             rrdp::PublishElement,
-            BgpSecCsrInfoList, BgpSecDefinitionUpdates, IdCertInfo,
-            ParentServerInfo, PublicationServerInfo, RoaConfigurationUpdates,
-            Timestamp,
+            BgpSecCsrInfoList, BgpSecDefinitionUpdates, CaIssuanceTiming,
+            CertAuthRename, CertAuthStorableCommand, EmergencyRekeyReport,
+            IdCertInfo, KeyRollPhase, KeyRollPolicy, ParentServerInfo,
+            PublicationServerInfo, RepoMigration, RepoMigrationPhase,
+            RepositoryMirrorName, ResourcePoolDefinition, ResourcePoolList,
+            ResourcePoolName, RoaConfigurationUpdates, Timestamp,
         },
         api::{
             AddChildRequest, AspaDefinitionList, AspaDefinitionUpdates,
-            AspaProvidersUpdate, CaCommandDetails, CertAuthList,
+            AspaProvidersUpdate, CaCommandDetails, CaTemplate,
+            CaTemplateReport, CertAuthFromTemplate, CertAuthList,
             CertAuthSummary, ChildCaInfo, CommandHistory,
-            CommandHistoryCriteria, CustomerAsn, ParentCaContact,
-            ParentCaReq, ReceivedCert, RepositoryContact, RtaName,
+            CommandHistoryCriteria, CustomerAsn, HistoryCompactionReport,
+            HistoryCompactionReports, HistoryPoint, HistoryRetention,
+            ParentCaContact, ParentCaReq, ReceivedCert, RepositoryContact,
+            RscName, RtaName, TemplateParent, TemplateRepository,
             UpdateChildRequest,
         },
         crypto::KrillSigner,
         error::Error,
-        eventsourcing::{Aggregate, AggregateStore},
+        eventsourcing::{Aggregate, AggregateStore, AggregateStoreError},
         util::{cmslogger::CmsLogger, httpclient},
         KrillResult,
     },
@@ -52,19 +64,21 @@ use crate::{
         auth::Handle,
         ca::{
             CaObjectsStore, CaStatus, CertAuth, CertAuthCommand,
-            CertAuthCommandDetails, DeprecatedRepository,
-            ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
-            StatusStore,
+            CertAuthCommandDetails, DeprecatedRepository, Geofeed,
+            GeofeedSignRequest, PendingRename, RepoStatusTarget,
+            ResourceTaggedAttestation, RscSignRequest, RtaContentRequest,
+            RtaPrepareRequest, SignedGeofeeds, StatusStore,
         },
         config::Config,
+        eventstream::EventStream,
         mq::{now, Task, TaskQueue},
     },
     pubd::RepositoryManager,
     ta::{
-        self, ta_handle, TrustAnchorProxy, TrustAnchorProxyCommand,
-        TrustAnchorProxyInitCommand, TrustAnchorSignedRequest,
-        TrustAnchorSignedResponse, TrustAnchorSigner,
-        TrustAnchorSignerCommand, TrustAnchorSignerInfo,
+        self, ta_handle, TaKeyRollRequest, TaSuccessor, TrustAnchorProxy,
+        TrustAnchorProxyCommand, TrustAnchorProxyInitCommand,
+        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
+        TrustAnchorSigner, TrustAnchorSignerCommand, TrustAnchorSignerInfo,
         TrustAnchorSignerInitCommand, TrustAnchorSignerInitCommandDetails,
         TA_NAME,
     },
@@ -109,6 +123,61 @@ pub struct CaManager {
     // System actor is used for (scheduled or triggered) system actions where
     // we have no operator actor context.
     system_actor: Actor,
+
+    // The RRDP snapshots last retrieved from repositories that CAs are
+    // migrating to, by notification URI.
+    migration_snapshots: Mutex<HashMap<uri::Https, MigrationSnapshot>>,
+}
+
+/// The objects found in the RRDP snapshot of a repository that a CA is
+/// migrating to, so that the snapshot is only retrieved again when the
+/// repository has a new session or serial.
+#[derive(Clone, Debug)]
+struct MigrationSnapshot {
+    session_id: uuid::Uuid,
+    serial: u64,
+    hashes: HashMap<String, Hash>,
+}
+
+impl MigrationSnapshot {
+    /// Parses the snapshot for the given notification file. The snapshot
+    /// must match the hash, session and serial from the notification.
+    fn parse(
+        notification: &NotificationFile,
+        snapshot: &[u8],
+    ) -> Result<Self, String> {
+        if !notification.snapshot().hash().matches(snapshot) {
+            return Err(format!(
+                "snapshot at {} does not match the hash in the notification file",
+                notification.snapshot().uri()
+            ));
+        }
+        let snapshot =
+            Snapshot::parse(snapshot).map_err(|e| e.to_string())?;
+        if snapshot.session_id() != notification.session_id()
+            || snapshot.serial() != notification.serial()
+        {
+            return Err(format!(
+                "snapshot at {} is not for the session and serial in the notification file",
+                notification.snapshot().uri()
+            ));
+        }
+
+        Ok(MigrationSnapshot {
+            session_id: snapshot.session_id(),
+            serial: snapshot.serial(),
+            hashes: snapshot
+                .elements()
+                .iter()
+                .map(|el| (el.uri().to_string(), Hash::from_data(el.data())))
+                .collect(),
+        })
+    }
+
+    fn is_current(&self, notification: &NotificationFile) -> bool {
+        self.session_id == notification.session_id()
+            && self.serial == notification.serial()
+    }
 }
 
 impl CaManager {
@@ -117,6 +186,7 @@ impl CaManager {
     pub async fn build(
         config: Arc<Config>,
         tasks: Arc<TaskQueue>,
+        events: Arc<EventStream>,
         signer: Arc<KrillSigner>,
         system_actor: Actor,
     ) -> KrillResult<Self> {
@@ -189,6 +259,10 @@ impl CaManager {
         // RFC 6492).
         ca_store.add_post_save_listener(tasks.clone());
 
+        // Publish all stored events to the `EventStream`, so that they can
+        // be followed through the API.
+        ca_store.add_post_save_listener(events.clone());
+
         // Create TA proxy store if we need it.
         let ta_proxy_store = if config.ta_proxy_enabled() {
             let mut store = AggregateStore::<TrustAnchorProxy>::create(
@@ -207,6 +281,7 @@ impl CaManager {
             // - re-sync for local children when the proxy has new responses
             //   AND is saved
             store.add_post_save_listener(tasks.clone());
+            store.add_post_save_listener(events);
 
             Some(store)
         } else {
@@ -239,9 +314,29 @@ impl CaManager {
             config,
             signer,
             system_actor,
+            migration_snapshots: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Drops all cached CA state, so that it is loaded again from storage.
+    ///
+    /// With leader election, other nodes may have changed the storage
+    /// while this node was on standby. This must be done before this node
+    /// acts as the leader.
+    pub fn reload(&self) -> KrillResult<()> {
+        self.ca_store.clear_cache();
+        if let Some(store) = &self.ta_proxy_store {
+            store.clear_cache();
+        }
+        if let Some(store) = &self.ta_signer_store {
+            store.clear_cache();
+        }
+        if let Some(mapper) = self.signer.get_mapper() {
+            mapper.clear_cache();
+        }
+        self.status_store.reload()
+    }
+
     pub fn testbed_enabled(&self) -> bool {
         self.config.testbed().is_some()
     }
@@ -262,7 +357,13 @@ impl CaManager {
     ) -> KrillResult<Vec<CaHandle>> {
         let mut res = vec![];
         for ca in self.ca_store.list()? {
-            match self.ca_objects_store.reissue_if_needed(force, &ca) {
+            let reissued = match self.get_ca(&ca).await {
+                Ok(cert_auth) => {
+                    self.ca_objects_store.reissue_if_needed(force, &cert_auth)
+                }
+                Err(e) => Err(e),
+            };
+            match reissued {
                 Err(e) => {
                     error!("Could not reissue manifest and crl for {ca}. Error: {e}");
                 }
@@ -478,6 +579,35 @@ impl CaManager {
         proxy.get_signer_request(self.config.ta_timing, &self.signer)
     }
 
+    /// Create a new key roll request for the signer (RFC 9691).
+    ///
+    /// Errors if:
+    /// - there is no proxy, or signer
+    /// - the proxy already has a request
+    /// - the key roll cannot be initiated, or activated, at this time
+    pub async fn ta_proxy_key_roll_request(
+        &self,
+        request: TaKeyRollRequest,
+        actor: &Actor,
+    ) -> KrillResult<TrustAnchorSignedRequest> {
+        let cmd = TrustAnchorProxyCommand::make_signer_key_roll_request(
+            &ta_handle(),
+            request,
+            actor,
+        );
+        let proxy = self.send_ta_proxy_command(cmd).await?;
+
+        proxy.get_signer_request(self.config.ta_timing, &self.signer)
+    }
+
+    /// Returns the successor TA key, if a key roll is in progress.
+    pub async fn ta_proxy_key_roll_successor(
+        &self,
+    ) -> KrillResult<Option<TaSuccessor>> {
+        let proxy = self.get_trust_anchor_proxy().await?;
+        Ok(proxy.get_successor()?.cloned())
+    }
+
     /// Create a new request for the signer.
     ///
     /// Errors if:
@@ -575,6 +705,8 @@ impl CaManager {
             Err(Error::TaNameReserved)
         } else if self.ca_store.has(handle)? {
             Err(Error::CaDuplicate(handle.clone()))
+        } else if let Some(ca) = self.ca_alias_owner(handle)? {
+            Err(Error::CaAliasInUse(handle.clone(), ca))
         } else {
             // Initialize the CA in self.ca_store, but note that there is no
             // need to create a new CA entry in
@@ -642,6 +774,29 @@ impl CaManager {
             .map_err(|_| Error::CaUnknown(handle.clone()))
     }
 
+    /// Gets a CA as it was at the given point in its history. The CA is
+    /// rebuilt from its stored commands, its current state is not affected.
+    pub fn get_ca_at(
+        &self,
+        handle: &CaHandle,
+        point: HistoryPoint,
+    ) -> KrillResult<CertAuth> {
+        match point {
+            HistoryPoint::Version(version) => {
+                self.ca_store.get_at_version(handle, version)
+            }
+            HistoryPoint::Time(time) => {
+                self.ca_store.get_at_time(handle, time)
+            }
+        }
+        .map_err(|e| match e {
+            Error::AggregateStoreError(
+                AggregateStoreError::UnknownAggregate(_),
+            ) => Error::CaUnknown(handle.clone()),
+            e => e,
+        })
+    }
+
     /// Checks whether a CA by the given handle exists.
     pub fn has_ca(&self, handle: &CaHandle) -> KrillResult<bool> {
         self.ca_store
@@ -704,6 +859,8 @@ impl CaManager {
             repos.push(deprecated.into());
         }
 
+        repos.extend(ca.repository_mirrors().values().cloned());
+
         for repo_contact in repos {
             if self
                 .ca_repo_sync(
@@ -711,6 +868,7 @@ impl CaManager {
                     ca_handle,
                     ca.id_cert(),
                     &repo_contact,
+                    RepoStatusTarget::Untracked,
                     vec![],
                 )
                 .await
@@ -730,137 +888,761 @@ impl CaManager {
     }
 }
 
-/// # CA History
+/// # Rename CAs
 impl CaManager {
-    /// Gets the history for a CA.
-    pub async fn ca_history(
+    /// Renames a CA.
+    ///
+    /// The rename is first recorded in the CA, and then finished as
+    /// described for `finish_rename`. If an earlier rename of the
+    /// CA to the same handle was interrupted, then it is resumed.
+    pub async fn ca_rename(
         &self,
-        handle: &CaHandle,
-        crit: CommandHistoryCriteria,
-    ) -> KrillResult<CommandHistory> {
-        Ok(self.ca_store.command_history(handle, crit)?)
-    }
+        repo_manager: &RepositoryManager,
+        ca_handle: &CaHandle,
+        rename: CertAuthRename,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let (new_handle, alias_days) = rename.unpack();
 
-    /// Shows the details for a CA command.
-    pub fn ca_command_details(
-        &self,
-        handle: &CaHandle,
-        version: u64,
-    ) -> KrillResult<CaCommandDetails> {
-        self.ca_store
-            .get_command(handle, version)
-            .map_err(Error::AggregateStoreError)
-    }
-}
+        let not_possible = |reason: String| {
+            Err(Error::CaRenameNotPossible(ca_handle.clone(), reason))
+        };
 
-/// # CAs as parents
-impl CaManager {
-    /// Adds a child under a CA. If the `AddChildRequest` contains resources
-    /// not held by this CA, then an `Error::CaChildExtraResources` is
-    /// returned.
-    pub async fn ca_add_child(
-        &self,
-        ca: &CaHandle,
-        req: AddChildRequest,
-        service_uri: &uri::Https,
-        actor: &Actor,
-    ) -> KrillResult<idexchange::ParentResponse> {
-        info!("CA '{}' process add child request: {}", &ca, &req);
-        if ca.as_str() != TA_NAME {
-            let (child_handle, child_res, id_cert) = req.unpack();
+        // Resume the rename if it was interrupted. The CA may have been
+        // moved to its new handle already.
+        let ca = match self.ca_store.has(ca_handle)? {
+            true => self.get_ca(ca_handle).await?,
+            false => self
+                .get_ca(&new_handle)
+                .await
+                .map_err(|_| Error::CaUnknown(ca_handle.clone()))?,
+        };
+        if let Some(pending) = ca.pending_rename() {
+            if pending.from() != ca_handle {
+                return Err(Error::CaUnknown(ca_handle.clone()));
+            } else if pending.to() != &new_handle {
+                return not_possible(format!(
+                    "it is being renamed to '{}'",
+                    pending.to()
+                ));
+            }
+            warn!(
+                "Resuming rename of CA {} as requested by: {}",
+                pending, actor
+            );
+            return self.finish_rename(repo_manager, pending, actor).await;
+        } else if ca.handle() != ca_handle {
+            return Err(Error::CaUnknown(ca_handle.clone()));
+        }
 
-            let add_child = CertAuthCommandDetails::child_add(
-                ca,
-                child_handle.clone(),
-                id_cert.into(),
-                child_res,
-                actor,
+        if new_handle == ta_handle() || new_handle.as_str() == "version" {
+            return Err(Error::TaNameReserved);
+        } else if self.ca_store.has(&new_handle)? {
+            return Err(Error::CaDuplicate(new_handle));
+        } else if let Some(owner) = self.ca_alias_owner(&new_handle)? {
+            // A CA may be renamed back to one of its own aliases.
+            if &owner != ca_handle {
+                return Err(Error::CaAliasInUse(new_handle, owner));
+            }
+        }
+        ca.rename_check()?;
+
+        let service_uri = self.config.service_uri();
+        for parent in ca.parents() {
+            let server_info = ca.parent(parent)?.parent_server_info();
+            if Self::local_parent(server_info.service_uri(), &service_uri)
+                .is_some_and(|local| local.as_str() == TA_NAME)
+            {
+                return not_possible(
+                    "its parent is the trust anchor".to_string(),
+                );
+            }
+        }
+
+        // Check whether the CA publishes in the embedded repository.
+        let embedded = Self::embedded_repo_service_uri(
+            repo_manager,
+            &ca_handle.convert(),
+        )?;
+        let publishes_embedded = |contact: &RepositoryContact| {
+            Some(contact.server_info().service_uri()) == embedded.as_ref()
+        };
+        if ca.repository_mirrors().values().any(publishes_embedded) {
+            return not_possible(
+                "it uses the embedded repository as mirror".to_string(),
             );
-            self.send_ca_command(add_child).await?;
-            self.ca_parent_response(ca, child_handle, service_uri).await
-        } else {
-            let child_handle = req.handle().clone();
-            let add_child_cmd =
-                TrustAnchorProxyCommand::add_child(ca, req, actor);
-            self.send_ta_proxy_command(add_child_cmd).await?;
-            self.ca_parent_response(ca, child_handle, service_uri).await
         }
-    }
+        let new_publisher = new_handle.convert();
+        if ca
+            .repository_contact()
+            .map(publishes_embedded)
+            .unwrap_or(false)
+            && repo_manager.publishers()?.contains(&new_publisher)
+        {
+            return Err(Error::PublisherDuplicate(new_publisher));
+        }
 
-    /// Show details for a child under the CA.
-    pub async fn ca_show_child(
-        &self,
-        ca: &CaHandle,
-        child: &ChildHandle,
-    ) -> KrillResult<ChildCaInfo> {
-        trace!("Finding details for CA: {} under parent: {}", child, ca);
-        let ca = self.get_ca(ca).await?;
-        ca.get_child(child).map(|details| details.clone().into())
+        warn!(
+            "Renaming CA '{}' to '{}' as requested by: {}",
+            ca_handle, new_handle, actor
+        );
+
+        let alias_until = alias_days
+            .map(|days| Timestamp::now_plus_hours(i64::from(days) * 24));
+        let ca = self
+            .send_ca_command(CertAuthCommandDetails::rename_start(
+                ca_handle,
+                new_handle,
+                alias_until,
+                actor,
+            ))
+            .await?;
+        match ca.pending_rename() {
+            Some(pending) => {
+                self.finish_rename(repo_manager, pending, actor).await
+            }
+            None => Err(Error::custom("CA rename was not started")),
+        }
     }
 
-    /// Export a child. Fails if:
-    /// - the child does not exist
-    /// - the child has no received certificate
-    /// - the child has more than one received certificate or resource class
-    ///
-    /// Primarily meant for testing that the child import function works.
-    pub async fn ca_child_export(
+    /// Finishes all CA renames which were interrupted, e.g. because Krill
+    /// was stopped.
+    pub async fn resume_ca_renames(
         &self,
-        ca: &CaHandle,
-        child_handle: &ChildHandle,
-    ) -> KrillResult<ExportChild> {
-        trace!("Exporting CA: {} under parent: {}", child_handle, ca);
-        self.get_ca(ca).await?.child_export(child_handle)
+        repo_manager: &RepositoryManager,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        for handle in self.ca_store.list()? {
+            let ca = self.get_ca(&handle).await?;
+            if let Some(pending) = ca.pending_rename() {
+                warn!("Resuming rename of CA {}", pending);
+                self.finish_rename(repo_manager, pending, actor).await?;
+            }
+        }
+        Ok(())
     }
 
-    /// Import a child under the given CA. Will fail if:
-    /// - the ca does not exist
-    /// - the ca has less than, or more than one resource class
-    /// - the ca does not hold the resources for the child
-    /// - the child already exists
-    pub async fn ca_child_import(
+    /// Moves the data of a CA to its new handle, updates local parents
+    /// and children, and then records that the rename is finished.
+    ///
+    /// The stored history, objects and status of the CA are moved to the
+    /// new handle, and so are its queued tasks. Its keys are kept: the
+    /// signer only refers to them by key identifier. If the CA publishes
+    /// in the embedded repository, then its publisher is renamed as well,
+    /// but it keeps its base URI so that no objects need to be re-issued.
+    /// Remote parents and children are not affected by the rename, except
+    /// that remote children need a new parent response, unless the old
+    /// handle is kept as an alias.
+    ///
+    /// Every step can be repeated, and the CA keeps the pending rename
+    /// until all steps are done. So, if this fails or Krill is stopped,
+    /// then the rename is resumed by calling this again.
+    async fn finish_rename(
         &self,
-        ca: &CaHandle,
-        import_child: ImportChild,
+        repo_manager: &RepositoryManager,
+        rename: &PendingRename,
         actor: &Actor,
     ) -> KrillResult<()> {
-        trace!("Importing CA: {} under parent: {}", import_child.name, ca);
-        self.send_ca_command(CertAuthCommandDetails::child_import(
-            ca,
-            import_child,
-            self.config.clone(),
-            self.signer.clone(),
+        let (from, to) = (rename.from(), rename.to());
+        let ca = match self.ca_store.has(from)? {
+            true => self.get_ca(from).await?,
+            false => self.get_ca(to).await?,
+        };
+
+        // Find the local parents which still know this CA by its old
+        // handle.
+        let service_uri = self.config.service_uri();
+        let mut local_parents = vec![];
+        for parent in ca.parents() {
+            let server_info = ca.parent(parent)?.parent_server_info();
+            if let Some(local) =
+                Self::local_parent(server_info.service_uri(), &service_uri)
+            {
+                if server_info.child_handle().as_str() == from.as_str() {
+                    local_parents.push((parent.clone(), local.convert()));
+                }
+            }
+        }
+
+        // The CA keeps its contact for the embedded repository under the
+        // old publisher name until the rename is finished.
+        let old_publisher = from.convert();
+        let new_publisher = to.convert();
+        let embedded =
+            ServiceUri::Https(self.config.rfc8181_uri(&old_publisher));
+        let rename_publisher = repo_manager.initialized()?
+            && ca
+                .repository_contact()
+                .map(|contact| {
+                    contact.server_info().service_uri() == &embedded
+                })
+                .unwrap_or(false);
+        let repository = if rename_publisher {
+            repo_manager.rename_publisher(
+                old_publisher,
+                new_publisher.clone(),
+                actor,
+            )?;
+            let response =
+                repo_manager.repository_response(&new_publisher)?;
+            Some(RepositoryContact::for_response(response)?)
+        } else {
+            None
+        };
+
+        self.ca_objects_store.rename_ca(from, to)?;
+        self.status_store.rename_ca(from, to)?;
+        self.tasks.rename_ca(from, to)?;
+        self.ca_store.rename_aggregate(from, to)?;
+
+        // Local parents need to know the CA by its new handle, and the CA
+        // needs the updated parent responses.
+        for (parent, parent_ca) in local_parents {
+            let old_child = from.convert();
+            let new_child = to.convert();
+            let parent_ca_has_old_child =
+                self.get_ca(&parent_ca).await?.get_child(&old_child).is_ok();
+            if parent_ca_has_old_child {
+                self.status_store
+                    .rename_child(&parent_ca, &old_child, &new_child)?;
+                self.send_ca_command(CertAuthCommandDetails::child_rename(
+                    &parent_ca,
+                    old_child,
+                    new_child.clone(),
+                    actor,
+                ))
+                .await?;
+            }
+
+            let response = self
+                .ca_parent_response(&parent_ca, new_child, &service_uri)
+                .await?;
+            self.ca_parent_add_or_update(
+                to.clone(),
+                ParentCaReq::new(parent, response),
+                actor,
+            )
+            .await?;
+        }
+
+        // Local children need to contact the CA under its new handle.
+        for child_ca in self.ca_store.list()? {
+            let child = self.get_ca(&child_ca).await?;
+            for parent in child.parents() {
+                let server_info = child.parent(parent)?.parent_server_info();
+                let local_parent = Self::local_parent(
+                    server_info.service_uri(),
+                    &service_uri,
+                );
+                if local_parent.as_ref().map(|p| p.as_str())
+                    == Some(from.as_str())
+                {
+                    // Follow the rename if the child used the parent's
+                    // handle as its name for the parent.
+                    let parent = if parent.as_str() == from.as_str() {
+                        let new_name: ParentHandle = to.convert();
+                        self.status_store.remove_parent(&child_ca, parent)?;
+                        self.send_ca_command(
+                            CertAuthCommandDetails::rename_parent(
+                                &child_ca,
+                                parent.clone(),
+                                new_name.clone(),
+                                actor,
+                            ),
+                        )
+                        .await?;
+                        new_name
+                    } else {
+                        parent.clone()
+                    };
+
+                    let response = self
+                        .ca_parent_response(
+                            to,
+                            server_info.child_handle().clone(),
+                            &service_uri,
+                        )
+                        .await?;
+                    self.ca_parent_add_or_update(
+                        child_ca.clone(),
+                        ParentCaReq::new(parent, response),
+                        actor,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        self.send_ca_command(CertAuthCommandDetails::rename(
+            to,
+            from.clone(),
+            repository,
             actor,
         ))
         .await?;
+
         Ok(())
     }
 
-    /// Show a contact for a child.
-    pub async fn ca_parent_contact(
+    /// Returns the CA which uses the given handle as an alias, if any.
+    pub fn ca_alias_owner(
         &self,
-        ca_handle: &CaHandle,
-        child_handle: ChildHandle,
-        service_uri: &uri::Https,
-    ) -> KrillResult<ParentCaContact> {
-        let service_uri = Self::service_uri_for_ca(service_uri, ca_handle);
-        let ca = self.get_ca(ca_handle).await?;
+        alias: &CaHandle,
+    ) -> KrillResult<Option<CaHandle>> {
+        for handle in self.ca_store.list()? {
+            if self.ca_store.get_latest(&handle)?.has_alias(alias) {
+                return Ok(Some(handle));
+            }
+        }
+        Ok(None)
+    }
 
-        let server_info = ParentServerInfo::new(
-            service_uri,
-            ca_handle.convert(),
-            child_handle,
-            ca.id_cert().clone(),
-        );
-        Ok(ParentCaContact::for_parent_server_info(server_info))
+    /// Returns the handle of the CA which uses the given handle as an
+    /// alias, or the given handle itself if there is no such CA.
+    pub fn ca_resolve_alias(
+        &self,
+        handle: CaHandle,
+    ) -> KrillResult<CaHandle> {
+        if self.ca_store.has(&handle)? {
+            Ok(handle)
+        } else {
+            Ok(self.ca_alias_owner(&handle)?.unwrap_or(handle))
+        }
     }
 
-    /// Gets an RFC8183 Parent Response for the child.
-    pub async fn ca_parent_response(
+    /// Returns the service URI of the embedded repository for a publisher,
+    /// if the repository is initialized and has this publisher.
+    fn embedded_repo_service_uri(
+        repo_manager: &RepositoryManager,
+        publisher: &PublisherHandle,
+    ) -> KrillResult<Option<ServiceUri>> {
+        if repo_manager.initialized()? {
+            Ok(repo_manager
+                .repository_response(publisher)
+                .ok()
+                .map(|response| response.service_uri().clone()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// # CA templates
+impl CaManager {
+    /// Returns a template with the repository and parents of a CA, and its
+    /// ROA and ASPA definitions.
+    pub async fn ca_template(
         &self,
+        repo_manager: &RepositoryManager,
         ca_handle: &CaHandle,
-        child_handle: ChildHandle,
-        service_uri: &uri::Https,
+    ) -> KrillResult<CaTemplate> {
+        let ca = self.get_ca(ca_handle).await?;
+
+        let repository = match ca.repository_contact() {
+            Err(_) => None,
+            Ok(contact) => {
+                let service_uri = contact.server_info().service_uri();
+                let embedded = Self::embedded_repo_service_uri(
+                    repo_manager,
+                    &ca_handle.convert(),
+                )?;
+                if embedded.as_ref() == Some(service_uri) {
+                    Some(TemplateRepository::Embedded)
+                } else {
+                    Some(TemplateRepository::Remote {
+                        service_uri: service_uri.clone(),
+                    })
+                }
+            }
+        };
+
+        let mut parents = vec![];
+        for parent in ca.parents() {
+            let server_info = ca.parent(parent)?.parent_server_info();
+            let service_uri = server_info.service_uri().clone();
+            let local: Option<CaHandle> =
+                Self::local_parent(&service_uri, &self.config.service_uri())
+                    .map(|parent| parent.convert());
+            let resources = match &local {
+                Some(local) if local.as_str() == TA_NAME => {
+                    let proxy = self.get_trust_anchor_proxy().await?;
+                    proxy
+                        .get_child(server_info.child_handle())?
+                        .resources
+                        .clone()
+                }
+                Some(local) => self
+                    .ca_show_child(local, server_info.child_handle())
+                    .await?
+                    .entitled_resources()
+                    .clone(),
+                None => ResourceSet::empty(),
+            };
+            parents.push(TemplateParent {
+                name: parent.clone(),
+                service_uri,
+                local,
+                resources,
+            });
+        }
+
+        let roas = ca
+            .configured_roas()
+            .into_iter()
+            .map(|roa| roa.roa_configuration().clone())
+            .collect();
+        let aspas = ca.aspas_definitions_show().unpack();
+
+        Ok(CaTemplate {
+            repository,
+            parents,
+            roas,
+            aspas,
+        })
+    }
+
+    /// Creates a new CA using a template.
+    ///
+    /// The new CA gets its own ID key. If the template uses the embedded
+    /// repository, then the CA is added as a publisher there. The CA is
+    /// added as a child to all local parents in the template, and gets its
+    /// certificates from them right away. Remote parents and repositories
+    /// are left for the operator to set up, as they require an RFC 8183
+    /// exchange with another party. Finally, if asked, the ROA and ASPA
+    /// definitions in the template are copied if the CA holds the
+    /// resources for them.
+    pub async fn ca_init_from_template(
+        &self,
+        repo_manager: &RepositoryManager,
+        request: CertAuthFromTemplate,
+        actor: &Actor,
+    ) -> KrillResult<CaTemplateReport> {
+        let CertAuthFromTemplate {
+            handle,
+            template,
+            copy_definitions,
+            resources,
+        } = request;
+
+        // Verify the template before creating anything.
+        if template.repository == Some(TemplateRepository::Embedded)
+            && !repo_manager.initialized()?
+        {
+            return Err(Error::RepositoryServerNotInitialized);
+        }
+        for parent in &template.parents {
+            if let Some(local) = &parent.local {
+                if local.as_str() != TA_NAME && !self.has_ca(local)? {
+                    return Err(Error::CaUnknown(local.clone()));
+                }
+            }
+        }
+
+        info!("Creating CA '{}' from a template", handle);
+        self.init_ca(&handle)?;
+        let ca = self.get_ca(&handle).await?;
+        let mut report = CaTemplateReport::new(handle.clone());
+
+        if template.repository == Some(TemplateRepository::Embedded) {
+            repo_manager.create_publisher(ca.publisher_request(), actor)?;
+            let response =
+                repo_manager.repository_response(&handle.convert())?;
+            let contact = RepositoryContact::for_response(response)
+                .map_err(Error::rfc8183)?;
+            self.update_repo(
+                repo_manager,
+                handle.clone(),
+                contact,
+                false,
+                actor,
+            )
+            .await?;
+            report.repository_configured = true;
+        }
+
+        let service_uri = self.config.service_uri();
+        for parent in template.parents {
+            let entitled = match &resources {
+                Some(resources) => parent.resources.intersection(resources),
+                None => parent.resources,
+            };
+            let local = match parent.local {
+                Some(local) if !entitled.is_empty() => local,
+                _ => {
+                    report.parents_skipped.push(parent.name);
+                    continue;
+                }
+            };
+
+            let id_cert =
+                ca.child_request().validate().map_err(Error::rfc8183)?;
+            let child_req =
+                AddChildRequest::new(handle.convert(), entitled, id_cert);
+            let response = self
+                .ca_add_child(&local, child_req, &service_uri, actor)
+                .await?;
+            self.ca_parent_add_or_update(
+                handle.clone(),
+                ParentCaReq::new(parent.name.clone(), response),
+                actor,
+            )
+            .await?;
+
+            // Get the certificates now, so that the definitions can be
+            // copied. The first sync gets the entitlements and creates
+            // the CSRs, the second sync sends them. The trust anchor
+            // needs a push to sign them.
+            self.ca_sync_parent(&handle, 0, &parent.name, actor).await?;
+            self.ca_sync_parent(&handle, 0, &parent.name, actor).await?;
+            if local.as_str() == TA_NAME {
+                self.sync_ta_proxy_signer_if_possible().await?;
+                self.ca_sync_parent(&handle, 0, &parent.name, actor).await?;
+            }
+
+            report.parents_added.push(parent.name);
+        }
+
+        if copy_definitions {
+            let held = self.get_ca(&handle).await?.all_resources();
+
+            let mut roa_updates = RoaConfigurationUpdates::empty();
+            for roa in template.roas {
+                if held.contains(&roa.payload().prefix().into()) {
+                    roa_updates.add(roa.clone());
+                    report.roas_copied.push(roa);
+                } else {
+                    report.roas_skipped.push(roa);
+                }
+            }
+            if !roa_updates.is_empty() {
+                self.ca_routes_update(handle.clone(), roa_updates, actor)
+                    .await?;
+            }
+
+            let mut aspas = vec![];
+            for aspa in template.aspas {
+                if held.contains_asn(aspa.customer()) {
+                    report.aspas_copied.push(aspa.customer());
+                    aspas.push(aspa);
+                } else {
+                    report.aspas_skipped.push(aspa.customer());
+                }
+            }
+            if !aspas.is_empty() {
+                self.ca_aspas_definitions_update(
+                    handle.clone(),
+                    AspaDefinitionUpdates::new(aspas, vec![]),
+                    actor,
+                )
+                .await?;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// # CA History
+impl CaManager {
+    /// Gets the history for a CA.
+    pub async fn ca_history(
+        &self,
+        handle: &CaHandle,
+        crit: CommandHistoryCriteria,
+    ) -> KrillResult<CommandHistory> {
+        Ok(self.ca_store.command_history(handle, crit)?)
+    }
+
+    /// Shows the details for a CA command.
+    pub fn ca_command_details(
+        &self,
+        handle: &CaHandle,
+        version: u64,
+    ) -> KrillResult<CaCommandDetails> {
+        self.ca_store
+            .get_command(handle, version)
+            .map_err(Error::AggregateStoreError)
+    }
+
+    /// Compacts the history for a CA. Stored commands outside of the
+    /// retention policy are moved to a compressed archive.
+    pub fn ca_compact_history(
+        &self,
+        handle: &CaHandle,
+        retention: &HistoryRetention,
+    ) -> KrillResult<HistoryCompactionReport> {
+        self.ca_store.compact_history(handle, retention)
+    }
+
+    /// Compacts the history for all CAs.
+    pub fn cas_compact_history(
+        &self,
+        retention: &HistoryRetention,
+    ) -> KrillResult<HistoryCompactionReports> {
+        let mut reports = HistoryCompactionReports::default();
+        for ca in self.ca_store.list()? {
+            reports.add(self.ca_compact_history(&ca, retention)?);
+        }
+        Ok(reports)
+    }
+}
+
+/// # CAs as parents
+impl CaManager {
+    /// Adds a child under a CA. If the `AddChildRequest` contains resources
+    /// not held by this CA, then an `Error::CaChildExtraResources` is
+    /// returned.
+    pub async fn ca_add_child(
+        &self,
+        ca: &CaHandle,
+        req: AddChildRequest,
+        service_uri: &uri::Https,
+        actor: &Actor,
+    ) -> KrillResult<idexchange::ParentResponse> {
+        info!("CA '{}' process add child request: {}", &ca, &req);
+        let cert_timing = *req.cert_timing();
+        let allocation = req.allocation().clone();
+        if ca.as_str() != TA_NAME {
+            // Check the timing overrides before adding the child, so that
+            // we do not end up with a child using the default timing.
+            self.get_ca(ca)
+                .await?
+                .issuance_timing(&self.config.issuance_timing)
+                .verify_child_overrides(&cert_timing)
+                .map_err(|msg| {
+                    Error::CaChildCertTimingInvalid(
+                        ca.clone(),
+                        req.handle().clone(),
+                        msg.to_string(),
+                    )
+                })?;
+
+            let (child_handle, child_res, id_cert) = req.unpack();
+
+            let add_child = CertAuthCommandDetails::child_add(
+                ca,
+                child_handle.clone(),
+                id_cert.into(),
+                child_res,
+                allocation,
+                actor,
+            );
+            self.send_ca_command(add_child).await?;
+
+            if !cert_timing.is_empty() {
+                self.send_ca_command(
+                    CertAuthCommandDetails::child_update_cert_timing(
+                        ca,
+                        child_handle.clone(),
+                        cert_timing,
+                        self.config.clone(),
+                        actor,
+                    ),
+                )
+                .await?;
+            }
+
+            self.ca_parent_response(ca, child_handle, service_uri).await
+        } else {
+            // Certificates for children of the trust anchor are issued by
+            // the trust anchor signer, using its own timing configuration.
+            if !cert_timing.is_empty() {
+                return Err(Error::CaChildCertTimingInvalid(
+                    ca.clone(),
+                    req.handle().clone(),
+                    "not supported for children of the trust anchor"
+                        .to_string(),
+                ));
+            }
+            // The trust anchor has no resource pools.
+            if let Some(from_pool) = allocation.from_pool {
+                return Err(Error::CaResourcePoolUnknown(
+                    ca.clone(),
+                    from_pool.pool,
+                ));
+            }
+            let child_handle = req.handle().clone();
+            let add_child_cmd =
+                TrustAnchorProxyCommand::add_child(ca, req, actor);
+            self.send_ta_proxy_command(add_child_cmd).await?;
+            self.ca_parent_response(ca, child_handle, service_uri).await
+        }
+    }
+
+    /// Show details for a child under the CA.
+    pub async fn ca_show_child(
+        &self,
+        ca: &CaHandle,
+        child: &ChildHandle,
+    ) -> KrillResult<ChildCaInfo> {
+        trace!("Finding details for CA: {} under parent: {}", child, ca);
+        let ca = self.get_ca(ca).await?;
+        ca.get_child(child).map(|details| details.clone().into())
+    }
+
+    /// Export a child. Fails if:
+    /// - the child does not exist
+    /// - the child has no received certificate
+    /// - the child has more than one received certificate or resource class
+    ///
+    /// Primarily meant for testing that the child import function works.
+    pub async fn ca_child_export(
+        &self,
+        ca: &CaHandle,
+        child_handle: &ChildHandle,
+    ) -> KrillResult<ExportChild> {
+        trace!("Exporting CA: {} under parent: {}", child_handle, ca);
+        self.get_ca(ca).await?.child_export(child_handle)
+    }
+
+    /// Import a child under the given CA. Will fail if:
+    /// - the ca does not exist
+    /// - the ca has less than, or more than one resource class
+    /// - the ca does not hold the resources for the child
+    /// - the child already exists
+    pub async fn ca_child_import(
+        &self,
+        ca: &CaHandle,
+        import_child: ImportChild,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        trace!("Importing CA: {} under parent: {}", import_child.name, ca);
+        self.send_ca_command(CertAuthCommandDetails::child_import(
+            ca,
+            import_child,
+            self.config.clone(),
+            self.signer.clone(),
+            actor,
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Show a contact for a child.
+    pub async fn ca_parent_contact(
+        &self,
+        ca_handle: &CaHandle,
+        child_handle: ChildHandle,
+        service_uri: &uri::Https,
+    ) -> KrillResult<ParentCaContact> {
+        let service_uri = Self::service_uri_for_ca(service_uri, ca_handle);
+        let ca = self.get_ca(ca_handle).await?;
+
+        let server_info = ParentServerInfo::new(
+            service_uri,
+            ca_handle.convert(),
+            child_handle,
+            ca.id_cert().clone(),
+        );
+        Ok(ParentCaContact::for_parent_server_info(server_info))
+    }
+
+    /// Gets an RFC8183 Parent Response for the child.
+    pub async fn ca_parent_response(
+        &self,
+        ca_handle: &CaHandle,
+        child_handle: ChildHandle,
+        service_uri: &uri::Https,
     ) -> KrillResult<idexchange::ParentResponse> {
         let service_uri = Self::service_uri_for_ca(service_uri, ca_handle);
         let id_cert: publication::Base64 = if ca_handle.as_str() != TA_NAME {
@@ -910,8 +1692,34 @@ impl CaManager {
             resources_opt,
             suspend_opt,
             resource_class_name_mapping_opt,
+            cert_timing_opt,
+            allocation,
         ) = req.unpack();
 
+        if ca.as_str() == TA_NAME {
+            // Only the resources of children of the trust anchor can be
+            // updated. The signer re-issues their certificates.
+            if id_opt.is_some()
+                || suspend_opt.is_some()
+                || resource_class_name_mapping_opt.is_some()
+                || cert_timing_opt.is_some()
+                || !allocation.is_empty()
+            {
+                return Err(Error::custom(
+                    "Only resources can be updated for children of the trust anchor",
+                ));
+            }
+            if let Some(resources) = resources_opt {
+                self.send_ta_proxy_command(
+                    TrustAnchorProxyCommand::update_child_resources(
+                        ca, child, resources, actor,
+                    ),
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+
         if let Some(id) = id_opt {
             self.send_ca_command(CertAuthCommandDetails::child_update_id(
                 ca,
@@ -921,12 +1729,26 @@ impl CaManager {
             ))
             .await?;
         }
+
+        // Resources can be allocated from a pool in addition to the
+        // current resources of the child, if no new resources are given.
+        let resources_opt = match resources_opt {
+            None if allocation.from_pool.is_some() => Some(
+                self.get_ca(ca)
+                    .await?
+                    .get_child(&child)?
+                    .resources()
+                    .clone(),
+            ),
+            resources_opt => resources_opt,
+        };
         if let Some(resources) = resources_opt {
             self.send_ca_command(
                 CertAuthCommandDetails::child_update_resources(
                     ca,
                     child.clone(),
                     resources,
+                    allocation,
                     actor,
                 ),
             )
@@ -953,6 +1775,18 @@ impl CaManager {
                 .await?;
             }
         }
+        if let Some(cert_timing) = cert_timing_opt {
+            self.send_ca_command(
+                CertAuthCommandDetails::child_update_cert_timing(
+                    ca,
+                    child.clone(),
+                    cert_timing,
+                    self.config.clone(),
+                    actor,
+                ),
+            )
+            .await?;
+        }
         if let Some(mapping) = resource_class_name_mapping_opt {
             self.send_ca_command(CertAuthCommandDetails::child_update_resource_class_name_mapping(
                 ca, child, mapping, actor,
@@ -970,12 +1804,65 @@ impl CaManager {
         child: ChildHandle,
         actor: &Actor,
     ) -> KrillResult<()> {
-        self.status_store.remove_child(ca, &child)?;
-        self.send_ca_command(CertAuthCommandDetails::child_remove(
-            ca, child, actor,
+        if ca.as_str() == TA_NAME {
+            // The signer revokes the certificates issued to the child. This
+            // fails if there is an open signer request, so only remove the
+            // status once the child is actually removed.
+            self.send_ta_proxy_command(
+                TrustAnchorProxyCommand::remove_child(
+                    ca,
+                    child.clone(),
+                    actor,
+                ),
+            )
+            .await?;
+            self.status_store.remove_child(ca, &child)?;
+        } else {
+            self.status_store.remove_child(ca, &child)?;
+            self.send_ca_command(CertAuthCommandDetails::child_remove(
+                ca, child, actor,
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Shows the resource pools of a CA, and how much of each pool is
+    /// delegated to children.
+    pub async fn ca_resource_pools(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<ResourcePoolList> {
+        Ok(self.get_ca(ca).await?.resource_pools())
+    }
+
+    /// Adds or replaces a resource pool of a CA.
+    pub async fn ca_resource_pool_update(
+        &self,
+        ca: &CaHandle,
+        definition: ResourcePoolDefinition,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.send_ca_command(CertAuthCommandDetails::resource_pool_update(
+            ca, definition, actor,
         ))
         .await?;
+        Ok(())
+    }
 
+    /// Removes a resource pool of a CA. This does not affect the resources
+    /// of children.
+    pub async fn ca_resource_pool_remove(
+        &self,
+        ca: &CaHandle,
+        name: ResourcePoolName,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.send_ca_command(CertAuthCommandDetails::resource_pool_remove(
+            ca, name, actor,
+        ))
+        .await?;
         Ok(())
     }
 
@@ -1575,13 +2462,17 @@ impl CaManager {
 
         if let Ok(mut proxy) = self.get_trust_anchor_proxy().await {
             if let Ok(mut signer) = self.get_trust_anchor_signer().await {
-                // make sign request in proxy
-                let sign_request_cmd =
-                    TrustAnchorProxyCommand::make_signer_request(
-                        &ta_handle,
-                        &self.system_actor,
-                    );
-                proxy = self.send_ta_proxy_command(sign_request_cmd).await?;
+                // make sign request in proxy, unless there already is an
+                // open request (e.g. for a key roll)
+                if !proxy.has_open_request() {
+                    let sign_request_cmd =
+                        TrustAnchorProxyCommand::make_signer_request(
+                            &ta_handle,
+                            &self.system_actor,
+                        );
+                    proxy =
+                        self.send_ta_proxy_command(sign_request_cmd).await?;
+                }
 
                 // get sign request for signer
                 let signed_request = proxy.get_signer_request(
@@ -2519,11 +3410,17 @@ impl CaManager {
                 let repo = proxy
                     .repository()
                     .ok_or(Error::TaProxyHasNoRepository)?;
-                let objects =
-                    proxy.get_trust_anchor_objects()?.publish_elements()?;
+                let objects = proxy.publish_elements()?;
 
-                self.ca_repo_sync(repo_manager, ca_handle, id, repo, objects)
-                    .await?;
+                self.ca_repo_sync(
+                    repo_manager,
+                    ca_handle,
+                    id,
+                    repo,
+                    RepoStatusTarget::Primary,
+                    objects,
+                )
+                .await?;
                 Ok(true)
             }
         } else if !self.has_ca(ca_handle)? {
@@ -2535,19 +3432,77 @@ impl CaManager {
             if ca.version() < ca_version {
                 Ok(false)
             } else {
-                for (repo_contact, objects) in
-                    self.ca_repo_elements(ca_handle).await?
-                {
+                let mut repo_elements =
+                    self.ca_repo_elements(ca_handle).await?;
+
+                for (repo_contact, objects) in repo_elements.iter() {
                     self.ca_repo_sync(
                         repo_manager,
                         ca_handle,
                         ca.id_cert(),
-                        &repo_contact,
-                        objects,
+                        repo_contact,
+                        RepoStatusTarget::Primary,
+                        objects.clone(),
                     )
                     .await?;
                 }
 
+                // Mirrors get the objects of the current repository. A
+                // failing mirror does not keep other mirrors from being
+                // synchronized, but the first error is returned so that
+                // the sync is retried.
+                let mut mirror_result = Ok(());
+                let mirror_objects = match ca.repository_contact() {
+                    Ok(contact) => {
+                        repo_elements.remove(contact).unwrap_or_default()
+                    }
+                    Err(_) => vec![],
+                };
+                for (name, mirror) in ca.repository_mirrors() {
+                    if let Err(e) = self
+                        .ca_repo_sync(
+                            repo_manager,
+                            ca_handle,
+                            ca.id_cert(),
+                            mirror,
+                            RepoStatusTarget::Mirror(name),
+                            mirror_objects.clone(),
+                        )
+                        .await
+                    {
+                        warn!(
+                            "Could not sync CA '{}' with repository mirror '{}': {}",
+                            ca_handle, name, e
+                        );
+                        if mirror_result.is_ok() {
+                            mirror_result = Err(e);
+                        }
+                    }
+                }
+
+                // Objects are staged in the repository that the CA is
+                // migrating to, using the current objects. The CA switches
+                // to the new repository as soon as they can all be
+                // retrieved from there.
+                if let Some(migration) = ca.repository_migration() {
+                    if migration.phase() == RepoMigrationPhase::Staging {
+                        if let Err(e) = self
+                            .ca_repo_migration_stage(
+                                repo_manager,
+                                &ca,
+                                migration,
+                                &mirror_objects,
+                            )
+                            .await
+                        {
+                            warn!(
+                                "Could not stage objects for CA '{}' in new repository: {}",
+                                ca_handle, e
+                            );
+                        }
+                    }
+                }
+
                 // Clean-up of old repos
                 for deprecated in self.ca_deprecated_repos(ca_handle)? {
                     info!(
@@ -2556,12 +3511,27 @@ impl CaManager {
                         ca_handle
                     );
 
+                    // A removed mirror uses the same URIs as the current
+                    // repository, so the result of withdrawing its objects
+                    // must not end up in the status of the current
+                    // repository.
+                    let status = match ca.repository_contact() {
+                        Ok(current)
+                            if current.repo_info()
+                                == deprecated.contact().repo_info() =>
+                        {
+                            RepoStatusTarget::Untracked
+                        }
+                        _ => RepoStatusTarget::Primary,
+                    };
+
                     if let Err(e) = self
                         .ca_repo_sync(
                             repo_manager,
                             ca_handle,
                             ca.id_cert(),
                             deprecated.contact(),
+                            status,
                             vec![],
                         )
                         .await
@@ -2586,7 +3556,25 @@ impl CaManager {
                     )?;
                 }
 
-                Ok(true)
+                // A migration is complete when the old repository was
+                // cleaned up.
+                if let Some(migration) = ca.repository_migration() {
+                    if migration.phase() == RepoMigrationPhase::Withdrawing
+                        && !self
+                            .ca_deprecated_repos(ca_handle)?
+                            .iter()
+                            .any(|d| d.contact() == migration.from())
+                    {
+                        let cmd =
+                            CertAuthCommandDetails::repo_migration_complete(
+                                ca_handle,
+                                &self.system_actor,
+                            );
+                        self.send_ca_command(cmd).await?;
+                    }
+                }
+
+                mirror_result.map(|_| true)
             }
         }
     }
@@ -2598,6 +3586,7 @@ impl CaManager {
         ca_handle: &CaHandle,
         id_cert: &IdCertInfo,
         repo_contact: &RepositoryContact,
+        status: RepoStatusTarget<'_>,
         publish_elements: Vec<PublishElement>,
     ) -> KrillResult<()> {
         debug!("CA '{}' sends list query to repo", ca_handle);
@@ -2607,6 +3596,7 @@ impl CaManager {
                 ca_handle,
                 id_cert,
                 repo_contact.server_info(),
+                status,
             )
             .await?;
 
@@ -2619,39 +3609,186 @@ impl CaManager {
         let mut all_objects: HashMap<_, _> =
             publish_elements.into_iter().map(|el| el.unpack()).collect();
 
-        let mut delta = PublishDelta::empty();
+        let mut delta = PublishDelta::empty();
+
+        for (uri, hash) in elements.into_iter() {
+            match all_objects.remove(&uri) {
+                Some(base64) => {
+                    if base64.to_hash() != hash {
+                        delta.add_update(Update::new(None, uri, base64, hash))
+                    }
+                }
+                None => delta.add_withdraw(Withdraw::new(None, uri, hash)),
+            }
+        }
+
+        for (uri, base64) in all_objects {
+            delta.add_publish(Publish::new(None, uri, base64));
+        }
+
+        if !delta.is_empty() {
+            debug!("CA '{}' sends delta", ca_handle);
+            self.send_rfc8181_delta(
+                repo_manager,
+                ca_handle,
+                id_cert,
+                repo_contact.server_info(),
+                status,
+                delta,
+            )
+            .await?;
+            debug!("CA '{}' sent delta", ca_handle);
+        } else {
+            debug!("CA '{}' has nothing to publish", ca_handle);
+        }
+
+        Ok(())
+    }
+
+    /// Publishes the current objects of a CA in the repository that it is
+    /// migrating to, and verifies that they can all be retrieved from
+    /// there with matching hashes. If so, the CA is told to switch to the
+    /// new repository.
+    async fn ca_repo_migration_stage(
+        &self,
+        repo_manager: &RepositoryManager,
+        ca: &CertAuth,
+        migration: &RepoMigration,
+        objects: &[PublishElement],
+    ) -> KrillResult<()> {
+        let from_base = migration.from().repo_info().base_uri();
+        let to_base = migration.to().repo_info().base_uri();
+
+        let mut staged = vec![];
+        for element in objects {
+            let relative = element
+                .uri()
+                .as_str()
+                .strip_prefix(from_base.as_str())
+                .ok_or_else(|| {
+                    Error::publishing_outside_jail(element.uri(), from_base)
+                })?;
+            let uri =
+                uri::Rsync::from_str(&format!("{}{}", to_base, relative))
+                    .map_err(|e| {
+                        Error::custom(format!("invalid uri: {}", e))
+                    })?;
+            staged.push(PublishElement::new(element.base64().clone(), uri));
+        }
+
+        self.ca_repo_sync(
+            repo_manager,
+            ca.handle(),
+            ca.id_cert(),
+            migration.to(),
+            RepoStatusTarget::Untracked,
+            staged.clone(),
+        )
+        .await?;
+
+        let found = self
+            .ca_repo_migration_retrieve(repo_manager, ca, migration.to())
+            .await?;
+        let missing = staged
+            .iter()
+            .filter(|el| {
+                found.get(el.uri().as_str()) != Some(&el.base64().to_hash())
+            })
+            .count();
+
+        if missing > 0 {
+            info!(
+                "CA '{}' is waiting for {} of {} objects to appear in the new repository",
+                ca.handle(),
+                missing,
+                staged.len()
+            );
+            return Ok(());
+        }
+
+        let cmd = CertAuthCommandDetails::repo_migration_verified(
+            ca.handle(),
+            staged.len(),
+            self.signer.clone(),
+            &self.system_actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
 
-        for (uri, hash) in elements.into_iter() {
-            match all_objects.remove(&uri) {
-                Some(base64) => {
-                    if base64.to_hash() != hash {
-                        delta.add_update(Update::new(None, uri, base64, hash))
-                    }
+    /// Returns the hashes of the objects which can be retrieved from a
+    /// repository, by uri. If the repository is the local publication
+    /// server, its current files for the CA are used, otherwise the
+    /// current RRDP snapshot is retrieved. The snapshot is only retrieved
+    /// again if the notification file has a new session or serial.
+    async fn ca_repo_migration_retrieve(
+        &self,
+        repo_manager: &RepositoryManager,
+        ca: &CertAuth,
+        contact: &RepositoryContact,
+    ) -> KrillResult<HashMap<String, Hash>> {
+        let publisher = ca.handle().convert();
+        if repo_manager.initialized()? {
+            if let Ok(response) = repo_manager.repository_response(&publisher)
+            {
+                if response.service_uri()
+                    == contact.server_info().service_uri()
+                {
+                    return Ok(repo_manager
+                        .get_publisher_details(&publisher)?
+                        .current_files()
+                        .iter()
+                        .map(|el| {
+                            (el.uri().to_string(), el.base64().to_hash())
+                        })
+                        .collect());
                 }
-                None => delta.add_withdraw(Withdraw::new(None, uri, hash)),
             }
         }
 
-        for (uri, base64) in all_objects {
-            delta.add_publish(Publish::new(None, uri, base64));
-        }
+        let issue = |msg: String| {
+            Error::custom(format!(
+                "cannot retrieve objects from {}: {}",
+                contact.server_info().service_uri(),
+                msg
+            ))
+        };
 
-        if !delta.is_empty() {
-            debug!("CA '{}' sends delta", ca_handle);
-            self.send_rfc8181_delta(
-                repo_manager,
-                ca_handle,
-                id_cert,
-                repo_contact.server_info(),
-                delta,
-            )
-            .await?;
-            debug!("CA '{}' sent delta", ca_handle);
-        } else {
-            debug!("CA '{}' has nothing to publish", ca_handle);
+        let notify_uri =
+            contact.repo_info().rpki_notify().ok_or_else(|| {
+                issue("the new repository does not support RRDP".to_string())
+            })?;
+        let notification = httpclient::get_text(notify_uri.as_str(), None)
+            .await
+            .map_err(|e| issue(e.to_string()))?;
+        let notification = NotificationFile::parse(notification.as_bytes())
+            .map_err(|e| issue(e.to_string()))?;
+
+        if let Some(known) =
+            self.migration_snapshots.lock().unwrap().get(notify_uri)
+        {
+            if known.is_current(&notification) {
+                return Ok(known.hashes.clone());
+            }
         }
 
-        Ok(())
+        let mut snapshot = vec![];
+        httpclient::get_to_writer(
+            notification.snapshot().uri().as_str(),
+            None,
+            &mut snapshot,
+        )
+        .await
+        .map_err(|e| issue(e.to_string()))?;
+        let snapshot = MigrationSnapshot::parse(&notification, &snapshot)
+            .map_err(issue)?;
+
+        let hashes = snapshot.hashes.clone();
+        self.migration_snapshots
+            .lock()
+            .unwrap()
+            .insert(notify_uri.clone(), snapshot);
+        Ok(hashes)
     }
 
     /// Get the current objects for a CA for each repository that it's using.
@@ -2722,6 +3859,7 @@ impl CaManager {
                 &ca_handle,
                 ca.id_cert(),
                 new_contact.server_info(),
+                RepoStatusTarget::Primary,
             )
             .await
             .map_err(|e| {
@@ -2738,12 +3876,102 @@ impl CaManager {
         Ok(())
     }
 
+    /// Adds or updates a repository which mirrors the objects published
+    /// by a CA in its primary repository. The mirror is checked to be
+    /// reachable first.
+    pub async fn ca_repo_mirror_add(
+        &self,
+        repo_manager: &RepositoryManager,
+        ca_handle: CaHandle,
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let ca = self.get_ca(&ca_handle).await?;
+        self.send_rfc8181_list(
+            repo_manager,
+            &ca_handle,
+            ca.id_cert(),
+            contact.server_info(),
+            RepoStatusTarget::Untracked,
+        )
+        .await
+        .map_err(|e| Error::CaRepoIssue(ca_handle.clone(), e.to_string()))?;
+
+        let cmd = CertAuthCommandDetails::repo_mirror_add(
+            &ca_handle, name, contact, actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Starts a staged migration of a CA to a new repository. The new
+    /// repository is checked to be reachable first.
+    pub async fn ca_repo_migration_start(
+        &self,
+        repo_manager: &RepositoryManager,
+        ca_handle: CaHandle,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let ca = self.get_ca(&ca_handle).await?;
+        self.send_rfc8181_list(
+            repo_manager,
+            &ca_handle,
+            ca.id_cert(),
+            contact.server_info(),
+            RepoStatusTarget::Untracked,
+        )
+        .await
+        .map_err(|e| Error::CaRepoIssue(ca_handle.clone(), e.to_string()))?;
+
+        let cmd = CertAuthCommandDetails::repo_migration_start(
+            &ca_handle, contact, actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Aborts a staged migration of a CA to a new repository. Objects
+    /// which were staged there are withdrawn when the CA is next
+    /// synchronized with its repositories.
+    pub async fn ca_repo_migration_abort(
+        &self,
+        ca_handle: CaHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd =
+            CertAuthCommandDetails::repo_migration_abort(&ca_handle, actor);
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Removes a repository mirror of a CA. Its objects are withdrawn from
+    /// the mirror when the CA is next synchronized with its repositories.
+    pub async fn ca_repo_mirror_remove(
+        &self,
+        ca_handle: CaHandle,
+        name: RepositoryMirrorName,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::repo_mirror_remove(
+            &ca_handle,
+            name.clone(),
+            actor,
+        );
+        self.send_ca_command(cmd).await?;
+        self.status_store
+            .remove_status_repo_mirror(&ca_handle, &name)?;
+        Ok(())
+    }
+
     async fn send_rfc8181_list(
         &self,
         repo_manager: &RepositoryManager,
         ca_handle: &CaHandle,
         id_cert: &IdCertInfo,
         server_info: &PublicationServerInfo,
+        status: RepoStatusTarget<'_>,
     ) -> KrillResult<ListReply> {
         let uri = server_info.service_uri();
         let signing_key = id_cert.public_key().key_identifier();
@@ -2764,6 +3992,7 @@ impl CaManager {
             Err(e) => {
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &e,
                 )?;
@@ -2773,14 +4002,18 @@ impl CaManager {
 
         match reply {
             publication::Reply::List(list_reply) => {
-                self.status_store
-                    .set_status_repo_success(ca_handle, uri.clone())?;
+                self.status_store.set_status_repo_success(
+                    ca_handle,
+                    status,
+                    uri.clone(),
+                )?;
                 Ok(list_reply)
             }
             publication::Reply::Success => {
                 let err = Error::custom("Got success reply to list query?!");
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &err,
                 )?;
@@ -2790,6 +4023,7 @@ impl CaManager {
                 let err = Error::Custom(format!("Got error reply: {}", e));
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &err,
                 )?;
@@ -2804,6 +4038,7 @@ impl CaManager {
         ca_handle: &CaHandle,
         id_cert: &IdCertInfo,
         server_info: &PublicationServerInfo,
+        status: RepoStatusTarget<'_>,
         delta: PublishDelta,
     ) -> KrillResult<()> {
         let uri = server_info.service_uri();
@@ -2825,6 +4060,7 @@ impl CaManager {
             Err(e) => {
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &e,
                 )?;
@@ -2836,6 +4072,7 @@ impl CaManager {
             publication::Reply::Success => {
                 self.status_store.set_status_repo_published(
                     ca_handle,
+                    status,
                     uri.clone(),
                     delta,
                 )?;
@@ -2845,6 +4082,7 @@ impl CaManager {
                 let err = Error::Custom(format!("Got error reply: {}", e));
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &err,
                 )?;
@@ -2854,6 +4092,7 @@ impl CaManager {
                 let err = Error::custom("Got list reply to delta query?!");
                 self.status_store.set_status_repo_failure(
                     ca_handle,
+                    status,
                     uri.clone(),
                     &err,
                 )?;
@@ -3199,6 +4438,105 @@ impl CaManager {
     }
 }
 
+/// # RPKI Signed Checklist functions
+impl CaManager {
+    /// Sign an RSC for resources which are all held by the CA
+    pub async fn rsc_sign(
+        &self,
+        ca: CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::rsc_sign(
+            &ca,
+            name,
+            request,
+            self.signer.clone(),
+            actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Co-sign an RSC for the part of the resources held by the CA
+    pub async fn rsc_cosign(
+        &self,
+        ca: CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::rsc_cosign(
+            &ca,
+            name,
+            request,
+            self.signer.clone(),
+            actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+}
+
+/// # Signed geofeed functions
+impl CaManager {
+    /// Sign a geofeed.
+    ///
+    /// The prefixes in the geofeed must all be held by the CA, or by any
+    /// of the co-signers in the request. In the latter case every CA signs
+    /// a geofeed with the entries for the prefixes it holds, trying the CA
+    /// first and then the co-signers in order.
+    pub async fn geofeed_sign(
+        &self,
+        ca: CaHandle,
+        request: GeofeedSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<SignedGeofeeds> {
+        let mut remaining = Geofeed::parse(request.geofeed())?;
+
+        let mut signers = vec![ca];
+        for cosigner in request.cosigners() {
+            if !signers.contains(cosigner) {
+                signers.push(cosigner.clone());
+            }
+        }
+
+        let mut parts = vec![];
+        for handle in signers {
+            let held = self.get_ca(&handle).await?.all_resources();
+            let part = remaining.take_held(&held);
+            if !part.is_empty() {
+                parts.push((handle, part));
+            }
+        }
+
+        if !remaining.is_empty() {
+            return Err(Error::GeofeedPrefixesNotHeld(Geofeed::prefixes(
+                &remaining.resources(),
+            )));
+        }
+
+        let mut signed = vec![];
+        for (handle, part) in parts {
+            let cmd = CertAuthCommandDetails::geofeed_sign(
+                &handle,
+                part,
+                request.validity(),
+                self.signer.clone(),
+                actor,
+            );
+            let ca = self.send_ca_command(cmd).await?;
+            let geofeed = ca.geofeed_latest().cloned().ok_or_else(|| {
+                Error::custom("Signed geofeed not found in CA")
+            })?;
+            signed.push((handle, geofeed));
+        }
+
+        Ok(SignedGeofeeds::new(signed))
+    }
+}
+
 /// CA Key Roll functions
 impl CaManager {
     /// Initiate an RFC 6489 key roll for all active keys in a CA older than
@@ -3240,4 +4578,236 @@ impl CaManager {
         self.send_ca_command(activate_cmd).await?;
         Ok(())
     }
+
+    /// Sets the policy for rolling the keys of a CA automatically, or
+    /// removes it so that the default from the server configuration is
+    /// used.
+    pub async fn ca_keyroll_policy_update(
+        &self,
+        handle: CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::key_roll_policy_update(
+            &handle, policy, actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Sets overrides of the server issuance timing for a CA, or removes
+    /// them so that the server configuration is used.
+    pub async fn ca_issuance_timing_update(
+        &self,
+        handle: CaHandle,
+        timing: Option<CaIssuanceTiming>,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::issuance_timing_update(
+            &handle,
+            timing,
+            self.config.clone(),
+            actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Initiates or activates key rolls for all CAs for which this is due
+    /// according to their key roll policy.
+    pub async fn ca_keyroll_all_if_needed(
+        &self,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let default_policy = self.config.key_roll_policy();
+        let now = Timestamp::now();
+
+        for handle in self.ca_store.list()? {
+            let ca = self.get_ca(&handle).await?;
+            let policy =
+                ca.key_roll_policy().copied().unwrap_or(default_policy);
+
+            let planned = match ca.planned_key_roll(&policy) {
+                Some(planned) if planned.due <= now => planned,
+                _ => continue,
+            };
+
+            info!(
+                "Key roll policy for CA '{}' requires '{}' for resource class '{}'",
+                handle, planned.phase, planned.resource_class_name
+            );
+
+            let res = match planned.phase {
+                KeyRollPhase::Initiate => {
+                    let max_age = policy.max_key_age(now.into());
+                    self.ca_keyroll_init(handle.clone(), max_age, actor).await
+                }
+                KeyRollPhase::Activate => {
+                    let staging = policy.staging_period();
+                    self.ca_keyroll_activate(handle.clone(), staging, actor)
+                        .await
+                }
+            };
+
+            if let Err(e) = res {
+                error!(
+                    "Scheduled key roll '{}' for CA '{}' failed with error: {}",
+                    planned.phase, handle, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the keys of a CA which may be compromised.
+    ///
+    /// New keys are created and certified by the parents, and activated
+    /// immediately. All ROAs, ASPAs, BGPSec certificates and child
+    /// certificates are re-issued under the new keys and the parents are
+    /// asked to revoke the old keys.
+    ///
+    /// If a parent does not certify a new key, then an error is returned.
+    /// The emergency key roll can be retried later, in which case the new
+    /// keys which are already pending are kept.
+    pub async fn ca_keyroll_emergency(
+        &self,
+        handle: CaHandle,
+        reason: String,
+        actor: &Actor,
+    ) -> KrillResult<EmergencyRekeyReport> {
+        let start_cmd = CertAuthCommandDetails::key_roll_emergency(
+            &handle,
+            reason.clone(),
+            self.signer.clone(),
+            actor,
+        );
+        let ca = self.send_ca_command(start_cmd).await?;
+
+        // Get the new keys certified.
+        let parents: HashSet<ParentHandle> = ca
+            .new_keys_pending()
+            .into_iter()
+            .map(|(_, parent)| parent)
+            .collect();
+        for parent in parents {
+            self.ca_sync_parent_now(&handle, &parent, actor).await?;
+        }
+
+        let ca = self.get_ca(&handle).await?;
+        if let Some((rcn, parent)) = ca.new_keys_pending().into_iter().next()
+        {
+            return Err(Error::KeyRollNotCertified(parent, rcn));
+        }
+
+        // Activate the new keys without staging.
+        let version_before = ca.version();
+        let activate_cmd = CertAuthCommandDetails::key_roll_activate(
+            &handle,
+            Duration::seconds(0),
+            self.config.clone(),
+            self.signer.clone(),
+            actor,
+        );
+        let ca = self.send_ca_command(activate_cmd).await?;
+
+        // Other commands could have been applied in the meantime, so look
+        // for the activation rather than assuming its version.
+        let mut events = vec![];
+        for version in version_before..ca.version() {
+            let command = self.ca_command_details(&handle, version)?;
+            if let CertAuthStorableCommand::KeyRollActivate { .. } =
+                command.details()
+            {
+                events.append(&mut command.into_events().unwrap_or_default());
+            }
+        }
+
+        // Ask the parents to revoke the old keys. If this fails, then the
+        // requests will be sent again when the parents are synchronised.
+        for parent in ca.parents_with_revoke_requests() {
+            if let Err(e) =
+                self.ca_sync_parent_now(&handle, &parent, actor).await
+            {
+                warn!(
+                    "CA '{}' could not request revocation of old key from parent '{}'. Will retry later. Error: {}",
+                    handle, parent, e
+                );
+            }
+        }
+
+        let ca = self.get_ca(&handle).await?;
+        Ok(ca.emergency_rekey_report(reason, events))
+    }
+
+    /// Synchronises a CA with its parent, and pushes the exchange along
+    /// if the parent is the local trust anchor.
+    async fn ca_sync_parent_now(
+        &self,
+        handle: &CaHandle,
+        parent: &ParentHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.ca_sync_parent(handle, 0, parent, actor).await?;
+        if parent.as_str() == TA_NAME {
+            self.sync_ta_proxy_signer_if_possible().await?;
+            self.ca_sync_parent(handle, 0, parent, actor).await?;
+        }
+        Ok(())
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rpki::rrdp::{self, SnapshotInfo};
+
+    use super::*;
+
+    #[test]
+    fn migration_snapshot_must_match_notification() {
+        let session_id = uuid::Uuid::new_v4();
+        let element = rrdp::PublishElement::new(
+            uri::Rsync::from_str("rsync://example.com/repo/ca/file.roa")
+                .unwrap(),
+            Bytes::from_static(b"object"),
+        );
+        let mut snapshot = vec![];
+        Snapshot::new(session_id, 2, vec![element])
+            .write_xml(&mut snapshot)
+            .unwrap();
+
+        let snapshot_uri =
+            uri::Https::from_str("https://example.com/rrdp/snapshot.xml")
+                .unwrap();
+        let notification = |session_id, serial, hash| {
+            NotificationFile::new(
+                session_id,
+                serial,
+                SnapshotInfo::new(snapshot_uri.clone(), hash),
+                vec![],
+            )
+        };
+
+        let current = notification(session_id, 2, Hash::from_data(&snapshot));
+        let parsed = MigrationSnapshot::parse(&current, &snapshot).unwrap();
+        assert_eq!(
+            parsed.hashes.get("rsync://example.com/repo/ca/file.roa"),
+            Some(&Hash::from_data(b"object"))
+        );
+        assert!(parsed.is_current(&current));
+        let newer = notification(session_id, 3, Hash::from_data(&snapshot));
+        assert!(!parsed.is_current(&newer));
+
+        // A snapshot that does not match the hash is rejected.
+        let wrong_hash =
+            notification(session_id, 2, Hash::from_data(b"other"));
+        assert!(MigrationSnapshot::parse(&wrong_hash, &snapshot).is_err());
+
+        // So is a snapshot for another serial or session.
+        assert!(MigrationSnapshot::parse(&newer, &snapshot).is_err());
+        let other_session =
+            notification(uuid::Uuid::new_v4(), 2, Hash::from_data(&snapshot));
+        assert!(MigrationSnapshot::parse(&other_session, &snapshot).is_err());
+    }
 }
@@ -21,6 +21,9 @@ pub use self::rc::ResourceClass;
 mod keys;
 pub use self::keys::*;
 
+mod pools;
+pub use self::pools::*;
+
 mod publishing;
 pub use self::publishing::*;
 
@@ -37,6 +40,12 @@ pub use self::events::*;
 mod manager;
 pub use self::manager::CaManager;
 
+mod rsc;
+pub use self::rsc::*;
+
+mod geofeed;
+pub use self::geofeed::*;
+
 mod rta;
 pub use self::rta::*;
 
@@ -0,0 +1,333 @@
+//! Resource pools, from which CAs can allocate resources to children.
+
+use std::collections::HashMap;
+
+use rpki::repository::resources::{
+    Addr, AsBlock, AsBlocks, AsBlocksBuilder, Asn, IpBlocks, IpBlocksBuilder,
+    Ipv4Blocks, Ipv6Blocks, Prefix, ResourceSet,
+};
+
+use crate::commons::api::{
+    PoolAllocation, ResourcePoolInfo, ResourcePoolList, ResourcePoolName,
+};
+
+//------------ ResourcePools -------------------------------------------------
+
+/// The resource pools defined for a CA. Pools do not overlap, and only
+/// contain resources that were held by the CA when the pool was defined.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ResourcePools {
+    pools: HashMap<ResourcePoolName, ResourceSet>,
+}
+
+impl ResourcePools {
+    pub fn add_or_replace(
+        &mut self,
+        name: ResourcePoolName,
+        resources: ResourceSet,
+    ) {
+        self.pools.insert(name, resources);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.pools.remove(name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ResourceSet> {
+        self.pools.get(name)
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.pools.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Returns the resources in all pools.
+    pub fn all_resources(&self) -> ResourceSet {
+        self.pools
+            .values()
+            .fold(ResourceSet::empty(), |all, resources| all.union(resources))
+    }
+
+    /// Returns the name of another pool than the named pool which
+    /// overlaps with the given resources, if any.
+    pub fn overlapping(
+        &self,
+        name: &str,
+        resources: &ResourceSet,
+    ) -> Option<&ResourcePoolName> {
+        let mut names: Vec<_> = self.pools.keys().collect();
+        names.sort();
+        names.into_iter().find(|other| {
+            other.as_str() != name
+                && !self.pools[other.as_str()]
+                    .intersection(resources)
+                    .is_empty()
+        })
+    }
+
+    /// Returns the resources in the named pool that are still held by the
+    /// CA and not delegated to children, or None if there is no such pool.
+    pub fn free(
+        &self,
+        name: &str,
+        held: &ResourceSet,
+        delegated: &ResourceSet,
+    ) -> Option<ResourceSet> {
+        let available = self.pools.get(name)?.intersection(held);
+        Some(ResourceSet::new(
+            available.asn().difference(delegated.asn()),
+            available.ipv4().difference(delegated.ipv4()).into(),
+            available.ipv6().difference(delegated.ipv6()).into(),
+        ))
+    }
+
+    /// Reports the resources in each pool that are delegated to children.
+    pub fn info(&self, delegated: &ResourceSet) -> ResourcePoolList {
+        let mut pools: Vec<_> = self
+            .pools
+            .iter()
+            .map(|(name, resources)| {
+                ResourcePoolInfo::new(
+                    name.clone(),
+                    resources.clone(),
+                    resources.intersection(delegated),
+                )
+            })
+            .collect();
+        pools.sort_by(|a, b| a.name.cmp(&b.name));
+        ResourcePoolList::new(pools)
+    }
+}
+
+//------------ Allocation ----------------------------------------------------
+
+/// Allocates the first free resources of the requested sizes from the
+/// given free resources. Returns a message suitable for the user if they
+/// cannot be allocated.
+pub fn allocate_from_pool(
+    allocation: &PoolAllocation,
+    free: &ResourceSet,
+) -> Result<ResourceSet, String> {
+    if allocation.ipv4_prefix_len.is_none()
+        && allocation.ipv6_prefix_len.is_none()
+        && allocation.asns.is_none()
+    {
+        return Err("nothing to allocate".to_string());
+    }
+
+    let mut allocated = ResourceSet::empty();
+
+    if let Some(len) = allocation.ipv4_prefix_len {
+        if len > 32 {
+            return Err(format!("invalid IPv4 prefix length /{}", len));
+        }
+        let prefix = first_free_prefix(free.ipv4(), len, 32)
+            .ok_or_else(|| format!("no free IPv4 /{} left", len))?;
+        allocated.set_ipv4(Ipv4Blocks::from(prefix_blocks(prefix)));
+    }
+
+    if let Some(len) = allocation.ipv6_prefix_len {
+        if len > 128 {
+            return Err(format!("invalid IPv6 prefix length /{}", len));
+        }
+        let prefix = first_free_prefix(free.ipv6(), len, 128)
+            .ok_or_else(|| format!("no free IPv6 /{} left", len))?;
+        allocated.set_ipv6(Ipv6Blocks::from(prefix_blocks(prefix)));
+    }
+
+    if let Some(number) = allocation.asns {
+        if number == 0 {
+            return Err("the number of ASNs must be at least 1".to_string());
+        }
+        let asns = first_free_asns(free.asn(), number)
+            .ok_or_else(|| format!("fewer than {} free ASNs left", number))?;
+        allocated.set_asn(asns);
+    }
+
+    Ok(allocated)
+}
+
+/// Returns the first prefix of the given length that fits entirely in one
+/// of the blocks. Addresses of a family with the given number of bits are
+/// stored in the most significant bits, so the calculation is done after
+/// shifting out the unused bits.
+fn first_free_prefix(blocks: &IpBlocks, len: u8, bits: u8) -> Option<Prefix> {
+    let shift = 128 - u32::from(bits);
+    let host_bits = u32::from(bits - len);
+    let mask = if host_bits == 128 {
+        u128::MAX
+    } else {
+        (1_u128 << host_bits) - 1
+    };
+
+    blocks.iter().find_map(|block| {
+        let min = block.min().to_bits() >> shift;
+        let max = block.max().to_bits() >> shift;
+
+        let start = if min & mask == 0 {
+            min
+        } else {
+            (min | mask).checked_add(1)?
+        };
+        let end = start | mask;
+
+        if end <= max {
+            Some(Prefix::new(Addr::from_bits(start << shift), len))
+        } else {
+            None
+        }
+    })
+}
+
+fn prefix_blocks(prefix: Prefix) -> IpBlocks {
+    let mut builder = IpBlocksBuilder::new();
+    builder.push(prefix);
+    builder.finalize()
+}
+
+/// Returns the first number of ASNs in the blocks, or None if there are
+/// not enough.
+fn first_free_asns(blocks: &AsBlocks, number: u32) -> Option<AsBlocks> {
+    let mut remaining = u64::from(number);
+    let mut builder = AsBlocksBuilder::new();
+
+    for block in blocks.iter() {
+        if remaining == 0 {
+            break;
+        }
+        let min = u64::from(block.min().into_u32());
+        let max = u64::from(block.max().into_u32());
+        let take = remaining.min(max - min + 1);
+        builder.push(AsBlock::from((
+            Asn::from_u32(min as u32),
+            Asn::from_u32((min + take - 1) as u32),
+        )));
+        remaining -= take;
+    }
+
+    if remaining == 0 {
+        Some(builder.finalize())
+    } else {
+        None
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources(asn: &str, v4: &str, v6: &str) -> ResourceSet {
+        ResourceSet::from_strs(asn, v4, v6).unwrap()
+    }
+
+    #[test]
+    fn allocate_next_free() {
+        let free = resources(
+            "AS65000, AS65003-AS65010",
+            "10.0.0.128/25, 10.0.1.0/24, 10.0.2.0/23",
+            "2001:db8::/47, 2001:db8:3::/48",
+        );
+
+        let allocation = PoolAllocation::new("pool".to_string())
+            .with_ipv4_prefix_len(24)
+            .with_ipv6_prefix_len(48)
+            .with_asns(3);
+        assert_eq!(
+            allocate_from_pool(&allocation, &free),
+            Ok(resources(
+                "AS65000, AS65003-AS65004",
+                "10.0.1.0/24",
+                "2001:db8::/48"
+            ))
+        );
+
+        let allocation = PoolAllocation::new("pool".to_string())
+            .with_ipv4_prefix_len(23)
+            .with_ipv6_prefix_len(46);
+        assert_eq!(
+            allocate_from_pool(&allocation, &free),
+            Err("no free IPv6 /46 left".to_string())
+        );
+
+        let allocation =
+            PoolAllocation::new("pool".to_string()).with_ipv4_prefix_len(23);
+        assert_eq!(
+            allocate_from_pool(&allocation, &free),
+            Ok(resources("", "10.0.2.0/23", ""))
+        );
+
+        let allocation =
+            PoolAllocation::new("pool".to_string()).with_asns(10);
+        assert!(allocate_from_pool(&allocation, &free).is_err());
+
+        let allocation = PoolAllocation::new("pool".to_string());
+        assert!(allocate_from_pool(&allocation, &free).is_err());
+
+        let allocation =
+            PoolAllocation::new("pool".to_string()).with_ipv4_prefix_len(0);
+        assert_eq!(
+            allocate_from_pool(&allocation, &ResourceSet::all()),
+            Ok(resources("", "0.0.0.0/0", ""))
+        );
+    }
+
+    #[test]
+    fn pools_overlap() {
+        let mut pools = ResourcePools::default();
+        pools.add_or_replace(
+            "customers".to_string(),
+            resources("", "10.0.0.0/16", ""),
+        );
+        pools.add_or_replace(
+            "infra".to_string(),
+            resources("AS65000", "10.1.0.0/16", ""),
+        );
+
+        let update = resources("", "10.0.0.0/15", "");
+        assert_eq!(
+            pools.overlapping("customers", &update),
+            Some(&"infra".to_string())
+        );
+        assert_eq!(
+            pools.overlapping("customers", &resources("", "10.0.0.0/8", "")),
+            Some(&"infra".to_string())
+        );
+        assert_eq!(
+            pools.overlapping("infra", &resources("AS65000", "", "")),
+            None
+        );
+        assert_eq!(
+            pools.all_resources(),
+            resources("AS65000", "10.0.0.0/15", "")
+        );
+
+        let held = resources("AS65000", "10.0.0.0/16, 10.1.0.0/24", "");
+        let delegated = resources("", "10.0.1.0/24, 10.1.0.0/25", "");
+        assert_eq!(
+            pools.free("customers", &held, &delegated),
+            Some(resources("", "10.0.0.0/24, 10.0.2.0-10.0.255.255", ""))
+        );
+        assert_eq!(
+            pools.free("infra", &held, &delegated),
+            Some(resources("AS65000", "10.1.0.128/25", ""))
+        );
+        assert_eq!(pools.free("unknown", &held, &delegated), None);
+
+        let info = pools.info(&resources("AS65000", "10.0.1.0/24", ""));
+        assert_eq!(info.pools().len(), 2);
+        assert_eq!(
+            info.get("customers").unwrap().allocated,
+            resources("", "10.0.1.0/24", "")
+        );
+        assert_eq!(
+            info.get("infra").unwrap().allocated,
+            resources("AS65000", "", "")
+        );
+    }
+}
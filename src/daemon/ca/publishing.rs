@@ -97,7 +97,7 @@ impl PreSaveEventListener<CertAuth> for CaObjectsStore {
         // Note that the `CertAuth` which is passed in has already been
         // updated with the state changes contained in the event.
 
-        let timing = &self.issuance_timing;
+        let timing = &ca.issuance_timing(&self.issuance_timing);
         let signer = &self.signer;
 
         self.with_ca_objects(ca.handle(), |objects| {
@@ -198,6 +198,35 @@ impl PreSaveEventListener<CertAuth> for CaObjectsStore {
                         objects.update_repo(contact);
                         force_reissue = true;
                     }
+                    super::CertAuthEvent::RepoMirrorAdded {
+                        contact, ..
+                    } => {
+                        objects.deprecated_repo_remove(contact);
+                    }
+                    super::CertAuthEvent::RepoMirrorRemoved {
+                        contact,
+                        ..
+                    } => {
+                        objects.deprecate_repo(contact.clone());
+                    }
+                    super::CertAuthEvent::RepoMigrationStarted {
+                        contact,
+                    } => {
+                        objects.deprecated_repo_remove(contact);
+                    }
+                    super::CertAuthEvent::RepoMigrationAborted {
+                        contact,
+                    } => {
+                        objects.deprecate_repo(contact.clone());
+                    }
+                    super::CertAuthEvent::Renamed {
+                        repository: Some(contact),
+                        ..
+                    } => {
+                        // The publisher was renamed in the embedded
+                        // repository, its objects did not move.
+                        objects.repo = Some(contact.clone());
+                    }
                     _ => {}
                 }
             }
@@ -245,6 +274,28 @@ impl CaObjectsStore {
             .map_err(Error::KeyValueError)
     }
 
+    /// Moves the objects of a CA to a new handle.
+    pub fn rename_ca(
+        &self,
+        from: &CaHandle,
+        to: &CaHandle,
+    ) -> KrillResult<()> {
+        let from_key = Self::key(from);
+        let to_key = Self::key(to);
+        self.store
+            .execute(&Scope::global(), |kv| {
+                if let Some(value) = kv.get(&from_key)? {
+                    let mut objects: CaObjects =
+                        serde_json::from_value(value)?;
+                    objects.ca = to.clone();
+                    kv.store(&to_key, serde_json::to_value(&objects)?)?;
+                    kv.delete(&from_key)?;
+                }
+                Ok(())
+            })
+            .map_err(Error::KeyValueError)
+    }
+
     /// Get objects for this CA, create a new empty CaObjects if there is
     /// none.
     pub fn ca_objects(&self, ca: &CaHandle) -> KrillResult<CaObjects> {
@@ -295,21 +346,19 @@ impl CaObjectsStore {
             .map_err(Error::KeyValueError)?
     }
 
-    // Re-issue MFT and CRL for all CAs *if needed*, returns all CAs which
-    // were updated.
+    // Re-issue MFT and CRL for a CA *if needed*, using the issuance timing
+    // for the CA. Returns whether anything was re-issued.
     pub fn reissue_if_needed(
         &self,
         force: bool,
-        ca_handle: &CaHandle,
+        ca: &CertAuth,
     ) -> KrillResult<bool> {
+        let ca_handle = ca.handle();
         debug!("Re-issue for CA {} using force: {}", ca_handle, force);
+        let timing = ca.issuance_timing(&self.issuance_timing);
         let mut re_issued = false;
         self.with_ca_objects(ca_handle, |objects| {
-            re_issued = objects.re_issue(
-                force,
-                &self.issuance_timing,
-                &self.signer,
-            )?;
+            re_issued = objects.re_issue(force, &timing, &self.signer)?;
             Ok(())
         })?;
         Ok(re_issued)
@@ -612,6 +661,15 @@ impl CaObjects {
                 .push(DeprecatedRepository::new(old_repo, 0));
         }
     }
+
+    // Marks a removed mirror repository, or a repository which a CA was
+    // migrating to, as deprecated so that its objects will be withdrawn.
+    fn deprecate_repo(&mut self, repo: RepositoryContact) {
+        if !self.deprecated_repos.iter().any(|d| d.contact() == &repo) {
+            self.deprecated_repos
+                .push(DeprecatedRepository::new(repo, 0));
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
 
@@ -20,7 +22,8 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            IssuedCertificate, ReceivedCert, ResourceClassInfo,
+            ChildCertTiming, IssuedCertificate, KeyRollPhase, KeyRollPolicy,
+            PlannedKeyRoll, ReceivedCert, ResourceClassInfo,
             RoaConfiguration, SuspendedCert, UnsuspendedCert,
         },
         crypto::{CsrInfo, KrillSigner, SignSupport},
@@ -31,8 +34,9 @@ use crate::{
         ca::events::RoaUpdates,
         ca::{
             self, AspaObjects, AspaObjectsUpdates, CertAuthEvent,
-            CertifiedKey, ChildCertificates, CurrentKey, KeyState, NewKey,
-            OldKey, PendingKey, Roas, Routes,
+            CertifiedKey, ChildCertificates, CurrentKey, Geofeed, KeyState,
+            NewKey, OldKey, PendingKey, Roas, Routes, RpkiSignedChecklist,
+            RscChecklistItem,
         },
         config::{Config, IssuanceTimingConfig},
     },
@@ -135,6 +139,11 @@ impl ResourceClass {
         &self.parent_handle
     }
 
+    /// Sets a new name for the parent, used when the parent is renamed.
+    pub fn set_parent_handle(&mut self, parent_handle: ParentHandle) {
+        self.parent_handle = parent_handle;
+    }
+
     /// Returns the name that the parent uses for this RC.
     pub fn parent_rc_name(&self) -> &ResourceClassName {
         &self.parent_rc_name
@@ -178,6 +187,12 @@ impl ResourceClass {
         matches!(&self.key_state, KeyState::Active(_))
     }
 
+    /// Returns true if a key roll was initiated, but the parent did not
+    /// yet certify the new key.
+    pub fn new_key_pending(&self) -> bool {
+        matches!(&self.key_state, KeyState::RollPending(_, _))
+    }
+
     /// Gets the new key for a key roll, or returns an error if there is none.
     pub fn get_new_key(&self) -> KrillResult<&NewKey> {
         if let KeyState::RollNew(new_key, _) = &self.key_state {
@@ -217,6 +232,7 @@ impl ResourceClass {
         all_aspas: &AspaDefinitions,
         all_bgpsecs: &BgpSecDefinitions,
         config: &Config,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         // If this is for a pending key, then we need to promote this key
@@ -297,6 +313,7 @@ impl ResourceClass {
                 all_aspas,
                 all_bgpsecs,
                 config,
+                child_timings,
                 signer,
             ),
             KeyState::RollPending(pending, current) => {
@@ -315,6 +332,7 @@ impl ResourceClass {
                         all_aspas,
                         all_bgpsecs,
                         config,
+                        child_timings,
                         signer,
                     )
                 }
@@ -335,6 +353,7 @@ impl ResourceClass {
                         all_aspas,
                         all_bgpsecs,
                         config,
+                        child_timings,
                         signer,
                     )
                 }
@@ -349,6 +368,7 @@ impl ResourceClass {
                     all_aspas,
                     all_bgpsecs,
                     config,
+                    child_timings,
                     signer,
                 )
             }
@@ -365,6 +385,7 @@ impl ResourceClass {
         all_aspas: &AspaDefinitions,
         all_bgpsecs: &BgpSecDefinitions,
         config: &Config,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         let ki = rcvd_cert.key_identifier();
@@ -399,6 +420,7 @@ impl ResourceClass {
             let updates = self.certificates.shrink_overclaiming(
                 updated_key.incoming_cert(),
                 &config.issuance_timing,
+                child_timings,
                 signer,
             )?;
             if !updates.is_empty() {
@@ -593,6 +615,7 @@ impl ResourceClass {
         match &self.key_state {
             KeyState::RollPending(_pending, current) => {
                 self.key_state = KeyState::RollNew(new, current.clone());
+                self.last_key_change = Time::now();
             }
             _ => panic!(
                 "Cannot move pending to new, if state is not roll pending"
@@ -605,6 +628,7 @@ impl ResourceClass {
         match &self.key_state {
             KeyState::Pending(_pending) => {
                 self.key_state = KeyState::Active(new);
+                self.last_key_change = Time::now();
             }
             _ => panic!(
                 "Cannot move pending to active, if state is not pending"
@@ -618,6 +642,7 @@ impl ResourceClass {
             KeyState::RollNew(new, current) => {
                 let old_key = OldKey::new(current.clone(), revoke_req);
                 self.key_state = KeyState::RollOld(new.clone(), old_key);
+                self.last_key_change = Time::now();
             }
             _ => panic!("Should never create event to activate key when no roll in progress"),
         }
@@ -657,10 +682,14 @@ impl ResourceClass {
     }
 
     /// Activate a new key, if it's been longer than the staging period.
+    ///
+    /// Child certificates are re-issued under the new key, using the
+    /// timing overrides for the children holding the certified keys.
     pub fn keyroll_activate(
         &self,
         staging_time: Duration,
         issuance_timing: &IssuanceTimingConfig,
+        child_timings: &HashMap<KeyIdentifier, ChildCertTiming>,
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
         if let Some(new_key) = self.key_state.new_key() {
@@ -708,6 +737,7 @@ impl ResourceClass {
                 let cert_updates = self.certificates.activate_key(
                     new_key.incoming_cert(),
                     issuance_timing,
+                    child_timings,
                     signer,
                 )?;
                 if !cert_updates.is_empty() {
@@ -739,6 +769,32 @@ impl ResourceClass {
         }
     }
 
+    /// Returns the next key roll phase for this resource class according
+    /// to the given policy, if any. There is none while a new key waits
+    /// for its certificate, or an old key for its revocation.
+    pub fn planned_key_roll(
+        &self,
+        policy: &KeyRollPolicy,
+    ) -> Option<PlannedKeyRoll> {
+        let (phase, due) = match &self.key_state {
+            KeyState::Active(_) => (
+                KeyRollPhase::Initiate,
+                policy.roll_due(self.last_key_change),
+            ),
+            KeyState::RollNew(_, _) => (
+                KeyRollPhase::Activate,
+                policy.activate_due(self.last_key_change),
+            ),
+            _ => return None,
+        };
+
+        Some(PlannedKeyRoll {
+            resource_class_name: self.name.clone(),
+            phase,
+            due: due.into(),
+        })
+    }
+
     /// Finish a key roll, withdraw the old key
     pub fn keyroll_finish(&self) -> KrillResult<CertAuthEvent> {
         match &self.key_state {
@@ -994,4 +1050,48 @@ impl ResourceClass {
 
         Ok(ee)
     }
+
+    /// Signs an RPKI Signed Checklist under the current key, returns the
+    /// RSC and its EE certificate.
+    pub fn create_rsc(
+        &self,
+        resources: &ResourceSet,
+        validity: Validity,
+        checklist: &[RscChecklistItem],
+        signer: &KrillSigner,
+    ) -> KrillResult<(RpkiSignedChecklist, Cert)> {
+        let current = self.current_key().ok_or_else(|| {
+            Error::custom("No current key to sign RSC with")
+        })?;
+
+        if !current.incoming_cert().resources().contains(resources) {
+            return Err(Error::RscResourcesNotHeld);
+        }
+
+        RpkiSignedChecklist::sign(
+            resources, validity, checklist, current, signer,
+        )
+    }
+
+    /// Signs a geofeed under the current key, returns the geofeed with its
+    /// signature and the EE certificate.
+    pub fn sign_geofeed(
+        &self,
+        geofeed: &Geofeed,
+        validity: Validity,
+        signer: &KrillSigner,
+    ) -> KrillResult<(String, Cert)> {
+        let current = self.current_key().ok_or_else(|| {
+            Error::custom("No current key to sign geofeed with")
+        })?;
+
+        if !current.incoming_cert().resources().contains(&geofeed.resources())
+        {
+            return Err(Error::GeofeedPrefixesNotHeld(Geofeed::prefixes(
+                &geofeed.not_held(current.incoming_cert().resources()),
+            )));
+        }
+
+        geofeed.sign(validity, current, signer)
+    }
 }
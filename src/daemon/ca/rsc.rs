@@ -0,0 +1,434 @@
+//! Support for RPKI Signed Checklists (RSC).
+//!
+//! See: https://www.rfc-editor.org/rfc/rfc9323.html
+use std::{collections::HashMap, fmt};
+
+use bcder::{
+    encode::{self, Values},
+    Captured, Mode, OctetString, Oid, Tag,
+};
+use bytes::Bytes;
+
+use rpki::{
+    ca::{provisioning::ResourceClassName, publication::Base64},
+    crypto::DigestAlgorithm,
+    repository::{
+        resources::{AddressFamily, ResourceSet},
+        x509::Validity,
+        Cert,
+    },
+    rrdp::Hash,
+};
+
+use crate::{
+    commons::{
+        api::{Revocation, RscList, RscName},
+        crypto::{KrillSigner, SignSupport},
+        error::Error,
+        util::ext_serde,
+        KrillResult,
+    },
+    daemon::ca::CertifiedKey,
+};
+
+/// The content type for RPKI Signed Checklists: 1.2.840.113549.1.9.16.1.48
+const CT_RPKI_SIGNED_CHECKLIST: Oid<&[u8]> =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48]);
+
+//------------ Rscs ---------------------------------------------------------
+
+/// The RPKI Signed Checklists issued by a CA, by their local name.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Rscs {
+    map: HashMap<RscName, SignedRsc>,
+}
+
+impl Rscs {
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn list(&self) -> RscList {
+        let mut names: Vec<RscName> = self.map.keys().cloned().collect();
+        names.sort();
+        RscList::new(names)
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.map.contains_key(name)
+    }
+
+    pub fn signed_rsc(&self, name: &str) -> KrillResult<SignedRsc> {
+        self.map
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::RscUnknown(name.to_string()))
+    }
+
+    pub fn add(&mut self, name: RscName, signed: SignedRsc) {
+        self.map.insert(name, signed);
+    }
+}
+
+//------------ RscChecklistItem ---------------------------------------------
+
+/// A single entry on a checklist: the SHA-256 digest of some file, and
+/// optionally the name of that file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscChecklistItem {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    file_name: Option<String>,
+    hash: Hash,
+}
+
+impl RscChecklistItem {
+    pub fn new(file_name: Option<String>, hash: Hash) -> Self {
+        RscChecklistItem { file_name, hash }
+    }
+
+    /// Creates an item for the given file name and file content.
+    pub fn for_content(file_name: Option<String>, content: &[u8]) -> Self {
+        RscChecklistItem {
+            file_name,
+            hash: Hash::from_data(content),
+        }
+    }
+
+    pub fn file_name(&self) -> Option<&String> {
+        self.file_name.as_ref()
+    }
+
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    /// File names must be a "PortableFilename" as defined in RFC 9323,
+    /// i.e. they may only use the characters a-z, A-Z, 0-9, '.', '_' and
+    /// '-'.
+    fn verify(&self) -> KrillResult<()> {
+        if let Some(name) = &self.file_name {
+            if name.is_empty()
+                || !name.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || c == '.'
+                        || c == '_'
+                        || c == '-'
+                })
+            {
+                return Err(Error::RscInvalidChecklist(format!(
+                    "file name '{}' is not a portable file name",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_ref(&self) -> impl encode::Values + '_ {
+        encode::sequence((
+            self.file_name.as_ref().map(|name| {
+                OctetString::encode_slice_as(name.as_bytes(), Tag::IA5_STRING)
+            }),
+            OctetString::encode_slice(self.hash.as_ref()),
+        ))
+    }
+}
+
+impl fmt::Display for RscChecklistItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file_name {
+            Some(name) => write!(f, "{}  {}", self.hash, name),
+            None => write!(f, "{}", self.hash),
+        }
+    }
+}
+
+//------------ RscSignRequest -----------------------------------------------
+
+/// A request to sign a checklist for the given resources.
+///
+/// When the resources span multiple CAs then the same request can be
+/// co-signed by each of these CAs. RFC 9323 allows only one signer per
+/// object, so every CA then signs its own RSC object over the part of the
+/// resources that it holds.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscSignRequest {
+    resources: ResourceSet,
+    validity: Validity,
+    checklist: Vec<RscChecklistItem>,
+}
+
+impl RscSignRequest {
+    pub fn new(
+        resources: ResourceSet,
+        validity: Validity,
+        checklist: Vec<RscChecklistItem>,
+    ) -> Self {
+        RscSignRequest {
+            resources,
+            validity,
+            checklist,
+        }
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    pub fn checklist(&self) -> &Vec<RscChecklistItem> {
+        &self.checklist
+    }
+
+    /// Verifies that this request can be turned into valid RSC objects.
+    pub fn verify(&self) -> KrillResult<()> {
+        if self.resources.is_empty() {
+            return Err(Error::RscInvalidChecklist(
+                "no resources were specified".to_string(),
+            ));
+        }
+        if self.checklist.is_empty() {
+            return Err(Error::RscInvalidChecklist(
+                "the checklist is empty".to_string(),
+            ));
+        }
+        for item in &self.checklist {
+            item.verify()?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RscSignRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "resources: {}", &self.resources)?;
+        writeln!(
+            f,
+            "validity, {}-{}",
+            self.validity.not_before().to_rfc3339(),
+            self.validity.not_after().to_rfc3339()
+        )?;
+        writeln!(f, "checklist:")?;
+        for item in &self.checklist {
+            writeln!(f, "  {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ SignedRsc ----------------------------------------------------
+
+/// The RSC objects signed by a CA for a single checklist.
+///
+/// There will be one object for each resource class that holds part of the
+/// resources. Note that the resources may be a subset of the requested
+/// resources if this CA co-signed a checklist with other CAs.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedRsc {
+    resources: ResourceSet,
+    checklist: Vec<RscChecklistItem>,
+    objects: Vec<RscObject>,
+}
+
+impl SignedRsc {
+    pub fn new(
+        resources: ResourceSet,
+        checklist: Vec<RscChecklistItem>,
+        objects: Vec<RscObject>,
+    ) -> Self {
+        SignedRsc {
+            resources,
+            checklist,
+            objects,
+        }
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    pub fn checklist(&self) -> &Vec<RscChecklistItem> {
+        &self.checklist
+    }
+
+    pub fn objects(&self) -> &Vec<RscObject> {
+        &self.objects
+    }
+}
+
+impl fmt::Display for SignedRsc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "resources: {}", self.resources)?;
+        writeln!(f, "checklist:")?;
+        for item in &self.checklist {
+            writeln!(f, "  {}", item)?;
+        }
+        writeln!(f, "objects:")?;
+        for object in &self.objects {
+            writeln!(
+                f,
+                "  resource class '{}', resources: {}",
+                object.resource_class_name, object.resources
+            )?;
+            writeln!(f, "  {}", object.rsc)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ RscObject ----------------------------------------------------
+
+/// A single RSC object signed under the key of a resource class.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscObject {
+    resource_class_name: ResourceClassName,
+    resources: ResourceSet,
+    revocation: Revocation,
+    rsc: RpkiSignedChecklist,
+}
+
+impl RscObject {
+    pub fn new(
+        resource_class_name: ResourceClassName,
+        resources: ResourceSet,
+        revocation: Revocation,
+        rsc: RpkiSignedChecklist,
+    ) -> Self {
+        RscObject {
+            resource_class_name,
+            resources,
+            revocation,
+            rsc,
+        }
+    }
+
+    pub fn resource_class_name(&self) -> &ResourceClassName {
+        &self.resource_class_name
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    pub fn rsc(&self) -> &RpkiSignedChecklist {
+        &self.rsc
+    }
+}
+
+//------------ RpkiSignedChecklist ------------------------------------------
+
+/// An encoded RPKI Signed Checklist.
+#[derive(Clone, Debug, Deserialize, Eq, Serialize, PartialEq)]
+pub struct RpkiSignedChecklist {
+    #[serde(
+        deserialize_with = "ext_serde::de_bytes",
+        serialize_with = "ext_serde::ser_bytes"
+    )]
+    bytes: Bytes,
+}
+
+impl AsRef<Bytes> for RpkiSignedChecklist {
+    fn as_ref(&self) -> &Bytes {
+        &self.bytes
+    }
+}
+
+impl RpkiSignedChecklist {
+    pub fn new(bytes: Bytes) -> Self {
+        RpkiSignedChecklist { bytes }
+    }
+
+    /// Signs a checklist over the given resources, using a one-off EE
+    /// certificate issued under the given key.
+    ///
+    /// The signed object is not made using the `SignedObjectBuilder` in
+    /// rpki-rs, because RFC 9323 forbids the Subject Information Access
+    /// extension in the EE certificate.
+    pub fn sign(
+        resources: &ResourceSet,
+        validity: Validity,
+        checklist: &[RscChecklistItem],
+        signing_key: &CertifiedKey,
+        signer: &KrillSigner,
+    ) -> KrillResult<(Self, Cert)> {
+        let content = Self::encode_content(resources, checklist)?;
+        let (bytes, ee) = SignSupport::sign_one_off_cms(
+            CT_RPKI_SIGNED_CHECKLIST,
+            content.as_slice(),
+            false,
+            resources,
+            validity,
+            signing_key,
+            signer,
+        )?;
+
+        Ok((RpkiSignedChecklist { bytes }, ee))
+    }
+
+    /// Encodes the RpkiSignedChecklist content. The version is left out
+    /// because it is DEFAULT 0.
+    fn encode_content(
+        resources: &ResourceSet,
+        checklist: &[RscChecklistItem],
+    ) -> KrillResult<Captured> {
+        let asns = resources.asn();
+        let ipv4 = resources.ipv4();
+        let ipv6 = resources.ipv6();
+
+        let as_id = if asns.is_empty() {
+            None
+        } else {
+            Some(encode::sequence_as(
+                Tag::CTX_0,
+                encode::sequence(encode::sequence_as(
+                    Tag::CTX_0,
+                    encode::sequence(asns.encode_ref()),
+                )),
+            ))
+        };
+
+        let ip_addr_blocks = if ipv4.is_empty() && ipv6.is_empty() {
+            None
+        } else {
+            Some(encode::sequence_as(
+                Tag::CTX_1,
+                encode::sequence((
+                    if ipv4.is_empty() {
+                        None
+                    } else {
+                        Some(ipv4.encode_family(AddressFamily::Ipv4))
+                    },
+                    if ipv6.is_empty() {
+                        None
+                    } else {
+                        Some(ipv6.encode_family(AddressFamily::Ipv6))
+                    },
+                )),
+            ))
+        };
+
+        if as_id.is_none() && ip_addr_blocks.is_none() {
+            return Err(Error::RscInvalidChecklist(
+                "no resources were specified".to_string(),
+            ));
+        }
+
+        Ok(encode::sequence((
+            encode::sequence((as_id, ip_addr_blocks)),
+            DigestAlgorithm::default().encode(),
+            encode::sequence(encode::iter(
+                checklist.iter().map(|item| item.encode_ref()),
+            )),
+        ))
+        .to_captured(Mode::Der))
+    }
+}
+
+impl fmt::Display for RpkiSignedChecklist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Base64::from_content(self.bytes.as_ref()))
+    }
+}
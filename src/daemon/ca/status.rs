@@ -65,6 +65,23 @@ impl CaStatus {
     }
 }
 
+//------------ RepoStatusTarget ----------------------------------------------
+
+/// Identifies the status which is updated with the result of an exchange
+/// with a repository.
+#[derive(Clone, Copy, Debug)]
+pub enum RepoStatusTarget<'a> {
+    /// The primary repository of the CA.
+    Primary,
+
+    /// A mirror of the primary repository.
+    Mirror(&'a str),
+
+    /// A repository for which no status is kept, e.g. a removed mirror
+    /// which is being cleaned up.
+    Untracked,
+}
+
 //------------ StatusStore ---------------------------------------------------
 
 pub struct StatusStore {
@@ -99,6 +116,15 @@ impl StatusStore {
         Ok(())
     }
 
+    /// Drops the cached status and loads it again from disk.
+    ///
+    /// To be used when another node may have changed the stored status,
+    /// e.g. when this node takes over as leader.
+    pub fn reload(&self) -> KrillResult<()> {
+        self.cache.write().unwrap().clear();
+        self.warm()
+    }
+
     /// Load current status from disk, to be used when starting up. If there
     /// are any issues parsing data then default values are used - this
     /// data is not critical so any missing, corrupted, or no longer
@@ -376,6 +402,52 @@ impl StatusStore {
         Ok(())
     }
 
+    /// Moves the saved status of a CA to a new handle.
+    pub fn rename_ca(
+        &self,
+        from: &CaHandle,
+        to: &CaHandle,
+    ) -> KrillResult<()> {
+        let mut cache = self.cache.write().unwrap();
+
+        let from_scope =
+            Scope::from_segment(Segment::parse_lossy(from.as_str()));
+        let to_scope = Scope::from_segment(Segment::parse_lossy(to.as_str()));
+        self.store.execute(&from_scope, |kv| {
+            if kv.has_scope(&from_scope)? {
+                kv.move_scope(&from_scope, &to_scope)
+            } else {
+                Ok(())
+            }
+        })?;
+
+        if let Some(status) = cache.remove(from) {
+            cache.insert(to.clone(), status);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the saved status of a child of a CA to a new child handle.
+    pub fn rename_child(
+        &self,
+        ca: &CaHandle,
+        from: &ChildHandle,
+        to: &ChildHandle,
+    ) -> KrillResult<()> {
+        let mut cache = self.cache.write().unwrap();
+
+        if let Some(ca_status) = cache.get_mut(ca) {
+            if let Some(status) = ca_status.children.remove(from) {
+                self.store.store(&Self::child_status_key(ca, to), &status)?;
+                self.store.drop_key(&Self::child_status_key(ca, from))?;
+                ca_status.children.insert(to.clone(), status);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Removes a child for the given CA.
     pub fn remove_child(
         &self,
@@ -395,11 +467,12 @@ impl StatusStore {
     pub fn set_status_repo_failure(
         &self,
         ca: &CaHandle,
+        target: RepoStatusTarget,
         uri: ServiceUri,
         error: &Error,
     ) -> KrillResult<()> {
         let error_response = Self::error_to_error_res(error);
-        self.update_repo_status(ca, |status| {
+        self.update_repo_status(ca, target, |status| {
             status.set_failure(uri, error_response)
         })
     }
@@ -407,26 +480,58 @@ impl StatusStore {
     pub fn set_status_repo_success(
         &self,
         ca: &CaHandle,
+        target: RepoStatusTarget,
         uri: ServiceUri,
     ) -> KrillResult<()> {
-        self.update_repo_status(ca, |status| status.set_last_updated(uri))
+        self.update_repo_status(ca, target, |status| {
+            status.set_last_updated(uri)
+        })
     }
 
     pub fn set_status_repo_published(
         &self,
         ca: &CaHandle,
+        target: RepoStatusTarget,
         uri: ServiceUri,
         delta: PublishDelta,
     ) -> KrillResult<()> {
-        self.update_repo_status(ca, |status| {
+        self.update_repo_status(ca, target, |status| {
             status.update_published(uri, delta)
         })
     }
 
-    fn update_repo_status<F>(&self, ca: &CaHandle, op: F) -> KrillResult<()>
+    /// Removes the status of a repository mirror for the given CA.
+    pub fn remove_status_repo_mirror(
+        &self,
+        ca: &CaHandle,
+        mirror: &str,
+    ) -> KrillResult<()> {
+        let mut cache = self.cache.write().unwrap();
+
+        if let Some(ca_status) = cache.get_mut(ca) {
+            ca_status.repo.remove_mirror(mirror);
+            self.store
+                .store(&Self::repo_status_key(ca), ca_status.repo())?;
+        }
+
+        Ok(())
+    }
+
+    fn update_repo_status<F>(
+        &self,
+        ca: &CaHandle,
+        target: RepoStatusTarget,
+        op: F,
+    ) -> KrillResult<()>
     where
         F: FnOnce(&mut RepoStatus),
     {
+        let status = match target {
+            RepoStatusTarget::Primary => None,
+            RepoStatusTarget::Mirror(name) => Some(name),
+            RepoStatusTarget::Untracked => return Ok(()),
+        };
+
         let mut cache = self.cache.write().unwrap();
 
         if !cache.contains_key(ca) {
@@ -434,7 +539,10 @@ impl StatusStore {
         }
 
         let ca_status = cache.get_mut(ca).unwrap(); // safe, we just set it if missing
-        op(&mut ca_status.repo);
+        match status {
+            None => op(&mut ca_status.repo),
+            Some(mirror) => op(ca_status.repo.mirror_mut(mirror)),
+        }
 
         self.store
             .store(&Self::repo_status_key(ca), ca_status.repo())?;
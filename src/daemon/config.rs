@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     env, fmt,
     fs::File,
     io::{self, Read},
@@ -23,17 +24,22 @@ use syslog::Facility;
 
 use crate::{
     commons::{
-        api::{PublicationServerUris, Token},
+        api::{
+            CaIssuanceTiming, ChildCertTiming, HistoryRetention,
+            KeyRollPolicy, PublicationServerUris, Token,
+        },
         crypto::{OpenSslSignerConfig, SignSupport},
         error::{Error, KrillIoError},
-        eventsourcing::KeyValueStore,
+        eventsourcing::{
+            register_storage_keys, KeyValueStore, StorageKey, StorageKeys,
+        },
         util::ext_serde,
         KrillResult,
     },
     constants::*,
     daemon::{
         http::tls_keys::{self, HTTPS_SUB_DIR},
-        mq::{in_seconds, Priority},
+        mq::{in_seconds, Priority, Task, TaskRetryPolicy},
     },
     ta::TaTimingConfig,
 };
@@ -56,6 +62,32 @@ impl ConfigDefaults {
         true
     }
 
+    fn task_retry_policy(kind: &str) -> TaskRetryPolicy {
+        if kind == "rrdp_update_if_needed" {
+            // Local issues, such as a full disk, take a while to resolve.
+            TaskRetryPolicy {
+                initial_delay_seconds: 3600,
+                multiplier: 1.0,
+                max_delay_seconds: 3600,
+                max_attempts: None,
+            }
+        } else if test_mode_enabled() {
+            TaskRetryPolicy {
+                initial_delay_seconds: 5,
+                multiplier: 1.0,
+                max_delay_seconds: 5,
+                max_attempts: None,
+            }
+        } else {
+            TaskRetryPolicy {
+                initial_delay_seconds: SCHEDULER_REQUEUE_DELAY_SECONDS,
+                multiplier: 2.0,
+                max_delay_seconds: SCHEDULER_REQUEUE_MAX_DELAY_SECONDS,
+                max_attempts: None,
+            }
+        }
+    }
+
     fn ip() -> Vec<IpAddr> {
         vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]
     }
@@ -154,6 +186,14 @@ impl ConfigDefaults {
         25
     }
 
+    fn scheduler_workers() -> usize {
+        4
+    }
+
+    fn key_roll_staging_hours() -> u32 {
+        KeyRollPolicy::MIN_STAGING_HOURS
+    }
+
     fn post_limit_api() -> u64 {
         256 * 1024 // 256kB
     }
@@ -179,6 +219,14 @@ impl ConfigDefaults {
         240 // 4 minutes by default should be plenty in most cases
     }
 
+    fn ha_lease_seconds() -> u64 {
+        30
+    }
+
+    fn ha_heartbeat_seconds() -> u64 {
+        10
+    }
+
     fn bgp_risdumps_enabled() -> bool {
         true
     }
@@ -509,6 +557,15 @@ pub struct Config {
     #[serde(default = "ConfigDefaults::dflt_true")]
     pub use_history_cache: bool,
 
+    history_retention_versions: Option<u64>,
+    history_retention_days: Option<u32>,
+
+    storage_encryption_key_file: Option<PathBuf>,
+    #[serde(default)]
+    storage_encryption_previous_key_files: Vec<PathBuf>,
+    #[serde(default)]
+    storage_encryption_allow_plaintext: bool,
+
     tls_keys_dir: Option<PathBuf>,
 
     repo_dir: Option<PathBuf>,
@@ -592,10 +649,19 @@ pub struct Config {
     #[serde(default = "ConfigDefaults::ca_refresh_parents_batch_size")]
     pub ca_refresh_parents_batch_size: usize,
 
+    #[serde(default = "ConfigDefaults::scheduler_workers")]
+    pub scheduler_workers: usize,
+
     #[serde(skip)]
     suspend_child_after_inactive_seconds: Option<u32>,
     suspend_child_after_inactive_hours: Option<u32>,
 
+    #[serde(default)]
+    key_roll_months: u32,
+
+    #[serde(default = "ConfigDefaults::key_roll_staging_hours")]
+    key_roll_staging_hours: u32,
+
     #[serde(default = "ConfigDefaults::post_limit_api")]
     pub post_limit_api: u64,
 
@@ -642,8 +708,23 @@ pub struct Config {
 
     pub benchmark: Option<Benchmark>,
 
+    pub ha: Option<HaConfig>,
+
     #[serde(default)]
     pub ta_timing: TaTimingConfig,
+
+    #[serde(default)]
+    task_retry_policies: HashMap<String, TaskRetryConfig>,
+}
+
+/// Overrides the default retry policy for a kind of task. Values which are
+/// not set are taken from the default policy for that kind of task.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TaskRetryConfig {
+    pub initial_delay_seconds: Option<u64>,
+    pub multiplier: Option<f64>,
+    pub max_delay_seconds: Option<u64>,
+    pub max_attempts: Option<u32>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -677,6 +758,136 @@ pub struct IssuanceTimingConfig {
 }
 
 impl IssuanceTimingConfig {
+    /// Returns a copy of this timing, using the values from the given CA
+    /// specific overrides where they are set.
+    pub fn for_ca(&self, overrides: &CaIssuanceTiming) -> Self {
+        let mut timing = self.clone();
+
+        let fields = [
+            (
+                &mut timing.timing_publish_next_hours,
+                overrides.timing_publish_next_hours,
+            ),
+            (
+                &mut timing.timing_publish_next_jitter_hours,
+                overrides.timing_publish_next_jitter_hours,
+            ),
+            (
+                &mut timing.timing_publish_hours_before_next,
+                overrides.timing_publish_hours_before_next,
+            ),
+            (
+                &mut timing.timing_child_certificate_valid_weeks,
+                overrides.timing_child_certificate_valid_weeks,
+            ),
+            (
+                &mut timing.timing_child_certificate_reissue_weeks_before,
+                overrides.timing_child_certificate_reissue_weeks_before,
+            ),
+            (
+                &mut timing.timing_roa_valid_weeks,
+                overrides.timing_roa_valid_weeks,
+            ),
+            (
+                &mut timing.timing_roa_reissue_weeks_before,
+                overrides.timing_roa_reissue_weeks_before,
+            ),
+            (
+                &mut timing.timing_aspa_valid_weeks,
+                overrides.timing_aspa_valid_weeks,
+            ),
+            (
+                &mut timing.timing_aspa_reissue_weeks_before,
+                overrides.timing_aspa_reissue_weeks_before,
+            ),
+            (
+                &mut timing.timing_bgpsec_valid_weeks,
+                overrides.timing_bgpsec_valid_weeks,
+            ),
+            (
+                &mut timing.timing_bgpsec_reissue_weeks_before,
+                overrides.timing_bgpsec_reissue_weeks_before,
+            ),
+        ];
+
+        for (field, value) in fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+
+        timing
+    }
+
+    /// Verifies that objects would be valid long enough, and would be
+    /// re-issued before they expire.
+    pub fn verify(&self) -> Result<(), &'static str> {
+        if self.timing_publish_next_hours < 2 {
+            return Err("timing_publish_next_hours must be at least 2");
+        }
+
+        if self.timing_publish_next_jitter_hours
+            > (self.timing_publish_next_hours / 2)
+        {
+            return Err("timing_publish_next_jitter_hours must be at most timing_publish_next_hours divided by 2");
+        }
+
+        if self.timing_publish_hours_before_next < 1 {
+            return Err(
+                "timing_publish_hours_before_next must be at least 1",
+            );
+        }
+
+        if self.timing_publish_hours_before_next
+            >= self.timing_publish_next_hours
+        {
+            return Err("timing_publish_hours_before_next must be smaller than timing_publish_hours");
+        }
+
+        if self.timing_child_certificate_valid_weeks < 2 {
+            return Err(
+                "timing_child_certificate_valid_weeks must be at least 2",
+            );
+        }
+
+        if self.timing_child_certificate_reissue_weeks_before < 1 {
+            return Err("timing_child_certificate_reissue_weeks_before must be at least 1");
+        }
+
+        if self.timing_child_certificate_reissue_weeks_before
+            >= self.timing_child_certificate_valid_weeks
+        {
+            return Err("timing_child_certificate_reissue_weeks_before must be smaller than timing_child_certificate_valid_weeks");
+        }
+
+        if self.timing_roa_valid_weeks < 2 {
+            return Err("timing_roa_valid_weeks must be at least 2");
+        }
+
+        if self.timing_roa_reissue_weeks_before < 1 {
+            return Err("timing_roa_reissue_weeks_before must be at least 1");
+        }
+
+        if self.timing_roa_reissue_weeks_before >= self.timing_roa_valid_weeks
+        {
+            return Err("timing_roa_reissue_weeks_before must be smaller than timing_roa_valid_week");
+        }
+
+        if self.timing_aspa_reissue_weeks_before
+            >= self.timing_aspa_valid_weeks
+        {
+            return Err("timing_aspa_reissue_weeks_before must be smaller than timing_aspa_valid_weeks");
+        }
+
+        if self.timing_bgpsec_reissue_weeks_before
+            >= self.timing_bgpsec_valid_weeks
+        {
+            return Err("timing_bgpsec_reissue_weeks_before must be smaller than timing_bgpsec_valid_weeks");
+        }
+
+        Ok(())
+    }
+
     //-- Publishing Manifests and CRLs
 
     /// Returns the next update time based on configuration:
@@ -730,6 +941,43 @@ impl IssuanceTimingConfig {
             )
     }
 
+    /// Returns a copy of this timing, using the child certificate validity
+    /// and reissue threshold from the given overrides where they are set.
+    pub fn for_child(&self, overrides: &ChildCertTiming) -> Self {
+        let mut timing = self.clone();
+        if let Some(weeks) = overrides.valid_weeks {
+            timing.timing_child_certificate_valid_weeks = weeks;
+        }
+        if let Some(weeks) = overrides.reissue_weeks_before {
+            timing.timing_child_certificate_reissue_weeks_before = weeks;
+        }
+        timing
+    }
+
+    /// Verifies that certificates issued to a child with the given
+    /// overrides would be valid long enough, and would be re-issued
+    /// before they expire. Values which are not overridden are taken
+    /// from this timing.
+    pub fn verify_child_overrides(
+        &self,
+        overrides: &ChildCertTiming,
+    ) -> Result<(), &'static str> {
+        let timing = self.for_child(overrides);
+        let valid_weeks = timing.timing_child_certificate_valid_weeks;
+        let reissue_weeks =
+            timing.timing_child_certificate_reissue_weeks_before;
+
+        if valid_weeks < 2 {
+            Err("validity must be at least 2 weeks")
+        } else if reissue_weeks < 1 {
+            Err("re-issue time must be at least 1 week before expiry")
+        } else if reissue_weeks >= valid_weeks {
+            Err("re-issue time must be shorter than validity")
+        } else {
+            Ok(())
+        }
+    }
+
     //-- ROAs
 
     /// Validity period for new ROA objects
@@ -904,6 +1152,20 @@ pub struct Benchmark {
     pub ca_roas: usize,
 }
 
+/// Enables leader election between Krill nodes that share the same
+/// storage. See [`LeaderElection`](crate::daemon::ha::LeaderElection).
+#[derive(Clone, Debug, Deserialize)]
+pub struct HaConfig {
+    /// The unique id of this node. A random id is used if this is not set.
+    pub node_id: Option<String>,
+
+    #[serde(default = "ConfigDefaults::ha_lease_seconds")]
+    pub lease_seconds: u64,
+
+    #[serde(default = "ConfigDefaults::ha_heartbeat_seconds")]
+    pub heartbeat_seconds: u64,
+}
+
 /// # Accessors
 impl Config {
     /// General purpose KV store, can be used to track server settings
@@ -1022,6 +1284,96 @@ impl Config {
         self.ta_signer_enabled || self.testbed.is_some()
     }
 
+    /// Returns the retention policy used when the command history of CAs
+    /// is compacted, if configured.
+    pub fn history_retention(&self) -> Option<HistoryRetention> {
+        let retention = HistoryRetention::new(
+            self.history_retention_versions,
+            self.history_retention_days,
+        );
+        if retention.is_empty() {
+            None
+        } else {
+            Some(retention)
+        }
+    }
+
+    /// Returns the key roll policy for CAs which do not have their own.
+    pub fn key_roll_policy(&self) -> KeyRollPolicy {
+        KeyRollPolicy::new(self.key_roll_months, self.key_roll_staging_hours)
+    }
+
+    /// Registers the keys used to encrypt data in the storage, if
+    /// configured.
+    ///
+    /// The current key is taken from the environment variable, or else
+    /// from 'storage_encryption_key_file'. Previous keys are only used to
+    /// read data that was not yet re-encrypted with the current key. Data
+    /// that is not encrypted can only be read if
+    /// 'storage_encryption_allow_plaintext' is set.
+    pub fn init_storage_encryption(&self) -> Result<(), ConfigError> {
+        let current = match env::var(KRILL_ENV_STORAGE_ENCRYPTION_KEY) {
+            Ok(key) => Some(StorageKey::from_base64(&key).map_err(|e| {
+                ConfigError::Other(format!(
+                    "Invalid storage encryption key in env var {}: {}",
+                    KRILL_ENV_STORAGE_ENCRYPTION_KEY, e
+                ))
+            })?),
+            Err(_) => self
+                .storage_encryption_key_file
+                .as_ref()
+                .map(|path| Self::read_storage_key(path))
+                .transpose()?,
+        };
+
+        let previous = self
+            .storage_encryption_previous_key_files
+            .iter()
+            .map(|path| Self::read_storage_key(path))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match current {
+            Some(current) => {
+                info!("Using storage encryption key '{}'", current.id());
+                if self.storage_encryption_allow_plaintext {
+                    warn!("Unencrypted data in the storage will be accepted, unset 'storage_encryption_allow_plaintext' after running 'krillup reencrypt'");
+                }
+                register_storage_keys(
+                    &self.storage_uri,
+                    StorageKeys::new(
+                        current,
+                        previous,
+                        self.storage_encryption_allow_plaintext,
+                    ),
+                );
+                Ok(())
+            }
+            None if !previous.is_empty() => Err(ConfigError::other(
+                "'storage_encryption_previous_key_files' is set, but there is no current storage encryption key",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn read_storage_key(path: &Path) -> Result<StorageKey, ConfigError> {
+        let key = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::IoError(KrillIoError::new(
+                format!(
+                    "Cannot read storage encryption key file '{}'",
+                    path.display()
+                ),
+                e,
+            ))
+        })?;
+        StorageKey::from_base64(&key).map_err(|e| {
+            ConfigError::Other(format!(
+                "Invalid storage encryption key in file '{}': {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
     pub fn suspend_child_after_inactive_seconds(&self) -> Option<i64> {
         match self.suspend_child_after_inactive_seconds {
             Some(seconds) => Some(seconds.into()),
@@ -1031,12 +1383,26 @@ impl Config {
         }
     }
 
-    pub fn requeue_remote_failed(&self) -> Priority {
-        if test_mode_enabled() {
-            in_seconds(5)
-        } else {
-            in_seconds(SCHEDULER_REQUEUE_DELAY_SECONDS)
+    /// Returns the retry policy for failed tasks of the given kind. See
+    /// [`Task::kind`].
+    pub fn task_retry_policy(&self, kind: &str) -> TaskRetryPolicy {
+        let mut policy = ConfigDefaults::task_retry_policy(kind);
+        if let Some(config) = self.task_retry_policies.get(kind) {
+            if let Some(initial_delay_seconds) = config.initial_delay_seconds
+            {
+                policy.initial_delay_seconds = initial_delay_seconds;
+            }
+            if let Some(multiplier) = config.multiplier {
+                policy.multiplier = multiplier;
+            }
+            if let Some(max_delay_seconds) = config.max_delay_seconds {
+                policy.max_delay_seconds = max_delay_seconds;
+            }
+            if config.max_attempts.is_some() {
+                policy.max_attempts = config.max_attempts;
+            }
         }
+        policy
     }
 
     /// Get the priority for the next CA refresh based on the configured
@@ -1230,6 +1596,11 @@ impl Config {
             https_mode,
             storage_uri: storage_uri.clone(),
             use_history_cache: false,
+            history_retention_versions: None,
+            history_retention_days: None,
+            storage_encryption_key_file: None,
+            storage_encryption_previous_key_files: vec![],
+            storage_encryption_allow_plaintext: false,
             tls_keys_dir: data_dir.map(|d| d.join(HTTPS_SUB_DIR)),
             repo_dir: data_dir.map(|d| d.join(REPOSITORY_DIR)),
             ta_support_enabled: false, /* but, enabled by testbed where
@@ -1258,8 +1629,11 @@ impl Config {
             ca_refresh_seconds,
             ca_refresh_jitter_seconds,
             ca_refresh_parents_batch_size,
+            scheduler_workers: ConfigDefaults::scheduler_workers(),
             suspend_child_after_inactive_seconds,
             suspend_child_after_inactive_hours: None,
+            key_roll_months: 0,
+            key_roll_staging_hours: ConfigDefaults::key_roll_staging_hours(),
             post_limit_api,
             post_limit_rfc8181,
             rfc8181_log_dir: None,
@@ -1276,7 +1650,9 @@ impl Config {
             metrics,
             testbed,
             benchmark: None,
+            ha: None,
             ta_timing: TaTimingConfig::default(),
+            task_retry_policies: HashMap::new(),
         }
     }
 
@@ -1347,6 +1723,8 @@ impl Config {
             ))
         })?;
 
+        config.init_storage_encryption()?;
+
         Ok(config)
     }
 
@@ -1488,77 +1866,7 @@ impl Config {
             }
         }
 
-        if self.issuance_timing.timing_publish_next_hours < 2 {
-            return Err(ConfigError::other(
-                "timing_publish_next_hours must be at least 2",
-            ));
-        }
-
-        if self.issuance_timing.timing_publish_next_jitter_hours
-            > (self.issuance_timing.timing_publish_next_hours / 2)
-        {
-            return Err(ConfigError::other(
-                "timing_publish_next_jitter_hours must be at most timing_publish_next_hours divided by 2",
-            ));
-        }
-
-        if self.issuance_timing.timing_publish_hours_before_next < 1 {
-            return Err(ConfigError::other(
-                "timing_publish_hours_before_next must be at least 1",
-            ));
-        }
-
-        if self.issuance_timing.timing_publish_hours_before_next
-            >= self.issuance_timing.timing_publish_next_hours
-        {
-            return Err(ConfigError::other(
-                "timing_publish_hours_before_next must be smaller than timing_publish_hours",
-            ));
-        }
-
-        if self.issuance_timing.timing_child_certificate_valid_weeks < 2 {
-            return Err(ConfigError::other(
-                "timing_child_certificate_valid_weeks must be at least 2",
-            ));
-        }
-
-        if self
-            .issuance_timing
-            .timing_child_certificate_reissue_weeks_before
-            < 1
-        {
-            return Err(ConfigError::other(
-                "timing_child_certificate_reissue_weeks_before must be at least 1",
-            ));
-        }
-
-        if self
-            .issuance_timing
-            .timing_child_certificate_reissue_weeks_before
-            >= self.issuance_timing.timing_child_certificate_valid_weeks
-        {
-            return Err(ConfigError::other("timing_child_certificate_reissue_weeks_before must be smaller than timing_child_certificate_valid_weeks"));
-        }
-
-        if self.issuance_timing.timing_roa_valid_weeks < 2 {
-            return Err(ConfigError::other(
-                "timing_roa_valid_weeks must be at least 2",
-            ));
-        }
-
-        if self.issuance_timing.timing_roa_reissue_weeks_before < 1 {
-            return Err(ConfigError::other(
-                "timing_roa_reissue_weeks_before must be at least 1",
-            ));
-        }
-
-        if self.issuance_timing.timing_roa_reissue_weeks_before
-            >= self.issuance_timing.timing_roa_valid_weeks
-        {
-            return Err(ConfigError::other(
-                "timing_roa_reissue_weeks_before must be smaller than timing_roa_valid_week",
-            ));
-        }
+        self.issuance_timing.verify().map_err(ConfigError::other)?;
 
         if let Some(threshold) = self.suspend_child_after_inactive_hours {
             if threshold < CA_SUSPEND_MIN_HOURS {
@@ -1587,6 +1895,42 @@ impl Config {
             }
         }
 
+        if self.scheduler_workers < 1 {
+            return Err(ConfigError::other(
+                "scheduler_workers must be at least 1",
+            ));
+        }
+
+        if self.key_roll_staging_hours < KeyRollPolicy::MIN_STAGING_HOURS {
+            return Err(ConfigError::Other(format!(
+                "key_roll_staging_hours must be {} or higher",
+                KeyRollPolicy::MIN_STAGING_HOURS
+            )));
+        }
+
+        if let Some(ha) = &self.ha {
+            if self.storage_uri.scheme() == "memory" {
+                return Err(ConfigError::other(
+                    "[ha] requires storage that is shared between nodes, it cannot be used with memory storage",
+                ));
+            }
+            if ha.node_id.as_ref().is_some_and(|id| id.trim().is_empty()) {
+                return Err(ConfigError::other(
+                    "node_id in [ha] must not be empty",
+                ));
+            }
+            if ha.heartbeat_seconds < 1 {
+                return Err(ConfigError::other(
+                    "heartbeat_seconds in [ha] must be at least 1",
+                ));
+            }
+            if ha.lease_seconds < 2 * ha.heartbeat_seconds {
+                return Err(ConfigError::other(
+                    "lease_seconds in [ha] must be at least twice heartbeat_seconds",
+                ));
+            }
+        }
+
         if self.signers.is_empty() {
             // Since Config.signers defaults via Serde to
             // ConfigDefaults::signers() which creates a vector with a
@@ -1627,6 +1971,43 @@ impl Config {
             }
         }
 
+        for kind in self.task_retry_policies.keys() {
+            if !Task::RETRYABLE_KINDS.contains(&kind.as_str()) {
+                return Err(ConfigError::other(&format!(
+                    "Unsupported task '{}' in task_retry_policies, supported are: {}",
+                    kind,
+                    Task::RETRYABLE_KINDS.join(", ")
+                )));
+            }
+            let policy = self.task_retry_policy(kind);
+            if policy.initial_delay_seconds < 1 {
+                return Err(ConfigError::other(&format!(
+                    "initial_delay_seconds for task '{}' must be at least 1",
+                    kind
+                )));
+            }
+            if policy.multiplier.is_nan() || policy.multiplier < 1.0 {
+                return Err(ConfigError::other(&format!(
+                    "multiplier for task '{}' must be at least 1.0",
+                    kind
+                )));
+            }
+            if policy.max_delay_seconds < policy.initial_delay_seconds
+                || policy.max_delay_seconds > TASK_RETRY_MAX_DELAY_SECONDS
+            {
+                return Err(ConfigError::other(&format!(
+                    "max_delay_seconds for task '{}' must be between initial_delay_seconds and {}",
+                    kind, TASK_RETRY_MAX_DELAY_SECONDS
+                )));
+            }
+            if policy.max_attempts == Some(0) {
+                return Err(ConfigError::other(&format!(
+                    "max_attempts for task '{}' must be at least 1",
+                    kind
+                )));
+            }
+        }
+
         if self.signers.len() > 1 && !self.default_signer.is_set() {
             return Err(ConfigError::other(
                 "'default_signer' must be set when more than one [[signers]] configuration is defined",
@@ -2514,6 +2895,208 @@ mod tests {
         assert_err_msg(res, "Signer name 'Blah' is not unique");
     }
 
+    #[test]
+    fn task_retry_policies() {
+        let config_str = r#"
+            auth_token = "secret"
+
+            [task_retry_policies.sync_parent]
+            max_attempts = 10
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        let policy = config.task_retry_policy("sync_parent");
+        assert_eq!(policy.max_attempts, Some(10));
+        assert_eq!(
+            policy.initial_delay_seconds,
+            ConfigDefaults::task_retry_policy("sync_parent")
+                .initial_delay_seconds
+        );
+        assert_eq!(config.task_retry_policy("sync_repo").max_attempts, None);
+
+        let config_str = r#"
+            auth_token = "secret"
+
+            [task_retry_policies.compact_history]
+            max_attempts = 10
+        "#;
+
+        let res = parse_and_process_config_str(config_str);
+        assert_err_msg(
+            res,
+            "Unsupported task 'compact_history' in task_retry_policies, supported are: sync_repo, sync_parent, rrdp_update_if_needed",
+        );
+
+        let config_str = r#"
+            auth_token = "secret"
+
+            [task_retry_policies.sync_repo]
+            multiplier = 0.5
+        "#;
+
+        let res = parse_and_process_config_str(config_str);
+        assert_err_msg(
+            res,
+            "multiplier for task 'sync_repo' must be at least 1.0",
+        );
+    }
+
+    #[test]
+    fn ha_config() {
+        let config_str = r#"
+            auth_token = "secret"
+
+            [ha]
+            node_id = "krill-a"
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        let ha = config.ha.unwrap();
+        assert_eq!(ha.node_id.as_deref(), Some("krill-a"));
+        assert_eq!(ha.lease_seconds, ConfigDefaults::ha_lease_seconds());
+        assert_eq!(
+            ha.heartbeat_seconds,
+            ConfigDefaults::ha_heartbeat_seconds()
+        );
+
+        let config_str = r#"
+            auth_token = "secret"
+
+            [ha]
+            lease_seconds = 15
+            heartbeat_seconds = 10
+        "#;
+
+        let res = parse_and_process_config_str(config_str);
+        assert_err_msg(
+            res,
+            "lease_seconds in [ha] must be at least twice heartbeat_seconds",
+        );
+
+        let config_str = r#"
+            auth_token = "secret"
+            storage_uri = "memory://ha"
+            tls_keys_dir = "/tmp/krill/tls"
+            repo_dir = "/tmp/krill/repo"
+            pid_file = "/tmp/krill/krill.pid"
+
+            [ha]
+        "#;
+
+        let res = parse_and_process_config_str(config_str);
+        assert_err_msg(
+            res,
+            "[ha] requires storage that is shared between nodes, it cannot be used with memory storage",
+        );
+    }
+
+    #[test]
+    fn key_roll_policy() {
+        let config_str = r#"
+            auth_token = "secret"
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        assert!(!config.key_roll_policy().is_enabled());
+
+        let config_str = r#"
+            auth_token = "secret"
+            key_roll_months = 12
+            key_roll_staging_hours = 48
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        assert_eq!(config.key_roll_policy(), KeyRollPolicy::new(12, 48));
+
+        let config_str = r#"
+            auth_token = "secret"
+            key_roll_months = 12
+            key_roll_staging_hours = 1
+        "#;
+
+        let res = parse_and_process_config_str(config_str);
+        assert_err_msg(res, "key_roll_staging_hours must be 24 or higher");
+    }
+
+    #[test]
+    fn child_cert_timing_overrides() {
+        let config_str = r#"
+            auth_token = "secret"
+            timing_child_certificate_valid_weeks = 52
+            timing_child_certificate_reissue_weeks_before = 4
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        let timing = &config.issuance_timing;
+
+        let lab = ChildCertTiming::new(Some(8), Some(2));
+        let lab_timing = timing.for_child(&lab);
+        assert_eq!(lab_timing.timing_child_certificate_valid_weeks, 8);
+        assert_eq!(
+            lab_timing.timing_child_certificate_reissue_weeks_before,
+            2
+        );
+        assert!(timing.verify_child_overrides(&lab).is_ok());
+
+        let critical = ChildCertTiming::new(None, Some(8));
+        let critical_timing = timing.for_child(&critical);
+        assert_eq!(critical_timing.timing_child_certificate_valid_weeks, 52);
+        assert_eq!(
+            critical_timing.timing_child_certificate_reissue_weeks_before,
+            8
+        );
+        assert!(timing.verify_child_overrides(&critical).is_ok());
+
+        // re-issue time falls back to the default of 4 weeks
+        let too_short = ChildCertTiming::new(Some(4), None);
+        assert_eq!(
+            timing.verify_child_overrides(&too_short),
+            Err("re-issue time must be shorter than validity")
+        );
+        assert!(timing
+            .verify_child_overrides(&ChildCertTiming::new(Some(1), Some(0)))
+            .is_err());
+    }
+
+    #[test]
+    fn ca_issuance_timing_overrides() {
+        let config_str = r#"
+            auth_token = "secret"
+        "#;
+
+        let config = parse_and_process_config_str(config_str).unwrap();
+        let timing = &config.issuance_timing;
+
+        let overrides = CaIssuanceTiming {
+            timing_publish_next_hours: Some(48),
+            timing_aspa_valid_weeks: Some(12),
+            ..Default::default()
+        };
+        let ca_timing = timing.for_ca(&overrides);
+        assert_eq!(ca_timing.timing_publish_next_hours, 48);
+        assert_eq!(ca_timing.timing_publish_next_jitter_hours, 4);
+        assert_eq!(ca_timing.timing_aspa_valid_weeks, 12);
+        assert_eq!(ca_timing.timing_roa_valid_weeks, 52);
+        assert!(ca_timing.verify().is_ok());
+
+        // the jitter of 4 hours is too large for the overridden next update
+        let overrides = CaIssuanceTiming {
+            timing_publish_next_hours: Some(6),
+            ..Default::default()
+        };
+        assert!(timing.for_ca(&overrides).verify().is_err());
+
+        let overrides = CaIssuanceTiming {
+            timing_bgpsec_valid_weeks: Some(4),
+            timing_bgpsec_reissue_weeks_before: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            timing.for_ca(&overrides).verify(),
+            Err("timing_bgpsec_reissue_weeks_before must be smaller than timing_bgpsec_valid_weeks")
+        );
+    }
+
     #[test]
     fn data_dir_for_storage() {
         fn test_uri(uri: &str, expected_path: &str) {
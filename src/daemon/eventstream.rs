@@ -0,0 +1,639 @@
+//! A stream of stored events, for integrations that want to follow changes
+//! in Krill as they happen rather than poll the history of each CA.
+//!
+//! The [`EventStream`] is registered as a post-save listener for CAs, the
+//! TA proxy and the publication server. It keeps a limited number of recent
+//! events in the key value store, and broadcasts new events to subscribers.
+//! Every event gets the next number of a stored sequence as its id, so ids
+//! keep increasing across restarts and can be used as a cursor by clients.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use kvx::{segment, Key, Scope, Segment};
+use rpki::{
+    ca::idexchange::{CaHandle, MyHandle},
+    repository::x509::Time,
+};
+use tokio::sync::broadcast;
+use url::Url;
+
+use crate::{
+    commons::{
+        eventsourcing::{self, Aggregate, Event, KeyValueStore},
+        KrillResult,
+    },
+    constants::{EVENT_STREAM_BUFFER_SIZE, EVENT_STREAM_NS, PUBSERVER_DFLT},
+    daemon::ca::CertAuth,
+    pubd::RepositoryAccess,
+    ta::{ta_handle, TrustAnchorProxy},
+};
+
+//------------ EventSource ---------------------------------------------------
+
+/// The kind of aggregate that an event was stored for.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    Ca,
+    TaProxy,
+    Repository,
+}
+
+impl fmt::Display for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventSource::Ca => write!(f, "ca"),
+            EventSource::TaProxy => write!(f, "ta_proxy"),
+            EventSource::Repository => write!(f, "repository"),
+        }
+    }
+}
+
+//------------ StreamEvent ---------------------------------------------------
+
+/// A stored event, as it is published to subscribers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StreamEvent {
+    /// Increasing id, usable as a cursor to resume a stream.
+    pub id: u64,
+    pub time: Time,
+    pub source: EventSource,
+
+    /// The handle of the CA, TA proxy or publication server.
+    pub handle: MyHandle,
+
+    /// The version of the command that resulted in this event.
+    pub version: u64,
+
+    /// The type of the event in snake case, e.g. "route_authorizations_updated".
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub summary: String,
+    pub details: serde_json::Value,
+}
+
+impl StreamEvent {
+    /// Returns the event formatted as a server-sent event.
+    pub fn to_sse(&self) -> String {
+        // Serializing this type cannot fail, and JSON does not contain
+        // bare newlines, so it fits on a single data line.
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("id: {}\ndata: {}\n\n", self.id, json)
+    }
+}
+
+//------------ EventStream ---------------------------------------------------
+
+/// Keeps recent events in storage and memory, and broadcasts new events.
+pub struct EventStream {
+    store: KeyValueStore,
+
+    /// The id of the last published event. This is held while new events
+    /// are stored, so that they are stored and broadcast in order.
+    last_id: Mutex<u64>,
+
+    buffer: Mutex<EventBuffer>,
+    sender: broadcast::Sender<Arc<StreamEvent>>,
+}
+
+struct EventBuffer {
+    events: VecDeque<Arc<StreamEvent>>,
+
+    /// Events up to and including this id can no longer be replayed,
+    /// because they were dropped from the buffer.
+    unavailable_until: u64,
+}
+
+impl EventStream {
+    const SEQUENCE_KEY: &'static Segment = segment!("sequence");
+    const EVENTS_SCOPE: &'static Segment = segment!("events");
+
+    /// Creates the event stream, with the events kept in the given storage.
+    pub fn create(storage_uri: &Url) -> KrillResult<Self> {
+        let store = KeyValueStore::create(storage_uri, EVENT_STREAM_NS)?;
+        let (last_id, buffer) = Self::load(&store)?;
+
+        let (sender, _) = broadcast::channel(EVENT_STREAM_BUFFER_SIZE);
+        Ok(EventStream {
+            store,
+            last_id: Mutex::new(last_id),
+            buffer: Mutex::new(buffer),
+            sender,
+        })
+    }
+
+    /// Loads the last used id and the recent events from storage.
+    ///
+    /// To be used when another node may have published events, e.g. when
+    /// this node takes over as leader. Events that are new to this node are
+    /// broadcast to its subscribers.
+    pub fn reload(&self) -> KrillResult<()> {
+        let mut last_id = self.last_id.lock().unwrap();
+        let (stored_last_id, stored) = Self::load(&self.store)?;
+
+        let mut buffer = self.buffer.lock().unwrap();
+        for event in stored.events.iter().filter(|event| event.id > *last_id)
+        {
+            // An error only means that there are no subscribers.
+            let _ = self.sender.send(event.clone());
+        }
+        *buffer = stored;
+        *last_id = stored_last_id;
+
+        Ok(())
+    }
+
+    fn load(store: &KeyValueStore) -> KrillResult<(u64, EventBuffer)> {
+        let last_id: u64 =
+            store.get(&Self::sequence_key())?.unwrap_or_default();
+
+        let mut ids: Vec<u64> = store
+            .keys(&Self::events_scope(), "")?
+            .iter()
+            .filter_map(|key| u64::from_str(key.name().as_str()).ok())
+            .collect();
+        ids.sort_unstable();
+
+        let mut events: VecDeque<Arc<StreamEvent>> =
+            VecDeque::with_capacity(EVENT_STREAM_BUFFER_SIZE);
+        for id in ids.into_iter().rev().take(EVENT_STREAM_BUFFER_SIZE).rev() {
+            if let Some(event) = store.get(&Self::event_key(id))? {
+                events.push_back(Arc::new(event));
+            }
+        }
+
+        let unavailable_until =
+            events.front().map(|event| event.id - 1).unwrap_or(last_id);
+
+        Ok((
+            last_id,
+            EventBuffer {
+                events,
+                unavailable_until,
+            },
+        ))
+    }
+
+    fn sequence_key() -> Key {
+        Key::new_global(Self::SEQUENCE_KEY)
+    }
+
+    fn events_scope() -> Scope {
+        Scope::from_segment(Self::EVENTS_SCOPE)
+    }
+
+    fn event_key(id: u64) -> Key {
+        Key::new_scoped(
+            Self::events_scope(),
+            Segment::parse(&id.to_string()).unwrap(), // a number is a valid segment
+        )
+    }
+
+    /// Subscribes to events that come after the given event id, or to new
+    /// events only if no id is given.
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> EventSubscription {
+        // Hold the lock while subscribing, so that we neither miss nor
+        // duplicate events published in the meantime.
+        let buffer = self.buffer.lock().unwrap();
+
+        let (backlog, missed) = match last_event_id {
+            Some(last_id) => (
+                buffer
+                    .events
+                    .iter()
+                    .filter(|event| event.id > last_id)
+                    .cloned()
+                    .collect(),
+                last_id < buffer.unavailable_until,
+            ),
+            None => (VecDeque::new(), false),
+        };
+
+        EventSubscription {
+            backlog,
+            missed,
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    /// Stores and broadcasts new events.
+    ///
+    /// The events are only broadcast, and their ids are only used up, once
+    /// they are stored. If they cannot be stored, then they are dropped, so
+    /// that clients never see an id that is used again after a restart.
+    fn publish<E: Event>(
+        &self,
+        source: EventSource,
+        handle: &MyHandle,
+        version: u64,
+        events: &[E],
+    ) {
+        let mut last_id = self.last_id.lock().unwrap();
+
+        let mut next_id = *last_id;
+        let mut new_events = vec![];
+        for event in events {
+            let details = match serde_json::to_value(event) {
+                Ok(details) => details,
+                Err(e) => {
+                    error!("Could not serialize event for stream: {}", e);
+                    continue;
+                }
+            };
+
+            next_id += 1;
+            new_events.push(Arc::new(StreamEvent {
+                id: next_id,
+                time: Time::now(),
+                source,
+                handle: handle.clone(),
+                version,
+                event_type: event_type(&details),
+                summary: event.to_string(),
+                details,
+            }));
+        }
+
+        if new_events.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.persist(&new_events, next_id) {
+            error!(
+                "Could not store {} events for stream, they will not be published: {}",
+                new_events.len(),
+                e
+            );
+            return;
+        }
+        *last_id = next_id;
+
+        let mut buffer = self.buffer.lock().unwrap();
+        for event in new_events {
+            if buffer.events.len() == EVENT_STREAM_BUFFER_SIZE {
+                if let Some(dropped) = buffer.events.pop_front() {
+                    buffer.unavailable_until = dropped.id;
+                }
+            }
+            buffer.events.push_back(event.clone());
+
+            // An error only means that there are no subscribers.
+            let _ = self.sender.send(event);
+        }
+    }
+
+    /// Stores the events and the last used id, and removes events that
+    /// no longer fit in the buffer.
+    fn persist(
+        &self,
+        events: &[Arc<StreamEvent>],
+        last_id: u64,
+    ) -> KrillResult<()> {
+        self.store.execute(&Scope::global(), |kv| {
+            for event in events {
+                kv.store(
+                    &Self::event_key(event.id),
+                    serde_json::to_value(event.as_ref())?,
+                )?;
+
+                let expired =
+                    event.id.checked_sub(EVENT_STREAM_BUFFER_SIZE as u64);
+                if let Some(expired) = expired {
+                    let key = Self::event_key(expired);
+                    if kv.has(&key)? {
+                        kv.delete(&key)?;
+                    }
+                }
+            }
+            kv.store(&Self::sequence_key(), serde_json::to_value(last_id)?)
+        })?;
+        Ok(())
+    }
+}
+
+/// Returns the type of an event in snake case.
+///
+/// Events are either tagged with a "type" field, or serialized as an
+/// externally tagged enum in which case the variant name is used.
+fn event_type(details: &serde_json::Value) -> String {
+    let name = match details {
+        serde_json::Value::Object(map) => match map.get("type") {
+            Some(serde_json::Value::String(tag)) => tag.clone(),
+            _ => map.keys().next().cloned().unwrap_or_default(),
+        },
+        serde_json::Value::String(variant) => variant.clone(),
+        _ => String::new(),
+    };
+
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+impl eventsourcing::PostSaveEventListener<CertAuth> for EventStream {
+    fn listen(
+        &self,
+        ca: &CertAuth,
+        events: &[<CertAuth as Aggregate>::Event],
+    ) {
+        self.publish(EventSource::Ca, ca.handle(), ca.version() - 1, events);
+    }
+}
+
+impl eventsourcing::PostSaveEventListener<TrustAnchorProxy> for EventStream {
+    fn listen(
+        &self,
+        proxy: &TrustAnchorProxy,
+        events: &[<TrustAnchorProxy as Aggregate>::Event],
+    ) {
+        self.publish(
+            EventSource::TaProxy,
+            &ta_handle(),
+            proxy.version() - 1,
+            events,
+        );
+    }
+}
+
+impl eventsourcing::PostSaveEventListener<RepositoryAccess> for EventStream {
+    fn listen(
+        &self,
+        access: &RepositoryAccess,
+        events: &[<RepositoryAccess as Aggregate>::Event],
+    ) {
+        self.publish(
+            EventSource::Repository,
+            &MyHandle::from_str(PUBSERVER_DFLT).unwrap(),
+            access.version() - 1,
+            events,
+        );
+    }
+}
+
+//------------ EventSubscription ---------------------------------------------
+
+/// A subscription to the event stream.
+pub struct EventSubscription {
+    backlog: VecDeque<Arc<StreamEvent>>,
+    missed: bool,
+    receiver: broadcast::Receiver<Arc<StreamEvent>>,
+}
+
+impl EventSubscription {
+    /// Waits for the next event. Returns `SubscriptionItem::Missed` if
+    /// events were missed, e.g. because the subscriber could not keep up.
+    pub async fn next(&mut self) -> Option<SubscriptionItem> {
+        if self.missed {
+            self.missed = false;
+            return Some(SubscriptionItem::Missed);
+        }
+
+        if let Some(event) = self.backlog.pop_front() {
+            return Some(SubscriptionItem::Event(event));
+        }
+
+        match self.receiver.recv().await {
+            Ok(event) => Some(SubscriptionItem::Event(event)),
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                Some(SubscriptionItem::Missed)
+            }
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+}
+
+/// An item received from an [`EventSubscription`].
+pub enum SubscriptionItem {
+    Event(Arc<StreamEvent>),
+    Missed,
+}
+
+//------------ EventFilter ---------------------------------------------------
+
+/// Selects events by CA handle and/or event type. An empty list matches
+/// everything.
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    cas: Vec<CaHandle>,
+    types: Vec<String>,
+}
+
+impl EventFilter {
+    pub fn new(cas: Vec<CaHandle>, types: Vec<String>) -> Self {
+        EventFilter { cas, types }
+    }
+
+    pub fn cas(&self) -> &[CaHandle] {
+        &self.cas
+    }
+
+    pub fn matches(&self, event: &StreamEvent) -> bool {
+        let ca_matches = self.cas.is_empty()
+            || (event.source == EventSource::Ca
+                && self.cas.contains(&event.handle));
+
+        let type_matches =
+            self.types.is_empty() || self.types.contains(&event.event_type);
+
+        ca_matches && type_matches
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use kvx::Namespace;
+
+    use crate::{
+        commons::eventsourcing::{register_write_fence, WriteFence},
+        pubd::RepositoryAccessEvent,
+        test,
+    };
+
+    fn publisher_removed(name: &str) -> RepositoryAccessEvent {
+        RepositoryAccessEvent::PublisherRemoved {
+            name: name.parse().unwrap(),
+        }
+    }
+
+    fn publish_removed(stream: &EventStream, names: &[&str]) {
+        let events: Vec<_> =
+            names.iter().map(|name| publisher_removed(name)).collect();
+        stream.publish(
+            EventSource::Repository,
+            &MyHandle::from_str("0").unwrap(),
+            1,
+            &events,
+        );
+    }
+
+    async fn next_event(sub: &mut EventSubscription) -> Arc<StreamEvent> {
+        match sub.next().await {
+            Some(SubscriptionItem::Event(event)) => event,
+            _ => panic!("expected event"),
+        }
+    }
+
+    #[test]
+    fn event_types_are_snake_case() {
+        let tagged = serde_json::json!({ "type": "publisher_removed" });
+        assert_eq!(event_type(&tagged), "publisher_removed");
+
+        let external = serde_json::json!({ "SignerRequestMade": "nonce" });
+        assert_eq!(event_type(&external), "signer_request_made");
+    }
+
+    #[tokio::test]
+    async fn subscribe_and_resume() {
+        let stream = EventStream::create(&test::mem_storage()).unwrap();
+
+        let mut live = stream.subscribe(None);
+        publish_removed(&stream, &["alice", "bob"]);
+
+        let alice = next_event(&mut live).await;
+        let bob = next_event(&mut live).await;
+        assert!(bob.id > alice.id);
+        assert_eq!(alice.event_type, "publisher_removed");
+        assert_eq!(alice.source, EventSource::Repository);
+
+        // Resuming after alice should give bob first, without a gap.
+        let mut resumed = stream.subscribe(Some(alice.id));
+        assert_eq!(next_event(&mut resumed).await.id, bob.id);
+
+        // Ids are a sequence, so resuming from the start gives all events.
+        let mut all = stream.subscribe(Some(0));
+        assert_eq!(next_event(&mut all).await.id, 1);
+        assert_eq!(next_event(&mut all).await.id, 2);
+    }
+
+    #[tokio::test]
+    async fn resume_after_restart() {
+        let storage_uri = test::mem_storage();
+
+        let stream = EventStream::create(&storage_uri).unwrap();
+        publish_removed(&stream, &["alice", "bob"]);
+
+        let restarted = EventStream::create(&storage_uri).unwrap();
+        let mut resumed = restarted.subscribe(Some(1));
+        let bob = next_event(&mut resumed).await;
+        assert_eq!(bob.id, 2);
+        assert_eq!(bob.summary, publisher_removed("bob").to_string());
+
+        // New events continue the sequence.
+        publish_removed(&restarted, &["carol"]);
+        assert_eq!(next_event(&mut resumed).await.id, 3);
+    }
+
+    #[tokio::test]
+    async fn reload_after_other_node_published() {
+        let storage_uri = test::mem_storage();
+
+        let leader = EventStream::create(&storage_uri).unwrap();
+        let standby = EventStream::create(&storage_uri).unwrap();
+        let mut live = standby.subscribe(None);
+
+        publish_removed(&leader, &["alice", "bob"]);
+        standby.reload().unwrap();
+
+        // Subscribers see the events of the former leader, and new events
+        // continue its sequence.
+        assert_eq!(next_event(&mut live).await.id, 1);
+        assert_eq!(next_event(&mut live).await.id, 2);
+        publish_removed(&standby, &["carol"]);
+        assert_eq!(next_event(&mut live).await.id, 3);
+        assert_eq!(standby.subscribe(Some(0)).backlog.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn resume_after_dropped_events_reports_gap() {
+        let storage_uri = test::mem_storage();
+
+        let stream = EventStream::create(&storage_uri).unwrap();
+        let names = vec!["alice"; EVENT_STREAM_BUFFER_SIZE + 2];
+        publish_removed(&stream, &names);
+
+        // The first two events were dropped, also from storage.
+        let restarted = EventStream::create(&storage_uri).unwrap();
+        for stream in [&stream, &restarted] {
+            let mut old = stream.subscribe(Some(1));
+            assert!(matches!(
+                old.next().await,
+                Some(SubscriptionItem::Missed)
+            ));
+            assert_eq!(next_event(&mut old).await.id, 3);
+
+            let mut recent = stream.subscribe(Some(2));
+            assert_eq!(next_event(&mut recent).await.id, 3);
+        }
+    }
+
+    /// A fence that can be closed to make writes fail.
+    #[derive(Debug, Default)]
+    struct ClosableFence(AtomicBool);
+
+    impl WriteFence for ClosableFence {
+        fn fences(&self, _namespace: &Namespace) -> bool {
+            true
+        }
+
+        fn check(&self) -> Result<(), kvx::Error> {
+            if self.0.load(Ordering::SeqCst) {
+                Err(kvx::Error::Unknown)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn events_that_cannot_be_stored_are_not_published() {
+        // The memory store keeps its lock after a failed write, so use
+        // disk storage.
+        let (dir, cleanup) = test::tmp_dir();
+        let storage_uri =
+            Url::parse(&format!("local://{}", dir.display())).unwrap();
+        let fence = Arc::new(ClosableFence::default());
+        register_write_fence(&storage_uri, fence.clone());
+
+        let stream = EventStream::create(&storage_uri).unwrap();
+        let mut live = stream.subscribe(None);
+        publish_removed(&stream, &["alice"]);
+
+        fence.0.store(true, Ordering::SeqCst);
+        publish_removed(&stream, &["bob"]);
+        fence.0.store(false, Ordering::SeqCst);
+        publish_removed(&stream, &["carol"]);
+
+        // Bob's event was dropped, and its id is used for carol.
+        let alice = next_event(&mut live).await;
+        let carol = next_event(&mut live).await;
+        assert_eq!(alice.id, 1);
+        assert_eq!(carol.id, 2);
+        assert_eq!(carol.summary, publisher_removed("carol").to_string());
+
+        let restarted = EventStream::create(&storage_uri).unwrap();
+        let mut resumed = restarted.subscribe(Some(1));
+        assert_eq!(next_event(&mut resumed).await, carol);
+
+        cleanup();
+    }
+}
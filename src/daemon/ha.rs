@@ -0,0 +1,496 @@
+//! Leader election between Krill nodes that share the same storage.
+//!
+//! Only one node, the leader, may run the scheduler and make changes. The
+//! leader holds a lease in the key value store which it renews on every
+//! heartbeat. Other nodes stand by: they serve read-only requests and try
+//! to take over the lease on each of their heartbeats, which succeeds once
+//! the lease has expired.
+//!
+//! Whenever the lease changes hands its fencing token is increased. The
+//! leader checks that the stored lease still has its token before it acts
+//! on anything, so that a node which stalled for longer than the lease
+//! lasts cannot make changes after another node took over. Since such a
+//! node may also stall while it acts, the election is registered as the
+//! [`WriteFence`] for the storage as well: every write transaction checks
+//! the token again before it writes anything.
+//!
+//! Nodes cache state that they loaded from the storage, which other nodes
+//! may have changed while they stood by. Hooks added through
+//! [`LeaderElection::on_lead`] let them reload that state when they become
+//! the leader, before they act as such.
+
+use std::{fmt, sync::RwLock, time::Duration};
+
+use kvx::{segment, Key, Namespace, Scope, Segment};
+use tokio::time::sleep;
+use url::Url;
+
+use crate::{
+    commons::{
+        api::{HaRole, HaStatus, LeaderLease, Timestamp},
+        error::Error,
+        eventsourcing::{KeyValueStore, WriteFence},
+        KrillResult,
+    },
+    constants::HA_NS,
+    daemon::config::HaConfig,
+};
+
+//------------ LeaderElection ------------------------------------------------
+
+type LeadHook = Box<dyn Fn() -> KrillResult<()> + Send + Sync>;
+
+pub struct LeaderElection {
+    store: KeyValueStore,
+    node_id: String,
+    lease_seconds: u64,
+    heartbeat_seconds: u64,
+
+    // The lease as this node last saw it in storage.
+    lease: RwLock<Option<LeaderLease>>,
+
+    // The fencing token of the last lease this node held.
+    token: RwLock<Option<u64>>,
+
+    // Called when this node becomes the leader.
+    on_lead: RwLock<Vec<LeadHook>>,
+}
+
+impl fmt::Debug for LeaderElection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LeaderElection")
+            .field("node_id", &self.node_id)
+            .field("lease", &self.lease)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl LeaderElection {
+    const LEASE_KEY: &'static Segment = segment!("leader");
+
+    pub fn new(storage_uri: &Url, config: &HaConfig) -> KrillResult<Self> {
+        let node_id = config
+            .node_id
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        Ok(LeaderElection {
+            store: KeyValueStore::create(storage_uri, HA_NS)?,
+            node_id,
+            lease_seconds: config.lease_seconds,
+            heartbeat_seconds: config.heartbeat_seconds,
+            lease: RwLock::new(None),
+            token: RwLock::new(None),
+            on_lead: RwLock::new(Vec::new()),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Renews the lease if this node holds it, or takes it over if it
+    /// expired. Returns whether this node is the leader.
+    pub fn heartbeat(&self) -> KrillResult<bool> {
+        let key = Key::new_global(Self::LEASE_KEY);
+        let node_id = self.node_id.as_str();
+        let lease_seconds = self.lease_seconds as i64;
+
+        let lease = self.store.execute(&Scope::global(), |kv| {
+            let now = Timestamp::now();
+            let current: Option<LeaderLease> = match kv.get(&key)? {
+                Some(value) => Some(serde_json::from_value(value)?),
+                None => None,
+            };
+
+            let next = match current {
+                Some(lease)
+                    if lease.is_held_by(node_id)
+                        && !lease.is_expired(now) =>
+                {
+                    LeaderLease {
+                        renewed: now,
+                        expires: now.plus_seconds(lease_seconds),
+                        ..lease
+                    }
+                }
+                Some(lease) if !lease.is_expired(now) => return Ok(lease),
+                expired_or_none => LeaderLease {
+                    holder: node_id.to_string(),
+                    fencing_token: expired_or_none
+                        .map(|lease| lease.fencing_token + 1)
+                        .unwrap_or(1),
+                    acquired: now,
+                    renewed: now,
+                    expires: now.plus_seconds(lease_seconds),
+                },
+            };
+
+            kv.store(&key, serde_json::to_value(&next)?)?;
+            Ok(next)
+        })?;
+
+        if !self.holds(&lease) || self.is_leader() {
+            return Ok(self.observe(Some(lease)));
+        }
+
+        // This node takes over. Let the hooks reload state before it acts
+        // as the leader. They may write, so use the token of the new lease.
+        // Keep the hooks locked until the lease is observed, so that hooks
+        // added in the meantime see whether this node leads already.
+        let hooks = self.on_lead.read().unwrap();
+        let previous =
+            self.token.write().unwrap().replace(lease.fencing_token);
+        for hook in hooks.iter() {
+            if let Err(e) = hook() {
+                error!(
+                    "Node '{}' could not prepare to become the leader, \
+                     will try again: {}",
+                    self.node_id, e
+                );
+                *self.token.write().unwrap() = previous;
+                return Ok(false);
+            }
+        }
+        Ok(self.observe(Some(lease)))
+    }
+
+    /// Adds a hook that is called whenever this node becomes the leader,
+    /// before it acts as the leader. If a hook fails, this node stays on
+    /// standby and tries again with its next heartbeat.
+    ///
+    /// Returns whether this node is the leader already. The hook is not
+    /// called for that, so the caller has to take care of it.
+    pub fn on_lead(
+        &self,
+        hook: impl Fn() -> KrillResult<()> + Send + Sync + 'static,
+    ) -> bool {
+        let mut hooks = self.on_lead.write().unwrap();
+        hooks.push(Box::new(hook));
+        self.is_leader()
+    }
+
+    /// Sends heartbeats until the end of time.
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.heartbeat() {
+                error!("Could not acquire or renew the leader lease: {}", e);
+            }
+            sleep(Duration::from_secs(self.heartbeat_seconds)).await;
+        }
+    }
+
+    /// Returns whether this node held an unexpired lease when it last
+    /// looked. Use [`check_leader`](Self::check_leader) before acting as
+    /// the leader.
+    pub fn is_leader(&self) -> bool {
+        self.lease
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|lease| self.holds(lease))
+    }
+
+    /// Verifies against storage that this node still holds the lease,
+    /// with the same fencing token that it last saw.
+    pub fn check_leader(&self) -> KrillResult<()> {
+        let token = match self.lease.read().unwrap().as_ref() {
+            Some(lease) if self.holds(lease) => lease.fencing_token,
+            _ => return Err(self.not_leader()),
+        };
+
+        let stored: Option<LeaderLease> =
+            self.store.get(&Key::new_global(Self::LEASE_KEY))?;
+
+        match stored {
+            Some(lease)
+                if lease.fencing_token == token && self.holds(&lease) =>
+            {
+                Ok(())
+            }
+            lease => {
+                self.observe(lease);
+                Err(self.not_leader())
+            }
+        }
+    }
+
+    pub fn status(&self) -> HaStatus {
+        let role = if self.is_leader() {
+            HaRole::Leader
+        } else {
+            HaRole::Standby
+        };
+
+        HaStatus {
+            node_id: Some(self.node_id.clone()),
+            role,
+            lease: self.lease.read().unwrap().clone(),
+        }
+    }
+
+    fn holds(&self, lease: &LeaderLease) -> bool {
+        lease.is_held_by(&self.node_id) && !lease.is_expired(Timestamp::now())
+    }
+
+    /// Remembers the lease found in storage, and logs any change in
+    /// leadership. Returns whether this node is the leader.
+    fn observe(&self, lease: Option<LeaderLease>) -> bool {
+        let mut current = self.lease.write().unwrap();
+
+        let was_leader = current.as_ref().is_some_and(|l| self.holds(l));
+        let is_leader = lease.as_ref().is_some_and(|l| self.holds(l));
+        let old_holder = current.as_ref().map(|l| l.holder.as_str());
+        let new_holder = lease.as_ref().map(|l| l.holder.as_str());
+
+        if is_leader {
+            *self.token.write().unwrap() =
+                lease.as_ref().map(|l| l.fencing_token);
+        }
+
+        if is_leader && !was_leader {
+            info!(
+                "Node '{}' is now the leader, with fencing token {}",
+                self.node_id,
+                lease.as_ref().map(|l| l.fencing_token).unwrap_or_default()
+            );
+        } else if !is_leader && (was_leader || old_holder != new_holder) {
+            match new_holder {
+                Some(holder) if holder != self.node_id => info!(
+                    "Node '{}' is on standby, the leader is '{}'",
+                    self.node_id, holder
+                ),
+                _ => warn!(
+                    "Node '{}' is on standby, there is currently no leader",
+                    self.node_id
+                ),
+            }
+        }
+
+        *current = lease;
+        is_leader
+    }
+
+    fn not_leader(&self) -> Error {
+        let now = Timestamp::now();
+        let leader = self
+            .lease
+            .read()
+            .unwrap()
+            .as_ref()
+            .filter(|lease| {
+                !lease.is_held_by(&self.node_id) && !lease.is_expired(now)
+            })
+            .map(|lease| lease.holder.clone());
+
+        Error::HaNotLeader(leader)
+    }
+}
+
+//------------ WriteFence ----------------------------------------------------
+
+impl WriteFence for LeaderElection {
+    fn fences(&self, namespace: &Namespace) -> bool {
+        namespace != HA_NS
+    }
+
+    /// Verifies that this node still holds the lease with the token of
+    /// the last lease it held.
+    ///
+    /// Nodes that never held the lease may not write at all. Setting up
+    /// the stores at start up only reads from the storage.
+    ///
+    /// With local storage the lease is read from its own file, outside of
+    /// the lock of the transaction that writes. A node that stalls right
+    /// after this check may still write once after it lost the lease.
+    fn check(&self) -> Result<(), kvx::Error> {
+        let token = *self.token.read().unwrap();
+        let token = token.ok_or_else(|| {
+            kvx::Error::Other(format!(
+                "node '{}' has not held the leader lease",
+                self.node_id
+            ))
+        })?;
+
+        let stored: Option<LeaderLease> = self
+            .store
+            .get(&Key::new_global(Self::LEASE_KEY))
+            .map_err(|e| kvx::Error::Other(e.to_string()))?;
+
+        match stored {
+            Some(lease)
+                if lease.fencing_token == token && self.holds(&lease) =>
+            {
+                Ok(())
+            }
+            lease => {
+                self.observe(lease);
+                Err(kvx::Error::Other(format!(
+                    "node '{}' no longer holds the leader lease with \
+                     fencing token {}",
+                    self.node_id, token
+                )))
+            }
+        }
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use kvx::{
+        namespace,
+        queue::{Queue, ScheduleMode},
+    };
+
+    use crate::{commons::eventsourcing::register_write_fence, test};
+
+    fn election(storage_uri: &Url, node_id: &str) -> LeaderElection {
+        let config = HaConfig {
+            node_id: Some(node_id.to_string()),
+            lease_seconds: 30,
+            heartbeat_seconds: 10,
+        };
+        LeaderElection::new(storage_uri, &config).unwrap()
+    }
+
+    #[test]
+    fn only_one_node_leads() {
+        let storage_uri = test::mem_storage();
+        let a = election(&storage_uri, "a");
+        let b = election(&storage_uri, "b");
+
+        assert!(a.heartbeat().unwrap());
+        assert!(!b.heartbeat().unwrap());
+
+        assert!(a.check_leader().is_ok());
+        assert!(
+            matches!(b.check_leader(), Err(Error::HaNotLeader(Some(l))) if l == "a")
+        );
+
+        // Renewing keeps the fencing token.
+        assert!(a.heartbeat().unwrap());
+        assert_eq!(a.status().lease.unwrap().fencing_token, 1);
+        assert_eq!(b.status().role, HaRole::Standby);
+    }
+
+    #[test]
+    fn standby_takes_over_expired_lease() {
+        let storage_uri = test::mem_storage();
+        let a = election(&storage_uri, "a");
+        let b = election(&storage_uri, "b");
+
+        assert!(a.heartbeat().unwrap());
+
+        // Pretend that node a stopped sending heartbeats a while ago.
+        let mut lease = a.status().lease.unwrap();
+        lease.expires = Timestamp::now().minus_seconds(1);
+        a.store
+            .store(&Key::new_global(LeaderElection::LEASE_KEY), &lease)
+            .unwrap();
+
+        assert!(b.heartbeat().unwrap());
+        assert_eq!(b.status().lease.unwrap().fencing_token, 2);
+
+        // Node a still thinks it is the leader until it checks the token.
+        assert!(a.is_leader());
+        assert!(
+            matches!(a.check_leader(), Err(Error::HaNotLeader(Some(l))) if l == "b")
+        );
+        assert!(!a.is_leader());
+        assert!(!a.heartbeat().unwrap());
+    }
+
+    #[test]
+    fn hooks_run_before_node_leads() {
+        let storage_uri = test::mem_storage();
+        let a = Arc::new(election(&storage_uri, "a"));
+
+        // The hook fails once, which keeps the node on standby until its
+        // next heartbeat.
+        let calls = Arc::new(AtomicUsize::new(0));
+        assert!(!a.on_lead({
+            let a = a.clone();
+            let calls = calls.clone();
+            move || {
+                assert!(!a.is_leader());
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(Error::custom("not ready")),
+                    _ => Ok(()),
+                }
+            }
+        }));
+
+        assert!(!a.heartbeat().unwrap());
+        assert!(!a.is_leader());
+        assert!(a.heartbeat().unwrap());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Hooks are not called again while the node keeps leading.
+        assert!(a.heartbeat().unwrap());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(a.on_lead(|| Ok(())));
+    }
+
+    #[test]
+    fn stale_leader_cannot_write() {
+        let storage_uri = test::mem_storage();
+        let a = Arc::new(election(&storage_uri, "a"));
+        let b = election(&storage_uri, "b");
+        register_write_fence(&storage_uri, a.clone());
+
+        let store =
+            KeyValueStore::create(&storage_uri, namespace!("data")).unwrap();
+        let key = Key::new_global(segment!("key"));
+
+        // Node a cannot write before it leads. Use another scope, as the
+        // memory backend keeps a scope locked after a failed transaction
+        // for a while.
+        let early_key = Key::new_scoped(
+            Scope::from_segment(segment!("early")),
+            segment!("key"),
+        );
+        assert!(store.store(&early_key, &1).is_err());
+
+        // Node a can write while it leads.
+        assert!(a.heartbeat().unwrap());
+        store.store(&key, &2).unwrap();
+
+        // Node b takes over while node a stalls.
+        let mut lease = a.status().lease.unwrap();
+        lease.expires = Timestamp::now().minus_seconds(1);
+        a.store
+            .store(&Key::new_global(LeaderElection::LEASE_KEY), &lease)
+            .unwrap();
+        assert!(b.heartbeat().unwrap());
+
+        // Node a still thinks it is the leader, but cannot write.
+        let stale_key = Key::new_scoped(
+            Scope::from_segment(segment!("stale")),
+            segment!("key"),
+        );
+        assert!(a.is_leader());
+        assert!(store.store(&stale_key, &3).is_err());
+        let queue =
+            KeyValueStore::create(&storage_uri, namespace!("queue")).unwrap();
+        assert!(queue
+            .schedule_task(
+                segment!("task").to_owned(),
+                serde_json::Value::Null,
+                None,
+                ScheduleMode::ReplaceExisting,
+            )
+            .is_err());
+        assert!(!a.is_leader());
+        assert_eq!(store.get::<u32>(&key).unwrap(), Some(2));
+    }
+}
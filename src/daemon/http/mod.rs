@@ -1,10 +1,18 @@
-use std::{io, str::from_utf8, str::FromStr};
+use std::{
+    convert::Infallible,
+    fmt, io,
+    pin::Pin,
+    str::from_utf8,
+    str::FromStr,
+    task::{Context, Poll},
+};
 
 use bytes::Bytes;
+use futures_util::stream::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 
 use http_body_util::{BodyExt, Either, Empty, Full, Limited};
-use hyper::body::Body;
+use hyper::body::{Body, Frame};
 use hyper::header::USER_AGENT;
 use hyper::http::uri::PathAndQuery;
 use hyper::{HeaderMap, Method, StatusCode};
@@ -49,6 +57,8 @@ enum ContentType {
     Svg,
     Woff,
     Woff2,
+    EventStream,
+    Gzip,
 }
 
 impl AsRef<str> for ContentType {
@@ -68,6 +78,8 @@ impl AsRef<str> for ContentType {
             ContentType::Svg => "image/svg+xml",
             ContentType::Woff => "font/woff",
             ContentType::Woff2 => "font/woff2",
+            ContentType::EventStream => "text/event-stream",
+            ContentType::Gzip => "application/gzip",
         }
     }
 }
@@ -75,9 +87,46 @@ impl AsRef<str> for ContentType {
 //------------ HyperRequest and HyperResponse --------------------------------
 
 pub type HyperRequest = hyper::Request<hyper::body::Incoming>;
-pub type HyperResponseBody = Either<Empty<Bytes>, Full<Bytes>>;
+pub type HyperResponseBody =
+    Either<Empty<Bytes>, Either<Full<Bytes>, StreamingBody>>;
 pub type HyperResponse = hyper::Response<HyperResponseBody>;
 
+//------------ StreamingBody -------------------------------------------------
+
+/// A response body that is produced by a stream of chunks, for responses
+/// that are sent incrementally, such as server-sent events.
+pub struct StreamingBody {
+    stream: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+}
+
+impl StreamingBody {
+    pub fn new(stream: impl Stream<Item = Bytes> + Send + 'static) -> Self {
+        StreamingBody {
+            stream: Box::pin(stream),
+        }
+    }
+}
+
+impl Body for StreamingBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        self.stream
+            .poll_next_unpin(cx)
+            .map(|chunk| chunk.map(|bytes| Ok(Frame::data(bytes))))
+    }
+}
+
+impl fmt::Debug for StreamingBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StreamingBody")
+    }
+}
+
 //----------- Response -------------------------------------------------------
 
 struct Response {
@@ -116,7 +165,7 @@ impl Response {
         let body = if self.body.is_empty() {
             Either::Left(Empty::new())
         } else {
-            Either::Right(Full::new(self.body.into()))
+            Either::Right(Either::Left(Full::new(self.body.into())))
         };
         let response = builder.body(body).unwrap();
 
@@ -244,11 +293,53 @@ impl HttpResponse {
                 .status(StatusCode::OK)
                 .header("Content-Type", ContentType::Text.as_ref())
                 .header("Cache-Control", "no-cache")
-                .body(Either::Right(Full::new(body.into())))
+                .body(Either::Right(Either::Left(Full::new(body.into()))))
                 .unwrap(),
         )
     }
 
+    /// Returns a response that sends the chunks of the given stream as
+    /// server-sent events, until the stream ends or the client goes away.
+    pub fn event_stream(
+        stream: impl Stream<Item = Bytes> + Send + 'static,
+    ) -> Self {
+        let mut res = HttpResponse::new(
+            hyper::Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", ContentType::EventStream.as_ref())
+                .header("Cache-Control", "no-cache")
+                .body(Either::Right(Either::Right(StreamingBody::new(
+                    stream,
+                ))))
+                .unwrap(),
+        );
+        res.do_not_log();
+        res
+    }
+
+    /// Returns a response that sends the chunks of the given stream as a
+    /// gzipped file download with the given file name.
+    pub fn gzip_stream(
+        file_name: &str,
+        stream: impl Stream<Item = Bytes> + Send + 'static,
+    ) -> Self {
+        let mut res = HttpResponse::new(
+            hyper::Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", ContentType::Gzip.as_ref())
+                .header(
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", file_name),
+                )
+                .body(Either::Right(Either::Right(StreamingBody::new(
+                    stream,
+                ))))
+                .unwrap(),
+        );
+        res.do_not_log();
+        res
+    }
+
     pub fn xml(body: Vec<u8>) -> Self {
         Self::ok_response(ContentType::Xml, body)
     }
@@ -411,6 +502,22 @@ impl Request {
         &self.state
     }
 
+    /// Returns the values of a query parameter. Values may be given by
+    /// repeating the parameter, or as a comma separated list.
+    pub fn query_param(&self, name: &str) -> Vec<String> {
+        let query = self.request.uri().query().unwrap_or_default();
+        url::form_urlencoded::parse(query.as_bytes())
+            .filter(|(key, _)| key == name)
+            .flat_map(|(_, value)| {
+                value
+                    .split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     /// Returns the method of this request.
     pub fn method(&self) -> &Method {
         self.request.method()
@@ -1,11 +1,12 @@
 //! Hyper based HTTP server for Krill.
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read};
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{env, process};
 
 use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
@@ -28,25 +29,34 @@ use tokio_rustls::TlsAcceptor;
 
 use crate::{
     commons::{
+        actor::Actor,
         api::{
             ApiRepositoryContact, AspaDefinitionUpdates, BgpStats,
-            CommandHistoryCriteria, ParentCaReq, PublisherList,
-            RepositoryContact, RoaConfigurationUpdates, RtaName, Token,
+            CommandHistoryCriteria, EmergencyRekeyRequest, HistoryPoint,
+            ParentCaReq, PublisherList, RepositoryContact,
+            RepositoryMirrorName, ResourcePoolName, RoaConfigurationUpdates,
+            RscName, RtaName, Token,
         },
         bgp::BgpAnalysisAdvice,
         error::Error,
         eventsourcing::AggregateStoreError,
         util::file,
+        KrillResult,
     },
     constants::{
+        BACKUP_CHUNK_SIZE, EVENT_STREAM_KEEP_ALIVE_SECS,
         KRILL_ENV_HTTP_LOG_INFO, KRILL_ENV_UPGRADE_ONLY, KRILL_VERSION_MAJOR,
         KRILL_VERSION_MINOR, KRILL_VERSION_PATCH, NO_RESOURCE,
     },
     daemon::{
         auth::common::permissions::Permission,
         auth::{Auth, Handle},
-        ca::CaStatus,
+        ca::{CaStatus, GeofeedSignRequest},
         config::Config,
+        eventstream::{
+            EventFilter, EventSource, EventSubscription, StreamEvent,
+            SubscriptionItem,
+        },
         http::{
             auth::auth, statics::statics, testbed::testbed, tls, tls_keys,
             HttpResponse, HyperRequest, HyperResponse, Request, RequestPath,
@@ -55,7 +65,7 @@ use crate::{
         krillserver::KrillServer,
         properties::PropertiesManager,
     },
-    ta::{self, TA_NAME},
+    ta::{self, TaKeyRollRequest, TA_NAME},
     upgrades::{
         finalise_data_migration, post_start_upgrade,
         prepare_upgrade_data_migrations, UpgradeError, UpgradeMode,
@@ -171,7 +181,7 @@ pub async fn start_krill_daemon(config: Arc<Config>) -> Result<(), Error> {
 
     // Build the scheduler which will be responsible for executing
     // planned/triggered tasks
-    let scheduler = krill_server.build_scheduler();
+    let scheduler = Arc::new(krill_server.build_scheduler());
     let scheduler_future = scheduler.run();
 
     // Start creating the server.
@@ -348,7 +358,11 @@ async fn map_requests(
     // stack overflow. By doing it by hand like this we avoid the use of the
     // macros that cause the recursion. We could also look at putting less
     // data on the stack.
-    let mut res = api(req).await;
+    let mut res = match standby_check(&req) {
+        Ok(()) => api(req).await,
+        // Expected while on standby, so not worth logging.
+        Err(e) => Ok(HttpResponse::response_from_error(e).with_benign(true)),
+    };
     if let Err(req) = res {
         res = auth(req).await;
     }
@@ -466,10 +480,36 @@ pub async fn render_not_found(_req: Request) -> RoutingResult {
     Ok(HttpResponse::not_found())
 }
 
+/// Refuses requests that would make changes, unless this server is the
+/// leader or the only node.
+fn standby_check(req: &Request) -> Result<(), Error> {
+    if req.is_get() || *req.method() == Method::HEAD {
+        return Ok(());
+    }
+    match req.path().segment() {
+        "api" | "rfc6492" | "rfc8181" | "testbed" => {
+            req.state().check_leader()
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Returns the server health.
+///
+/// The `/health/leader` variant fails on standby nodes, so that load
+/// balancers can send changes to the leader only.
 pub async fn health(req: Request) -> RoutingResult {
     if req.is_get() && req.path().segment() == "health" {
-        render_ok()
+        if req.path().full() == "/health/leader" {
+            match req.state().check_leader() {
+                Ok(()) => render_ok(),
+                Err(e) => {
+                    Ok(HttpResponse::response_from_error(e).with_benign(true))
+                }
+            }
+        } else {
+            render_ok()
+        }
     } else {
         Err(req)
     }
@@ -558,6 +598,82 @@ pub async fn metrics(req: Request) -> RoutingResult {
             ));
         }
 
+        if let Ok(retrying) = server.tasks_retrying() {
+            res.push('\n');
+            res.push_str("# HELP krill_tasks_retrying number of failed tasks that are being retried\n");
+            res.push_str("# TYPE krill_tasks_retrying gauge\n");
+            res.push_str(&format!("krill_tasks_retrying {}\n", retrying));
+        }
+
+        if let Ok(dead_letters) = server.dead_letter_tasks() {
+            res.push('\n');
+            res.push_str("# HELP krill_tasks_dead_letters number of tasks that were given up on after failing too often\n");
+            res.push_str("# TYPE krill_tasks_dead_letters gauge\n");
+            res.push_str(&format!(
+                "krill_tasks_dead_letters {}\n",
+                dead_letters.tasks.len()
+            ));
+        }
+
+        if let Ok(depths) = server.task_depths() {
+            res.push('\n');
+            res.push_str("# HELP krill_tasks_pending number of pending tasks, by type\n");
+            res.push_str("# TYPE krill_tasks_pending gauge\n");
+            for (kind, depth) in &depths {
+                res.push_str(&format!(
+                    "krill_tasks_pending{{type=\"{}\"}} {}\n",
+                    kind, depth.pending
+                ));
+            }
+
+            res.push('\n');
+            res.push_str("# HELP krill_tasks_running number of running tasks, by type\n");
+            res.push_str("# TYPE krill_tasks_running gauge\n");
+            for (kind, depth) in &depths {
+                res.push_str(&format!(
+                    "krill_tasks_running{{type=\"{}\"}} {}\n",
+                    kind, depth.running
+                ));
+            }
+        }
+
+        let run_stats = server.task_run_stats();
+        if !run_stats.is_empty() {
+            res.push('\n');
+            res.push_str("# HELP krill_task_duration_seconds time taken to run tasks since krill started, by type\n");
+            res.push_str("# TYPE krill_task_duration_seconds summary\n");
+            for (kind, stats) in &run_stats {
+                res.push_str(&format!(
+                    "krill_task_duration_seconds_sum{{type=\"{}\"}} {}\n",
+                    kind, stats.seconds_sum
+                ));
+                res.push_str(&format!(
+                    "krill_task_duration_seconds_count{{type=\"{}\"}} {}\n",
+                    kind, stats.count
+                ));
+            }
+
+            res.push('\n');
+            res.push_str("# HELP krill_task_duration_seconds_max longest time taken to run a task since krill started, by type\n");
+            res.push_str("# TYPE krill_task_duration_seconds_max gauge\n");
+            for (kind, stats) in &run_stats {
+                res.push_str(&format!(
+                    "krill_task_duration_seconds_max{{type=\"{}\"}} {}\n",
+                    kind, stats.seconds_max
+                ));
+            }
+        }
+
+        if let Some(ha) = server.leader_election() {
+            res.push('\n');
+            res.push_str("# HELP krill_ha_leader whether this node is the leader (1) or on standby (0)\n");
+            res.push_str("# TYPE krill_ha_leader gauge\n");
+            res.push_str(&format!(
+                "krill_ha_leader {}\n",
+                i32::from(ha.is_leader())
+            ));
+        }
+
         if let Ok(cas_stats) = server.cas_stats().await {
             let number_cas = cas_stats.len();
 
@@ -1044,6 +1160,9 @@ async fn ta(req: Request) -> RoutingResult {
             "/ta/ta.tal" => tal(req).await,
             "/testbed.tal" => tal(req).await,
             "/ta/ta.cer" => ta_cer(req).await,
+            path if path.starts_with("/ta/") && path.ends_with(".cer") => {
+                ta_cer_for_path(req).await
+            }
             _ => Err(req),
         },
         _ => Err(req),
@@ -1066,6 +1185,16 @@ pub async fn ta_cer(req: Request) -> RoutingResult {
     }
 }
 
+/// Serves a TA certificate for one of its other TAL URIs, e.g. that of
+/// the successor key during a TA key rollover.
+pub async fn ta_cer_for_path(req: Request) -> RoutingResult {
+    let path = req.path.full().to_string();
+    match req.state().trust_anchor_cert_for_path(&path).await {
+        Some(cert) => Ok(HttpResponse::cert(cert.to_bytes().to_vec())),
+        None => Err(req),
+    }
+}
+
 //------------ Provisioning (RFC6492) ----------------------------------------
 
 /// Process an RFC 6492 request
@@ -1243,8 +1372,19 @@ async fn api(req: Request) -> RoutingResult {
                 // Make sure access is allowed
                 aa!(req, Permission::LOGIN, {
                     match restricted_endpoint {
+                        Some("admin") => aa!(
+                            req,
+                            Permission::CA_ADMIN,
+                            aa!(
+                                req,
+                                Permission::PUB_ADMIN,
+                                api_admin(req, &mut path).await
+                            )
+                        ),
                         Some("bulk") => api_bulk(req, &mut path).await,
                         Some("cas") => api_cas(req, &mut path).await,
+                        Some("events") => api_events(req).await,
+                        Some("ha") => api_ha(req).await,
                         Some("pubd") => aa!(
                             req,
                             Permission::PUB_ADMIN,
@@ -1255,6 +1395,7 @@ async fn api(req: Request) -> RoutingResult {
                             Permission::CA_ADMIN,
                             api_ta(req, &mut path).await
                         ),
+                        Some("tasks") => api_tasks(req, &mut path).await,
                         _ => render_unknown_method(),
                     }
                 })
@@ -1278,9 +1419,78 @@ async fn api_authorized(req: Request) -> RoutingResult {
     )
 }
 
+async fn api_admin(req: Request, path: &mut RequestPath) -> RoutingResult {
+    match path.next() {
+        Some("backup") => api_backup(req).await,
+        _ => render_unknown_method(),
+    }
+}
+
+/// Streams a backup of all stored data as a gzipped tar archive.
+///
+/// The backup is written by a blocking task, because it reads all data
+/// from storage while holding locks. The locks are released before the
+/// archive is streamed. The archive ends with a manifest, so clients can
+/// tell whether they received a complete backup.
+async fn api_backup(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => {
+            // Refuse before the response starts, standby nodes cannot make
+            // backups.
+            if let Err(e) = req.state().check_leader() {
+                return Ok(HttpResponse::response_from_error(e));
+            }
+
+            let (sender, receiver) = tokio::sync::mpsc::channel(16);
+            let server = req.state().clone();
+            tokio::task::spawn_blocking(move || {
+                let out = io::BufWriter::with_capacity(
+                    BACKUP_CHUNK_SIZE,
+                    ChannelWriter(sender),
+                );
+                match server.backup(out) {
+                    Ok(manifest) => info!(
+                        "Created backup for Krill version {}",
+                        manifest.krill_version
+                    ),
+                    Err(e) => error!("Could not create backup: {}", e),
+                }
+            });
+
+            let stream =
+                futures_util::stream::unfold(receiver, |mut rx| async move {
+                    rx.recv().await.map(|chunk| (chunk, rx))
+                });
+
+            Ok(HttpResponse::gzip_stream("krill-backup.tar.gz", stream))
+        }
+        _ => render_unknown_method(),
+    }
+}
+
+/// Sends everything written to it as chunks over a channel, so that a
+/// blocking task can produce a streaming response body.
+struct ChannelWriter(tokio::sync::mpsc::Sender<Bytes>);
+
+impl io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .blocking_send(Bytes::copy_from_slice(buf))
+            .map_err(|_| {
+                io::Error::new(io::ErrorKind::BrokenPipe, "client went away")
+            })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 async fn api_bulk(req: Request, path: &mut RequestPath) -> RoutingResult {
     match path.full() {
         "/api/v1/bulk/cas/import" => api_cas_import(req).await,
+        "/api/v1/bulk/cas/template" => api_cas_from_template(req).await,
         "/api/v1/bulk/cas/issues" => api_all_ca_issues(req).await,
         "/api/v1/bulk/cas/sync/parent" => api_refresh_all(req).await,
         "/api/v1/bulk/cas/sync/repo" => api_resync_all(req).await,
@@ -1289,38 +1499,209 @@ async fn api_bulk(req: Request, path: &mut RequestPath) -> RoutingResult {
             api_republish_all(req, true).await
         }
         "/api/v1/bulk/cas/suspend" => api_suspend_all(req).await,
+        "/api/v1/bulk/cas/history/compact" => {
+            api_compact_history_all(req).await
+        }
         _ => render_unknown_method(),
     }
 }
 
-async fn api_cas(req: Request, path: &mut RequestPath) -> RoutingResult {
-    match path.path_arg::<CaHandle>() {
-        Some(ca) => aa!(req, Permission::CA_READ, Handle::from(&ca), {
-            match path.next() {
-                None => match *req.method() {
-                    Method::GET => api_ca_info(req, ca).await,
-                    Method::DELETE => api_ca_delete(req, ca).await,
+/// Shows the leader election status of this node.
+async fn api_ha(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => render_json(req.state().ha_status()),
+        _ => render_unknown_method(),
+    }
+}
+
+/// Lists, reschedules or cancels queued tasks:
+///
+///   GET    /api/v1/tasks
+///   GET    /api/v1/tasks/{name}
+///   POST   /api/v1/tasks/{name}/reschedule
+///   DELETE /api/v1/tasks/{name}
+///
+/// And lists, requeues or discards tasks that failed too often:
+///
+///   GET    /api/v1/tasks/dead
+///   POST   /api/v1/tasks/dead/{name}/requeue
+///   DELETE /api/v1/tasks/dead/{name}
+async fn api_tasks(req: Request, path: &mut RequestPath) -> RoutingResult {
+    let server = req.state().clone();
+    match path.next() {
+        None => match *req.method() {
+            Method::GET => aa!(req, Permission::TASKS_READ, {
+                render_json_res(server.tasks())
+            }),
+            _ => render_unknown_method(),
+        },
+        Some("dead") => match path.next() {
+            None => match *req.method() {
+                Method::GET => aa!(req, Permission::TASKS_READ, {
+                    render_json_res(server.dead_letter_tasks())
+                }),
+                _ => render_unknown_method(),
+            },
+            Some(name) => {
+                let name = name.to_string();
+                match (req.method().clone(), path.next()) {
+                    (Method::DELETE, None) => {
+                        aa!(req, Permission::TASKS_UPDATE, {
+                            render_empty_res(
+                                server.dead_letter_discard(&name),
+                            )
+                        })
+                    }
+                    (Method::POST, Some("requeue")) => {
+                        aa!(req, Permission::TASKS_UPDATE, {
+                            render_empty_res(
+                                server.dead_letter_requeue(&name),
+                            )
+                        })
+                    }
                     _ => render_unknown_method(),
+                }
+            }
+        },
+        Some(name) => {
+            let name = name.to_string();
+            match (req.method().clone(), path.next()) {
+                (Method::GET, None) => aa!(req, Permission::TASKS_READ, {
+                    render_json_res(server.task(&name))
+                }),
+                (Method::DELETE, None) => {
+                    aa!(req, Permission::TASKS_UPDATE, {
+                        render_empty_res(server.task_cancel(&name))
+                    })
+                }
+                (Method::POST, Some("reschedule")) => {
+                    aa!(req, Permission::TASKS_UPDATE, {
+                        render_empty_res(server.task_reschedule(&name))
+                    })
+                }
+                _ => render_unknown_method(),
+            }
+        }
+    }
+}
+
+/// Streams stored events as server-sent events. Events can be filtered by
+/// CA handle and event type, using the "ca" and "type" query parameters.
+/// Clients can resume a stream using the "Last-Event-ID" header.
+async fn api_events(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => {
+            let actor = req.actor();
+
+            let cas = match req
+                .query_param("ca")
+                .iter()
+                .map(|ca| CaHandle::from_str(ca))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(cas) => cas,
+                Err(_) => return render_error(Error::ApiInvalidHandle),
+            };
+
+            // Refuse filters on CAs that the actor may not see, rather
+            // than silently never returning any events for them.
+            for ca in &cas {
+                if !matches!(
+                    actor.is_allowed(Permission::CA_READ, Handle::from(ca)),
+                    Ok(true)
+                ) {
+                    return Ok(HttpResponse::forbidden(format!(
+                        "User '{}' does not have permission '{}' on resource '{}'",
+                        actor.name(),
+                        Permission::CA_READ,
+                        ca
+                    )));
+                }
+            }
+
+            let filter = EventFilter::new(cas, req.query_param("type"));
+
+            let last_event_id = req
+                .headers()
+                .get("Last-Event-ID")
+                .and_then(|id| id.to_str().ok())
+                .map(|id| id.to_string())
+                .or_else(|| req.query_param("last_event_id").pop())
+                .and_then(|id| u64::from_str(id.trim()).ok());
+
+            let subscription = req.state().events().subscribe(last_event_id);
+
+            let stream = futures_util::stream::unfold(
+                (subscription, filter, actor),
+                |(mut subscription, filter, actor)| async move {
+                    let chunk =
+                        next_event_chunk(&mut subscription, &filter, &actor)
+                            .await?;
+                    Some((chunk, (subscription, filter, actor)))
                 },
-                Some("aspas") => api_ca_aspas(req, path, ca).await,
-                Some("bgpsec") => api_ca_bgpsec(req, path, ca).await,
-                Some("children") => api_ca_children(req, path, ca).await,
-                Some("history") => api_ca_history(req, path, ca).await,
-
-                Some("id") => api_ca_id(req, path, ca).await,
-                Some("issues") => api_ca_issues(req, ca).await,
-                Some("keys") => api_ca_keys(req, path, ca).await,
-                Some("parents") => api_ca_parents(req, path, ca).await,
-                Some("repo") => api_ca_repo(req, path, ca).await,
-                Some("routes") => api_ca_routes(req, path, ca).await,
-                Some("stats") => api_ca_stats(req, path, ca).await,
-                Some("sync") => api_ca_sync(req, path, ca).await,
-
-                Some("rta") => api_ca_rta(req, path, ca).await,
+            );
 
-                _ => render_unknown_method(),
+            Ok(HttpResponse::event_stream(stream))
+        }
+        _ => render_unknown_method(),
+    }
+}
+
+/// Returns the next chunk to send for an event stream: an event that the
+/// actor may see and that matches the filter, a notice that events were
+/// missed, or a keep-alive comment if there was nothing to send for a
+/// while.
+async fn next_event_chunk(
+    subscription: &mut EventSubscription,
+    filter: &EventFilter,
+    actor: &Actor,
+) -> Option<Bytes> {
+    let keep_alive = Duration::from_secs(EVENT_STREAM_KEEP_ALIVE_SECS);
+    loop {
+        match tokio::time::timeout(keep_alive, subscription.next()).await {
+            Err(_) => return Some(Bytes::from_static(b": keep-alive\n\n")),
+            Ok(None) => return None,
+            Ok(Some(SubscriptionItem::Missed)) => {
+                return Some(Bytes::from_static(
+                    b"event: gap\ndata: events were missed\n\n",
+                ))
             }
-        }),
+            Ok(Some(SubscriptionItem::Event(event))) => {
+                if filter.matches(&event) && event_permitted(actor, &event) {
+                    return Some(Bytes::from(event.to_sse()));
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether the actor may see the event. This uses the same
+/// permissions that are needed to see the history of the aggregate.
+fn event_permitted(actor: &Actor, event: &StreamEvent) -> bool {
+    let allowed = match event.source {
+        EventSource::Ca => {
+            actor.is_allowed(Permission::CA_READ, Handle::from(&event.handle))
+        }
+        EventSource::TaProxy => {
+            actor.is_allowed(Permission::CA_ADMIN, NO_RESOURCE)
+        }
+        EventSource::Repository => {
+            actor.is_allowed(Permission::PUB_ADMIN, NO_RESOURCE)
+        }
+    };
+    matches!(allowed, Ok(true))
+}
+
+async fn api_cas(req: Request, path: &mut RequestPath) -> RoutingResult {
+    match path.path_arg::<CaHandle>() {
+        Some(ca) => {
+            // The old handle of a renamed CA may still be used as an alias.
+            let ca = match req.state().ca_resolve_alias(ca) {
+                Ok(ca) => ca,
+                Err(e) => return render_error(e),
+            };
+            api_ca(req, path, ca).await
+        }
         None => match *req.method() {
             Method::GET => api_cas_list(req).await,
             Method::POST => api_ca_init(req).await,
@@ -1329,15 +1710,65 @@ async fn api_cas(req: Request, path: &mut RequestPath) -> RoutingResult {
     }
 }
 
+async fn api_ca(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_READ, Handle::from(&ca), {
+        match path.next() {
+            None => match *req.method() {
+                Method::GET => api_ca_info(req, ca).await,
+                Method::DELETE => api_ca_delete(req, ca).await,
+                _ => render_unknown_method(),
+            },
+            Some("aspas") => api_ca_aspas(req, path, ca).await,
+            Some("bgpsec") => api_ca_bgpsec(req, path, ca).await,
+            Some("children") => api_ca_children(req, path, ca).await,
+            Some("clone") => api_ca_clone(req, ca).await,
+            Some("history") => api_ca_history(req, path, ca).await,
+
+            Some("id") => api_ca_id(req, path, ca).await,
+            Some("issues") => api_ca_issues(req, ca).await,
+            Some("keys") => api_ca_keys(req, path, ca).await,
+            Some("parents") => api_ca_parents(req, path, ca).await,
+            Some("pools") => api_ca_pools(req, path, ca).await,
+            Some("rename") => api_ca_rename(req, ca).await,
+            Some("repo") => api_ca_repo(req, path, ca).await,
+            Some("routes") => api_ca_routes(req, path, ca).await,
+            Some("stats") => api_ca_stats(req, path, ca).await,
+            Some("template") => api_ca_template(req, ca).await,
+            Some("sync") => api_ca_sync(req, path, ca).await,
+            Some("timing") => api_ca_timing(req, ca).await,
+
+            Some("rta") => api_ca_rta(req, path, ca).await,
+            Some("rsc") => api_ca_rsc(req, path, ca).await,
+            Some("geofeeds") => api_ca_geofeeds(req, path, ca).await,
+
+            _ => render_unknown_method(),
+        }
+    })
+}
+
 async fn api_ca_keys(
     req: Request,
     path: &mut RequestPath,
     ca: CaHandle,
 ) -> RoutingResult {
     match *req.method() {
+        Method::GET => match path.next() {
+            Some("roll_policy") => api_ca_kr_policy(req, ca).await,
+            _ => render_unknown_method(),
+        },
         Method::POST => match path.next() {
             Some("roll_init") => api_ca_kr_init(req, ca).await,
             Some("roll_activate") => api_ca_kr_activate(req, ca).await,
+            Some("roll_policy") => api_ca_kr_policy_update(req, ca).await,
+            Some("emergency_rekey") => api_ca_kr_emergency(req, ca).await,
+            _ => render_unknown_method(),
+        },
+        Method::DELETE => match path.next() {
+            Some("roll_policy") => api_ca_kr_policy_remove(req, ca).await,
             _ => render_unknown_method(),
         },
         _ => render_unknown_method(),
@@ -1379,6 +1810,20 @@ async fn api_ca_repo(
             _ => render_unknown_method(),
         },
         Some("status") => api_ca_repo_status(req, ca).await,
+        Some("mirrors") => match (req.method().clone(), path.next()) {
+            (Method::POST, Some(name)) => {
+                api_ca_repo_mirror_add(req, ca, name.to_string()).await
+            }
+            (Method::DELETE, Some(name)) => {
+                api_ca_repo_mirror_remove(req, ca, name.to_string()).await
+            }
+            _ => render_unknown_method(),
+        },
+        Some("migration") => match *req.method() {
+            Method::POST => api_ca_repo_migration_start(req, ca).await,
+            Method::DELETE => api_ca_repo_migration_abort(req, ca).await,
+            _ => render_unknown_method(),
+        },
         _ => render_unknown_method(),
     }
 }
@@ -1760,6 +2205,23 @@ async fn api_cas_import(req: Request) -> RoutingResult {
     }
 }
 
+/// Create a new CA using a template.
+async fn api_cas_from_template(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::POST => aa!(req, Permission::CA_CREATE, {
+            let actor = req.actor();
+            let server = req.state().clone();
+            match req.json().await {
+                Ok(request) => render_json_res(
+                    server.ca_init_from_template(request, &actor).await,
+                ),
+                Err(e) => render_error(e),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
 async fn api_all_ca_issues(req: Request) -> RoutingResult {
     match *req.method() {
         Method::GET => aa!(req, Permission::CA_READ, {
@@ -2035,6 +2497,71 @@ async fn api_ca_history(
     match path.next() {
         Some("details") => api_ca_command_details(req, path, ca).await,
         Some("commands") => api_ca_history_commands(req, path, ca).await,
+        Some("compact") => api_ca_history_compact(req, ca).await,
+        Some("at") => api_ca_history_at(req, path, ca).await,
+        _ => render_unknown_method(),
+    }
+}
+
+async fn api_ca_history_at(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    // /api/v1/cas/{ca}/history/at/<version|time>[/routes|/aspas|/bgpsec]
+    let point: HistoryPoint = match path.path_arg() {
+        Some(point) => point,
+        None => return render_unknown_resource(),
+    };
+
+    match *req.method() {
+        Method::GET => {
+            aa!(req, Permission::CA_READ, Handle::from(&ca), {
+                let server = req.state();
+                match path.next() {
+                    None => render_ca_at(server.ca_info_at(&ca, point)),
+                    Some("routes") => {
+                        render_ca_at(server.ca_routes_at(&ca, point))
+                    }
+                    Some("aspas") => render_ca_at(
+                        server.ca_aspas_definitions_at(&ca, point),
+                    ),
+                    Some("bgpsec") => render_ca_at(
+                        server.ca_bgpsec_definitions_at(&ca, point),
+                    ),
+                    _ => render_unknown_method(),
+                }
+            })
+        }
+        _ => render_unknown_method(),
+    }
+}
+
+/// Renders a view on a CA at a point in its history, or 'not found' if
+/// there is no such point in the history of the CA.
+fn render_ca_at<O: Serialize>(res: KrillResult<O>) -> RoutingResult {
+    match res {
+        Err(Error::AggregateStoreError(
+            AggregateStoreError::UnknownCommand(_, _)
+            | AggregateStoreError::UnknownAtTime(_, _),
+        )) => render_unknown_resource(),
+        res => render_json_res(res),
+    }
+}
+
+async fn api_ca_history_compact(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::POST => {
+            aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+                let server = req.state().clone();
+                match req.json().await {
+                    Ok(retention) => render_json_res(
+                        server.ca_compact_history(&ca, retention),
+                    ),
+                    Err(e) => render_error(e),
+                }
+            })
+        }
         _ => render_unknown_method(),
     }
 }
@@ -2217,35 +2744,107 @@ async fn api_ca_repo_update(req: Request, ca: CaHandle) -> RoutingResult {
     })
 }
 
-async fn api_ca_parent_add_or_update(
+/// Add or update a repository mirror of a CA.
+async fn api_ca_repo_mirror_add(
     req: Request,
     ca: CaHandle,
-    parent_override: Option<ParentHandle>,
+    name: RepositoryMirrorName,
 ) -> RoutingResult {
     aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let server = req.state().clone();
 
-        let bytes = match req.api_bytes().await {
-            Ok(bytes) => bytes,
-            Err(e) => return render_error(e),
-        };
-
-        match extract_parent_ca_req(&ca, bytes, parent_override) {
-            Ok(parent_req) => render_empty_res(
-                server.ca_parent_add_or_update(ca, parent_req, &actor).await,
+        match req
+            .api_bytes()
+            .await
+            .map(|bytes| extract_repository_contact(&ca, bytes))
+        {
+            Ok(Ok(contact)) => render_empty_res(
+                server.ca_repo_mirror_add(ca, name, contact, &actor).await,
             ),
-            Err(e) => render_error(e),
+            Ok(Err(e)) | Err(e) => render_error(e),
         }
     })
 }
 
-fn extract_parent_ca_req(
-    ca: &CaHandle,
-    bytes: Bytes,
-    parent_override: Option<ParentHandle>,
-) -> Result<ParentCaReq, Error> {
-    let string = String::from_utf8(bytes.to_vec()).map_err(Error::custom)?;
+/// Remove a repository mirror of a CA.
+async fn api_ca_repo_mirror_remove(
+    req: Request,
+    ca: CaHandle,
+    name: RepositoryMirrorName,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_repo_mirror_remove(ca, name, &actor).await,
+        )
+    })
+}
+
+/// Start a staged migration of a CA to a new repository.
+async fn api_ca_repo_migration_start(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let server = req.state().clone();
+
+        match req
+            .api_bytes()
+            .await
+            .map(|bytes| extract_repository_contact(&ca, bytes))
+        {
+            Ok(Ok(contact)) => render_empty_res(
+                server.ca_repo_migration_start(ca, contact, &actor).await,
+            ),
+            Ok(Err(e)) | Err(e) => render_error(e),
+        }
+    })
+}
+
+/// Abort a staged migration of a CA to a new repository.
+async fn api_ca_repo_migration_abort(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_repo_migration_abort(ca, &actor).await,
+        )
+    })
+}
+
+async fn api_ca_parent_add_or_update(
+    req: Request,
+    ca: CaHandle,
+    parent_override: Option<ParentHandle>,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let server = req.state().clone();
+
+        let bytes = match req.api_bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => return render_error(e),
+        };
+
+        match extract_parent_ca_req(&ca, bytes, parent_override) {
+            Ok(parent_req) => render_empty_res(
+                server.ca_parent_add_or_update(ca, parent_req, &actor).await,
+            ),
+            Err(e) => render_error(e),
+        }
+    })
+}
+
+fn extract_parent_ca_req(
+    ca: &CaHandle,
+    bytes: Bytes,
+    parent_override: Option<ParentHandle>,
+) -> Result<ParentCaReq, Error> {
+    let string = String::from_utf8(bytes.to_vec()).map_err(Error::custom)?;
 
     // Get rid of whitespace first so we can check if it smells like XML.
     // We could change this to check for Content-Type headers instead.
@@ -2316,6 +2915,221 @@ async fn api_ca_kr_activate(req: Request, ca: CaHandle) -> RoutingResult {
     })
 }
 
+/// Show the key roll policy for a CA, and the next planned key roll phase.
+async fn api_ca_kr_policy(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(
+        req,
+        Permission::CA_READ,
+        Handle::from(&ca),
+        render_json_res(req.state().ca_keyroll_status(&ca).await)
+    )
+}
+
+/// Set the key roll policy for a CA.
+async fn api_ca_kr_policy_update(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+
+        match req.json().await {
+            Err(e) => render_error(e),
+            Ok(policy) => render_empty_res(
+                state
+                    .ca_keyroll_policy_update(ca, Some(policy), &actor)
+                    .await,
+            ),
+        }
+    })
+}
+
+/// Replace all keys of a CA immediately, e.g. because they may have been
+/// compromised, and report all objects that were re-issued.
+async fn api_ca_kr_emergency(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+
+        match req.json::<EmergencyRekeyRequest>().await {
+            Err(e) => render_error(e),
+            Ok(rekey) => render_json_res(
+                state.ca_keyroll_emergency(ca, rekey.reason, &actor).await,
+            ),
+        }
+    })
+}
+
+/// Remove the key roll policy for a CA, so that the server default is used.
+async fn api_ca_kr_policy_remove(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_keyroll_policy_update(ca, None, &actor).await,
+        )
+    })
+}
+
+// -- Issuance timing functions
+
+async fn api_ca_timing(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::GET => api_ca_timing_show(req, ca).await,
+        Method::POST => api_ca_timing_update(req, ca).await,
+        Method::DELETE => api_ca_timing_remove(req, ca).await,
+        _ => render_unknown_method(),
+    }
+}
+
+/// Show the overrides of the server issuance timing for a CA.
+async fn api_ca_timing_show(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(
+        req,
+        Permission::CA_READ,
+        Handle::from(&ca),
+        render_json_res(req.state().ca_issuance_timing(&ca).await)
+    )
+}
+
+/// Set the overrides of the server issuance timing for a CA.
+async fn api_ca_timing_update(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+
+        match req.json().await {
+            Err(e) => render_error(e),
+            Ok(timing) => render_empty_res(
+                state
+                    .ca_issuance_timing_update(ca, Some(timing), &actor)
+                    .await,
+            ),
+        }
+    })
+}
+
+/// Remove the overrides of the server issuance timing for a CA, so that
+/// the server configuration is used.
+async fn api_ca_timing_remove(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state()
+                .ca_issuance_timing_update(ca, None, &actor)
+                .await,
+        )
+    })
+}
+
+/// Rename a CA.
+async fn api_ca_rename(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::POST => aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+            let actor = req.actor();
+            let state = req.state().clone();
+
+            match req.json().await {
+                Err(e) => render_error(e),
+                Ok(rename) => render_empty_res(
+                    state.ca_rename(&ca, rename, &actor).await,
+                ),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
+/// Show the setup of a CA as a template for new CAs.
+async fn api_ca_template(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::GET => aa!(
+            req,
+            Permission::CA_READ,
+            Handle::from(&ca),
+            render_json_res(req.state().ca_template(&ca).await)
+        ),
+        _ => render_unknown_method(),
+    }
+}
+
+/// Create a new CA using this CA as its template.
+async fn api_ca_clone(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::POST => aa!(req, Permission::CA_CREATE, {
+            let actor = req.actor();
+            let state = req.state().clone();
+
+            match req.json().await {
+                Err(e) => render_error(e),
+                Ok(clone) => {
+                    render_json_res(state.ca_clone(&ca, clone, &actor).await)
+                }
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
+// -- Resource pool functions
+
+async fn api_ca_pools(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    match (req.method().clone(), path.next()) {
+        (Method::GET, None) => api_ca_pools_show(req, ca).await,
+        (Method::POST, None) => api_ca_pools_update(req, ca).await,
+        (Method::DELETE, Some(name)) => {
+            api_ca_pools_remove(req, ca, name.to_string()).await
+        }
+        _ => render_unknown_method(),
+    }
+}
+
+/// Show the resource pools of a CA and how much of them is allocated.
+async fn api_ca_pools_show(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(
+        req,
+        Permission::CA_READ,
+        Handle::from(&ca),
+        render_json_res(req.state().ca_resource_pools(&ca).await)
+    )
+}
+
+/// Add or replace a resource pool of a CA.
+async fn api_ca_pools_update(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+
+        match req.json().await {
+            Err(e) => render_error(e),
+            Ok(definition) => render_empty_res(
+                state.ca_resource_pool_update(&ca, definition, &actor).await,
+            ),
+        }
+    })
+}
+
+/// Remove a resource pool of a CA.
+async fn api_ca_pools_remove(
+    req: Request,
+    ca: CaHandle,
+    name: ResourcePoolName,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_resource_pool_remove(&ca, name, &actor).await,
+        )
+    })
+}
+
 // -- ASPA functions
 
 /// List the current ASPA definitions for a CA
@@ -2543,6 +3357,22 @@ async fn api_refresh_all(req: Request) -> RoutingResult {
     }
 }
 
+/// Compact the history of all CAs
+async fn api_compact_history_all(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::POST => aa!(req, Permission::CA_ADMIN, {
+            let server = req.state().clone();
+            match req.json().await {
+                Ok(retention) => {
+                    render_json_res(server.cas_compact_history(retention))
+                }
+                Err(e) => render_error(e),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
 /// Schedule check suspend for all CAs
 async fn api_suspend_all(req: Request) -> RoutingResult {
     match *req.method() {
@@ -2699,6 +3529,160 @@ async fn api_ca_rta_multi_sign(
     })
 }
 
+//------------ Support RPKI Signed Checklists (RSC) ---------------------------
+
+async fn api_ca_rsc(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    match path.path_arg() {
+        Some(name) => match *req.method() {
+            Method::POST => match path.next() {
+                Some("sign") => api_ca_rsc_sign(req, ca, name, false).await,
+                Some("cosign") => api_ca_rsc_sign(req, ca, name, true).await,
+                _ => render_unknown_method(),
+            },
+            Method::GET => {
+                if name.is_empty() {
+                    api_ca_rsc_list(req, ca).await
+                } else {
+                    api_ca_rsc_show(req, ca, name).await
+                }
+            }
+            _ => render_unknown_method(),
+        },
+        None => match *req.method() {
+            Method::GET => api_ca_rsc_list(req, ca).await,
+            _ => render_unknown_method(),
+        },
+    }
+}
+
+async fn api_ca_rsc_list(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(
+        req,
+        Permission::RSC_LIST,
+        Handle::from(&ca),
+        render_json_res(req.state().rsc_list(ca).await)
+    )
+}
+
+async fn api_ca_rsc_show(
+    req: Request,
+    ca: CaHandle,
+    name: RscName,
+) -> RoutingResult {
+    aa!(
+        req,
+        Permission::RSC_READ,
+        Handle::from(&ca),
+        render_json_res(req.state().rsc_show(ca, name).await)
+    )
+}
+
+async fn api_ca_rsc_sign(
+    req: Request,
+    ca: CaHandle,
+    name: RscName,
+    cosign: bool,
+) -> RoutingResult {
+    aa!(req, Permission::RSC_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+        match req.json().await {
+            Err(e) => render_error(e),
+            Ok(request) => {
+                if cosign {
+                    render_empty_res(
+                        state.rsc_cosign(ca, name, request, &actor).await,
+                    )
+                } else {
+                    render_empty_res(
+                        state.rsc_sign(ca, name, request, &actor).await,
+                    )
+                }
+            }
+        }
+    })
+}
+
+//------------ Support signed geofeeds (RFC 9632) -----------------------------
+
+async fn api_ca_geofeeds(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    match *req.method() {
+        Method::GET => match path.next() {
+            None => api_ca_geofeed_list(req, ca).await,
+            Some(number) => match u64::from_str(number) {
+                Ok(number) => api_ca_geofeed_show(req, ca, number).await,
+                Err(_) => render_unknown_method(),
+            },
+        },
+        Method::POST => match path.next() {
+            Some("sign") => api_ca_geofeed_sign(req, ca).await,
+            _ => render_unknown_method(),
+        },
+        _ => render_unknown_method(),
+    }
+}
+
+async fn api_ca_geofeed_list(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(
+        req,
+        Permission::GEOFEED_LIST,
+        Handle::from(&ca),
+        render_json_res(req.state().geofeed_list(ca).await)
+    )
+}
+
+async fn api_ca_geofeed_show(
+    req: Request,
+    ca: CaHandle,
+    number: u64,
+) -> RoutingResult {
+    aa!(
+        req,
+        Permission::GEOFEED_READ,
+        Handle::from(&ca),
+        render_json_res(req.state().geofeed_show(ca, number).await)
+    )
+}
+
+async fn api_ca_geofeed_sign(req: Request, ca: CaHandle) -> RoutingResult {
+    aa!(req, Permission::GEOFEED_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let state = req.state().clone();
+        match req.json::<GeofeedSignRequest>().await {
+            Err(e) => render_error(e),
+            Ok(request) => {
+                // Co-signers sign part of the geofeed, so the actor needs
+                // the same permission on them.
+                for cosigner in request.cosigners() {
+                    if !matches!(
+                        actor.is_allowed(
+                            Permission::GEOFEED_UPDATE,
+                            Handle::from(cosigner)
+                        ),
+                        Ok(true)
+                    ) {
+                        return Ok(HttpResponse::forbidden(format!(
+                            "User '{}' does not have permission '{}' on resource '{}'",
+                            actor.name(),
+                            Permission::GEOFEED_UPDATE,
+                            cosigner
+                        )));
+                    }
+                }
+                render_json_res(state.geofeed_sign(ca, request, &actor).await)
+            }
+        }
+    })
+}
+
 //-------------------------------- API TA --------------------------------------------------
 async fn api_ta(req: Request, path: &mut RequestPath) -> RoutingResult {
     //
@@ -2721,14 +3705,20 @@ async fn api_ta(req: Request, path: &mut RequestPath) -> RoutingResult {
     // sign request if any    POST /proxy/signer/response          process
     // sign response from signer
     //
+    //    - TA key roll (RFC 9691)
+    //    GET  /proxy/key-roll                  show successor key, if any
+    //    POST /proxy/key-roll/init             create key roll init request
+    //    POST /proxy/key-roll/activate         create key roll activate
+    // request
+    //
     //    - children
     //    GET  /proxy/children/                 future: list children
     //    POST /proxy/children/                 add child
     //    GET  /proxy/children/{child}/parent_response.json    show parent
     // response for child    GET  /proxy/children/{child}/parent_response.
     // xml    show parent response for child    POST /proxy/children/
-    // {child}          future: update child    DEL  /proxy/children/
-    // {child}          future: remove child
+    // {child}          update child resources    DEL  /proxy/children/
+    // {child}          remove child
     //
     // krillta signer --dir
     //            init
@@ -2823,6 +3813,35 @@ async fn api_ta(req: Request, path: &mut RequestPath) -> RoutingResult {
                 },
                 _ => render_unknown_method(),
             },
+            Some("key-roll") => match (req.method().clone(), path.next()) {
+                (Method::GET, None) => render_json_res(
+                    req.state().ta_proxy_key_roll_successor().await,
+                ),
+                (Method::POST, Some("init")) => {
+                    let server = req.state().clone();
+                    let actor = req.actor.clone();
+                    match req.json().await {
+                        Ok(init) => render_json_res(
+                            server
+                                .ta_proxy_key_roll_request(
+                                    TaKeyRollRequest::Initiate(init),
+                                    &actor,
+                                )
+                                .await,
+                        ),
+                        Err(e) => render_error(e),
+                    }
+                }
+                (Method::POST, Some("activate")) => render_json_res(
+                    req.state()
+                        .ta_proxy_key_roll_request(
+                            TaKeyRollRequest::Activate,
+                            &req.actor(),
+                        )
+                        .await,
+                ),
+                _ => render_unknown_method(),
+            },
             Some("children") => match path.path_arg::<ChildHandle>() {
                 Some(child) => match path.next() {
                     Some("parent_response.json") => render_json_res(
@@ -2843,12 +3862,28 @@ async fn api_ta(req: Request, path: &mut RequestPath) -> RoutingResult {
                         }
                     }
                     None => match *req.method() {
-                        Method::POST => render_error(Error::custom(
-                            "update TA child not yet supported",
-                        )),
-                        Method::DELETE => render_error(Error::custom(
-                            "remove TA child not yet supported",
-                        )),
+                        Method::POST => {
+                            let actor = req.actor();
+                            let server = req.state().clone();
+                            match req.json().await {
+                                Ok(child_req) => render_empty_res(
+                                    server
+                                        .ta_proxy_children_update(
+                                            child, child_req, &actor,
+                                        )
+                                        .await,
+                                ),
+                                Err(e) => render_error(e),
+                            }
+                        }
+                        Method::DELETE => {
+                            let actor = req.actor();
+                            render_empty_res(
+                                req.state()
+                                    .ta_proxy_children_remove(child, &actor)
+                                    .await,
+                            )
+                        }
                         _ => render_unknown_method(),
                     },
                     _ => render_unknown_method(),
@@ -1,5 +1,11 @@
 //! An RPKI publication protocol server.
-use std::{collections::HashMap, path::PathBuf, str::FromStr, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+};
 
 use bytes::Bytes;
 use chrono::Duration;
@@ -22,38 +28,51 @@ use crate::{
             self,
             import::{ExportChild, ImportChild},
             AddChildRequest, AllCertAuthIssues, AspaDefinitionList,
-            AspaDefinitionUpdates, AspaProvidersUpdate, BgpSecCsrInfoList,
-            BgpSecDefinitionUpdates, CaCommandDetails, CaRepoDetails,
-            CertAuthInfo, CertAuthInit, CertAuthIssues, CertAuthList,
-            CertAuthStats, ChildCaInfo, ChildrenConnectionStats,
-            CommandHistory, CommandHistoryCriteria, ConfiguredRoa,
-            CustomerAsn, IdCertInfo, ParentCaContact, ParentCaReq,
-            PublicationServerUris, PublisherDetails, ReceivedCert,
-            RepoFileDeleteCriteria, RepositoryContact, RoaConfiguration,
-            RoaConfigurationUpdates, RoaPayload, RtaList, RtaName,
-            RtaPrepResponse, ServerInfo, Timestamp, UpdateChildRequest,
+            AspaDefinitionUpdates, AspaProvidersUpdate, BackupManifest,
+            BgpSecCsrInfoList, BgpSecDefinitionUpdates, CaCommandDetails,
+            CaIssuanceTiming, CaRepoDetails, CaTemplate, CaTemplateReport,
+            CertAuthClone, CertAuthFromTemplate, CertAuthInfo, CertAuthInit,
+            CertAuthIssues, CertAuthList, CertAuthRename, CertAuthStats,
+            ChildCaInfo, ChildrenConnectionStats, CommandHistory,
+            CommandHistoryCriteria, ConfiguredRoa, CustomerAsn,
+            DeadLetterList, EmergencyRekeyReport, GeofeedList, HaStatus,
+            HistoryCompactionReport, HistoryCompactionReports, HistoryPoint,
+            HistoryRetention, IdCertInfo, KeyRollPolicy, KeyRollStatus,
+            ParentCaContact, ParentCaReq, PublicationServerUris,
+            PublisherDetails, ReceivedCert, RepoFileDeleteCriteria,
+            RepositoryContact, RepositoryMirrorName, ResourcePoolDefinition,
+            ResourcePoolList, ResourcePoolName, RoaConfiguration,
+            RoaConfigurationUpdates, RoaPayload, RscList, RscName, RtaList,
+            RtaName, RtaPrepResponse, ServerInfo, TaskDetails, TaskList,
+            Timestamp, UpdateChildRequest,
         },
         bgp::{BgpAnalyser, BgpAnalysisReport, BgpAnalysisSuggestion},
         crypto::KrillSignerBuilder,
         error::Error,
+        eventsourcing::register_write_fence,
         KrillEmptyResult, KrillResult,
     },
     constants::*,
     daemon::{
         auth::{providers::AdminTokenAuthProvider, Authorizer, LoggedInUser},
+        backup,
         ca::{
-            self, testbed_ca_handle, CaManager, CaStatus,
-            ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
+            self, testbed_ca_handle, CaManager, CaStatus, GeofeedSignRequest,
+            ResourceTaggedAttestation, RscSignRequest, RtaContentRequest,
+            RtaPrepareRequest, SignedGeofeed, SignedGeofeeds, SignedRsc,
         },
         config::{AuthType, Config},
+        eventstream::EventStream,
+        ha::LeaderElection,
         http::{HttpResponse, HyperRequest},
-        mq::{now, Task, TaskQueue},
+        mq::{now, Task, TaskDepth, TaskQueue, TaskRunStats},
         scheduler::Scheduler,
     },
     pubd::{RepoStats, RepositoryManager},
     ta::{
-        ta_handle, TaCertDetails, TrustAnchorSignedRequest,
-        TrustAnchorSignedResponse, TrustAnchorSignerInfo, TA_NAME,
+        ta_handle, TaCertDetails, TaKeyRollRequest, TaSuccessor,
+        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
+        TrustAnchorSignerInfo, TA_NAME,
     },
 };
 
@@ -86,6 +105,12 @@ pub struct KrillServer {
     // Shared message queue
     mq: Arc<TaskQueue>,
 
+    // Stream of stored events, shared with the CA and repository managers
+    events: Arc<EventStream>,
+
+    // Leader election with other nodes, if configured
+    ha: Option<Arc<LeaderElection>>,
+
     // Time this server was started
     started: Timestamp,
 
@@ -109,6 +134,28 @@ impl KrillServer {
         info!("Starting {} v{}", KRILL_SERVER_APP, KRILL_VERSION);
         info!("{} uses service uri: {}", KRILL_SERVER_APP, service_uri);
 
+        // With leader election, only the leader may write to the storage.
+        // Set this up first, so that all stores created below check that
+        // this node still holds the lease before they write.
+        let ha = match &config.ha {
+            Some(ha_config) => {
+                let election = Arc::new(LeaderElection::new(
+                    &config.storage_uri,
+                    ha_config,
+                )?);
+                register_write_fence(&config.storage_uri, election.clone());
+                election.heartbeat()?;
+
+                // Keep the lease from here on, as the remaining set up
+                // may take longer than the lease lasts.
+                let heartbeat = election.clone();
+                tokio::spawn(async move { heartbeat.run().await });
+
+                Some(election)
+            }
+            None => None,
+        };
+
         // Assumes that Config::verify() has already ensured that the signer
         // configuration is valid and that Config::resolve() has been
         // used to update signer name references to resolve to the
@@ -164,12 +211,17 @@ impl KrillServer {
         // scheduler.
         let mq = Arc::new(TaskQueue::new(&config.storage_uri)?);
 
+        // Event stream Arc is shared between ca_manager and repo_manager,
+        // which publish to it, and the API which lets clients follow it.
+        let events = Arc::new(EventStream::create(&config.storage_uri)?);
+
         // for now, support that existing embedded repositories are still
         // supported. this should be removed in future after people
         // have had a chance to separate.
         let repo_manager = Arc::new(RepositoryManager::build(
             config.clone(),
             mq.clone(),
+            events.clone(),
             signer.clone(),
         )?);
 
@@ -177,6 +229,7 @@ impl KrillServer {
             ca::CaManager::build(
                 config.clone(),
                 mq.clone(),
+                events.clone(),
                 signer,
                 system_actor.clone(),
             )
@@ -189,12 +242,34 @@ impl KrillServer {
             &config.bgp_risdumps_v6_uri,
         ));
 
-        // When multi-node set ups with a shared queue are
-        // supported then we can no longer safely reschedule
-        // ALL running tests. See issue: #1112
-        mq.reschedule_tasks_at_startup()?;
+        // With leader election, other nodes may change the storage while
+        // this node stands by. Reload all cached state whenever this node
+        // becomes the leader, and now if it became the leader while the
+        // state was loaded above.
+        if let Some(election) = &ha {
+            let reload = {
+                let repo_manager = repo_manager.clone();
+                let ca_manager = ca_manager.clone();
+                let events = events.clone();
+                Arc::new(move || -> KrillResult<()> {
+                    repo_manager.reload()?;
+                    ca_manager.reload()?;
+                    events.reload()
+                })
+            };
+            let hook = reload.clone();
+            if election.on_lead(move || hook()) {
+                reload()?;
+            }
+        }
 
-        mq.schedule(Task::QueueStartTasks, now())?;
+        // With leader election, the scheduler does this when this node
+        // becomes the leader. Until then, other nodes may be running tasks
+        // from the shared queue.
+        if ha.is_none() {
+            mq.reschedule_tasks_at_startup()?;
+            mq.schedule(Task::QueueStartTasks, now())?;
+        }
 
         let server = KrillServer {
             service_uri,
@@ -203,6 +278,8 @@ impl KrillServer {
             ca_manager,
             bgp_analyser,
             mq,
+            events,
+            ha,
             started: Timestamp::now(),
             #[cfg(feature = "multi-user")]
             login_session_cache,
@@ -214,7 +291,9 @@ impl KrillServer {
         let testbed_handle = testbed_ca_handle();
 
         if let Some(testbed) = config.testbed() {
-            if server.ca_manager.has_ca(&testbed_handle)? {
+            if !server.is_leader() {
+                info!("Leaving TESTBED set up to the leader");
+            } else if server.ca_manager.has_ca(&testbed_handle)? {
                 if config.benchmark.is_some() {
                     info!("Resuming BENCHMARK mode - will NOT recreate CAs. If you wanted this, then wipe the data dir and restart.");
                 } else {
@@ -319,6 +398,7 @@ impl KrillServer {
             self.login_session_cache.clone(),
             self.config.clone(),
             self.system_actor.clone(),
+            self.ha.clone(),
         )
     }
 
@@ -367,6 +447,10 @@ impl KrillServer {
         self.ca_manager.testbed_enabled()
     }
 
+    pub fn events(&self) -> &EventStream {
+        &self.events
+    }
+
     #[cfg(feature = "multi-user")]
     pub fn login_session_cache_size(&self) -> usize {
         self.login_session_cache.size()
@@ -513,6 +597,22 @@ impl KrillServer {
             .await
     }
 
+    pub async fn ta_proxy_key_roll_request(
+        &self,
+        request: TaKeyRollRequest,
+        actor: &Actor,
+    ) -> KrillResult<TrustAnchorSignedRequest> {
+        self.ca_manager
+            .ta_proxy_key_roll_request(request, actor)
+            .await
+    }
+
+    pub async fn ta_proxy_key_roll_successor(
+        &self,
+    ) -> KrillResult<Option<TaSuccessor>> {
+        self.ca_manager.ta_proxy_key_roll_successor().await
+    }
+
     pub async fn ta_proxy_children_add(
         &self,
         child_request: AddChildRequest,
@@ -529,6 +629,27 @@ impl KrillServer {
             .await
     }
 
+    pub async fn ta_proxy_children_update(
+        &self,
+        child: ChildHandle,
+        req: UpdateChildRequest,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_child_update(&ta_handle().convert(), child, req, actor)
+            .await
+    }
+
+    pub async fn ta_proxy_children_remove(
+        &self,
+        child: ChildHandle,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_child_remove(&ta_handle().convert(), child, actor)
+            .await
+    }
+
     pub async fn ta_cert_details(&self) -> KrillResult<TaCertDetails> {
         let proxy = self.ca_manager.get_trust_anchor_proxy().await?;
         Ok(proxy.get_ta_details()?.clone())
@@ -540,6 +661,24 @@ impl KrillServer {
             .ok()
             .map(|details| details.into())
     }
+
+    /// Returns the current, or successor, TA certificate which uses
+    /// the given path in one of its TAL HTTPS URIs.
+    pub async fn trust_anchor_cert_for_path(
+        &self,
+        path: &str,
+    ) -> Option<ReceivedCert> {
+        let proxy = self.ca_manager.get_trust_anchor_proxy().await.ok()?;
+        let current = proxy.get_ta_details().ok()?;
+        let successor = proxy.get_successor().ok()?;
+
+        std::iter::once(current)
+            .chain(successor.map(|successor| successor.ta_cert_details()))
+            .find(|details| {
+                details.tal().uris().iter().any(|uri| uri.path() == path)
+            })
+            .map(|details| details.cert().clone())
+    }
 }
 
 /// # Being a parent
@@ -1060,7 +1199,21 @@ impl KrillServer {
     /// Returns the public CA info for a CA, or NONE if the CA cannot be
     /// found.
     pub async fn ca_info(&self, ca: &CaHandle) -> KrillResult<CertAuthInfo> {
-        self.ca_manager.get_ca(ca).await.map(|ca| ca.as_ca_info())
+        let ca = self.ca_manager.get_ca(ca).await?;
+        let key_roll = ca.key_roll_status(self.config.key_roll_policy());
+        Ok(ca.as_ca_info().with_key_roll(key_roll))
+    }
+
+    /// Returns the public CA info for a CA as it was at the given point in
+    /// its history.
+    pub fn ca_info_at(
+        &self,
+        ca: &CaHandle,
+        point: HistoryPoint,
+    ) -> KrillResult<CertAuthInfo> {
+        self.ca_manager
+            .get_ca_at(ca, point)
+            .map(|ca| ca.as_ca_info())
     }
 
     /// Returns the CA status, or an error if none can be found.
@@ -1082,6 +1235,57 @@ impl KrillServer {
             .await
     }
 
+    /// Renames a CA, see [`CaManager::ca_rename`].
+    pub async fn ca_rename(
+        &self,
+        ca: &CaHandle,
+        rename: CertAuthRename,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.ca_manager
+            .ca_rename(self.repo_manager.as_ref(), ca, rename, actor)
+            .await
+    }
+
+    /// Returns the setup of a CA as a template for new CAs.
+    pub async fn ca_template(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<CaTemplate> {
+        self.ca_manager
+            .ca_template(self.repo_manager.as_ref(), ca)
+            .await
+    }
+
+    /// Creates a new CA using a template.
+    pub async fn ca_init_from_template(
+        &self,
+        request: CertAuthFromTemplate,
+        actor: &Actor,
+    ) -> KrillResult<CaTemplateReport> {
+        self.ca_manager
+            .ca_init_from_template(self.repo_manager.as_ref(), request, actor)
+            .await
+    }
+
+    /// Creates a new CA using an existing CA as its template.
+    pub async fn ca_clone(
+        &self,
+        ca: &CaHandle,
+        clone: CertAuthClone,
+        actor: &Actor,
+    ) -> KrillResult<CaTemplateReport> {
+        let template = self.ca_template(ca).await?;
+        self.ca_init_from_template(clone.into_from_template(template), actor)
+            .await
+    }
+
+    /// Returns the handle of the CA using the given handle as an alias,
+    /// or the given handle if it is not an alias.
+    pub fn ca_resolve_alias(&self, ca: CaHandle) -> KrillResult<CaHandle> {
+        self.ca_manager.ca_resolve_alias(ca)
+    }
+
     /// Returns the parent contact for a CA and parent, or NONE if either the
     /// CA or the parent cannot be found.
     pub async fn ca_my_parent_contact(
@@ -1110,6 +1314,42 @@ impl KrillServer {
         self.ca_manager.ca_command_details(ca, version)
     }
 
+    /// Compacts the history of a CA. If the retention policy is empty, then
+    /// the policy from the configuration is used.
+    pub fn ca_compact_history(
+        &self,
+        ca: &CaHandle,
+        retention: HistoryRetention,
+    ) -> KrillResult<HistoryCompactionReport> {
+        let retention = self.history_retention(retention)?;
+        self.ca_manager.ca_compact_history(ca, &retention)
+    }
+
+    /// Compacts the history of all CAs. If the retention policy is empty,
+    /// then the policy from the configuration is used.
+    pub fn cas_compact_history(
+        &self,
+        retention: HistoryRetention,
+    ) -> KrillResult<HistoryCompactionReports> {
+        let retention = self.history_retention(retention)?;
+        self.ca_manager.cas_compact_history(&retention)
+    }
+
+    fn history_retention(
+        &self,
+        retention: HistoryRetention,
+    ) -> KrillResult<HistoryRetention> {
+        if !retention.is_empty() {
+            Ok(retention)
+        } else {
+            self.config.history_retention().ok_or_else(|| {
+                Error::custom(
+                    "No history retention policy given, and none configured",
+                )
+            })
+        }
+    }
+
     /// Returns the publisher request for a CA, or NONE of the CA cannot be
     /// found.
     pub async fn ca_publisher_req(
@@ -1135,7 +1375,9 @@ impl KrillServer {
     ) -> KrillResult<CaRepoDetails> {
         let ca = self.ca_manager.get_ca(ca_handle).await?;
         let contact = ca.repository_contact()?;
-        Ok(CaRepoDetails::new(contact.clone()))
+        Ok(CaRepoDetails::new(contact.clone())
+            .with_mirrors(ca.repository_mirrors().clone())
+            .with_migration(ca.repository_migration().cloned()))
     }
 
     /// Update the repository for a CA, or return an error. (see
@@ -1151,6 +1393,61 @@ impl KrillServer {
             .await
     }
 
+    /// Add or update a repository mirror for a CA. (see
+    /// `CertAuth::repo_mirror_add`)
+    pub async fn ca_repo_mirror_add(
+        &self,
+        ca: CaHandle,
+        name: RepositoryMirrorName,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_repo_mirror_add(
+                self.repo_manager.as_ref(),
+                ca,
+                name,
+                contact,
+                actor,
+            )
+            .await
+    }
+
+    pub async fn ca_repo_mirror_remove(
+        &self,
+        ca: CaHandle,
+        name: RepositoryMirrorName,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager.ca_repo_mirror_remove(ca, name, actor).await
+    }
+
+    /// Start a staged migration of a CA to a new repository. (see
+    /// `CertAuth::repo_migration_start`)
+    pub async fn ca_repo_migration_start(
+        &self,
+        ca: CaHandle,
+        contact: RepositoryContact,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_repo_migration_start(
+                self.repo_manager.as_ref(),
+                ca,
+                contact,
+                actor,
+            )
+            .await
+    }
+
+    pub async fn ca_repo_migration_abort(
+        &self,
+        ca: CaHandle,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager.ca_repo_migration_abort(ca, actor).await
+    }
+
     pub async fn ca_update_id(
         &self,
         ca: CaHandle,
@@ -1179,6 +1476,84 @@ impl KrillServer {
             .await
     }
 
+    pub async fn ca_keyroll_status(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<KeyRollStatus> {
+        let ca = self.ca_manager.get_ca(ca).await?;
+        Ok(ca.key_roll_status(self.config.key_roll_policy()))
+    }
+
+    pub async fn ca_keyroll_policy_update(
+        &self,
+        ca: CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_keyroll_policy_update(ca, policy, actor)
+            .await
+    }
+
+    pub async fn ca_keyroll_emergency(
+        &self,
+        ca: CaHandle,
+        reason: String,
+        actor: &Actor,
+    ) -> KrillResult<EmergencyRekeyReport> {
+        self.ca_manager
+            .ca_keyroll_emergency(ca, reason, actor)
+            .await
+    }
+
+    pub async fn ca_issuance_timing(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<CaIssuanceTiming> {
+        let ca = self.ca_manager.get_ca(ca).await?;
+        Ok(ca.issuance_timing_overrides().copied().unwrap_or_default())
+    }
+
+    pub async fn ca_issuance_timing_update(
+        &self,
+        ca: CaHandle,
+        timing: Option<CaIssuanceTiming>,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_issuance_timing_update(ca, timing, actor)
+            .await
+    }
+
+    pub async fn ca_resource_pools(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<ResourcePoolList> {
+        self.ca_manager.ca_resource_pools(ca).await
+    }
+
+    pub async fn ca_resource_pool_update(
+        &self,
+        ca: &CaHandle,
+        definition: ResourcePoolDefinition,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_resource_pool_update(ca, definition, actor)
+            .await
+    }
+
+    pub async fn ca_resource_pool_remove(
+        &self,
+        ca: &CaHandle,
+        name: ResourcePoolName,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_resource_pool_remove(ca, name, actor)
+            .await
+    }
+
     pub async fn rfc6492(
         &self,
         ca: CaHandle,
@@ -1186,6 +1561,8 @@ impl KrillServer {
         user_agent: Option<String>,
         actor: &Actor,
     ) -> KrillResult<Bytes> {
+        // Remote children may still use an alias of a renamed CA.
+        let ca = self.ca_manager.ca_resolve_alias(ca)?;
         self.ca_manager
             .rfc6492(&ca, msg_bytes, user_agent, actor)
             .await
@@ -1201,6 +1578,16 @@ impl KrillServer {
         self.ca_manager.ca_aspas_definitions_show(ca).await
     }
 
+    pub fn ca_aspas_definitions_at(
+        &self,
+        ca: &CaHandle,
+        point: HistoryPoint,
+    ) -> KrillResult<AspaDefinitionList> {
+        self.ca_manager
+            .get_ca_at(ca, point)
+            .map(|ca| ca.aspas_definitions_show())
+    }
+
     pub async fn ca_aspas_definitions_update(
         &self,
         ca: CaHandle,
@@ -1234,6 +1621,16 @@ impl KrillServer {
         self.ca_manager.ca_bgpsec_definitions_show(ca).await
     }
 
+    pub fn ca_bgpsec_definitions_at(
+        &self,
+        ca: &CaHandle,
+        point: HistoryPoint,
+    ) -> KrillResult<BgpSecCsrInfoList> {
+        self.ca_manager
+            .get_ca_at(ca, point)
+            .map(|ca| ca.bgpsec_definitions_show())
+    }
+
     pub async fn ca_bgpsec_definitions_update(
         &self,
         ca: CaHandle,
@@ -1266,6 +1663,16 @@ impl KrillServer {
         Ok(ca.configured_roas())
     }
 
+    pub fn ca_routes_at(
+        &self,
+        handle: &CaHandle,
+        point: HistoryPoint,
+    ) -> KrillResult<Vec<ConfiguredRoa>> {
+        let ca = self.ca_manager.get_ca_at(handle, point)?;
+
+        Ok(ca.configured_roas())
+    }
+
     pub async fn ca_routes_bgp_analysis(
         &self,
         handle: &CaHandle,
@@ -1406,4 +1813,175 @@ impl KrillServer {
     }
 }
 
+/// # Handle RPKI Signed Checklist requests
+impl KrillServer {
+    /// List all RSCs signed by a CA
+    pub async fn rsc_list(&self, ca: CaHandle) -> KrillResult<RscList> {
+        let ca = self.ca_manager.get_ca(&ca).await?;
+        Ok(ca.rsc_list())
+    }
+
+    /// Show the RSC objects signed by a CA for a checklist
+    pub async fn rsc_show(
+        &self,
+        ca: CaHandle,
+        name: RscName,
+    ) -> KrillResult<SignedRsc> {
+        let ca = self.ca_manager.get_ca(&ca).await?;
+        ca.rsc_show(&name)
+    }
+
+    /// Sign an RSC
+    pub async fn rsc_sign(
+        &self,
+        ca: CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.ca_manager.rsc_sign(ca, name, request, actor).await
+    }
+
+    /// Co-sign an RSC for resources which span multiple CAs
+    pub async fn rsc_cosign(
+        &self,
+        ca: CaHandle,
+        name: RscName,
+        request: RscSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.ca_manager.rsc_cosign(ca, name, request, actor).await
+    }
+}
+
+/// # Handle signed geofeed requests
+impl KrillServer {
+    /// List the geofeeds signed by a CA
+    pub async fn geofeed_list(
+        &self,
+        ca: CaHandle,
+    ) -> KrillResult<GeofeedList> {
+        let ca = self.ca_manager.get_ca(&ca).await?;
+        Ok(ca.geofeed_list())
+    }
+
+    /// Show a geofeed signed by a CA
+    pub async fn geofeed_show(
+        &self,
+        ca: CaHandle,
+        number: u64,
+    ) -> KrillResult<SignedGeofeed> {
+        let ca = self.ca_manager.get_ca(&ca).await?;
+        ca.geofeed_show(number)
+    }
+
+    /// Sign a geofeed
+    pub async fn geofeed_sign(
+        &self,
+        ca: CaHandle,
+        request: GeofeedSignRequest,
+        actor: &Actor,
+    ) -> KrillResult<SignedGeofeeds> {
+        self.ca_manager.geofeed_sign(ca, request, actor).await
+    }
+}
+
+/// # High availability
+impl KrillServer {
+    /// Returns whether this server may make changes, i.e. whether it is
+    /// the leader or the only node.
+    pub fn is_leader(&self) -> bool {
+        self.ha.as_ref().map_or(true, |ha| ha.is_leader())
+    }
+
+    /// Verifies that this server may make changes right now.
+    pub fn check_leader(&self) -> KrillEmptyResult {
+        match &self.ha {
+            Some(ha) => ha.check_leader(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the leader election, if this server is one of several
+    /// nodes sharing the same storage.
+    pub fn leader_election(&self) -> Option<&LeaderElection> {
+        self.ha.as_deref()
+    }
+
+    pub fn ha_status(&self) -> HaStatus {
+        match &self.ha {
+            Some(ha) => ha.status(),
+            None => HaStatus::standalone(),
+        }
+    }
+}
+
+/// # Task queue
+impl KrillServer {
+    /// Returns all running and pending tasks.
+    pub fn tasks(&self) -> KrillResult<TaskList> {
+        self.mq.list()
+    }
+
+    /// Returns the running and pending tasks with the given name.
+    pub fn task(&self, name: &str) -> KrillResult<TaskDetails> {
+        self.mq.show(name)
+    }
+
+    /// Reschedules the task with the given name to run now.
+    pub fn task_reschedule(&self, name: &str) -> KrillResult<()> {
+        self.mq.reschedule_now(name)
+    }
+
+    /// Removes the task with the given name from the queue.
+    pub fn task_cancel(&self, name: &str) -> KrillResult<()> {
+        self.mq.cancel(name)
+    }
+
+    /// Returns the number of failed tasks that are being retried.
+    pub fn tasks_retrying(&self) -> KrillResult<usize> {
+        self.mq.retrying_count()
+    }
+
+    /// Returns the number of pending and running tasks, by kind.
+    pub fn task_depths(&self) -> KrillResult<BTreeMap<String, TaskDepth>> {
+        self.mq.depths()
+    }
+
+    /// Returns how long tasks took to run since the server started, by
+    /// kind.
+    pub fn task_run_stats(&self) -> BTreeMap<&'static str, TaskRunStats> {
+        self.mq.run_stats()
+    }
+
+    /// Returns the tasks which failed more often than their retry policy
+    /// allows.
+    pub fn dead_letter_tasks(&self) -> KrillResult<DeadLetterList> {
+        self.mq.dead_letters()
+    }
+
+    /// Moves the dead letter task with the given name back onto the queue.
+    pub fn dead_letter_requeue(&self, name: &str) -> KrillResult<()> {
+        self.mq.requeue(name)
+    }
+
+    /// Removes the dead letter task with the given name.
+    pub fn dead_letter_discard(&self, name: &str) -> KrillResult<()> {
+        self.mq.discard(name)
+    }
+}
+
+/// # Backups
+impl KrillServer {
+    /// Writes a backup of all stored data to `out`. This blocks until the
+    /// backup is complete, so it should not be called from async code.
+    ///
+    /// Only the leader can keep out changes while the data is read, so
+    /// this fails on standby nodes.
+    pub fn backup(&self, out: impl io::Write) -> KrillResult<BackupManifest> {
+        self.check_leader()?;
+        backup::write_backup(&self.config.storage_uri, out)
+    }
+}
+
 // Tested through integration tests
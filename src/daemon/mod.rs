@@ -1,6 +1,9 @@
 pub mod auth;
+pub mod backup;
 pub mod ca;
 pub mod config;
+pub mod eventstream;
+pub mod ha;
 pub mod http;
 pub mod krillserver;
 pub mod mq;
@@ -3,28 +3,37 @@
 //! signed material, or asking a newly added parent for resource
 //! entitlements.
 
-use std::{fmt, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::RwLock,
+    time::Duration,
+};
 
 use url::Url;
 
 use kvx::{
     queue::{Queue, RunningTask, ScheduleMode},
-    segment, Segment, SegmentBuf,
+    segment, Scope, Segment, SegmentBuf,
 };
 
 use rpki::{
     ca::{
-        idexchange::{CaHandle, ParentHandle},
+        idexchange::{CaHandle, ChildHandle, ParentHandle},
         provisioning::{ResourceClassName, RevocationRequest},
     },
     repository::x509::Time,
 };
 
 use crate::{
-    commons::api::Timestamp,
+    commons::api::{
+        DeadLetterList, DeadLetterTask, TaskDetails, TaskInfo, TaskList,
+        TaskOutcome, TaskOutcomeResult, TaskState, Timestamp,
+    },
     commons::eventsourcing,
     commons::{eventsourcing::Aggregate, Error, KrillResult},
-    constants::TASK_QUEUE_NS,
+    constants::{TASK_DEAD_LETTER_NS, TASK_QUEUE_NS},
     daemon::ca::{CertAuth, CertAuthEvent},
     ta::{ta_handle, TrustAnchorProxy, TrustAnchorProxyEvent},
 };
@@ -88,11 +97,14 @@ pub enum Task {
 
     RepublishIfNeeded,
     RenewObjectsIfNeeded,
+    RollKeysIfNeeded,
 
     RefreshAnnouncementsInfo,
 
     UpdateSnapshots,
 
+    CompactHistory,
+
     RrdpUpdateIfNeeded,
 
     #[cfg(feature = "multi-user")]
@@ -100,6 +112,73 @@ pub enum Task {
 }
 
 impl Task {
+    /// The kinds of tasks which can fail, and for which a retry policy can
+    /// be configured.
+    pub const RETRYABLE_KINDS: &'static [&'static str] =
+        &["sync_repo", "sync_parent", "rrdp_update_if_needed"];
+
+    /// Returns whether this task keeps a CA in sync, so that it must stay
+    /// on the queue even if it is moved to the dead letters.
+    pub fn is_recurring(&self) -> bool {
+        matches!(self, Task::SyncRepo { .. } | Task::SyncParent { .. })
+    }
+
+    /// Returns the kind of this task, as used in its serialization and in
+    /// the configuration of retry policies.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Task::QueueStartTasks => "queue_start_tasks",
+            Task::SyncRepo { .. } => "sync_repo",
+            Task::SyncParent { .. } => "sync_parent",
+            Task::ResourceClassRemoved { .. } => "resource_class_removed",
+            Task::UnexpectedKey { .. } => "unexpected_key",
+            Task::SyncTrustAnchorProxySignerIfPossible => {
+                "sync_trust_anchor_proxy_signer_if_possible"
+            }
+            Task::SuspendChildrenIfNeeded { .. } => {
+                "suspend_children_if_needed"
+            }
+            Task::RenewTestbedTa => "renew_testbed_ta",
+            Task::RepublishIfNeeded => "republish_if_needed",
+            Task::RenewObjectsIfNeeded => "renew_objects_if_needed",
+            Task::RollKeysIfNeeded => "roll_keys_if_needed",
+            Task::RefreshAnnouncementsInfo => "refresh_announcements_info",
+            Task::UpdateSnapshots => "update_snapshots",
+            Task::CompactHistory => "compact_history",
+            Task::RrdpUpdateIfNeeded => "rrdp_update_if_needed",
+            #[cfg(feature = "multi-user")]
+            Task::SweepLoginCache => "sweep_login_cache",
+        }
+    }
+
+    /// Returns the CA this task is for, if any. Tasks for the same CA are
+    /// never run at the same time.
+    pub fn ca_handle(&self) -> Option<&CaHandle> {
+        match self {
+            Task::SyncRepo { ca_handle, .. }
+            | Task::SyncParent { ca_handle, .. }
+            | Task::ResourceClassRemoved { ca_handle, .. }
+            | Task::UnexpectedKey { ca_handle, .. }
+            | Task::SuspendChildrenIfNeeded { ca_handle } => Some(ca_handle),
+            _ => None,
+        }
+    }
+
+    /// Returns this task for the new handle of a renamed CA.
+    pub fn for_renamed_ca(mut self, new_handle: &CaHandle) -> Self {
+        match &mut self {
+            Task::SyncRepo { ca_handle, .. }
+            | Task::SyncParent { ca_handle, .. }
+            | Task::ResourceClassRemoved { ca_handle, .. }
+            | Task::UnexpectedKey { ca_handle, .. }
+            | Task::SuspendChildrenIfNeeded { ca_handle } => {
+                *ca_handle = new_handle.clone()
+            }
+            _ => {}
+        }
+        self
+    }
+
     fn name(&self) -> KrillResult<SegmentBuf> {
         match self {
             Task::SyncRepo { ca_handle: ca, .. } => {
@@ -125,6 +204,9 @@ impl Task {
             Task::RenewObjectsIfNeeded => {
                 Ok(segment!("all_cas_renew_objects_if_needed").to_owned())
             }
+            Task::RollKeysIfNeeded => {
+                Ok(segment!("all_cas_roll_keys_if_needed").to_owned())
+            }
             Task::ResourceClassRemoved {
                 ca_handle: ca,
                 parent,
@@ -151,6 +233,9 @@ impl Task {
             Task::UpdateSnapshots => {
                 Ok(segment!("update_stored_snapshots").to_owned())
             }
+            Task::CompactHistory => {
+                Ok(segment!("compact_ca_history").to_owned())
+            }
             Task::RrdpUpdateIfNeeded => {
                 Ok(segment!("update_rrdp_if_needed").to_owned())
             }
@@ -199,12 +284,18 @@ impl fmt::Display for Task {
             Task::RenewObjectsIfNeeded => {
                 write!(f, "let CAs renew their signed objects if needed")
             }
+            Task::RollKeysIfNeeded => {
+                write!(f, "let CAs roll their keys if their policy says so")
+            }
             Task::RefreshAnnouncementsInfo => {
                 write!(f, "check for new announcement info")
             }
             Task::UpdateSnapshots => {
                 write!(f, "update repository content snapshot on disk")
             }
+            Task::CompactHistory => {
+                write!(f, "archive old commands in the history of CAs")
+            }
             Task::RrdpUpdateIfNeeded => {
                 write!(f, "create new RRDP delta, if needed")
             }
@@ -231,20 +322,107 @@ pub enum TaskResult {
     Done,                     // finished, nothing more to do
     FollowUp(Task, Priority), // finished, follow-up should be scheduled
     Reschedule(Priority),     // not finished, should be rescheduled
+    Failed(String),           // failed, retry according to the policy
+}
+
+//------------ TaskRetryPolicy ----------------------------------------------
+
+/// Determines when a failed task is retried, and when it is given up on
+/// and moved to the dead letters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskRetryPolicy {
+    pub initial_delay_seconds: u64,
+    pub multiplier: f64,
+    pub max_delay_seconds: u64,
+
+    // None means that the task is retried forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl TaskRetryPolicy {
+    /// Returns the delay before the next attempt, after the given number
+    /// of consecutive failed attempts.
+    pub fn delay_seconds(&self, attempts: u32) -> u64 {
+        let exponent = attempts.saturating_sub(1).min(64) as i32;
+        let delay = self.initial_delay_seconds as f64
+            * self.multiplier.powi(exponent);
+        if delay < self.max_delay_seconds as f64 {
+            delay as u64
+        } else {
+            self.max_delay_seconds
+        }
+    }
+
+    /// Returns whether the task should be given up on after the given
+    /// number of consecutive failed attempts.
+    pub fn is_exhausted(&self, attempts: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempts >= max)
+    }
 }
 
 //------------ TaskQueue ----------------------------------------------------
 
 #[derive(Debug)]
 pub struct TaskQueue {
-    q: kvx::KeyValueStore,
+    q: eventsourcing::KeyValueStore,
+
+    // Tasks which failed too often, by task name.
+    dead_letters: eventsourcing::KeyValueStore,
+
+    // The outcome of the last run of each task, by task name. This is
+    // kept in memory only and is used for reporting to operators.
+    results: RwLock<HashMap<String, TaskOutcome>>,
+
+    // How long tasks took to run since the server started, by kind.
+    run_stats: RwLock<HashMap<&'static str, TaskRunStats>>,
+}
+
+/// The consecutive failures of a task that is being retried.
+///
+/// These are stored with the queued task, next to its fields, so that
+/// they survive a restart of the server or a change of leader.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct TaskFailures {
+    attempts: u32,
+    first_failure: Timestamp,
+}
+
+impl TaskFailures {
+    const FIELD: &'static str = "failures";
+
+    /// Returns the failures stored with a queued task, if any.
+    fn from_value(value: &serde_json::Value) -> Option<Self> {
+        value.get(Self::FIELD).and_then(|failures| {
+            serde_json::from_value(failures.clone()).ok()
+        })
+    }
+
+    /// Returns the value for a queued task with these failures.
+    fn task_value(self, task: &Task) -> KrillResult<serde_json::Value> {
+        let mut value = serde_json::to_value(task).map_err(|e| {
+            Error::Custom(format!("could not serialize task. error: {}", e))
+        })?;
+        if let serde_json::Value::Object(fields) = &mut value {
+            fields.insert(Self::FIELD.to_string(), serde_json::json!(self));
+        }
+        Ok(value)
+    }
 }
 
 impl TaskQueue {
     pub fn new(storage_uri: &Url) -> KrillResult<Self> {
-        kvx::KeyValueStore::new(storage_uri, TASK_QUEUE_NS)
-            .map(|q| TaskQueue { q })
-            .map_err(Error::from)
+        let q =
+            eventsourcing::KeyValueStore::create(storage_uri, TASK_QUEUE_NS)?;
+        let dead_letters = eventsourcing::KeyValueStore::create(
+            storage_uri,
+            TASK_DEAD_LETTER_NS,
+        )?;
+        Ok(TaskQueue {
+            q,
+            dead_letters,
+            results: RwLock::new(HashMap::new()),
+            run_stats: RwLock::new(HashMap::new()),
+        })
     }
 }
 impl TaskQueue {
@@ -341,18 +519,32 @@ impl TaskQueue {
     }
 
     /// Finish a running task, without rescheduling it.
+    ///
+    /// If the task was rescheduled or cancelled by an operator while it
+    /// was running, then there is nothing left to finish.
     pub fn finish(&self, task: &kvx::Key) -> KrillResult<()> {
         debug!("Finish task: {}", task);
+        if !self.q.is_running_task(task)? {
+            warn!("Task {} was rescheduled or cancelled while running", task);
+            return Ok(());
+        }
         self.q.finish_running_task(task).map_err(Error::from)
     }
 
     /// Reschedule a running task, without finishing it.
+    ///
+    /// If the task was rescheduled or cancelled by an operator while it
+    /// was running, then the operator's decision stands.
     pub fn reschedule(
         &self,
         task: &kvx::Key,
         priority: Priority,
     ) -> KrillResult<()> {
         debug!("Reschedule task: {} to: {}", task, priority);
+        if !self.q.is_running_task(task)? {
+            warn!("Task {} was rescheduled or cancelled while running", task);
+            return Ok(());
+        }
         self.q
             .reschedule_running_task(task, Some(priority.to_millis()))
             .map_err(Error::from)
@@ -377,6 +569,395 @@ impl TaskQueue {
 
         Ok(())
     }
+
+    /// Moves the queued tasks for a renamed CA to its new handle.
+    ///
+    /// Running tasks are not interrupted, but they are not rescheduled
+    /// when they are done. Their tasks for the new handle run now instead.
+    pub fn rename_ca(
+        &self,
+        from: &CaHandle,
+        to: &CaHandle,
+    ) -> KrillResult<()> {
+        for queued in self.q.queued_tasks()? {
+            let task = match serde_json::from_value::<Task>(queued.value) {
+                Ok(task) if task.ca_handle() == Some(from) => task,
+                _ => continue,
+            };
+            let priority = if queued.running {
+                now()
+            } else {
+                Priority::from_timestamp_ms(queued.timestamp_millis)
+            };
+
+            debug!("Move task {} to renamed CA '{}'", queued.name, to);
+            self.q.drop_task_by_name(&queued.name)?;
+            self.schedule(task.for_renamed_ca(to), priority)?;
+        }
+        Ok(())
+    }
+}
+
+/// Introspection and control of queued tasks by operators.
+impl TaskQueue {
+    /// Remembers the outcome of running the task with the given name.
+    /// Failures are recorded by [`TaskQueue::fail`] instead.
+    ///
+    /// A task that succeeds is no longer a dead letter. This happens for
+    /// recurring tasks, which stay on the queue when they are moved to the
+    /// dead letters.
+    pub fn record_result(&self, name: &str, result: &TaskResult) {
+        let result = match result {
+            TaskResult::Done => TaskOutcomeResult::Done,
+            TaskResult::FollowUp(task, priority) => {
+                TaskOutcomeResult::FollowUp {
+                    task: task
+                        .name()
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|_| task.to_string()),
+                    scheduled: priority.into(),
+                }
+            }
+            TaskResult::Reschedule(priority) => {
+                TaskOutcomeResult::Rescheduled {
+                    scheduled: priority.into(),
+                }
+            }
+            TaskResult::Failed(_) => return,
+        };
+        if !matches!(result, TaskOutcomeResult::Rescheduled { .. }) {
+            if let Err(e) = self.discard_recovered(name) {
+                error!("Could not remove dead letter task {}: {}", name, e);
+            }
+        }
+        self.record_outcome(name, result);
+    }
+
+    fn record_outcome(&self, name: &str, result: TaskOutcomeResult) {
+        let outcome = TaskOutcome {
+            finished: Timestamp::now(),
+            result,
+        };
+        self.results
+            .write()
+            .unwrap()
+            .insert(name.to_string(), outcome);
+    }
+
+    /// Returns all running and pending tasks.
+    pub fn list(&self) -> KrillResult<TaskList> {
+        let now = Timestamp::now();
+        let results = self.results.read().unwrap();
+
+        let tasks = self
+            .q
+            .queued_tasks()?
+            .into_iter()
+            .map(|queued| {
+                let name = queued.name.to_string();
+                let description = match serde_json::from_value::<Task>(
+                    queued.value.clone(),
+                ) {
+                    Ok(task) => task.to_string(),
+                    Err(_) => "unknown task".to_string(),
+                };
+                let timestamp: Timestamp =
+                    (&Priority::from_timestamp_ms(queued.timestamp_millis))
+                        .into();
+
+                let (state, scheduled, started, running_seconds) =
+                    if queued.running {
+                        (
+                            TaskState::Running,
+                            None,
+                            Some(timestamp),
+                            Some(i64::from(now) - i64::from(timestamp)),
+                        )
+                    } else {
+                        (TaskState::Pending, Some(timestamp), None, None)
+                    };
+
+                TaskInfo {
+                    failed_attempts: TaskFailures::from_value(&queued.value)
+                        .map(|failures| failures.attempts),
+                    last_result: results.get(&name).cloned(),
+                    name,
+                    description,
+                    task: queued.value,
+                    state,
+                    scheduled,
+                    started,
+                    running_seconds,
+                }
+            })
+            .collect();
+
+        Ok(TaskList { tasks })
+    }
+
+    /// Returns the running and pending tasks with the given name.
+    pub fn show(&self, name: &str) -> KrillResult<TaskDetails> {
+        let mut tasks = self.list()?.tasks;
+        tasks.retain(|task| task.name == name);
+        if tasks.is_empty() {
+            Err(Error::ApiUnknownResource)
+        } else {
+            Ok(TaskDetails { tasks })
+        }
+    }
+
+    /// Reschedules the task with the given name to run now. If the task
+    /// is currently running, then it will be picked up again.
+    pub fn reschedule_now(&self, name: &str) -> KrillResult<()> {
+        let name = Self::task_name(name)?;
+        info!("Rescheduling task {} to run now", name);
+        if self.q.reschedule_task_by_name(&name, now().to_millis())? {
+            Ok(())
+        } else {
+            Err(Error::ApiUnknownResource)
+        }
+    }
+
+    /// Removes the task with the given name from the queue. Note that a
+    /// task which is currently running will not be interrupted, but it
+    /// will not be rescheduled when it is done. Its follow-up tasks, if
+    /// any, will still be scheduled.
+    pub fn cancel(&self, name: &str) -> KrillResult<()> {
+        let name = Self::task_name(name)?;
+        info!("Cancelling task {}", name);
+        if self.q.drop_task_by_name(&name)? {
+            Ok(())
+        } else {
+            Err(Error::ApiUnknownResource)
+        }
+    }
+
+    fn task_name(name: &str) -> KrillResult<SegmentBuf> {
+        SegmentBuf::from_str(name).map_err(|_| Error::ApiUnknownResource)
+    }
+}
+
+/// Retries of failed tasks, and the dead letters.
+impl TaskQueue {
+    /// Handles a failed run of a running task. The task is rescheduled
+    /// according to the retry policy, or moved to the dead letters if it
+    /// failed more often than the policy allows.
+    ///
+    /// Recurring tasks are not taken off the queue when they are moved to
+    /// the dead letters, as that would silently stop keeping the CA in
+    /// sync. They are rescheduled for the normal `refresh` instead.
+    pub fn fail(
+        &self,
+        running: &kvx::Key,
+        task: &Task,
+        error: String,
+        policy: &TaskRetryPolicy,
+        refresh: Priority,
+    ) -> KrillResult<()> {
+        let name = task.name()?;
+        let now = Timestamp::now();
+
+        let mut failures = self
+            .q
+            .get::<serde_json::Value>(running)?
+            .and_then(|value| TaskFailures::from_value(&value))
+            .unwrap_or(TaskFailures {
+                attempts: 0,
+                first_failure: now,
+            });
+        failures.attempts += 1;
+        let attempts = failures.attempts;
+
+        if policy.is_exhausted(attempts) {
+            warn!(
+                "Task {} failed {} times, moving it to the dead letters. Error: {}",
+                name, attempts, error
+            );
+            let dead = DeadLetterTask {
+                name: name.to_string(),
+                description: task.to_string(),
+                task: serde_json::to_value(task).map_err(|e| {
+                    Error::Custom(format!(
+                        "could not serialize task {}. error: {}",
+                        name, e
+                    ))
+                })?,
+                attempts,
+                first_failure: failures.first_failure,
+                last_failure: now,
+                last_error: error.clone(),
+            };
+            self.dead_letters
+                .store(&kvx::Key::new_global(name.clone()), &dead)?;
+            if task.is_recurring() {
+                warn!(
+                    "Task {} will still run at {}, it keeps the CA in sync",
+                    name, refresh
+                );
+                self.retry(running, failures.task_value(task)?, refresh)?;
+            } else {
+                self.finish(running)?;
+            }
+            self.record_outcome(
+                name.as_str(),
+                TaskOutcomeResult::DeadLettered { error, attempts },
+            );
+        } else {
+            let delay = policy.delay_seconds(attempts);
+            let retry = in_seconds(i64::try_from(delay).unwrap_or(i64::MAX));
+            warn!(
+                "Task {} failed (attempt {}), will retry at {}. Error: {}",
+                name, attempts, retry, error
+            );
+            self.retry(running, failures.task_value(task)?, retry)?;
+            self.record_outcome(
+                name.as_str(),
+                TaskOutcomeResult::Failed {
+                    error,
+                    attempts,
+                    retry: (&retry).into(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Reschedules a failed running task, with its failures.
+    ///
+    /// If the task was rescheduled or cancelled by an operator while it
+    /// was running, then the operator's decision stands.
+    fn retry(
+        &self,
+        running: &kvx::Key,
+        value: serde_json::Value,
+        priority: Priority,
+    ) -> KrillResult<()> {
+        if !self.q.reschedule_running_task_with_value(
+            running,
+            value,
+            priority.to_millis(),
+        )? {
+            warn!(
+                "Task {} was rescheduled or cancelled while running",
+                running
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the number of tasks that failed and are being retried.
+    pub fn retrying_count(&self) -> KrillResult<usize> {
+        Ok(self
+            .q
+            .queued_tasks()?
+            .iter()
+            .filter(|queued| {
+                TaskFailures::from_value(&queued.value).is_some()
+            })
+            .count())
+    }
+
+    /// Returns all tasks that were moved to the dead letters.
+    pub fn dead_letters(&self) -> KrillResult<DeadLetterList> {
+        let mut tasks = vec![];
+        for key in self.dead_letters.keys(&Scope::global(), "")? {
+            if let Some(task) = self.dead_letters.get(&key)? {
+                tasks.push(task);
+            }
+        }
+        tasks.sort_by(|a: &DeadLetterTask, b| a.name.cmp(&b.name));
+        Ok(DeadLetterList { tasks })
+    }
+
+    /// Moves the dead letter task with the given name back onto the queue,
+    /// to run now. This is meant to be used once the underlying issue is
+    /// fixed.
+    pub fn requeue(&self, name: &str) -> KrillResult<()> {
+        let key = kvx::Key::new_global(Self::task_name(name)?);
+        let dead: DeadLetterTask = self
+            .dead_letters
+            .get(&key)?
+            .ok_or(Error::ApiUnknownResource)?;
+        let task: Task = serde_json::from_value(dead.task).map_err(|e| {
+            Error::Custom(format!(
+                "could not parse dead letter task {}. error: {}",
+                name, e
+            ))
+        })?;
+
+        info!("Requeueing dead letter task {}", name);
+        self.schedule(task, now())?;
+        self.dead_letters.drop_key(&key)?;
+        Ok(())
+    }
+
+    /// Removes the dead letter task with the given name, if there is one.
+    fn discard_recovered(&self, name: &str) -> KrillResult<()> {
+        let key = kvx::Key::new_global(Self::task_name(name)?);
+        if self.dead_letters.has(&key)? {
+            info!(
+                "Task {} succeeded, removing it from the dead letters",
+                name
+            );
+            self.dead_letters.drop_key(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the dead letter task with the given name.
+    pub fn discard(&self, name: &str) -> KrillResult<()> {
+        let key = kvx::Key::new_global(Self::task_name(name)?);
+        if !self.dead_letters.has(&key)? {
+            return Err(Error::ApiUnknownResource);
+        }
+        info!("Discarding dead letter task {}", name);
+        self.dead_letters.drop_key(&key).map_err(Error::from)
+    }
+}
+
+/// Statistics for metrics.
+impl TaskQueue {
+    /// Remembers how long a task of the given kind took to run.
+    pub fn record_run(&self, kind: &'static str, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut run_stats = self.run_stats.write().unwrap();
+        let stats = run_stats.entry(kind).or_default();
+        stats.count += 1;
+        stats.seconds_sum += seconds;
+        if seconds > stats.seconds_max {
+            stats.seconds_max = seconds;
+        }
+    }
+
+    /// Returns how long tasks took to run since the server started, by
+    /// kind.
+    pub fn run_stats(&self) -> BTreeMap<&'static str, TaskRunStats> {
+        self.run_stats
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(kind, stats)| (*kind, *stats))
+            .collect()
+    }
+
+    /// Returns the number of pending and running tasks, by kind.
+    pub fn depths(&self) -> KrillResult<BTreeMap<String, TaskDepth>> {
+        let mut depths: BTreeMap<String, TaskDepth> = BTreeMap::new();
+        for queued in self.q.queued_tasks()? {
+            let kind = queued
+                .value
+                .get("type")
+                .and_then(|kind| kind.as_str())
+                .unwrap_or("unknown");
+            let depth = depths.entry(kind.to_string()).or_default();
+            if queued.running {
+                depth.running += 1;
+            } else {
+                depth.pending += 1;
+            }
+        }
+        Ok(depths)
+    }
 }
 
 /// Implement listening for CertAuth events.
@@ -436,7 +1017,13 @@ impl TaskQueue {
                 )
             }
 
-            CertAuthEvent::ParentRemoved { .. } => self.schedule(
+            CertAuthEvent::ParentRemoved { .. }
+            | CertAuthEvent::RepoMirrorAdded { .. }
+            | CertAuthEvent::RepoMirrorRemoved { .. }
+            | CertAuthEvent::RepoMigrationStarted { .. }
+            | CertAuthEvent::RepoMigrationWithdrawing
+            | CertAuthEvent::RepoMigrationAborted { .. }
+            | CertAuthEvent::Renamed { .. } => self.schedule(
                 Task::SyncRepo {
                     ca_handle,
                     ca_version,
@@ -612,10 +1199,10 @@ impl eventsourcing::PreSaveEventListener<TrustAnchorProxy> for TaskQueue {
         for event in events {
             trace!("Seen TrustAnchorProxy event '{}'", event);
             match event {
-                TrustAnchorProxyEvent::ChildRequestAdded(
-                    _child,
-                    _request,
-                ) => {
+                TrustAnchorProxyEvent::ChildRequestAdded(_, _)
+                | TrustAnchorProxyEvent::ChildResourcesUpdated(_, _)
+                | TrustAnchorProxyEvent::ChildRemoved(_)
+                | TrustAnchorProxyEvent::SignerKeyRollRequestMade(_, _) => {
                     // schedule proxy -> signer sync
                     self.schedule(
                         Task::SyncTrustAnchorProxySignerIfPossible,
@@ -649,15 +1236,36 @@ impl eventsourcing::PreSaveEventListener<TrustAnchorProxy> for TaskQueue {
 impl eventsourcing::PostSaveEventListener<TrustAnchorProxy> for TaskQueue {
     fn listen(
         &self,
-        _proxy: &TrustAnchorProxy,
+        proxy: &TrustAnchorProxy,
         events: &[TrustAnchorProxyEvent],
     ) {
         for event in events {
             match event {
                 TrustAnchorProxyEvent::SignerResponseReceived(response) => {
                     // Schedule child->ta sync(s) now that there is a
-                    // response.
-                    for ca in response.content().child_responses.keys() {
+                    // response. If a new TA key was activated, then all
+                    // children need to pick up their re-issued certificate.
+                    // Likewise for children whose resources were updated.
+                    let children: Vec<&ChildHandle> =
+                        if response.content().activated.is_some() {
+                            proxy.child_handles().collect()
+                        } else {
+                            let content = response.content();
+                            content
+                                .child_responses
+                                .keys()
+                                .chain(
+                                    content.updated_children.iter().filter(
+                                        |child| {
+                                            !content
+                                                .child_responses
+                                                .contains_key(*child)
+                                        },
+                                    ),
+                                )
+                                .collect()
+                        };
+                    for ca in children {
                         trace!(
                             "Received signed response for TA child {}",
                             ca
@@ -687,6 +1295,25 @@ impl eventsourcing::PostSaveEventListener<TrustAnchorProxy> for TaskQueue {
     }
 }
 
+//------------ TaskRunStats --------------------------------------------------
+
+/// How long tasks of a kind took to run.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TaskRunStats {
+    pub count: u64,
+    pub seconds_sum: f64,
+    pub seconds_max: f64,
+}
+
+//------------ TaskDepth -----------------------------------------------------
+
+/// The number of queued tasks of a kind.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TaskDepth {
+    pub pending: usize,
+    pub running: usize,
+}
+
 //------------ Priority ------------------------------------------------------
 
 /// Can be used as a priority value for [`PriorityQueue`]. Meaning that the
@@ -766,3 +1393,259 @@ impl From<Time> for Priority {
         Priority(time.timestamp())
     }
 }
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test;
+
+    fn policy(max_attempts: Option<u32>) -> TaskRetryPolicy {
+        TaskRetryPolicy {
+            initial_delay_seconds: 60,
+            multiplier: 2.0,
+            max_delay_seconds: 300,
+            max_attempts,
+        }
+    }
+
+    #[test]
+    fn retry_policy_backs_off_exponentially() {
+        let policy = policy(Some(3));
+        assert_eq!(policy.delay_seconds(1), 60);
+        assert_eq!(policy.delay_seconds(2), 120);
+        assert_eq!(policy.delay_seconds(3), 240);
+        assert_eq!(policy.delay_seconds(4), 300);
+        assert_eq!(policy.delay_seconds(1000), 300);
+
+        assert!(!policy.is_exhausted(2));
+        assert!(policy.is_exhausted(3));
+        assert!(!self::policy(None).is_exhausted(u32::MAX));
+    }
+
+    #[test]
+    fn failed_task_is_retried_then_dead_lettered() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+        let task = Task::RepublishIfNeeded;
+        let name = task.name().unwrap().to_string();
+        queue.schedule(task.clone(), now()).unwrap();
+
+        // The first failure reschedules the task.
+        let running = queue.pop().unwrap();
+        queue
+            .fail(
+                &kvx::Key::from(&running),
+                &task,
+                "oops".into(),
+                &policy(Some(2)),
+                in_hours(1),
+            )
+            .unwrap();
+        let list = queue.list().unwrap();
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.tasks[0].state, TaskState::Pending);
+        assert_eq!(list.tasks[0].failed_attempts, Some(1));
+        assert_eq!(queue.retrying_count().unwrap(), 1);
+
+        // The second failure exhausts the policy.
+        queue.reschedule_now(&name).unwrap();
+        let running = queue.pop().unwrap();
+        queue
+            .fail(
+                &kvx::Key::from(&running),
+                &task,
+                "oops".into(),
+                &policy(Some(2)),
+                in_hours(1),
+            )
+            .unwrap();
+        assert!(queue.list().unwrap().tasks.is_empty());
+        assert_eq!(queue.retrying_count().unwrap(), 0);
+
+        let dead = queue.dead_letters().unwrap();
+        assert_eq!(dead.tasks.len(), 1);
+        assert_eq!(dead.tasks[0].name, name);
+        assert_eq!(dead.tasks[0].attempts, 2);
+        assert_eq!(dead.tasks[0].last_error, "oops");
+
+        // Requeueing puts it back on the queue.
+        queue.requeue(&name).unwrap();
+        assert!(queue.dead_letters().unwrap().tasks.is_empty());
+        assert_eq!(queue.list().unwrap().tasks.len(), 1);
+        assert!(queue.requeue(&name).is_err());
+        assert!(queue.discard(&name).is_err());
+    }
+
+    #[test]
+    fn failures_are_kept_across_restarts() {
+        let storage_uri = test::mem_storage();
+        let task = Task::RrdpUpdateIfNeeded;
+        let name = task.name().unwrap().to_string();
+
+        let queue = TaskQueue::new(&storage_uri).unwrap();
+        queue.schedule(task.clone(), now()).unwrap();
+        let running = queue.pop().unwrap();
+        queue
+            .fail(
+                &kvx::Key::from(&running),
+                &task,
+                "oops".into(),
+                &policy(Some(2)),
+                in_hours(1),
+            )
+            .unwrap();
+
+        // A new queue on the same storage, e.g. after a restart or on a
+        // new leader, sees the failed attempt.
+        let queue = TaskQueue::new(&storage_uri).unwrap();
+        assert_eq!(queue.list().unwrap().tasks[0].failed_attempts, Some(1));
+
+        queue.reschedule_now(&name).unwrap();
+        let running = queue.pop().unwrap();
+        assert_eq!(
+            serde_json::from_value::<Task>(running.value.clone()).unwrap(),
+            task
+        );
+        queue
+            .fail(
+                &kvx::Key::from(&running),
+                &task,
+                "oops".into(),
+                &policy(Some(2)),
+                in_hours(1),
+            )
+            .unwrap();
+        assert_eq!(queue.dead_letters().unwrap().tasks[0].attempts, 2);
+    }
+
+    #[test]
+    fn recurring_task_stays_queued_when_dead_lettered() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+        let task = Task::SyncParent {
+            ca_handle: CaHandle::from_str("ca").unwrap(),
+            ca_version: 1,
+            parent: ParentHandle::from_str("parent").unwrap(),
+        };
+        let name = task.name().unwrap().to_string();
+        queue.schedule(task.clone(), now()).unwrap();
+
+        let running = queue.pop().unwrap();
+        queue
+            .fail(
+                &kvx::Key::from(&running),
+                &task,
+                "oops".into(),
+                &policy(Some(1)),
+                in_hours(1),
+            )
+            .unwrap();
+
+        // The task is a dead letter, but it still runs at the normal
+        // refresh.
+        assert_eq!(queue.dead_letters().unwrap().tasks.len(), 1);
+        let list = queue.list().unwrap();
+        assert_eq!(list.tasks.len(), 1);
+        assert_eq!(list.tasks[0].state, TaskState::Pending);
+        assert_eq!(list.tasks[0].failed_attempts, Some(1));
+
+        // Once it succeeds, it is no longer a dead letter and its failures
+        // are forgotten.
+        queue.reschedule_now(&name).unwrap();
+        let running = queue.pop().unwrap();
+        queue.record_result(
+            &name,
+            &TaskResult::FollowUp(task.clone(), in_hours(1)),
+        );
+        queue
+            .schedule_and_finish_existing(task, in_hours(1))
+            .unwrap();
+        assert!(!queue.q.is_running_task(&kvx::Key::from(&running)).unwrap());
+        assert!(queue.dead_letters().unwrap().tasks.is_empty());
+        assert_eq!(queue.list().unwrap().tasks[0].failed_attempts, None);
+        assert_eq!(queue.retrying_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn depths_and_run_stats_by_kind() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+        let ca = CaHandle::from_str("ca").unwrap();
+        let task = Task::SyncRepo {
+            ca_handle: ca.clone(),
+            ca_version: 1,
+        };
+        assert_eq!(task.ca_handle(), Some(&ca));
+        assert_eq!(Task::RepublishIfNeeded.ca_handle(), None);
+
+        queue.schedule(task, now()).unwrap();
+        queue
+            .schedule(Task::RepublishIfNeeded, in_hours(1))
+            .unwrap();
+        queue.pop().unwrap();
+
+        let depths = queue.depths().unwrap();
+        assert_eq!(
+            depths.get("sync_repo"),
+            Some(&TaskDepth {
+                pending: 0,
+                running: 1
+            })
+        );
+        assert_eq!(
+            depths.get("republish_if_needed"),
+            Some(&TaskDepth {
+                pending: 1,
+                running: 0
+            })
+        );
+
+        queue.record_run("sync_repo", Duration::from_secs(2));
+        queue.record_run("sync_repo", Duration::from_secs(4));
+        let stats = queue.run_stats()["sync_repo"];
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.seconds_sum, 6.0);
+        assert_eq!(stats.seconds_max, 4.0);
+    }
+
+    #[test]
+    fn tasks_move_to_renamed_ca() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+        let old = CaHandle::from_str("old").unwrap();
+        let new = CaHandle::from_str("new").unwrap();
+        let sync_repo = |ca_handle: &CaHandle| Task::SyncRepo {
+            ca_handle: ca_handle.clone(),
+            ca_version: 1,
+        };
+        let suspend = |ca_handle: &CaHandle| Task::SuspendChildrenIfNeeded {
+            ca_handle: ca_handle.clone(),
+        };
+
+        queue.schedule(sync_repo(&old), now()).unwrap();
+        queue.pop().unwrap();
+        queue.schedule(suspend(&old), in_hours(1)).unwrap();
+        queue
+            .schedule(Task::RepublishIfNeeded, in_hours(1))
+            .unwrap();
+
+        queue.rename_ca(&old, &new).unwrap();
+
+        let mut tasks: Vec<(Task, TaskState)> = queue
+            .list()
+            .unwrap()
+            .tasks
+            .into_iter()
+            .map(|info| {
+                (serde_json::from_value(info.task).unwrap(), info.state)
+            })
+            .collect();
+        tasks.sort_by_key(|(task, _)| task.kind());
+        assert_eq!(
+            tasks,
+            vec![
+                (Task::RepublishIfNeeded, TaskState::Pending),
+                (suspend(&new), TaskState::Pending),
+                (sync_repo(&new), TaskState::Pending),
+            ]
+        );
+    }
+}
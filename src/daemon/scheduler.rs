@@ -1,10 +1,19 @@
 //! Deal with asynchronous scheduled processes, either triggered by an
 //! event that occurred, or planned (e.g. re-publishing).
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use kvx::Namespace;
-use tokio::time::sleep;
+use kvx::{queue::RunningTask, Namespace};
+use tokio::{
+    select,
+    sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock, Semaphore},
+    task::JoinSet,
+    time::sleep,
+};
 
 use rpki::ca::{
     idexchange::{CaHandle, ParentHandle},
@@ -15,7 +24,7 @@ use url::Url;
 use crate::{
     commons::{
         actor::Actor,
-        api::Timestamp,
+        api::{RepoMigrationPhase, Timestamp},
         bgp::BgpAnalyser,
         crypto::dispatch::signerinfo::SignerInfo,
         error::FatalError,
@@ -24,13 +33,16 @@ use crate::{
     },
     constants::{
         CASERVER_NS, PROPERTIES_NS, PUBSERVER_CONTENT_NS, PUBSERVER_NS,
-        SCHEDULER_INTERVAL_RENEW_MINS, SCHEDULER_INTERVAL_REPUBLISH_MINS,
+        SCHEDULER_INTERVAL_KEY_ROLL_MINS, SCHEDULER_INTERVAL_RENEW_MINS,
+        SCHEDULER_INTERVAL_REPO_MIGRATION_SECS,
+        SCHEDULER_INTERVAL_REPUBLISH_MINS,
         SCHEDULER_RESYNC_REPO_CAS_THRESHOLD,
         SCHEDULER_USE_JITTER_CAS_THRESHOLD, SIGNERS_NS,
     },
     daemon::{
         ca::{CaManager, CertAuth},
         config::Config,
+        ha::LeaderElection,
         mq::{
             in_hours, in_minutes, in_seconds, in_weeks, now, Task, TaskQueue,
         },
@@ -44,6 +56,62 @@ use crate::daemon::auth::common::session::LoginSessionCache;
 
 use super::mq::TaskResult;
 
+/// Tasks in the same lane are run one at a time.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Lane {
+    /// All tasks for a CA.
+    Ca(CaHandle),
+
+    /// A task that is not for a specific CA, by its name.
+    Task(String),
+}
+
+impl Lane {
+    fn of(task_name: &str, task: &Task) -> Self {
+        match task.ca_handle() {
+            Some(ca) => Lane::Ca(ca.clone()),
+            None => Lane::Task(task_name.to_string()),
+        }
+    }
+
+    /// Waits until a task in this lane may run.
+    ///
+    /// Some tasks are not for a specific CA but touch all CAs. These must
+    /// not run while a task for any CA is running, and vice versa. So,
+    /// tasks for a CA share the lock, and these tasks hold it exclusively.
+    async fn enter(&self, task: &Task, cas: Arc<RwLock<()>>) -> LaneGuard {
+        match self {
+            Lane::Ca(_) => LaneGuard {
+                _shared: Some(cas.read_owned().await),
+                _exclusive: None,
+            },
+            Lane::Task(_) if Self::touches_all_cas(task) => LaneGuard {
+                _shared: None,
+                _exclusive: Some(cas.write_owned().await),
+            },
+            Lane::Task(_) => LaneGuard {
+                _shared: None,
+                _exclusive: None,
+            },
+        }
+    }
+
+    fn touches_all_cas(task: &Task) -> bool {
+        matches!(
+            task,
+            Task::RepublishIfNeeded
+                | Task::RenewObjectsIfNeeded
+                | Task::RollKeysIfNeeded
+        )
+    }
+}
+
+/// Held while a task runs, see [`Lane::enter`].
+struct LaneGuard {
+    _shared: Option<OwnedRwLockReadGuard<()>>,
+    _exclusive: Option<OwnedRwLockWriteGuard<()>>,
+}
+
 pub struct Scheduler {
     tasks: Arc<TaskQueue>,
     ca_manager: Arc<CaManager>,
@@ -54,10 +122,13 @@ pub struct Scheduler {
     login_session_cache: Arc<LoginSessionCache>,
     config: Arc<Config>,
     system_actor: Actor,
+    // Only the leader runs tasks, if leader election is configured
+    ha: Option<Arc<LeaderElection>>,
     started: Timestamp,
 }
 
 impl Scheduler {
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         tasks: Arc<TaskQueue>,
         ca_manager: Arc<CaManager>,
@@ -68,6 +139,7 @@ impl Scheduler {
         >,
         config: Arc<Config>,
         system_actor: Actor,
+        ha: Option<Arc<LeaderElection>>,
     ) -> Self {
         Scheduler {
             tasks,
@@ -78,63 +150,190 @@ impl Scheduler {
             login_session_cache,
             config,
             system_actor,
+            ha,
             started: Timestamp::now(),
         }
     }
 
     /// Run the scheduler in the background. It will sweep the message queue
     /// for tasks and re-schedule new tasks as needed.
-    pub async fn run(&self) {
+    ///
+    /// Each task is run in its own tokio task, and up to
+    /// `scheduler_workers` tasks are run concurrently, so that a slow parent
+    /// or repository for one CA does not hold up other CAs. Tasks for the
+    /// same CA are run one at a time, and tasks which touch all CAs do not
+    /// run concurrently with any task for a CA. Tasks that have to wait for
+    /// another task to finish are kept, claimed, in a small backlog.
+    pub async fn run(self: Arc<Self>) {
+        let workers = self.config.scheduler_workers;
+        let permits = Arc::new(Semaphore::new(workers));
+        let cas = Arc::new(RwLock::new(()));
+        let mut leading = self.ha.is_none();
+        let mut running: JoinSet<Lane> = JoinSet::new();
+        let mut busy: HashSet<Lane> = HashSet::new();
+        let mut backlog: VecDeque<(kvx::Key, String, Task)> = VecDeque::new();
+
         loop {
-            while let Some(running_task) = self.tasks.pop() {
-                // remember the key so we can finish or re-schedule the task.
-                let task_key = kvx::Key::from(&running_task);
+            leading = self.lead(leading);
+            if !leading {
+                // The new leader reschedules the tasks we claimed.
+                backlog.clear();
+            }
 
-                match serde_json::from_value(running_task.value) {
+            while let Some(permit) = leading
+                .then(|| permits.clone().try_acquire_owned().ok())
+                .flatten()
+            {
+                let next = match backlog.iter().position(|(_, name, task)| {
+                    !busy.contains(&Lane::of(name, task))
+                }) {
+                    Some(pos) => backlog.remove(pos),
+                    None if backlog.len() < workers => {
+                        self.next_task(leading).map(Self::parse_task)
+                    }
+                    None => None,
+                };
+
+                let (task_key, task_name, task) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                let lane = Lane::of(&task_name, &task);
+                if busy.contains(&lane) {
+                    backlog.push_back((task_key, task_name, task));
+                } else {
+                    busy.insert(lane.clone());
+                    let scheduler = self.clone();
+                    let cas = cas.clone();
+                    running.spawn(async move {
+                        let guard = lane.enter(&task, cas).await;
+                        scheduler.run_task(task_key, task_name, task).await;
+                        drop(guard);
+                        drop(permit);
+                        lane
+                    });
+                }
+            }
+
+            select! {
+                Some(res) = running.join_next() => match res {
+                    Ok(lane) => {
+                        busy.remove(&lane);
+                    }
                     Err(e) => {
-                        // If we cannot parse the value of this task, then we
-                        // have a major
-                        // issue. Essentially, this can only happen if we did
-                        // a Krill upgrade
-                        // to a new version that no longer understands
-                        // existing tasks.
-                        //
-                        // So, if we ever change the content of tasks then we
-                        // should make sure that Krill
-                        // is either backward compatible, or the task queue is
-                        // migrated on upgrade.
-                        error!("Fatal error parsing task: {}. Krill will now stop! This may be because this task is not for this Krill version ({}). If this issue persists, then try deleting this task from storage, it will appear in the 'tasks' dir if you use disk storage. The error was {}", task_key, KrillVersion::code_version(), e);
+                        error!("Task worker failed: {}. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks.", e);
                         std::process::exit(1);
                     }
-                    Ok(task) => match self.process_task(task).await {
-                        Ok(result) => {
-                            if let Err(e) = match result {
-                                TaskResult::Done => {
-                                    self.tasks.finish(&task_key)
-                                }
-                                TaskResult::FollowUp(task, priority) => {
-                                    self.tasks.schedule_and_finish_existing(
-                                        task, priority,
-                                    )
-                                }
-                                TaskResult::Reschedule(priority) => {
-                                    self.tasks.reschedule(&task_key, priority)
-                                }
-                            } {
-                                error!("Error finishing / scheduling task {}. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
-                                std::process::exit(1);
-                            }
-                        }
-                        Err(e) => {
-                            error!("Error processing task: {}. Tasks are only allowed to return fatal errors. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
-                            std::process::exit(1);
-                        }
-                    },
+                },
+                _ = sleep(Duration::from_millis(500)) => {}
+            }
+        }
+    }
+
+    /// Parses a claimed task.
+    fn parse_task(running_task: RunningTask) -> (kvx::Key, String, Task) {
+        // remember the key so we can finish or re-schedule the task.
+        let task_key = kvx::Key::from(&running_task);
+        let task_name = running_task.name.to_string();
+
+        match serde_json::from_value::<Task>(running_task.value) {
+            Ok(task) => (task_key, task_name, task),
+            Err(e) => {
+                // If we cannot parse the value of this task, then we have a
+                // major issue. Essentially, this can only happen if we did a
+                // Krill upgrade to a new version that no longer understands
+                // existing tasks.
+                //
+                // So, if we ever change the content of tasks then we should
+                // make sure that Krill is either backward compatible, or the
+                // task queue is migrated on upgrade.
+                error!("Fatal error parsing task: {}. Krill will now stop! This may be because this task is not for this Krill version ({}). If this issue persists, then try deleting this task from storage, it will appear in the 'tasks' dir if you use disk storage. The error was {}", task_key, KrillVersion::code_version(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Runs a single claimed task, and finishes or re-schedules it.
+    async fn run_task(
+        &self,
+        task_key: kvx::Key,
+        task_name: String,
+        task: Task,
+    ) {
+        let started = Instant::now();
+        let result = self.process_task(task.clone()).await;
+        self.tasks.record_run(task.kind(), started.elapsed());
+
+        match result {
+            Ok(result) => {
+                self.tasks.record_result(&task_name, &result);
+                if let Err(e) = match result {
+                    TaskResult::Done => self.tasks.finish(&task_key),
+                    TaskResult::FollowUp(task, priority) => self
+                        .tasks
+                        .schedule_and_finish_existing(task, priority),
+                    TaskResult::Reschedule(priority) => {
+                        self.tasks.reschedule(&task_key, priority)
+                    }
+                    TaskResult::Failed(error) => self.tasks.fail(
+                        &task_key,
+                        &task,
+                        error,
+                        &self.config.task_retry_policy(task.kind()),
+                        self.config.ca_refresh_next(),
+                    ),
+                } {
+                    error!("Error finishing / scheduling task {}. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
+                    std::process::exit(1);
                 }
             }
+            Err(e) => {
+                error!("Error processing task: {}. Tasks are only allowed to return fatal errors. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-            sleep(Duration::from_millis(500)).await;
+    /// Returns whether this node may run tasks. When it has just become
+    /// the leader, it first takes over the task queue.
+    fn lead(&self, was_leading: bool) -> bool {
+        let ha = match &self.ha {
+            Some(ha) => ha,
+            None => return true,
+        };
+
+        let leading = ha.check_leader().is_ok();
+        if leading && !was_leading {
+            // Any tasks that are still running were claimed by a previous
+            // leader, which lost its lease and will not finish them.
+            info!("Taking over the task queue as the new leader");
+            let res =
+                self.tasks.reschedule_tasks_at_startup().and_then(|_| {
+                    self.tasks.schedule(Task::QueueStartTasks, now())
+                });
+            if let Err(e) = res {
+                error!("Could not take over the task queue: {}", e);
+                return false;
+            }
+        } else if !leading && was_leading {
+            info!("No longer the leader, stopped running tasks");
         }
+        leading
+    }
+
+    /// Claims the next task, unless this node is not, or no longer, the
+    /// leader.
+    fn next_task(&self, leading: bool) -> Option<RunningTask> {
+        if !leading {
+            return None;
+        }
+        if let Some(ha) = &self.ha {
+            if ha.check_leader().is_err() {
+                return None;
+            }
+        }
+        self.tasks.pop()
     }
 
     /// Process a single task
@@ -175,6 +374,7 @@ impl Scheduler {
             Task::RenewObjectsIfNeeded => {
                 self.renew_objects_if_needed().await
             }
+            Task::RollKeysIfNeeded => self.roll_keys_if_needed().await,
 
             Task::RefreshAnnouncementsInfo => {
                 self.announcements_refresh().await
@@ -185,6 +385,8 @@ impl Scheduler {
 
             Task::UpdateSnapshots => self.update_snapshots(),
 
+            Task::CompactHistory => self.compact_history(),
+
             Task::RrdpUpdateIfNeeded => self.update_rrdp_if_needed(),
 
             Task::ResourceClassRemoved {
@@ -237,6 +439,17 @@ impl Scheduler {
         // to avoid a thundering herd. Note that the operator can always
         // choose to run bulk operations manually if they know that they
         // cannot wait.
+
+        // Renames of CAs that were interrupted are finished first, so that
+        // the tasks are added for the new handles.
+        if let Err(e) = self
+            .ca_manager
+            .resume_ca_renames(&self.repo_manager, &self.system_actor)
+            .await
+        {
+            error!("Could not resume renaming CAs: {}", e);
+        }
+
         let ca_list = self
             .ca_manager
             .ca_list(&self.system_actor)
@@ -321,6 +534,9 @@ impl Scheduler {
         self.tasks
             .schedule_missing(Task::RenewObjectsIfNeeded, now())
             .map_err(FatalError)?;
+        self.tasks
+            .schedule_missing(Task::RollKeysIfNeeded, now())
+            .map_err(FatalError)?;
 
         // BGP announcement info is only kept in-memory, so it
         // is lost after a restart, so schedule refreshing this
@@ -343,6 +559,15 @@ impl Scheduler {
             .schedule_missing(Task::UpdateSnapshots, now())
             .map_err(FatalError)?;
 
+        // Plan compacting the history of CAs, if a retention policy is
+        // configured. This is not urgent, so give the server some time to
+        // do more important things first.
+        if self.config.history_retention().is_some() {
+            self.tasks
+                .schedule_missing(Task::CompactHistory, in_hours(1))
+                .map_err(FatalError)?;
+        }
+
         if self.config.testbed().is_some() {
             self.tasks
                 .schedule_missing(Task::RenewTestbedTa, now())
@@ -365,16 +590,31 @@ impl Scheduler {
             .await
         {
             Err(e) => {
-                let next = self.config.requeue_remote_failed();
-
-                error!(
-                    "Failed to publish for '{}'. Will reschedule to: '{}'. Error: {}",
-                    ca, next, e
-                );
-
-                Ok(TaskResult::Reschedule(next))
+                error!("Failed to publish for '{}'. Error: {}", ca, e);
+                Ok(TaskResult::Failed(e.to_string()))
+            }
+            Ok(true) => {
+                // Keep checking whether the objects can be retrieved from
+                // the repository that the CA is migrating to.
+                let staging = match self.ca_manager.get_ca(&ca).await {
+                    Ok(ca) => {
+                        ca.repository_migration().map(|m| m.phase())
+                            == Some(RepoMigrationPhase::Staging)
+                    }
+                    Err(_) => false,
+                };
+                if staging {
+                    Ok(TaskResult::FollowUp(
+                        Task::SyncRepo {
+                            ca_handle: ca,
+                            ca_version: version,
+                        },
+                        in_seconds(SCHEDULER_INTERVAL_REPO_MIGRATION_SECS),
+                    ))
+                } else {
+                    Ok(TaskResult::Done)
+                }
             }
-            Ok(true) => Ok(TaskResult::Done),
             Ok(false) => {
                 debug!("sync was premature, reschedule");
                 let next = in_seconds(1);
@@ -399,13 +639,11 @@ impl Scheduler {
                 .await
             {
                 Err(e) => {
-                    let next = self.config.requeue_remote_failed();
-
                     error!(
-                        "Failed to synchronize CA '{}' with its parent '{}'. Will reschedule to: '{}'. Error: {}",
-                        ca, parent, next, e
+                        "Failed to synchronize CA '{}' with its parent '{}'. Error: {}",
+                        ca, parent, e
                     );
-                    Ok(TaskResult::Reschedule(next))
+                    Ok(TaskResult::Failed(e.to_string()))
                 }
                 Ok(true) => {
                     let next = self.config.ca_refresh_next();
@@ -564,6 +802,20 @@ impl Scheduler {
         ))
     }
 
+    /// Let CAs initiate or activate key rolls as required by their key
+    /// roll policy.
+    async fn roll_keys_if_needed(&self) -> Result<TaskResult, FatalError> {
+        self.ca_manager
+            .ca_keyroll_all_if_needed(&self.system_actor)
+            .await
+            .map_err(FatalError)?;
+
+        Ok(TaskResult::FollowUp(
+            Task::RollKeysIfNeeded,
+            in_minutes(SCHEDULER_INTERVAL_KEY_ROLL_MINS),
+        ))
+    }
+
     #[cfg(feature = "multi-user")]
     fn sweep_login_cache(&self) -> Result<TaskResult, FatalError> {
         if let Err(e) = self.login_session_cache.sweep() {
@@ -661,6 +913,32 @@ impl Scheduler {
         Ok(TaskResult::FollowUp(Task::UpdateSnapshots, in_hours(24)))
     }
 
+    // Archive old commands for all CAs, using the configured retention
+    fn compact_history(&self) -> Result<TaskResult, FatalError> {
+        let retention = match self.config.history_retention() {
+            Some(retention) => retention,
+            None => return Ok(TaskResult::Done), // no longer configured
+        };
+
+        match self.ca_manager.cas_compact_history(&retention) {
+            Ok(reports) => {
+                info!(
+                    "Compacted CA history ({}), reclaimed {} bytes",
+                    retention,
+                    reports.bytes_reclaimed()
+                );
+            }
+            Err(e) => {
+                error!(
+                    "Could not compact CA history, will try again in 24 hours. Error: {}",
+                    e
+                );
+            }
+        }
+
+        Ok(TaskResult::FollowUp(Task::CompactHistory, in_hours(24)))
+    }
+
     fn update_rrdp_if_needed(&self) -> Result<TaskResult, FatalError> {
         match self.repo_manager.update_rrdp_if_needed() {
             Err(e) => {
@@ -668,7 +946,7 @@ impl Scheduler {
                 // Should we panic in this case? For now, just keep trying,
                 // this may be an issue that gets resolved
                 // (permission? disk space?)
-                Ok(TaskResult::Reschedule(in_hours(1)))
+                Ok(TaskResult::Failed(e.to_string()))
             }
             Ok(None) => {
                 // update was done, or there were no staged changes
@@ -777,3 +1055,86 @@ impl Scheduler {
         }
     }
 }
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes() {
+        let ca = CaHandle::new("ca".into());
+        let sync_repo = Task::SyncRepo {
+            ca_handle: ca.clone(),
+            ca_version: 1,
+        };
+        let suspend = Task::SuspendChildrenIfNeeded { ca_handle: ca };
+
+        // Tasks for the same CA share a lane.
+        assert_eq!(
+            Lane::of("sync_repo_ca", &sync_repo),
+            Lane::of("suspend_children_ca", &suspend)
+        );
+
+        // Other tasks do not wait for each other.
+        assert_ne!(
+            Lane::of("rrdp_update", &Task::RrdpUpdateIfNeeded),
+            Lane::of("republish", &Task::RepublishIfNeeded)
+        );
+    }
+
+    #[tokio::test]
+    async fn global_tasks_exclusive_with_ca_lanes() {
+        let cas = Arc::new(RwLock::new(()));
+        let wait = Duration::from_millis(100);
+
+        let sync_repo = Task::SyncRepo {
+            ca_handle: CaHandle::new("ca".into()),
+            ca_version: 1,
+        };
+        let ca_lane = Lane::of("sync_repo_ca", &sync_repo);
+        let republish = Task::RepublishIfNeeded;
+        let republish_lane = Lane::of("republish", &republish);
+        let rrdp = Task::RrdpUpdateIfNeeded;
+        let rrdp_lane = Lane::of("rrdp_update", &rrdp);
+
+        // A global task has to wait for the running CA task.
+        let ca_guard = ca_lane.enter(&sync_repo, cas.clone()).await;
+        assert!(tokio::time::timeout(
+            wait,
+            republish_lane.enter(&republish, cas.clone())
+        )
+        .await
+        .is_err());
+
+        // Tasks that do not touch CAs can still run.
+        assert!(tokio::time::timeout(
+            wait,
+            rrdp_lane.enter(&rrdp, cas.clone())
+        )
+        .await
+        .is_ok());
+
+        // Once the CA task is done, the global task runs, and then a CA
+        // task has to wait for it.
+        drop(ca_guard);
+        let republish_guard = tokio::time::timeout(
+            wait,
+            republish_lane.enter(&republish, cas.clone()),
+        )
+        .await
+        .unwrap();
+        assert!(tokio::time::timeout(
+            wait,
+            ca_lane.enter(&sync_repo, cas.clone())
+        )
+        .await
+        .is_err());
+
+        drop(republish_guard);
+        assert!(tokio::time::timeout(wait, ca_lane.enter(&sync_repo, cas))
+            .await
+            .is_ok());
+    }
+}
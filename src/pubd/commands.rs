@@ -82,6 +82,10 @@ pub enum RepositoryAccessCommandDetails {
     RemovePublisher {
         name: PublisherHandle,
     },
+    RenamePublisher {
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+    },
 }
 
 impl CommandDetails for RepositoryAccessCommandDetails {
@@ -125,6 +129,23 @@ impl RepositoryAccessCommandDetails {
             actor,
         )
     }
+
+    pub fn rename_publisher(
+        handle: &MyHandle,
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+        actor: &Actor,
+    ) -> RepositoryAccessCommand {
+        SentCommand::new(
+            handle,
+            None,
+            RepositoryAccessCommandDetails::RenamePublisher {
+                name,
+                new_name,
+            },
+            actor,
+        )
+    }
 }
 
 impl fmt::Display for RepositoryAccessCommandDetails {
@@ -142,6 +163,12 @@ impl From<RepositoryAccessCommandDetails> for StorableRepositoryCommand {
             RepositoryAccessCommandDetails::RemovePublisher { name } => {
                 StorableRepositoryCommand::RemovePublisher { name }
             }
+            RepositoryAccessCommandDetails::RenamePublisher {
+                name,
+                new_name,
+            } => {
+                StorableRepositoryCommand::RenamePublisher { name, new_name }
+            }
         }
     }
 }
@@ -82,6 +82,10 @@ pub enum RepositoryAccessEvent {
     PublisherRemoved {
         name: PublisherHandle,
     },
+    PublisherRenamed {
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+    },
 }
 
 impl Event for RepositoryAccessEvent {}
@@ -95,6 +99,9 @@ impl fmt::Display for RepositoryAccessEvent {
             RepositoryAccessEvent::PublisherRemoved { name } => {
                 write!(f, "Publisher '{}' removed", name)
             }
+            RepositoryAccessEvent::PublisherRenamed { name, new_name } => {
+                write!(f, "Publisher '{}' renamed to '{}'", name, new_name)
+            }
         }
     }
 }
@@ -112,4 +119,11 @@ impl RepositoryAccessEvent {
     ) -> RepositoryAccessEvent {
         RepositoryAccessEvent::PublisherRemoved { name }
     }
+
+    pub(super) fn publisher_renamed(
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+    ) -> RepositoryAccessEvent {
+        RepositoryAccessEvent::PublisherRenamed { name, new_name }
+    }
 }
@@ -25,6 +25,7 @@ use crate::{
     },
     daemon::{
         config::Config,
+        eventstream::EventStream,
         mq::{now, Task, TaskQueue},
     },
     pubd::{RepoStats, RepositoryAccessProxy, RepositoryContentProxy},
@@ -57,9 +58,11 @@ impl RepositoryManager {
     pub fn build(
         config: Arc<Config>,
         tasks: Arc<TaskQueue>,
+        events: Arc<EventStream>,
         signer: Arc<KrillSigner>,
     ) -> Result<Self, Error> {
-        let access_proxy = Arc::new(RepositoryAccessProxy::create(&config)?);
+        let access_proxy =
+            Arc::new(RepositoryAccessProxy::create(&config, events)?);
         let content_proxy =
             Arc::new(RepositoryContentProxy::create(&config)?);
 
@@ -71,6 +74,17 @@ impl RepositoryManager {
             signer,
         })
     }
+
+    /// Drops the cached repository state, so that it is loaded again from
+    /// storage.
+    ///
+    /// With leader election, other nodes may have changed the storage
+    /// while this node was on standby. This must be done before this node
+    /// acts as the leader.
+    pub fn reload(&self) -> KrillResult<()> {
+        self.access.clear_cache();
+        self.content.reload()
+    }
 }
 /// # Repository Server Management
 impl RepositoryManager {
@@ -311,6 +325,27 @@ impl RepositoryManager {
         self.content.add_publisher(name)
     }
 
+    /// Renames a publisher. Its content and base URI are kept, but it will
+    /// need to use the RFC 8181 service URI for the new name.
+    ///
+    /// A rename that was interrupted can be resumed by calling this again.
+    pub fn rename_publisher(
+        &self,
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let publishers = self.publishers()?;
+        if publishers.contains(&name) || !publishers.contains(&new_name) {
+            self.access.rename_publisher(
+                name.clone(),
+                new_name.clone(),
+                actor,
+            )?;
+        }
+        self.content.rename_publisher(name, new_name)
+    }
+
     /// Removes a publisher and all of its content.
     pub fn remove_publisher(
         &self,
@@ -359,6 +394,7 @@ mod tests {
     use super::*;
 
     use crate::{
+        commons::eventsourcing::WalStore,
         commons::{
             api::{
                 rrdp::{PublicationDeltaError, RrdpSession},
@@ -368,8 +404,11 @@ mod tests {
             util::file::{self, CurrentFile},
         },
         constants::*,
-        daemon::config::{SignerConfig, SignerType},
-        pubd::{Publisher, RrdpServer},
+        daemon::{
+            config::{HaConfig, SignerConfig, SignerType},
+            ha::LeaderElection,
+        },
+        pubd::{Publisher, RepositoryContent, RrdpServer},
         test::{self, https, init_config, rsync},
     };
 
@@ -441,8 +480,10 @@ mod tests {
         let signer = Arc::new(signer);
         let config = Arc::new(config);
         let mq = Arc::new(TaskQueue::new(&config.storage_uri).unwrap());
+        let events =
+            Arc::new(EventStream::create(&config.storage_uri).unwrap());
         let repository_manager =
-            RepositoryManager::build(config, mq, signer).unwrap();
+            RepositoryManager::build(config, mq, events, signer).unwrap();
 
         let rsync_base = rsync("rsync://localhost/repo/");
         let rrdp_base = https("https://localhost/repo/rrdp/");
@@ -509,6 +550,80 @@ mod tests {
         cleanup();
     }
 
+    #[test]
+    fn standby_sees_changes_after_taking_over() {
+        let (data_dir, cleanup) = test::tmp_dir();
+        let storage_uri = test::mem_storage();
+
+        let election = |node_id: &str| {
+            let config = HaConfig {
+                node_id: Some(node_id.to_string()),
+                lease_seconds: 1,
+                heartbeat_seconds: 1,
+            };
+            LeaderElection::new(&storage_uri, &config).unwrap()
+        };
+        let a = election("a");
+        let b = election("b");
+        assert!(a.heartbeat().unwrap());
+        assert!(!b.heartbeat().unwrap());
+
+        // Node b loads the repository while it stands by.
+        let server_a = make_server(&storage_uri, &data_dir);
+        let build = || {
+            let mq = Arc::new(TaskQueue::new(&storage_uri).unwrap());
+            let events = Arc::new(EventStream::create(&storage_uri).unwrap());
+            RepositoryManager::build(
+                server_a.config.clone(),
+                mq,
+                events,
+                server_a.signer.clone(),
+            )
+            .unwrap()
+        };
+        let server_b = Arc::new(build());
+        let stats_before = server_b.repo_stats().unwrap();
+        assert!(!b.on_lead({
+            let server_b = server_b.clone();
+            move || server_b.reload()
+        }));
+
+        // Node a adds a publisher and resets the session. Its snapshots
+        // are updated, so that the change sets are removed.
+        let alice = publisher_alice(&storage_uri);
+        let alice_handle = Handle::from_str("alice").unwrap();
+        let publisher_req =
+            make_publisher_req(alice_handle.as_str(), alice.id_cert());
+        let actor = Actor::actor_from_def(ACTOR_DEF_TEST);
+        server_a.create_publisher(publisher_req, &actor).unwrap();
+        server_a.rrdp_session_reset().unwrap();
+        WalStore::<RepositoryContent>::create(
+            &storage_uri,
+            PUBSERVER_CONTENT_NS,
+        )
+        .unwrap()
+        .update_snapshots()
+        .unwrap();
+        let stats_a = server_a.repo_stats().unwrap();
+        assert_ne!(stats_a, stats_before);
+
+        // Node b takes over once the lease of node a expired, and sees
+        // the changes.
+        std::thread::sleep(Duration::from_secs(2));
+        assert!(b.heartbeat().unwrap());
+        assert_eq!(server_b.repo_stats().unwrap(), stats_a);
+        assert!(server_b.get_publisher_details(&alice_handle).is_ok());
+
+        // Changes by node b continue from those of node a.
+        server_b.rrdp_session_reset().unwrap();
+        let stats_b = server_b.repo_stats().unwrap();
+        assert_ne!(stats_b.session(), stats_a.session());
+        assert!(stats_b.get_publishers().contains_key(&alice_handle));
+        assert_eq!(build().repo_stats().unwrap(), stats_b);
+
+        cleanup();
+    }
+
     #[test]
     fn should_list_files() {
         // we need a disk, as repo_dir, etc. use data_dir by default
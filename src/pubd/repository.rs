@@ -50,6 +50,7 @@ use crate::{
     daemon::{
         ca::Rfc8183Id,
         config::{Config, RrdpUpdatesConfig},
+        eventstream::EventStream,
     },
     pubd::{
         publishers::Publisher, RepositoryAccessCommand,
@@ -90,6 +91,13 @@ impl RepositoryContentProxy {
         })
     }
 
+    /// Drops the cached content and loads it again from storage.
+    pub fn reload(&self) -> KrillResult<()> {
+        self.store.clear_cache();
+        self.store.warm()?;
+        Ok(())
+    }
+
     /// Initialize
     pub fn init(
         &self,
@@ -172,6 +180,22 @@ impl RepositoryContentProxy {
         Ok(())
     }
 
+    /// Moves the content of a publisher to a new name.
+    pub fn rename_publisher(
+        &self,
+        publisher: PublisherHandle,
+        new_name: PublisherHandle,
+    ) -> KrillResult<()> {
+        let command = RepositoryContentCommand::rename_publisher(
+            self.default_handle.clone(),
+            publisher,
+            new_name,
+        );
+        self.store.send_command(command)?;
+
+        Ok(())
+    }
+
     /// Publish an update for a publisher.
     ///
     /// Assumes that the RFC 8181 CMS has been verified, but will check that
@@ -292,6 +316,11 @@ pub enum RepositoryContentCommand {
         handle: MyHandle,
         publisher: PublisherHandle,
     },
+    RenamePublisher {
+        handle: MyHandle,
+        publisher: PublisherHandle,
+        new_name: PublisherHandle,
+    },
     DeleteMatchingFiles {
         handle: MyHandle,
         uri: uri::Rsync,
@@ -327,6 +356,18 @@ impl RepositoryContentCommand {
         RepositoryContentCommand::RemovePublisher { handle, publisher }
     }
 
+    pub fn rename_publisher(
+        handle: MyHandle,
+        publisher: PublisherHandle,
+        new_name: PublisherHandle,
+    ) -> Self {
+        RepositoryContentCommand::RenamePublisher {
+            handle,
+            publisher,
+            new_name,
+        }
+    }
+
     pub fn delete_matching_files(handle: MyHandle, uri: uri::Rsync) -> Self {
         RepositoryContentCommand::DeleteMatchingFiles { handle, uri }
     }
@@ -362,6 +403,7 @@ impl WalCommand for RepositoryContentCommand {
             RepositoryContentCommand::ResetSession { handle }
             | RepositoryContentCommand::AddPublisher { handle, .. }
             | RepositoryContentCommand::RemovePublisher { handle, .. }
+            | RepositoryContentCommand::RenamePublisher { handle, .. }
             | RepositoryContentCommand::Publish { handle, .. }
             | RepositoryContentCommand::DeleteMatchingFiles {
                 handle, ..
@@ -400,6 +442,17 @@ impl fmt::Display for RepositoryContentCommand {
                     publisher, handle
                 )
             }
+            RepositoryContentCommand::RenamePublisher {
+                handle,
+                publisher,
+                new_name,
+            } => {
+                write!(
+                    f,
+                    "rename publisher '{}' to '{}' in repository {}",
+                    publisher, new_name, handle
+                )
+            }
             RepositoryContentCommand::DeleteMatchingFiles {
                 handle,
                 uri,
@@ -438,6 +491,10 @@ pub enum RepositoryContentChange {
     PublisherRemoved {
         publisher: PublisherHandle,
     },
+    PublisherRenamed {
+        publisher: PublisherHandle,
+        new_name: PublisherHandle,
+    },
     RrdpDeltaStaged {
         publisher: PublisherHandle,
         delta: DeltaElements,
@@ -479,6 +536,12 @@ impl fmt::Display for RepositoryContentChange {
             RepositoryContentChange::PublisherRemoved { publisher } => {
                 write!(f, "removed publisher: {}", publisher)
             }
+            RepositoryContentChange::PublisherRenamed {
+                publisher,
+                new_name,
+            } => {
+                write!(f, "renamed publisher: {} to {}", publisher, new_name)
+            }
         }
     }
 }
@@ -551,6 +614,10 @@ impl WalSupport for RepositoryContent {
                 RepositoryContentChange::PublisherRemoved { publisher } => {
                     self.rrdp.apply_publisher_removed(&publisher)
                 }
+                RepositoryContentChange::PublisherRenamed {
+                    publisher,
+                    new_name,
+                } => self.rrdp.apply_publisher_renamed(&publisher, new_name),
             }
         }
         self.revision += 1;
@@ -574,6 +641,11 @@ impl WalSupport for RepositoryContent {
             RepositoryContentCommand::RemovePublisher {
                 publisher, ..
             } => self.remove_publisher(publisher),
+            RepositoryContentCommand::RenamePublisher {
+                publisher,
+                new_name,
+                ..
+            } => self.rename_publisher(publisher, new_name),
             RepositoryContentCommand::DeleteMatchingFiles { uri, .. } => {
                 self.delete_files(uri)
             }
@@ -688,6 +760,22 @@ impl RepositoryContent {
         Ok(res)
     }
 
+    /// Renames a publisher, keeping its current and staged content.
+    fn rename_publisher(
+        &self,
+        publisher: PublisherHandle,
+        new_name: PublisherHandle,
+    ) -> KrillResult<Vec<RepositoryContentChange>> {
+        // Nothing to do if the content was renamed already.
+        if !self.publishers().contains(&publisher) {
+            return Ok(vec![]);
+        }
+        Ok(vec![RepositoryContentChange::PublisherRenamed {
+            publisher,
+            new_name,
+        }])
+    }
+
     /// Purges content matching the given URI. Recursive if it ends with a
     /// '/'. Removes the content from existing publishers if found, and
     /// removes it from the (global) repository content. Can be used to
@@ -1254,6 +1342,18 @@ impl RrdpServer {
         self.snapshot.apply_publisher_removed(publisher);
     }
 
+    /// Apply a change that a publisher was renamed.
+    fn apply_publisher_renamed(
+        &mut self,
+        publisher: &PublisherHandle,
+        new_name: PublisherHandle,
+    ) {
+        if let Some(staged) = self.staged_elements.remove(publisher) {
+            self.staged_elements.insert(new_name.clone(), staged);
+        }
+        self.snapshot.apply_publisher_renamed(publisher, new_name);
+    }
+
     /// Applies staged DeltaElements
     fn apply_rrdp_staged(
         &mut self,
@@ -1834,12 +1934,16 @@ pub struct RepositoryAccessProxy {
 }
 
 impl RepositoryAccessProxy {
-    pub fn create(config: &Config) -> KrillResult<Self> {
-        let store = AggregateStore::<RepositoryAccess>::create(
+    pub fn create(
+        config: &Config,
+        events: Arc<EventStream>,
+    ) -> KrillResult<Self> {
+        let mut store = AggregateStore::<RepositoryAccess>::create(
             &config.storage_uri,
             PUBSERVER_NS,
             config.use_history_cache,
         )?;
+        store.add_post_save_listener(events);
         let key = MyHandle::from_str(PUBSERVER_DFLT).unwrap();
 
         if store.has(&key)? {
@@ -1868,6 +1972,11 @@ impl RepositoryAccessProxy {
         Ok(RepositoryAccessProxy { store, key })
     }
 
+    /// Drops the cached repository access.
+    pub fn clear_cache(&self) {
+        self.store.clear_cache();
+    }
+
     pub fn initialized(&self) -> KrillResult<bool> {
         self.store
             .has(&self.key)
@@ -1970,6 +2079,23 @@ impl RepositoryAccessProxy {
         }
     }
 
+    pub fn rename_publisher(
+        &self,
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        if !self.initialized()? {
+            Err(Error::RepositoryServerNotInitialized)
+        } else {
+            let cmd = RepositoryAccessCommandDetails::rename_publisher(
+                &self.key, name, new_name, actor,
+            );
+            self.store.command(cmd)?;
+            Ok(())
+        }
+    }
+
     /// Returns the repository URI information for a publisher.
     pub fn repo_info_for(
         &self,
@@ -2092,6 +2218,11 @@ impl Aggregate for RepositoryAccess {
             RepositoryAccessEvent::PublisherRemoved { name } => {
                 self.publishers.remove(&name);
             }
+            RepositoryAccessEvent::PublisherRenamed { name, new_name } => {
+                if let Some(publisher) = self.publishers.remove(&name) {
+                    self.publishers.insert(new_name, publisher);
+                }
+            }
         }
     }
 
@@ -2113,6 +2244,10 @@ impl Aggregate for RepositoryAccess {
             RepositoryAccessCommandDetails::RemovePublisher { name } => {
                 self.remove_publisher(name)
             }
+            RepositoryAccessCommandDetails::RenamePublisher {
+                name,
+                new_name,
+            } => self.rename_publisher(name, new_name),
         }
     }
 }
@@ -2151,6 +2286,24 @@ impl RepositoryAccess {
         }
     }
 
+    /// Renames a publisher. The publisher keeps its base URI, so that its
+    /// published objects do not need to move.
+    fn rename_publisher(
+        &self,
+        name: PublisherHandle,
+        new_name: PublisherHandle,
+    ) -> Result<Vec<RepositoryAccessEvent>, Error> {
+        if !self.has_publisher(&name) {
+            Err(Error::PublisherUnknown(name))
+        } else if self.has_publisher(&new_name) {
+            Err(Error::PublisherDuplicate(new_name))
+        } else {
+            Ok(vec![RepositoryAccessEvent::publisher_renamed(
+                name, new_name,
+            )])
+        }
+    }
+
     fn notification_uri(&self) -> uri::Https {
         self.rrdp_base.join(b"notification.xml").unwrap()
     }
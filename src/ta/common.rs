@@ -36,6 +36,8 @@ use crate::{
     },
 };
 
+use super::TrustAnchorKeys;
+
 //------------ TrustAnchorObjects ------------------------------------------
 
 /// Contains all Trust Anchor objects, including the the TA certificate
@@ -70,6 +72,11 @@ pub struct TrustAnchorObjects {
     // Certificates issued to children. We use a map to avoid having
     // to loop. (yes, even if typically the list would be very short)
     issued: HashMap<KeyIdentifier, IssuedCertificate>,
+
+    // The last issued TAK object (RFC 9691), if any. This is only
+    // published while a TA key rollover is in progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tak: Option<PublishedObject>,
 }
 
 impl TrustAnchorObjects {
@@ -113,15 +120,18 @@ impl TrustAnchorObjects {
             crl,
             manifest,
             issued: HashMap::new(),
+            tak: None,
         })
     }
 
     /// Publish next revision of the published objects.
     /// - Update CRL (times and revocations)
+    /// - Update TAK object (if given, otherwise it is removed)
     /// - Update Manifest (times and listed objects)
     pub fn republish(
         &mut self,
         signing_cert: &ReceivedCert,
+        tak: Option<&TrustAnchorKeys>,
         next_update_weeks: i64,
         mft_number_override: Option<u64>,
         signer: &KrillSigner,
@@ -132,8 +142,8 @@ impl TrustAnchorObjects {
         let signing_key = signing_cert.key_identifier();
 
         if signing_key != self.key_identifier {
-            // This would be a bug.. we will need to re-think this when
-            // implementing signed TALs and TA key rollovers.
+            // This would be a bug.. a TA key rollover uses a new set of
+            // objects for the successor key.
             Err(Error::custom("TA key changed when republishing"))
         } else {
             let issuer = signing_cert.subject().clone();
@@ -146,8 +156,12 @@ impl TrustAnchorObjects {
                 signer,
             )?;
 
+            self.tak = tak
+                .map(|tak| tak.sign(signing_cert, &self.revision, signer))
+                .transpose()?;
+
             self.manifest = ManifestBuilder::new(self.revision)
-                .with_objects(&self.crl, &self.issued_certs_objects())
+                .with_objects(&self.crl, &self.published_objects())
                 .build_new_mft(signing_cert, signer)
                 .map(|m| m.into())?;
 
@@ -172,16 +186,28 @@ impl TrustAnchorObjects {
             .map_err(|e| Error::Custom(format!("Cannot make uri: {}", e)))?;
         res.push(self.crl.publish_element(crl_uri));
 
-        for (name, object) in self.issued_certs_objects() {
-            let cert_uri =
+        for (name, object) in self.published_objects() {
+            let object_uri =
                 self.base_uri.join(name.as_ref()).map_err(|e| {
                     Error::Custom(format!("Cannot make uri: {}", e))
                 })?;
-            res.push(object.publish_element(cert_uri));
+            res.push(object.publish_element(object_uri));
         }
         Ok(res)
     }
 
+    /// Returns all objects listed on the manifest, other than the CRL.
+    fn published_objects(&self) -> HashMap<ObjectName, PublishedObject> {
+        let mut objects = self.issued_certs_objects();
+        if let Some(tak) = &self.tak {
+            objects.insert(
+                ObjectName::tak_for_key(&self.key_identifier),
+                tak.clone(),
+            );
+        }
+        objects
+    }
+
     fn issued_certs_objects(&self) -> HashMap<ObjectName, PublishedObject> {
         self.issued
             .iter()
@@ -201,6 +227,14 @@ impl TrustAnchorObjects {
         &self.revision
     }
 
+    pub fn tak(&self) -> Option<&PublishedObject> {
+        self.tak.as_ref()
+    }
+
+    pub fn issued(&self) -> impl Iterator<Item = &IssuedCertificate> {
+        self.issued.values()
+    }
+
     pub fn this_update() -> Time {
         Time::five_minutes_ago()
     }
@@ -368,6 +402,133 @@ impl std::fmt::Display for TrustAnchorLocator {
     }
 }
 
+//------------ TaKeyRollRequest --------------------------------------------
+
+/// Request for the Trust Anchor Signer to initiate, or activate, a
+/// rollover to a new TA key (RFC 9691).
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TaKeyRollRequest {
+    Initiate(TaKeyRollInitRequest),
+    Activate,
+}
+
+impl fmt::Display for TaKeyRollRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaKeyRollRequest::Initiate(init) => {
+                write!(f, "initiate, successor TAL uris: ")?;
+                for uri in &init.tal_https {
+                    write!(f, "{} ", uri)?;
+                }
+                write!(f, "{}", init.tal_rsync)
+            }
+            TaKeyRollRequest::Activate => write!(f, "activate"),
+        }
+    }
+}
+
+//------------ TaKeyRollInitRequest ----------------------------------------
+
+/// The TAL URIs for a new (successor) TA key. The rsync URI must differ
+/// from that of the current key, as both certificates are published
+/// during the rollover.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaKeyRollInitRequest {
+    pub tal_https: Vec<uri::Https>,
+    pub tal_rsync: uri::Rsync,
+}
+
+//------------ TaSuccessor -------------------------------------------------
+
+/// The successor key of the Trust Anchor during a key rollover. It has
+/// its own set of published objects, and is announced in TAK objects
+/// until it is activated.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaSuccessor {
+    ta_cert_details: TaCertDetails,
+    objects: TrustAnchorObjects,
+    activate_after: Time,
+}
+
+impl TaSuccessor {
+    pub fn new(
+        ta_cert_details: TaCertDetails,
+        objects: TrustAnchorObjects,
+        activate_after: Time,
+    ) -> Self {
+        TaSuccessor {
+            ta_cert_details,
+            objects,
+            activate_after,
+        }
+    }
+
+    pub fn ta_cert_details(&self) -> &TaCertDetails {
+        &self.ta_cert_details
+    }
+
+    pub fn objects(&self) -> &TrustAnchorObjects {
+        &self.objects
+    }
+
+    pub fn activate_after(&self) -> Time {
+        self.activate_after
+    }
+
+    /// Returns true if the acceptance window for the new key has passed.
+    pub fn can_activate(&self) -> bool {
+        Time::now() >= self.activate_after
+    }
+
+    /// Republish the objects for the successor key, including a TAK
+    /// object listing the current key as its predecessor.
+    pub fn republish(
+        &mut self,
+        current: &TaCertDetails,
+        next_update_weeks: i64,
+        signer: &KrillSigner,
+    ) -> KrillResult<()> {
+        let tak = TrustAnchorKeys::new(&self.ta_cert_details)
+            .with_predecessor(current);
+
+        self.objects.republish(
+            self.ta_cert_details.cert(),
+            Some(&tak),
+            next_update_weeks,
+            None,
+            signer,
+        )
+    }
+
+    pub fn unpack(self) -> (TaCertDetails, TrustAnchorObjects) {
+        (self.ta_cert_details, self.objects)
+    }
+}
+
+impl fmt::Display for TaSuccessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "-------------------------------------------------------"
+        )?;
+        writeln!(f, "                  Successor TAL")?;
+        writeln!(
+            f,
+            "-------------------------------------------------------"
+        )?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.ta_cert_details.tal())?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "Can be activated after: {}",
+            self.activate_after.to_rfc3339()
+        )?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.objects)
+    }
+}
+
 //------------ TrustAnchorSignerInfo ---------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -378,6 +539,23 @@ pub struct TrustAnchorSignerInfo {
     pub objects: TrustAnchorObjects,
     // The TA certificate and TAL
     pub ta_cert_details: TaCertDetails,
+    // The successor key, if a key rollover is in progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub successor: Option<TaSuccessor>,
+}
+
+impl TrustAnchorSignerInfo {
+    /// Returns the elements to publish for the current key, and the
+    /// successor key if there is one.
+    pub fn publish_elements(
+        &self,
+    ) -> KrillResult<Vec<crate::commons::api::rrdp::PublishElement>> {
+        let mut res = self.objects.publish_elements()?;
+        if let Some(successor) = &self.successor {
+            res.append(&mut successor.objects().publish_elements()?);
+        }
+        Ok(res)
+    }
 }
 
 impl fmt::Display for TrustAnchorSignerInfo {
@@ -418,6 +596,11 @@ impl fmt::Display for TrustAnchorSignerInfo {
             "-------------------------------------------------------"
         )?;
 
+        if let Some(successor) = &self.successor {
+            writeln!(f)?;
+            writeln!(f, "{}", successor)?;
+        }
+
         Ok(())
     }
 }
@@ -548,11 +731,15 @@ impl fmt::Display for TrustAnchorSignedRequest {
 /// more children to either issue a new certificate, or revoke
 /// a key. If there are no requests for a child, then it is
 /// assumed that the current issued certificate(s) to the child
-/// should not change.
+/// should not change, unless there is a child update for it.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct TrustAnchorSignerRequest {
     pub nonce: Nonce, // should be matched in response (replay protection)
     pub child_requests: Vec<TrustAnchorChildRequests>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub child_updates: Vec<TrustAnchorChildUpdate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_roll: Option<TaKeyRollRequest>,
 }
 
 impl TrustAnchorSignerRequest {
@@ -582,6 +769,19 @@ impl fmt::Display for TrustAnchorSignerRequest {
         writeln!(f, "-------------------------------")?;
         writeln!(f)?;
 
+        if let Some(key_roll) = &self.key_roll {
+            writeln!(f, "key roll: {}", key_roll)?;
+            writeln!(f)?;
+        }
+
+        for update in &self.child_updates {
+            writeln!(f, "-------------------------------")?;
+            writeln!(f, "          child update")?;
+            writeln!(f, "-------------------------------")?;
+            writeln!(f, "{}", update)?;
+            writeln!(f)?;
+        }
+
         for request in &self.child_requests {
             writeln!(f, "-------------------------------")?;
             writeln!(f, "          child request")?;
@@ -616,6 +816,63 @@ pub struct TrustAnchorChildRequests {
     pub requests: HashMap<KeyIdentifier, ProvisioningRequest>,
 }
 
+//------------ TrustAnchorChildUpdate --------------------------------------
+
+/// A change to a Trust Anchor child, made in the proxy, that affects the
+/// certificates issued to it. The keys are the keys for which the child
+/// was issued a certificate, as known to the proxy.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TrustAnchorChildUpdate {
+    /// Re-issue the certificates for the keys with the new resources.
+    /// Certificates are revoked if no resources remain.
+    Resources {
+        child: ChildHandle,
+        resources: ResourceSet,
+        keys: Vec<KeyIdentifier>,
+    },
+    /// The child was removed. Revoke the certificates for the keys.
+    Remove {
+        child: ChildHandle,
+        keys: Vec<KeyIdentifier>,
+    },
+}
+
+impl TrustAnchorChildUpdate {
+    pub fn child(&self) -> &ChildHandle {
+        match self {
+            TrustAnchorChildUpdate::Resources { child, .. }
+            | TrustAnchorChildUpdate::Remove { child, .. } => child,
+        }
+    }
+
+    pub fn keys(&self) -> &[KeyIdentifier] {
+        match self {
+            TrustAnchorChildUpdate::Resources { keys, .. }
+            | TrustAnchorChildUpdate::Remove { keys, .. } => keys,
+        }
+    }
+}
+
+impl fmt::Display for TrustAnchorChildUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustAnchorChildUpdate::Resources {
+                child, resources, ..
+            } => {
+                writeln!(f, "child:         {}", child)?;
+                writeln!(f, "entitlements:  {}", resources)?;
+            }
+            TrustAnchorChildUpdate::Remove { child, .. } => {
+                writeln!(f, "child:         {}    removed", child)?;
+            }
+        }
+        for key in self.keys() {
+            writeln!(f, "key:           {}", key)?;
+        }
+        Ok(())
+    }
+}
+
 //------------ TrustAnchorSignedResponse -----------------------------------
 
 /// A [`TrustAnchorSignerResponse`] and its signed message as base64 for
@@ -673,6 +930,18 @@ pub struct TrustAnchorSignerResponse {
     pub objects: TrustAnchorObjects,
     pub child_responses:
         HashMap<ChildHandle, HashMap<KeyIdentifier, ProvisioningResponse>>,
+    // The successor key, if a key rollover is in progress after
+    // processing the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub successor: Option<TaSuccessor>,
+    // The new TA certificate and TAL, if the successor key was activated
+    // when processing the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activated: Option<TaCertDetails>,
+    // The children for which certificates were re-issued, or revoked,
+    // because their resources were updated in the proxy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updated_children: Vec<ChildHandle>,
 }
 
 impl TrustAnchorSignerResponse {
@@ -703,6 +972,20 @@ impl fmt::Display for TrustAnchorSignerResponse {
         writeln!(f)?;
         writeln!(f, "{}", self.objects)?;
         writeln!(f)?;
+        if let Some(successor) = &self.successor {
+            writeln!(f, "{}", successor)?;
+            writeln!(f)?;
+        }
+        if self.activated.is_some() {
+            writeln!(f, "The successor TA key was activated.")?;
+            writeln!(f)?;
+        }
+        for child in &self.updated_children {
+            writeln!(f, "Updated certificates for child: {}", child)?;
+        }
+        if !self.updated_children.is_empty() {
+            writeln!(f)?;
+        }
         for (child, responses) in &self.child_responses {
             writeln!(f, "-------------------------------")?;
             writeln!(f, "          child response")?;
@@ -756,6 +1039,15 @@ impl TrustAnchorChild {
             open_responses: HashMap::new(),
         }
     }
+
+    /// Returns the keys for which the child currently holds a certificate.
+    pub fn in_use_keys(&self) -> Vec<KeyIdentifier> {
+        self.used_keys
+            .iter()
+            .filter(|(_, state)| matches!(state, UsedKeyState::InUse(_)))
+            .map(|(key, _)| *key)
+            .collect()
+    }
 }
 
 //------------ ProvisioningRequest -----------------------------------------
@@ -13,6 +13,9 @@ pub use self::proxy::*;
 mod signer;
 pub use self::signer::*;
 
+mod tak;
+pub use self::tak::*;
+
 pub const TA_NAME: &str = "ta"; // reserved for TA
 
 //------------ TrustAnchor Handle Types ------------------------------------
@@ -31,7 +34,10 @@ fn ta_resource_class_name() -> rpki::ca::provisioning::ResourceClassName {
 //----------------- TESTS --------------------------------------------------------------
 #[cfg(test)]
 mod tests {
-    use rpki::ca::idexchange::{RepoInfo, ServiceUri};
+    use rpki::{
+        ca::idexchange::{RepoInfo, ServiceUri},
+        repository::{sigobj::SignedObject, tal::TalInfo},
+    };
 
     use super::*;
 
@@ -186,7 +192,7 @@ mod tests {
                     signed_request,
                     timing,
                     Some(55), // override the next manifest number again
-                    signer,
+                    signer.clone(),
                     &actor,
                 );
             ta_signer = ta_signer_store
@@ -210,6 +216,152 @@ mod tests {
             // overridden number.
             let ta_objects = proxy.get_trust_anchor_objects().unwrap();
             assert_eq!(ta_objects.revision().number(), 55);
+            assert!(ta_objects.tak().is_none());
+
+            // Let the signer process the open request of the proxy, and
+            // give the response back to the proxy.
+            let process_open_request =
+                |proxy: &TrustAnchorProxy, timing: TaTimingConfig| {
+                    let signed_request =
+                        proxy.get_signer_request(timing, &signer).unwrap();
+                    let request_nonce =
+                        signed_request.content().nonce.clone();
+
+                    let cmd =
+                        TrustAnchorSignerCommand::make_process_request_command(
+                            &signer_handle,
+                            signed_request,
+                            timing,
+                            None,
+                            signer.clone(),
+                            &actor,
+                        );
+                    let ta_signer = ta_signer_store.command(cmd).unwrap();
+                    let exchange =
+                        ta_signer.get_exchange(&request_nonce).unwrap();
+
+                    let cmd =
+                        TrustAnchorProxyCommand::process_signer_response(
+                            &proxy_handle,
+                            exchange.response.clone(),
+                            &actor,
+                        );
+                    ta_proxy_store.command(cmd).unwrap()
+                };
+
+            // Initiate a TA key roll. We use an acceptance window of 0 days
+            // so that we can activate the successor key immediately.
+            let roll_timing = TaTimingConfig {
+                key_roll_acceptance_days: 0,
+                ..timing
+            };
+            let successor_tal_rsync = test::rsync(
+                "rsync://example.krill.cloud/ta/ta-successor.cer",
+            );
+            let key_roll_init =
+                TaKeyRollRequest::Initiate(TaKeyRollInitRequest {
+                    tal_https: vec![test::https(
+                        "https://example.krill.cloud/ta/ta-successor.cer",
+                    )],
+                    tal_rsync: successor_tal_rsync.clone(),
+                });
+
+            let key_roll_init_cmd =
+                TrustAnchorProxyCommand::make_signer_key_roll_request(
+                    &proxy_handle,
+                    key_roll_init.clone(),
+                    &actor,
+                );
+            proxy = ta_proxy_store.command(key_roll_init_cmd).unwrap();
+            proxy = process_open_request(&proxy, roll_timing);
+
+            let successor = proxy.get_successor().unwrap().unwrap();
+            assert_eq!(
+                successor.ta_cert_details().tal().rsync_uri(),
+                &successor_tal_rsync
+            );
+
+            // Both the current and the successor key publish a TAK object
+            // with their own CRL and manifest.
+            let taks: Vec<_> = proxy
+                .publish_elements()
+                .unwrap()
+                .into_iter()
+                .filter(|el| el.uri().as_str().ends_with(".tak"))
+                .collect();
+            assert_eq!(taks.len(), 2);
+            let ta_certs = [
+                proxy.get_ta_details().unwrap(),
+                successor.ta_cert_details(),
+            ];
+            for tak in taks {
+                let object =
+                    SignedObject::decode(tak.base64().to_bytes(), true)
+                        .unwrap();
+                assert_eq!(
+                    object.content_type().as_ref(),
+                    tak::CT_TRUST_ANCHOR_KEYS
+                );
+                assert!(object.cert().as_resources().is_inherited());
+
+                // Each TAK is validly signed by one of the TA keys
+                let issuer = ta_certs
+                    .iter()
+                    .find(|details| {
+                        object.cert().authority_key_identifier()
+                            == Some(details.cert().key_identifier())
+                    })
+                    .unwrap()
+                    .cert()
+                    .to_cert()
+                    .unwrap()
+                    .validate_ta(
+                        TalInfo::from_name("test".into()).into_arc(),
+                        true,
+                    )
+                    .unwrap();
+                object.process(&issuer, true, |_| Ok(())).unwrap();
+            }
+
+            // We cannot initiate another key roll while one is in progress
+            let key_roll_init_cmd =
+                TrustAnchorProxyCommand::make_signer_key_roll_request(
+                    &proxy_handle,
+                    key_roll_init,
+                    &actor,
+                );
+            assert!(ta_proxy_store.command(key_roll_init_cmd).is_err());
+
+            // Activate the successor key. It replaces the current key, and
+            // TAK objects are no longer published.
+            let key_roll_activate_cmd =
+                TrustAnchorProxyCommand::make_signer_key_roll_request(
+                    &proxy_handle,
+                    TaKeyRollRequest::Activate,
+                    &actor,
+                );
+            proxy = ta_proxy_store.command(key_roll_activate_cmd).unwrap();
+            proxy = process_open_request(&proxy, roll_timing);
+
+            assert!(proxy.get_successor().unwrap().is_none());
+            assert_eq!(
+                proxy.get_ta_details().unwrap().tal().rsync_uri(),
+                &successor_tal_rsync
+            );
+            assert!(proxy
+                .get_trust_anchor_objects()
+                .unwrap()
+                .tak()
+                .is_none());
+
+            // There is no key roll left to activate
+            let key_roll_activate_cmd =
+                TrustAnchorProxyCommand::make_signer_key_roll_request(
+                    &proxy_handle,
+                    TaKeyRollRequest::Activate,
+                    &actor,
+                );
+            assert!(ta_proxy_store.command(key_roll_activate_cmd).is_err());
 
             // We still need to test some higher order functions:
             // - add child
@@ -14,7 +14,7 @@ use rpki::{
         provisioning::{ResourceClassEntitlements, SigningCert},
     },
     crypto::KeyIdentifier,
-    repository::x509::Time,
+    repository::{resources::ResourceSet, x509::Time},
 };
 
 use crate::{
@@ -89,6 +89,15 @@ pub struct TrustAnchorProxy {
     // is an open request. We first need to process the response, before we
     // can accept new requests from any child.
     open_signer_request: Option<Nonce>,
+
+    // A key roll request to include in the open signer request, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_key_roll_request: Option<TaKeyRollRequest>,
+
+    // Updated or removed children for which the signer still needs to
+    // re-issue or revoke certificates. Included in the next signer request.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    open_child_updates: Vec<TrustAnchorChildUpdate>,
 }
 
 //------------ TrustAnchorProxy: Commands and Events -----------------------
@@ -158,12 +167,15 @@ pub enum TrustAnchorProxyEvent {
     // Proxy -> Signer interactions
     SignerAdded(TrustAnchorSignerInfo),
     SignerRequestMade(Nonce),
+    SignerKeyRollRequestMade(Nonce, TaKeyRollRequest),
     SignerResponseReceived(TrustAnchorSignedResponse),
 
     // Children
     ChildAdded(TrustAnchorChild),
     ChildRequestAdded(ChildHandle, ProvisioningRequest),
     ChildResponseGiven(ChildHandle, KeyIdentifier),
+    ChildResourcesUpdated(ChildHandle, ResourceSet),
+    ChildRemoved(ChildHandle),
 }
 
 impl Event for TrustAnchorProxyEvent {}
@@ -192,6 +204,16 @@ impl fmt::Display for TrustAnchorProxyEvent {
             TrustAnchorProxyEvent::SignerRequestMade(nonce) => {
                 write!(f, "Created signer request with nonce '{}'", nonce)
             }
+            TrustAnchorProxyEvent::SignerKeyRollRequestMade(
+                nonce,
+                request,
+            ) => {
+                write!(
+                    f,
+                    "Created signer request with nonce '{}' for key roll: {}",
+                    nonce, request
+                )
+            }
             TrustAnchorProxyEvent::SignerResponseReceived(response) => {
                 write!(
                     f,
@@ -225,6 +247,19 @@ impl fmt::Display for TrustAnchorProxyEvent {
                     child_handle, key
                 )
             }
+            TrustAnchorProxyEvent::ChildResourcesUpdated(
+                child_handle,
+                resources,
+            ) => {
+                write!(
+                    f,
+                    "Updated resources for child {} to: {}",
+                    child_handle, resources
+                )
+            }
+            TrustAnchorProxyEvent::ChildRemoved(child_handle) => {
+                write!(f, "Removed child: {}", child_handle)
+            }
         }
     }
 }
@@ -243,12 +278,15 @@ pub enum TrustAnchorProxyCommandDetails {
     // Proxy -> Signer interactions
     AddSigner(TrustAnchorSignerInfo),
     MakeSignerRequest,
+    MakeSignerKeyRollRequest(TaKeyRollRequest),
     ProcessSignerResponse(TrustAnchorSignedResponse),
 
     // Children
     AddChild(AddChildRequest),
     AddChildRequest(ChildHandle, ProvisioningRequest),
     GiveChildResponse(ChildHandle, KeyIdentifier),
+    UpdateChildResources(ChildHandle, ResourceSet),
+    RemoveChild(ChildHandle),
 }
 
 impl fmt::Display for TrustAnchorProxyCommandDetails {
@@ -278,6 +316,15 @@ impl fmt::Display for TrustAnchorProxyCommandDetails {
             TrustAnchorProxyCommandDetails::MakeSignerRequest => {
                 write!(f, "Create new publish request for signer")
             }
+            TrustAnchorProxyCommandDetails::MakeSignerKeyRollRequest(
+                request,
+            ) => {
+                write!(
+                    f,
+                    "Create new key roll request for signer: {}",
+                    request
+                )
+            }
             TrustAnchorProxyCommandDetails::ProcessSignerResponse(
                 response,
             ) => {
@@ -313,6 +360,19 @@ impl fmt::Display for TrustAnchorProxyCommandDetails {
                     child_handle, key
                 )
             }
+            TrustAnchorProxyCommandDetails::UpdateChildResources(
+                child_handle,
+                resources,
+            ) => {
+                write!(
+                    f,
+                    "Update resources for child {} to: {}",
+                    child_handle, resources
+                )
+            }
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                write!(f, "Remove child: {}", child_handle)
+            }
         }
     }
 }
@@ -350,6 +410,13 @@ impl eventsourcing::WithStorableDetails for TrustAnchorProxyCommandDetails {
                     self,
                 )
             }
+            TrustAnchorProxyCommandDetails::MakeSignerKeyRollRequest(
+                request,
+            ) => crate::commons::api::CommandSummary::new(
+                "cmd-ta-proxy-key-roll-req",
+                self,
+            )
+            .with_arg("request", request),
             TrustAnchorProxyCommandDetails::ProcessSignerResponse(
                 response,
             ) => crate::commons::api::CommandSummary::new(
@@ -404,6 +471,22 @@ impl eventsourcing::WithStorableDetails for TrustAnchorProxyCommandDetails {
                 self,
             )
             .with_child(child_handle),
+            TrustAnchorProxyCommandDetails::UpdateChildResources(
+                child_handle,
+                resources,
+            ) => crate::commons::api::CommandSummary::new(
+                "cmd-ta-proxy-child-update-res",
+                self,
+            )
+            .with_child(child_handle)
+            .with_resources(resources),
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                crate::commons::api::CommandSummary::new(
+                    "cmd-ta-proxy-child-remove",
+                    self,
+                )
+                .with_child(child_handle)
+            }
         }
     }
 
@@ -451,6 +534,19 @@ impl TrustAnchorProxyCommand {
         )
     }
 
+    pub fn make_signer_key_roll_request(
+        id: &TrustAnchorHandle,
+        request: TaKeyRollRequest,
+        actor: &Actor,
+    ) -> Self {
+        TrustAnchorProxyCommand::new(
+            id,
+            None,
+            TrustAnchorProxyCommandDetails::MakeSignerKeyRollRequest(request),
+            actor,
+        )
+    }
+
     pub fn process_signer_response(
         id: &TrustAnchorHandle,
         response: TrustAnchorSignedResponse,
@@ -504,6 +600,35 @@ impl TrustAnchorProxyCommand {
             actor,
         )
     }
+
+    pub fn update_child_resources(
+        id: &TrustAnchorHandle,
+        child: ChildHandle,
+        resources: ResourceSet,
+        actor: &Actor,
+    ) -> Self {
+        TrustAnchorProxyCommand::new(
+            id,
+            None,
+            TrustAnchorProxyCommandDetails::UpdateChildResources(
+                child, resources,
+            ),
+            actor,
+        )
+    }
+
+    pub fn remove_child(
+        id: &TrustAnchorHandle,
+        child: ChildHandle,
+        actor: &Actor,
+    ) -> Self {
+        TrustAnchorProxyCommand::new(
+            id,
+            None,
+            TrustAnchorProxyCommandDetails::RemoveChild(child),
+            actor,
+        )
+    }
 }
 
 impl eventsourcing::CommandDetails for TrustAnchorProxyCommandDetails {
@@ -536,6 +661,8 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
             signer: None,
             child_details: HashMap::new(),
             open_signer_request: None,
+            open_key_roll_request: None,
+            open_child_updates: vec![],
         }
     }
 
@@ -577,6 +704,13 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
             TrustAnchorProxyEvent::SignerRequestMade(nonce) => {
                 self.open_signer_request = Some(nonce)
             }
+            TrustAnchorProxyEvent::SignerKeyRollRequestMade(
+                nonce,
+                request,
+            ) => {
+                self.open_signer_request = Some(nonce);
+                self.open_key_roll_request = Some(request);
+            }
             TrustAnchorProxyEvent::SignerResponseReceived(response) => {
                 let content = response.into_content();
                 for (child_handle, child_responses) in content.child_responses
@@ -609,8 +743,15 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                 }
                 // We cannot have an accepted response if we did not have a
                 // signer
-                self.signer.as_mut().unwrap().objects = content.objects;
+                let signer = self.signer.as_mut().unwrap();
+                signer.objects = content.objects;
+                signer.successor = content.successor;
+                if let Some(activated) = content.activated {
+                    signer.ta_cert_details = activated;
+                }
                 self.open_signer_request = None;
+                self.open_key_roll_request = None;
+                self.open_child_updates.clear();
             }
 
             // Children
@@ -636,6 +777,45 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                     .open_responses
                     .remove(&key);
             }
+            TrustAnchorProxyEvent::ChildResourcesUpdated(
+                child_handle,
+                resources,
+            ) => {
+                let child =
+                    self.child_details.get_mut(&child_handle).unwrap(); // safe - we can only have an event for this child if it exists
+
+                child.resources = resources.clone();
+
+                // Open requests were verified against the old resources.
+                // The child will make new requests when it finds that its
+                // entitlements have changed.
+                child.open_requests.clear();
+
+                let keys = child.in_use_keys();
+                if !keys.is_empty() {
+                    self.open_child_updates.push(
+                        TrustAnchorChildUpdate::Resources {
+                            child: child_handle,
+                            resources,
+                            keys,
+                        },
+                    );
+                }
+            }
+            TrustAnchorProxyEvent::ChildRemoved(child_handle) => {
+                if let Some(child) = self.child_details.remove(&child_handle)
+                {
+                    let keys = child.in_use_keys();
+                    if !keys.is_empty() {
+                        self.open_child_updates.push(
+                            TrustAnchorChildUpdate::Remove {
+                                child: child_handle,
+                                keys,
+                            },
+                        );
+                    }
+                }
+            }
         }
     }
 
@@ -679,6 +859,9 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
             TrustAnchorProxyCommandDetails::MakeSignerRequest => {
                 self.process_make_signer_request()
             }
+            TrustAnchorProxyCommandDetails::MakeSignerKeyRollRequest(
+                request,
+            ) => self.process_make_signer_key_roll_request(request),
             TrustAnchorProxyCommandDetails::ProcessSignerResponse(
                 response,
             ) => self.process_signer_response(response),
@@ -695,6 +878,13 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                 child_handle,
                 key,
             ) => self.process_give_child_response(child_handle, key),
+            TrustAnchorProxyCommandDetails::UpdateChildResources(
+                child_handle,
+                resources,
+            ) => self.process_update_child_resources(child_handle, resources),
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                self.process_remove_child(child_handle)
+            }
         }
     }
 }
@@ -733,6 +923,35 @@ impl TrustAnchorProxy {
         }
     }
 
+    fn process_make_signer_key_roll_request(
+        &self,
+        request: TaKeyRollRequest,
+    ) -> KrillResult<Vec<TrustAnchorProxyEvent>> {
+        if self.open_signer_request.is_some() {
+            return Err(Error::TaProxyHasRequest);
+        }
+        let signer = self.signer.as_ref().ok_or(Error::TaProxyHasNoSigner)?;
+
+        // The signer will do the same checks, but we can fail fast here.
+        match (&request, &signer.successor) {
+            (TaKeyRollRequest::Initiate(_), Some(_)) => {
+                Err(Error::TaKeyRollInProgress)
+            }
+            (TaKeyRollRequest::Activate, None) => {
+                Err(Error::TaKeyRollNotInProgress)
+            }
+            (TaKeyRollRequest::Activate, Some(successor))
+                if !successor.can_activate() =>
+            {
+                Err(Error::TaKeyRollNotReady(successor.activate_after()))
+            }
+            _ => Ok(vec![TrustAnchorProxyEvent::SignerKeyRollRequestMade(
+                Nonce::new(),
+                request,
+            )]),
+        }
+    }
+
     fn process_signer_response(
         &self,
         response: TrustAnchorSignedResponse,
@@ -753,7 +972,7 @@ impl TrustAnchorProxy {
             response.validate(&signer.id)?;
 
             // We accept the response as is. Since children cannot be
-            // modified, and requests cannot change as long as
+            // updated or removed, and requests cannot change as long as
             // there is an open signer request we cannot have any
             // mismatches between the children and child requests in the proxy
             // vs the children and responses received from the
@@ -872,6 +1091,50 @@ impl TrustAnchorProxy {
             )))
         }
     }
+
+    fn process_update_child_resources(
+        &self,
+        child_handle: ChildHandle,
+        resources: ResourceSet,
+    ) -> KrillResult<Vec<TrustAnchorProxyEvent>> {
+        // The open signer request was made for the current resources, we
+        // can only change them after the response was processed.
+        if self.open_signer_request.is_some() {
+            return Err(Error::TaProxyHasRequest);
+        }
+        let child = self.get_child_details(&child_handle)?;
+
+        if child.resources == resources {
+            debug!(
+                "TA update child '{}' resources has no effect, child already holds these resources",
+                child_handle
+            );
+            Ok(vec![])
+        } else {
+            info!(
+                "TA update child '{}' resources: {}",
+                child_handle, resources
+            );
+            Ok(vec![TrustAnchorProxyEvent::ChildResourcesUpdated(
+                child_handle,
+                resources,
+            )])
+        }
+    }
+
+    fn process_remove_child(
+        &self,
+        child_handle: ChildHandle,
+    ) -> KrillResult<Vec<TrustAnchorProxyEvent>> {
+        // The open signer request may include requests for this child,
+        // we can only remove it after the response was processed.
+        if self.open_signer_request.is_some() {
+            return Err(Error::TaProxyHasRequest);
+        }
+        self.get_child_details(&child_handle)?;
+
+        Ok(vec![TrustAnchorProxyEvent::ChildRemoved(child_handle)])
+    }
 }
 
 impl TrustAnchorProxy {
@@ -899,6 +1162,8 @@ impl TrustAnchorProxy {
             TrustAnchorSignerRequest {
                 nonce,
                 child_requests,
+                child_updates: self.open_child_updates.clone(),
+                key_roll: self.open_key_roll_request.clone(),
             }
             .sign(
                 self.id.public_key().key_identifier(),
@@ -926,6 +1191,29 @@ impl TrustAnchorProxy {
             .map(|signer| &signer.objects)
     }
 
+    /// Returns the successor key, if a key rollover is in progress.
+    pub fn get_successor(&self) -> KrillResult<Option<&TaSuccessor>> {
+        self.signer
+            .as_ref()
+            .ok_or(Error::TaNotInitialized)
+            .map(|signer| signer.successor.as_ref())
+    }
+
+    /// Returns all elements to publish, including those for the
+    /// successor key during a key rollover.
+    pub fn publish_elements(
+        &self,
+    ) -> KrillResult<Vec<crate::commons::api::rrdp::PublishElement>> {
+        self.signer
+            .as_ref()
+            .ok_or(Error::TaNotInitialized)?
+            .publish_elements()
+    }
+
+    pub fn child_handles(&self) -> impl Iterator<Item = &ChildHandle> {
+        self.child_details.keys()
+    }
+
     pub fn id(&self) -> &IdCertInfo {
         &self.id
     }
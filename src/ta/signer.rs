@@ -62,6 +62,10 @@ pub struct TrustAnchorSigner {
     // Objects to be published under the TA certificate
     objects: TrustAnchorObjects,
 
+    // Successor TA key, if a key rollover is in progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    successor: Option<TaSuccessor>,
+
     // Proxy Signer Exchanges
     exchanges: TrustAnchorProxySignerExchanges,
 }
@@ -274,6 +278,7 @@ impl eventsourcing::Aggregate for TrustAnchorSigner {
             proxy_id: event.proxy_id,
             ta_cert_details: event.ta_cert_details,
             objects: event.objects,
+            successor: None,
             exchanges: TrustAnchorProxySignerExchanges::default(),
         }
     }
@@ -331,7 +336,12 @@ impl eventsourcing::Aggregate for TrustAnchorSigner {
 
         match event {
             TrustAnchorSignerEvent::ProxySignerExchangeDone(exchange) => {
-                self.objects = exchange.response.content().objects.clone();
+                let response = exchange.response.content();
+                self.objects = response.objects.clone();
+                self.successor = response.successor.clone();
+                if let Some(activated) = &response.activated {
+                    self.ta_cert_details = activated.clone();
+                }
                 self.exchanges.0.push(exchange);
             }
         }
@@ -372,6 +382,7 @@ impl TrustAnchorSigner {
             id: self.id.clone(),
             objects: self.objects.clone(),
             ta_cert_details: self.ta_cert_details.clone(),
+            successor: self.successor.clone(),
         }
     }
 
@@ -453,6 +464,45 @@ impl TrustAnchorSigner {
         Ok(TaCertDetails::new(rcvd_cert, tal))
     }
 
+    /// Creates a new successor key for a TA key rollover. The new TA
+    /// certificate uses the same repository as the current certificate.
+    fn create_successor(
+        &self,
+        init: &TaKeyRollInitRequest,
+        timing: &TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<TaSuccessor> {
+        if init.tal_rsync == *self.ta_cert_details.tal().rsync_uri() {
+            return Err(Error::Custom(
+                "The successor TA certificate needs a different rsync URI"
+                    .to_string(),
+            ));
+        }
+
+        let (ca_repository, _, rpki_notify, _) =
+            self.ta_cert_details.cert().csr_info().clone().unpack();
+        let repo_info = RepoInfo::new(ca_repository, rpki_notify);
+
+        let ta_cert_details = Self::create_ta_cert_details(
+            repo_info,
+            init.tal_https.clone(),
+            init.tal_rsync.clone(),
+            None,
+            timing.certificate_validity_years,
+            signer,
+        )?;
+        let objects = TrustAnchorObjects::create(
+            ta_cert_details.cert(),
+            1,
+            timing.mft_next_update_weeks,
+            signer,
+        )?;
+        let activate_after = Time::now()
+            + chrono::Duration::days(timing.key_roll_acceptance_days);
+
+        Ok(TaSuccessor::new(ta_cert_details, objects, activate_after))
+    }
+
     /// Process a request.
     fn process_signer_request(
         &self,
@@ -465,14 +515,73 @@ impl TrustAnchorSigner {
         // and the 'content' is not tampered with.
         signed_request.validate(&self.proxy_id)?;
 
+        let mut ta_cert_details = self.ta_cert_details.clone();
         let mut objects = self.objects.clone();
+        let mut successor = self.successor.clone();
+        let mut activated = None;
+
+        match &signed_request.content().key_roll {
+            None => {}
+            Some(TaKeyRollRequest::Initiate(init)) => {
+                if successor.is_some() {
+                    return Err(Error::TaKeyRollInProgress);
+                }
+                successor = Some(self.create_successor(
+                    init,
+                    &ta_timing_config,
+                    signer,
+                )?);
+            }
+            Some(TaKeyRollRequest::Activate) => {
+                let new =
+                    successor.take().ok_or(Error::TaKeyRollNotInProgress)?;
+                if !new.can_activate() {
+                    return Err(Error::TaKeyRollNotReady(
+                        new.activate_after(),
+                    ));
+                }
+                let (new_details, mut new_objects) = new.unpack();
+
+                // Re-issue all current child certificates under the new
+                // key. The old key is retired: its objects, including the
+                // certificates it issued, will no longer be published.
+                let validity = SignSupport::sign_validity_weeks(
+                    ta_timing_config.issued_certificate_validity_weeks,
+                );
+                for issued in objects.issued() {
+                    new_objects.add_issued(SignSupport::make_issued_cert(
+                        issued.csr_info().clone(),
+                        issued.resources(),
+                        issued.limit().clone(),
+                        new_details.cert(),
+                        validity,
+                        signer,
+                    )?);
+                }
+
+                ta_cert_details = new_details.clone();
+                objects = new_objects;
+                activated = Some(new_details);
+            }
+        }
+
+        let signing_cert = ta_cert_details.cert();
+
+        // Process updated and removed children before any child requests,
+        // so that new requests from an updated child take precedence.
+        let updated_children = Self::process_child_updates(
+            &signed_request.content().child_updates,
+            &mut objects,
+            signing_cert,
+            &ta_timing_config,
+            signer,
+        )?;
 
         let mut child_responses: HashMap<
             ChildHandle,
             HashMap<KeyIdentifier, ProvisioningResponse>,
         > = HashMap::new();
 
-        let signing_cert = self.ta_cert_details.cert();
         let ta_rcn = ta_resource_class_name();
 
         for child_request in &signed_request.content().child_requests {
@@ -576,17 +685,36 @@ impl TrustAnchorSigner {
             child_responses.insert(child_request.child.clone(), responses);
         }
 
+        // TAK objects are only published while a key rollover is in
+        // progress, to announce the successor key (RFC 9691).
+        let tak = successor.as_ref().map(|successor| {
+            TrustAnchorKeys::new(&ta_cert_details)
+                .with_successor(successor.ta_cert_details())
+        });
+
         objects.republish(
             signing_cert,
+            tak.as_ref(),
             ta_timing_config.mft_next_update_weeks,
             ta_mft_number_override,
             signer,
         )?;
 
+        if let Some(successor) = successor.as_mut() {
+            successor.republish(
+                &ta_cert_details,
+                ta_timing_config.mft_next_update_weeks,
+                signer,
+            )?;
+        }
+
         let response = TrustAnchorSignerResponse {
             nonce: signed_request.content().nonce.clone(),
             objects,
             child_responses,
+            successor,
+            activated,
+            updated_children,
         }
         .sign(
             ta_timing_config.signed_message_validity_days,
@@ -605,6 +733,84 @@ impl TrustAnchorSigner {
         )])
     }
 
+    /// Re-issue or revoke the certificates of children that were updated
+    /// or removed in the proxy. Returns the children that remain, so that
+    /// they can be told to pick up their new certificates.
+    fn process_child_updates(
+        updates: &[TrustAnchorChildUpdate],
+        objects: &mut TrustAnchorObjects,
+        signing_cert: &ReceivedCert,
+        ta_timing_config: &TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<ChildHandle>> {
+        let mut updated_children = vec![];
+
+        for update in updates {
+            match update {
+                TrustAnchorChildUpdate::Resources {
+                    child,
+                    resources,
+                    keys,
+                } => {
+                    let validity = SignSupport::sign_validity_weeks(
+                        ta_timing_config.issued_certificate_validity_weeks,
+                    );
+                    for key in keys {
+                        // There is nothing to re-issue if the certificate
+                        // was already revoked.
+                        let issued = match objects.get_issued(key) {
+                            Some(issued) => issued.clone(),
+                            None => continue,
+                        };
+
+                        // Keep the limit the child asked for, unless it is
+                        // no longer entitled to it. In that case keep what
+                        // remains of the resources it held.
+                        let (resources, limit) = match issued
+                            .limit()
+                            .apply_to(resources)
+                        {
+                            Ok(_) => {
+                                (resources.clone(), issued.limit().clone())
+                            }
+                            Err(_) => (
+                                resources.intersection(issued.resources()),
+                                RequestResourceLimit::default(),
+                            ),
+                        };
+
+                        if limit.apply_to(&resources)?.is_empty() {
+                            objects.revoke_issued(key);
+                        } else {
+                            objects.add_issued(
+                                SignSupport::make_issued_cert(
+                                    issued.csr_info().clone(),
+                                    &resources,
+                                    limit,
+                                    signing_cert,
+                                    validity,
+                                    signer,
+                                )?,
+                            );
+                        }
+                    }
+                    updated_children.push(child.clone());
+                }
+                TrustAnchorChildUpdate::Remove { keys, .. } => {
+                    // Unlike revocation requests from a child, we do not
+                    // fail on unknown keys here. There is no child left to
+                    // investigate the issue with, and all that matters is
+                    // that no certificate remains.
+                    for key in keys {
+                        objects.revoke_issued(key);
+                    }
+                }
+            }
+        }
+
+        Ok(updated_children)
+    }
+
     /// Get all exchanges
     pub fn get_exchanges(&self) -> &TrustAnchorProxySignerExchanges {
         &self.exchanges
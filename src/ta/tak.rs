@@ -0,0 +1,407 @@
+//! Trust Anchor Key (TAK) objects.
+//!
+//! See RFC 9691: https://www.rfc-editor.org/rfc/rfc9691.html
+//!
+//! TAK objects are signed by a Trust Anchor key and published alongside
+//! its CRL and manifest. They list the current key of the Trust Anchor,
+//! and its successor (or predecessor) during a key rollover, so that
+//! relying parties can learn about a new TA key before it is activated.
+
+use bcder::{
+    decode::{self, DecodeError, IntoSource},
+    encode::{self, Values},
+    Mode, OctetString, Oid, Tag,
+};
+use bytes::Bytes;
+use rpki::{
+    ca::publication::Base64,
+    crypto::PublicKey,
+    repository::{
+        sigobj::SignedObjectBuilder,
+        x509::{Time, Validity},
+    },
+};
+
+use crate::{
+    commons::{
+        api::{ObjectName, ReceivedCert},
+        crypto::KrillSigner,
+        KrillResult,
+    },
+    daemon::ca::{ObjectSetRevision, PublishedObject},
+};
+
+use super::TaCertDetails;
+
+/// The content type for TAK objects: 1.2.840.113549.1.9.16.1.50
+pub(super) const CT_TRUST_ANCHOR_KEYS: &[u8] =
+    &[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 50];
+
+//------------ TrustAnchorKeys ---------------------------------------------
+
+/// The content of a TAK object. This always includes the current key
+/// of the Trust Anchor, and during a key rollover its successor (when
+/// signed by the current key) or its predecessor (when signed by the
+/// successor key).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrustAnchorKeys {
+    current: TrustAnchorKey,
+    predecessor: Option<TrustAnchorKey>,
+    successor: Option<TrustAnchorKey>,
+}
+
+impl TrustAnchorKeys {
+    pub fn new(current: &TaCertDetails) -> Self {
+        TrustAnchorKeys {
+            current: current.into(),
+            predecessor: None,
+            successor: None,
+        }
+    }
+
+    pub fn with_predecessor(mut self, predecessor: &TaCertDetails) -> Self {
+        self.predecessor = Some(predecessor.into());
+        self
+    }
+
+    pub fn with_successor(mut self, successor: &TaCertDetails) -> Self {
+        self.successor = Some(successor.into());
+        self
+    }
+
+    pub fn current(&self) -> &TrustAnchorKey {
+        &self.current
+    }
+
+    pub fn predecessor(&self) -> Option<&TrustAnchorKey> {
+        self.predecessor.as_ref()
+    }
+
+    pub fn successor(&self) -> Option<&TrustAnchorKey> {
+        self.successor.as_ref()
+    }
+
+    /// Signs a TAK object with this content, using the given (TA)
+    /// signing certificate. The EE certificate inherits all resources,
+    /// and shares the validity of the manifest and CRL revision.
+    pub fn sign(
+        &self,
+        signing_cert: &ReceivedCert,
+        revision: &ObjectSetRevision,
+        signer: &KrillSigner,
+    ) -> KrillResult<PublishedObject> {
+        let signing_key = signing_cert.key_identifier();
+        let name = ObjectName::tak_for_key(&signing_key);
+
+        let mut object_builder = SignedObjectBuilder::new(
+            signer.random_serial()?,
+            Validity::new(revision.this_update(), revision.next_update()),
+            signing_cert.crl_uri(),
+            signing_cert.uri().clone(),
+            signing_cert.uri_for_name(&name),
+        );
+        object_builder.set_issuer(Some(signing_cert.subject().clone()));
+        object_builder.set_signing_time(Some(Time::now()));
+        object_builder.set_as_resources_inherit();
+        object_builder.set_v4_resources_inherit();
+        object_builder.set_v6_resources_inherit();
+
+        let object = signer.sign_object(
+            Oid(Bytes::from_static(CT_TRUST_ANCHOR_KEYS)),
+            self.encode_content(),
+            object_builder,
+            &signing_key,
+        )?;
+
+        let base64 = Base64::from_content(
+            &object.encode_ref().to_captured(Mode::Der).into_bytes(),
+        );
+
+        Ok(PublishedObject::new(
+            name,
+            base64,
+            object.cert().serial_number(),
+            revision.next_update(),
+        ))
+    }
+
+    /// Encodes the TAK content. The version is omitted, because it uses
+    /// the default value (0).
+    fn encode_content(&self) -> Bytes {
+        encode::sequence((
+            self.current.encode_ref(),
+            self.predecessor
+                .as_ref()
+                .map(|key| encode::sequence_as(Tag::CTX_0, key.encode_ref())),
+            self.successor
+                .as_ref()
+                .map(|key| encode::sequence_as(Tag::CTX_1, key.encode_ref())),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes()
+    }
+
+    /// Decodes TAK content, e.g. taken from a TAK object.
+    pub fn decode<S: IntoSource>(
+        source: S,
+    ) -> Result<Self, DecodeError<<S::Source as decode::Source>::Error>> {
+        Mode::Der.decode(source.into_source(), Self::take_from)
+    }
+
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>,
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            if let Some(version) = cons.take_opt_u8()? {
+                // DER does not allow encoding the default value.
+                return Err(cons.content_err(format!(
+                    "unexpected TAK version {}",
+                    version
+                )));
+            }
+            let current = TrustAnchorKey::take_from(cons)?;
+            let predecessor = cons.take_opt_constructed_if(
+                Tag::CTX_0,
+                TrustAnchorKey::take_from,
+            )?;
+            let successor = cons.take_opt_constructed_if(
+                Tag::CTX_1,
+                TrustAnchorKey::take_from,
+            )?;
+            Ok(TrustAnchorKeys {
+                current,
+                predecessor,
+                successor,
+            })
+        })
+    }
+}
+
+//------------ TrustAnchorKey ----------------------------------------------
+
+/// A single key listed in a TAK object, with the URIs of its TAL.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrustAnchorKey {
+    comments: Vec<String>,
+    certificate_uris: Vec<String>,
+    public_key: PublicKey,
+}
+
+impl TrustAnchorKey {
+    pub fn certificate_uris(&self) -> &[String] {
+        &self.certificate_uris
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    fn take_from<S: decode::Source>(
+        cons: &mut decode::Constructed<S>,
+    ) -> Result<Self, DecodeError<S::Error>> {
+        cons.take_sequence(|cons| {
+            let comments = cons.take_sequence(|cons| {
+                let mut comments = vec![];
+                while let Some(comment) =
+                    Self::take_opt_string(cons, Tag::UTF8_STRING)?
+                {
+                    comments.push(comment);
+                }
+                Ok(comments)
+            })?;
+            let certificate_uris = cons.take_sequence(|cons| {
+                let mut uris = vec![];
+                while let Some(uri) =
+                    Self::take_opt_string(cons, Tag::IA5_STRING)?
+                {
+                    uris.push(uri);
+                }
+                if uris.is_empty() {
+                    return Err(cons.content_err("TAK key without URIs"));
+                }
+                Ok(uris)
+            })?;
+            let public_key = PublicKey::take_from(cons)?;
+            Ok(TrustAnchorKey {
+                comments,
+                certificate_uris,
+                public_key,
+            })
+        })
+    }
+
+    fn take_opt_string<S: decode::Source>(
+        cons: &mut decode::Constructed<S>,
+        tag: Tag,
+    ) -> Result<Option<String>, DecodeError<S::Error>> {
+        cons.take_opt_value_if(tag, |content| {
+            let bytes = OctetString::from_content(content)?.into_bytes();
+            String::from_utf8(bytes.to_vec())
+                .map_err(|_| content.content_err("invalid string in TAK"))
+        })
+    }
+
+    fn encode_ref(&self) -> impl encode::Values + '_ {
+        encode::sequence((
+            encode::sequence(encode::iter(self.comments.iter().map(
+                |comment| {
+                    OctetString::encode_slice_as(
+                        comment.as_bytes(),
+                        Tag::UTF8_STRING,
+                    )
+                },
+            ))),
+            encode::sequence(encode::iter(self.certificate_uris.iter().map(
+                |uri| {
+                    OctetString::encode_slice_as(
+                        uri.as_bytes(),
+                        Tag::IA5_STRING,
+                    )
+                },
+            ))),
+            self.public_key.encode_ref(),
+        ))
+    }
+}
+
+impl From<&TaCertDetails> for TrustAnchorKey {
+    fn from(details: &TaCertDetails) -> Self {
+        let tal = details.tal();
+
+        let certificate_uris = tal
+            .uris()
+            .iter()
+            .map(|uri| uri.to_string())
+            .chain(std::iter::once(tal.rsync_uri().to_string()))
+            .collect();
+
+        TrustAnchorKey {
+            comments: vec![],
+            certificate_uris,
+            public_key: details.cert().csr_info().key().clone(),
+        }
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rpki::repository::Cert;
+
+    use super::*;
+
+    fn key(uri: &str) -> TrustAnchorKey {
+        let cert = Cert::decode(
+            include_bytes!("../../test-resources/ta.cer").as_ref(),
+        )
+        .unwrap();
+        TrustAnchorKey {
+            comments: vec!["test key".to_string()],
+            certificate_uris: vec![
+                format!("https://example.krill.cloud/ta/{}", uri),
+                format!("rsync://example.krill.cloud/ta/{}", uri),
+            ],
+            public_key: cert.subject_public_key_info().clone(),
+        }
+    }
+
+    #[test]
+    fn content_type() {
+        assert_eq!(
+            Oid(Bytes::from_static(CT_TRUST_ANCHOR_KEYS)).to_string(),
+            "1.2.840.113549.1.9.16.1.50"
+        );
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let current_only = TrustAnchorKeys {
+            current: key("ta.cer"),
+            predecessor: None,
+            successor: None,
+        };
+        let with_successor = TrustAnchorKeys {
+            successor: Some(key("ta-successor.cer")),
+            ..current_only.clone()
+        };
+        let with_predecessor = TrustAnchorKeys {
+            current: key("ta-successor.cer"),
+            predecessor: Some(key("ta.cer")),
+            successor: None,
+        };
+
+        for keys in [current_only, with_successor, with_predecessor] {
+            let decoded =
+                TrustAnchorKeys::decode(keys.encode_content()).unwrap();
+            assert_eq!(decoded, keys);
+        }
+    }
+
+    #[test]
+    fn predecessor_and_successor_are_explicitly_tagged() {
+        let keys = TrustAnchorKeys {
+            current: key("ta.cer"),
+            predecessor: Some(key("ta-old.cer")),
+            successor: Some(key("ta-new.cer")),
+        };
+
+        // The version is omitted, and both optional keys are a complete
+        // TAKey SEQUENCE inside a constructed context tag.
+        let public_keys = Mode::Der
+            .decode(keys.encode_content().into_source(), |cons| {
+                cons.take_sequence(|cons| {
+                    let current = TrustAnchorKey::take_from(cons)?;
+                    let predecessor = cons.take_constructed_if(
+                        Tag::CTX_0,
+                        TrustAnchorKey::take_from,
+                    )?;
+                    let successor = cons.take_constructed_if(
+                        Tag::CTX_1,
+                        TrustAnchorKey::take_from,
+                    )?;
+                    Ok((current, predecessor, successor))
+                })
+            })
+            .unwrap();
+        assert_eq!(
+            public_keys,
+            (key("ta.cer"), key("ta-old.cer"), key("ta-new.cer"))
+        );
+
+        // Implicitly tagged keys are rejected.
+        let (current, successor) = (key("ta.cer"), key("ta-new.cer"));
+        let implicit = encode::sequence((
+            current.encode_ref(),
+            encode::sequence_as(
+                Tag::CTX_1,
+                (
+                    encode::sequence(OctetString::encode_slice_as(
+                        b"implicitly tagged",
+                        Tag::UTF8_STRING,
+                    )),
+                    encode::sequence(OctetString::encode_slice_as(
+                        b"rsync://example.krill.cloud/ta/ta-new.cer",
+                        Tag::IA5_STRING,
+                    )),
+                    successor.public_key.encode_ref(),
+                ),
+            ),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes();
+        assert!(TrustAnchorKeys::decode(implicit).is_err());
+    }
+
+    #[test]
+    fn key_without_uris_is_rejected() {
+        let mut current = key("ta.cer");
+        current.certificate_uris.clear();
+        let keys = TrustAnchorKeys {
+            current,
+            predecessor: None,
+            successor: None,
+        };
+        assert!(TrustAnchorKeys::decode(keys.encode_content()).is_err());
+    }
+}
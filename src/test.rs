@@ -29,7 +29,8 @@ use url::Url;
 use crate::{
     cli::{
         options::{
-            BulkCaCommand, CaCommand, Command, Options, PubServerCommand,
+            BulkCaCommand, CaCommand, Command, HistoryOptions, Options,
+            PubServerCommand,
         },
         report::{ApiResponse, ReportFormat},
         {Error, KrillClient},
@@ -38,13 +39,18 @@ use crate::{
         api::{
             self, AddChildRequest, AspaDefinition, AspaDefinitionList,
             AspaProvidersUpdate, BgpSecAsnKey, BgpSecCsrInfoList,
-            BgpSecDefinition, CertAuthInfo, CertAuthInit, CertifiedKeyInfo,
-            ConfiguredRoa, ConfiguredRoas, CustomerAsn, ObjectName,
+            BgpSecDefinition, CaIssuanceTiming, CaRepoDetails, CaTemplate,
+            CaTemplateReport, CertAuthClone, CertAuthFromTemplate,
+            CertAuthInfo, CertAuthInit, CertAuthRename, CertifiedKeyInfo,
+            ChildAllocation, ChildCaInfo, ChildCertTiming, ConfiguredRoa,
+            ConfiguredRoas, CustomerAsn, EmergencyRekeyReport, GeofeedList,
+            HistoryPoint, KeyRollPhase, KeyRollPolicy, ObjectName,
             ParentCaContact, ParentCaReq, ParentStatuses,
             PublicationServerUris, PublisherDetails, PublisherList,
-            ResourceClassKeysInfo, RoaConfiguration, RoaConfigurationUpdates,
-            RoaPayload, RtaList, RtaName, RtaPrepResponse, TypedPrefix,
-            UpdateChildRequest,
+            RepoStatus, ResourceClassKeysInfo, ResourcePoolDefinition,
+            ResourcePoolList, RoaConfiguration, RoaConfigurationUpdates,
+            RoaPayload, RscList, RscName, RtaList, RtaName, RtaPrepResponse,
+            Token, TypedPrefix, UpdateChildRequest,
         },
         bgp::{Announcement, BgpAnalysisReport, BgpAnalysisSuggestion},
         crypto::SignSupport,
@@ -52,11 +58,14 @@ use crate::{
     },
     daemon::{
         ca::{
-            ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
+            GeofeedSignRequest, ResourceTaggedAttestation, RscChecklistItem,
+            RscSignRequest, RtaContentRequest, RtaPrepareRequest,
+            SignedGeofeed, SignedGeofeeds, SignedRsc,
         },
         config::Config,
         http::server,
     },
+    ta::{TaKeyRollInitRequest, TaSuccessor, TrustAnchorSignedRequest},
 };
 
 // #[cfg(test)]
@@ -251,6 +260,29 @@ async fn start_krill_with_error_trap(config: Arc<Config>) {
 /// own temp dir for storage.
 pub async fn start_krill_pubd(
     rrdp_delta_rrdp_delta_min_interval_seconds: u32,
+) -> impl FnOnce() {
+    // Initialize the repository using separate URIs
+    let uris = {
+        let rsync_base =
+            uri::Rsync::from_str("rsync://localhost/dedicated-repo/")
+                .unwrap();
+        let rrdp_base_uri =
+            uri::Https::from_str("https://localhost:3001/test-rrdp/")
+                .unwrap();
+        PublicationServerUris::new(rrdp_base_uri, rsync_base)
+    };
+    start_krill_pubd_with_uris(
+        rrdp_delta_rrdp_delta_min_interval_seconds,
+        uris,
+    )
+    .await
+}
+
+/// Starts a krill pubd for testing on its own port, and its own temp dir
+/// for storage, and initializes its repository with the given URIs.
+pub async fn start_krill_pubd_with_uris(
+    rrdp_delta_rrdp_delta_min_interval_seconds: u32,
+    uris: PublicationServerUris,
 ) -> impl FnOnce() {
     let (data_dir, cleanup) = tmp_dir();
     let storage_uri = mem_storage();
@@ -264,16 +296,6 @@ pub async fn start_krill_pubd(
     tokio::spawn(start_krill_with_error_trap(Arc::new(config)));
     assert!(krill_pubd_ready().await);
 
-    // Initialize the repository using separate URIs
-    let uris = {
-        let rsync_base =
-            uri::Rsync::from_str("rsync://localhost/dedicated-repo/")
-                .unwrap();
-        let rrdp_base_uri =
-            uri::Https::from_str("https://localhost:3001/test-rrdp/")
-                .unwrap();
-        PublicationServerUris::new(rrdp_base_uri, rsync_base)
-    };
     let command = PubServerCommand::RepositoryInit(uris);
     krill_dedicated_pubd_admin(command).await;
 
@@ -316,6 +338,18 @@ pub async fn krill_anon_http_get(
         .await
 }
 
+pub async fn krill_anon_http_bytes(rel_url: &str) -> Bytes {
+    let mut bytes = vec![];
+    httpclient::get_to_writer(
+        &format!("{}{}", KRILL_SERVER_URI, rel_url),
+        None,
+        &mut bytes,
+    )
+    .await
+    .unwrap();
+    Bytes::from(bytes)
+}
+
 pub async fn krill_admin(command: Command) -> ApiResponse {
     admin(service_uri(KRILL_SERVER_URI), command).await
 }
@@ -419,6 +453,50 @@ pub async fn delete_ca(ca: &CaHandle) {
     krill_admin(Command::CertAuth(CaCommand::Delete(ca.clone()))).await;
 }
 
+pub async fn ca_rename(
+    ca: &CaHandle,
+    new_handle: &CaHandle,
+    alias_days: Option<u32>,
+) {
+    krill_admin(Command::CertAuth(CaCommand::Rename(
+        ca.clone(),
+        CertAuthRename::new(new_handle.clone(), alias_days),
+    )))
+    .await;
+}
+
+pub async fn ca_template(ca: &CaHandle) -> CaTemplate {
+    match krill_admin(Command::CertAuth(CaCommand::TemplateShow(ca.clone())))
+        .await
+    {
+        ApiResponse::CaTemplate(template) => template,
+        _ => panic!("Expected CA template"),
+    }
+}
+
+pub async fn ca_clone(
+    ca: &CaHandle,
+    clone: CertAuthClone,
+) -> CaTemplateReport {
+    match krill_admin(Command::CertAuth(CaCommand::Clone(ca.clone(), clone)))
+        .await
+    {
+        ApiResponse::CaTemplateReport(report) => report,
+        _ => panic!("Expected CA template report"),
+    }
+}
+
+pub async fn ca_init_from_template(
+    request: CertAuthFromTemplate,
+) -> CaTemplateReport {
+    match krill_admin(Command::CertAuth(CaCommand::InitFromTemplate(request)))
+        .await
+    {
+        ApiResponse::CaTemplateReport(report) => report,
+        _ => panic!("Expected CA template report"),
+    }
+}
+
 pub async fn ca_repo_update_rfc8181(
     ca: &CaHandle,
     response: idexchange::RepositoryResponse,
@@ -430,6 +508,61 @@ pub async fn ca_repo_update_rfc8181(
     .await;
 }
 
+pub async fn ca_repo_details(ca: &CaHandle) -> CaRepoDetails {
+    match krill_admin(Command::CertAuth(CaCommand::RepoDetails(ca.clone())))
+        .await
+    {
+        ApiResponse::RepoDetails(details) => details,
+        _ => panic!("Expected repo details"),
+    }
+}
+
+pub async fn ca_repo_status(ca: &CaHandle) -> RepoStatus {
+    match krill_admin(Command::CertAuth(CaCommand::RepoStatus(ca.clone())))
+        .await
+    {
+        ApiResponse::RepoStatus(status) => status,
+        _ => panic!("Expected repo status"),
+    }
+}
+
+pub async fn ca_repo_mirror_add(
+    ca: &CaHandle,
+    name: &str,
+    response: idexchange::RepositoryResponse,
+) {
+    krill_admin(Command::CertAuth(CaCommand::RepoMirrorAdd(
+        ca.clone(),
+        name.to_string(),
+        response,
+    )))
+    .await;
+}
+
+pub async fn ca_repo_mirror_remove(ca: &CaHandle, name: &str) {
+    krill_admin(Command::CertAuth(CaCommand::RepoMirrorRemove(
+        ca.clone(),
+        name.to_string(),
+    )))
+    .await;
+}
+
+pub async fn ca_repo_migration_start(
+    ca: &CaHandle,
+    response: idexchange::RepositoryResponse,
+) {
+    krill_admin(Command::CertAuth(CaCommand::RepoMigrationStart(
+        ca.clone(),
+        response,
+    )))
+    .await;
+}
+
+pub async fn ca_repo_migration_abort(ca: &CaHandle) {
+    krill_admin(Command::CertAuth(CaCommand::RepoMigrationAbort(ca.clone())))
+        .await;
+}
+
 pub async fn generate_new_id(ca: &CaHandle) {
     krill_admin(Command::CertAuth(CaCommand::UpdateId(ca.clone()))).await;
 }
@@ -472,10 +605,51 @@ pub async fn add_child_rfc6492(
     child: ChildHandle,
     child_request: idexchange::ChildRequest,
     resources: ResourceSet,
+) -> idexchange::ParentResponse {
+    add_child_rfc6492_with_cert_timing(
+        ca,
+        child,
+        child_request,
+        resources,
+        ChildCertTiming::default(),
+    )
+    .await
+}
+
+pub async fn add_child_rfc6492_with_cert_timing(
+    ca: CaHandle,
+    child: ChildHandle,
+    child_request: idexchange::ChildRequest,
+    resources: ResourceSet,
+    cert_timing: ChildCertTiming,
+) -> idexchange::ParentResponse {
+    let id_cert = child_request.validate().unwrap();
+
+    let add_child_request = AddChildRequest::new(child, resources, id_cert)
+        .with_cert_timing(cert_timing);
+
+    match krill_admin(Command::CertAuth(CaCommand::ChildAdd(
+        ca,
+        add_child_request,
+    )))
+    .await
+    {
+        ApiResponse::Rfc8183ParentResponse(response) => response,
+        _ => panic!("Expected ParentCaInfo response"),
+    }
+}
+
+pub async fn add_child_rfc6492_with_allocation(
+    ca: CaHandle,
+    child: ChildHandle,
+    child_request: idexchange::ChildRequest,
+    resources: ResourceSet,
+    allocation: ChildAllocation,
 ) -> idexchange::ParentResponse {
     let id_cert = child_request.validate().unwrap();
 
-    let add_child_request = AddChildRequest::new(child, resources, id_cert);
+    let add_child_request = AddChildRequest::new(child, resources, id_cert)
+        .with_allocation(allocation);
 
     match krill_admin(Command::CertAuth(CaCommand::ChildAdd(
         ca,
@@ -509,6 +683,28 @@ pub async fn update_child_id(
     send_child_request(ca, &child_handle, req).await
 }
 
+pub async fn update_child_cert_timing(
+    ca: &CaHandle,
+    child: &CaHandle,
+    cert_timing: ChildCertTiming,
+) {
+    let child_handle = child.convert();
+    let req = UpdateChildRequest::cert_timing(cert_timing);
+    send_child_request(ca, &child_handle, req).await
+}
+
+pub async fn ca_child_info(ca: &CaHandle, child: &CaHandle) -> ChildCaInfo {
+    match krill_admin(Command::CertAuth(CaCommand::ChildInfo(
+        ca.clone(),
+        child.convert(),
+    )))
+    .await
+    {
+        ApiResponse::ChildInfo(info) => info,
+        _ => panic!("Expected child info"),
+    }
+}
+
 pub async fn delete_child(ca: &CaHandle, child: &CaHandle) {
     let child_handle = child.convert();
     krill_admin(Command::CertAuth(CaCommand::ChildDelete(
@@ -518,6 +714,39 @@ pub async fn delete_child(ca: &CaHandle, child: &CaHandle) {
     .await;
 }
 
+/// Starts a key roll of the embedded TA. The local TA signer picks up the
+/// request.
+pub async fn ta_key_roll_init(init: TaKeyRollInitRequest) {
+    let uri = format!("{}api/v1/ta/proxy/key-roll/init", KRILL_SERVER_URI);
+    let _request: TrustAnchorSignedRequest =
+        httpclient::post_json_with_response(&uri, init, Some(&admin_token()))
+            .await
+            .unwrap();
+}
+
+/// Activates the successor key of the embedded TA.
+pub async fn ta_key_roll_activate() {
+    let uri =
+        format!("{}api/v1/ta/proxy/key-roll/activate", KRILL_SERVER_URI);
+    let _request: TrustAnchorSignedRequest =
+        httpclient::post_empty_with_response(&uri, Some(&admin_token()))
+            .await
+            .unwrap();
+}
+
+/// Returns the successor key of the embedded TA, if a key roll is in
+/// progress.
+pub async fn ta_key_roll_successor() -> Option<TaSuccessor> {
+    let uri = format!("{}api/v1/ta/proxy/key-roll", KRILL_SERVER_URI);
+    httpclient::get_json(&uri, Some(&admin_token()))
+        .await
+        .unwrap()
+}
+
+fn admin_token() -> Token {
+    Token::from("secret")
+}
+
 pub async fn suspend_inactive_child(ca: &CaHandle, child: &ChildHandle) {
     let update = UpdateChildRequest::suspend();
 
@@ -632,8 +861,23 @@ pub async fn ca_route_authorizations_update_expect_error(
 }
 
 pub async fn ca_configured_roas(ca: &CaHandle) -> ConfiguredRoas {
+    ca_configured_roas_at_opt(ca, None).await
+}
+
+pub async fn ca_configured_roas_at(
+    ca: &CaHandle,
+    at: HistoryPoint,
+) -> ConfiguredRoas {
+    ca_configured_roas_at_opt(ca, Some(at)).await
+}
+
+async fn ca_configured_roas_at_opt(
+    ca: &CaHandle,
+    at: Option<HistoryPoint>,
+) -> ConfiguredRoas {
     match krill_admin(Command::CertAuth(CaCommand::RouteAuthorizationsList(
         ca.clone(),
+        at,
     )))
     .await
     {
@@ -647,7 +891,23 @@ pub async fn expect_configured_roas(
     ca: &CaHandle,
     expected: &[RoaConfiguration],
 ) {
-    let configured_roas = ca_configured_roas(ca).await.unpack();
+    check_configured_roas(ca_configured_roas(ca).await, expected);
+}
+
+// short hand to expect ROA configurations in a CA at a point in its history
+pub async fn expect_configured_roas_at(
+    ca: &CaHandle,
+    at: HistoryPoint,
+    expected: &[RoaConfiguration],
+) {
+    check_configured_roas(ca_configured_roas_at(ca, at).await, expected);
+}
+
+fn check_configured_roas(
+    configured_roas: ConfiguredRoas,
+    expected: &[RoaConfiguration],
+) {
+    let configured_roas = configured_roas.unpack();
     assert_eq!(configured_roas.len(), expected.len());
 
     // Copy the expected configs, but convert them to an explicit max length
@@ -752,9 +1012,11 @@ pub async fn ca_bgpsec_remove(ca: &CaHandle, key: BgpSecAsnKey) {
 }
 
 pub async fn ca_bgpsec_list(ca: &CaHandle) -> BgpSecCsrInfoList {
-    let res =
-        krill_admin(Command::CertAuth(CaCommand::BgpSecList(ca.clone())))
-            .await;
+    let res = krill_admin(Command::CertAuth(CaCommand::BgpSecList(
+        ca.clone(),
+        None,
+    )))
+    .await;
     match res {
         ApiResponse::BgpSecDefinitions(list) => list,
         _ => panic!("Expected BGPSec definitions"),
@@ -780,9 +1042,11 @@ pub async fn expect_aspa_definitions(
     ca: &CaHandle,
     expected_aspas: AspaDefinitionList,
 ) {
-    let res =
-        krill_admin(Command::CertAuth(CaCommand::AspasList(ca.clone())))
-            .await;
+    let res = krill_admin(Command::CertAuth(CaCommand::AspasList(
+        ca.clone(),
+        None,
+    )))
+    .await;
 
     if let ApiResponse::AspaDefinitions(found_aspas) = res {
         if expected_aspas != found_aspas {
@@ -834,9 +1098,35 @@ pub async fn ca_details(ca: &CaHandle) -> CertAuthInfo {
     ca_details_opt(ca).await.expect("Expected cert auth info")
 }
 
-pub async fn ca_details_opt(ca: &CaHandle) -> Option<CertAuthInfo> {
-    match krill_admin_may_fail(Command::CertAuth(CaCommand::Show(ca.clone())))
+/// Returns the version of the last command in the history of the CA.
+pub async fn ca_latest_version(ca: &CaHandle) -> u64 {
+    let history = |offset: usize| async move {
+        let options = HistoryOptions {
+            offset: offset as u64,
+            rows: 1,
+            ..Default::default()
+        };
+        match krill_admin(Command::CertAuth(CaCommand::ShowHistoryCommands(
+            ca.clone(),
+            options,
+        )))
         .await
+        {
+            ApiResponse::CertAuthHistory(history) => history,
+            _ => panic!("Expected CA history"),
+        }
+    };
+
+    let total = history(0).await.total();
+    history(total - 1).await.commands()[0].version
+}
+
+pub async fn ca_details_opt(ca: &CaHandle) -> Option<CertAuthInfo> {
+    match krill_admin_may_fail(Command::CertAuth(CaCommand::Show(
+        ca.clone(),
+        None,
+    )))
+    .await
     {
         Ok(ApiResponse::CertAuthInfo(inf)) => Some(inf),
         _ => None,
@@ -844,7 +1134,9 @@ pub async fn ca_details_opt(ca: &CaHandle) -> Option<CertAuthInfo> {
 }
 
 pub async fn ca_details_krill2(ca: &CaHandle) -> CertAuthInfo {
-    match krill2_admin(Command::CertAuth(CaCommand::Show(ca.clone()))).await {
+    match krill2_admin(Command::CertAuth(CaCommand::Show(ca.clone(), None)))
+        .await
+    {
         ApiResponse::CertAuthInfo(inf) => inf,
         _ => panic!("Expected cert auth info"),
     }
@@ -912,6 +1204,118 @@ pub async fn rta_multi_cosign(
     krill_admin(command).await;
 }
 
+fn rsc_sign_request(
+    resources: ResourceSet,
+    checklist: Vec<RscChecklistItem>,
+) -> RscSignRequest {
+    RscSignRequest::new(
+        resources,
+        SignSupport::sign_validity_days(14),
+        checklist,
+    )
+}
+
+pub async fn rsc_sign(
+    ca: CaHandle,
+    name: RscName,
+    resources: ResourceSet,
+    checklist: Vec<RscChecklistItem>,
+) {
+    let request = rsc_sign_request(resources, checklist);
+    let command = Command::CertAuth(CaCommand::RscSign(ca, name, request));
+    krill_admin(command).await;
+}
+
+pub async fn rsc_sign_expect_error(
+    ca: CaHandle,
+    name: RscName,
+    resources: ResourceSet,
+    checklist: Vec<RscChecklistItem>,
+) {
+    let request = rsc_sign_request(resources, checklist);
+    let command = Command::CertAuth(CaCommand::RscSign(ca, name, request));
+    krill_admin_expect_error(command).await;
+}
+
+pub async fn rsc_cosign(
+    ca: CaHandle,
+    name: RscName,
+    resources: ResourceSet,
+    checklist: Vec<RscChecklistItem>,
+) {
+    let request = rsc_sign_request(resources, checklist);
+    let command = Command::CertAuth(CaCommand::RscCoSign(ca, name, request));
+    krill_admin(command).await;
+}
+
+pub async fn rsc_list(ca: CaHandle) -> RscList {
+    let command = Command::CertAuth(CaCommand::RscList(ca));
+    match krill_admin(command).await {
+        ApiResponse::RscList(list) => list,
+        _ => panic!("Expected RSC list"),
+    }
+}
+
+pub async fn rsc_show(ca: CaHandle, name: RscName) -> SignedRsc {
+    let command = Command::CertAuth(CaCommand::RscShow(ca, name, None));
+    match krill_admin(command).await {
+        ApiResponse::Rsc(rsc) => rsc,
+        _ => panic!("Expected RSC"),
+    }
+}
+
+fn geofeed_sign_request(
+    geofeed: &str,
+    cosigners: Vec<CaHandle>,
+) -> GeofeedSignRequest {
+    GeofeedSignRequest::new(
+        geofeed.to_string(),
+        SignSupport::sign_validity_days(14),
+        cosigners,
+    )
+}
+
+pub async fn geofeed_sign(
+    ca: CaHandle,
+    geofeed: &str,
+    cosigners: Vec<CaHandle>,
+) -> SignedGeofeeds {
+    let request = geofeed_sign_request(geofeed, cosigners);
+    let command =
+        Command::CertAuth(CaCommand::GeofeedSign(ca, request, None));
+    match krill_admin(command).await {
+        ApiResponse::SignedGeofeeds(signed) => signed,
+        _ => panic!("Expected signed geofeeds"),
+    }
+}
+
+pub async fn geofeed_sign_expect_error(
+    ca: CaHandle,
+    geofeed: &str,
+    cosigners: Vec<CaHandle>,
+) {
+    let request = geofeed_sign_request(geofeed, cosigners);
+    let command =
+        Command::CertAuth(CaCommand::GeofeedSign(ca, request, None));
+    krill_admin_expect_error(command).await;
+}
+
+pub async fn geofeed_list(ca: CaHandle) -> GeofeedList {
+    let command = Command::CertAuth(CaCommand::GeofeedList(ca));
+    match krill_admin(command).await {
+        ApiResponse::GeofeedList(list) => list,
+        _ => panic!("Expected geofeed list"),
+    }
+}
+
+pub async fn geofeed_show(ca: CaHandle, number: u64) -> SignedGeofeed {
+    let command = Command::CertAuth(CaCommand::GeofeedShow(ca, number, None));
+    match krill_admin(command).await {
+        ApiResponse::Geofeed(geofeed) => geofeed,
+        _ => panic!("Expected signed geofeed"),
+    }
+}
+
 pub async fn ca_key_for_rcn(
     ca: &CaHandle,
     rcn: &ResourceClassName,
@@ -1164,6 +1568,34 @@ pub fn tmp_dir() -> (PathBuf, impl FnOnce()) {
     })
 }
 
+/// A random sub directory of the system's temporary directory.
+///
+/// The directory is removed when this is dropped, also when the test
+/// fails.
+pub struct TmpDir(PathBuf);
+
+impl TmpDir {
+    pub fn new() -> Self {
+        TmpDir(random_sub_dir(&std::env::temp_dir()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Default for TmpDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 fn random_hex_string() -> String {
     let mut bytes = [0; 8];
     openssl::rand::rand_bytes(&mut bytes).unwrap();
@@ -1177,6 +1609,13 @@ pub fn mem_storage() -> Url {
     Url::parse(&format!("memory://{}", random_hex_string())).unwrap()
 }
 
+/// Returns a storage URI for an SQLite database file in the given
+/// directory.
+#[cfg(feature = "sqlite")]
+pub fn sqlite_storage(dir: &Path) -> Url {
+    Url::parse(&format!("sqlite://{}/krill.db", dir.display())).unwrap()
+}
+
 /// This method sets up a random subdirectory and returns it. It is
 /// assumed that the caller will clean this directory themselves.
 // #[cfg(test)]
@@ -1466,6 +1905,94 @@ pub async fn ca_roll_activate(ca: &CaHandle) {
         .await;
 }
 
+pub async fn ca_emergency_rekey(
+    ca: &CaHandle,
+    reason: &str,
+) -> EmergencyRekeyReport {
+    match krill_admin(Command::CertAuth(CaCommand::KeyRollEmergency(
+        ca.clone(),
+        reason.to_string(),
+    )))
+    .await
+    {
+        ApiResponse::EmergencyRekey(report) => report,
+        _ => panic!("Expected emergency rekey report"),
+    }
+}
+
+pub async fn ca_roll_policy_set(ca: &CaHandle, policy: KeyRollPolicy) {
+    krill_admin(Command::CertAuth(CaCommand::KeyRollPolicySet(
+        ca.clone(),
+        policy,
+    )))
+    .await;
+}
+
+pub async fn ca_issuance_timing(ca: &CaHandle) -> CaIssuanceTiming {
+    match krill_admin(Command::CertAuth(CaCommand::IssuanceTimingShow(
+        ca.clone(),
+    )))
+    .await
+    {
+        ApiResponse::IssuanceTiming(timing) => timing,
+        _ => panic!("Expected issuance timing"),
+    }
+}
+
+pub async fn ca_issuance_timing_set(ca: &CaHandle, timing: CaIssuanceTiming) {
+    krill_admin(Command::CertAuth(CaCommand::IssuanceTimingSet(
+        ca.clone(),
+        timing,
+    )))
+    .await;
+}
+
+pub async fn ca_issuance_timing_remove(ca: &CaHandle) {
+    krill_admin(Command::CertAuth(CaCommand::IssuanceTimingRemove(
+        ca.clone(),
+    )))
+    .await;
+}
+
+pub async fn ca_resource_pools(ca: &CaHandle) -> ResourcePoolList {
+    match krill_admin(Command::CertAuth(CaCommand::ResourcePoolsList(
+        ca.clone(),
+    )))
+    .await
+    {
+        ApiResponse::ResourcePools(pools) => pools,
+        _ => panic!("Expected resource pools"),
+    }
+}
+
+pub async fn ca_resource_pool_set(
+    ca: &CaHandle,
+    name: &str,
+    resources: ResourceSet,
+) {
+    krill_admin(Command::CertAuth(CaCommand::ResourcePoolUpdate(
+        ca.clone(),
+        ResourcePoolDefinition::new(name.to_string(), resources),
+    )))
+    .await;
+}
+
+pub async fn ca_resource_pool_remove(ca: &CaHandle, name: &str) {
+    krill_admin(Command::CertAuth(CaCommand::ResourcePoolRemove(
+        ca.clone(),
+        name.to_string(),
+    )))
+    .await;
+}
+
+pub async fn ca_planned_key_roll(ca: &CaHandle) -> Option<KeyRollPhase> {
+    ca_details(ca)
+        .await
+        .key_roll()
+        .and_then(|status| status.next.as_ref())
+        .map(|planned| planned.phase)
+}
+
 pub async fn state_becomes_new_key(ca: &CaHandle) -> bool {
     for _ in 0..30_u8 {
         let ca = ca_details(ca).await;
@@ -1,6 +1,6 @@
 //! Support data migrations from one KV storage type to another.
 
-use std::{str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::Arc};
 
 use kvx::{Namespace, Scope};
 use rpki::crypto::KeyIdentifier;
@@ -21,6 +21,7 @@ use crate::{
         PUBSERVER_NS, SIGNERS_NS, TA_PROXY_SERVER_NS, TA_SIGNER_SERVER_NS,
     },
     daemon::{
+        backup::BACKUP_NAMESPACES,
         ca::{CaObjectsStore, CertAuth},
         config::Config,
         properties::{Properties, PropertiesManager},
@@ -54,7 +55,12 @@ pub fn migrate(mut config: Config, target_storage: Url) -> UpgradeResult<()> {
     // Update the config file with the new target_storage
     // and perform a normal data migration - the source data
     // could be for an older version of Krill.
+    // Data is copied as is, so the target storage uses the same
+    // encryption keys, if any.
     config.storage_uri = target_storage;
+    config
+        .init_storage_encryption()
+        .map_err(|e| UpgradeError::Custom(e.to_string()))?;
     let properties_manager =
         PropertiesManager::create(&config.storage_uri, false)?;
 
@@ -260,10 +266,10 @@ fn copy_data_for_migration(
             ))
         })?;
         let source_kv_store =
-            KeyValueStore::create(&config.storage_uri, namespace)?;
+            KeyValueStore::create_raw(&config.storage_uri, namespace)?;
         if !source_kv_store.is_empty()? {
             let target_kv_store =
-                KeyValueStore::create(target_storage, namespace)?;
+                KeyValueStore::create_raw(target_storage, namespace)?;
             target_kv_store.import(&source_kv_store)?;
         }
     }
@@ -271,6 +277,140 @@ fn copy_data_for_migration(
     Ok(())
 }
 
+//------------ Migrate Storage -----------------------------------------------
+
+/// The outcome of copying all data from one storage to another.
+#[derive(Clone, Debug, Default)]
+pub struct StorageMigrationReport {
+    pub namespaces: Vec<NamespaceMigration>,
+}
+
+/// The keys copied and verified for a single namespace.
+#[derive(Clone, Debug)]
+pub struct NamespaceMigration {
+    pub namespace: String,
+    pub scopes: usize,
+    pub keys: usize,
+}
+
+impl fmt::Display for StorageMigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<16} {:>8} {:>8}  status",
+            "namespace", "scopes", "keys"
+        )?;
+        for ns in &self.namespaces {
+            writeln!(
+                f,
+                "{:<16} {:>8} {:>8}  {}",
+                ns.namespace,
+                ns.scopes,
+                ns.keys,
+                if ns.keys == 0 { "empty" } else { "verified" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies all data in every namespace known to Krill from one storage to
+/// another, as is.
+///
+/// Every namespace in the target storage must be empty. After a namespace
+/// is copied, all its keys and values are read back from both storages
+/// and compared. Krill should not be running when doing this, as the
+/// source data would otherwise change while it is copied.
+///
+/// Values are copied as they are stored, so encrypted data stays
+/// encrypted with the same storage keys.
+pub fn migrate_storage(
+    from: &Url,
+    to: &Url,
+) -> UpgradeResult<StorageMigrationReport> {
+    if from == to {
+        return Err(UpgradeError::custom(
+            "Source and destination storage are the same",
+        ));
+    }
+
+    for ns in BACKUP_NAMESPACES {
+        let target = KeyValueStore::create(to, ns)?;
+        if !target.is_empty()? {
+            return Err(UpgradeError::custom(format!(
+                "Cannot migrate to {}, it already has data for {}",
+                to, ns
+            )));
+        }
+    }
+
+    let mut report = StorageMigrationReport::default();
+    for ns in BACKUP_NAMESPACES {
+        let source = KeyValueStore::create_raw(from, ns)?;
+        let target = KeyValueStore::create_raw(to, ns)?;
+
+        let scopes = source.scopes()?.len();
+        if !source.is_empty()? {
+            target.import(&source)?;
+        }
+
+        let source_entries = read_all(&source)?;
+        let target_entries = read_all(&target)?;
+        if let Some(key) = first_difference(&source_entries, &target_entries)
+        {
+            return Err(UpgradeError::custom(format!(
+                "Verification failed for namespace {}, key '{}' differs \
+                 between {} and {}",
+                ns, key, from, to
+            )));
+        }
+
+        info!(
+            "Migrated {} keys for namespace {}",
+            source_entries.len(),
+            ns
+        );
+        report.namespaces.push(NamespaceMigration {
+            namespace: ns.to_string(),
+            scopes,
+            keys: source_entries.len(),
+        });
+    }
+
+    Ok(report)
+}
+
+/// Reads all keys and values in a store, including those in the global
+/// scope.
+fn read_all(
+    store: &KeyValueStore,
+) -> UpgradeResult<BTreeMap<String, serde_json::Value>> {
+    let mut scopes = store.scopes()?;
+    scopes.push(Scope::global());
+
+    let mut entries = BTreeMap::new();
+    for scope in scopes {
+        for key in store.keys(&scope, "")? {
+            if let Some(value) = store.get(&key)? {
+                entries.insert(key.to_string(), value);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Returns the first key that is missing or has a different value in one
+/// of the two maps.
+fn first_difference<'a>(
+    left: &'a BTreeMap<String, serde_json::Value>,
+    right: &'a BTreeMap<String, serde_json::Value>,
+) -> Option<&'a str> {
+    left.iter()
+        .find(|(key, value)| right.get(*key) != Some(*value))
+        .or_else(|| right.iter().find(|(key, _)| !left.contains_key(*key)))
+        .map(|(key, _)| key.as_str())
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -278,7 +418,10 @@ pub mod tests {
 
     use log::LevelFilter;
 
-    use crate::test;
+    use crate::{
+        commons::eventsourcing::{segment, Key, Segment},
+        test,
+    };
 
     use super::*;
 
@@ -307,4 +450,29 @@ pub mod tests {
 
         migrate(config, target_store).unwrap();
     }
+
+    #[test]
+    fn test_migrate_storage() {
+        let source = test::mem_storage();
+        let cas = KeyValueStore::create(&source, CASERVER_NS).unwrap();
+        let ca = Scope::from_segment(segment!("ca"));
+        cas.store(&Key::new_scoped(ca, segment!("snapshot.json")), &1)
+            .unwrap();
+        let keys = KeyValueStore::create(&source, KEYS_NS).unwrap();
+        keys.store(&Key::new_global(segment!("key")), &"secret")
+            .unwrap();
+
+        let target = test::mem_storage();
+        let report = migrate_storage(&source, &target).unwrap();
+        assert_eq!(report.namespaces.len(), BACKUP_NAMESPACES.len());
+
+        let cas_report = &report.namespaces[0];
+        assert_eq!(cas_report.namespace, CASERVER_NS.to_string());
+        assert_eq!((cas_report.scopes, cas_report.keys), (1, 1));
+        let total: usize = report.namespaces.iter().map(|ns| ns.keys).sum();
+        assert_eq!(total, 2);
+
+        // The destination is no longer empty.
+        assert!(migrate_storage(&source, &target).is_err());
+    }
 }
@@ -0,0 +1,355 @@
+//! Check the consistency of stored Krill data.
+
+use kvx::Namespace;
+
+use crate::{
+    commons::{
+        crypto::dispatch::signerinfo::SignerInfo,
+        eventsourcing::{
+            Aggregate, AggregateStore, StorageCheck, WalStore, WalSupport,
+        },
+    },
+    constants::{
+        CASERVER_NS, PROPERTIES_NS, PUBSERVER_CONTENT_NS, PUBSERVER_NS,
+        SIGNERS_NS, TA_PROXY_SERVER_NS, TA_SIGNER_SERVER_NS,
+    },
+    daemon::{ca::CertAuth, config::Config, properties::Properties},
+    pubd::{RepositoryAccess, RepositoryContent},
+    ta::{TrustAnchorProxy, TrustAnchorSigner},
+};
+
+use super::UpgradeResult;
+
+//------------ FsckReport ----------------------------------------------------
+
+/// The outcome of checking all stored data.
+#[derive(Clone, Debug, Default)]
+pub struct FsckReport {
+    /// The number of instances checked.
+    pub checked: usize,
+
+    /// The number of instances with issues.
+    pub inconsistent: usize,
+
+    /// The number of instances for which all issues can be repaired.
+    pub repairable: usize,
+
+    /// The number of instances for which all issues were repaired.
+    pub repaired: usize,
+}
+
+impl FsckReport {
+    /// Returns whether issues remain that were not repaired.
+    pub fn has_unrepaired_issues(&self) -> bool {
+        self.inconsistent > self.repaired
+    }
+
+    fn add(&mut self, name: &str, check: &StorageCheck) {
+        self.checked += 1;
+        if check.is_ok() {
+            return;
+        }
+
+        self.inconsistent += 1;
+        if check.is_repairable() {
+            self.repairable += 1;
+        }
+        for issue in &check.issues {
+            warn!("  {} '{}': {}", name, check.handle, issue);
+        }
+        if check.repaired {
+            self.repaired += 1;
+            info!(
+                "  {} '{}': repaired by rebuilding snapshot",
+                name, check.handle
+            );
+        } else if !check.is_repairable() {
+            warn!(
+                "  {} '{}': cannot be repaired automatically",
+                name, check.handle
+            );
+        }
+    }
+}
+
+//------------ Check all data ------------------------------------------------
+
+/// Checks the consistency of all event-sourced and write-ahead logged data
+/// in the storage used by the config.
+///
+/// If `repair` is set and issues were found that can be repaired, then
+/// `confirm_repair` is asked whether to go ahead. Only if it agrees, the
+/// snapshots are rebuilt where this resolves the issues found, and the
+/// report of the repair is returned. Krill should not be running when
+/// doing this.
+pub fn check_storage(
+    config: &Config,
+    repair: bool,
+    confirm_repair: impl FnOnce(&FsckReport) -> bool,
+) -> UpgradeResult<FsckReport> {
+    info!("-----------------------------------------------------------");
+    info!("                 Krill Storage Check");
+    info!("-----------------------------------------------------------");
+    info!("");
+    info!("Storage: {}", &config.storage_uri);
+
+    let report = check_all(config, false)?;
+    if !repair || report.repairable == 0 {
+        return Ok(report);
+    }
+    if !confirm_repair(&report) {
+        info!("");
+        info!("Nothing was repaired.");
+        return Ok(report);
+    }
+
+    info!("");
+    info!("Repair: rebuild snapshots where possible");
+    check_all(config, true)
+}
+
+fn check_all(config: &Config, repair: bool) -> UpgradeResult<FsckReport> {
+    let mut report = FsckReport::default();
+
+    check_agg_store::<Properties>(
+        config,
+        PROPERTIES_NS,
+        "Properties",
+        repair,
+        &mut report,
+    )?;
+    check_agg_store::<SignerInfo>(
+        config,
+        SIGNERS_NS,
+        "Signer",
+        repair,
+        &mut report,
+    )?;
+    check_agg_store::<CertAuth>(
+        config,
+        CASERVER_NS,
+        "CA",
+        repair,
+        &mut report,
+    )?;
+    check_agg_store::<RepositoryAccess>(
+        config,
+        PUBSERVER_NS,
+        "Publication Server Access",
+        repair,
+        &mut report,
+    )?;
+    check_wal_store::<RepositoryContent>(
+        config,
+        PUBSERVER_CONTENT_NS,
+        "Publication Server Objects",
+        repair,
+        &mut report,
+    )?;
+    check_agg_store::<TrustAnchorProxy>(
+        config,
+        TA_PROXY_SERVER_NS,
+        "TA Proxy",
+        repair,
+        &mut report,
+    )?;
+    check_agg_store::<TrustAnchorSigner>(
+        config,
+        TA_SIGNER_SERVER_NS,
+        "TA Signer",
+        repair,
+        &mut report,
+    )?;
+
+    info!("");
+    info!(
+        "Checked {} instances, found {} with issues, repaired {}.",
+        report.checked, report.inconsistent, report.repaired
+    );
+
+    Ok(report)
+}
+
+fn check_agg_store<A: Aggregate>(
+    config: &Config,
+    ns: &Namespace,
+    name: &str,
+    repair: bool,
+    report: &mut FsckReport,
+) -> UpgradeResult<()> {
+    info!("");
+    info!("Check: {name}");
+    let store: AggregateStore<A> =
+        AggregateStore::create(&config.storage_uri, ns, false)?;
+
+    for handle in store.list()? {
+        let check = store.check_consistency(&handle, repair)?;
+        report.add(name, &check);
+    }
+    Ok(())
+}
+
+fn check_wal_store<W: WalSupport>(
+    config: &Config,
+    ns: &Namespace,
+    name: &str,
+    repair: bool,
+    report: &mut FsckReport,
+) -> UpgradeResult<()> {
+    info!("");
+    info!("Check: {name}");
+    let store: WalStore<W> = WalStore::create(&config.storage_uri, ns)?;
+
+    for handle in store.list()? {
+        let check = store.check_consistency(&handle, repair)?;
+        report.add(name, &check);
+    }
+    Ok(())
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, path::PathBuf, str::FromStr};
+
+    use kvx::{segment, Key, Scope, Segment};
+    use rpki::ca::idexchange::MyHandle;
+
+    use crate::{
+        commons::{eventsourcing::KeyValueStore, util::KrillVersion},
+        constants::PROPERTIES_DFLT_NAME,
+        daemon::properties::PropertiesManager,
+        test,
+    };
+
+    use super::*;
+
+    /// Returns a config for storage with properties at version 2, and a
+    /// snapshot, and the store to tamper with them.
+    fn properties_storage() -> (Config, KeyValueStore) {
+        let storage_uri = test::mem_storage();
+        let properties =
+            PropertiesManager::create(&storage_uri, false).unwrap();
+        properties.init(KrillVersion::release(0, 13, 0)).unwrap();
+        properties
+            .upgrade_krill_version(KrillVersion::release(0, 14, 0))
+            .unwrap();
+        properties
+            .upgrade_krill_version(KrillVersion::code_version())
+            .unwrap();
+
+        let store: AggregateStore<Properties> =
+            AggregateStore::create(&storage_uri, PROPERTIES_NS, false)
+                .unwrap();
+        let handle = MyHandle::from_str(PROPERTIES_DFLT_NAME).unwrap();
+        store.save_snapshot(&handle).unwrap();
+
+        // needed for tls_dir etc, but will be ignored here
+        let bogus_path = PathBuf::from("/dev/null");
+        let config = Config::test(
+            &storage_uri,
+            Some(&bogus_path),
+            false,
+            false,
+            false,
+            false,
+        );
+        let kv = KeyValueStore::create(&storage_uri, PROPERTIES_NS).unwrap();
+        (config, kv)
+    }
+
+    fn properties_key(name: &'static str) -> Key {
+        Key::new_scoped(
+            Scope::from_segment(segment!("main")),
+            Segment::parse(name).unwrap(),
+        )
+    }
+
+    /// Checks the storage, and returns the report and whether a repair
+    /// was confirmed.
+    fn check(
+        config: &Config,
+        repair: bool,
+        agree: bool,
+    ) -> (FsckReport, bool) {
+        let asked = Cell::new(false);
+        let report = check_storage(config, repair, |_| {
+            asked.set(true);
+            agree
+        })
+        .unwrap();
+        (report, asked.get())
+    }
+
+    #[test]
+    fn consistent_storage() {
+        let (config, _) = properties_storage();
+        let (report, asked) = check(&config, true, true);
+        assert_eq!(report.checked, 1);
+        assert_eq!(report.inconsistent, 0);
+        assert!(!report.has_unrepaired_issues());
+        assert!(!asked);
+    }
+
+    #[test]
+    fn repair_snapshot_mismatch_after_confirmation() {
+        let (config, kv) = properties_storage();
+        let snapshot_key = properties_key("snapshot.json");
+        let mut snapshot: serde_json::Value =
+            kv.get(&snapshot_key).unwrap().unwrap();
+        snapshot["krill_version"] =
+            serde_json::to_value(KrillVersion::release(0, 9, 0)).unwrap();
+        kv.store(&snapshot_key, &snapshot).unwrap();
+
+        // Without --repair, nothing is asked or changed.
+        let (report, asked) = check(&config, false, true);
+        assert_eq!(report.inconsistent, 1);
+        assert_eq!(report.repairable, 1);
+        assert!(report.has_unrepaired_issues());
+        assert!(!asked);
+
+        // With --repair, but without confirmation, nothing is changed.
+        let (report, asked) = check(&config, true, false);
+        assert!(asked);
+        assert_eq!(report.repaired, 0);
+        assert_eq!(
+            kv.get::<serde_json::Value>(&snapshot_key).unwrap().unwrap(),
+            snapshot
+        );
+
+        // Once confirmed, the snapshot is rebuilt.
+        let (report, asked) = check(&config, true, true);
+        assert!(asked);
+        assert_eq!(report.repaired, 1);
+        assert!(!report.has_unrepaired_issues());
+
+        let (report, _) = check(&config, false, false);
+        assert_eq!(report.inconsistent, 0);
+    }
+
+    #[test]
+    fn version_gap_cannot_be_repaired() {
+        let (config, kv) = properties_storage();
+        kv.drop_key(&properties_key("command-1.json")).unwrap();
+
+        let (report, asked) = check(&config, true, true);
+        assert_eq!(report.inconsistent, 1);
+        assert_eq!(report.repairable, 0);
+        assert!(report.has_unrepaired_issues());
+        assert!(!asked);
+    }
+
+    #[test]
+    fn unreadable_command_cannot_be_repaired() {
+        let (config, kv) = properties_storage();
+        kv.store(&properties_key("command-1.json"), &"not a command")
+            .unwrap();
+
+        let (report, asked) = check(&config, true, true);
+        assert_eq!(report.inconsistent, 1);
+        assert_eq!(report.repairable, 0);
+        assert!(report.has_unrepaired_issues());
+        assert!(!asked);
+    }
+}
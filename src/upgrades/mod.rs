@@ -54,6 +54,12 @@ use self::{pre_0_13_0::OldRepositoryContent, pre_0_14_0::OldCommandKey};
 
 pub mod data_migration;
 
+pub mod fsck;
+
+pub mod reencrypt;
+
+pub mod restore;
+
 pub mod pre_0_10_0;
 
 #[allow(clippy::mutable_key_type)]
@@ -164,6 +170,18 @@ impl UpgradeVersions {
         }
     }
 
+    /// Returns an error if data cannot be upgraded directly from the
+    /// current version to the code version.
+    pub fn check_supported(&self) -> UpgradeResult<()> {
+        if self.from < KrillVersion::release(0, 6, 0) {
+            Err(UpgradeError::custom("Cannot upgrade Krill installations from before version 0.6.0. Please upgrade to 0.8.1 first, then upgrade to 0.12.3, and then upgrade to this version."))
+        } else if self.from < KrillVersion::release(0, 9, 0) {
+            Err(UpgradeError::custom("Cannot upgrade Krill installations from before version 0.9.0. Please upgrade to 0.12.3 first, and then upgrade to this version."))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn from(&self) -> &KrillVersion {
         &self.from
     }
@@ -874,14 +892,9 @@ pub fn prepare_upgrade_data_migrations(
                 return Err(UpgradeError::OldTaMigration);
             }
 
-            if versions.from < KrillVersion::release(0, 6, 0) {
-                let msg = "Cannot upgrade Krill installations from before version 0.6.0. Please upgrade to 0.8.1 first, then upgrade to 0.12.3, and then upgrade to this version.";
-                error!("{}", msg);
-                Err(UpgradeError::custom(msg))
-            } else if versions.from < KrillVersion::release(0, 9, 0) {
-                let msg = "Cannot upgrade Krill installations from before version 0.9.0. Please upgrade to 0.12.3 first, and then upgrade to this version.";
-                error!("{}", msg);
-                Err(UpgradeError::custom(msg))
+            if let Err(e) = versions.check_supported() {
+                error!("{}", e);
+                Err(e)
             } else if versions.from < KrillVersion::candidate(0, 10, 0, 1) {
                 // Complex migrations involving command / event conversions
                 pre_0_10_0::PublicationServerRepositoryAccessMigration::upgrade(mode, config, &versions)?;
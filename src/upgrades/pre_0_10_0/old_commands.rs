@@ -12,8 +12,9 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            CertAuthStorableCommand, RoaConfigurationUpdates, RtaName,
-            StorableParentContact, StorableRcEntitlement,
+            CertAuthStorableCommand, ChildAllocation,
+            RoaConfigurationUpdates, RtaName, StorableParentContact,
+            StorableRcEntitlement,
         },
         eventsourcing::WithStorableDetails,
     },
@@ -145,13 +146,13 @@ impl From<Pre0_10_0CertAuthStorableCommand> for CertAuthStorableCommand {
         match old {
             Pre0_10_0CertAuthStorableCommand::Init => CertAuthStorableCommand::Init,
             Pre0_10_0CertAuthStorableCommand::ChildAdd { child, ski, resources } => {
-                CertAuthStorableCommand::ChildAdd { child, ski, resources }
+                CertAuthStorableCommand::ChildAdd { child, ski, resources, allocation: ChildAllocation::default() }
             }
             Pre0_10_0CertAuthStorableCommand::ChildImport { child, ski, resources } => {
                 CertAuthStorableCommand::ChildImport { child, ski, resources }
             }
             Pre0_10_0CertAuthStorableCommand::ChildUpdateResources { child, resources } => {
-                CertAuthStorableCommand::ChildUpdateResources { child, resources }
+                CertAuthStorableCommand::ChildUpdateResources { child, resources, allocation: ChildAllocation::default() }
             }
             Pre0_10_0CertAuthStorableCommand::ChildUpdateId { child, ski } => {
                 CertAuthStorableCommand::ChildUpdateId { child, ski }
@@ -14,8 +14,9 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            CertAuthStorableCommand, CustomerAsn, RoaConfigurationUpdates,
-            RtaName, StorableParentContact, StorableRcEntitlement,
+            CertAuthStorableCommand, ChildAllocation, CustomerAsn,
+            RoaConfigurationUpdates, RtaName, StorableParentContact,
+            StorableRcEntitlement,
         },
         eventsourcing::WithStorableDetails,
     },
@@ -143,13 +144,13 @@ impl From<Pre0_14_0CertAuthStorableCommand> for CertAuthStorableCommand {
         match old {
             Pre0_14_0CertAuthStorableCommand::Init => CertAuthStorableCommand::Init,
             Pre0_14_0CertAuthStorableCommand::ChildAdd { child, ski, resources } => {
-                CertAuthStorableCommand::ChildAdd { child, ski, resources }
+                CertAuthStorableCommand::ChildAdd { child, ski, resources, allocation: ChildAllocation::default() }
             }
             Pre0_14_0CertAuthStorableCommand::ChildImport { child, ski, resources } => {
                 CertAuthStorableCommand::ChildImport { child, ski, resources }
             }
             Pre0_14_0CertAuthStorableCommand::ChildUpdateResources { child, resources } => {
-                CertAuthStorableCommand::ChildUpdateResources { child, resources }
+                CertAuthStorableCommand::ChildUpdateResources { child, resources, allocation: ChildAllocation::default() }
             }
             Pre0_14_0CertAuthStorableCommand::ChildUpdateId { child, ski } => {
                 CertAuthStorableCommand::ChildUpdateId { child, ski }
@@ -0,0 +1,42 @@
+//! Re-encrypt all stored Krill data with the current storage key.
+
+use crate::{
+    commons::eventsourcing::{storage_keys, KeyValueStore},
+    daemon::{backup::BACKUP_NAMESPACES, config::Config},
+    upgrades::UpgradeError,
+};
+
+use super::UpgradeResult;
+
+/// Re-encrypts all values in the storage used by the config with the
+/// current storage encryption key.
+///
+/// This is used to rotate the key, after the previous key was configured
+/// as a previous key, or to encrypt data that was stored before encryption
+/// was enabled. Every scope is locked while its values are re-encrypted,
+/// so this can be done while Krill is running.
+pub fn reencrypt_storage(config: &Config) -> UpgradeResult<()> {
+    info!("-----------------------------------------------------------");
+    info!("                 Krill Storage Re-encryption");
+    info!("-----------------------------------------------------------");
+    info!("");
+    info!("Storage: {}", &config.storage_uri);
+
+    let keys = storage_keys(&config.storage_uri).ok_or_else(|| {
+        UpgradeError::custom("No storage encryption key is configured")
+    })?;
+    info!("Key:     {}", keys.current().id());
+    info!("");
+
+    let mut total = 0;
+    for ns in BACKUP_NAMESPACES {
+        let store = KeyValueStore::create(&config.storage_uri, ns)?;
+        let count = store.reencrypt()?;
+        info!("Re-encrypted {} values for namespace {}", count, ns);
+        total += count;
+    }
+
+    info!("");
+    info!("Re-encrypted {} values in total", total);
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Restore Krill data from a backup archive.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use crate::{
+    commons::{error::KrillIoError, eventsourcing::KeyValueStore},
+    daemon::{
+        backup::{self, BACKUP_NAMESPACES},
+        config::Config,
+        properties::PropertiesManager,
+    },
+    upgrades::{
+        finalise_data_migration, prepare_upgrade_data_migrations,
+        UpgradeError, UpgradeMode, UpgradeVersions,
+    },
+};
+
+use super::UpgradeResult;
+
+/// Restores a backup archive into the storage used by the config.
+///
+/// The archive is verified against its manifest first, and the storage
+/// must be empty. Backups made by an older version of Krill are upgraded
+/// after they have been restored, if there is an upgrade path. Backups
+/// made by a newer version are refused.
+pub fn restore(config: &Config, archive: &Path) -> UpgradeResult<()> {
+    info!("-----------------------------------------------------------");
+    info!("                 Krill Restore");
+    info!("-----------------------------------------------------------");
+    info!("");
+    info!("Archive: {}", archive.display());
+    info!("Storage: {}", &config.storage_uri);
+    info!("");
+
+    let manifest = backup::verify_backup(open_archive(archive)?)?;
+    info!(
+        "Verified backup made by Krill {} at {}",
+        manifest.krill_version,
+        manifest.created.to_rfc3339()
+    );
+
+    let versions = UpgradeVersions::for_current(manifest.krill_version)?;
+    if let Some(versions) = &versions {
+        versions.check_supported()?;
+        info!(
+            "Data will be upgraded from {} to {} after it is restored",
+            versions.from(),
+            versions.to()
+        );
+    }
+
+    for ns in BACKUP_NAMESPACES {
+        let store = KeyValueStore::create(&config.storage_uri, ns)?;
+        if !store.is_empty()? {
+            return Err(UpgradeError::custom(format!(
+                "Cannot restore into {}, it already has data for {}",
+                config.storage_uri, ns
+            )));
+        }
+    }
+
+    backup::restore_backup(open_archive(archive)?, &config.storage_uri)?;
+
+    if versions.is_some() {
+        let properties_manager =
+            PropertiesManager::create(&config.storage_uri, false)?;
+        if let Some(upgrade) = prepare_upgrade_data_migrations(
+            UpgradeMode::PrepareToFinalise,
+            config,
+            &properties_manager,
+        )? {
+            finalise_data_migration(
+                upgrade.versions(),
+                config,
+                &properties_manager,
+            )?;
+        }
+    }
+
+    info!("");
+    info!("Restored backup into {}", config.storage_uri);
+    Ok(())
+}
+
+fn open_archive(path: &Path) -> UpgradeResult<BufReader<File>> {
+    let file = File::open(path).map_err(|e| {
+        KrillIoError::new(
+            format!("Cannot open backup archive '{}'", path.display()),
+            e,
+        )
+    })?;
+    Ok(BufReader::new(file))
+}
@@ -0,0 +1,204 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use chrono::Duration;
+use rpki::{
+    ca::idexchange::CaHandle,
+    repository::{resources::ResourceSet, x509::Time, Manifest, Roa},
+};
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{
+        CaIssuanceTiming, ObjectName, RoaConfiguration,
+        RoaConfigurationUpdates, RoaPayload,
+    },
+    test::*,
+};
+
+/// Returns whether the given time is the expected duration from now, give
+/// or take an hour.
+fn is_from_now(time: Time, expected: Duration) -> bool {
+    let expected = Time::now() + expected;
+    time > expected - Duration::hours(1)
+        && time < expected + Duration::hours(1)
+}
+
+/// Returns whether the CA publishes a ROA valid for the given number of
+/// weeks, and a manifest with a next update time the given number of hours
+/// from now.
+async fn published_with_timing(
+    ca: &CaHandle,
+    roa_name: &str,
+    roa_weeks: i64,
+    mft_hours: i64,
+) -> bool {
+    for _ in 0..30_u8 {
+        let published = publisher_details(ca.convert()).await;
+        let roa = published
+            .current_files()
+            .iter()
+            .find(|file| file.uri().ends_with(roa_name))
+            .map(|file| {
+                Roa::decode(file.base64().to_bytes().as_ref(), true).unwrap()
+            });
+        let mft = published
+            .current_files()
+            .iter()
+            .find(|file| file.uri().ends_with(".mft"))
+            .map(|file| {
+                Manifest::decode(file.base64().to_bytes().as_ref(), true)
+                    .unwrap()
+            });
+
+        if let (Some(roa), Some(mft)) = (roa, mft) {
+            if is_from_now(
+                roa.cert().validity().not_after(),
+                Duration::weeks(roa_weeks),
+            ) && is_from_now(
+                mft.content().next_update(),
+                Duration::hours(mft_hours),
+            ) {
+                return true;
+            }
+        }
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_ca_issuance_timing() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test CA Issuance Timing                          #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * Invalid issuance timing overrides for a CA are rejected     #",
+    );
+    info(
+        "#  * ROAs and manifests are issued using the CA overrides        #",
+    );
+    info(
+        "#  * Overrides can be removed to use the server defaults again   #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Setting ROAs to be re-issued only after they expire fails     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let timing = CaIssuanceTiming {
+            timing_roa_valid_weeks: Some(4),
+            timing_roa_reissue_weeks_before: Some(4),
+            ..Default::default()
+        };
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::IssuanceTimingSet(testbed.clone(), timing),
+        ))
+        .await;
+        assert!(ca_issuance_timing(&testbed).await.is_empty());
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Issue ROAs valid for 10 weeks, and manifests with a next      #",
+    );
+    info(
+        "#  update time 48 hours from now                                 #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let timing = CaIssuanceTiming {
+            timing_publish_next_hours: Some(48),
+            timing_publish_next_jitter_hours: Some(0),
+            timing_roa_valid_weeks: Some(10),
+            timing_roa_reissue_weeks_before: Some(2),
+            ..Default::default()
+        };
+        ca_issuance_timing_set(&testbed, timing).await;
+        assert_eq!(ca_issuance_timing(&testbed).await, timing);
+
+        let roa_payload =
+            RoaPayload::from_str("10.0.0.0/16-16 => 64496").unwrap();
+        let roa_name = ObjectName::from(&roa_payload).to_string();
+        let mut updates = RoaConfigurationUpdates::empty();
+        updates.add(RoaConfiguration::from(roa_payload));
+        ca_route_authorizations_update(&testbed, updates).await;
+
+        assert!(published_with_timing(&testbed, &roa_name, 10, 48).await);
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Remove the overrides to use the server defaults again         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        ca_issuance_timing_remove(&testbed).await;
+        assert!(ca_issuance_timing(&testbed).await.is_empty());
+    }
+
+    cleanup();
+}
@@ -0,0 +1,178 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{CertAuthInit, ObjectName, RoaConfigurationUpdates},
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_ca_rename() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Renaming a CA                               #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * A renamed CA keeps its objects in the embedded repository   #",
+    );
+    info(
+        "#  * Its local parent and child use the new name                 #",
+    );
+    info(
+        "#  * The old name can be used as an alias for the API            #",
+    );
+    info(
+        "#  * The alias cannot be used for a new CA                       #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca1 = ca_handle("CA1");
+    let ca1_renamed = ca_handle("CA1-renamed");
+    let ca2 = ca_handle("CA2");
+
+    let ca1_res = resources("AS65000", "10.0.0.0/8", "");
+    let ca2_res = resources("AS65000", "10.0.0.0/16", "");
+    let ca2_res_shrunk = resources("AS65000", "10.0.0.0/24", "");
+
+    let roa = roa_configuration("10.0.0.0/24-24 => 64496");
+
+    let rcn_0 = rcn(0);
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &testbed, &ca1_res).await;
+        set_up_ca_with_repo(&ca2).await;
+        set_up_ca_under_parent_with_resources(&ca2, &ca1, &ca2_res).await;
+
+        let mut updates = RoaConfigurationUpdates::empty();
+        updates.add(roa.clone());
+        ca_route_authorizations_update(&ca1, updates).await;
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Rename CA1, and keep its old name as an alias                 #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        ca_rename(&ca1, &ca1_renamed, Some(1)).await;
+
+        let details = ca_details(&ca1_renamed).await;
+        assert_eq!(details.handle(), &ca1_renamed);
+        assert!(details.aliases().contains_key(&ca1));
+
+        // The old name is an alias for the renamed CA
+        assert_eq!(ca_details(&ca1).await.handle(), &ca1_renamed);
+
+        // The parent knows the CA by its new name
+        assert_eq!(
+            ca_child_info(&testbed, &ca1_renamed)
+                .await
+                .entitled_resources(),
+            &ca1_res
+        );
+
+        // The CA still publishes its objects in the embedded repository
+        let mut expected_files =
+            expected_mft_and_crl(&ca1_renamed, &rcn_0).await;
+        expected_files.push(expected_issued_cer(&ca2, &rcn_0).await);
+        expected_files.push(ObjectName::from(&roa.payload()).to_string());
+        assert!(
+            will_publish_embedded(
+                "renamed CA should publish its objects",
+                &ca1_renamed,
+                &expected_files
+            )
+            .await
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Verify that the child keeps syncing with the renamed parent   #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        // The child uses the new name for its parent
+        let ca2_details = ca_details(&ca2).await;
+        assert_eq!(ca2_details.parents().len(), 1);
+        assert_eq!(
+            ca2_details.parents()[0].handle().as_str(),
+            ca1_renamed.as_str()
+        );
+
+        update_child(&ca1_renamed, &ca2, &ca2_res_shrunk).await;
+        assert!(ca_equals_resources(&ca2, &ca2_res_shrunk).await);
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Verify that the alias cannot be used for a new CA             #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        krill_admin_expect_error(Command::CertAuth(CaCommand::Init(
+            CertAuthInit::new(ca1.clone()),
+        )))
+        .await;
+    }
+
+    cleanup();
+}
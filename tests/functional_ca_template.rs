@@ -0,0 +1,195 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    commons::api::{
+        AspaDefinition, AspaDefinitionList, CertAuthClone,
+        CertAuthFromTemplate, RoaConfigurationUpdates, TemplateRepository,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_ca_template() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Creating CAs from Templates                 #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * The setup of a CA can be shown as a template                #",
+    );
+    info(
+        "#  * A clone gets the same repository and local parents          #",
+    );
+    info(
+        "#  * Only definitions for held resources are copied              #",
+    );
+    info(
+        "#  * A CA can be created from a template document                #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca1 = ca_handle("CA1");
+    let ca2 = ca_handle("CA2");
+    let ca3 = ca_handle("CA3");
+
+    let ca1_res = resources("AS65000-AS65001", "10.0.0.0/16", "");
+    let ca2_res = resources("AS65000", "10.0.0.0/24", "");
+
+    let roa_held = roa_configuration("10.0.0.0/24-24 => 64496");
+    let roa_not_held = roa_configuration("10.0.1.0/24-24 => 64496");
+    let aspa_held = AspaDefinition::from_str("AS65000 => AS65002").unwrap();
+    let aspa_not_held =
+        AspaDefinition::from_str("AS65001 => AS65002").unwrap();
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &testbed, &ca1_res).await;
+
+        let mut updates = RoaConfigurationUpdates::empty();
+        updates.add(roa_held.clone());
+        updates.add(roa_not_held.clone());
+        ca_route_authorizations_update(&ca1, updates).await;
+
+        ca_aspas_add(&ca1, aspa_held.clone()).await;
+        ca_aspas_add(&ca1, aspa_not_held.clone()).await;
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Show the template for CA1                                     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let template = {
+        let template = ca_template(&ca1).await;
+        assert_eq!(template.repository, Some(TemplateRepository::Embedded));
+        assert_eq!(template.parents.len(), 1);
+        assert_eq!(template.parents[0].local, Some(testbed.clone()));
+        assert_eq!(template.parents[0].resources, ca1_res);
+        assert_eq!(template.roas.len(), 2);
+        assert_eq!(template.aspas.len(), 2);
+        template
+    };
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Clone CA1 with fewer resources, and copy its definitions      #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let report = ca_clone(
+            &ca1,
+            CertAuthClone {
+                handle: ca2.clone(),
+                copy_definitions: true,
+                resources: Some(ca2_res.clone()),
+            },
+        )
+        .await;
+
+        assert!(report.repository_configured);
+        assert_eq!(report.parents_added, vec![testbed.convert()]);
+        assert_eq!(report.roas_copied, vec![roa_held.clone()]);
+        assert_eq!(report.roas_skipped, vec![roa_not_held.clone()]);
+        assert_eq!(report.aspas_copied, vec![aspa_held.customer()]);
+        assert_eq!(report.aspas_skipped, vec![aspa_not_held.customer()]);
+
+        assert!(ca_contains_resources(&ca2, &ca2_res).await);
+        assert_eq!(
+            ca_child_info(&testbed, &ca2).await.entitled_resources(),
+            &ca2_res
+        );
+        expect_configured_roas(&ca2, std::slice::from_ref(&roa_held)).await;
+        expect_aspa_definitions(
+            &ca2,
+            AspaDefinitionList::new(vec![aspa_held.clone()]),
+        )
+        .await;
+        assert_ne!(
+            ca_details(&ca2).await.id_cert().public_key(),
+            ca_details(&ca1).await.id_cert().public_key()
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Create CA3 from the template document, without definitions    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let report = ca_init_from_template(CertAuthFromTemplate {
+            handle: ca3.clone(),
+            template,
+            copy_definitions: false,
+            resources: None,
+        })
+        .await;
+
+        assert!(report.repository_configured);
+        assert_eq!(report.parents_added, vec![testbed.convert()]);
+        assert!(report.roas_copied.is_empty());
+
+        assert!(ca_contains_resources(&ca3, &ca1_res).await);
+        expect_configured_roas(&ca3, &[]).await;
+    }
+
+    cleanup();
+}
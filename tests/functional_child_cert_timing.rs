@@ -0,0 +1,183 @@
+//! Perform functional tests on a Krill instance, using the API
+use chrono::Duration;
+use rpki::{
+    ca::idexchange::CaHandle,
+    repository::{resources::ResourceSet, x509::Time},
+};
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{AddChildRequest, ChildCertTiming, ParentCaReq},
+    test::*,
+};
+
+/// Returns whether the current certificate of the CA expires in the
+/// given number of weeks, give or take a day.
+async fn cert_valid_weeks(ca: &CaHandle, weeks: i64) -> bool {
+    for _ in 0..30_u8 {
+        let key = ca_key_for_rcn(ca, &rcn(0)).await;
+        let not_after = key.incoming_cert().validity().not_after();
+        let expected = Time::now() + Duration::weeks(weeks);
+        if not_after > expected - Duration::days(1)
+            && not_after < expected + Duration::days(1)
+        {
+            return true;
+        }
+        cas_refresh_single(ca).await;
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_child_cert_timing() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Child Certificate Timing                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * Invalid timing overrides for a child are rejected           #",
+    );
+    info(
+        "#  * Certificates are issued to a child using its overrides      #",
+    );
+    info(
+        "#  * Updated overrides are used when certificates are re-issued  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let lab = ca_handle("lab");
+    let lab_resources = resources("AS65000", "10.0.0.0/16", "");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+    set_up_ca_with_repo(&lab).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Adding a child which would not be re-issued before its        #",
+    );
+    info(
+        "#  certificate expires fails                                     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let id_cert = request(&lab).await.validate().unwrap();
+        let req = AddChildRequest::new(
+            lab.convert(),
+            lab_resources.clone(),
+            id_cert,
+        )
+        .with_cert_timing(ChildCertTiming::new(Some(2), Some(2)));
+        krill_admin_expect_error(Command::CertAuth(CaCommand::ChildAdd(
+            testbed.clone(),
+            req,
+        )))
+        .await;
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Add the child with certificates valid for 8 weeks             #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let timing = ChildCertTiming::new(Some(8), Some(2));
+        let response = add_child_rfc6492_with_cert_timing(
+            testbed.clone(),
+            lab.convert(),
+            request(&lab).await,
+            lab_resources.clone(),
+            timing,
+        )
+        .await;
+        add_parent_to_ca(&lab, ParentCaReq::new(testbed.convert(), response))
+            .await;
+        assert!(ca_contains_resources(&lab, &lab_resources).await);
+
+        assert_eq!(
+            ca_child_info(&testbed, &lab).await.cert_timing(),
+            &timing
+        );
+        assert!(cert_valid_weeks(&lab, 8).await);
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Update the child to certificates valid for 12 weeks, and      #",
+    );
+    info(
+        "#  verify that they are used when the parent rolls its key       #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let timing = ChildCertTiming::new(Some(12), Some(3));
+        update_child_cert_timing(&testbed, &lab, timing).await;
+        assert_eq!(
+            ca_child_info(&testbed, &lab).await.cert_timing(),
+            &timing
+        );
+
+        ca_emergency_rekey(&testbed, "test re-issuing child certificates")
+            .await;
+        assert!(cert_valid_weeks(&lab, 12).await);
+    }
+
+    cleanup();
+}
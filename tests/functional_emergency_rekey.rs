@@ -0,0 +1,157 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use bytes::Bytes;
+use rpki::{
+    ca::csr::BgpsecCsr,
+    repository::resources::{Asn, ResourceSet},
+};
+
+use krill::{
+    commons::api::{
+        AspaDefinition, BgpSecDefinition, ObjectName, RoaConfiguration,
+        RoaConfigurationUpdates, RoaPayload,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_emergency_rekey() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Emergency Key Roll                          #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * CAs can replace their key immediately:                      #",
+    );
+    info(
+        "#      * the new key is certified and activated at once          #",
+    );
+    info(
+        "#      * ROAs, ASPAs, BGPSec and child certificates are          #",
+    );
+    info(
+        "#        re-issued under the new key, and reported               #",
+    );
+    info(
+        "#      * the old key is revoked                                  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_resources = resources("AS65000", "10.0.0.0/16", "");
+
+    let dflt_rc_name = rcn(0);
+
+    let roa_payload =
+        RoaPayload::from_str("10.0.0.0/16-16 => 64496").unwrap();
+    let aspa_def =
+        AspaDefinition::from_str("AS65000 => AS65002, AS65003, AS65005")
+            .unwrap();
+    let bgpsec_def = {
+        let csr_bytes =
+            include_bytes!("../test-resources/bgpsec/router-csr.der");
+        let csr_bytes = Bytes::copy_from_slice(csr_bytes);
+        let csr = BgpsecCsr::decode(csr_bytes.as_ref()).unwrap();
+        BgpSecDefinition::new(Asn::from_u32(65000), csr)
+    };
+
+    let roa_file = ObjectName::from(&roa_payload);
+    let aspa_file = ObjectName::aspa(aspa_def.customer());
+    let bgpsec_file = ObjectName::bgpsec(
+        bgpsec_def.asn(),
+        bgpsec_def.csr().public_key().key_identifier(),
+    );
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Set up a CA under testbed, and ROA, ASPA and BGPSec objects   #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_resources).await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(RoaConfiguration::from(roa_payload));
+    ca_route_authorizations_update(&testbed, updates).await;
+    ca_aspas_add(&testbed, aspa_def).await;
+    ca_bgpsec_add(&testbed, bgpsec_def).await;
+
+    let old_key = *ca_key_for_rcn(&testbed, &dflt_rc_name).await.key_id();
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                testbed replaces its key at once                #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let reason = "key may be compromised";
+    let report = ca_emergency_rekey(&testbed, reason).await;
+
+    assert_eq!(report.ca, testbed);
+    assert_eq!(report.reason, reason);
+    assert_eq!(report.resource_classes.len(), 1);
+
+    let rekeyed = &report.resource_classes[0];
+    assert_eq!(rekeyed.resource_class_name, dflt_rc_name);
+    assert_eq!(rekeyed.old_key, old_key);
+    assert_ne!(rekeyed.new_key, old_key);
+    assert_eq!(rekeyed.roas, vec![roa_file]);
+    assert_eq!(rekeyed.aspas, vec![aspa_file]);
+    assert_eq!(rekeyed.bgpsec_certs, vec![bgpsec_file]);
+    assert_eq!(rekeyed.child_certs.len(), 1);
+
+    // The old key is revoked, and the new key is the current key.
+    assert!(state_becomes_active(&testbed).await);
+    let current_key = *ca_key_for_rcn(&testbed, &dflt_rc_name).await.key_id();
+    assert_eq!(current_key, rekeyed.new_key);
+
+    cleanup();
+}
@@ -0,0 +1,268 @@
+//! Perform functional tests on a Krill instance, using the API
+use base64::{engine::general_purpose::STANDARD as BASE64_ENGINE, Engine};
+use bcder::{decode::IntoSource, Mode, Oid, Tag};
+use rpki::{
+    crypto::DigestAlgorithm,
+    oid,
+    repository::{resources::ResourceSet, Cert},
+};
+
+use krill::{daemon::ca::SignedGeofeed, test::*};
+
+/// The content type for signed geofeeds: 1.2.840.113549.1.9.16.1.47
+const CT_GEOFEED: &[u8] = &[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 47];
+
+/// Verifies the structure of a signed geofeed, and returns its entries.
+fn verify_signed_geofeed(
+    signed: &SignedGeofeed,
+    resources: &ResourceSet,
+) -> Vec<String> {
+    assert_eq!(signed.resources(), resources);
+
+    let lines: Vec<&str> = signed.geofeed().lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with("# RPKI Signature: "))
+        .unwrap();
+    let end = lines
+        .iter()
+        .position(|line| line.starts_with("# End Signature: "))
+        .unwrap();
+    assert_eq!(end, lines.len() - 1);
+
+    let signature: String = lines[start + 1..end]
+        .iter()
+        .map(|line| {
+            assert!(line.len() <= 74);
+            line.strip_prefix("# ").unwrap()
+        })
+        .collect();
+    let signature = BASE64_ENGINE.decode(signature).unwrap();
+
+    let (content_type, cert) = Mode::Der
+        .decode(signature.into_source(), |cons| {
+            cons.take_sequence(|cons| {
+                oid::SIGNED_DATA.skip_if(cons)?;
+                cons.take_constructed_if(Tag::CTX_0, |cons| {
+                    cons.take_sequence(|cons| {
+                        cons.skip_u8_if(3)?;
+                        DigestAlgorithm::skip_set(cons)?;
+                        // RFC 9632 uses a detached signature, so there is
+                        // no eContent after the content type.
+                        let content_type =
+                            cons.take_sequence(Oid::take_from)?;
+                        let cert = cons.take_constructed_if(
+                            Tag::CTX_0,
+                            Cert::take_from,
+                        )?;
+                        cons.skip_all()?;
+                        Ok((content_type, cert))
+                    })
+                })
+            })
+        })
+        .unwrap();
+    assert_eq!(content_type.as_ref(), CT_GEOFEED);
+
+    // The EE certificate has no SIA, and covers exactly the prefixes
+    assert!(cert.signed_object().is_none());
+    assert!(cert.ca_repository().is_none());
+    assert!(cert.as_resources().to_blocks().unwrap().is_empty());
+    let ee_resources = ResourceSet::new(
+        Default::default(),
+        cert.v4_resources().to_blocks().unwrap().into(),
+        cert.v6_resources().to_blocks().unwrap().into(),
+    );
+    assert_eq!(&ee_resources, resources);
+
+    lines[..start]
+        .iter()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn functional_geofeed() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#        Test signed geofeed (RFC 9632) support                  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                 /   |                                          #",
+    );
+    info(
+        "#               CA1   CA2                                        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# * We will then have a geofeed signed by CA1                    #",
+    );
+    info(
+        "# * And a geofeed with prefixes of CA1 and CA2 signed by both    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+
+    let ca1 = ca_handle("CA1");
+    let ca1_res = resources("", "10.0.0.0/16", "2001:db8::/32");
+
+    let ca2 = ca_handle("CA2");
+    let ca2_res = resources("", "10.1.0.0/16", "");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                Set up CA1 and CA2 under testbed                #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &testbed, &ca1_res).await;
+
+        set_up_ca_with_repo(&ca2).await;
+        set_up_ca_under_parent_with_resources(&ca2, &testbed, &ca2_res).await;
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Sign a geofeed with CA1, and sign it again                     #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        let geofeed = "\
+            # prefix,country,region,city,postal\n\
+            10.0.0.0/24,NL,NL-NH,Amsterdam,\n\
+            2001:db8::/48,NL,NL-NH,Amsterdam,\n";
+        let geofeed_res = resources("", "10.0.0.0/24", "2001:db8::/48");
+
+        let signed = geofeed_sign(ca1.clone(), geofeed, vec![]).await;
+        assert_eq!(signed.len(), 1);
+        let (signer, first) = signed.iter().next().unwrap();
+        assert_eq!(signer, &ca1);
+        assert!(first.geofeed().starts_with(geofeed));
+        assert_eq!(
+            verify_signed_geofeed(first, &geofeed_res),
+            vec![
+                "10.0.0.0/24,NL,NL-NH,Amsterdam,",
+                "2001:db8::/48,NL,NL-NH,Amsterdam,"
+            ]
+        );
+
+        // An existing signature is replaced
+        let signed = geofeed_sign(ca1.clone(), first.geofeed(), vec![]).await;
+        let (_, second) = signed.iter().next().unwrap();
+        assert_eq!(second.number(), 2);
+        assert_eq!(second.geofeed().matches("# RPKI Signature:").count(), 1);
+        verify_signed_geofeed(second, &geofeed_res);
+
+        let list = geofeed_list(ca1.clone()).await;
+        let numbers: Vec<u64> =
+            list.as_slice().iter().map(|s| s.number()).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert_eq!(&geofeed_show(ca1.clone(), 1).await, first);
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Signing requires all prefixes, and a valid geofeed             #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        geofeed_sign_expect_error(
+            ca1.clone(),
+            "10.0.0.0/24,NL,,,\n10.1.0.0/24,NL,,,\n",
+            vec![],
+        )
+        .await;
+        geofeed_sign_expect_error(
+            ca1.clone(),
+            "not-a-prefix,NL,,,\n",
+            vec![],
+        )
+        .await;
+        geofeed_sign_expect_error(ca1.clone(), "# only a comment\n", vec![])
+            .await;
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Sign a geofeed with prefixes of CA1 and CA2                    #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        let geofeed = "\
+            # prefix,country,region,city,postal\n\
+            10.0.1.0/24,NL,NL-NH,Amsterdam,\n\
+            10.1.0.0/24,DE,DE-BE,Berlin,\n";
+
+        let signed =
+            geofeed_sign(ca1.clone(), geofeed, vec![ca2.clone()]).await;
+        assert_eq!(signed.len(), 2);
+
+        let mut signed = signed.iter();
+        let (signer, ca1_geofeed) = signed.next().unwrap();
+        assert_eq!(signer, &ca1);
+        assert_eq!(
+            verify_signed_geofeed(
+                ca1_geofeed,
+                &resources("", "10.0.1.0/24", "")
+            ),
+            vec!["10.0.1.0/24,NL,NL-NH,Amsterdam,"]
+        );
+
+        let (signer, ca2_geofeed) = signed.next().unwrap();
+        assert_eq!(signer, &ca2);
+        assert_eq!(
+            verify_signed_geofeed(
+                ca2_geofeed,
+                &resources("", "10.1.0.0/24", "")
+            ),
+            vec!["10.1.0.0/24,DE,DE-BE,Berlin,"]
+        );
+
+        assert_eq!(geofeed_list(ca2.clone()).await.as_slice().len(), 1);
+    }
+
+    cleanup();
+}
@@ -13,8 +13,9 @@ use rpki::{
 
 use krill::{
     commons::api::{
-        AspaDefinition, BgpSecDefinition, ObjectName, ReceivedCert,
-        RoaConfiguration, RoaConfigurationUpdates, RoaPayload,
+        AspaDefinition, BgpSecDefinition, KeyRollPhase, KeyRollPolicy,
+        ObjectName, ReceivedCert, RoaConfiguration, RoaConfigurationUpdates,
+        RoaPayload,
     },
     test::*,
 };
@@ -63,6 +64,9 @@ async fn functional_keyroll() {
     info(
         "#      * revoke and retire old key, mft and crl                  #",
     );
+    info(
+        "#  * CAs show the next key roll phase planned by their policy    #",
+    );
     info(
         "#                                                                #",
     );
@@ -236,8 +240,19 @@ async fn functional_keyroll() {
         info("#                                                                #");
         info("##################################################################");
         info("");
+        assert_eq!(ca_planned_key_roll(&testbed).await, None);
+        ca_roll_policy_set(&testbed, KeyRollPolicy::new(12, 24)).await;
+        assert_eq!(
+            ca_planned_key_roll(&testbed).await,
+            Some(KeyRollPhase::Initiate)
+        );
+
         ca_roll_init(&testbed).await;
         assert!(state_becomes_new_key(&testbed).await);
+        assert_eq!(
+            ca_planned_key_roll(&testbed).await,
+            Some(KeyRollPhase::Activate)
+        );
 
         // Expect that the MFT, CRL and objects are still published under the
         // current key. But we will also have a new key with just a MFT and
@@ -0,0 +1,305 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use rpki::{
+    ca::{idexchange::CaHandle, provisioning::ResourceClassName},
+    repository::resources::ResourceSet,
+};
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{
+        ObjectName, RepoMigrationPhase, RoaConfigurationUpdates, RoaPayload,
+    },
+    test::*,
+};
+
+/// Returns whether the repository migration of the CA reaches the given
+/// phase, or is finished if no phase is given.
+async fn migration_becomes(
+    ca: &CaHandle,
+    phase: Option<RepoMigrationPhase>,
+) -> bool {
+    for _ in 0..60_u8 {
+        let current = ca_repo_details(ca)
+            .await
+            .migration()
+            .map(|migration| migration.phase());
+        if current == phase {
+            return true;
+        }
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+/// Returns whether the dedicated repository gets any objects for the CA.
+async fn dedicated_gets_objects(ca: &CaHandle) -> bool {
+    for _ in 0..30_u8 {
+        if !dedicated_repo_publisher_details(ca.convert())
+            .await
+            .current_files()
+            .is_empty()
+        {
+            return true;
+        }
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_repo_migration() {
+    let cleanup_krill = start_krill_testbed_with_rrdp_interval(1).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Staged Repository Migration                 #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * Objects are staged in the new repository first              #",
+    );
+    info(
+        "#  * A migration can be aborted while staging                    #",
+    );
+    info(
+        "#  * A verified migration rolls to a new key, and cannot be      #",
+    );
+    info(
+        "#    aborted anymore                                             #",
+    );
+    info(
+        "#  * The old repository is cleaned up when the roll is done      #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    // The RRDP files of this publication server are not served, so a
+    // migration to it can never be verified.
+    let cleanup_pubd = start_krill_pubd(1).await;
+
+    let testbed = ca_handle("testbed");
+    let ca1 = ca_handle("CA1");
+    let ca1_res = ipv4_resources("10.0.0.0/16");
+    let ca1_route_definition =
+        RoaPayload::from_str("10.0.0.0/16-16 => 65000").unwrap();
+    let rcn_0 = ResourceClassName::from(0);
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Set up CA1 under testbed, using the embedded repository       #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &testbed, &ca1_res).await;
+
+        let mut updates = RoaConfigurationUpdates::empty();
+        updates.add(ca1_route_definition.into());
+        ca_route_authorizations_update(&ca1, updates).await;
+
+        let mut expected_files = expected_mft_and_crl(&ca1, &rcn_0).await;
+        expected_files
+            .push(ObjectName::from(&ca1_route_definition).to_string());
+        assert!(
+            will_publish_embedded(
+                "CA1 should publish in the embedded repo",
+                &ca1,
+                &expected_files
+            )
+            .await
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Start a migration to the dedicated repository, and abort it   #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMigrationAbort(ca1.clone()),
+        ))
+        .await;
+
+        dedicated_repo_add_publisher(publisher_request(&ca1).await).await;
+        let response = dedicated_repository_response(&ca1).await;
+        ca_repo_migration_start(&ca1, response.clone()).await;
+
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMigrationStart(ca1.clone(), response.clone()),
+        ))
+        .await;
+        krill_admin_expect_error(Command::CertAuth(CaCommand::RepoUpdate(
+            ca1.clone(),
+            response,
+        )))
+        .await;
+
+        // Objects are staged, but the CA keeps using its current key and
+        // repository.
+        assert!(dedicated_gets_objects(&ca1).await);
+        assert!(
+            migration_becomes(&ca1, Some(RepoMigrationPhase::Staging)).await
+        );
+        assert!(state_becomes_active(&ca1).await);
+
+        ca_repo_migration_abort(&ca1).await;
+        assert!(migration_becomes(&ca1, None).await);
+        assert!(
+            will_publish_dedicated(
+                "CA1 should withdraw staged objects from the dedicated repo",
+                &ca1,
+                &[]
+            )
+            .await
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Move CA1 to the dedicated repository, using a key roll        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        repo_update(&ca1, dedicated_repository_response(&ca1).await).await;
+        assert!(state_becomes_new_key(&ca1).await);
+        ca_roll_activate(&ca1).await;
+        assert!(state_becomes_active(&ca1).await);
+        assert!(
+            will_publish_embedded(
+                "CA1 should no longer publish in the embedded repo",
+                &ca1,
+                &[]
+            )
+            .await
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Migrate CA1 back to the embedded repository. The objects are  #",
+    );
+    info(
+        "#  verified in the local repository.                             #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let response = embedded_repository_response(ca1.convert()).await;
+        ca_repo_migration_start(&ca1, response).await;
+
+        assert!(
+            migration_becomes(&ca1, Some(RepoMigrationPhase::Certifying))
+                .await
+        );
+        let migration = ca_repo_details(&ca1).await.migration().cloned();
+        assert!(migration.unwrap().verified_objects().unwrap() > 0);
+
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMigrationAbort(ca1.clone()),
+        ))
+        .await;
+
+        // The parent certifies the new key for the new repository, which
+        // is then activated.
+        assert!(state_becomes_new_key(&ca1).await);
+        let expected_files = expected_new_key_mft_and_crl(&ca1, &rcn_0).await;
+        assert!(
+            will_publish_embedded(
+                "CA1 should publish the new key in the embedded repo",
+                &ca1,
+                &expected_files
+            )
+            .await
+        );
+
+        ca_roll_activate(&ca1).await;
+        assert!(state_becomes_active(&ca1).await);
+        assert!(migration_becomes(&ca1, None).await);
+
+        let mut expected_files = expected_mft_and_crl(&ca1, &rcn_0).await;
+        expected_files
+            .push(ObjectName::from(&ca1_route_definition).to_string());
+        assert!(
+            will_publish_embedded(
+                "CA1 should publish all objects in the embedded repo",
+                &ca1,
+                &expected_files
+            )
+            .await
+        );
+        assert!(
+            will_publish_dedicated(
+                "CA1 should no longer publish in the dedicated repo",
+                &ca1,
+                &[]
+            )
+            .await
+        );
+    }
+
+    cleanup_pubd();
+    cleanup_krill();
+}
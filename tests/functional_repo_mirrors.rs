@@ -0,0 +1,220 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use rpki::{
+    ca::idexchange::CaHandle, repository::resources::ResourceSet, uri,
+};
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{rrdp::PublishElement, PublicationServerUris},
+    test::*,
+};
+
+/// Returns the files published by a CA, sorted by URI so that they can be
+/// compared between repositories.
+fn sorted_files(mut files: Vec<PublishElement>) -> Vec<PublishElement> {
+    files.sort_by_key(|file| file.uri().to_string());
+    files
+}
+
+/// Returns whether the mirror repository ends up with the same files as
+/// the primary repository for the CA.
+async fn mirror_in_sync(ca: &CaHandle) -> bool {
+    for _ in 0..30_u8 {
+        let primary = sorted_files(
+            publisher_details(ca.convert())
+                .await
+                .current_files()
+                .clone(),
+        );
+        let mirror = sorted_files(
+            dedicated_repo_publisher_details(ca.convert())
+                .await
+                .current_files()
+                .clone(),
+        );
+        if !primary.is_empty() && primary == mirror {
+            return true;
+        }
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+/// Returns whether all files of the CA are withdrawn from the mirror.
+async fn mirror_withdrawn(ca: &CaHandle) -> bool {
+    for _ in 0..30_u8 {
+        if dedicated_repo_publisher_details(ca.convert())
+            .await
+            .current_files()
+            .is_empty()
+        {
+            return true;
+        }
+        sleep_seconds(1).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_repo_mirrors() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Repository Mirrors                          #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * A CA can publish all its objects to a mirror repository     #",
+    );
+    info(
+        "#  * The status of the mirror is reported                        #",
+    );
+    info(
+        "#  * The primary repository cannot be used as a mirror           #",
+    );
+    info(
+        "#  * Objects are withdrawn from a mirror when it is removed      #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    // The mirror uses the same base URIs as the embedded repository of
+    // the testbed.
+    let uris = PublicationServerUris::new(
+        uri::Https::from_str("https://localhost:3000/rrdp/").unwrap(),
+        uri::Rsync::from_str("rsync://localhost/repo/").unwrap(),
+    );
+    let cleanup_pubd = start_krill_pubd_with_uris(1, uris).await;
+
+    let testbed = ca_handle("testbed");
+    let mirror = "backup";
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Using the primary repository as a mirror fails                #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let response = embedded_repository_response(testbed.convert()).await;
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMirrorAdd(
+                testbed.clone(),
+                mirror.to_string(),
+                response,
+            ),
+        ))
+        .await;
+        assert!(ca_repo_details(&testbed).await.mirrors().is_empty());
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Add a mirror, and verify that it gets all objects             #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        dedicated_repo_add_publisher(publisher_request(&testbed).await).await;
+        let response = dedicated_repository_response(&testbed).await;
+
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMirrorAdd(
+                testbed.clone(),
+                "not.valid".to_string(),
+                response.clone(),
+            ),
+        ))
+        .await;
+
+        ca_repo_mirror_add(&testbed, mirror, response).await;
+        assert!(ca_repo_details(&testbed)
+            .await
+            .mirrors()
+            .contains_key(mirror));
+        assert!(mirror_in_sync(&testbed).await);
+
+        let status = ca_repo_status(&testbed).await;
+        let mirror_status = status.mirror(mirror).unwrap();
+        assert!(mirror_status.last_success().is_some());
+        assert!(mirror_status.to_failure_opt().is_none());
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Remove the mirror, and verify that its objects are withdrawn  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        ca_repo_mirror_remove(&testbed, mirror).await;
+        assert!(ca_repo_details(&testbed).await.mirrors().is_empty());
+        assert!(mirror_withdrawn(&testbed).await);
+        assert!(ca_repo_status(&testbed).await.mirror(mirror).is_none());
+        assert!(!publisher_details(testbed.convert())
+            .await
+            .current_files()
+            .is_empty());
+
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::RepoMirrorRemove(testbed.clone(), mirror.to_string()),
+        ))
+        .await;
+    }
+
+    cleanup_pubd();
+    cleanup();
+}
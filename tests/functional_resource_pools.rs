@@ -0,0 +1,264 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    commons::api::{
+        AddChildRequest, ChildAllocation, PoolAllocation,
+        ResourcePoolDefinition, ResourceUsage, UpdateChildRequest,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_resource_pools() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#               Test Resource Pools                              #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# We will verify that:                                           #",
+    );
+    info(
+        "#  * Pools which overlap with other pools are rejected           #",
+    );
+    info(
+        "#  * Children get the next free resources allocated from a pool  #",
+    );
+    info(
+        "#  * Resources in pools cannot be given to two children, unless  #",
+    );
+    info(
+        "#    this is explicitly allowed                                  #",
+    );
+    info(
+        "#  * The utilization of pools is reported                        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let alice = ca_handle("alice");
+    let bob = ca_handle("bob");
+    let carol = ca_handle("carol");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+    init_ca(&alice).await;
+    init_ca(&bob).await;
+    init_ca(&carol).await;
+
+    let customers = "customers";
+    let pool_resources = resources("AS65000-AS65009", "10.0.0.0/16", "");
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Define a pool, and verify that overlapping pools are rejected #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        ca_resource_pool_set(&testbed, customers, pool_resources.clone())
+            .await;
+
+        krill_admin_expect_error(Command::CertAuth(
+            CaCommand::ResourcePoolUpdate(
+                testbed.clone(),
+                ResourcePoolDefinition::new(
+                    "infra".to_string(),
+                    resources("", "10.0.0.0/8", ""),
+                ),
+            ),
+        ))
+        .await;
+
+        let pools = ca_resource_pools(&testbed).await;
+        assert_eq!(pools.pools().len(), 1);
+        assert_eq!(pools.get(customers).unwrap().resources, pool_resources);
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Add two children with the next free /24 and ASN from the pool #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let allocation = ChildAllocation::new(
+            Some(
+                PoolAllocation::new(customers.to_string())
+                    .with_ipv4_prefix_len(24)
+                    .with_asns(1),
+            ),
+            false,
+        );
+
+        add_child_rfc6492_with_allocation(
+            testbed.clone(),
+            alice.convert(),
+            request(&alice).await,
+            ResourceSet::empty(),
+            allocation.clone(),
+        )
+        .await;
+        add_child_rfc6492_with_allocation(
+            testbed.clone(),
+            bob.convert(),
+            request(&bob).await,
+            ResourceSet::empty(),
+            allocation,
+        )
+        .await;
+
+        assert_eq!(
+            ca_child_info(&testbed, &alice).await.entitled_resources(),
+            &resources("AS65000", "10.0.0.0/24", "")
+        );
+        assert_eq!(
+            ca_child_info(&testbed, &bob).await.entitled_resources(),
+            &resources("AS65001", "10.0.1.0/24", "")
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Giving a child resources delegated to another child fails,    #",
+    );
+    info(
+        "#  unless overlaps are allowed                                   #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let id_cert = request(&carol).await.validate().unwrap();
+        let overlapping = resources("", "10.0.1.0/25", "");
+        let req = AddChildRequest::new(
+            carol.convert(),
+            overlapping.clone(),
+            id_cert,
+        );
+        krill_admin_expect_error(Command::CertAuth(CaCommand::ChildAdd(
+            testbed.clone(),
+            req,
+        )))
+        .await;
+
+        add_child_rfc6492_with_allocation(
+            testbed.clone(),
+            carol.convert(),
+            request(&carol).await,
+            overlapping.clone(),
+            ChildAllocation::new(None, true),
+        )
+        .await;
+        assert_eq!(
+            ca_child_info(&testbed, &carol).await.entitled_resources(),
+            &overlapping
+        );
+
+        // Extend the child with the next free /24, which keeps its
+        // current resources.
+        let update = UpdateChildRequest::new(None, None, None)
+            .with_allocation(ChildAllocation::new(
+                Some(
+                    PoolAllocation::new(customers.to_string())
+                        .with_ipv4_prefix_len(24),
+                ),
+                true,
+            ));
+        krill_admin(Command::CertAuth(CaCommand::ChildUpdate(
+            testbed.clone(),
+            carol.convert(),
+            update,
+        )))
+        .await;
+        assert_eq!(
+            ca_child_info(&testbed, &carol).await.entitled_resources(),
+            &resources("", "10.0.1.0/25, 10.0.2.0/24", "")
+        );
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#  Verify the utilization of the pool, and remove it             #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    {
+        let pools = ca_resource_pools(&testbed).await;
+        let pool = pools.get(customers).unwrap();
+        assert_eq!(
+            pool.allocated,
+            resources("AS65000-AS65001", "10.0.0.0/23, 10.0.2.0/24", "")
+        );
+        assert_eq!(pool.asns, ResourceUsage::new(10, 2));
+        assert_eq!(pool.ipv4, ResourceUsage::new(65536, 768));
+
+        ca_resource_pool_remove(&testbed, customers).await;
+        assert!(ca_resource_pools(&testbed).await.pools().is_empty());
+        assert_eq!(
+            ca_child_info(&testbed, &alice).await.entitled_resources(),
+            &resources("AS65000", "10.0.0.0/24", "")
+        );
+    }
+
+    cleanup();
+}
@@ -2,7 +2,10 @@
 use hyper::StatusCode;
 use rpki::repository::resources::ResourceSet;
 
-use krill::{commons::api::RoaConfigurationUpdates, test::*};
+use krill::{
+    commons::api::{HistoryPoint, RoaConfigurationUpdates},
+    test::*,
+};
 
 #[tokio::test]
 async fn functional_roas() {
@@ -144,6 +147,11 @@ async fn functional_roas() {
         .await;
     }
 
+    // Remember the version at which CA4 had only these three ROA
+    // configurations.
+    let three_roas_configured =
+        HistoryPoint::Version(ca_latest_version(&ca).await);
+
     {
         info("##################################################################");
         info("#                                                                #");
@@ -238,6 +246,29 @@ async fn functional_roas() {
         .await;
     }
 
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# The ROA configurations at an earlier point in time can still   #");
+        info("# be shown                                                       #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        expect_configured_roas_at(
+            &ca,
+            three_roas_configured,
+            &[
+                route_resource_set_10_0_0_0_def_1.clone(),
+                route_resource_set_10_0_0_0_def_2.clone(),
+                route_resource_set_10_1_0_0_def_1.clone(),
+            ],
+        )
+        .await;
+
+        expect_configured_roas(&ca, &[route_resource_set_10_0_0_0_def_1])
+            .await;
+    }
+
     {
         info("##################################################################");
         info("#                                                                #");
@@ -0,0 +1,237 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::repository::{resources::ResourceSet, sigobj::SignedObject};
+
+use krill::{
+    commons::api::RscList,
+    daemon::ca::{RscChecklistItem, SignedRsc},
+    test::*,
+};
+
+/// The content type for RPKI Signed Checklists: 1.2.840.113549.1.9.16.1.48
+const CT_RSC: &[u8] = &[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48];
+
+fn verify_rsc_objects(rsc: &SignedRsc, resources: &ResourceSet) {
+    assert_eq!(rsc.resources(), resources);
+    assert_eq!(rsc.objects().len(), 1);
+
+    for object in rsc.objects() {
+        let signed =
+            SignedObject::decode(object.rsc().as_ref().clone(), true)
+                .unwrap();
+        assert_eq!(signed.content_type().as_ref(), CT_RSC);
+
+        // RFC 9323 forbids the SIA extension in the EE certificate
+        let cert = signed.cert();
+        assert!(cert.signed_object().is_none());
+        assert!(cert.ca_repository().is_none());
+
+        let ee_resources = ResourceSet::new(
+            cert.as_resources().to_blocks().unwrap(),
+            cert.v4_resources().to_blocks().unwrap().into(),
+            cert.v6_resources().to_blocks().unwrap().into(),
+        );
+        assert_eq!(&ee_resources, object.resources());
+    }
+}
+
+#[tokio::test]
+async fn functional_rsc() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#       Test RPKI Signed Checklist (RSC, RFC 9323) support       #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                 /   |                                          #",
+    );
+    info(
+        "#               CA1   CA2                                        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# * We will then have a single signed RSC under CA1              #",
+    );
+    info(
+        "# * And an RSC co-signed by CA1 and CA2                          #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+
+    let ca1 = ca_handle("CA1");
+    let ca1_res = resources("AS65000", "10.0.0.0/16", "");
+
+    let ca2 = ca_handle("CA2");
+    let ca2_res = resources("", "10.1.0.0/16", "");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                Set up CA1 and CA2 under testbed                #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &testbed, &ca1_res).await;
+
+        set_up_ca_with_repo(&ca2).await;
+        set_up_ca_under_parent_with_resources(&ca2, &testbed, &ca2_res).await;
+    }
+
+    let checklist = vec![
+        RscChecklistItem::for_content(
+            Some("test.tal".to_string()),
+            include_bytes!("../test-resources/test.tal"),
+        ),
+        RscChecklistItem::for_content(None, b"some other content"),
+    ];
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Create a single signed RSC                                     #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        let name = "single".to_string();
+        let single_res = resources("AS65000", "10.0.0.0/24", "");
+
+        rsc_sign(
+            ca1.clone(),
+            name.clone(),
+            single_res.clone(),
+            checklist.clone(),
+        )
+        .await;
+
+        assert_eq!(
+            rsc_list(ca1.clone()).await,
+            RscList::new(vec![name.clone()])
+        );
+
+        let rsc = rsc_show(ca1.clone(), name.clone()).await;
+        assert_eq!(rsc.checklist(), &checklist);
+        verify_rsc_objects(&rsc, &single_res);
+
+        // The name cannot be re-used
+        rsc_sign_expect_error(
+            ca1.clone(),
+            name,
+            single_res,
+            checklist.clone(),
+        )
+        .await;
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Signing requires all resources, and a valid checklist          #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        let multi_res = ipv4_resources("10.0.0.0/16, 10.1.0.0/16");
+        rsc_sign_expect_error(
+            ca1.clone(),
+            "not-held".to_string(),
+            multi_res,
+            checklist.clone(),
+        )
+        .await;
+
+        let bad_name = vec![RscChecklistItem::for_content(
+            Some("not portable".to_string()),
+            b"content",
+        )];
+        rsc_sign_expect_error(
+            ca1.clone(),
+            "bad-name".to_string(),
+            ca1_res.clone(),
+            bad_name,
+        )
+        .await;
+
+        rsc_sign_expect_error(
+            ca1.clone(),
+            "empty".to_string(),
+            ca1_res.clone(),
+            vec![],
+        )
+        .await;
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Co-sign an RSC with resources spanning CA1 and CA2             #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        let name = "multi".to_string();
+        let multi_res = ipv4_resources("10.0.0.0/16, 10.1.0.0/16");
+
+        rsc_cosign(
+            ca1.clone(),
+            name.clone(),
+            multi_res.clone(),
+            checklist.clone(),
+        )
+        .await;
+        rsc_cosign(
+            ca2.clone(),
+            name.clone(),
+            multi_res.clone(),
+            checklist.clone(),
+        )
+        .await;
+
+        // Each CA signed for the part of the resources it holds
+        let ca1_rsc = rsc_show(ca1.clone(), name.clone()).await;
+        verify_rsc_objects(&ca1_rsc, &ipv4_resources("10.0.0.0/16"));
+
+        let ca2_rsc = rsc_show(ca2.clone(), name.clone()).await;
+        verify_rsc_objects(&ca2_rsc, &ipv4_resources("10.1.0.0/16"));
+
+        assert_eq!(
+            rsc_list(ca1.clone()).await,
+            RscList::new(vec![name, "single".to_string()])
+        );
+    }
+
+    cleanup();
+}
@@ -0,0 +1,122 @@
+//! Perform functional tests on a Krill instance, using the API
+use krill::test::*;
+
+/// Returns the number of certificates published by the TA.
+async fn ta_published_certs() -> usize {
+    publisher_details(ca_handle("ta").convert())
+        .await
+        .current_files()
+        .iter()
+        .filter(|file| file.uri().as_str().ends_with(".cer"))
+        .count()
+}
+
+/// Waits until the TA publishes the given number of certificates.
+async fn ta_will_publish_certs(nr: usize) -> bool {
+    for _ in 0..30_u8 {
+        if ta_published_certs().await == nr {
+            return true;
+        }
+        sleep_seconds(1).await
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_ta_children() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#        Test updating and removing children of the TA           #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                 /  |                                           #",
+    );
+    info(
+        "#           testbed  CA1                                         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# * We will shrink and grow the resources of CA1                 #",
+    );
+    info(
+        "# * And then remove CA1 as a child of the TA                     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let ta = ca_handle("ta");
+
+    let ca1 = ca_handle("CA1");
+    let ca1_res = resources("AS65000", "10.0.0.0/16", "2001:db8::/32");
+    let ca1_res_shrunk = resources("", "10.0.0.0/24", "");
+
+    // The TA publishes the certificate for the testbed CA
+    assert!(ta_will_publish_certs(1).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                Set up CA1 under the TA                         #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &ta, &ca1_res).await;
+        assert!(ta_will_publish_certs(2).await);
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Shrink and grow the resources of CA1, the TA signer re-issues  #");
+        info("# its certificate                                                #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        update_child(&ta, &ca1, &ca1_res_shrunk).await;
+        assert!(ca_equals_resources(&ca1, &ca1_res_shrunk).await);
+
+        update_child(&ta, &ca1, &ca1_res).await;
+        assert!(ca_equals_resources(&ca1, &ca1_res).await);
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Remove CA1, the TA signer revokes its certificate              #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        delete_child(&ta, &ca1).await;
+        assert!(ta_will_publish_certs(1).await);
+    }
+
+    cleanup();
+}
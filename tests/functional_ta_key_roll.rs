@@ -0,0 +1,257 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::{
+    ca::idexchange::CaHandle,
+    crypto::KeyIdentifier,
+    repository::{sigobj::SignedObject, Cert},
+};
+
+use krill::{
+    commons::api::rrdp::PublishElement,
+    ta::{TaKeyRollInitRequest, TrustAnchorKeys},
+    test::*,
+};
+
+/// The content type for TAK objects: 1.2.840.113549.1.9.16.1.50
+const CT_TRUST_ANCHOR_KEYS: &[u8] =
+    &[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 50];
+
+/// Returns the files published by the TA with the given extension.
+async fn ta_published(extension: &str) -> Vec<PublishElement> {
+    publisher_details(ca_handle("ta").convert())
+        .await
+        .current_files()
+        .iter()
+        .filter(|file| file.uri().as_str().ends_with(extension))
+        .cloned()
+        .collect()
+}
+
+/// Waits until the TA publishes the given number of TAK objects.
+async fn ta_will_publish_taks(nr: usize) -> bool {
+    for _ in 0..30_u8 {
+        if ta_published(".tak").await.len() == nr {
+            return true;
+        }
+        sleep_seconds(1).await
+    }
+    false
+}
+
+/// Returns the key of the TA which signed the current certificate of the
+/// given CA.
+async fn ca_signed_by(ca: &CaHandle) -> KeyIdentifier {
+    ca_details(ca)
+        .await
+        .resource_classes()
+        .values()
+        .next()
+        .unwrap()
+        .current_key()
+        .unwrap()
+        .incoming_cert()
+        .to_cert()
+        .unwrap()
+        .authority_key_identifier()
+        .unwrap()
+}
+
+/// Waits until the current certificate of the given CA is signed by the
+/// given TA key.
+async fn ca_will_be_signed_by(ca: &CaHandle, key: KeyIdentifier) -> bool {
+    for _ in 0..30_u8 {
+        if ca_signed_by(ca).await == key {
+            return true;
+        }
+        sleep_seconds(1).await
+    }
+    false
+}
+
+#[tokio::test]
+async fn functional_ta_key_roll() {
+    let data_dir = TmpDir::new();
+    let mut config = test_config(
+        &mem_storage(),
+        Some(data_dir.path()),
+        true,
+        false,
+        false,
+        false,
+    );
+    // Allow activating the successor key immediately.
+    config.ta_timing.key_roll_acceptance_days = 0;
+    start_krill(config).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#        Test a key rollover of the TA (RFC 9691)                #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                 /  |                                           #",
+    );
+    info(
+        "#           testbed  CA1                                         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# * We will stage a successor key for the TA                     #",
+    );
+    info(
+        "# * Both keys publish a TAK object announcing the other          #",
+    );
+    info(
+        "# * We activate the successor, which re-issues all children      #",
+    );
+    info(
+        "# * The old key and its TAK objects are retired                  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let ta = ca_handle("ta");
+    let testbed = ca_handle("testbed");
+
+    let ca1 = ca_handle("CA1");
+    let ca1_res = resources("AS65000", "10.0.0.0/16", "2001:db8::/32");
+
+    let successor_tal = TaKeyRollInitRequest {
+        tal_https: vec![https("https://localhost:3000/ta/ta-successor.cer")],
+        tal_rsync: rsync("rsync://localhost/ta/ta-successor.cer"),
+    };
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                Set up CA1 under the TA                         #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca1).await;
+        set_up_ca_under_parent_with_resources(&ca1, &ta, &ca1_res).await;
+    }
+
+    let old_key = ca_signed_by(&ca1).await;
+    assert_eq!(ca_signed_by(&testbed).await, old_key);
+    assert!(ta_key_roll_successor().await.is_none());
+    assert!(ta_published(".tak").await.is_empty());
+
+    let new_key = {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Stage a successor key, both keys publish a TAK object          #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        ta_key_roll_init(successor_tal.clone()).await;
+        assert!(ta_will_publish_taks(2).await);
+
+        let successor = ta_key_roll_successor().await.unwrap();
+        let successor_details = successor.ta_cert_details();
+        assert_eq!(
+            successor_details.tal().rsync_uri(),
+            &successor_tal.tal_rsync
+        );
+        let new_key = successor_details.cert().key_identifier();
+        assert_ne!(new_key, old_key);
+
+        // The children are still signed by the current key.
+        assert_eq!(ca_signed_by(&ca1).await, old_key);
+
+        // Each key announces itself as the current key, and the other key
+        // as its successor or predecessor.
+        for tak in ta_published(".tak").await {
+            let object =
+                SignedObject::decode(tak.base64().to_bytes(), true).unwrap();
+            assert_eq!(object.content_type().as_ref(), CT_TRUST_ANCHOR_KEYS);
+
+            let keys =
+                TrustAnchorKeys::decode(object.content().to_bytes()).unwrap();
+            let signing_key =
+                object.cert().authority_key_identifier().unwrap();
+            let current = keys.current().public_key().key_identifier();
+            assert_eq!(current, signing_key);
+
+            if signing_key == old_key {
+                assert!(keys.predecessor().is_none());
+                let successor = keys.successor().unwrap();
+                assert_eq!(successor.public_key().key_identifier(), new_key);
+                assert!(successor
+                    .certificate_uris()
+                    .contains(&successor_tal.tal_rsync.to_string()));
+            } else {
+                assert_eq!(signing_key, new_key);
+                assert!(keys.successor().is_none());
+                let predecessor = keys.predecessor().unwrap();
+                assert_eq!(
+                    predecessor.public_key().key_identifier(),
+                    old_key
+                );
+            }
+        }
+
+        // The TA certificate for the successor key is available at its
+        // TAL URI.
+        let cert = krill_anon_http_bytes("ta/ta-successor.cer").await;
+        let cert = Cert::decode(cert).unwrap();
+        assert_eq!(cert.subject_key_identifier(), new_key);
+
+        new_key
+    };
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# Activate the successor key, all children are re-issued         #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        ta_key_roll_activate().await;
+        assert!(ca_will_be_signed_by(&ca1, new_key).await);
+        assert!(ca_will_be_signed_by(&testbed, new_key).await);
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("# The old key is retired, and no TAK objects are published       #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        assert!(ta_key_roll_successor().await.is_none());
+        assert!(ta_will_publish_taks(0).await);
+
+        let old_key_files: Vec<_> = ta_published("")
+            .await
+            .into_iter()
+            .filter(|file| file.uri().as_str().contains(&old_key.to_string()))
+            .collect();
+        assert!(old_key_files.is_empty());
+        assert_eq!(ta_published(".crl").await.len(), 1);
+        assert_eq!(ta_published(".mft").await.len(), 1);
+        assert_eq!(ta_published(".cer").await.len(), 2);
+    }
+}